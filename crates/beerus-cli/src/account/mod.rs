@@ -0,0 +1,179 @@
+use crate::{
+    model::{AccountSubCommands, CommandResponse},
+    tx::load_signing_key,
+};
+use beerus_core::data_dir::DataDir;
+use eyre::{eyre, Result};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use starknet::{core::types::FieldElement, signers::SigningKey};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Account stored in a keystore. The address is unknown until the account is deployed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountInfo {
+    pub name: String,
+    pub address: Option<FieldElement>,
+    pub public_key: FieldElement,
+}
+
+/// Return the directory of the keystores, in the data directory set by `DATA_DIR`.
+pub fn accounts_dir() -> PathBuf {
+    DataDir::from_env().keystores()
+}
+
+/// Return the keystore file of an account.
+pub fn keystore_path(dir: &Path, name: &str) -> PathBuf {
+    dir.join(format!("{name}.json"))
+}
+
+/// Encrypt the key of an account in a new keystore, with scrypt.
+/// The address and public key of the account are stored along the encrypted key.
+/// # Arguments
+/// * `dir` - The directory of the keystores.
+/// * `name` - The name of the account, which names its keystore file.
+/// * `address` - The address of the account, if deployed.
+/// * `secret` - The private key of the account.
+/// * `password` - The password the keystore is encrypted with.
+pub fn store_account(
+    dir: &Path,
+    name: &str,
+    address: Option<FieldElement>,
+    secret: FieldElement,
+    password: &str,
+) -> Result<AccountInfo> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(eyre!(
+            "Invalid account name {name}, use letters, digits, - and _"
+        ));
+    }
+    let path = keystore_path(dir, name);
+    if path.exists() {
+        return Err(eyre!("Account {name} already exists"));
+    }
+    fs::create_dir_all(dir)?;
+    let info = AccountInfo {
+        name: name.to_string(),
+        address,
+        public_key: SigningKey::from_secret_scalar(secret)
+            .verifying_key()
+            .scalar(),
+    };
+    let file_name = format!("{name}.json");
+    eth_keystore::encrypt_key(
+        dir,
+        &mut rand::thread_rng(),
+        secret.to_bytes_be(),
+        password,
+        Some(&file_name),
+    )
+    .map_err(|err| eyre!("Failed to encrypt the key of {name}: {err}"))?;
+
+    // The keystore readers ignore the fields they don't know.
+    let mut keystore: Value = serde_json::from_slice(&fs::read(&path)?)?;
+    keystore["starknet"] = serde_json::to_value(&info)?;
+    fs::write(&path, serde_json::to_vec_pretty(&keystore)?)?;
+    Ok(info)
+}
+
+/// Read the address and public key of an account from its keystore, without decrypting it.
+pub fn read_account(dir: &Path, name: &str) -> Result<AccountInfo> {
+    let path = keystore_path(dir, name);
+    let keystore: Value = serde_json::from_slice(
+        &fs::read(&path).map_err(|_| eyre!("Account {name} not found in {}", dir.display()))?,
+    )?;
+    Ok(serde_json::from_value(keystore["starknet"].clone())?)
+}
+
+/// Return the accounts stored in a directory, by name.
+pub fn list_accounts(dir: &Path) -> Result<Vec<AccountInfo>> {
+    let mut accounts = Vec::new();
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(accounts),
+        Err(err) => return Err(err.into()),
+    };
+    for entry in entries {
+        let path = entry?.path();
+        if path
+            .extension()
+            .map_or(true, |extension| extension != "json")
+        {
+            continue;
+        }
+        let name = match path.file_stem().and_then(|stem| stem.to_str()) {
+            Some(name) => name,
+            None => continue,
+        };
+        accounts.push(read_account(dir, name)?);
+    }
+    accounts.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(accounts)
+}
+
+/// Decrypt the key of an account stored in a directory.
+pub fn load_account(dir: &Path, name: &str, password: &str) -> Result<(AccountInfo, SigningKey)> {
+    let info = read_account(dir, name)?;
+    let signing_key = load_signing_key(&keystore_path(dir, name), password)?;
+    Ok((info, signing_key))
+}
+
+/// Generate a private key. It is kept below 2^250, under the order of the curve.
+pub fn generate_secret() -> FieldElement {
+    let mut bytes = [0_u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes[0] &= 0x03;
+    FieldElement::from_bytes_be(&bytes).unwrap()
+}
+
+/// Prompt for a password, and for its confirmation when a keystore is created.
+pub fn prompt_password(confirm: bool) -> Result<String> {
+    if let Ok(password) = std::env::var("BEERUS_KEYSTORE_PASSWORD") {
+        return Ok(password);
+    }
+    let password = rpassword::prompt_password("Keystore password: ")?;
+    if confirm && rpassword::prompt_password("Confirm password: ")? != password {
+        return Err(eyre!("The passwords don't match"));
+    }
+    Ok(password)
+}
+
+/// Entry point for the account commands, which run without a light client.
+/// # Arguments
+/// * `command` - The account subcommand.
+/// # Returns
+/// * `Result<CommandResponse>` - The accounts created or listed.
+/// # Errors
+/// * If a keystore can't be written or read.
+pub fn run_account(command: &AccountSubCommands) -> Result<CommandResponse> {
+    let data_dir = DataDir::from_env();
+    data_dir.init()?;
+    let dir = data_dir.keystores();
+    match command {
+        AccountSubCommands::New { name, address } => {
+            let address = address
+                .as_deref()
+                .map(FieldElement::from_hex_be)
+                .transpose()?;
+            let password = prompt_password(true)?;
+            let info = store_account(&dir, name, address, generate_secret(), &password)?;
+            Ok(CommandResponse::AccountNew(info))
+        }
+        AccountSubCommands::Import { name, address } => {
+            let address = FieldElement::from_hex_be(address)?;
+            let secret = FieldElement::from_hex_be(&rpassword::prompt_password("Private key: ")?)?;
+            let password = prompt_password(true)?;
+            let info = store_account(&dir, name, Some(address), secret, &password)?;
+            Ok(CommandResponse::AccountImport(info))
+        }
+        AccountSubCommands::List {} => Ok(CommandResponse::AccountList(list_accounts(&dir)?)),
+    }
+}
@@ -0,0 +1,42 @@
+use crate::model::CommandResponse;
+use beerus_core::lightclient::beerus::BeerusLightClient;
+use eyre::Result;
+
+/// Query the range of blocks retained by the light client and the memory they use.
+/// # Arguments
+/// * `beerus` - The Beerus light client.
+/// # Returns
+/// * `Result<CommandResponse>` - The retention statistics.
+pub async fn query_retention_stats(beerus: BeerusLightClient) -> Result<CommandResponse> {
+    Ok(CommandResponse::AdminQueryRetentionStats(
+        beerus.retention_stats(),
+    ))
+}
+
+/// Query the memory used by each block retained by the light client.
+/// # Arguments
+/// * `beerus` - The Beerus light client.
+/// # Returns
+/// * `Result<CommandResponse>` - The memory usage of each block, by ascending number.
+pub async fn query_block_usage(beerus: BeerusLightClient) -> Result<CommandResponse> {
+    Ok(CommandResponse::AdminQueryBlockUsage(beerus.block_usage()))
+}
+
+/// Drop the blocks below a given block number.
+/// # Arguments
+/// * `beerus` - The Beerus light client.
+/// * `block_number` - The first block number to keep.
+/// # Returns
+/// * `Result<CommandResponse>` - The number of blocks dropped.
+pub async fn prune_to(beerus: BeerusLightClient, block_number: u64) -> Result<CommandResponse> {
+    Ok(CommandResponse::AdminPruneTo(beerus.prune_to(block_number)))
+}
+
+/// Compact the blocks retained by the light client.
+/// # Arguments
+/// * `beerus` - The Beerus light client.
+/// # Returns
+/// * `Result<CommandResponse>` - The number of blocks compressed.
+pub async fn compact(beerus: BeerusLightClient) -> Result<CommandResponse> {
+    Ok(CommandResponse::AdminCompact(beerus.compact()))
+}
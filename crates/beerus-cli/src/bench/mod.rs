@@ -0,0 +1,167 @@
+use crate::model::{BenchArgs, CommandResponse};
+use ethers::providers::{Http, JsonRpcClient};
+use eyre::{eyre, Result};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::{
+    collections::BTreeMap,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
+
+/// Methods called when no mix is given: cheap reads served by every node.
+pub const DEFAULT_METHOD_MIX: [&str; 4] = [
+    "starknet_blockNumber=4",
+    "starknet_blockHashAndNumber=2",
+    "beerus_l1ProvenBlockNumber=2",
+    "beerus_status=1",
+];
+
+/// Latency distribution of a set of calls, in milliseconds.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LatencyStats {
+    pub requests: usize,
+    pub errors: usize,
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+impl LatencyStats {
+    /// Compute the percentiles of the latencies of the calls, failed ones included.
+    pub fn new(mut latencies_ms: Vec<f64>, errors: usize) -> Self {
+        latencies_ms.sort_by(|a, b| a.total_cmp(b));
+        Self {
+            requests: latencies_ms.len(),
+            errors,
+            p50_ms: percentile(&latencies_ms, 50.0),
+            p90_ms: percentile(&latencies_ms, 90.0),
+            p99_ms: percentile(&latencies_ms, 99.0),
+            max_ms: latencies_ms.last().copied().unwrap_or_default(),
+        }
+    }
+}
+
+/// Result of a benchmark run.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BenchReport {
+    pub concurrency: usize,
+    pub duration_ms: f64,
+    pub requests_per_sec: f64,
+    pub overall: LatencyStats,
+    pub methods: BTreeMap<String, LatencyStats>,
+}
+
+/// Return the nearest-rank percentile of sorted values, zero if there are none.
+pub fn percentile(sorted: &[f64], percentile: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = (percentile / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// Expand a mix of methods, given as `method` or `method=weight`, into the sequence of
+/// methods the workers cycle through.
+pub fn parse_method_mix(methods: &[String]) -> Result<Vec<String>> {
+    let mut schedule = Vec::new();
+    for method in methods {
+        let (name, weight) = match method.split_once('=') {
+            Some((name, weight)) => (
+                name,
+                weight
+                    .parse::<usize>()
+                    .map_err(|_| eyre!("Invalid weight in {method}"))?,
+            ),
+            None => (method.as_str(), 1),
+        };
+        if name.is_empty() {
+            return Err(eyre!("Invalid method in {method}"));
+        }
+        schedule.extend(std::iter::repeat(name.to_string()).take(weight));
+    }
+    if schedule.is_empty() {
+        return Err(eyre!("The method mix is empty"));
+    }
+    Ok(schedule)
+}
+
+/// Drive a mix of RPC methods against a Beerus instance from concurrent workers, and report the
+/// latency percentiles of the calls.
+/// # Arguments
+/// * `args` - The endpoint, the concurrency, the number of requests and the method mix.
+/// # Returns
+/// * `Result<CommandResponse>` - The benchmark report.
+/// # Errors
+/// * If the arguments are invalid. Failed calls are counted, not returned.
+pub async fn run_bench(args: &BenchArgs) -> Result<CommandResponse> {
+    let client = Http::from_str(&args.endpoint)?;
+    let schedule = Arc::new(parse_method_mix(&args.methods)?);
+    let concurrency = args.concurrency.max(1);
+    let requests = args.requests;
+    let next = Arc::new(AtomicUsize::new(0));
+
+    let started = Instant::now();
+    let workers: Vec<_> = (0..concurrency)
+        .map(|_| {
+            let client = client.clone();
+            let schedule = schedule.clone();
+            let next = next.clone();
+            tokio::spawn(async move {
+                let mut samples = Vec::new();
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    if index >= requests {
+                        return samples;
+                    }
+                    let method = &schedule[index % schedule.len()];
+                    let call_started = Instant::now();
+                    let result: Result<Value, _> = client.request(method, json!([])).await;
+                    let latency_ms = call_started.elapsed().as_secs_f64() * 1000.0;
+                    samples.push((index % schedule.len(), latency_ms, result.is_ok()));
+                }
+            })
+        })
+        .collect();
+
+    let mut samples = Vec::with_capacity(requests);
+    for worker in workers {
+        samples.extend(worker.await?);
+    }
+    let duration_ms = started.elapsed().as_secs_f64() * 1000.0;
+
+    let stats = |samples: &[&(usize, f64, bool)]| {
+        LatencyStats::new(
+            samples.iter().map(|(_, latency, _)| *latency).collect(),
+            samples.iter().filter(|(_, _, ok)| !ok).count(),
+        )
+    };
+    let mut methods = BTreeMap::new();
+    for method in schedule.iter() {
+        if methods.contains_key(method) {
+            continue;
+        }
+        let method_samples: Vec<_> = samples
+            .iter()
+            .filter(|(index, _, _)| schedule[*index] == *method)
+            .collect();
+        methods.insert(method.clone(), stats(&method_samples));
+    }
+    let all_samples: Vec<_> = samples.iter().collect();
+
+    Ok(CommandResponse::Bench(BenchReport {
+        concurrency,
+        duration_ms,
+        requests_per_sec: match duration_ms > 0.0 {
+            true => samples.len() as f64 * 1000.0 / duration_ms,
+            false => 0.0,
+        },
+        overall: stats(&all_samples),
+        methods,
+    }))
+}
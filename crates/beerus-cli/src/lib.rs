@@ -1,4 +1,9 @@
+pub mod account;
+pub mod admin;
+pub mod bench;
 pub mod ethereum;
 pub mod model;
 pub mod runner;
 pub mod starknet;
+pub mod tx;
+pub mod verify;
@@ -1,6 +1,11 @@
-use beerus_cli::{model::Cli, runner};
+use beerus_cli::{
+    account, bench,
+    model::{Cli, CommandResponse, Commands},
+    runner,
+};
 use beerus_core::{
-    config::Config,
+    config::{Config, Profile},
+    data_dir::DataDir,
     lightclient::{
         beerus::BeerusLightClient, ethereum::helios_lightclient::HeliosLightClient,
         starknet::StarkNetLightClientImpl,
@@ -8,7 +13,7 @@ use beerus_core::{
 };
 use clap::Parser;
 use env_logger::Env;
-use log::{error, info};
+use log::{error, info, warn};
 use std::process::exit;
 
 #[tokio::main]
@@ -17,7 +22,53 @@ async fn main() {
 
     // TODO: we need to print CLI usage
     let cli = Cli::parse();
-    let config = match Config::new_from_env() {
+
+    // The verification and account commands run offline, and the load test against a remote
+    // instance, all without configuration or light client.
+    match &cli.command {
+        Commands::Account(account_commands) => {
+            print_response(account::run_account(&account_commands.command));
+            return;
+        }
+        Commands::Verify(verify_commands) => {
+            print_response(runner::run_verify(&verify_commands.command));
+            return;
+        }
+        Commands::Bench(bench_args) => {
+            print_response(bench::run_bench(bench_args).await);
+            return;
+        }
+        _ => {}
+    }
+
+    // The settings come from the environment, over the selected profile of the config file. The
+    // purge and archive commands only need the data directory, they run without light client.
+    let profile = match Profile::load(cli.config.as_deref(), cli.profile.as_deref()) {
+        Ok(profile) => profile,
+        Err(err) => {
+            error! {"{}", err};
+            exit(1);
+        }
+    };
+    match &cli.command {
+        Commands::Purge(purge_args) => {
+            print_response(runner::run_purge(
+                &DataDir::from_profile(&profile),
+                purge_args,
+            ));
+            return;
+        }
+        Commands::Archive(archive_commands) => {
+            print_response(runner::run_archive(
+                &DataDir::from_profile(&profile),
+                &archive_commands.command,
+            ));
+            return;
+        }
+        _ => {}
+    }
+
+    let config = match Config::new_from_profile(&profile) {
         Ok(config) => config,
         Err(err) => {
             error! {"{}", err};
@@ -35,7 +86,14 @@ async fn main() {
     };
 
     info!("creating starknet lightclient...");
-    let starknet_lightclient = match StarkNetLightClientImpl::new(&config) {
+    let starknet_lightclient = match cli.inject_faults.clone() {
+        Some(faults) => {
+            warn!("injecting faults in the StarkNet provider requests: {faults:?}");
+            StarkNetLightClientImpl::with_faults(&config, faults)
+        }
+        None => StarkNetLightClientImpl::new(&config),
+    };
+    let starknet_lightclient = match starknet_lightclient {
         Ok(starknet_lightclient) => starknet_lightclient,
         Err(err) => {
             error! {"{}", err};
@@ -57,7 +115,11 @@ async fn main() {
     };
 
     info!("running cli...");
-    match runner::run(beerus, cli).await {
+    print_response(runner::run(beerus, cli).await);
+}
+
+fn print_response(response: eyre::Result<CommandResponse>) {
+    match response {
         Ok(cmd_response) => {
             info!("successful command run...");
             println!("{cmd_response}");
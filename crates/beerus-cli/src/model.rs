@@ -1,4 +1,14 @@
-use beerus_core::lightclient::starknet::storage_proof::GetProofOutput;
+use crate::{
+    account::AccountInfo,
+    bench::{BenchReport, DEFAULT_METHOD_MIX},
+};
+use beerus_core::lightclient::{
+    beerus::RetentionStats,
+    export::{ExportFormat, ExportTable},
+    payload::BlockUsage,
+    starknet::storage_proof::GetProofOutput,
+    transport::faults::FaultConfig,
+};
 use clap::{Parser, Subcommand};
 use ethers::{
     prelude::Log,
@@ -21,9 +31,15 @@ use std::{fmt::Display, path::PathBuf};
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 pub struct Cli {
-    /// Set a custom config file
+    /// Set a custom config file, `beerus.toml` if a profile is selected without one
     #[arg(short, long, value_name = "FILE", global = true)]
     pub config: Option<PathBuf>,
+    /// Select a profile of the config file, `default` if a config file is set without one
+    #[arg(long, value_name = "NAME", global = true)]
+    pub profile: Option<String>,
+    /// Inject faults in the requests to the StarkNet providers, e.g. `error_rate=0.1,latency_ms=200`
+    #[arg(long, value_name = "FAULTS", global = true, hide = true)]
+    pub inject_faults: Option<FaultConfig>,
     /// List of supported commands.
     #[command(subcommand)]
     pub command: Commands,
@@ -38,6 +54,30 @@ pub enum Commands {
     /// StarkNet related subcommands
     #[command(name = "starknet", about = "StarkNet related subcommands")]
     StarkNet(StarkNetCommands),
+    /// Transaction subcommands, signed with a local keystore
+    #[command(about = "Transaction subcommands, signed with a local keystore")]
+    Tx(TxCommands),
+    /// Account subcommands, managing encrypted keystores
+    #[command(about = "Account subcommands, managing encrypted keystores")]
+    Account(AccountCommands),
+    /// Maintenance subcommands
+    #[command(about = "Maintenance subcommands")]
+    Admin(AdminCommands),
+    /// Offline verification subcommands
+    #[command(about = "Offline verification subcommands")]
+    Verify(VerifyCommands),
+    /// Load test of a Beerus RPC server
+    #[command(about = "Load test of a Beerus RPC server")]
+    Bench(BenchArgs),
+    /// Remove the data stored in the data directory
+    #[command(about = "Remove the data stored in the data directory")]
+    Purge(PurgeArgs),
+    /// Archive subcommands, reading the archive of the data directory
+    #[command(about = "Archive subcommands, reading the archive of the data directory")]
+    Archive(ArchiveCommands),
+    /// Export verified blocks, transactions, receipts or events
+    #[command(about = "Export verified blocks, transactions, receipts or events")]
+    Export(ExportArgs),
 }
 
 /// Ethereum related commands.
@@ -429,6 +469,231 @@ pub enum StarkNetSubCommands {
     },
 }
 
+/// Transaction commands.
+#[derive(Parser, Debug)]
+pub struct TxCommands {
+    /// Transaction subcommands.
+    #[command(subcommand)]
+    pub command: TxSubCommands,
+}
+
+/// Transaction subcommands.
+#[derive(Subcommand, Debug)]
+pub enum TxSubCommands {
+    /// Build, sign and broadcast an invoke transaction calling a contract from an account.
+    Invoke {
+        /// The account sending the transaction: its name, or its address with `--keystore`
+        #[arg(long, value_name = "ACCOUNT")]
+        account: String,
+        /// The keystore file holding the key of the account, if it isn't a stored account
+        #[arg(long, value_name = "FILE")]
+        keystore: Option<PathBuf>,
+        /// The password of the keystore, prompted for if not set
+        #[arg(
+            long,
+            value_name = "PASSWORD",
+            env = "BEERUS_KEYSTORE_PASSWORD",
+            hide_env_values = true
+        )]
+        password: Option<String>,
+        /// The contract called
+        #[arg(long, value_name = "ADDRESS")]
+        contract_address: String,
+        /// The name of the function called
+        #[arg(long, value_name = "NAME")]
+        function: String,
+        /// The ABI of the contract, to encode the arguments
+        #[arg(
+            long,
+            value_name = "FILE",
+            requires = "args",
+            conflicts_with = "calldata"
+        )]
+        abi: Option<PathBuf>,
+        /// The arguments of the function as a JSON array, encoded following the ABI
+        /// eg. '["0x1", ["0x2", "0x3"], {"low": "0x1", "high": "0x0"}]'
+        #[arg(long, value_name = "JSON", requires = "abi")]
+        args: Option<String>,
+        /// The calldata, if no ABI is given
+        #[arg(long, value_name = "CALLDATA", value_delimiter = ',')]
+        calldata: Vec<String>,
+        /// The max fee, estimated if not set
+        #[arg(long, value_name = "MAX_FEE")]
+        max_fee: Option<String>,
+    },
+}
+
+/// Account commands.
+#[derive(Parser, Debug)]
+pub struct AccountCommands {
+    /// Account subcommands.
+    #[command(subcommand)]
+    pub command: AccountSubCommands,
+}
+
+/// Account subcommands. The keystores are stored in the `accounts` directory of the data
+/// directory, encrypted with scrypt.
+#[derive(Subcommand, Debug)]
+pub enum AccountSubCommands {
+    /// Generate the key of a new account.
+    New {
+        /// The name of the account
+        #[arg(long, value_name = "NAME")]
+        name: String,
+        /// The address of the account, once deployed
+        #[arg(long, value_name = "ADDRESS")]
+        address: Option<String>,
+    },
+    /// Import the private key of an account, which is prompted for.
+    Import {
+        /// The name of the account
+        #[arg(long, value_name = "NAME")]
+        name: String,
+        /// The address of the account
+        #[arg(long, value_name = "ADDRESS")]
+        address: String,
+    },
+    /// List the stored accounts.
+    List {},
+}
+
+/// Maintenance commands.
+#[derive(Parser, Debug)]
+pub struct AdminCommands {
+    /// Maintenance subcommands.
+    #[command(subcommand)]
+    pub command: AdminSubCommands,
+}
+
+/// Maintenance subcommands.
+#[derive(Subcommand, Debug)]
+pub enum AdminSubCommands {
+    /// Query the range of retained blocks and the memory they use.
+    QueryRetentionStats {},
+    /// Query the memory used by each retained block.
+    QueryBlockUsage {},
+    /// Drop the blocks below a block number.
+    PruneTo {
+        /// The first block number to keep
+        #[arg(short, long, value_name = "BLOCK_NUMBER")]
+        block_number: u64,
+    },
+    /// Compress the blocks stored as is and drop stale block hashes.
+    Compact {},
+}
+
+/// Offline verification commands, which need no network access.
+#[derive(Parser, Debug)]
+pub struct VerifyCommands {
+    /// Offline verification subcommands.
+    #[command(subcommand)]
+    pub command: VerifySubCommands,
+}
+
+/// Offline verification subcommands.
+#[derive(Subcommand, Debug)]
+pub enum VerifySubCommands {
+    /// Verify a proof exported by beerus_getProof or beerus_getTransactionProof.
+    Proof {
+        /// The JSON file holding the proof
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+        /// The trusted state root to check the proof against
+        #[arg(long, value_name = "STATE_ROOT")]
+        root: String,
+    },
+    /// Verify the hash of a block returned by starknet_getBlockWithReceipts.
+    Block {
+        /// The JSON file holding the block
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+    },
+}
+
+/// Arguments of the load test of a Beerus RPC server.
+#[derive(Parser, Debug)]
+pub struct BenchArgs {
+    /// The RPC endpoint of the Beerus instance
+    #[arg(long, value_name = "URL", default_value = "http://0.0.0.0:3030")]
+    pub endpoint: String,
+    /// The number of concurrent workers
+    #[arg(short, long, value_name = "N", default_value_t = 10)]
+    pub concurrency: usize,
+    /// The total number of requests
+    #[arg(short = 'n', long, value_name = "N", default_value_t = 1000)]
+    pub requests: usize,
+    /// The methods to call, without parameters, as `method` or `method=weight`
+    #[arg(
+        short,
+        long,
+        value_name = "METHODS",
+        value_delimiter = ',',
+        default_values_t = DEFAULT_METHOD_MIX.map(String::from)
+    )]
+    pub methods: Vec<String>,
+}
+
+/// Arguments of the purge of the data directory.
+#[derive(Parser, Debug)]
+pub struct PurgeArgs {
+    /// Remove the keystores of the accounts too
+    #[arg(long)]
+    pub keystores: bool,
+}
+
+/// Archive commands, which run offline against the archive of the data directory.
+#[derive(Parser, Debug)]
+pub struct ArchiveCommands {
+    /// Archive subcommands.
+    #[command(subcommand)]
+    pub command: ArchiveSubCommands,
+}
+
+/// Archive subcommands. The archive is written by a node running with `ARCHIVE_MODE=true`.
+#[derive(Subcommand, Debug)]
+pub enum ArchiveSubCommands {
+    /// Export a range of archived blocks as CSV.
+    Export {
+        /// The table to export: blocks, transactions, receipts or events
+        #[arg(short, long, value_name = "TABLE", default_value = "blocks")]
+        table: ExportTable,
+        /// The first block number to export
+        #[arg(long, value_name = "BLOCK_NUMBER")]
+        from: u64,
+        /// The last block number to export
+        #[arg(long, value_name = "BLOCK_NUMBER")]
+        to: u64,
+        /// The format of the export: csv or parquet
+        #[arg(short, long, value_name = "FORMAT", default_value = "csv")]
+        format: ExportFormat,
+        /// The file to write, the standard output if unset, required for parquet
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    /// Compress the archive segments no longer appended to.
+    Compact {},
+}
+
+/// Arguments of the export of verified blocks, which must be proven on L1.
+#[derive(Parser, Debug)]
+pub struct ExportArgs {
+    /// The table to export: blocks, transactions, receipts or events
+    #[arg(value_name = "TABLE")]
+    pub table: ExportTable,
+    /// The first block number to export
+    #[arg(long, value_name = "BLOCK_NUMBER")]
+    pub from: u64,
+    /// The last block number to export
+    #[arg(long, value_name = "BLOCK_NUMBER")]
+    pub to: u64,
+    /// The format of the export: csv or parquet
+    #[arg(short, long, value_name = "FORMAT", default_value = "csv")]
+    pub format: ExportFormat,
+    /// The file to write, the standard output if unset, required for parquet
+    #[arg(short, long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+}
+
 /// The response from a CLI command.
 pub enum CommandResponse {
     EthereumSendRawTransaction(H256),
@@ -476,6 +741,20 @@ pub enum CommandResponse {
     StarknetQueryPendingTransactions(Vec<Transaction>),
     StarknetQueryTxReceipt(MaybePendingTransactionReceipt),
     StarknetQueryContractStorageProof(GetProofOutput),
+    AdminQueryRetentionStats(RetentionStats),
+    AdminQueryBlockUsage(Vec<BlockUsage>),
+    AdminPruneTo(usize),
+    AdminCompact(usize),
+    VerifyProof(FieldElement),
+    VerifyBlock(u64, FieldElement),
+    Bench(BenchReport),
+    AccountNew(AccountInfo),
+    AccountImport(AccountInfo),
+    AccountList(Vec<AccountInfo>),
+    Purge(Vec<PathBuf>),
+    Export(String),
+    ExportFile(usize, PathBuf),
+    ArchiveCompact(usize),
 }
 
 /// Display implementation for the CLI command response.
@@ -855,6 +1134,52 @@ impl Display for CommandResponse {
             CommandResponse::StarknetAddDeclareTransaction(response) => {
                 write!(f, "{response:?}")
             }
+
+            // Print the retention statistics as JSON
+            CommandResponse::AdminQueryRetentionStats(stats) => {
+                write!(f, "{}", json!(stats))
+            }
+            // Print the memory usage of each block as JSON
+            CommandResponse::AdminQueryBlockUsage(usage) => write!(f, "{}", json!(usage)),
+            // Result looks like: Pruned blocks: 10
+            CommandResponse::AdminPruneTo(pruned) => write!(f, "Pruned blocks: {pruned}"),
+            // Result looks like: Compressed blocks: 2
+            CommandResponse::AdminCompact(compressed) => {
+                write!(f, "Compressed blocks: {compressed}")
+            }
+            // Result looks like: Proof verified against state root 0x123
+            CommandResponse::VerifyProof(state_root) => {
+                write!(f, "Proof verified against state root {state_root:#x}")
+            }
+            // Result looks like: Block 10 verified, hash 0x123
+            CommandResponse::VerifyBlock(block_number, block_hash) => {
+                write!(f, "Block {block_number} verified, hash {block_hash:#x}")
+            }
+            // Print the latency percentiles as JSON
+            CommandResponse::Bench(report) => write!(f, "{}", json!(report)),
+            // Print the accounts as JSON
+            CommandResponse::AccountNew(account) | CommandResponse::AccountImport(account) => {
+                write!(f, "{}", json!(account))
+            }
+            CommandResponse::AccountList(accounts) => write!(f, "{}", json!(accounts)),
+            // Result looks like: Removed: /tmp/helios, /tmp/payload
+            CommandResponse::Purge(removed) => {
+                let removed: Vec<String> = removed
+                    .iter()
+                    .map(|dir| dir.display().to_string())
+                    .collect();
+                write!(f, "Removed: {}", removed.join(", "))
+            }
+            // Print the CSV as is, it ends with a new line.
+            CommandResponse::Export(csv) => write!(f, "{}", csv.trim_end()),
+            // Result looks like: Exported 12 rows to blocks.csv
+            CommandResponse::ExportFile(rows, output) => {
+                write!(f, "Exported {rows} rows to {}", output.display())
+            }
+            // Result looks like: Compacted segments: 2
+            CommandResponse::ArchiveCompact(compacted) => {
+                write!(f, "Compacted segments: {compacted}")
+            }
         }
     }
 }
@@ -1,15 +1,23 @@
 use crate::{
-    model::{CommandResponse, StarkNetSubCommands},
-    starknet,
+    account, admin, bench,
+    model::{
+        AdminSubCommands, ArchiveSubCommands, CommandResponse, ExportArgs, PurgeArgs,
+        StarkNetSubCommands, VerifySubCommands,
+    },
+    starknet, tx, verify,
 };
 
 use super::{
     ethereum,
     model::{Cli, Commands, EthereumSubCommands},
 };
-use beerus_core::lightclient::beerus::BeerusLightClient;
-use eyre::Result;
+use beerus_core::{
+    data_dir::DataDir,
+    lightclient::{archive::Archive, beerus::BeerusLightClient, export::ExportFormat},
+};
+use eyre::{eyre, Result};
 use helios::types::BlockTag;
+use std::path::PathBuf;
 
 /// Main entry point for the Beerus CLI.
 /// # Arguments
@@ -319,5 +327,127 @@ pub async fn run(beerus: BeerusLightClient, cli: Cli) -> Result<CommandResponse>
                 .await
             }
         },
+        // Transaction commands.
+        Commands::Tx(tx_commands) => tx::run_tx(beerus, &tx_commands.command).await,
+        // Account commands.
+        Commands::Account(account_commands) => account::run_account(&account_commands.command),
+        // Maintenance commands.
+        Commands::Admin(admin_commands) => match &admin_commands.command {
+            AdminSubCommands::QueryRetentionStats {} => admin::query_retention_stats(beerus).await,
+            AdminSubCommands::QueryBlockUsage {} => admin::query_block_usage(beerus).await,
+            AdminSubCommands::PruneTo { block_number } => {
+                admin::prune_to(beerus, *block_number).await
+            }
+            AdminSubCommands::Compact {} => admin::compact(beerus).await,
+        },
+        // Offline verification commands.
+        Commands::Verify(verify_commands) => run_verify(&verify_commands.command),
+        // Load test of a remote instance.
+        Commands::Bench(bench_args) => bench::run_bench(bench_args).await,
+        // Purge of the data directory.
+        Commands::Purge(purge_args) => run_purge(&beerus.config.data_layout(), purge_args),
+        // Offline archive commands.
+        Commands::Archive(archive_commands) => {
+            run_archive(&beerus.config.data_layout(), &archive_commands.command)
+        }
+        // Export of verified blocks.
+        Commands::Export(export_args) => run_export(&beerus, export_args).await,
+    }
+}
+
+/// Entry point for the purge of the data directory, which runs without a light client. Only the
+/// subdirectories Beerus lays out are removed.
+/// # Arguments
+/// * `data_dir` - The data directory, set by `DATA_DIR` or the selected profile.
+/// * `args` - The purge arguments.
+/// # Returns
+/// * `Result<CommandResponse>` - The subdirectories removed.
+/// # Errors
+/// * If the directory wasn't laid out by Beerus, or can't be cleaned.
+pub fn run_purge(data_dir: &DataDir, args: &PurgeArgs) -> Result<CommandResponse> {
+    let removed = data_dir.purge(args.keystores)?;
+    Ok(CommandResponse::Purge(removed))
+}
+
+/// Entry point for the archive commands, which read the archive of the data directory without a
+/// light client.
+/// # Arguments
+/// * `data_dir` - The data directory, set by `DATA_DIR` or the selected profile.
+/// * `command` - The archive subcommand.
+/// # Returns
+/// * `Result<CommandResponse>` - The exported CSV, or the number of rows or segments written.
+/// # Errors
+/// * If the range is invalid, or the archive or the output can't be accessed.
+pub fn run_archive(data_dir: &DataDir, command: &ArchiveSubCommands) -> Result<CommandResponse> {
+    let archive = Archive::new(data_dir.archive());
+    match command {
+        ArchiveSubCommands::Export {
+            table,
+            from,
+            to,
+            format,
+            output,
+        } => {
+            check_export_output(*format, output)?;
+            let mut bytes = Vec::new();
+            let rows = archive.export(*table, *format, *from, *to, &mut bytes)?;
+            export_response(rows, bytes, output)
+        }
+        ArchiveSubCommands::Compact {} => Ok(CommandResponse::ArchiveCompact(archive.compact()?)),
+    }
+}
+
+/// Export a table of verified blocks, fetched through the light client.
+/// # Arguments
+/// * `beerus` - The Beerus light client.
+/// * `args` - The table, range, format and output of the export.
+/// # Returns
+/// * `Result<CommandResponse>` - The exported CSV, or the number of rows written.
+/// # Errors
+/// * If a block isn't proven on L1, or can't be fetched or verified.
+pub async fn run_export(beerus: &BeerusLightClient, args: &ExportArgs) -> Result<CommandResponse> {
+    check_export_output(args.format, &args.output)?;
+    let mut bytes = Vec::new();
+    let rows = beerus
+        .export_blocks(args.table, args.format, args.from, args.to, &mut bytes)
+        .await?;
+    export_response(rows, bytes, &args.output)
+}
+
+/// Parquet is binary, it is only written to a file.
+fn check_export_output(format: ExportFormat, output: &Option<PathBuf>) -> Result<()> {
+    match (format, output) {
+        (ExportFormat::Parquet, None) => Err(eyre!("Parquet exports need a file, set --output")),
+        _ => Ok(()),
+    }
+}
+
+/// Write an export to its file, or return it to be printed when no file is given.
+fn export_response(
+    rows: usize,
+    bytes: Vec<u8>,
+    output: &Option<PathBuf>,
+) -> Result<CommandResponse> {
+    match output {
+        Some(output) => {
+            std::fs::write(output, bytes)
+                .map_err(|err| eyre!("Failed to write {}: {err}", output.display()))?;
+            Ok(CommandResponse::ExportFile(rows, output.clone()))
+        }
+        None => Ok(CommandResponse::Export(String::from_utf8(bytes)?)),
+    }
+}
+
+/// Entry point for the offline verification commands, which run without a light client.
+/// # Arguments
+/// * `command` - The verification subcommand.
+/// # Returns
+/// * `Result<CommandResponse>` - The result of the verification.
+/// # Errors
+/// * If the verification fails.
+pub fn run_verify(command: &VerifySubCommands) -> Result<CommandResponse> {
+    match command {
+        VerifySubCommands::Proof { file, root } => verify::verify_proof(file, root),
+        VerifySubCommands::Block { file } => verify::verify_block(file),
     }
 }
@@ -213,24 +213,26 @@ pub async fn query_chain_id(beerus: BeerusLightClient) -> Result<CommandResponse
     Ok(CommandResponse::StarknetQueryChainId(chain_id))
 }
 
-/// Query the current block number of the StarkNet network.
+/// Query the current block number of the StarkNet network, the last block proven on L1 unless
+/// `BLOCK_NUMBER_SOURCE` is `upstream`.
 /// # Arguments
 /// * `beerus` - The Beerus light client.
 /// # Returns
 /// * `Result<CommandResponse>` - The current block number of the StarkNet network.
 pub async fn query_block_number(beerus: BeerusLightClient) -> Result<CommandResponse> {
-    let block_number = beerus.starknet_lightclient.block_number().await?;
+    let block_number = beerus.starknet_block_number().await?;
     Ok(CommandResponse::StarknetQueryBlockNumber(block_number))
 }
 
-/// Query the current block hash and number of the StarkNet network.
+/// Query the current block hash and number of the StarkNet network, from the same source as
+/// [`query_block_number`].
 /// # Arguments
 /// * `beerus` - The Beerus light client.
 /// # Returns
 /// * `Result<CommandResponse>` - The current block hash and number of the StarkNet network.
 pub async fn query_block_hash_and_number(beerus: BeerusLightClient) -> Result<CommandResponse> {
     Ok(CommandResponse::StarknetQueryBlockHashAndNumber(
-        beerus.starknet_lightclient.block_hash_and_number().await?,
+        beerus.starknet_block_hash_and_number().await?,
     ))
 }
 
@@ -248,8 +250,7 @@ pub async fn get_class(
     block_id: String,
     class_hash: String,
 ) -> Result<CommandResponse> {
-    let block_id =
-        beerus_core::starknet_helper::block_id_string_to_block_id_type(&block_id_type, &block_id)?;
+    let block_id = beerus.resolve_block_id(&block_id_type, &block_id).await?;
     let class_hash = FieldElement::from_str(&class_hash)?;
     Ok(CommandResponse::StarknetQueryGetClass(
         beerus
@@ -274,8 +275,7 @@ pub async fn get_class_hash(
     block_id: String,
     contract_address: String,
 ) -> Result<CommandResponse> {
-    let block_id =
-        beerus_core::starknet_helper::block_id_string_to_block_id_type(&block_id_type, &block_id)?;
+    let block_id = beerus.resolve_block_id(&block_id_type, &block_id).await?;
     let contract_address = FieldElement::from_str(&contract_address)?;
     Ok(CommandResponse::StarknetQueryGetClassHash(
         beerus
@@ -300,8 +300,7 @@ pub async fn get_class_at(
     block_id: String,
     contract_address: String,
 ) -> Result<CommandResponse> {
-    let block_id =
-        beerus_core::starknet_helper::block_id_string_to_block_id_type(&block_id_type, &block_id)?;
+    let block_id = beerus.resolve_block_id(&block_id_type, &block_id).await?;
     let contract_address = FieldElement::from_str(&contract_address)?;
 
     Ok(CommandResponse::StarknetQueryGetClass(
@@ -324,8 +323,7 @@ pub async fn get_block_transaction_count(
     block_id_type: String,
     block_id: String,
 ) -> Result<CommandResponse> {
-    let block_id =
-        beerus_core::starknet_helper::block_id_string_to_block_id_type(&block_id_type, &block_id)?;
+    let block_id = beerus.resolve_block_id(&block_id_type, &block_id).await?;
     Ok(CommandResponse::StarknetQueryGetBlockTransactionCount(
         beerus
             .starknet_lightclient
@@ -345,8 +343,7 @@ pub async fn get_state_update(
     block_id_type: String,
     block_id: String,
 ) -> Result<CommandResponse> {
-    let block_id =
-        beerus_core::starknet_helper::block_id_string_to_block_id_type(&block_id_type, &block_id)?;
+    let block_id = beerus.resolve_block_id(&block_id_type, &block_id).await?;
     Ok(CommandResponse::StarknetQueryGetStateUpdate(
         beerus
             .starknet_lightclient
@@ -369,10 +366,9 @@ pub async fn get_events(beerus: BeerusLightClient, params: String) -> Result<Com
         events_object.from_block_id,
     ) {
         (Some(from_block_id_type_str), Some(from_block_id_str)) => {
-            let result = beerus_core::starknet_helper::block_id_string_to_block_id_type(
-                &from_block_id_type_str,
-                &from_block_id_str,
-            );
+            let result = beerus
+                .resolve_block_id(&from_block_id_type_str, &from_block_id_str)
+                .await;
             Some(result?)
         }
         _ => None,
@@ -380,10 +376,9 @@ pub async fn get_events(beerus: BeerusLightClient, params: String) -> Result<Com
 
     let to_block = match (events_object.to_block_id_type, events_object.to_block_id) {
         (Some(to_block_id_type_str), Some(to_block_id_str)) => {
-            let result = beerus_core::starknet_helper::block_id_string_to_block_id_type(
-                &to_block_id_type_str,
-                &to_block_id_str,
-            );
+            let result = beerus
+                .resolve_block_id(&to_block_id_type_str, &to_block_id_str)
+                .await;
             Some(result?)
         }
         _ => None,
@@ -444,8 +439,7 @@ pub async fn query_starknet_estimate_fee(
     block_id_type: String,
     broadcasted_transaction: String,
 ) -> Result<CommandResponse> {
-    let block_id =
-        beerus_core::starknet_helper::block_id_string_to_block_id_type(&block_id_type, &block_id)?;
+    let block_id = beerus.resolve_block_id(&block_id_type, &block_id).await?;
     let tx = serde_json::from_str(broadcasted_transaction.as_str())?;
     Ok(CommandResponse::StarknetQueryEstimateFee(
         beerus
@@ -564,8 +558,7 @@ pub async fn query_block_with_txs(
     block_id_type: String,
     block_id: String,
 ) -> Result<CommandResponse> {
-    let block_id =
-        beerus_core::starknet_helper::block_id_string_to_block_id_type(&block_id_type, &block_id)?;
+    let block_id = beerus.resolve_block_id(&block_id_type, &block_id).await?;
     Ok(CommandResponse::StarknetQueryBlockWithTxs(
         beerus
             .starknet_lightclient
@@ -606,8 +599,7 @@ pub async fn get_transaction_by_block_id_and_index(
     index: String,
 ) -> Result<CommandResponse> {
     let index = u64::from_str(&index)?;
-    let block_id =
-        beerus_core::starknet_helper::block_id_string_to_block_id_type(&block_id_type, &block_id)?;
+    let block_id = beerus.resolve_block_id(&block_id_type, &block_id).await?;
     Ok(CommandResponse::StarknetQueryTransactionByBlockIdAndIndex(
         beerus
             .starknet_lightclient
@@ -641,8 +633,7 @@ pub async fn query_block_with_tx_hashes(
     block_id_type: String,
     block_id: String,
 ) -> Result<CommandResponse> {
-    let block_id =
-        beerus_core::starknet_helper::block_id_string_to_block_id_type(&block_id_type, &block_id)?;
+    let block_id = beerus.resolve_block_id(&block_id_type, &block_id).await?;
     Ok(CommandResponse::StarknetQueryBlockWithTxHashes(
         beerus
             .starknet_lightclient
@@ -683,8 +674,7 @@ pub async fn query_contract_storage_proof(
     contract_address: String,
     keys: &[String],
 ) -> Result<CommandResponse> {
-    let block_id =
-        beerus_core::starknet_helper::block_id_string_to_block_id_type(&block_id_type, &block_id)?;
+    let block_id = beerus.resolve_block_id(&block_id_type, &block_id).await?;
     let contract_address = FieldElement::from_str(&contract_address)?;
     let keys: Result<Vec<FieldElement>, _> =
         keys.iter().map(|k| FieldElement::from_str(k)).collect();
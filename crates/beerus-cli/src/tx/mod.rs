@@ -0,0 +1,239 @@
+use crate::{
+    account::{accounts_dir, load_account},
+    model::{CommandResponse, TxSubCommands},
+};
+use beerus_core::{
+    encoding::{get_selector_from_name, short_string_to_felt},
+    lightclient::{
+        abi::{AbiValue, ContractAbi},
+        beerus::BeerusLightClient,
+        nonce_manager::NonceManager,
+    },
+};
+use eyre::{eyre, Result};
+use serde_json::Value;
+use starknet::{
+    core::{crypto::compute_hash_on_elements, types::FieldElement},
+    providers::jsonrpc::models::{
+        BlockId, BlockTag, BroadcastedInvokeTransaction, BroadcastedInvokeTransactionV1,
+        BroadcastedTransaction,
+    },
+    signers::SigningKey,
+};
+use std::{path::Path, str::FromStr};
+
+/// Prefix of the hash of invoke transactions.
+const INVOKE_PREFIX: &str = "invoke";
+
+/// Load the signing key of an account from an encrypted keystore file.
+/// # Arguments
+/// * `keystore` - The keystore file.
+/// * `password` - The password the keystore is encrypted with.
+pub fn load_signing_key(keystore: &Path, password: &str) -> Result<SigningKey> {
+    let secret = eth_keystore::decrypt_key(keystore, password)
+        .map_err(|err| eyre!("Failed to decrypt keystore {}: {err}", keystore.display()))?;
+    Ok(SigningKey::from_secret_scalar(
+        FieldElement::from_byte_slice_be(&secret)?,
+    ))
+}
+
+/// Encode the arguments of a function as calldata, following the types of its inputs in a
+/// Cairo 0 or Sierra ABI. The arguments are a JSON array, with one item per input: a felt as a
+/// string or a number, an array for an array input, whose length is added, an object or array
+/// of members for a struct, and an object with a single member for an enum variant.
+/// # Arguments
+/// * `abi` - The contract ABI.
+/// * `function` - The name of the function.
+/// * `args` - The JSON array of arguments.
+pub fn abi_calldata(abi: &Value, function: &str, args: &Value) -> Result<Vec<FieldElement>> {
+    let args = args
+        .as_array()
+        .ok_or_else(|| eyre!("The arguments are not an array"))?
+        .iter()
+        .map(AbiValue::from_json)
+        .collect::<Result<Vec<_>>>()?;
+    ContractAbi::from_json(abi)?.encode(function, &args)
+}
+
+/// Calldata of the `__execute__` entrypoint of an account performing a single call.
+/// # Arguments
+/// * `to` - The contract called.
+/// * `selector` - The selector of the function called.
+/// * `calldata` - The calldata of the call.
+pub fn execute_calldata(
+    to: FieldElement,
+    selector: FieldElement,
+    calldata: &[FieldElement],
+) -> Vec<FieldElement> {
+    let len = FieldElement::from(calldata.len() as u64);
+    let mut execute = vec![
+        FieldElement::ONE,
+        to,
+        selector,
+        FieldElement::ZERO,
+        len,
+        len,
+    ];
+    execute.extend_from_slice(calldata);
+    execute
+}
+
+/// Hash of an invoke transaction of version 1, the message signed by the account.
+pub fn invoke_v1_hash(
+    transaction: &BroadcastedInvokeTransactionV1,
+    chain_id: FieldElement,
+) -> FieldElement {
+    compute_hash_on_elements(&[
+        short_string_to_felt(INVOKE_PREFIX).unwrap(),
+        FieldElement::ONE,
+        transaction.sender_address,
+        FieldElement::ZERO,
+        compute_hash_on_elements(&transaction.calldata),
+        transaction.max_fee,
+        chain_id,
+        transaction.nonce,
+    ])
+}
+
+/// Sign an invoke transaction with the key of its account.
+fn sign(
+    mut transaction: BroadcastedInvokeTransactionV1,
+    chain_id: FieldElement,
+    signing_key: &SigningKey,
+) -> Result<BroadcastedInvokeTransactionV1> {
+    let signature = signing_key
+        .sign(&invoke_v1_hash(&transaction, chain_id))
+        .map_err(|err| eyre!("Failed to sign the transaction: {err}"))?;
+    transaction.signature = vec![signature.r, signature.s];
+    Ok(transaction)
+}
+
+/// Build, sign and broadcast an invoke transaction calling a contract from an account.
+/// The nonce is the next one of the account, pending transactions included. Unless a max fee
+/// is given, it is the fee estimated on the pending block, plus a margin of 50%.
+/// # Arguments
+/// * `beerus` - The Beerus light client.
+/// * `account` - The address of the account sending the transaction.
+/// * `signing_key` - The key of the account.
+/// * `contract_address` - The contract called.
+/// * `function` - The name of the function called.
+/// * `calldata` - The calldata of the call.
+/// * `max_fee` - The max fee, estimated if `None`.
+/// # Returns
+/// * `Result<CommandResponse>` - The hash of the transaction.
+pub async fn invoke(
+    beerus: BeerusLightClient,
+    account: FieldElement,
+    signing_key: &SigningKey,
+    contract_address: FieldElement,
+    function: &str,
+    calldata: Vec<FieldElement>,
+    max_fee: Option<FieldElement>,
+) -> Result<CommandResponse> {
+    let selector = get_selector_from_name(function)?;
+    let nonce_manager = NonceManager::new();
+    let nonce = nonce_manager.next_nonce(&beerus, account).await?;
+    let chain_id = beerus.starknet_lightclient.chain_id().await?;
+    let transaction = BroadcastedInvokeTransactionV1 {
+        max_fee: FieldElement::ZERO,
+        signature: vec![],
+        nonce,
+        sender_address: account,
+        calldata: execute_calldata(contract_address, selector, &calldata),
+    };
+
+    let max_fee = match max_fee {
+        Some(max_fee) => max_fee,
+        None => {
+            let estimate = beerus
+                .starknet_estimate_fee(
+                    BroadcastedTransaction::Invoke(BroadcastedInvokeTransaction::V1(sign(
+                        transaction.clone(),
+                        chain_id,
+                        signing_key,
+                    )?)),
+                    &BlockId::Tag(BlockTag::Pending),
+                )
+                .await?;
+            FieldElement::from(estimate.overall_fee + estimate.overall_fee / 2)
+        }
+    };
+    let transaction = sign(
+        BroadcastedInvokeTransactionV1 {
+            max_fee,
+            ..transaction
+        },
+        chain_id,
+        signing_key,
+    )?;
+
+    Ok(CommandResponse::StarknetAddInvokeTransaction(
+        nonce_manager
+            .add_invoke_transaction(&beerus, &BroadcastedInvokeTransaction::V1(transaction))
+            .await?,
+    ))
+}
+
+/// Entry point for the transaction commands.
+/// # Arguments
+/// * `beerus` - The Beerus light client.
+/// * `command` - The transaction subcommand.
+/// # Returns
+/// * `Result<CommandResponse>` - The result of the transaction command.
+/// # Errors
+/// * If the keystore can't be decrypted, the calldata can't be encoded or the transaction
+///   is rejected.
+pub async fn run_tx(beerus: BeerusLightClient, command: &TxSubCommands) -> Result<CommandResponse> {
+    match command {
+        TxSubCommands::Invoke {
+            account,
+            keystore,
+            password,
+            contract_address,
+            function,
+            abi,
+            args,
+            calldata,
+            max_fee,
+        } => {
+            let calldata = match (abi, args) {
+                (Some(abi), Some(args)) => abi_calldata(
+                    &serde_json::from_slice(&std::fs::read(abi)?)?,
+                    function,
+                    &serde_json::from_str(args)?,
+                )?,
+                _ => calldata
+                    .iter()
+                    .map(|value| FieldElement::from_str(value))
+                    .collect::<Result<_, _>>()?,
+            };
+            let password = match password {
+                Some(password) => password.clone(),
+                None => rpassword::prompt_password("Keystore password: ")?,
+            };
+            let (account, signing_key) = match keystore {
+                Some(keystore) => (
+                    FieldElement::from_str(account)?,
+                    load_signing_key(keystore, &password)?,
+                ),
+                None => {
+                    let (info, signing_key) = load_account(&accounts_dir(), account, &password)?;
+                    let address = info.address.ok_or_else(|| {
+                        eyre!("Account {account} has no address, import it with its address")
+                    })?;
+                    (address, signing_key)
+                }
+            };
+            invoke(
+                beerus,
+                account,
+                &signing_key,
+                FieldElement::from_str(contract_address)?,
+                function,
+                calldata,
+                max_fee.as_deref().map(FieldElement::from_str).transpose()?,
+            )
+            .await
+        }
+    }
+}
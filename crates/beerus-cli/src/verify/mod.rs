@@ -0,0 +1,90 @@
+use crate::model::CommandResponse;
+use beerus_core::lightclient::{
+    beerus::{BlockWithReceipts, ContractProof},
+    starknet::{
+        block_hash::verify_block_hash_with_receipts, inclusion_proof::TransactionProof,
+        storage_proof::Membership,
+    },
+};
+use eyre::{eyre, Result};
+use serde_json::Value;
+use starknet::core::types::FieldElement;
+use std::path::Path;
+
+/// Verify a proof exported by `beerus_getProof` or `beerus_getTransactionProof` against a
+/// trusted state root. No network access is needed.
+/// # Arguments
+/// * `file` - The JSON file holding the proof, or the JSON-RPC response carrying it.
+/// * `root` - The state root to check the proof against.
+/// # Returns
+/// * `Result<CommandResponse>` - The state root the proof was checked against.
+/// # Errors
+/// * If the file can't be read or the proof does not match the state root.
+pub fn verify_proof(file: &Path, root: &str) -> Result<CommandResponse> {
+    let state_root = FieldElement::from_hex_be(root)?;
+    let proof = read_result(file)?;
+
+    // Transaction proofs are recognized by their commitment path.
+    if proof.get("commitment_proof").is_some() {
+        let mut proof: TransactionProof = serde_json::from_value(proof)?;
+        proof.l1_state_root = Some(state_root);
+        proof.verify()?;
+        return Ok(CommandResponse::VerifyProof(state_root));
+    }
+
+    let ContractProof {
+        contract_address,
+        storage_keys,
+        storage_values,
+        proof,
+    } = serde_json::from_value(proof)?;
+    let memberships = proof
+        .verify(state_root, contract_address, &storage_keys, &storage_values)
+        .ok_or_else(|| {
+            eyre!(
+                "Contract proof of {contract_address:#x} does not match state root {state_root:#x}"
+            )
+        })?;
+    for ((key, value), membership) in storage_keys.iter().zip(&storage_values).zip(memberships) {
+        match membership {
+            Some(Membership::Member) => {}
+            Some(Membership::NonMember) if *value == FieldElement::ZERO => {}
+            _ => {
+                return Err(eyre!(
+                    "Storage proof of key {key:#x} does not match value {value:#x}"
+                ))
+            }
+        }
+    }
+    Ok(CommandResponse::VerifyProof(state_root))
+}
+
+/// Verify the hash of a block returned by `starknet_getBlockWithReceipts`, by recomputing it
+/// from its header, transactions and receipts. No network access is needed.
+/// # Arguments
+/// * `file` - The JSON file holding the block, or the JSON-RPC response carrying it.
+/// # Returns
+/// * `Result<CommandResponse>` - The number and hash of the verified block.
+/// # Errors
+/// * If the file can't be read or the block hash does not match.
+pub fn verify_block(file: &Path) -> Result<CommandResponse> {
+    let block: BlockWithReceipts = serde_json::from_value(read_result(file)?)?;
+    let (block, receipts) = block.into_parts();
+    verify_block_hash_with_receipts(&block, &receipts)?;
+    Ok(CommandResponse::VerifyBlock(
+        block.block_number,
+        block.block_hash,
+    ))
+}
+
+/// Read a JSON file, unwrapping the `result` of a JSON-RPC response and of a response with
+/// metadata.
+fn read_result(file: &Path) -> Result<Value> {
+    let content = std::fs::read_to_string(file)
+        .map_err(|e| eyre!("Failed to read {}: {e}", file.display()))?;
+    let mut value: Value = serde_json::from_str(&content)?;
+    while let Some(result) = value.get_mut("result") {
+        value = result.take();
+    }
+    Ok(value)
+}
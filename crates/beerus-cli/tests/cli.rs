@@ -4,34 +4,45 @@ mod test {
     use std::{path::PathBuf, str::FromStr};
 
     use beerus_cli::{
+        account,
+        bench::{parse_method_mix, percentile, LatencyStats},
         model::{
-            Cli, Commands, EthereumCommands, EthereumSubCommands, StarkNetCommands,
-            StarkNetSubCommands,
+            ArchiveSubCommands, Cli, Commands, EthereumCommands, EthereumSubCommands, ExportArgs,
+            PurgeArgs, StarkNetCommands, StarkNetSubCommands, VerifySubCommands,
         },
-        runner,
+        runner, tx,
     };
     use beerus_core::{
-        config::Config,
+        config::{
+            Config, HttpConfig, DEFAULT_STARKNET_BATCH_FLUSH_INTERVAL_MS,
+            DEFAULT_STARKNET_BATCH_MAX_SIZE,
+        },
+        data_dir::DataDir,
         lightclient::{
-            beerus::BeerusLightClient,
+            beerus::{BeerusLightClient, BlockWithReceipts},
             ethereum::MockEthereumLightClient,
-            starknet::{storage_proof::GetProofOutput, MockStarkNetLightClient},
+            export::{ExportFormat, ExportTable},
+            starknet::{
+                block_hash::compute_block_hash, inclusion_proof::TransactionProof,
+                storage_proof::GetProofOutput, MockStarkNetLightClient,
+            },
         },
         starknet_helper::create_mock_broadcasted_transaction,
     };
     use ethers::types::{Address, H256, U256};
     use helios::types::{ExecutionBlock, Transactions};
     use starknet::{
-        core::types::FieldElement,
+        core::{crypto::Signature, types::FieldElement, utils::get_selector_from_name},
         providers::jsonrpc::models::{
-            BlockHashAndNumber, BlockStatus, BlockWithTxHashes, BlockWithTxs, ContractClass,
-            ContractEntryPoint, DeclareTransactionResult, DeployTransactionResult,
-            EntryPointsByType, FeeEstimate, InvokeTransaction, InvokeTransactionReceipt,
-            InvokeTransactionResult, InvokeTransactionV0, InvokeTransactionV1,
-            MaybePendingBlockWithTxHashes, MaybePendingBlockWithTxs,
-            MaybePendingTransactionReceipt, StateDiff, StateUpdate,
+            BlockHashAndNumber, BlockStatus, BlockWithTxHashes, BlockWithTxs,
+            BroadcastedInvokeTransaction, ContractClass, ContractEntryPoint,
+            DeclareTransactionResult, DeployTransactionResult, EntryPointsByType, FeeEstimate,
+            InvokeTransaction, InvokeTransactionReceipt, InvokeTransactionResult,
+            InvokeTransactionV0, InvokeTransactionV1, MaybePendingBlockWithTxHashes,
+            MaybePendingBlockWithTxs, MaybePendingTransactionReceipt, StateDiff, StateUpdate,
             Transaction as StarknetTransaction, TransactionReceipt, TransactionStatus,
         },
+        signers::SigningKey,
     };
 
     /// Test the `send_raw_transaction` CLI command.
@@ -59,6 +70,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::Ethereum(EthereumCommands {
                 command: EthereumSubCommands::SendRawTransaction {
                     bytes: "0xc24215226336d22238a20a72f8e489c005b44c4a".to_string(),
@@ -96,6 +109,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::Ethereum(EthereumCommands {
                 command: EthereumSubCommands::SendRawTransaction {
                     bytes: "0xc24215226336d22238a20a72f8e489c005b44c4a".to_string(),
@@ -136,6 +151,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::Ethereum(EthereumCommands {
                 command: EthereumSubCommands::QueryBalance {
                     address: "0xc24215226336d22238a20a72f8e489c005b44c4a".to_string(),
@@ -172,6 +189,8 @@ mod test {
         // Mock the command line arguments,
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::Ethereum(EthereumCommands {
                 command: EthereumSubCommands::QueryNonce {
                     address: "0xc24215226336d22238a20a72f8e489c005b44c4a".to_string(),
@@ -209,6 +228,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::Ethereum(EthereumCommands {
                 command: EthereumSubCommands::QueryBlockNumber {},
             }),
@@ -244,6 +265,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::Ethereum(EthereumCommands {
                 command: EthereumSubCommands::QueryBalance {
                     address: "0xc24215226336d22238a20a72f8e489c005b44c4a".to_string(),
@@ -283,6 +306,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::Ethereum(EthereumCommands {
                 command: EthereumSubCommands::QueryNonce {
                     address: "0xc24215226336d22238a20a72f8e489c005b44c4a".to_string(),
@@ -323,6 +348,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::Ethereum(EthereumCommands {
                 command: EthereumSubCommands::QueryBlockNumber {},
             }),
@@ -360,6 +387,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::Ethereum(EthereumCommands {
                 command: EthereumSubCommands::QueryChainId {},
             }),
@@ -395,6 +424,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::Ethereum(EthereumCommands {
                 command: EthereumSubCommands::QueryCode { address },
             }),
@@ -430,6 +461,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::Ethereum(EthereumCommands {
                 command: EthereumSubCommands::QueryCode { address },
             }),
@@ -469,6 +502,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::Ethereum(EthereumCommands {
                 command: EthereumSubCommands::QueryBlockTxCountByNumber { block },
             }),
@@ -504,6 +539,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::Ethereum(EthereumCommands {
                 command: EthereumSubCommands::QueryBlockTxCountByNumber { block },
             }),
@@ -545,6 +582,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::Ethereum(EthereumCommands {
                 command: EthereumSubCommands::QueryTxCount { address, block },
             }),
@@ -582,6 +621,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::Ethereum(EthereumCommands {
                 command: EthereumSubCommands::QueryTxCount { address, block },
             }),
@@ -621,6 +662,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::Ethereum(EthereumCommands {
                 command: EthereumSubCommands::QueryBlockTxCountByHash { hash },
             }),
@@ -656,6 +699,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::Ethereum(EthereumCommands {
                 command: EthereumSubCommands::QueryBlockTxCountByHash { hash },
             }),
@@ -710,6 +755,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::StarkNet(StarkNetCommands {
                 command: StarkNetSubCommands::QueryTxReceipt { tx_hash: hash },
             }),
@@ -751,6 +798,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::StarkNet(StarkNetCommands {
                 command: StarkNetSubCommands::QueryTxReceipt { tx_hash },
             }),
@@ -792,6 +841,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::Ethereum(EthereumCommands {
                 command: EthereumSubCommands::QueryTxByHash { hash },
             }),
@@ -831,6 +882,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::Ethereum(EthereumCommands {
                 command: EthereumSubCommands::QueryGasPrice {},
             }),
@@ -865,6 +918,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::Ethereum(EthereumCommands {
                 command: EthereumSubCommands::QueryGasPrice {},
             }),
@@ -903,6 +958,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::Ethereum(EthereumCommands {
                 command: EthereumSubCommands::QueryEstimateGas { params },
             }),
@@ -938,6 +995,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::Ethereum(EthereumCommands {
                 command: EthereumSubCommands::QueryEstimateGas { params },
             }),
@@ -1002,6 +1061,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::Ethereum(EthereumCommands {
                 command: EthereumSubCommands::QueryBlockByHash {
                     hash: "0xc24215226336d22238a20a72f8e489c005b44c4a".to_string(),
@@ -1039,6 +1100,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::Ethereum(EthereumCommands {
                 command: EthereumSubCommands::QueryBlockByHash {
                     hash: "0xc24215226336d22238a20a72f8e489c005b44c4a".to_string(),
@@ -1080,6 +1143,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::Ethereum(EthereumCommands {
                 command: EthereumSubCommands::QueryBalance {
                     // Testing wrong address input
@@ -1121,6 +1186,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::Ethereum(EthereumCommands {
                 command: EthereumSubCommands::QueryPriorityFee {},
             }),
@@ -1155,6 +1222,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::Ethereum(EthereumCommands {
                 command: EthereumSubCommands::QueryPriorityFee {},
             }),
@@ -1219,6 +1288,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::Ethereum(EthereumCommands {
                 command: EthereumSubCommands::QueryBlockByNumber {
                     block: "1".to_string(),
@@ -1256,6 +1327,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::Ethereum(EthereumCommands {
                 command: EthereumSubCommands::QueryBlockByNumber {
                     block: "1".to_string(),
@@ -1446,6 +1519,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::Ethereum(EthereumCommands { command: params }),
         };
 
@@ -1496,6 +1571,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::Ethereum(EthereumCommands { command: params }),
         };
 
@@ -1715,6 +1792,8 @@ mod test {
         );
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::StarkNet(StarkNetCommands {
                 command: StarkNetSubCommands::QueryChainId {},
             }),
@@ -1732,14 +1811,17 @@ mod test {
     #[tokio::test]
     async fn given_normal_conditions_when_starknet_query_block_number_then_ok() {
         // Build mocks.
-        let (config, ethereum_lightclient, mut starknet_lightclient) = config_and_mocks();
+        let (config, mut ethereum_lightclient, mut starknet_lightclient) = config_and_mocks();
 
         // Given
-        let expected_result: u64 = 123456;
-        // Set the expected return value for the StarkNet light client mock.
-        starknet_lightclient
-            .expect_block_number()
-            .return_once(move || Ok(expected_result));
+        // The head is the last block proven on L1, not the one of the provider.
+        ethereum_lightclient
+            .expect_starknet_last_proven_block()
+            .return_once(move || Ok(U256::from(123456)));
+        ethereum_lightclient
+            .expect_starknet_last_proven_block_hash()
+            .return_once(move || Ok(U256::from(123456)));
+        starknet_lightclient.expect_block_number().never();
 
         let beerus = BeerusLightClient::new(
             config,
@@ -1750,6 +1832,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::StarkNet(StarkNetCommands {
                 command: StarkNetSubCommands::QueryBlockNumber {},
             }),
@@ -1762,19 +1846,22 @@ mod test {
     }
 
     /// Test the `query_block_number` CLI command.
-    /// Given starknet lightclient returns an error, when query block_number, then the error is propagated.
+    /// Given ethereum lightclient returns an error, when query block_number, then the error is propagated.
     /// Error case.
     #[tokio::test]
-    async fn given_starknet_lightclient_returns_error_when_starknet_query_block_number_then_error_is_propagated(
+    async fn given_ethereum_lightclient_returns_error_when_starknet_query_block_number_then_error_is_propagated(
     ) {
         // Build mocks.
-        let (config, ethereum_lightclient, mut starknet_lightclient) = config_and_mocks();
+        let (config, mut ethereum_lightclient, starknet_lightclient) = config_and_mocks();
 
         // Given
-        // Set the expected return value for the StarkNet light client mock.
-        starknet_lightclient
-            .expect_block_number()
-            .return_once(move || Err(eyre::eyre!("starknet_lightclient_error")));
+        // Set the expected return value for the Ethereum light client mock.
+        ethereum_lightclient
+            .expect_starknet_last_proven_block()
+            .return_once(move || Err(eyre::eyre!("ethereum_lightclient_error")));
+        ethereum_lightclient
+            .expect_starknet_last_proven_block_hash()
+            .return_once(move || Ok(U256::from(123456)));
 
         let beerus = BeerusLightClient::new(
             config,
@@ -1785,6 +1872,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::StarkNet(StarkNetCommands {
                 command: StarkNetSubCommands::QueryBlockNumber {},
             }),
@@ -1794,7 +1883,7 @@ mod test {
 
         // Then
         match result {
-            Err(e) => assert_eq!("starknet_lightclient_error", e.to_string()),
+            Err(e) => assert_eq!("ethereum_lightclient_error", e.to_string()),
             Ok(_) => panic!("Expected error, got ok"),
         }
     }
@@ -1804,17 +1893,17 @@ mod test {
     #[tokio::test]
     async fn given_normal_conditions_when_starknet_query_block_hash_and_number_then_ok() {
         // Build mocks.
-        let (config, ethereum_lightclient, mut starknet_lightclient) = config_and_mocks();
+        let (config, mut ethereum_lightclient, mut starknet_lightclient) = config_and_mocks();
 
         // Given
-        let expected_result = BlockHashAndNumber {
-            block_hash: FieldElement::from_dec_str("123456").unwrap(),
-            block_number: 123456,
-        };
-        // Set the expected return value for the StarkNet light client mock.
-        starknet_lightclient
-            .expect_block_hash_and_number()
-            .return_once(move || Ok(expected_result));
+        // The head is the last block proven on L1, not the one of the provider.
+        ethereum_lightclient
+            .expect_starknet_last_proven_block()
+            .return_once(move || Ok(U256::from(123456)));
+        ethereum_lightclient
+            .expect_starknet_last_proven_block_hash()
+            .return_once(move || Ok(U256::from(123456)));
+        starknet_lightclient.expect_block_hash_and_number().never();
 
         let beerus = BeerusLightClient::new(
             config,
@@ -1825,6 +1914,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::StarkNet(StarkNetCommands {
                 command: StarkNetSubCommands::QueryBlockHashAndNumber {},
             }),
@@ -1840,19 +1931,22 @@ mod test {
     }
 
     /// Test the `query_block_hash_and_number` CLI command.
-    /// Given starknet lightclient returns an error, when query block_hash_and_number, then the error is propagated.
+    /// Given ethereum lightclient returns an error, when query block_hash_and_number, then the error is propagated.
     /// Error case.
     #[tokio::test]
-    async fn given_starknet_lightclient_returns_error_when_starknet_query_block_hash_and_number_then_error_is_propagated(
+    async fn given_ethereum_lightclient_returns_error_when_starknet_query_block_hash_and_number_then_error_is_propagated(
     ) {
         // Build mocks.
-        let (config, ethereum_lightclient, mut starknet_lightclient) = config_and_mocks();
+        let (config, mut ethereum_lightclient, starknet_lightclient) = config_and_mocks();
 
         // Given
-        // Set the expected return value for the StarkNet light client mock.
-        starknet_lightclient
-            .expect_block_hash_and_number()
-            .return_once(move || Err(eyre::eyre!("starknet_lightclient_error")));
+        // Set the expected return value for the Ethereum light client mock.
+        ethereum_lightclient
+            .expect_starknet_last_proven_block()
+            .return_once(move || Ok(U256::from(123456)));
+        ethereum_lightclient
+            .expect_starknet_last_proven_block_hash()
+            .return_once(move || Err(eyre::eyre!("ethereum_lightclient_error")));
 
         let beerus = BeerusLightClient::new(
             config,
@@ -1863,6 +1957,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::StarkNet(StarkNetCommands {
                 command: StarkNetSubCommands::QueryBlockHashAndNumber {},
             }),
@@ -1872,7 +1968,7 @@ mod test {
 
         // Then
         match result {
-            Err(e) => assert_eq!("starknet_lightclient_error", e.to_string()),
+            Err(e) => assert_eq!("ethereum_lightclient_error", e.to_string()),
             Ok(_) => panic!("Expected error, got ok"),
         }
     }
@@ -1903,6 +1999,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::StarkNet(StarkNetCommands {
                 command: StarkNetSubCommands::QueryGetClass {
                     block_id_type: "number".to_string(),
@@ -1947,6 +2045,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::StarkNet(StarkNetCommands {
                 command: StarkNetSubCommands::QueryGetClass {
                     block_id_type: "number".to_string(),
@@ -1990,6 +2090,8 @@ mod test {
         );
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::StarkNet(StarkNetCommands {
                 command: StarkNetSubCommands::L2ToL1Messages {
                     msg_hash: "0".to_string(),
@@ -2025,6 +2127,8 @@ mod test {
         );
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::StarkNet(StarkNetCommands {
                 command: StarkNetSubCommands::L2ToL1Messages {
                     msg_hash: "0".to_string(),
@@ -2069,6 +2173,8 @@ mod test {
         );
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::StarkNet(StarkNetCommands {
                 command: StarkNetSubCommands::L1ToL2MessageNonce {},
             }),
@@ -2104,6 +2210,8 @@ mod test {
         );
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::StarkNet(StarkNetCommands {
                 command: StarkNetSubCommands::L1ToL2MessageNonce {},
             }),
@@ -2144,6 +2252,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::StarkNet(StarkNetCommands {
                 command: StarkNetSubCommands::QueryGetClassHash {
                     block_id_type: "number".to_string(),
@@ -2183,6 +2293,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::StarkNet(StarkNetCommands {
                 command: StarkNetSubCommands::QueryGetClassHash {
                     block_id_type: "number".to_string(),
@@ -2228,6 +2340,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::StarkNet(StarkNetCommands {
                 command: StarkNetSubCommands::QueryGetClassAt {
                     block_id_type: "number".to_string(),
@@ -2272,6 +2386,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::StarkNet(StarkNetCommands {
                 command: StarkNetSubCommands::QueryGetClassAt {
                     block_id_type: "number".to_string(),
@@ -2313,6 +2429,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::StarkNet(StarkNetCommands {
                 command: StarkNetSubCommands::QueryGetBlockTransactionCount {
                     block_id_type: "number".to_string(),
@@ -2351,6 +2469,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::StarkNet(StarkNetCommands {
                 command: StarkNetSubCommands::QueryGetBlockTransactionCount {
                     block_id_type: "number".to_string(),
@@ -2393,6 +2513,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::StarkNet(StarkNetCommands {
                 command: StarkNetSubCommands::QueryGetEvents {
                     params: r#"{
@@ -2437,6 +2559,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::StarkNet(StarkNetCommands {
                 command: StarkNetSubCommands::QueryGetEvents {
                     params: r#"{
@@ -2483,6 +2607,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::StarkNet(StarkNetCommands {
                 command: StarkNetSubCommands::QuerySyncing {},
             }),
@@ -2523,6 +2649,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::StarkNet(StarkNetCommands {
                 command: StarkNetSubCommands::QuerySyncing {},
             }),
@@ -2561,6 +2689,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::StarkNet(StarkNetCommands {
                 command: StarkNetSubCommands::QuerySyncing {},
             }),
@@ -2604,6 +2734,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::StarkNet(StarkNetCommands {
                 command: StarkNetSubCommands::QueryEstimateFee {
                     block_id_type: "tag".to_string(),
@@ -2645,6 +2777,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::StarkNet(StarkNetCommands {
                 command: StarkNetSubCommands::QueryEstimateFee {
                     block_id_type: "tag".to_string(),
@@ -2704,6 +2838,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
 
             command: Commands::StarkNet(StarkNetCommands { command: params }),
         };
@@ -2743,6 +2879,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::StarkNet(StarkNetCommands { command: params }),
         };
         // When
@@ -2789,6 +2927,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::StarkNet(StarkNetCommands { command: params }),
         };
         // When
@@ -2822,6 +2962,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::StarkNet(StarkNetCommands {
                 command: StarkNetSubCommands::AddInvokeTransaction {
                     max_fee: "0".to_string(),
@@ -2906,6 +3048,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::StarkNet(StarkNetCommands { command: params }),
         };
         // When
@@ -2976,6 +3120,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::StarkNet(StarkNetCommands { command: params }),
         };
         // When
@@ -3033,6 +3179,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::StarkNet(StarkNetCommands {
                 command: StarkNetSubCommands::QueryBlockWithTxs {
                     block_id_type: "number".to_string(),
@@ -3074,6 +3222,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::StarkNet(StarkNetCommands {
                 command: StarkNetSubCommands::QueryBlockWithTxs {
                     block_id_type: "number".to_string(),
@@ -3134,6 +3284,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::StarkNet(StarkNetCommands {
                 command: StarkNetSubCommands::QueryTransactionByBlockIdAndIndex {
                     block_id_type: "number".to_string(),
@@ -3176,6 +3328,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::StarkNet(StarkNetCommands {
                 command: StarkNetSubCommands::QueryTransactionByBlockIdAndIndex {
                     block_id_type: "number".to_string(),
@@ -3217,6 +3371,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::StarkNet(StarkNetCommands {
                 command: StarkNetSubCommands::QueryPendingTransactions {},
             }),
@@ -3252,6 +3408,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::StarkNet(StarkNetCommands {
                 command: StarkNetSubCommands::QueryPendingTransactions {},
             }),
@@ -3310,6 +3468,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::StarkNet(StarkNetCommands {
                 command: StarkNetSubCommands::QueryBlockWithTxHashes {
                     block_id_type: "number".to_string(),
@@ -3351,6 +3511,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::StarkNet(StarkNetCommands {
                 command: StarkNetSubCommands::QueryBlockWithTxHashes {
                     block_id_type: "number".to_string(),
@@ -3402,6 +3564,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::StarkNet(StarkNetCommands {
                 command: StarkNetSubCommands::QueryTransactionByHash { hash },
             }),
@@ -3437,6 +3601,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::StarkNet(StarkNetCommands {
                 command: StarkNetSubCommands::QueryTransactionByHash { hash },
             }),
@@ -3496,6 +3662,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::StarkNet(StarkNetCommands {
                 command: StarkNetSubCommands::QueryContractStorageProof {
                     block_id_type: "number".to_string(),
@@ -3531,6 +3699,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::StarkNet(StarkNetCommands {
                 command: StarkNetSubCommands::QueryContractStorageProof {
                     block_id_type: "number".to_string(),
@@ -3568,6 +3738,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::StarkNet(StarkNetCommands {
                 command: StarkNetSubCommands::QueryContractStorageProof {
                     block_id_type: "number".to_string(),
@@ -3653,6 +3825,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::StarkNet(StarkNetCommands { command: params }),
         };
         // When
@@ -3725,6 +3899,8 @@ mod test {
         // Mock the command line arguments.
         let cli = Cli {
             config: None,
+            profile: None,
+            inject_faults: None,
             command: Commands::StarkNet(StarkNetCommands { command: params }),
         };
         // When
@@ -3737,13 +3913,422 @@ mod test {
         }
     }
 
+    /// Test that the `verify proof` command accepts a transaction proof saved as a JSON-RPC
+    /// response, and rejects it against another state root.
+    #[test]
+    fn given_transaction_proof_file_when_verify_proof_then_checked_against_root() {
+        // Given
+        let mut block = block_with_single_invoke();
+        block.block_hash = compute_block_hash(&block, &[]);
+        let proof = TransactionProof::new(&block, &[], 0, None).unwrap();
+        let file = std::env::temp_dir().join("beerus_verify_transaction_proof.json");
+        std::fs::write(
+            &file,
+            serde_json::json!({"jsonrpc": "2.0", "id": 1, "result": proof}).to_string(),
+        )
+        .unwrap();
+
+        // When
+        let verified = runner::run_verify(&VerifySubCommands::Proof {
+            file: file.clone(),
+            root: "0x123".to_string(),
+        });
+        let rejected = runner::run_verify(&VerifySubCommands::Proof {
+            file,
+            root: "0x124".to_string(),
+        });
+
+        // Then
+        assert_eq!(
+            verified.unwrap().to_string(),
+            "Proof verified against state root 0x123"
+        );
+        assert!(rejected.is_err());
+    }
+
+    /// Test that the `verify block` command recomputes the hash of a block with receipts.
+    #[test]
+    fn given_block_file_when_verify_block_then_hash_is_checked() {
+        // Given
+        let mut block = block_with_single_invoke();
+        block.transactions.clear();
+        block.block_hash = compute_block_hash(&block, &[]);
+        let valid = std::env::temp_dir().join("beerus_verify_valid_block.json");
+        std::fs::write(
+            &valid,
+            serde_json::to_string(&BlockWithReceipts::new(&block, &[])).unwrap(),
+        )
+        .unwrap();
+        block.block_hash = FieldElement::ONE;
+        let invalid = std::env::temp_dir().join("beerus_verify_invalid_block.json");
+        std::fs::write(
+            &invalid,
+            serde_json::to_string(&BlockWithReceipts::new(&block, &[])).unwrap(),
+        )
+        .unwrap();
+
+        // When
+        let verified = runner::run_verify(&VerifySubCommands::Block { file: valid });
+        let rejected = runner::run_verify(&VerifySubCommands::Block { file: invalid });
+
+        // Then
+        assert!(verified
+            .unwrap()
+            .to_string()
+            .starts_with("Block 10 verified, hash 0x"));
+        assert!(rejected.is_err());
+    }
+
+    /// Test that the `bench` method mix expands weights and rejects invalid entries.
+    #[test]
+    fn given_method_mix_when_parse_method_mix_then_weights_are_expanded() {
+        // Given
+        let mix = vec![
+            "starknet_blockNumber=2".to_string(),
+            "beerus_status".to_string(),
+        ];
+
+        // When
+        let schedule = parse_method_mix(&mix);
+
+        // Then
+        assert_eq!(
+            schedule.unwrap(),
+            vec![
+                "starknet_blockNumber".to_string(),
+                "starknet_blockNumber".to_string(),
+                "beerus_status".to_string(),
+            ]
+        );
+        assert!(parse_method_mix(&["starknet_blockNumber=x".to_string()]).is_err());
+        assert!(parse_method_mix(&["=1".to_string()]).is_err());
+        assert!(parse_method_mix(&["starknet_blockNumber=0".to_string()]).is_err());
+        assert!(parse_method_mix(&[]).is_err());
+    }
+
+    /// Test that the `bench` latency percentiles use the nearest rank.
+    #[test]
+    fn given_latencies_when_compute_latency_stats_then_percentiles_are_nearest_rank() {
+        // Given
+        let latencies: Vec<f64> = (1..=100).rev().map(f64::from).collect();
+
+        // When
+        let stats = LatencyStats::new(latencies, 3);
+
+        // Then
+        assert_eq!(stats.requests, 100);
+        assert_eq!(stats.errors, 3);
+        assert_eq!(stats.p50_ms, 50.0);
+        assert_eq!(stats.p90_ms, 90.0);
+        assert_eq!(stats.p99_ms, 99.0);
+        assert_eq!(stats.max_ms, 100.0);
+        assert_eq!(percentile(&[], 50.0), 0.0);
+        assert_eq!(percentile(&[7.0], 1.0), 7.0);
+    }
+
+    fn block_with_single_invoke() -> BlockWithTxs {
+        let transaction = InvokeTransactionV0 {
+            transaction_hash: FieldElement::from_hex_be("0x1234").unwrap(),
+            max_fee: FieldElement::ONE,
+            signature: vec![FieldElement::TWO],
+            nonce: FieldElement::ZERO,
+            contract_address: FieldElement::from_hex_be("0x42").unwrap(),
+            entry_point_selector: FieldElement::THREE,
+            calldata: vec![],
+        };
+        BlockWithTxs {
+            status: BlockStatus::AcceptedOnL2,
+            block_hash: FieldElement::ZERO,
+            parent_hash: FieldElement::from_hex_be("0x99").unwrap(),
+            block_number: 10,
+            new_root: FieldElement::from_hex_be("0x123").unwrap(),
+            timestamp: 1_000,
+            sequencer_address: FieldElement::from_hex_be("0x5").unwrap(),
+            transactions: vec![StarknetTransaction::Invoke(InvokeTransaction::V0(
+                transaction,
+            ))],
+        }
+    }
+
+    /// Test the encoding of the arguments of a function following its ABI.
+    #[test]
+    fn given_abi_when_encoding_arguments_then_lengths_and_struct_members_are_added() {
+        // Given
+        let abi = serde_json::json!([
+            {
+                "type": "struct",
+                "name": "Uint256",
+                "size": 2,
+                "members": [
+                    {"name": "low", "type": "felt", "offset": 0},
+                    {"name": "high", "type": "felt", "offset": 1}
+                ]
+            },
+            {
+                "type": "function",
+                "name": "transfer",
+                "inputs": [
+                    {"name": "recipient", "type": "felt"},
+                    {"name": "amount", "type": "Uint256"},
+                    {"name": "data_len", "type": "felt"},
+                    {"name": "data", "type": "felt*"}
+                ],
+                "outputs": []
+            }
+        ]);
+        let args = serde_json::json!(["0x1", {"low": 5, "high": "0x0"}, ["0x7", "0x8"]]);
+
+        // When
+        let calldata = tx::abi_calldata(&abi, "transfer", &args).unwrap();
+        let missing = tx::abi_calldata(&abi, "transfer", &serde_json::json!(["0x1"]));
+        let unknown = tx::abi_calldata(&abi, "approve", &args);
+
+        // Then
+        let expected: Vec<FieldElement> = [1_u64, 5, 0, 2, 7, 8]
+            .into_iter()
+            .map(FieldElement::from)
+            .collect();
+        assert_eq!(calldata, expected);
+        assert!(missing.is_err());
+        assert!(unknown.is_err());
+    }
+
+    /// Test the `tx invoke` CLI command.
+    /// Given normal conditions, when sending an invoke, then it is signed with the fee margin.
+    #[tokio::test]
+    async fn given_normal_conditions_when_tx_invoke_then_signed_transaction_is_broadcast() {
+        // Build mocks.
+        let (config, mut ethereum_lightclient, mut starknet_lightclient) = config_and_mocks();
+
+        // Given
+        let signing_key = SigningKey::from_secret_scalar(FieldElement::from(42_u64));
+        let account = FieldElement::from(0x100_u64);
+        let chain_id = FieldElement::from_hex_be("0x534e5f474f45524c49").unwrap();
+        ethereum_lightclient
+            .expect_starknet_last_proven_block()
+            .return_once(move || Ok(U256::from(10)));
+        starknet_lightclient
+            .expect_get_nonce()
+            .return_once(move |_, _| Ok(FieldElement::from(2_u64)));
+        starknet_lightclient
+            .expect_get_nonce_at()
+            .return_once(move |_, _| Ok(FieldElement::from(3_u64)));
+        starknet_lightclient
+            .expect_chain_id()
+            .return_once(move || Ok(chain_id));
+        starknet_lightclient
+            .expect_estimate_fee()
+            .return_once(move |_, _| {
+                Ok(FeeEstimate {
+                    gas_consumed: 10,
+                    gas_price: 100,
+                    overall_fee: 1000,
+                })
+            });
+        let verifying_key = signing_key.verifying_key();
+        starknet_lightclient
+            .expect_add_invoke_transaction()
+            .return_once(move |transaction| {
+                let transaction = match transaction {
+                    BroadcastedInvokeTransaction::V1(transaction) => transaction,
+                    _ => panic!("Expected an invoke transaction of version 1"),
+                };
+                assert_eq!(transaction.sender_address, account);
+                assert_eq!(transaction.nonce, FieldElement::from(3_u64));
+                assert_eq!(transaction.max_fee, FieldElement::from(1500_u64));
+                assert_eq!(
+                    transaction.calldata,
+                    tx::execute_calldata(
+                        FieldElement::from(0x200_u64),
+                        get_selector_from_name("increase_balance").unwrap(),
+                        &[FieldElement::from(7_u64)],
+                    )
+                );
+                let signature = Signature {
+                    r: transaction.signature[0],
+                    s: transaction.signature[1],
+                };
+                assert!(verifying_key
+                    .verify(&tx::invoke_v1_hash(transaction, chain_id), &signature)
+                    .unwrap());
+                Ok(InvokeTransactionResult {
+                    transaction_hash: FieldElement::from(0x33_u64),
+                })
+            });
+
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient),
+            Box::new(starknet_lightclient),
+        );
+
+        // When
+        let result = tx::invoke(
+            beerus,
+            account,
+            &signing_key,
+            FieldElement::from(0x200_u64),
+            "increase_balance",
+            vec![FieldElement::from(7_u64)],
+            None,
+        )
+        .await
+        .unwrap();
+
+        // Then
+        assert_eq!(
+            result.to_string(),
+            format!(
+                "{:?}",
+                InvokeTransactionResult {
+                    transaction_hash: FieldElement::from(0x33_u64),
+                }
+            )
+        );
+    }
+
+    /// Test the keystores of the `account` CLI commands.
+    /// Given a stored account, when listing and loading it, then its key is decrypted.
+    #[test]
+    fn given_stored_account_when_list_and_load_then_key_is_decrypted() {
+        // Given
+        let dir = std::env::temp_dir().join(format!("beerus-accounts-{}", std::process::id()));
+        let secret = FieldElement::from(42_u64);
+        let address = FieldElement::from(0x100_u64);
+        let stored =
+            account::store_account(&dir, "alice", Some(address), secret, "password").unwrap();
+
+        // When
+        let accounts = account::list_accounts(&dir).unwrap();
+        let (info, signing_key) = account::load_account(&dir, "alice", "password").unwrap();
+        let wrong_password = account::load_account(&dir, "alice", "wrong");
+        let duplicate = account::store_account(&dir, "alice", None, secret, "password");
+        let invalid_name = account::store_account(&dir, "../alice", None, secret, "password");
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // Then
+        assert_eq!(accounts, vec![stored.clone()]);
+        assert_eq!(info, stored);
+        assert_eq!(info.address, Some(address));
+        assert_eq!(
+            signing_key.verifying_key().scalar(),
+            SigningKey::from_secret_scalar(secret)
+                .verifying_key()
+                .scalar()
+        );
+        assert!(wrong_password.is_err());
+        assert!(duplicate.is_err());
+        assert!(invalid_name.is_err());
+    }
+
+    /// Test the `purge` CLI command.
+    /// Given a data directory, when purging it, then only the layout of Beerus is removed.
+    #[test]
+    fn given_data_dir_when_purge_then_only_beerus_data_is_removed() {
+        // Given
+        let root = std::env::temp_dir().join(format!("beerus-purge-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        let data_dir = DataDir::new(&root);
+        let unmarked = runner::run_purge(&data_dir, &PurgeArgs { keystores: true });
+        data_dir.init().unwrap();
+        std::fs::write(root.join("other"), "kept").unwrap();
+
+        // When
+        let result = runner::run_purge(&data_dir, &PurgeArgs { keystores: false }).unwrap();
+
+        // Then
+        assert!(unmarked.is_err());
+        assert_eq!(
+            format!("{result}"),
+            format!(
+                "Removed: {}, {}, {}, {}",
+                data_dir.helios().display(),
+                data_dir.payload().display(),
+                data_dir.archive().display(),
+                data_dir.snapshots().display()
+            )
+        );
+        assert!(data_dir.keystores().is_dir());
+        assert!(root.join("other").is_file());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    /// Test the `archive export` CLI command.
+    /// Given an inverted range, when exporting it, then the export is refused before reading
+    /// the archive.
+    #[test]
+    fn given_inverted_range_when_archive_export_then_error() {
+        // Given
+        let command = ArchiveSubCommands::Export {
+            table: ExportTable::Receipts,
+            from: 20,
+            to: 10,
+            format: ExportFormat::Csv,
+            output: None,
+        };
+
+        // When
+        let result = runner::run_archive(&DataDir::new("/tmp"), &command);
+
+        // Then
+        match result {
+            Err(e) => assert!(e.to_string().contains("Invalid range 20 to 10")),
+            Ok(_) => panic!("Expected error, got ok"),
+        }
+    }
+
+    /// Test the `export` CLI command.
+    /// Given a Parquet export without output file, when exporting, then the export is refused
+    /// before fetching any block.
+    #[tokio::test]
+    async fn given_parquet_without_output_when_export_then_error() {
+        // Given
+        let (config, ethereum_lightclient, starknet_lightclient) = config_and_mocks();
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient),
+            Box::new(starknet_lightclient),
+        );
+        let args = ExportArgs {
+            table: ExportTable::Events,
+            from: 10,
+            to: 20,
+            format: ExportFormat::Parquet,
+            output: None,
+        };
+
+        // When
+        let result = runner::run_export(&beerus, &args).await;
+
+        // Then
+        match result {
+            Err(e) => assert_eq!(e.to_string(), "Parquet exports need a file, set --output"),
+            Ok(_) => panic!("Expected error, got ok"),
+        }
+    }
+
     fn config_and_mocks() -> (Config, MockEthereumLightClient, MockStarkNetLightClient) {
         let config = Config {
             ethereum_network: "mainnet".to_string(),
             ethereum_consensus_rpc: "http://localhost:8545".to_string(),
             ethereum_execution_rpc: "http://localhost:8545".to_string(),
             starknet_rpc: "http://localhost:8545".to_string(),
+            starknet_rpc_routes: Default::default(),
             data_dir: Some(PathBuf::from("/tmp")),
+            starknet_batch_max_size: DEFAULT_STARKNET_BATCH_MAX_SIZE,
+            starknet_batch_flush_interval_ms: DEFAULT_STARKNET_BATCH_FLUSH_INTERVAL_MS,
+            payload_compression_level: None,
+            payload_headers_only: false,
+            archive_mode: false,
+            prefetch_receipts: false,
+            starknet_rpc_passthrough: false,
+            memory_budget_mb: None,
+            block_number_source: Default::default(),
+            finality: Default::default(),
+            starknet_feeder_gateway: None,
+            block_sources: Default::default(),
+            http: HttpConfig::default(),
+            alerts: Default::default(),
             starknet_core_contract_address: Address::from_str(
                 "0x0000000000000000000000000000000000000000",
             )
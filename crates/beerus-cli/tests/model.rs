@@ -112,4 +112,10 @@ mod tests {
             "GetProofOutput { contract_proof: [Binary(Binary { left: FieldElement { inner: 0x015e7882b80e22844ca62d3e3260a21d0d45c2b0c1744328e2763b4b486de738 }, right: FieldElement { inner: 0x07779bcf84c8a6a4cca695c2d44d1455db0cb13d457ea7a01887676b9f779455 } }), Edge(Edge { path: Path { value: FieldElement { inner: 0x0000000000000000000000000000000000000000000000000000000000000001 }, len: 1 }, child: FieldElement { inner: 0x0173d276dbe8497dd2d59b88aa7eaebeb760e450e7a34a1ae5d513a930a3bf9d } })], contract_data: None }"
         );
     }
+
+    #[test]
+    fn test_display_admin_prune_to() {
+        let response = CommandResponse::AdminPruneTo(10);
+        assert_eq!(response.to_string(), "Pruned blocks: 10");
+    }
 }
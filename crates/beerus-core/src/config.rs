@@ -1,13 +1,32 @@
+use crate::data_dir::DataDir;
 use ethers::types::Address;
 use eyre::{eyre, Result};
 use helios::config::{checkpoints, networks::Network};
-use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 pub const STARKNET_MAINNET_CC_ADDRESS: &str = "0xc662c410C0ECf747543f5bA90660f6ABeBD9C8c4";
 pub const STARKNET_GOERLI_CC_ADDRESS: &str = "0xde29d060D45901Fb19ED6C6e959EB22d8626708e";
+/// Fact registry the memory pages holding the StarkNet state diffs are registered with.
+pub const STARKNET_MAINNET_MEMORY_PAGE_FACT_REGISTRY_ADDRESS: &str =
+    "0x96375087b2F6eFc59e5e0dd5111B4d090EBFDD8B";
+pub const STARKNET_GOERLI_MEMORY_PAGE_FACT_REGISTRY_ADDRESS: &str =
+    "0x743789ff2fF82Bfb907009C9911a7dA636D34FA7";
 pub const DEFAULT_ETHEREUM_NETWORK: &str = "goerli";
 pub const DEFAULT_DATA_DIR: &str = "/tmp";
+pub const DEFAULT_STARKNET_BATCH_MAX_SIZE: usize = 1;
+pub const DEFAULT_STARKNET_BATCH_FLUSH_INTERVAL_MS: u64 = 10;
+pub const DEFAULT_HTTP_TIMEOUT_MS: u64 = 30_000;
+pub const DEFAULT_HTTP_CONNECT_TIMEOUT_MS: u64 = 10_000;
+pub const DEFAULT_HTTP_POOL_MAX_IDLE_PER_HOST: usize = 32;
+pub const DEFAULT_HTTP_POOL_IDLE_TIMEOUT_MS: u64 = 90_000;
+pub const DEFAULT_ALERT_SYNC_STALL_SECS: u64 = 600;
+/// Config file read when a profile is selected without one.
+pub const DEFAULT_CONFIG_FILE: &str = "beerus.toml";
+/// Profile read when a config file is given without one.
+pub const DEFAULT_PROFILE: &str = "default";
 
 /// Global configuration.
 #[derive(Clone, PartialEq)]
@@ -20,45 +39,581 @@ pub struct Config {
     pub ethereum_execution_rpc: String,
     /// StarkNet RPC endpoint.
     pub starknet_rpc: String,
+    /// StarkNet RPC endpoints serving specific methods instead of `starknet_rpc`, by method name.
+    pub starknet_rpc_routes: BTreeMap<String, String>,
     // StarkNet core contract address.
     pub starknet_core_contract_address: Address,
     // Path to storage directory
     pub data_dir: Option<PathBuf>,
+    /// Maximum number of requests sent to the StarkNet RPC in a single batch.
+    /// 1 disables batching.
+    pub starknet_batch_max_size: usize,
+    /// Time to wait for more requests before sending a batch, in milliseconds.
+    pub starknet_batch_flush_interval_ms: u64,
+    /// zstd level used to compress the payload blocks. Blocks are stored uncompressed if unset.
+    pub payload_compression_level: Option<i32>,
+    /// Retain only the headers and the commitments of the blocks, which is enough to check the
+    /// state roots and the block hashes, and fetch the rest from the provider when queried.
+    pub payload_headers_only: bool,
+    /// Persist every proven block, with its transactions and receipts, to the archive in the
+    /// `archive` subdirectory of the data directory.
+    pub archive_mode: bool,
+    /// Fetch the receipts of the transactions of every new block along with it, so that they
+    /// are answered locally.
+    pub prefetch_receipts: bool,
+    /// Relay the JSON of the provider as is for the methods Beerus does not verify, and for the
+    /// blocks it does not retain, instead of parsing it into models and serializing it again.
+    pub starknet_rpc_passthrough: bool,
+    /// Approximate memory the retained blocks, their events, receipts and indexes may use, in
+    /// megabytes. The cached receipts, then the oldest blocks, are evicted beyond it. Unbounded
+    /// if unset.
+    pub memory_budget_mb: Option<usize>,
+    /// Source of the latest block reported by `starknet_blockNumber` and
+    /// `starknet_blockHashAndNumber`.
+    pub block_number_source: BlockNumberSource,
+    /// Finality the queries of the `beerus_` namespace are answered at when they don't set a
+    /// block tag.
+    pub finality: Finality,
+    /// StarkNet feeder gateway, e.g. `https://alpha-mainnet.starknet.io`, used as a secondary
+    /// source of blocks.
+    pub starknet_feeder_gateway: Option<String>,
+    /// Order in which the sources of blocks are tried.
+    pub block_sources: BlockSources,
+    /// Settings of the HTTP clients used to reach the StarkNet RPC.
+    pub http: HttpConfig,
+    /// Hooks fired on the events operators must act upon.
+    pub alerts: AlertConfig,
+}
+
+/// Source of the latest block reported by `starknet_blockNumber` and
+/// `starknet_blockHashAndNumber`, both follow the same one.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BlockNumberSource {
+    /// The last block proven on L1, which the light client can verify.
+    #[default]
+    Proven,
+    /// The head of the StarkNet provider, the last proven block is reported while it is
+    /// unavailable.
+    Upstream,
+}
+
+impl FromStr for BlockNumberSource {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "proven" => Ok(Self::Proven),
+            "upstream" => Ok(Self::Upstream),
+            _ => Err(eyre!("Invalid block number source: {s}")),
+        }
+    }
+}
+
+/// Finality required from the blocks the `beerus_` queries are answered at by default.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Finality {
+    /// Blocks accepted on L2: the latest block fetched from StarkNet, fresher but unverified.
+    L2Accepted,
+    /// Blocks proven on L1, the results are checked against the state root proven there.
+    #[default]
+    L1Accepted,
+}
+
+impl FromStr for Finality {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "l2_accepted" => Ok(Self::L2Accepted),
+            "l1_accepted" => Ok(Self::L1Accepted),
+            _ => Err(eyre!("Invalid finality: {s}")),
+        }
+    }
+}
+
+/// Source of the blocks fetched by the light client.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlockSource {
+    /// The StarkNet JSON-RPC provider.
+    Rpc,
+    /// The StarkNet feeder gateway, if one is configured.
+    FeederGateway,
+}
+
+impl FromStr for BlockSource {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "rpc" => Ok(Self::Rpc),
+            "feeder_gateway" => Ok(Self::FeederGateway),
+            _ => Err(eyre!("Invalid block source: {s}")),
+        }
+    }
+}
+
+/// Sources of blocks, by priority. The next source is tried when one fails, or when it lags
+/// behind the latest block already seen.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlockSources(pub Vec<BlockSource>);
+
+impl Default for BlockSources {
+    fn default() -> Self {
+        Self(vec![BlockSource::Rpc, BlockSource::FeederGateway])
+    }
+}
+
+impl FromStr for BlockSources {
+    type Err = eyre::Report;
+
+    /// Parse sources separated by commas, e.g. `feeder_gateway,rpc`.
+    fn from_str(s: &str) -> Result<Self> {
+        let mut sources = Vec::new();
+        for source in s.split(',').filter(|source| !source.trim().is_empty()) {
+            let source = source.parse()?;
+            if !sources.contains(&source) {
+                sources.push(source);
+            }
+        }
+        if sources.is_empty() {
+            return Err(eyre!("No block source given"));
+        }
+        Ok(Self(sources))
+    }
+}
+
+/// Settings of the outbound HTTP clients.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HttpConfig {
+    /// Timeout of a whole request, in milliseconds.
+    pub timeout_ms: u64,
+    /// Timeout of the connection phase, in milliseconds.
+    pub connect_timeout_ms: u64,
+    /// Maximum number of idle connections kept per host.
+    pub pool_max_idle_per_host: usize,
+    /// Time after which idle connections are closed, in milliseconds.
+    pub pool_idle_timeout_ms: u64,
+    /// Interval of the TCP keep-alive probes, in milliseconds. Disabled if unset.
+    pub tcp_keepalive_ms: Option<u64>,
+    /// Use HTTP/2 without negotiating it first. The providers must support it.
+    pub http2_prior_knowledge: bool,
+    /// HTTP, HTTPS or SOCKS5 proxy all the requests go through,
+    /// e.g. `socks5://127.0.0.1:1080`.
+    pub proxy: Option<String>,
+}
+
+impl HttpConfig {
+    /// Read the HTTP client settings from environment variables, using the defaults for the
+    /// missing ones.
+    pub fn new_from_env() -> Result<Self> {
+        Self::new_from_profile(&Profile::default())
+    }
+
+    /// Read the HTTP client settings from a profile, see [`Profile::var`].
+    pub fn new_from_profile(profile: &Profile) -> Result<Self> {
+        Ok(Self {
+            timeout_ms: profile.var_or("HTTP_TIMEOUT_MS", DEFAULT_HTTP_TIMEOUT_MS)?,
+            connect_timeout_ms: profile
+                .var_or("HTTP_CONNECT_TIMEOUT_MS", DEFAULT_HTTP_CONNECT_TIMEOUT_MS)?,
+            pool_max_idle_per_host: profile.var_or(
+                "HTTP_POOL_MAX_IDLE_PER_HOST",
+                DEFAULT_HTTP_POOL_MAX_IDLE_PER_HOST,
+            )?,
+            pool_idle_timeout_ms: profile.var_or(
+                "HTTP_POOL_IDLE_TIMEOUT_MS",
+                DEFAULT_HTTP_POOL_IDLE_TIMEOUT_MS,
+            )?,
+            tcp_keepalive_ms: profile.var_opt("HTTP_TCP_KEEPALIVE_MS")?,
+            http2_prior_knowledge: profile.var_or("HTTP2_PRIOR_KNOWLEDGE", false)?,
+            proxy: profile.var("HTTP_PROXY_URL"),
+        })
+    }
+
+    /// Build a HTTP client with these settings.
+    /// # Returns
+    /// `Ok(reqwest::Client)` if the client could be built.
+    /// `Err(eyre::Report)` if the proxy URL is invalid.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn build_client(&self) -> Result<reqwest::Client> {
+        use std::time::Duration;
+
+        let mut builder = reqwest::Client::builder()
+            .timeout(Duration::from_millis(self.timeout_ms))
+            .connect_timeout(Duration::from_millis(self.connect_timeout_ms))
+            .pool_max_idle_per_host(self.pool_max_idle_per_host)
+            .pool_idle_timeout(Duration::from_millis(self.pool_idle_timeout_ms))
+            .tcp_keepalive(self.tcp_keepalive_ms.map(Duration::from_millis));
+        if self.http2_prior_knowledge {
+            builder = builder.http2_prior_knowledge();
+        }
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(
+                reqwest::Proxy::all(proxy).map_err(|_| eyre!("Invalid HTTP_PROXY_URL: {proxy}"))?,
+            );
+        }
+        Ok(builder.build()?)
+    }
+
+    /// The browser manages the connections itself, none of the settings apply.
+    #[cfg(target_arch = "wasm32")]
+    pub fn build_client(&self) -> Result<reqwest::Client> {
+        Ok(reqwest::Client::new())
+    }
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            timeout_ms: DEFAULT_HTTP_TIMEOUT_MS,
+            connect_timeout_ms: DEFAULT_HTTP_CONNECT_TIMEOUT_MS,
+            pool_max_idle_per_host: DEFAULT_HTTP_POOL_MAX_IDLE_PER_HOST,
+            pool_idle_timeout_ms: DEFAULT_HTTP_POOL_IDLE_TIMEOUT_MS,
+            tcp_keepalive_ms: None,
+            http2_prior_knowledge: false,
+            proxy: None,
+        }
+    }
+}
+
+/// Hooks fired on reorgs, state root mismatches, provider divergences and sync stalls.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AlertConfig {
+    /// URLs the alerts are posted to, as JSON.
+    pub webhook_urls: Vec<String>,
+    /// Shell command run on every alert, which finds the alert in its environment.
+    pub command: Option<String>,
+    /// Seconds without a new block after which the sync is reported stalled, 0 disables it.
+    pub sync_stall_secs: u64,
+}
+
+impl AlertConfig {
+    /// Read the alert hooks from environment variables, none are set by default.
+    pub fn new_from_env() -> Result<Self> {
+        Self::new_from_profile(&Profile::default())
+    }
+
+    /// Read the alert hooks from a profile, see [`Profile::var`].
+    pub fn new_from_profile(profile: &Profile) -> Result<Self> {
+        Ok(Self {
+            webhook_urls: profile
+                .var("ALERT_WEBHOOK_URLS")
+                .map(|urls| {
+                    urls.split(',')
+                        .map(str::trim)
+                        .filter(|url| !url.is_empty())
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            command: profile.var("ALERT_COMMAND"),
+            sync_stall_secs: profile
+                .var_or("ALERT_SYNC_STALL_SECS", DEFAULT_ALERT_SYNC_STALL_SECS)?,
+        })
+    }
+}
+
+impl Default for AlertConfig {
+    fn default() -> Self {
+        Self {
+            webhook_urls: Vec::new(),
+            command: None,
+            sync_stall_secs: DEFAULT_ALERT_SYNC_STALL_SECS,
+        }
+    }
+}
+
+/// Parse method routes written as `method=url` pairs separated by commas,
+/// e.g. `starknet_traceTransaction=https://archive.node,starknet_call=https://cheap.node`.
+pub fn parse_routes(routes: &str) -> Result<BTreeMap<String, String>> {
+    routes
+        .split(',')
+        .map(str::trim)
+        .filter(|route| !route.is_empty())
+        .map(|route| match route.split_once('=') {
+            Some((method, url)) if !method.trim().is_empty() && !url.trim().is_empty() => {
+                Ok((method.trim().to_string(), url.trim().to_string()))
+            }
+            _ => Err(eyre!("Invalid STARKNET_RPC_ROUTES entry: {route}")),
+        })
+        .collect()
+}
+
+/// Named set of settings of a config file, e.g. one per network, written as:
+///
+/// ```toml
+/// # Settings shared by every profile.
+/// ethereum_consensus_rpc_url = "https://www.lightclientdata.org"
+///
+/// [profiles.mainnet]
+/// ethereum_network = "mainnet"
+/// starknet_rpc_url = "https://starknet-mainnet.infura.io/v3/<key>"
+/// data_dir = "/var/lib/beerus/mainnet"
+/// finality = "l1_accepted"
+/// block_sources = ["rpc", "feeder_gateway"]
+/// ```
+///
+/// The settings are named after their environment variable, in lowercase. Arrays are joined
+/// with commas and tables written as `key=value` pairs, e.g. for `starknet_rpc_routes`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Profile {
+    /// Name of the profile, empty for the environment alone.
+    pub name: String,
+    /// Settings by environment variable name, the profile's overriding the shared ones.
+    vars: BTreeMap<String, String>,
+}
+
+impl Profile {
+    /// Select the settings: a profile of a config file when either is given, the environment
+    /// alone otherwise.
+    /// # Arguments
+    /// * `config_file` - The config file, `DEFAULT_CONFIG_FILE` if unset.
+    /// * `name` - The profile, `DEFAULT_PROFILE` if unset.
+    /// # Errors
+    /// * If the file can't be read or parsed, or has no such profile.
+    pub fn load(config_file: Option<&Path>, name: Option<&str>) -> Result<Self> {
+        if config_file.is_none() && name.is_none() {
+            return Ok(Self::default());
+        }
+        Self::from_file(
+            config_file.unwrap_or_else(|| Path::new(DEFAULT_CONFIG_FILE)),
+            name.unwrap_or(DEFAULT_PROFILE),
+        )
+    }
+
+    /// Read a profile from a config file.
+    /// # Errors
+    /// * If the file can't be read or parsed, or has no such profile.
+    pub fn from_file(path: &Path, name: &str) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|err| eyre!("Failed to read config file {}: {err}", path.display()))?;
+        Self::parse(&contents, name)
+            .map_err(|err| eyre!("Invalid config file {}: {err}", path.display()))
+    }
+
+    /// Parse a profile from the contents of a config file.
+    /// # Errors
+    /// * If the contents are not valid TOML, or have no such profile.
+    pub fn parse(contents: &str, name: &str) -> Result<Self> {
+        let mut file: toml::Table = contents.parse()?;
+        let mut profiles = match file.remove("profiles") {
+            Some(toml::Value::Table(profiles)) => profiles,
+            Some(_) => return Err(eyre!("`profiles` must be a table")),
+            None => toml::Table::new(),
+        };
+        let profile = match profiles.remove(name) {
+            Some(toml::Value::Table(profile)) => profile,
+            Some(_) => return Err(eyre!("Profile {name} must be a table")),
+            None => {
+                let names: Vec<_> = profiles.keys().map(String::as_str).collect();
+                return Err(eyre!(
+                    "Unknown profile {name}, available profiles: {}",
+                    names.join(", ")
+                ));
+            }
+        };
+        let mut vars = BTreeMap::new();
+        for (key, value) in file.into_iter().chain(profile) {
+            vars.insert(key.to_uppercase(), toml_to_var(&key, value)?);
+        }
+        Ok(Self {
+            name: name.to_string(),
+            vars,
+        })
+    }
+
+    /// Return a setting, the environment variable overriding the profile.
+    pub fn var(&self, name: &str) -> Option<String> {
+        std::env::var(name)
+            .ok()
+            .or_else(|| self.vars.get(name).cloned())
+    }
+
+    /// Parse an optional setting, returning `default` if it is not set.
+    fn var_or<T: FromStr>(&self, name: &str, default: T) -> Result<T> {
+        Ok(self.var_opt(name)?.unwrap_or(default))
+    }
+
+    /// Parse an optional setting.
+    fn var_opt<T: FromStr>(&self, name: &str) -> Result<Option<T>> {
+        match self.var(name) {
+            Some(value) => match value.parse() {
+                Ok(value) => Ok(Some(value)),
+                Err(_) => Err(eyre!("Invalid {name}: {value}")),
+            },
+            None => Ok(None),
+        }
+    }
+}
+
+/// Write a value of a config file as its environment variable would be.
+fn toml_to_var(key: &str, value: toml::Value) -> Result<String> {
+    match value {
+        toml::Value::String(value) => Ok(value),
+        toml::Value::Integer(value) => Ok(value.to_string()),
+        toml::Value::Float(value) => Ok(value.to_string()),
+        toml::Value::Boolean(value) => Ok(value.to_string()),
+        toml::Value::Array(values) => values
+            .into_iter()
+            .map(|value| toml_to_var(key, value))
+            .collect::<Result<Vec<_>>>()
+            .map(|values| values.join(",")),
+        toml::Value::Table(table) => table
+            .into_iter()
+            .map(|(name, value)| Ok(format!("{name}={}", toml_to_var(key, value)?)))
+            .collect::<Result<Vec<_>>>()
+            .map(|pairs| pairs.join(",")),
+        toml::Value::Datetime(_) => Err(eyre!("Invalid {key}: dates are not supported")),
+    }
 }
 
 impl Config {
     /// Create a new global configuration from environment variables.
     pub fn new_from_env() -> Result<Self> {
-        let ethereum_network = std::env::var("ETHEREUM_NETWORK")
-            .unwrap_or_else(|_| DEFAULT_ETHEREUM_NETWORK.to_string());
+        Self::new_from_profile(&Profile::default())
+    }
+
+    /// Create a new global configuration from a profile, see [`Profile::var`].
+    pub fn new_from_profile(profile: &Profile) -> Result<Self> {
+        let ethereum_network = profile
+            .var("ETHEREUM_NETWORK")
+            .unwrap_or_else(|| DEFAULT_ETHEREUM_NETWORK.to_string());
 
         let starknet_core_contract_address = match ethereum_network.as_str() {
             DEFAULT_ETHEREUM_NETWORK => Address::from_str(STARKNET_GOERLI_CC_ADDRESS)?,
             _ => Address::from_str(STARKNET_MAINNET_CC_ADDRESS)?,
         };
 
-        let ethereum_consensus_rpc = std::env::var("ETHEREUM_CONSENSUS_RPC_URL").map_err(|_| {
-            eyre!("Missing mandatory environment variable: ETHEREUM_CONSENSUS_RPC_URL")
-        })?;
-        let ethereum_execution_rpc = std::env::var("ETHEREUM_EXECUTION_RPC_URL").map_err(|_| {
-            eyre!("Missing mandatory environment variable: ETHEREUM_EXECUTION_RPC_URL")
-        })?;
-        let starknet_rpc = std::env::var("STARKNET_RPC_URL")
-            .map_err(|_| eyre!("Missing mandatory environment variable: STARKNET_RPC_URL"))?;
-        let data_dir_str =
-            std::env::var("DATA_DIR").unwrap_or_else(|_| DEFAULT_DATA_DIR.to_string());
+        let ethereum_consensus_rpc =
+            profile.var("ETHEREUM_CONSENSUS_RPC_URL").ok_or_else(|| {
+                eyre!("Missing mandatory environment variable: ETHEREUM_CONSENSUS_RPC_URL")
+            })?;
+        let ethereum_execution_rpc =
+            profile.var("ETHEREUM_EXECUTION_RPC_URL").ok_or_else(|| {
+                eyre!("Missing mandatory environment variable: ETHEREUM_EXECUTION_RPC_URL")
+            })?;
+        let starknet_rpc = profile
+            .var("STARKNET_RPC_URL")
+            .ok_or_else(|| eyre!("Missing mandatory environment variable: STARKNET_RPC_URL"))?;
+        let starknet_rpc_routes = match profile.var("STARKNET_RPC_ROUTES") {
+            Some(routes) => parse_routes(&routes)?,
+            None => BTreeMap::new(),
+        };
+        let data_dir_str = profile
+            .var("DATA_DIR")
+            .unwrap_or_else(|| DEFAULT_DATA_DIR.to_string());
         let data_dir = PathBuf::from(data_dir_str);
+        let starknet_batch_max_size =
+            profile.var_or("STARKNET_BATCH_MAX_SIZE", DEFAULT_STARKNET_BATCH_MAX_SIZE)?;
+        let starknet_batch_flush_interval_ms = profile.var_or(
+            "STARKNET_BATCH_FLUSH_INTERVAL_MS",
+            DEFAULT_STARKNET_BATCH_FLUSH_INTERVAL_MS,
+        )?;
+        let payload_compression_level = profile.var_opt("PAYLOAD_COMPRESSION_LEVEL")?;
+        let payload_headers_only = profile.var_or("PAYLOAD_HEADERS_ONLY", false)?;
+        let archive_mode = profile.var_or("ARCHIVE_MODE", false)?;
+        let prefetch_receipts = profile.var_or("PREFETCH_RECEIPTS", false)?;
+        let starknet_rpc_passthrough = profile.var_or("STARKNET_RPC_PASSTHROUGH", false)?;
+        let memory_budget_mb = profile.var_opt("MEMORY_BUDGET_MB")?;
+        let block_number_source =
+            profile.var_or("BLOCK_NUMBER_SOURCE", BlockNumberSource::default())?;
+        let finality = profile.var_or("FINALITY", Finality::default())?;
+        let starknet_feeder_gateway = profile.var("STARKNET_FEEDER_GATEWAY_URL");
+        let block_sources = profile.var_or("BLOCK_SOURCES", BlockSources::default())?;
 
         Ok(Self {
             ethereum_network,
             ethereum_consensus_rpc,
             ethereum_execution_rpc,
             starknet_rpc,
+            starknet_rpc_routes,
             starknet_core_contract_address,
             data_dir: Some(data_dir),
+            starknet_batch_max_size,
+            starknet_batch_flush_interval_ms,
+            payload_compression_level,
+            payload_headers_only,
+            archive_mode,
+            prefetch_receipts,
+            starknet_rpc_passthrough,
+            memory_budget_mb,
+            block_number_source,
+            finality,
+            starknet_feeder_gateway,
+            block_sources,
+            http: HttpConfig::new_from_profile(profile)?,
+            alerts: AlertConfig::new_from_profile(profile)?,
         })
     }
 
+    /// Create a configuration from its mandatory parts, every other setting keeps its default.
+    /// Used by the bindings, which don't read the environment.
+    /// # Arguments
+    /// * `ethereum_network` - `mainnet` or `goerli`.
+    /// * `ethereum_consensus_rpc` - Ethereum consensus RPC endpoint.
+    /// * `ethereum_execution_rpc` - Ethereum execution RPC endpoint.
+    /// * `starknet_rpc` - StarkNet RPC endpoint.
+    /// * `starknet_core_contract_address` - Address of the StarkNet core contract on L1.
+    /// * `data_dir` - Directory where the Ethereum checkpoint is stored, if any.
+    pub fn from_parts(
+        ethereum_network: String,
+        ethereum_consensus_rpc: String,
+        ethereum_execution_rpc: String,
+        starknet_rpc: String,
+        starknet_core_contract_address: Address,
+        data_dir: Option<PathBuf>,
+    ) -> Self {
+        Self {
+            ethereum_network,
+            ethereum_consensus_rpc,
+            ethereum_execution_rpc,
+            starknet_rpc,
+            starknet_rpc_routes: BTreeMap::new(),
+            starknet_core_contract_address,
+            data_dir,
+            starknet_batch_max_size: DEFAULT_STARKNET_BATCH_MAX_SIZE,
+            starknet_batch_flush_interval_ms: DEFAULT_STARKNET_BATCH_FLUSH_INTERVAL_MS,
+            payload_compression_level: None,
+            payload_headers_only: false,
+            archive_mode: false,
+            prefetch_receipts: false,
+            starknet_rpc_passthrough: false,
+            memory_budget_mb: None,
+            block_number_source: BlockNumberSource::default(),
+            finality: Finality::default(),
+            starknet_feeder_gateway: None,
+            block_sources: BlockSources::default(),
+            http: HttpConfig::default(),
+            alerts: AlertConfig::default(),
+        }
+    }
+
+    /// Return the layout of the data directory, `DEFAULT_DATA_DIR` if unset.
+    pub fn data_layout(&self) -> DataDir {
+        DataDir::new(
+            self.data_dir
+                .clone()
+                .unwrap_or_else(|| PathBuf::from(DEFAULT_DATA_DIR)),
+        )
+    }
+
+    /// Return the address of the memory page fact registry of the StarkNet network.
+    /// # Errors
+    /// If the registry of the Ethereum network is not known.
+    pub fn memory_page_fact_registry_address(&self) -> Result<Address> {
+        let address = match self.ethereum_network.to_lowercase().as_str() {
+            "goerli" => STARKNET_GOERLI_MEMORY_PAGE_FACT_REGISTRY_ADDRESS,
+            "mainnet" => STARKNET_MAINNET_MEMORY_PAGE_FACT_REGISTRY_ADDRESS,
+            network => {
+                return Err(eyre!(
+                    "No known memory page fact registry on the {network} network"
+                ))
+            }
+        };
+        // The addresses are constants, they are valid.
+        Ok(Address::from_str(address).unwrap())
+    }
+
     /// Return the Ethereum network.
     pub fn ethereum_network(&self) -> Result<Network> {
         match self.ethereum_network.to_lowercase().as_str() {
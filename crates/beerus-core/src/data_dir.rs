@@ -0,0 +1,195 @@
+//! Layout of the data directory. Every kind of data lives in a subdirectory of its own, so that
+//! the directory can be shared, e.g. the default `/tmp`, and purged without touching anything
+//! Beerus didn't write.
+use crate::config::{Profile, DEFAULT_DATA_DIR};
+use eyre::{eyre, Result};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Checkpoints of the Helios light client.
+pub const HELIOS_DIR: &str = "helios";
+/// Database of the payload blocks.
+pub const PAYLOAD_DIR: &str = "payload";
+/// Segments of the archived blocks, in archival mode.
+pub const ARCHIVE_DIR: &str = "archive";
+/// Snapshots of the node data.
+pub const SNAPSHOTS_DIR: &str = "snapshots";
+/// Encrypted keystores of the accounts.
+pub const KEYSTORES_DIR: &str = "accounts";
+/// File marking a directory laid out by Beerus, purges are refused without it.
+pub const MARKER_FILE: &str = ".beerus";
+
+/// Data directory of a Beerus node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataDir {
+    root: PathBuf,
+}
+
+impl DataDir {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Data directory set by `DATA_DIR`, `DEFAULT_DATA_DIR` if unset.
+    pub fn from_env() -> Self {
+        Self::from_profile(&Profile::default())
+    }
+
+    /// Data directory of a profile, `DEFAULT_DATA_DIR` if unset.
+    pub fn from_profile(profile: &Profile) -> Self {
+        Self::new(
+            profile
+                .var("DATA_DIR")
+                .unwrap_or_else(|| DEFAULT_DATA_DIR.to_string()),
+        )
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn helios(&self) -> PathBuf {
+        self.root.join(HELIOS_DIR)
+    }
+
+    pub fn payload(&self) -> PathBuf {
+        self.root.join(PAYLOAD_DIR)
+    }
+
+    pub fn archive(&self) -> PathBuf {
+        self.root.join(ARCHIVE_DIR)
+    }
+
+    pub fn snapshots(&self) -> PathBuf {
+        self.root.join(SNAPSHOTS_DIR)
+    }
+
+    pub fn keystores(&self) -> PathBuf {
+        self.root.join(KEYSTORES_DIR)
+    }
+
+    /// Create the directory and its subdirectories, and check that they are writable.
+    /// # Errors
+    /// * If a path of the layout exists and isn't a directory, or can't be written.
+    pub fn init(&self) -> Result<()> {
+        for dir in [
+            self.root.clone(),
+            self.helios(),
+            self.payload(),
+            self.archive(),
+            self.snapshots(),
+            self.keystores(),
+        ] {
+            if dir.exists() && !dir.is_dir() {
+                return Err(eyre!("Data directory {} is not a directory", dir.display()));
+            }
+            fs::create_dir_all(&dir)
+                .map_err(|err| eyre!("Failed to create data directory {}: {err}", dir.display()))?;
+        }
+
+        // Writing the marker checks that the directory is writable.
+        fs::write(self.root.join(MARKER_FILE), env!("CARGO_PKG_VERSION")).map_err(|err| {
+            eyre!(
+                "Data directory {} is not writable: {err}",
+                self.root.display()
+            )
+        })?;
+        Ok(())
+    }
+
+    /// Remove the subdirectories of the layout. Nothing else in the directory is touched.
+    /// # Arguments
+    /// * `keystores` - Remove the keystores too, which hold the only copy of generated keys.
+    /// # Returns
+    /// * The subdirectories removed.
+    /// # Errors
+    /// * If the directory wasn't laid out by Beerus, or a subdirectory can't be removed.
+    pub fn purge(&self, keystores: bool) -> Result<Vec<PathBuf>> {
+        if !self.root.join(MARKER_FILE).is_file() {
+            return Err(eyre!(
+                "{} is not a Beerus data directory, nothing was removed",
+                self.root.display()
+            ));
+        }
+        let mut dirs = vec![
+            self.helios(),
+            self.payload(),
+            self.archive(),
+            self.snapshots(),
+        ];
+        if keystores {
+            dirs.push(self.keystores());
+        }
+
+        let mut removed = Vec::new();
+        for dir in dirs {
+            if dir.is_dir() {
+                fs::remove_dir_all(&dir)
+                    .map_err(|err| eyre!("Failed to remove {}: {err}", dir.display()))?;
+                removed.push(dir);
+            }
+        }
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_root(name: &str) -> PathBuf {
+        let root =
+            std::env::temp_dir().join(format!("beerus-data-dir-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        root
+    }
+
+    #[test]
+    fn test_init_creates_the_layout_and_purge_keeps_the_rest() {
+        let data_dir = DataDir::new(temp_root("purge"));
+        data_dir.init().unwrap();
+        for dir in [
+            data_dir.helios(),
+            data_dir.payload(),
+            data_dir.archive(),
+            data_dir.snapshots(),
+            data_dir.keystores(),
+        ] {
+            assert!(dir.is_dir());
+        }
+        fs::write(data_dir.keystores().join("alice.json"), "{}").unwrap();
+        fs::write(data_dir.root().join("other"), "kept").unwrap();
+
+        let removed = data_dir.purge(false).unwrap();
+        assert_eq!(
+            removed,
+            vec![
+                data_dir.helios(),
+                data_dir.payload(),
+                data_dir.archive(),
+                data_dir.snapshots()
+            ]
+        );
+        assert!(data_dir.keystores().join("alice.json").is_file());
+        assert!(data_dir.root().join("other").is_file());
+
+        assert_eq!(data_dir.purge(true).unwrap(), vec![data_dir.keystores()]);
+        assert!(data_dir.root().join("other").is_file());
+        fs::remove_dir_all(data_dir.root()).unwrap();
+    }
+
+    #[test]
+    fn test_purge_is_refused_outside_a_data_directory() {
+        let root = temp_root("unmarked");
+        fs::create_dir_all(root.join(HELIOS_DIR)).unwrap();
+        assert!(DataDir::new(&root).purge(true).is_err());
+        assert!(root.join(HELIOS_DIR).is_dir());
+
+        // A file in place of a subdirectory fails the validation.
+        fs::write(root.join(PAYLOAD_DIR), "").unwrap();
+        assert!(DataDir::new(&root).init().is_err());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,178 @@
+//! Encodings of the Cairo values in felts: selectors, short strings, `u256` and `ByteArray`.
+use ethers::types::U256;
+use eyre::{eyre, Result};
+use starknet::core::types::FieldElement;
+
+pub use crate::ethers_helper::felt_to_u256;
+pub use starknet::core::utils::get_selector_from_name;
+
+/// Most characters a short string holds, as a felt is under 2^252.
+pub const MAX_SHORT_STRING_LEN: usize = 31;
+
+/// Bytes in each word of a `ByteArray`.
+const BYTES_PER_WORD: usize = 31;
+
+/// Encode an ASCII string of at most 31 characters as a felt, such as `SN_MAIN`.
+pub fn short_string_to_felt(value: &str) -> Result<FieldElement> {
+    if !value.is_ascii() {
+        return Err(eyre!("Short string {value} is not ASCII"));
+    }
+    if value.len() > MAX_SHORT_STRING_LEN {
+        return Err(eyre!(
+            "Short string {value} is longer than {MAX_SHORT_STRING_LEN} characters"
+        ));
+    }
+    Ok(FieldElement::from_byte_slice_be(value.as_bytes())?)
+}
+
+/// Decode a short string from a felt.
+pub fn felt_to_short_string(felt: FieldElement) -> Result<String> {
+    let bytes = felt.to_bytes_be();
+    let start = bytes
+        .iter()
+        .position(|byte| *byte != 0)
+        .unwrap_or(bytes.len());
+    if start == 0 {
+        return Err(eyre!("{felt:#x} is longer than a short string"));
+    }
+    let bytes = &bytes[start..];
+    if !bytes.is_ascii() {
+        return Err(eyre!("{felt:#x} is not an ASCII short string"));
+    }
+    Ok(String::from_utf8(bytes.to_vec())?)
+}
+
+/// Convert a 128-bit integer to a felt.
+pub fn u128_to_felt(value: u128) -> FieldElement {
+    FieldElement::from_byte_slice_be(&value.to_be_bytes()).unwrap()
+}
+
+/// Split a `u256` into its low and high 128 bits, the way Cairo passes it.
+pub fn u256_to_felts(value: U256) -> (FieldElement, FieldElement) {
+    (
+        u128_to_felt(value.low_u128()),
+        u128_to_felt((value >> 128).low_u128()),
+    )
+}
+
+/// Join the low and high 128 bits of a `u256`.
+pub fn felts_to_u256(low: FieldElement, high: FieldElement) -> Result<U256> {
+    let limit = U256::one() << 128;
+    let (low, high) = (felt_to_u256(low), felt_to_u256(high));
+    if low >= limit || high >= limit {
+        return Err(eyre!(
+            "Invalid u256, {low:#x} or {high:#x} exceeds 128 bits"
+        ));
+    }
+    Ok((high << 128) + low)
+}
+
+/// Serialize bytes as a Cairo `ByteArray`: the number of full words of 31 bytes, the words, the
+/// pending word holding the bytes left, and its length.
+pub fn byte_array_to_felts(bytes: &[u8]) -> Vec<FieldElement> {
+    let words = bytes.chunks(BYTES_PER_WORD);
+    let (full, pending): (Vec<&[u8]>, Vec<&[u8]>) =
+        words.partition(|word| word.len() == BYTES_PER_WORD);
+    let pending = pending.first().copied().unwrap_or_default();
+
+    let mut felts = vec![FieldElement::from(full.len() as u64)];
+    felts.extend(
+        full.iter()
+            .map(|word| FieldElement::from_byte_slice_be(word).unwrap()),
+    );
+    felts.push(FieldElement::from_byte_slice_be(pending).unwrap());
+    felts.push(FieldElement::from(pending.len() as u64));
+    felts
+}
+
+/// Deserialize the bytes of a Cairo `ByteArray`. The felts must hold exactly one array.
+pub fn felts_to_byte_array(felts: &[FieldElement]) -> Result<Vec<u8>> {
+    let word_count = felts
+        .first()
+        .map(|count| felt_to_u256(*count))
+        .filter(|count| *count < U256::from(felts.len()))
+        .ok_or_else(|| eyre!("Invalid ByteArray, the number of words is missing or too large"))?
+        .as_usize();
+    if felts.len() != word_count + 3 {
+        return Err(eyre!(
+            "Invalid ByteArray, expected {} felts for {word_count} words, got {}",
+            word_count + 3,
+            felts.len()
+        ));
+    }
+    let pending_len = felt_to_u256(felts[word_count + 2]);
+    if pending_len >= U256::from(BYTES_PER_WORD) {
+        return Err(eyre!(
+            "Invalid ByteArray, pending word of {pending_len} bytes"
+        ));
+    }
+
+    let mut bytes = Vec::new();
+    for (word, len) in felts[1..=word_count]
+        .iter()
+        .map(|word| (word, BYTES_PER_WORD))
+        .chain([(&felts[word_count + 1], pending_len.as_usize())])
+    {
+        let word_bytes = word.to_bytes_be();
+        let (padding, word_bytes) = word_bytes.split_at(word_bytes.len() - len);
+        if padding.iter().any(|byte| *byte != 0) {
+            return Err(eyre!(
+                "Invalid ByteArray, word {word:#x} exceeds {len} bytes"
+            ));
+        }
+        bytes.extend_from_slice(word_bytes);
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_short_string_round_trip() {
+        let felt = short_string_to_felt("SN_MAIN").unwrap();
+        assert_eq!(felt, FieldElement::from_hex_be("0x534e5f4d41494e").unwrap());
+        assert_eq!(felt_to_short_string(felt).unwrap(), "SN_MAIN");
+        assert_eq!(felt_to_short_string(FieldElement::ZERO).unwrap(), "");
+        assert!(short_string_to_felt(&"a".repeat(32)).is_err());
+        assert!(short_string_to_felt("é").is_err());
+        assert!(felt_to_short_string(FieldElement::from_hex_be("0xff").unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_u256_split_and_join() {
+        let value = (U256::from(2) << 128) + U256::from(1);
+        let (low, high) = u256_to_felts(value);
+        assert_eq!((low, high), (FieldElement::ONE, FieldElement::from(2_u64)));
+        assert_eq!(felts_to_u256(low, high).unwrap(), value);
+        let (low, high) = u256_to_felts(U256::MAX);
+        assert_eq!(felts_to_u256(low, high).unwrap(), U256::MAX);
+        assert!(felts_to_u256(
+            FieldElement::from_hex_be(&format!("0x1{}", "0".repeat(32))).unwrap(),
+            FieldElement::ZERO
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_byte_array_round_trip() {
+        let text = "Beerus, a trustless StarkNet light client";
+        let felts = byte_array_to_felts(text.as_bytes());
+        assert_eq!(felts.len(), 4);
+        assert_eq!(felts[0], FieldElement::ONE);
+        assert_eq!(felts[3], FieldElement::from(10_u64));
+        assert_eq!(felts_to_byte_array(&felts).unwrap(), text.as_bytes());
+
+        let empty = byte_array_to_felts(&[]);
+        assert_eq!(empty, vec![FieldElement::ZERO; 3]);
+        assert!(felts_to_byte_array(&empty).unwrap().is_empty());
+        assert!(felts_to_byte_array(&felts[..3]).is_err());
+        assert!(felts_to_byte_array(&[
+            FieldElement::ZERO,
+            FieldElement::from(0x100_u64),
+            FieldElement::ONE
+        ])
+        .is_err());
+    }
+}
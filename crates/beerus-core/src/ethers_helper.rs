@@ -1,9 +1,10 @@
 use ethers::{
     abi::{Abi, AbiError, Token, Tokenize},
-    types::{Bytes, U256},
+    types::{Address, Bytes, H256, U256},
 };
 use eyre::{eyre, Result};
 use helios::types::BlockTag;
+use starknet::core::types::FieldElement;
 
 /// Helper for ABI encoding arguments for a specific function in a contract.
 /// # Arguments
@@ -44,6 +45,73 @@ pub fn u256_to_bytes32_type(value: U256) -> Token {
     Token::FixedBytes(u256_to_bytes32_slice(value).to_vec())
 }
 
+/// Convert big-endian bytes, e.g. the return data of an `eth_call`, to a U256.
+/// # Arguments
+/// * `bytes` - At most 32 bytes, fewer are left-padded with zeros.
+/// # Returns
+/// The U256.
+/// # Errors
+/// * If there are more than 32 bytes.
+pub fn bytes_to_u256(bytes: &[u8]) -> Result<U256> {
+    if bytes.len() > 32 {
+        return Err(eyre!("{} bytes don't fit in a uint256", bytes.len()));
+    }
+    Ok(U256::from_big_endian(bytes))
+}
+
+/// Convert a U256 to a H256, its 32 big-endian bytes.
+pub fn u256_to_h256(value: U256) -> H256 {
+    H256::from(u256_to_bytes32_slice(value))
+}
+
+/// Convert a H256 to a U256, read as big-endian.
+pub fn h256_to_u256(value: H256) -> U256 {
+    U256::from_big_endian(value.as_bytes())
+}
+
+/// Convert a felt to a U256, which always holds it.
+pub fn felt_to_u256(felt: FieldElement) -> U256 {
+    U256::from_big_endian(&felt.to_bytes_be())
+}
+
+/// Convert a U256, e.g. a state root read from L1, to a felt.
+/// # Errors
+/// * If the value is not below the StarkNet prime.
+pub fn u256_to_felt(value: U256) -> Result<FieldElement> {
+    FieldElement::from_bytes_be(&u256_to_bytes32_slice(value))
+        .map_err(|_| eyre!("{value:#x} exceeds the StarkNet field"))
+}
+
+/// Convert a felt to a H256, its 32 big-endian bytes.
+pub fn felt_to_h256(felt: FieldElement) -> H256 {
+    H256::from(felt.to_bytes_be())
+}
+
+/// Convert a H256 to a felt.
+/// # Errors
+/// * If the value is not below the StarkNet prime.
+pub fn h256_to_felt(value: H256) -> Result<FieldElement> {
+    FieldElement::from_bytes_be(value.as_fixed_bytes())
+        .map_err(|_| eyre!("{value:#x} exceeds the StarkNet field"))
+}
+
+/// Convert a U256, e.g. a storage slot holding an address, to an address.
+/// # Errors
+/// * If the value doesn't fit in 20 bytes.
+pub fn u256_to_address(value: U256) -> Result<Address> {
+    let bytes = u256_to_bytes32_slice(value);
+    let (padding, address) = bytes.split_at(12);
+    if padding.iter().any(|byte| *byte != 0) {
+        return Err(eyre!("{value:#x} is not an address"));
+    }
+    Ok(Address::from_slice(address))
+}
+
+/// Convert an address to a U256, as stored in a slot.
+pub fn address_to_u256(address: Address) -> U256 {
+    U256::from_big_endian(address.as_bytes())
+}
+
 /// Helper converting block identifier string with corresponding type to a BlockTag Type
 /// # Arguments
 /// * `block` - The block identifier.
@@ -80,8 +148,9 @@ pub fn block_tag_eq(a: &BlockTag, b: &BlockTag) -> bool {
 #[cfg(test)]
 mod tests {
     use super::block_tag_eq;
-    use ethers::types::Bytes;
+    use ethers::types::{Address, Bytes, H256, U256};
     use helios::types::BlockTag;
+    use starknet::core::types::FieldElement;
     use std::str::FromStr;
 
     #[test]
@@ -144,6 +213,44 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_felt_round_trips() {
+        let felt = FieldElement::from_hex_be(
+            "0x47f25798a804800b657d4e1508776e3c3c70f0d7587d125a558208f88570aa7",
+        )
+        .unwrap();
+        let value = super::felt_to_u256(felt);
+        assert_eq!(super::u256_to_felt(value).unwrap(), felt);
+        let hash = super::felt_to_h256(felt);
+        assert_eq!(super::h256_to_felt(hash).unwrap(), felt);
+        assert_eq!(super::h256_to_u256(hash), value);
+        assert_eq!(super::u256_to_h256(value), hash);
+
+        // The StarkNet prime doesn't fit in a felt, nor does the largest U256.
+        let prime = U256::from_dec_str(
+            "3618502788666131213697322783095070105623107215331596699973092056135872020481",
+        )
+        .unwrap();
+        assert!(super::u256_to_felt(prime).is_err());
+        assert_eq!(
+            super::u256_to_felt(prime - 1).unwrap(),
+            FieldElement::ZERO - FieldElement::ONE
+        );
+        assert!(super::h256_to_felt(H256::repeat_byte(0xff)).is_err());
+    }
+
+    #[test]
+    fn test_bytes_and_address_conversions() {
+        assert_eq!(super::bytes_to_u256(&[1, 0]).unwrap(), U256::from(256));
+        assert_eq!(super::bytes_to_u256(&[]).unwrap(), U256::zero());
+        assert!(super::bytes_to_u256(&[0; 33]).is_err());
+
+        let address = Address::from_str("0xc662c410c0ecf747543f5ba90660f6abebd9c8c4").unwrap();
+        let value = super::address_to_u256(address);
+        assert_eq!(super::u256_to_address(value).unwrap(), address);
+        assert!(super::u256_to_address(U256::MAX).is_err());
+    }
+
     #[test]
     fn test_block_string_to_block_tag_type() {
         // Testing for Number type
@@ -1,5 +1,8 @@
 #![allow(incomplete_features)]
 pub mod config;
+pub mod data_dir;
+pub mod encoding;
 pub mod ethers_helper;
 pub mod lightclient;
+pub mod runtime;
 pub mod starknet_helper;
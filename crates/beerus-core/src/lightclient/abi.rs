@@ -0,0 +1,616 @@
+//! Calls by function name, with the calldata and the results following the types of a contract
+//! ABI. Both the ABIs of Cairo 0 contracts and the ones of Sierra classes are read: the
+//! functions are looked up at the top level and in the interfaces, structs and enums by name.
+use crate::encoding::{
+    felt_to_u256, felts_to_u256, get_selector_from_name, u128_to_felt, u256_to_felts,
+};
+use ethers::types::U256;
+use eyre::{eyre, Result};
+use serde_json::{Map, Value};
+use starknet::core::types::FieldElement;
+use std::str::FromStr;
+
+/// Types encoded as a single felt.
+const FELT_TYPES: &[&str] = &[
+    "felt",
+    "core::felt252",
+    "core::bool",
+    "core::integer::u8",
+    "core::integer::u16",
+    "core::integer::u32",
+    "core::integer::u64",
+    "core::integer::u128",
+    "core::integer::i8",
+    "core::integer::i16",
+    "core::integer::i32",
+    "core::integer::i64",
+    "core::integer::i128",
+    "core::bytes_31::bytes31",
+    "core::starknet::contract_address::ContractAddress",
+    "core::starknet::class_hash::ClassHash",
+    "core::starknet::eth_address::EthAddress",
+    "core::starknet::storage_access::StorageAddress",
+    "core::starknet::ContractAddress",
+    "core::starknet::ClassHash",
+];
+
+/// Prefixes of the Sierra types encoded as their length followed by their items.
+const ARRAY_PREFIXES: &[&str] = &["core::array::Array::<", "core::array::Span::<"];
+
+/// Value of an ABI type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AbiValue {
+    Felt(FieldElement),
+    /// Items of an array or a span, members of a tuple, or members of a struct in order.
+    Array(Vec<AbiValue>),
+    /// Members of a struct by name.
+    Struct(Vec<(String, AbiValue)>),
+    /// Variant of an enum by name, and its value.
+    Enum(String, Box<AbiValue>),
+}
+
+impl AbiValue {
+    /// Value of an enum variant without data, such as `None`.
+    pub fn unit_variant(name: &str) -> Self {
+        Self::Enum(name.to_string(), Box::new(Self::Array(vec![])))
+    }
+
+    pub fn as_felt(&self) -> Option<FieldElement> {
+        match self {
+            Self::Felt(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[AbiValue]> {
+        match self {
+            Self::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        self.as_felt().map(|value| value != FieldElement::ZERO)
+    }
+
+    /// Read a `u256`, or a Cairo 0 `Uint256`, from its low and high members.
+    pub fn as_u256(&self) -> Option<U256> {
+        let (low, high) = match self {
+            Self::Array(values) if values.len() == 2 => (&values[0], &values[1]),
+            Self::Struct(_) => (self.member("low")?, self.member("high")?),
+            _ => return None,
+        };
+        felts_to_u256(low.as_felt()?, high.as_felt()?).ok()
+    }
+
+    /// Return a member of a struct.
+    pub fn member(&self, name: &str) -> Option<&AbiValue> {
+        match self {
+            Self::Struct(members) => members
+                .iter()
+                .find(|(member, _)| member == name)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    /// Read a value from JSON: a felt is a string, hexadecimal or decimal, or a number, an
+    /// array holds items or members in order, and an object holds members by name. An object
+    /// with a single member also stands for an enum variant.
+    pub fn from_json(value: &Value) -> Result<Self> {
+        match value {
+            Value::String(value) => Ok(Self::Felt(FieldElement::from_str(value)?)),
+            Value::Number(value) => Ok(Self::Felt(FieldElement::from_str(&value.to_string())?)),
+            Value::Bool(value) => Ok(Self::from(*value)),
+            Value::Array(values) => Ok(Self::Array(
+                values.iter().map(Self::from_json).collect::<Result<_>>()?,
+            )),
+            Value::Object(members) => Ok(Self::Struct(
+                members
+                    .iter()
+                    .map(|(name, value)| Ok((name.clone(), Self::from_json(value)?)))
+                    .collect::<Result<_>>()?,
+            )),
+            Value::Null => Err(eyre!("Expected an ABI value, got null")),
+        }
+    }
+
+    /// Write a value as JSON, with the felts in hexadecimal.
+    pub fn to_json(&self) -> Value {
+        match self {
+            Self::Felt(value) => Value::String(format!("{value:#x}")),
+            Self::Array(values) => Value::Array(values.iter().map(Self::to_json).collect()),
+            Self::Struct(members) => Value::Object(
+                members
+                    .iter()
+                    .map(|(name, value)| (name.clone(), value.to_json()))
+                    .collect::<Map<_, _>>(),
+            ),
+            Self::Enum(variant, value) => {
+                let mut object = Map::new();
+                object.insert(variant.clone(), value.to_json());
+                Value::Object(object)
+            }
+        }
+    }
+}
+
+impl From<FieldElement> for AbiValue {
+    fn from(value: FieldElement) -> Self {
+        Self::Felt(value)
+    }
+}
+
+impl From<bool> for AbiValue {
+    fn from(value: bool) -> Self {
+        Self::Felt(if value {
+            FieldElement::ONE
+        } else {
+            FieldElement::ZERO
+        })
+    }
+}
+
+macro_rules! impl_from_integer {
+    ($($ty:ty),*) => {
+        $(
+            impl From<$ty> for AbiValue {
+                fn from(value: $ty) -> Self {
+                    Self::Felt(FieldElement::from(value))
+                }
+            }
+        )*
+    };
+}
+
+impl_from_integer!(u8, u16, u32, u64);
+
+impl From<u128> for AbiValue {
+    fn from(value: u128) -> Self {
+        Self::Felt(u128_to_felt(value))
+    }
+}
+
+/// A `u256` is its low and high 128 bits.
+impl From<U256> for AbiValue {
+    fn from(value: U256) -> Self {
+        let (low, high) = u256_to_felts(value);
+        Self::Array(vec![low.into(), high.into()])
+    }
+}
+
+impl<T: Into<AbiValue>> From<Vec<T>> for AbiValue {
+    fn from(values: Vec<T>) -> Self {
+        Self::Array(values.into_iter().map(Into::into).collect())
+    }
+}
+
+/// Input or output of a function.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Param {
+    name: String,
+    ty: String,
+}
+
+/// ABI of a contract, Cairo 0 or Sierra.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContractAbi {
+    entries: Vec<Value>,
+}
+
+impl ContractAbi {
+    /// Read an ABI: its entries, the ABI of a Sierra class as a string, or a class along with
+    /// its ABI.
+    pub fn from_json(abi: &Value) -> Result<Self> {
+        match abi {
+            Value::Array(entries) => {
+                let mut flattened = Vec::new();
+                for entry in entries {
+                    // The functions of a Sierra class are grouped by interface.
+                    match entry["items"].as_array() {
+                        Some(items) if entry["type"] == "interface" => {
+                            flattened.extend(items.iter().cloned())
+                        }
+                        _ => flattened.push(entry.clone()),
+                    }
+                }
+                Ok(Self { entries: flattened })
+            }
+            Value::String(abi) => Self::from_json(&serde_json::from_str(abi)?),
+            Value::Object(class) if class.contains_key("abi") => Self::from_json(&class["abi"]),
+            _ => Err(eyre!("Invalid contract ABI")),
+        }
+    }
+
+    /// Return the selector of a function of the ABI.
+    pub fn selector(&self, function: &str) -> Result<FieldElement> {
+        self.function(function)?;
+        Ok(get_selector_from_name(function)?)
+    }
+
+    /// Encode the arguments of a function as calldata, one argument per input.
+    /// The lengths of the arrays of Cairo 0 are not arguments of their own.
+    /// # Arguments
+    /// * `function` - The name of the function.
+    /// * `args` - The arguments.
+    pub fn encode(&self, function: &str, args: &[AbiValue]) -> Result<Vec<FieldElement>> {
+        let inputs = self.params(function, "inputs")?;
+        if inputs.len() != args.len() {
+            return Err(eyre!(
+                "Function {function} takes {} arguments, got {}",
+                inputs.len(),
+                args.len()
+            ));
+        }
+        let mut calldata = Vec::new();
+        for (input, arg) in inputs.iter().zip(args) {
+            self.encode_value(&input.ty, arg, &mut calldata)
+                .map_err(|err| eyre!("Invalid argument {}: {err}", input.name))?;
+        }
+        Ok(calldata)
+    }
+
+    /// Decode the result of a function, one value per output.
+    /// # Arguments
+    /// * `function` - The name of the function.
+    /// * `result` - The felts returned by the call.
+    pub fn decode(&self, function: &str, result: &[FieldElement]) -> Result<Vec<AbiValue>> {
+        let outputs = self.params(function, "outputs")?;
+        let mut felts = result.iter();
+        let values = outputs
+            .iter()
+            .map(|output| self.decode_value(&output.ty, &mut felts))
+            .collect::<Result<Vec<_>>>()?;
+        if felts.next().is_some() {
+            return Err(eyre!(
+                "Function {function} returned more than its outputs: {} felts",
+                result.len()
+            ));
+        }
+        Ok(values)
+    }
+
+    /// Return the entry of a function.
+    fn function(&self, function: &str) -> Result<&Value> {
+        self.entries
+            .iter()
+            .find(|entry| entry["type"] == "function" && entry["name"] == function)
+            .ok_or_else(|| eyre!("Function {function} not found in the ABI"))
+    }
+
+    /// Return the inputs or outputs of a function, without the lengths of the Cairo 0 arrays,
+    /// which precede them and are named after them.
+    fn params(&self, function: &str, kind: &str) -> Result<Vec<Param>> {
+        let params = self.function(function)?[kind]
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .map(|param| {
+                Ok(Param {
+                    name: param["name"].as_str().unwrap_or_default().to_string(),
+                    ty: param["type"]
+                        .as_str()
+                        .ok_or_else(|| eyre!("Invalid {kind} of {function}: {param}"))?
+                        .to_string(),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(params
+            .iter()
+            .enumerate()
+            .filter(|(index, param)| {
+                !params.get(index + 1).map_or(false, |next| {
+                    next.ty.ends_with('*') && param.name == format!("{}_len", next.name)
+                })
+            })
+            .map(|(_, param)| param.clone())
+            .collect())
+    }
+
+    /// Return the entry of a struct or an enum.
+    fn entry(&self, kind: &str, ty: &str) -> Option<&Vec<Value>> {
+        let field = if kind == "struct" {
+            "members"
+        } else {
+            "variants"
+        };
+        self.entries
+            .iter()
+            .find(|entry| entry["type"] == kind && entry["name"] == ty)
+            .and_then(|entry| entry[field].as_array())
+    }
+
+    fn encode_value(
+        &self,
+        ty: &str,
+        value: &AbiValue,
+        calldata: &mut Vec<FieldElement>,
+    ) -> Result<()> {
+        let ty = ty.trim();
+        if FELT_TYPES.contains(&ty) {
+            let felt = value
+                .as_felt()
+                .ok_or_else(|| eyre!("Expected a {ty}, got {}", value.to_json()))?;
+            calldata.push(felt);
+            return Ok(());
+        }
+        if let Some(item_ty) = array_item_type(ty) {
+            let items = value
+                .as_array()
+                .ok_or_else(|| eyre!("Expected an array of {item_ty}, got {}", value.to_json()))?;
+            calldata.push(FieldElement::from(items.len() as u64));
+            for item in items {
+                self.encode_value(item_ty, item, calldata)?;
+            }
+            return Ok(());
+        }
+        if let Some(member_types) = tuple_types(ty) {
+            let members = value
+                .as_array()
+                .filter(|members| members.len() == member_types.len())
+                .ok_or_else(|| eyre!("Expected a tuple {ty}, got {}", value.to_json()))?;
+            for (member_ty, member) in member_types.iter().zip(members) {
+                self.encode_value(member_ty, member, calldata)?;
+            }
+            return Ok(());
+        }
+        if let Some(members) = self.entry("struct", ty) {
+            for (index, member) in members.iter().enumerate() {
+                let name = member["name"].as_str().unwrap_or_default();
+                let member_value = match value {
+                    AbiValue::Array(values) => values.get(index),
+                    AbiValue::Struct(_) => value.member(name),
+                    _ => None,
+                }
+                .ok_or_else(|| eyre!("Missing member {name} of {ty} in {}", value.to_json()))?;
+                self.encode_value(member_type(member)?, member_value, calldata)?;
+            }
+            return Ok(());
+        }
+        if let Some(variants) = self.entry("enum", ty) {
+            let (variant, variant_value) = match value {
+                AbiValue::Enum(variant, variant_value) => (variant.as_str(), &**variant_value),
+                AbiValue::Struct(members) if members.len() == 1 => {
+                    (members[0].0.as_str(), &members[0].1)
+                }
+                _ => return Err(eyre!("Expected a variant of {ty}, got {}", value.to_json())),
+            };
+            let (index, variant_entry) = variants
+                .iter()
+                .enumerate()
+                .find(|(_, entry)| entry["name"] == variant)
+                .ok_or_else(|| eyre!("Unknown variant {variant} of {ty}"))?;
+            calldata.push(FieldElement::from(index as u64));
+            return self.encode_value(member_type(variant_entry)?, variant_value, calldata);
+        }
+        if ty == "core::integer::u256" {
+            return self.encode_value(
+                "(core::integer::u128, core::integer::u128)",
+                value,
+                calldata,
+            );
+        }
+        Err(eyre!("Unsupported ABI type {ty}"))
+    }
+
+    fn decode_value<'a>(
+        &self,
+        ty: &str,
+        felts: &mut impl Iterator<Item = &'a FieldElement>,
+    ) -> Result<AbiValue> {
+        let ty = ty.trim();
+        let mut next = || {
+            felts
+                .next()
+                .copied()
+                .ok_or_else(|| eyre!("The result is too short for {ty}"))
+        };
+        if FELT_TYPES.contains(&ty) {
+            return Ok(AbiValue::Felt(next()?));
+        }
+        if let Some(item_ty) = array_item_type(ty) {
+            let len = felt_to_index(&next()?)?;
+            return Ok(AbiValue::Array(
+                (0..len)
+                    .map(|_| self.decode_value(item_ty, felts))
+                    .collect::<Result<_>>()?,
+            ));
+        }
+        if let Some(member_types) = tuple_types(ty) {
+            return Ok(AbiValue::Array(
+                member_types
+                    .iter()
+                    .map(|member_ty| self.decode_value(member_ty, felts))
+                    .collect::<Result<_>>()?,
+            ));
+        }
+        if let Some(members) = self.entry("struct", ty) {
+            return Ok(AbiValue::Struct(
+                members
+                    .iter()
+                    .map(|member| {
+                        let name = member["name"].as_str().unwrap_or_default().to_string();
+                        Ok((name, self.decode_value(member_type(member)?, felts)?))
+                    })
+                    .collect::<Result<_>>()?,
+            ));
+        }
+        if let Some(variants) = self.entry("enum", ty) {
+            let index = felt_to_index(&next()?)?;
+            let variant = variants
+                .get(index)
+                .ok_or_else(|| eyre!("Unknown variant {index} of {ty}"))?;
+            let name = variant["name"].as_str().unwrap_or_default().to_string();
+            let value = self.decode_value(member_type(variant)?, felts)?;
+            return Ok(AbiValue::Enum(name, Box::new(value)));
+        }
+        if ty == "core::integer::u256" {
+            return self.decode_value("(core::integer::u128, core::integer::u128)", felts);
+        }
+        Err(eyre!("Unsupported ABI type {ty}"))
+    }
+}
+
+/// Return the type of the items of an array: `T*` in Cairo 0, `Array::<T>` or `Span::<T>`.
+fn array_item_type(ty: &str) -> Option<&str> {
+    if let Some(item_ty) = ty.strip_suffix('*') {
+        return Some(item_ty);
+    }
+    ARRAY_PREFIXES
+        .iter()
+        .find_map(|prefix| ty.strip_prefix(prefix))
+        .and_then(|item_ty| item_ty.strip_suffix('>'))
+}
+
+/// Return the types of the members of a tuple, whose members are named in Cairo 0.
+fn tuple_types(ty: &str) -> Option<Vec<&str>> {
+    let members = ty.strip_prefix('(')?.strip_suffix(')')?;
+    let mut types = Vec::new();
+    let (mut depth, mut start) = (0, 0);
+    for (index, c) in members.char_indices() {
+        match c {
+            '(' | '<' => depth += 1,
+            ')' | '>' => depth -= 1,
+            ',' if depth == 0 => {
+                types.push(&members[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    types.push(&members[start..]);
+    Some(
+        types
+            .into_iter()
+            .map(str::trim)
+            .filter(|member| !member.is_empty())
+            .map(|member| match member.split_once(": ") {
+                Some((_, member_ty)) if !member.starts_with('(') => member_ty,
+                _ => member,
+            })
+            .collect(),
+    )
+}
+
+fn member_type(member: &Value) -> Result<&str> {
+    member["type"]
+        .as_str()
+        .ok_or_else(|| eyre!("Invalid member in the ABI: {member}"))
+}
+
+fn felt_to_index(felt: &FieldElement) -> Result<usize> {
+    let value = felt_to_u256(*felt);
+    if value > U256::from(u32::MAX) {
+        return Err(eyre!("Invalid length or variant {felt:#x}"));
+    }
+    Ok(value.as_usize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn felt(value: u64) -> FieldElement {
+        FieldElement::from(value)
+    }
+
+    #[test]
+    fn test_cairo_0_abi_skips_the_lengths_of_the_arrays() {
+        let abi = ContractAbi::from_json(&json!([
+            {"type": "struct", "name": "Uint256", "size": 2, "members": [
+                {"name": "low", "type": "felt", "offset": 0},
+                {"name": "high", "type": "felt", "offset": 1}
+            ]},
+            {"type": "function", "name": "transfer", "inputs": [
+                {"name": "to", "type": "felt"},
+                {"name": "amounts_len", "type": "felt"},
+                {"name": "amounts", "type": "Uint256*"}
+            ], "outputs": [
+                {"name": "balances_len", "type": "felt"},
+                {"name": "balances", "type": "felt*"},
+                {"name": "point", "type": "(x: felt, y: felt)"}
+            ]}
+        ]))
+        .unwrap();
+
+        let calldata = abi
+            .encode(
+                "transfer",
+                &[
+                    felt(0x12).into(),
+                    AbiValue::Array(vec![U256::from(5).into(), U256::MAX.into()]),
+                ],
+            )
+            .unwrap();
+        let max = u128_to_felt(u128::MAX);
+        assert_eq!(
+            calldata,
+            vec![felt(0x12), felt(2), felt(5), felt(0), max, max]
+        );
+
+        let result = abi
+            .decode("transfer", &[felt(2), felt(7), felt(8), felt(1), felt(2)])
+            .unwrap();
+        assert_eq!(
+            result,
+            vec![vec![felt(7), felt(8)].into(), vec![felt(1), felt(2)].into()]
+        );
+        assert!(abi.decode("transfer", &[felt(2), felt(7)]).is_err());
+    }
+
+    #[test]
+    fn test_sierra_abi_encodes_structs_and_enums() {
+        let abi = json!([
+            {"type": "impl", "name": "TokenImpl", "interface_name": "token::IToken"},
+            {"type": "struct", "name": "core::integer::u256", "members": [
+                {"name": "low", "type": "core::integer::u128"},
+                {"name": "high", "type": "core::integer::u128"}
+            ]},
+            {"type": "enum", "name": "core::option::Option::<core::felt252>", "variants": [
+                {"name": "Some", "type": "core::felt252"},
+                {"name": "None", "type": "()"}
+            ]},
+            {"type": "interface", "name": "token::IToken", "items": [
+                {"type": "function", "name": "balance_of", "inputs": [
+                    {"name": "account", "type": "core::starknet::contract_address::ContractAddress"},
+                    {"name": "memo", "type": "core::option::Option::<core::felt252>"},
+                    {"name": "tags", "type": "core::array::Span::<core::felt252>"}
+                ], "outputs": [
+                    {"type": "core::integer::u256"}
+                ], "state_mutability": "view"}
+            ]}
+        ]);
+        // The ABI of a Sierra class is a string.
+        let abi = ContractAbi::from_json(&json!({"abi": abi.to_string()})).unwrap();
+        assert_eq!(
+            abi.selector("balance_of").unwrap(),
+            get_selector_from_name("balance_of").unwrap()
+        );
+
+        let calldata = abi
+            .encode(
+                "balance_of",
+                &[
+                    felt(0x12).into(),
+                    AbiValue::unit_variant("None"),
+                    vec![felt(3)].into(),
+                ],
+            )
+            .unwrap();
+        assert_eq!(calldata, vec![felt(0x12), felt(1), felt(1), felt(3)]);
+        let args = AbiValue::from_json(&json!(["0x12", {"Some": 5}, []])).unwrap();
+        let calldata = abi.encode("balance_of", args.as_array().unwrap()).unwrap();
+        assert_eq!(calldata, vec![felt(0x12), felt(0), felt(5), felt(0)]);
+
+        let result = abi.decode("balance_of", &[felt(1), felt(2)]).unwrap();
+        assert_eq!(
+            result[0].as_u256(),
+            Some((U256::from(2) << 128) + U256::from(1))
+        );
+        assert_eq!(result[0].to_json(), json!({"low": "0x1", "high": "0x2"}));
+        assert!(abi.encode("balance_of", &[felt(0x12).into()]).is_err());
+        assert!(abi.selector("transfer").is_err());
+    }
+}
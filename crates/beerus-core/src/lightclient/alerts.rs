@@ -0,0 +1,229 @@
+//! Alerts on the events operators must act upon: reorgs of the synced chain, state roots
+//! differing from the ones proven on L1, providers disagreeing with the other sources of
+//! blocks, and a syncer making no progress.
+//!
+//! Alerts are logged, published to the subscribers, and delivered to the configured hooks in
+//! the background, so that a slow hook never holds the sync tasks up.
+use crate::{config::AlertConfig, runtime};
+use eyre::{eyre, Result};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::{fmt, sync::Arc};
+use tokio::sync::broadcast;
+
+/// Alerts kept for the subscribers lagging behind.
+const ALERT_CHANNEL_CAPACITY: usize = 64;
+
+/// Kind of event an alert reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    /// A new block doesn't extend the retained chain, the blocks from the fork were dropped.
+    Reorg,
+    /// The state root of a synced block differs from the one proven on L1 for it.
+    StateRootMismatch,
+    /// A source of blocks lags behind the others repeatedly and was down-ranked.
+    ProviderDivergence,
+    /// No new block was synced for longer than `ALERT_SYNC_STALL_SECS`.
+    SyncStall,
+}
+
+impl AlertKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Reorg => "reorg",
+            Self::StateRootMismatch => "state_root_mismatch",
+            Self::ProviderDivergence => "provider_divergence",
+            Self::SyncStall => "sync_stall",
+        }
+    }
+}
+
+impl fmt::Display for AlertKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Alert as posted to the webhooks.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Alert {
+    pub kind: AlertKind,
+    pub message: String,
+    /// StarkNet block the event is about, if any.
+    pub block_number: Option<u64>,
+    /// Milliseconds since the Unix epoch.
+    pub timestamp_ms: u64,
+}
+
+impl Alert {
+    pub fn new(kind: AlertKind, message: impl Into<String>, block_number: Option<u64>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            block_number,
+            timestamp_ms: runtime::now_millis(),
+        }
+    }
+}
+
+/// Hooks the alerts are delivered to, see [`AlertConfig`].
+#[derive(Debug)]
+pub struct AlertHooks {
+    webhook_urls: Vec<String>,
+    command: Option<String>,
+    http_client: reqwest::Client,
+    alerts: broadcast::Sender<Alert>,
+}
+
+impl AlertHooks {
+    pub fn new(config: &AlertConfig, http_client: reqwest::Client) -> Self {
+        Self {
+            webhook_urls: config.webhook_urls.clone(),
+            command: config.command.clone(),
+            http_client,
+            alerts: broadcast::channel(ALERT_CHANNEL_CAPACITY).0,
+        }
+    }
+
+    /// Subscribe to the alerts fired from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<Alert> {
+        self.alerts.subscribe()
+    }
+
+    /// Log and publish an alert, and deliver it to the hooks in the background.
+    pub fn fire(self: &Arc<Self>, alert: Alert) {
+        warn!("Alert {}: {}", alert.kind, alert.message);
+        // Nobody may be subscribed.
+        let _ = self.alerts.send(alert.clone());
+        if self.webhook_urls.is_empty() && self.command.is_none() {
+            return;
+        }
+        let hooks = self.clone();
+        runtime::spawn(async move {
+            if let Err(err) = hooks.deliver(&alert).await {
+                warn!("Failed to deliver alert {}: {err}", alert.kind);
+            }
+        });
+    }
+
+    /// Post an alert to every webhook and run the command, if any.
+    /// # Errors
+    /// * If a hook failed, the others are still run.
+    pub async fn deliver(&self, alert: &Alert) -> Result<()> {
+        let mut errors = Vec::new();
+        for url in self.webhook_urls.iter() {
+            let response = self
+                .http_client
+                .post(url)
+                .json(alert)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status());
+            if let Err(err) = response {
+                errors.push(format!("webhook {url}: {err}"));
+            }
+        }
+        if let Some(command) = &self.command {
+            if let Err(err) = run_command(command, alert).await {
+                errors.push(format!("command: {err}"));
+            }
+        }
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(eyre!(errors.join(", "))),
+        }
+    }
+}
+
+/// Run the alert command with `sh`. The alert is passed in `BEERUS_ALERT` as JSON, and its
+/// kind and message in `BEERUS_ALERT_KIND` and `BEERUS_ALERT_MESSAGE`.
+#[cfg(not(target_arch = "wasm32"))]
+async fn run_command(command: &str, alert: &Alert) -> Result<()> {
+    let status = tokio::process::Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .env("BEERUS_ALERT", serde_json::to_string(alert)?)
+        .env("BEERUS_ALERT_KIND", alert.kind.as_str())
+        .env("BEERUS_ALERT_MESSAGE", &alert.message)
+        .status()
+        .await?;
+    match status.success() {
+        true => Ok(()),
+        false => Err(eyre!("{command} exited with {status}")),
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+async fn run_command(_command: &str, _alert: &Alert) -> Result<()> {
+    Err(eyre!("Alert commands can't be run in the browser"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use httpmock::prelude::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_alert_is_posted_to_the_webhooks_and_passed_to_the_command() {
+        let server = MockServer::start();
+        let alert = Alert::new(AlertKind::Reorg, "Block 10 reorged", Some(10));
+        let webhook = server.mock(|when, then| {
+            when.method(POST).path("/alerts").json_body(json!({
+                "kind": "reorg",
+                "message": "Block 10 reorged",
+                "block_number": 10,
+                "timestamp_ms": alert.timestamp_ms,
+            }));
+            then.status(200);
+        });
+        let output = std::env::temp_dir().join(format!("beerus-alert-{}", std::process::id()));
+        let hooks = AlertHooks::new(
+            &AlertConfig {
+                webhook_urls: vec![server.url("/alerts")],
+                command: Some(format!(
+                    "echo \"$BEERUS_ALERT_KIND $BEERUS_ALERT_MESSAGE\" > {}",
+                    output.display()
+                )),
+                ..AlertConfig::default()
+            },
+            reqwest::Client::new(),
+        );
+
+        hooks.deliver(&alert).await.unwrap();
+
+        webhook.assert();
+        assert_eq!(
+            std::fs::read_to_string(&output).unwrap(),
+            "reorg Block 10 reorged\n"
+        );
+        std::fs::remove_file(output).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_failed_hooks_are_reported() {
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(POST);
+            then.status(500);
+        });
+        let hooks = AlertHooks::new(
+            &AlertConfig {
+                webhook_urls: vec![server.url("/alerts")],
+                command: Some("exit 3".to_string()),
+                ..AlertConfig::default()
+            },
+            reqwest::Client::new(),
+        );
+
+        let err = hooks
+            .deliver(&Alert::new(AlertKind::SyncStall, "Stalled", None))
+            .await
+            .unwrap_err()
+            .to_string();
+
+        assert!(err.contains("webhook"));
+        assert!(err.contains("command"));
+    }
+}
@@ -0,0 +1,350 @@
+//! Archive of the proven blocks on disk, with their transactions and receipts, for the nodes
+//! running in archival mode.
+//!
+//! Blocks are appended as JSON lines to segments of `ARCHIVE_SEGMENT_BLOCKS` blocks, named
+//! after their first block. Once the archive moved past a segment, compaction rewrites it
+//! with one line per block, by ascending number, compressed with zstd when available. A block
+//! archived again, e.g. after a resync, replaces the previous copy.
+use super::export::{
+    check_range, table_rows, write_rows, ExportFormat, ExportTable, MAX_EXPORT_BLOCKS,
+};
+use eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
+use starknet::providers::jsonrpc::models::{BlockWithTxs, MaybePendingTransactionReceipt};
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// Blocks per segment of the archive.
+pub const ARCHIVE_SEGMENT_BLOCKS: u64 = 1_000;
+
+const SEGMENT_PREFIX: &str = "segment-";
+const PLAIN_EXTENSION: &str = ".jsonl";
+const COMPACTED_EXTENSION: &str = ".jsonl.zst";
+/// zstd level of the compacted segments.
+#[cfg(not(target_arch = "wasm32"))]
+const COMPACTION_LEVEL: i32 = 3;
+
+/// Block of the archive, along with the receipts of its transactions, in order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchivedBlock {
+    pub block: BlockWithTxs,
+    pub receipts: Vec<MaybePendingTransactionReceipt>,
+}
+
+/// Archive stored in a directory, usually the `archive` subdirectory of the data directory.
+#[derive(Debug, Clone)]
+pub struct Archive {
+    dir: PathBuf,
+}
+
+impl Archive {
+    /// The directory is created on the first write.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Append a block and its receipts to its segment.
+    /// # Errors
+    /// * If the segment can't be written.
+    pub fn append(
+        &self,
+        block: &BlockWithTxs,
+        receipts: &[MaybePendingTransactionReceipt],
+    ) -> Result<()> {
+        fs::create_dir_all(&self.dir)
+            .map_err(|err| eyre!("Failed to create archive {}: {err}", self.dir.display()))?;
+        let archived = ArchivedBlock {
+            block: block.clone(),
+            receipts: receipts.to_vec(),
+        };
+        let mut line = serde_json::to_vec(&archived)?;
+        line.push(b'\n');
+        let path = self.segment_path(segment_start(block.block_number), false);
+        fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .and_then(|mut file| file.write_all(&line))
+            .map_err(|err| eyre!("Failed to append to {}: {err}", path.display()))
+    }
+
+    /// Number of the newest block archived, if any.
+    pub fn last_block_number(&self) -> Result<Option<u64>> {
+        let last_segment = match self.segments()?.into_iter().next_back() {
+            Some(start) => start,
+            None => return Ok(None),
+        };
+        Ok(self.read_segment(last_segment)?.into_keys().next_back())
+    }
+
+    /// Return the archived blocks from `from` to `to` included, by ascending number.
+    /// # Errors
+    /// * If a segment can't be read or is corrupted.
+    pub fn read_range(&self, from: u64, to: u64) -> Result<Vec<ArchivedBlock>> {
+        let mut blocks = Vec::new();
+        for start in self.segments()? {
+            if start + ARCHIVE_SEGMENT_BLOCKS <= from || start > to {
+                continue;
+            }
+            blocks.extend(
+                self.read_segment(start)?
+                    .into_iter()
+                    .filter(|(block_number, _)| (from..=to).contains(block_number))
+                    .map(|(_, archived)| archived),
+            );
+        }
+        Ok(blocks)
+    }
+
+    /// Compact the segments the archive moved past, the one of the newest block is left as is.
+    /// # Returns
+    /// * The number of segments compacted.
+    /// # Errors
+    /// * If a segment can't be read or rewritten.
+    pub fn compact(&self) -> Result<usize> {
+        let segments = self.segments()?;
+        let current = match segments.last() {
+            Some(start) => *start,
+            None => return Ok(0),
+        };
+        let mut compacted = 0;
+        for start in segments.into_iter().filter(|start| *start < current) {
+            let plain = self.segment_path(start, false);
+            if !plain.is_file() {
+                continue;
+            }
+            let mut lines = Vec::new();
+            for archived in self.read_segment(start)?.values() {
+                lines.extend(serde_json::to_vec(archived)?);
+                lines.push(b'\n');
+            }
+            let path = self.segment_path(start, true);
+            fs::write(&path, compress(&lines)?)
+                .map_err(|err| eyre!("Failed to write {}: {err}", path.display()))?;
+            fs::remove_file(&plain)
+                .map_err(|err| eyre!("Failed to remove {}: {err}", plain.display()))?;
+            compacted += 1;
+        }
+        Ok(compacted)
+    }
+
+    /// Export a table of the archived blocks from `from` to `to` included.
+    /// # Returns
+    /// * The number of rows written.
+    /// # Errors
+    /// * If the range is empty or longer than `MAX_EXPORT_BLOCKS`, or the archive can't be
+    ///   read.
+    pub fn export(
+        &self,
+        table: ExportTable,
+        format: ExportFormat,
+        from: u64,
+        to: u64,
+        out: &mut (impl Write + Send),
+    ) -> Result<usize> {
+        check_range(from, to, MAX_EXPORT_BLOCKS)?;
+        let rows: Vec<_> = self
+            .read_range(from, to)?
+            .iter()
+            .flat_map(|archived| table_rows(table, &archived.block, &archived.receipts))
+            .collect();
+        write_rows(table, format, &rows, out)
+    }
+
+    /// First block of every segment, by ascending number.
+    fn segments(&self) -> Result<Vec<u64>> {
+        if !self.dir.is_dir() {
+            return Ok(Vec::new());
+        }
+        let mut segments: Vec<u64> = fs::read_dir(&self.dir)?
+            .filter_map(|entry| {
+                let name = entry.ok()?.file_name().into_string().ok()?;
+                let start = name.strip_prefix(SEGMENT_PREFIX)?;
+                let start = start
+                    .strip_suffix(COMPACTED_EXTENSION)
+                    .or_else(|| start.strip_suffix(PLAIN_EXTENSION))?;
+                start.parse().ok()
+            })
+            .collect();
+        segments.sort_unstable();
+        segments.dedup();
+        Ok(segments)
+    }
+
+    /// Read a segment, the compacted lines then the ones appended since, later copies of a
+    /// block replacing the earlier ones.
+    fn read_segment(&self, start: u64) -> Result<BTreeMap<u64, ArchivedBlock>> {
+        let mut blocks = BTreeMap::new();
+        for compacted in [true, false] {
+            let path = self.segment_path(start, compacted);
+            if !path.is_file() {
+                continue;
+            }
+            let bytes =
+                fs::read(&path).map_err(|err| eyre!("Failed to read {}: {err}", path.display()))?;
+            let bytes = match compacted {
+                true => decompress(&bytes)?,
+                false => bytes,
+            };
+            for line in bytes.split(|byte| *byte == b'\n') {
+                if line.is_empty() {
+                    continue;
+                }
+                let archived: ArchivedBlock = serde_json::from_slice(line)
+                    .map_err(|err| eyre!("Corrupted archive segment {}: {err}", path.display()))?;
+                blocks.insert(archived.block.block_number, archived);
+            }
+        }
+        Ok(blocks)
+    }
+
+    fn segment_path(&self, start: u64, compacted: bool) -> PathBuf {
+        let extension = match compacted {
+            true => COMPACTED_EXTENSION,
+            false => PLAIN_EXTENSION,
+        };
+        self.dir
+            .join(format!("{SEGMENT_PREFIX}{start:012}{extension}"))
+    }
+}
+
+/// First block of the segment of a block.
+fn segment_start(block_number: u64) -> u64 {
+    block_number - block_number % ARCHIVE_SEGMENT_BLOCKS
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn compress(bytes: &[u8]) -> Result<Vec<u8>> {
+    Ok(zstd::bulk::compress(bytes, COMPACTION_LEVEL)?)
+}
+
+/// zstd is not available in the browser, compacted segments are stored as is.
+#[cfg(target_arch = "wasm32")]
+fn compress(bytes: &[u8]) -> Result<Vec<u8>> {
+    Ok(bytes.to_vec())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn decompress(bytes: &[u8]) -> Result<Vec<u8>> {
+    Ok(zstd::stream::decode_all(bytes)?)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn decompress(bytes: &[u8]) -> Result<Vec<u8>> {
+    Ok(bytes.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use starknet::{
+        core::types::FieldElement,
+        providers::jsonrpc::models::{BlockStatus, L1HandlerTransaction, Transaction},
+    };
+
+    fn block(block_number: u64, block_hash: u64) -> BlockWithTxs {
+        BlockWithTxs {
+            status: BlockStatus::AcceptedOnL1,
+            block_hash: FieldElement::from(block_hash),
+            parent_hash: FieldElement::ZERO,
+            block_number,
+            new_root: FieldElement::ONE,
+            timestamp: 1_000_000,
+            sequencer_address: FieldElement::TWO,
+            transactions: vec![Transaction::L1Handler(L1HandlerTransaction {
+                transaction_hash: FieldElement::from(0x100 + block_number),
+                version: 0,
+                nonce: 0,
+                contract_address: FieldElement::ONE,
+                entry_point_selector: FieldElement::TWO,
+                calldata: vec![],
+            })],
+        }
+    }
+
+    fn temp_archive(name: &str) -> Archive {
+        let dir =
+            std::env::temp_dir().join(format!("beerus-archive-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        Archive::new(dir)
+    }
+
+    #[test]
+    fn test_compacted_segments_are_read_back_with_the_latest_copies() {
+        let archive = temp_archive("compact");
+        assert_eq!(archive.last_block_number().unwrap(), None);
+        for block_number in [998, 999, 1_000] {
+            archive
+                .append(&block(block_number, block_number), &[])
+                .unwrap();
+        }
+        // Archived again after a resync.
+        archive.append(&block(999, 0x999), &[]).unwrap();
+
+        assert_eq!(archive.compact().unwrap(), 1);
+        assert_eq!(archive.compact().unwrap(), 0);
+
+        let blocks = archive.read_range(998, 1_000).unwrap();
+        let hashes: Vec<FieldElement> = blocks
+            .iter()
+            .map(|archived| archived.block.block_hash)
+            .collect();
+        assert_eq!(
+            hashes,
+            vec![
+                FieldElement::from(998_u64),
+                FieldElement::from(0x999_u64),
+                FieldElement::from(1_000_u64)
+            ]
+        );
+        assert_eq!(archive.last_block_number().unwrap(), Some(1_000));
+        fs::remove_dir_all(archive.dir()).unwrap();
+    }
+
+    #[test]
+    fn test_export_csv_writes_a_row_per_transaction() {
+        let archive = temp_archive("export");
+        for block_number in 10..13 {
+            archive
+                .append(&block(block_number, block_number), &[])
+                .unwrap();
+        }
+
+        let mut csv = Vec::new();
+        let rows = archive
+            .export(
+                ExportTable::Transactions,
+                ExportFormat::Csv,
+                11,
+                20,
+                &mut csv,
+            )
+            .unwrap();
+
+        assert_eq!(rows, 2);
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "block_number,transaction_index,transaction_hash,type\n\
+             11,0,0x10b,l1_handler\n\
+             12,0,0x10c,l1_handler\n"
+        );
+        assert!(archive
+            .export(
+                ExportTable::Blocks,
+                ExportFormat::Csv,
+                0,
+                MAX_EXPORT_BLOCKS,
+                &mut Vec::new()
+            )
+            .is_err());
+        fs::remove_dir_all(archive.dir()).unwrap();
+    }
+}
@@ -1,39 +1,256 @@
-use std::{collections::BTreeMap, str::FromStr, sync::Arc, thread, time};
-use tokio::sync::RwLock;
+use arc_swap::ArcSwap;
+use std::{
+    cmp::Ordering as CmpOrdering,
+    collections::{BTreeMap, HashMap},
+    future::Future,
+    io::Write,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use tokio::sync::{broadcast, oneshot, watch, RwLock};
 
-use super::{ethereum::EthereumLightClient, starknet::StarkNetLightClient};
-use crate::{config::Config, ethers_helper};
+use super::{
+    abi::{AbiValue, ContractAbi},
+    alerts::{Alert, AlertHooks},
+    archive::Archive,
+    class_index::{class_declarations, ClassDeclaration},
+    contract_index::{contract_deployments, ContractDeployment},
+    ethereum::EthereumLightClient,
+    event_index::BlockEventIndex,
+    export::{check_range, table_rows, write_rows, ExportFormat, ExportTable},
+    memory::{self, MemoryUsage},
+    messaging::{
+        ConsumedMessage, L1ToL2Message, L1ToL2MessageDelivery, L1ToL2MessageDeposit,
+        L1ToL2MessageStatus, L2ToL1Message, L2ToL1MessageConsumption, MessageWatcher,
+        MAX_L1_TO_L2_MESSAGE_FEE,
+    },
+    payload::{BlockUsage, Payload, PayloadStats},
+    service::{
+        spawn_archiver, spawn_message_watcher, spawn_sync_tasks, spawn_upgrade_monitor,
+        ResyncTrigger, SyncTasksHealth,
+    },
+    starknet::{
+        block_hash::{
+            fetch_receipts, receipt_block_number, receipt_transaction_hash, transaction_hash,
+            verify_block_hash, verify_block_hash_with_receipts, BlockHashCheck,
+        },
+        data_availability::{
+            check_memory_page_fact, decode_memory_page_transaction,
+            decode_update_state_transaction, LocalState, StateDiff,
+        },
+        errors::{is_upstream_unavailable, StarknetError, StarknetErrorCode},
+        fee::{AnyBroadcastedTransaction, FeeEstimateWithUnit, SimulationFlag},
+        health::ProviderHealth,
+        inclusion_proof::TransactionProof,
+        storage_proof::{GetProofOutput, Membership},
+        StarkNetLightClient,
+    },
+    upgrade_monitor::{CoreContractUpgrade, UpgradeMonitor},
+};
+use crate::{
+    config::{BlockNumberSource, Config, Finality},
+    ethers_helper, runtime,
+    starknet_helper::block_id_string_to_block_id_type,
+};
 use ethers::{
-    abi::Abi,
-    types::{H160, U256},
+    abi::{Abi, RawLog, Token},
+    types::{Address, Log, H160, H256, U256},
+    utils::keccak256,
 };
 use eyre::Result;
+use futures::{future::try_join_all, try_join};
 use helios::types::{BlockTag, CallOpts};
-use log::{error, info, warn};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, value::RawValue, Value};
 use starknet::{
     core::types::FieldElement,
     providers::jsonrpc::models::{
         BlockHashAndNumber, BlockId, BlockStatus, BlockTag as StarknetBlockTag, BlockWithTxHashes,
-        BlockWithTxs, BroadcastedTransaction, DeclareTransaction, DeployAccountTransaction,
-        DeployTransaction, FeeEstimate, FunctionCall, InvokeTransaction, L1HandlerTransaction,
-        MaybePendingBlockWithTxHashes, MaybePendingBlockWithTxs, MaybePendingTransactionReceipt,
-        Transaction,
+        BlockWithTxs, BroadcastedTransaction, EmittedEvent, Event, EventFilter, EventsPage,
+        FeeEstimate, FunctionCall, MaybePendingBlockWithTxHashes, MaybePendingBlockWithTxs,
+        MaybePendingTransactionReceipt, PendingBlockWithTxHashes, Transaction,
     },
 };
 
 /// Enum representing the different synchronization status of the light client.
-#[derive(Debug, Clone, PartialEq)]
+/// Its transitions are published, see [`BeerusLightClient::subscribe_sync_status`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum SyncStatus {
+    /// The light clients are not started, the queries which need them are rejected.
     NotSynced,
-    Syncing,
+    /// The syncer catches up with the latest StarkNet block.
+    Syncing(SyncProgress),
+    /// The syncer follows the latest StarkNet block.
     Synced,
+    /// The StarkNet provider is unavailable, only what was verified so far is served.
+    Degraded,
+    /// The sync tasks stopped and await a restart, only what was verified so far is served.
+    Restarting,
+}
+
+/// JSON-RPC error code returned while the light client is not synced.
+pub const NOT_SYNCED_CODE: i32 = -32003;
+/// Prefix of the continuation tokens of the events answered from the event index.
+pub const LOCAL_EVENTS_TOKEN_PREFIX: &str = "beerus-";
+/// Maximum number of events of a page answered from the event index.
+pub const MAX_EVENTS_CHUNK_SIZE: u64 = 1024;
+/// Maximum number of blocks returned by [`BeerusLightClient::get_blocks_in_range`].
+pub const MAX_BLOCK_RANGE: u64 = 100;
+/// Maximum number of blocks exported by [`BeerusLightClient::export_blocks`], which holds them
+/// in memory.
+pub const MAX_FETCHED_EXPORT_BLOCKS: u64 = 10_000;
+/// Tag of the named storage slot of the StarkNet core contract holding the verifier address.
+pub const STARKNET_VERIFIER_ADDRESS_TAG: &str = "STARKNET_1.0_INIT_VERIFIER_ADDRESS";
+/// Time given to the syncer to refresh the payload after a state root mismatch.
+pub const STATE_ROOT_REFRESH_TIMEOUT: Duration = Duration::from_secs(10);
+/// Interval between two checks of the payload while it is refreshed.
+const STATE_ROOT_REFRESH_POLL: Duration = Duration::from_millis(200);
+
+/// The light client is not synced yet, the query can't be answered.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NotSynced {
+    pub status: SyncStatus,
+}
+
+impl std::fmt::Display for NotSynced {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Beerus is not synced yet, status: {:?}", self.status)
+    }
+}
+
+impl std::error::Error for NotSynced {}
+
+/// The state root of the last synced block differs from the last one proven on L1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateRootMismatch {
+    /// Last state root proven on L1.
+    pub l1_state_root: FieldElement,
+    /// StarkNet block the L1 state root was proven for.
+    pub l1_block_number: u64,
+    /// State root of the last block of the payload, `None` if nothing was synced.
+    pub local_state_root: Option<FieldElement>,
+    /// Number of the last block of the payload.
+    pub local_block_number: u64,
+}
+
+impl StateRootMismatch {
+    /// Whether the payload is ahead of L1, which only has to prove its last block.
+    pub fn is_ahead_of_l1(&self) -> bool {
+        self.local_block_number > self.l1_block_number
+    }
+
+    /// What to do about the mismatch, depending on which side is stale.
+    pub fn remedy(&self) -> &'static str {
+        match self.local_block_number.cmp(&self.l1_block_number) {
+            CmpOrdering::Greater => {
+                "the payload is ahead of L1, wait for L1 to prove its last block"
+            }
+            CmpOrdering::Less => {
+                "the payload is stale, check the StarkNet provider and resync"
+            }
+            CmpOrdering::Equal => {
+                "the StarkNet provider served a block L1 didn't prove, switch providers and resync from this block"
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for StateRootMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "State root mismatch: L1 proves {:#x} for block {}, ",
+            self.l1_state_root, self.l1_block_number
+        )?;
+        match &self.local_state_root {
+            Some(local_state_root) => write!(
+                f,
+                "the payload has {local_state_root:#x} for block {}",
+                self.local_block_number
+            )?,
+            None => write!(f, "the payload is empty")?,
+        }
+        write!(f, ", {}", self.remedy())
+    }
 }
 
+impl std::error::Error for StateRootMismatch {}
+
+/// The inclusion of a transaction fetched from the StarkNet provider couldn't be verified.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionNotVerified {
+    pub transaction_hash: FieldElement,
+    /// Block the provider claims includes the transaction, `None` if it is pending.
+    pub block_number: Option<u64>,
+    pub reason: String,
+}
+
+impl TransactionNotVerified {
+    pub fn new(
+        transaction_hash: FieldElement,
+        block_number: Option<u64>,
+        reason: impl Into<String>,
+    ) -> Self {
+        Self {
+            transaction_hash,
+            block_number,
+            reason: reason.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for TransactionNotVerified {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Transaction {:#x}", self.transaction_hash)?;
+        if let Some(block_number) = self.block_number {
+            write!(f, " of block {block_number}")?;
+        }
+        write!(f, " could not be verified: {}", self.reason)
+    }
+}
+
+impl std::error::Error for TransactionNotVerified {}
+
+/// Data of the node, published as immutable snapshots.
+/// The maps are shared between snapshots, so that cloning the node data is cheap and an update
+/// only copies the maps it changes.
 #[derive(Clone, Debug)]
 pub struct NodeData {
     pub block_number: u64,
     pub state_root: String,
-    pub payload: BTreeMap<u64, BlockWithTxs>,
+    pub payload: Payload,
+    /// Result of the block hash verification of the last fetched block.
+    pub last_block_hash_check: Option<BlockHashCheck>,
+    /// State roots proven on L1, by StarkNet block number.
+    pub proven_state_roots: Arc<BTreeMap<u64, FieldElement>>,
+    /// Events emitted by the blocks of the payload, by block number.
+    pub events: Arc<BTreeMap<u64, Arc<Vec<EmittedEvent>>>>,
+    /// Index of the events of the payload blocks, by block number.
+    pub event_index: Arc<BTreeMap<u64, Arc<BlockEventIndex>>>,
+    /// Receipts of the transactions of the blocks of the payload, by block number, cached when
+    /// first requested.
+    pub receipts: Arc<BTreeMap<u64, Arc<Vec<MaybePendingTransactionReceipt>>>>,
+    /// First declaration of each class declared by the synced blocks, by class hash.
+    /// Unlike the blocks, the declarations are kept when the payload is pruned.
+    pub class_declarations: Arc<HashMap<[u8; 32], ClassDeclaration>>,
+    /// Contracts deployed by the blocks of the payload, by address.
+    pub contract_deployments: Arc<HashMap<[u8; 32], ContractDeployment>>,
+    /// Availability of the StarkNet provider, as last observed by the syncer.
+    pub starknet_provider: ProviderStatus,
+    /// Catch-up progress, as last measured by the syncer.
+    pub sync_progress: SyncProgress,
+    /// Approximate memory the node data may use, in bytes, see
+    /// [`NodeData::enforce_memory_budget`]. Unbounded if `None`.
+    pub memory_budget: Option<usize>,
+    /// Blocks evicted to stay within the memory budget.
+    pub evicted_blocks: u64,
+    /// Blocks whose cached receipts were evicted to stay within the memory budget.
+    pub evicted_receipts: u64,
 }
 
 impl NodeData {
@@ -41,9 +258,390 @@ impl NodeData {
         NodeData {
             block_number: 0,
             state_root: "".to_string(),
-            payload: BTreeMap::new(),
+            payload: Payload::default(),
+            last_block_hash_check: None,
+            proven_state_roots: Arc::default(),
+            events: Arc::default(),
+            event_index: Arc::default(),
+            receipts: Arc::default(),
+            class_declarations: Arc::default(),
+            contract_deployments: Arc::default(),
+            starknet_provider: ProviderStatus::default(),
+            sync_progress: SyncProgress::default(),
+            memory_budget: None,
+            evicted_blocks: 0,
+            evicted_receipts: 0,
+        }
+    }
+
+    /// Return the range of blocks retained and the memory they use.
+    pub fn retention_stats(&self) -> RetentionStats {
+        RetentionStats {
+            first_block_number: self.payload.first_block_number(),
+            last_block_number: self.payload.last_block_number(),
+            event_blocks: self.events.len(),
+            proven_state_roots: self.proven_state_roots.len(),
+            payload_stats: self.payload.stats(),
+        }
+    }
+
+    /// Return the approximate memory used by the retained blocks, their events and receipts,
+    /// and the indexes.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let payload_bytes = self.payload.stored_bytes();
+        let events_bytes = self
+            .events
+            .values()
+            .map(|events| memory::events_bytes(events))
+            .sum();
+        let receipts_bytes = self
+            .receipts
+            .values()
+            .map(|receipts| memory::receipts_bytes(receipts))
+            .sum();
+        let indexes_bytes = self
+            .event_index
+            .values()
+            .map(|index| index.memory_bytes())
+            .sum::<usize>()
+            + memory::map_bytes::<[u8; 32], ClassDeclaration>(self.class_declarations.len())
+            + memory::map_bytes::<[u8; 32], ContractDeployment>(self.contract_deployments.len())
+            + memory::map_bytes::<u64, FieldElement>(self.proven_state_roots.len());
+        MemoryUsage {
+            payload_bytes,
+            events_bytes,
+            receipts_bytes,
+            indexes_bytes,
+            total_bytes: payload_bytes + events_bytes + receipts_bytes + indexes_bytes,
+            budget_bytes: self.memory_budget,
+            evicted_blocks: self.evicted_blocks,
+            evicted_receipts: self.evicted_receipts,
+        }
+    }
+
+    /// Evict data until the memory used fits in the budget, if one is set: the cached
+    /// receipts first, which are fetched again when queried, oldest block first, then the
+    /// oldest blocks with their events. The last synchronized block is always kept.
+    pub fn enforce_memory_budget(&mut self) {
+        let budget = match self.memory_budget {
+            Some(budget) => budget,
+            None => return,
+        };
+        let mut excess = self.memory_usage().total_bytes.saturating_sub(budget);
+        while excess > 0 {
+            let block_number = match self.receipts.keys().next() {
+                Some(block_number) => *block_number,
+                None => break,
+            };
+            if let Some(receipts) = Arc::make_mut(&mut self.receipts).remove(&block_number) {
+                excess = excess.saturating_sub(memory::receipts_bytes(&receipts));
+                self.evicted_receipts += 1;
+            }
+        }
+        let mut prune_to = None;
+        for usage in self.payload.block_usage() {
+            if excess == 0 || usage.block_number >= self.block_number {
+                break;
+            }
+            let events_bytes = self
+                .events
+                .get(&usage.block_number)
+                .map_or(0, |events| memory::events_bytes(events))
+                + self
+                    .event_index
+                    .get(&usage.block_number)
+                    .map_or(0, |index| index.memory_bytes());
+            excess = excess.saturating_sub(usage.stored_bytes + events_bytes);
+            prune_to = Some(usage.block_number + 1);
+        }
+        if let Some(block_number) = prune_to {
+            let evicted = self.prune_to(block_number);
+            self.evicted_blocks += evicted as u64;
+            info!("Evicted {evicted} blocks below block {block_number} to fit the memory budget");
+        }
+    }
+
+    /// Drop the blocks from `block_number` onwards, with their events, and go back to the last
+    /// block retained before it. The proven state roots are kept.
+    pub fn rewind_to(&mut self, block_number: u64) {
+        self.payload.truncate(block_number);
+        Arc::make_mut(&mut self.events).retain(|number, _| *number < block_number);
+        Arc::make_mut(&mut self.event_index).retain(|number, _| *number < block_number);
+        Arc::make_mut(&mut self.receipts).retain(|number, _| *number < block_number);
+        Arc::make_mut(&mut self.class_declarations)
+            .retain(|_, declaration| declaration.block_number < block_number);
+        Arc::make_mut(&mut self.contract_deployments)
+            .retain(|_, deployment| deployment.block_number < block_number);
+        let last_block = self
+            .payload
+            .last_block_number()
+            .and_then(|block_number| Some((block_number, self.payload.new_root(&block_number)?)));
+        match last_block {
+            Some((block_number, new_root)) => {
+                self.block_number = block_number;
+                self.state_root = new_root.to_string();
+            }
+            None => {
+                self.block_number = 0;
+                self.state_root = "".to_string();
+            }
+        }
+        if let Some(check) = &self.last_block_hash_check {
+            if check.block_number >= block_number {
+                self.last_block_hash_check = None;
+            }
+        }
+    }
+
+    /// Drop the blocks below `block_number`, with their events and proven state roots.
+    /// The last synchronized block and the last proven state root are always kept.
+    /// Returns the number of blocks dropped.
+    pub fn prune_to(&mut self, block_number: u64) -> usize {
+        let block_number = block_number.min(self.block_number);
+        retain_from(&mut self.events, &block_number);
+        retain_from(&mut self.event_index, &block_number);
+        retain_from(&mut self.receipts, &block_number);
+        Arc::make_mut(&mut self.contract_deployments)
+            .retain(|_, deployment| deployment.block_number >= block_number);
+        let proven_from = self
+            .last_proven_block_number()
+            .map_or(block_number, |last| last.min(block_number));
+        retain_from(&mut self.proven_state_roots, &proven_from);
+        self.payload.prune_to(block_number)
+    }
+
+    /// Status of the started light client, as last observed by the syncer.
+    pub fn sync_status(&self) -> SyncStatus {
+        if !self.starknet_provider.available {
+            SyncStatus::Degraded
+        } else if self.sync_progress.blocks_behind > 0 {
+            SyncStatus::Syncing(self.sync_progress.clone())
+        } else {
+            SyncStatus::Synced
+        }
+    }
+
+    /// Number of the last block whose state root was proven on L1, if any was observed.
+    pub fn last_proven_block_number(&self) -> Option<u64> {
+        self.proven_state_roots.keys().next_back().copied()
+    }
+
+    /// Number and state root of the last block proven on L1, if any was observed.
+    pub fn last_proven_state_root(&self) -> Option<(u64, FieldElement)> {
+        self.proven_state_roots
+            .iter()
+            .next_back()
+            .map(|(block_number, state_root)| (*block_number, *state_root))
+    }
+
+    /// Cache the receipts of a block, if the payload retains that block, within the memory
+    /// budget. Returns whether they were cached.
+    pub fn cache_receipts(
+        &mut self,
+        block_number: u64,
+        block_hash: FieldElement,
+        receipts: Arc<Vec<MaybePendingTransactionReceipt>>,
+    ) -> bool {
+        let retained = self.payload.get(&block_number);
+        if retained.map_or(false, |retained| retained.block_hash == block_hash) {
+            Arc::make_mut(&mut self.receipts).insert(block_number, receipts);
+            self.enforce_memory_budget();
+            return true;
+        }
+        false
+    }
+
+    /// Return the cached receipt of a transaction, looking at the most recent blocks first.
+    pub fn cached_receipt(&self, tx_hash: &FieldElement) -> Option<MaybePendingTransactionReceipt> {
+        self.receipts
+            .values()
+            .rev()
+            .flat_map(|receipts| receipts.iter())
+            .find(|receipt| receipt_transaction_hash(receipt).as_ref() == Some(tx_hash))
+            .cloned()
+    }
+
+    /// Record the events of a block along with their index.
+    pub fn insert_events(&mut self, block_number: u64, events: Arc<Vec<EmittedEvent>>) {
+        Arc::make_mut(&mut self.event_index)
+            .insert(block_number, Arc::new(BlockEventIndex::new(&events)));
+        Arc::make_mut(&mut self.events).insert(block_number, events);
+    }
+
+    /// Record the classes declared by a block, unless they were declared before.
+    pub fn insert_class_declarations(&mut self, block: &BlockWithTxs) {
+        for declaration in class_declarations(block) {
+            let class_hash = declaration.class_hash.to_bytes_be();
+            if !self.class_declarations.contains_key(&class_hash) {
+                Arc::make_mut(&mut self.class_declarations).insert(class_hash, declaration);
+            }
+        }
+    }
+
+    /// Return the first declaration of a class by the synced blocks, if any.
+    pub fn class_declaration(&self, class_hash: &FieldElement) -> Option<ClassDeclaration> {
+        let mut declaration = self
+            .class_declarations
+            .get(&class_hash.to_bytes_be())?
+            .clone();
+        declaration.proven = self
+            .last_proven_block_number()
+            .map_or(false, |proven| declaration.block_number <= proven);
+        Some(declaration)
+    }
+
+    /// Record the contracts deployed by a block, given the events it emitted.
+    pub fn insert_contract_deployments(&mut self, block: &BlockWithTxs, events: &[EmittedEvent]) {
+        for deployment in contract_deployments(block, events) {
+            let address = deployment.address.to_bytes_be();
+            if !self.contract_deployments.contains_key(&address) {
+                Arc::make_mut(&mut self.contract_deployments).insert(address, deployment);
+            }
         }
     }
+
+    /// Return the deployment of a contract by the blocks of the payload, if any.
+    pub fn contract_deployment(&self, address: &FieldElement) -> Option<ContractDeployment> {
+        let deployment = self.contract_deployments.get(&address.to_bytes_be())?;
+        Some(self.with_proven(deployment))
+    }
+
+    /// Return the contracts of a class deployed by the blocks of the payload, by block.
+    pub fn contracts_of_class(&self, class_hash: &FieldElement) -> Vec<ContractDeployment> {
+        let mut deployments: Vec<ContractDeployment> = self
+            .contract_deployments
+            .values()
+            .filter(|deployment| deployment.class_hash == *class_hash)
+            .map(|deployment| self.with_proven(deployment))
+            .collect();
+        deployments.sort_by_key(|deployment| deployment.block_number);
+        deployments
+    }
+
+    fn with_proven(&self, deployment: &ContractDeployment) -> ContractDeployment {
+        ContractDeployment {
+            proven: self
+                .last_proven_block_number()
+                .map_or(false, |proven| deployment.block_number <= proven),
+            ..deployment.clone()
+        }
+    }
+
+    /// Check the hash of a block fetched from the provider against the header retained for its
+    /// number, if any, in header-only mode.
+    pub fn check_header(&self, block_number: u64, block_hash: &FieldElement) -> Result<()> {
+        if !self.payload.is_headers_only() {
+            return Ok(());
+        }
+        match self.payload.block_hash(&block_number) {
+            Some(verified) if verified != *block_hash => Err(eyre::eyre!(
+                "Block {block_number} has hash {block_hash:#x}, but {verified:#x} was verified"
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Return a transaction of the payload, if its block is retained.
+    pub fn transaction(&self, tx_hash: &FieldElement) -> Option<Transaction> {
+        let (block_number, index) = self.payload.transaction_position(tx_hash)?;
+        self.payload
+            .get(&block_number)?
+            .transactions
+            .get(index)
+            .cloned()
+    }
+
+    /// Build the inclusion proof of a transaction of the payload, if its block and the events of
+    /// that block are retained.
+    pub fn transaction_proof(&self, tx_hash: &FieldElement) -> Option<TransactionProof> {
+        let (block_number, index) = self.payload.transaction_position(tx_hash)?;
+        let block = self.payload.get(&block_number)?;
+        let events: Vec<Event> = self
+            .events
+            .get(&block_number)?
+            .iter()
+            .map(|event| Event {
+                from_address: event.from_address,
+                keys: event.keys.clone(),
+                data: event.data.clone(),
+            })
+            .collect();
+        let l1_state_root = self.proven_state_roots.get(&block_number).copied();
+        TransactionProof::new(&block, &events, index, l1_state_root)
+    }
+
+    /// Return the events of the blocks from `from` to `to` included emitted by `address`, if
+    /// set, whose first key is one of `keys`, if set, by block and in emission order.
+    pub fn matching_events(
+        &self,
+        from: u64,
+        to: u64,
+        address: Option<&FieldElement>,
+        keys: Option<&[FieldElement]>,
+    ) -> Vec<&EmittedEvent> {
+        if from > to {
+            return vec![];
+        }
+        self.events
+            .range(from..=to)
+            .flat_map(|(block_number, events)| {
+                let positions = match self.event_index.get(block_number) {
+                    Some(index) => index.matching(address, keys),
+                    None => BlockEventIndex::new(events).matching(address, keys),
+                };
+                positions.into_iter().map(|position| &events[position])
+            })
+            .collect()
+    }
+
+    /// Return the retained block matching a block id, if any. Blocks are looked up by hash
+    /// through the hash index of the payload.
+    pub fn block(&self, block_id: &BlockId) -> Option<Arc<BlockWithTxs>> {
+        match block_id {
+            BlockId::Number(block_number) => self.payload.get(block_number),
+            BlockId::Hash(block_hash) => self.payload.get_by_hash(block_hash),
+            BlockId::Tag(StarknetBlockTag::Latest) => self.payload.get(&self.block_number),
+            BlockId::Tag(StarknetBlockTag::Pending) => None,
+        }
+    }
+
+    /// Return the first retained block which is not in the chain of a new block: its parent if
+    /// the hashes differ, or the block it replaces. The blocks from there onwards were reorged
+    /// out and must be dropped before the new block is inserted.
+    pub fn fork_point(&self, block: &BlockWithTxs) -> Option<u64> {
+        let forked = |block_number: u64, block_hash: &FieldElement| {
+            self.payload.contains_key(&block_number)
+                && self.payload.block_number_by_hash(block_hash) != Some(block_number)
+        };
+        match block.block_number.checked_sub(1) {
+            Some(parent_number) if forked(parent_number, &block.parent_hash) => Some(parent_number),
+            _ => forked(block.block_number, &block.block_hash).then_some(block.block_number),
+        }
+    }
+
+    /// Return the number of a block, if the payload retains it.
+    pub fn retained_block_number(&self, block_id: &BlockId) -> Option<u64> {
+        let block_number = match block_id {
+            BlockId::Number(block_number) => *block_number,
+            BlockId::Hash(block_hash) => self.payload.get_by_hash(block_hash)?.block_number,
+            BlockId::Tag(StarknetBlockTag::Latest) => self.block_number,
+            BlockId::Tag(StarknetBlockTag::Pending) => return None,
+        };
+        self.events
+            .contains_key(&block_number)
+            .then_some(block_number)
+    }
+
+    /// Whether the events of every block from `from` to `to` included are retained.
+    pub fn retains_events(&self, from: u64, to: u64) -> bool {
+        from <= to && self.events.range(from..=to).count() as u64 == to - from + 1
+    }
+
+    /// Return the retained blocks from `from` to `to` included, by ascending number.
+    /// Blocks which are not retained are skipped.
+    pub fn blocks_in_range(&self, from: u64, to: u64) -> Vec<Arc<BlockWithTxs>> {
+        self.payload.range(from..=to).collect()
+    }
 }
 
 impl Default for NodeData {
@@ -52,6 +650,338 @@ impl Default for NodeData {
     }
 }
 
+/// Transaction of a block along with its receipt.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionWithReceipt {
+    pub transaction: Transaction,
+    pub receipt: MaybePendingTransactionReceipt,
+}
+
+/// Block along with the receipts of its transactions, as returned by
+/// `starknet_getBlockWithReceipts`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockWithReceipts {
+    pub status: BlockStatus,
+    pub block_hash: FieldElement,
+    pub parent_hash: FieldElement,
+    pub block_number: u64,
+    pub new_root: FieldElement,
+    pub timestamp: u64,
+    pub sequencer_address: FieldElement,
+    pub transactions: Vec<TransactionWithReceipt>,
+}
+
+impl BlockWithReceipts {
+    /// Pair the transactions of a block with their receipts, in the same order.
+    pub fn new(block: &BlockWithTxs, receipts: &[MaybePendingTransactionReceipt]) -> Self {
+        Self {
+            status: block.status.clone(),
+            block_hash: block.block_hash,
+            parent_hash: block.parent_hash,
+            block_number: block.block_number,
+            new_root: block.new_root,
+            timestamp: block.timestamp,
+            sequencer_address: block.sequencer_address,
+            transactions: block
+                .transactions
+                .iter()
+                .zip(receipts)
+                .map(|(transaction, receipt)| TransactionWithReceipt {
+                    transaction: transaction.clone(),
+                    receipt: receipt.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Split the block back into its transactions and their receipts, in the same order.
+    pub fn into_parts(self) -> (BlockWithTxs, Vec<MaybePendingTransactionReceipt>) {
+        let (transactions, receipts) = self
+            .transactions
+            .into_iter()
+            .map(|with_receipt| (with_receipt.transaction, with_receipt.receipt))
+            .unzip();
+        let block = BlockWithTxs {
+            status: self.status,
+            block_hash: self.block_hash,
+            parent_hash: self.parent_hash,
+            block_number: self.block_number,
+            new_root: self.new_root,
+            timestamp: self.timestamp,
+            sequencer_address: self.sequencer_address,
+            transactions,
+        };
+        (block, receipts)
+    }
+}
+
+/// Iterator over the retained blocks proven on L1, by ascending number, see
+/// [`BeerusLightClient::proven_blocks`].
+/// It reads from the snapshot of the node data taken when it was created, so blocks synced or
+/// pruned meanwhile don't affect it.
+pub struct ProvenBlocks {
+    node: Arc<NodeData>,
+    next_block_number: u64,
+}
+
+impl Iterator for ProvenBlocks {
+    type Item = Arc<BlockWithTxs>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let last_proven = self.node.last_proven_block_number()?;
+        let block = self
+            .node
+            .payload
+            .range(self.next_block_number..=last_proven)
+            .next()?;
+        self.next_block_number = block.block_number + 1;
+        Some(block)
+    }
+}
+
+/// Status of the Beerus light client, as exposed by `beerus_status`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BeerusStatus {
+    pub sync_status: SyncStatus,
+    pub block_number: u64,
+    pub state_root: String,
+    pub last_block_hash_check: Option<BlockHashCheck>,
+    pub payload_stats: PayloadStats,
+    /// Approximate memory used by the node data, and the budget it is kept within.
+    #[serde(default)]
+    pub memory_usage: MemoryUsage,
+    pub starknet_provider: ProviderStatus,
+    pub sync_progress: SyncProgress,
+    /// Last upgrade of the StarkNet core contract observed since the start, if any.
+    pub core_contract_upgrade: Option<CoreContractUpgrade>,
+    /// Health of the upstream providers reached since the start.
+    #[serde(default)]
+    pub providers: Vec<ProviderHealth>,
+    /// Health of the sync tasks, as reported by their supervisor.
+    #[serde(default)]
+    pub sync_tasks: SyncTasksHealth,
+    /// Finality the `beerus_` queries are answered at when they don't set a block tag, which
+    /// `l1_accepted` and `l2_accepted` override per request.
+    #[serde(default)]
+    pub finality: Finality,
+}
+
+/// Availability of a provider, as observed by the syncer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProviderStatus {
+    /// Whether the last poll succeeded.
+    pub available: bool,
+    /// Number of consecutive failed polls.
+    pub failures: u64,
+    /// Error of the last failed poll, if the provider is unavailable.
+    pub error: Option<String>,
+}
+
+impl Default for ProviderStatus {
+    fn default() -> Self {
+        Self {
+            available: true,
+            failures: 0,
+            error: None,
+        }
+    }
+}
+
+impl ProviderStatus {
+    /// Record the result of a poll.
+    pub fn record<T, E: std::fmt::Display>(&self, result: &std::result::Result<T, E>) -> Self {
+        match result {
+            Ok(_) => Self::default(),
+            Err(err) => Self {
+                available: false,
+                failures: self.failures + 1,
+                error: Some(err.to_string()),
+            },
+        }
+    }
+}
+
+/// Throughput of the syncer while it catches up with the chains, and the resulting estimate
+/// of the time left until the light client holds the latest StarkNet block.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SyncProgress {
+    /// StarkNet blocks fetched per second.
+    pub blocks_per_sec: f64,
+    /// L1 blocks followed per second.
+    pub l1_slots_per_sec: f64,
+    /// StarkNet blocks left to fetch.
+    pub blocks_behind: u64,
+    /// Estimated seconds left to sync, `None` while the syncer makes no progress.
+    pub eta_secs: Option<u64>,
+}
+
+impl SyncProgress {
+    /// Measure the progress between two polls of the syncer.
+    /// # Arguments
+    /// * `elapsed_ms` - Time between the two polls.
+    /// * `blocks` - StarkNet blocks fetched meanwhile.
+    /// * `l1_slots` - L1 blocks followed meanwhile.
+    /// * `blocks_behind` - StarkNet blocks left to fetch.
+    pub fn measure(elapsed_ms: u64, blocks: u64, l1_slots: u64, blocks_behind: u64) -> Self {
+        let per_sec = |count: u64| match elapsed_ms {
+            0 => 0.0,
+            elapsed_ms => count as f64 * 1000.0 / elapsed_ms as f64,
+        };
+        let blocks_per_sec = per_sec(blocks);
+        let eta_secs = if blocks_behind == 0 {
+            Some(0)
+        } else if blocks_per_sec > 0.0 {
+            Some((blocks_behind as f64 / blocks_per_sec).ceil() as u64)
+        } else {
+            None
+        };
+        Self {
+            blocks_per_sec,
+            l1_slots_per_sec: per_sec(l1_slots),
+            blocks_behind,
+            eta_secs,
+        }
+    }
+}
+
+/// Blocks retained by the light client, as exposed by `admin_retentionStats`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionStats {
+    pub first_block_number: Option<u64>,
+    pub last_block_number: Option<u64>,
+    /// Number of blocks whose events are retained.
+    pub event_blocks: usize,
+    pub proven_state_roots: usize,
+    pub payload_stats: PayloadStats,
+}
+
+/// Snapshot of the node data, as exposed by `admin_dumpState`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StateDump {
+    pub block_number: u64,
+    pub state_root: String,
+    pub last_block_hash_check: Option<BlockHashCheck>,
+    pub proven_state_roots: BTreeMap<u64, FieldElement>,
+    /// Hashes of the retained blocks, by block number.
+    pub block_hashes: BTreeMap<u64, FieldElement>,
+    pub retention_stats: RetentionStats,
+}
+
+/// Response relayed from the StarkNet provider without any verification by Beerus.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Unverified<T> {
+    /// Always `false`, so that clients can't mistake the result for a verified one.
+    pub verified: bool,
+    pub result: T,
+}
+
+impl<T> Unverified<T> {
+    pub fn new(result: T) -> Self {
+        Self {
+            verified: false,
+            result,
+        }
+    }
+}
+
+/// Result of a method served in passthrough mode: parsed into its model when Beerus answers it,
+/// or relayed as the provider wrote it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Passthrough<T> {
+    Parsed(T),
+    Raw(Box<RawValue>),
+}
+
+/// Fields of a block relayed in passthrough mode which are checked against its header.
+#[derive(Deserialize)]
+struct RelayedBlockHeader {
+    block_number: Option<u64>,
+    block_hash: Option<String>,
+}
+
+/// Tag of the block proven on L1, accepted wherever a block tag is.
+pub const L1_ACCEPTED_TAG: &str = "l1_accepted";
+/// Tag of the latest block accepted on L2, an alias of `latest` for the `beerus_` queries.
+pub const L2_ACCEPTED_TAG: &str = "l2_accepted";
+
+/// Block the state queries of the `beerus_` namespace are answered at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockTarget {
+    /// Last block proven on L1. Results can be checked against the state root proven there.
+    #[default]
+    L1Accepted,
+    /// Latest block fetched from StarkNet. It is ahead of L1, so results can't be verified.
+    Latest,
+    /// Pending block of the StarkNet provider, relayed as is. Only nonces are served there, so
+    /// that wallets can send transactions back to back.
+    Pending,
+    /// Block whose state root, previously proven on L1, is the given one. Results are checked
+    /// against that root, so reads pinned to a checkpoint are reproducible.
+    StateRoot(FieldElement),
+}
+
+impl FromStr for BlockTarget {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s.starts_with("0x") {
+            return FieldElement::from_hex_be(s)
+                .map(BlockTarget::StateRoot)
+                .map_err(|_| eyre::eyre!("Invalid state root: {s}"));
+        }
+        match s.to_lowercase().as_str() {
+            L1_ACCEPTED_TAG => Ok(BlockTarget::L1Accepted),
+            "latest" | L2_ACCEPTED_TAG => Ok(BlockTarget::Latest),
+            "pending" => Ok(BlockTarget::Pending),
+            _ => Err(eyre::eyre!("Invalid block tag: {s}")),
+        }
+    }
+}
+
+impl From<Finality> for BlockTarget {
+    fn from(finality: Finality) -> Self {
+        match finality {
+            Finality::L2Accepted => BlockTarget::Latest,
+            Finality::L1Accepted => BlockTarget::L1Accepted,
+        }
+    }
+}
+
+/// Verification metadata attached to the responses of the `beerus_` namespace.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResponseMetadata {
+    /// Block the result was read at.
+    pub block_number: u64,
+    /// Last StarkNet block proven on L1 when the response was built, or the block of the
+    /// pinned state root.
+    pub proven_block_number: u64,
+    /// State root proven on L1 for that block.
+    pub state_root: FieldElement,
+    /// Whether the result was checked against `state_root` with a proof.
+    pub verified: bool,
+}
+
+/// Contract and storage proofs of a contract, as fetched from the StarkNet provider, along with
+/// the storage values they prove. The class hash and nonce of the contract are proven by the
+/// contract proof, through the contract data.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContractProof {
+    pub contract_address: FieldElement,
+    pub storage_keys: Vec<FieldElement>,
+    pub storage_values: Vec<FieldElement>,
+    #[serde(flatten)]
+    pub proof: GetProofOutput,
+}
+
+/// Result along with the metadata describing how it was obtained.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WithMetadata<T> {
+    pub result: T,
+    pub metadata: ResponseMetadata,
+}
+
 /// Beerus Light Client service.
 pub struct BeerusLightClient {
     /// Global configuration.
@@ -60,14 +990,32 @@ pub struct BeerusLightClient {
     pub ethereum_lightclient: Arc<RwLock<Box<dyn EthereumLightClient>>>,
     /// StarkNet light client.
     pub starknet_lightclient: Arc<Box<dyn StarkNetLightClient>>,
-    /// Sync status.
-    pub sync_status: SyncStatus,
+    /// Sync status, shared with the background start and the sync tasks, see
+    /// [`Self::await_synced`].
+    pub sync_status: Arc<watch::Sender<SyncStatus>>,
+    /// Health of the sync tasks, updated by their supervisor.
+    pub sync_tasks: Arc<Mutex<SyncTasksHealth>>,
     /// StarkNet core ABI.
     pub starknet_core_abi: Abi,
     /// StarkNet core contract address.
     pub starknet_core_contract_address: H160,
     // TODO: Add Payload data
-    pub node: Arc<RwLock<NodeData>>,
+    /// Last snapshot of the node data. Reads never wait on the sync tasks.
+    pub node: Arc<ArcSwap<NodeData>>,
+    /// StarkNet state rebuilt from the state diffs published on L1.
+    pub local_state: Arc<RwLock<LocalState>>,
+    /// Subscriptions to the consumption of L2 to L1 messages.
+    pub message_watcher: Arc<MessageWatcher>,
+    /// Wakes the syncer up to fetch the blocks again.
+    pub resync: Arc<ResyncTrigger>,
+    /// Upgrades of the StarkNet core contract observed on L1.
+    pub upgrade_monitor: Arc<UpgradeMonitor>,
+    /// Hooks fired on the events operators must act upon.
+    pub alerts: Arc<AlertHooks>,
+    /// Archive of the proven blocks, in archival mode.
+    pub archive: Option<Arc<Archive>>,
+    /// Set once a start is requested, so that the light clients are started once.
+    starting: AtomicBool,
 }
 
 impl BeerusLightClient {
@@ -87,115 +1035,494 @@ impl BeerusLightClient {
         // For now we assume that the ABI is valid and that the deserialization will never fail.
         let starknet_core_abi: Abi = serde_json::from_str(starknet_core_abi).unwrap();
         let starknet_core_contract_address = config.starknet_core_contract_address;
-        let node_raw = NodeData::new();
-        let node = Arc::new(RwLock::new(node_raw));
+        let node_raw = NodeData {
+            payload: Payload::new(config.payload_compression_level)
+                .with_headers_only(config.payload_headers_only),
+            memory_budget: config
+                .memory_budget_mb
+                .map(|mb| mb.saturating_mul(1024 * 1024)),
+            ..NodeData::new()
+        };
+        let node = Arc::new(ArcSwap::from_pointee(node_raw));
+        let local_state = Arc::new(RwLock::new(LocalState::new()));
+        // An invalid proxy already fails the creation of the StarkNet light client.
+        let alerts = Arc::new(AlertHooks::new(
+            &config.alerts,
+            config.http.build_client().unwrap_or_default(),
+        ));
+        let archive = config
+            .archive_mode
+            .then(|| Arc::new(Archive::new(config.data_layout().archive())));
 
         Self {
             config,
             ethereum_lightclient,
             starknet_lightclient,
-            sync_status: SyncStatus::NotSynced,
+            sync_status: Arc::new(watch::channel(SyncStatus::NotSynced).0),
+            sync_tasks: Arc::new(Mutex::new(SyncTasksHealth::default())),
             starknet_core_abi,
             starknet_core_contract_address,
             node,
+            local_state,
+            message_watcher: Arc::new(MessageWatcher::new()),
+            resync: Arc::new(ResyncTrigger::new()),
+            upgrade_monitor: Arc::new(UpgradeMonitor::new()),
+            alerts,
+            archive,
+            starting: AtomicBool::new(false),
         }
     }
 
     /// Start Beerus light client and synchronize with Ethereum and StarkNet.
+    /// Returns once the Ethereum light client is synced, see [`BeerusHandle::start_in_background`]
+    /// to start without waiting.
+    ///
+    /// [`BeerusHandle::start_in_background`]: super::service::BeerusHandle::start_in_background
     pub async fn start(&mut self) -> Result<()> {
-        if let SyncStatus::NotSynced = self.sync_status {
-            // Start the Ethereum light client.
-            self.ethereum_lightclient.write().await.start().await?;
-            // Start the StarkNet light client.
-            self.starknet_lightclient.start().await?;
-            self.sync_status = SyncStatus::Synced;
-            let ethereum_clone = self.ethereum_lightclient.clone();
-            let starknet_clone = self.starknet_lightclient.clone();
-            let node_clone = self.node.clone();
-
-            // Define function that will loop
-            let task = async move {
-                loop {
-                    let state_root = ethereum_clone
-                        .read()
-                        .await
-                        .starknet_state_root()
-                        .await
-                        .unwrap();
-
-                    let last_proven_block = ethereum_clone
-                        .read()
-                        .await
-                        .starknet_last_proven_block()
-                        .await
-                        .unwrap();
-
-                    // TODO: these logs don't get caught by the main thread
-                    info!("State Root: {state_root}");
-                    info!("Block Number: {last_proven_block}");
+        self.run_start().await
+    }
 
-                    match starknet_clone
-                        .get_block_with_txs(&BlockId::Tag(StarknetBlockTag::Latest))
-                        .await
-                    {
-                        Ok(block) => {
-                            println!("block: {:?}", block);
-                            let mut data = node_clone.write().await;
-                            match block {
-                                MaybePendingBlockWithTxs::Block(block) => {
-                                    // if block.block_number > data.block_number && block.block_number == last_proven_block
-                                    if block.block_number > data.block_number
-                                        && 0 < block.block_number
-                                    {
-                                        data.block_number = block.block_number;
-                                        data.state_root = block.new_root.to_string();
-                                        data.payload.insert(block.block_number, block);
-                                        info!("New Block Added to Payload:");
-                                        info!("Block Number {:?}", &data.block_number);
-                                        info!("Block Root {:?}", &data.state_root);
-                                    }
-                                }
-                                MaybePendingBlockWithTxs::PendingBlock(_) => {
-                                    warn!("Pending Block");
-                                }
-                            }
-                        }
-                        Err(err) => {
-                            error!("Error getting block: {}", err);
-                        }
-                    }
-                    //TODO: Make this configurable
-                    thread::sleep(time::Duration::from_secs(5));
-                }
-            };
-            // Spawn loop function
-            tokio::spawn(task);
-        };
+    /// Start the light clients and the sync tasks, unless already started.
+    pub(crate) async fn run_start(&self) -> Result<()> {
+        if self.starting.swap(true, Ordering::SeqCst) {
+            return Ok(());
+        }
+        if let Err(err) = self.start_lightclients().await {
+            self.starting.store(false, Ordering::SeqCst);
+            return Err(err);
+        }
+        self.sync_status
+            .send_replace(self.node.load().sync_status());
+        spawn_sync_tasks(
+            self.ethereum_lightclient.clone(),
+            self.starknet_lightclient.clone(),
+            self.node.clone(),
+            self.sync_status.clone(),
+            self.sync_tasks.clone(),
+            self.resync.clone(),
+            self.alerts.clone(),
+            self.config.prefetch_receipts,
+            self.config.alerts.sync_stall_secs,
+        );
+        spawn_message_watcher(
+            self.ethereum_lightclient.clone(),
+            self.starknet_core_abi.clone(),
+            self.starknet_core_contract_address,
+            self.message_watcher.clone(),
+        );
+        spawn_upgrade_monitor(
+            self.ethereum_lightclient.clone(),
+            self.starknet_core_abi.clone(),
+            self.starknet_core_contract_address,
+            self.upgrade_monitor.clone(),
+        );
+        if let Some(archive) = &self.archive {
+            spawn_archiver(
+                self.starknet_lightclient.clone(),
+                self.node.clone(),
+                archive.clone(),
+            );
+        }
         Ok(())
     }
 
+    async fn start_lightclients(&self) -> Result<()> {
+        // Start the Ethereum light client.
+        self.ethereum_lightclient.write().await.start().await?;
+        // Start the StarkNet light client.
+        self.starknet_lightclient.start().await
+    }
+
     /// Return the current synchronization status.
-    pub fn sync_status(&self) -> &SyncStatus {
-        &self.sync_status
+    pub fn sync_status(&self) -> SyncStatus {
+        self.sync_status.borrow().clone()
     }
 
-    /// Get the storage at a given address/key.
-    /// This function is used to get the storage at a given address and key.
-    ///
-    /// # Arguments
-    ///
-    /// * `contract_address` - The StarkNet contract address.
-    /// * `storage_key` - The storage key.
-    ///
-    /// # Returns
-    ///
-    /// `Ok(FieldElement)` if the operation was successful.
-    /// `Err(eyre::Report)` if the operation failed.
-    pub async fn starknet_get_storage_at(
-        &self,
-        contract_address: FieldElement,
-        storage_key: FieldElement,
-    ) -> Result<FieldElement> {
+    /// Return the health of the sync tasks.
+    pub fn sync_tasks_health(&self) -> SyncTasksHealth {
+        self.sync_tasks.lock().unwrap().clone()
+    }
+
+    /// Subscribe to the transitions of the sync status, from `NotSynced` to `Syncing` while the
+    /// syncer catches up, then `Synced`, or `Degraded` while the StarkNet provider is down and
+    /// `Restarting` while the sync tasks await a restart.
+    pub fn subscribe_sync_status(&self) -> watch::Receiver<SyncStatus> {
+        self.sync_status.subscribe()
+    }
+
+    /// Wait until the light clients are started, so that the queries which need them are
+    /// served. The syncer may still be catching up.
+    pub async fn await_synced(&self) {
+        let mut status = self.sync_status.subscribe();
+        while *status.borrow_and_update() == SyncStatus::NotSynced {
+            // The sender lives as long as the light client.
+            if status.changed().await.is_err() {
+                return;
+            }
+        }
+    }
+
+    /// Fail with [`NotSynced`] until the light clients are started, so that queries which need
+    /// the Ethereum light client don't wait on its sync.
+    pub fn ensure_synced(&self) -> Result<()> {
+        match self.sync_status() {
+            SyncStatus::NotSynced => Err(NotSynced {
+                status: SyncStatus::NotSynced,
+            }
+            .into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Return the status of the light client, including the result of the last block hash
+    /// verification.
+    pub async fn beerus_status(&self) -> BeerusStatus {
+        let node = self.node.load();
+        BeerusStatus {
+            sync_status: self.sync_status(),
+            block_number: node.block_number,
+            state_root: node.state_root.clone(),
+            last_block_hash_check: node.last_block_hash_check.clone(),
+            payload_stats: node.payload.stats(),
+            memory_usage: node.memory_usage(),
+            starknet_provider: node.starknet_provider.clone(),
+            sync_progress: node.sync_progress.clone(),
+            core_contract_upgrade: self.upgrade_monitor.last_upgrade(),
+            providers: self.starknet_lightclient.provider_health(),
+            sync_tasks: self.sync_tasks_health(),
+            finality: self.config.finality,
+        }
+    }
+
+    /// Subscribe to the upgrades of the StarkNet core contract, an implementation or program
+    /// hash change alters what L1 accepts and should be reviewed by operators.
+    pub fn subscribe_core_contract_upgrades(&self) -> watch::Receiver<Option<CoreContractUpgrade>> {
+        self.upgrade_monitor.subscribe()
+    }
+
+    /// Subscribe to the alerts on reorgs, state root mismatches, provider divergences and sync
+    /// stalls, the ones delivered to the configured hooks.
+    pub fn subscribe_alerts(&self) -> broadcast::Receiver<Alert> {
+        self.alerts.subscribe()
+    }
+
+    /// Return the block which first declared a class, among the blocks synced by the light
+    /// client, for audit tooling and explorers built on verified data.
+    /// # Arguments
+    /// * `class_hash` - The hash of the class.
+    /// # Returns
+    /// `Some(ClassDeclaration)` if a synced block declared the class, `None` otherwise.
+    pub fn get_class_declaration(&self, class_hash: &FieldElement) -> Option<ClassDeclaration> {
+        self.node.load().class_declaration(class_hash)
+    }
+
+    /// Return the deployment of a contract, among the blocks retained by the light client.
+    /// # Arguments
+    /// * `address` - The address of the contract.
+    /// # Returns
+    /// `Some(ContractDeployment)` if a retained block deployed the contract, `None` otherwise.
+    pub fn get_contract_deployment(&self, address: &FieldElement) -> Option<ContractDeployment> {
+        self.node.load().contract_deployment(address)
+    }
+
+    /// Return the contracts of a class deployed by the blocks retained by the light client.
+    /// # Arguments
+    /// * `class_hash` - The hash of the class.
+    /// # Returns
+    /// The deployments of the contracts of the class, by ascending block number.
+    pub fn get_contracts_of_class(&self, class_hash: &FieldElement) -> Vec<ContractDeployment> {
+        self.node.load().contracts_of_class(class_hash)
+    }
+
+    /// Return the proof that a transaction is included in a retained block, so that a third
+    /// party can check it without trusting the light client.
+    /// # Arguments
+    /// * `tx_hash` - The transaction hash.
+    /// # Returns
+    /// `Ok(TransactionProof)` if the block of the transaction is retained.
+    /// `Err(eyre::Report)` with `InvalidTransactionHash` otherwise.
+    pub fn get_transaction_proof(&self, tx_hash: &FieldElement) -> Result<TransactionProof> {
+        self.node.load().transaction_proof(tx_hash).ok_or_else(|| {
+            StarknetError::new(
+                StarknetErrorCode::InvalidTransactionHash,
+                format!("Transaction {tx_hash:#x} is not in a retained block"),
+            )
+            .into()
+        })
+    }
+
+    /// Return the range of blocks retained by the light client and the memory they use.
+    pub fn retention_stats(&self) -> RetentionStats {
+        self.node.load().retention_stats()
+    }
+
+    /// Return the memory used by each retained block, by ascending number.
+    pub fn block_usage(&self) -> Vec<BlockUsage> {
+        self.node.load().payload.block_usage()
+    }
+
+    /// Drop the blocks below a given block number, with their events and proven state roots.
+    /// # Arguments
+    /// * `block_number` - The first block number to keep.
+    /// # Returns
+    /// The number of blocks dropped.
+    pub fn prune_to(&self, block_number: u64) -> usize {
+        let mut pruned = 0;
+        self.node.rcu(|node| {
+            let mut node = NodeData::clone(node);
+            pruned = node.prune_to(block_number);
+            node
+        });
+        info!("Pruned {pruned} blocks below block {block_number}");
+        pruned
+    }
+
+    /// Return the retained blocks in a range, for indexers consuming blocks in bulk.
+    /// # Arguments
+    /// * `from` - The first block number of the range.
+    /// * `to` - The last block number of the range, included.
+    /// # Returns
+    /// `Ok(Vec<Arc<BlockWithTxs>>)` with the retained blocks of the range, by ascending number.
+    /// `Err(eyre::Report)` if the range is empty or longer than `MAX_BLOCK_RANGE` blocks.
+    pub fn get_blocks_in_range(&self, from: u64, to: u64) -> Result<Vec<Arc<BlockWithTxs>>> {
+        if from > to {
+            return Err(eyre::eyre!("Invalid block range {from} to {to}"));
+        }
+        if to - from >= MAX_BLOCK_RANGE {
+            return Err(StarknetError::new(
+                StarknetErrorCode::PageSizeTooBig,
+                format!("At most {MAX_BLOCK_RANGE} blocks can be requested at once"),
+            )
+            .into());
+        }
+        Ok(self.node.load().blocks_in_range(from, to))
+    }
+
+    /// Iterate over the retained blocks proven on L1, from a given block number.
+    /// The blocks synced after the iterator was created are not yielded.
+    pub fn proven_blocks(&self, from_block: u64) -> ProvenBlocks {
+        ProvenBlocks {
+            node: self.node.load_full(),
+            next_block_number: from_block,
+        }
+    }
+
+    /// Compact the retained blocks, see [`Payload::compact`].
+    /// # Returns
+    /// The number of blocks compressed.
+    pub fn compact(&self) -> usize {
+        let mut compressed = 0;
+        self.node.rcu(|node| {
+            let mut node = NodeData::clone(node);
+            compressed = node.payload.compact();
+            node
+        });
+        info!("Compacted the payload, {compressed} blocks compressed");
+        compressed
+    }
+
+    /// Export a table of the archived blocks from `from` to `to` included as CSV.
+    /// # Errors
+    /// * If archival mode is disabled, or the export failed, see [`Archive::export`].
+    pub fn export_archive(&self, table: ExportTable, from: u64, to: u64) -> Result<String> {
+        let archive = self
+            .archive
+            .as_ref()
+            .ok_or_else(|| eyre::eyre!("Archival mode is disabled, set ARCHIVE_MODE=true"))?;
+        let mut csv = Vec::new();
+        archive.export(table, ExportFormat::Csv, from, to, &mut csv)?;
+        Ok(String::from_utf8(csv)?)
+    }
+
+    /// Export a table of the blocks from `from` to `to` included, without archival mode.
+    /// Only blocks proven on L1 are exported, and the ones which are not retained are fetched
+    /// from StarkNet and checked against their hash, along with their receipts.
+    /// # Arguments
+    /// * `table` - The table to export.
+    /// * `format` - The format of the export.
+    /// * `from` - The first block number.
+    /// * `to` - The last block number.
+    /// * `out` - The output the table is written to.
+    /// # Returns
+    /// * The number of rows written.
+    /// # Errors
+    /// * If the range is longer than `MAX_FETCHED_EXPORT_BLOCKS` or not proven on L1, or a
+    ///   block can't be fetched or verified.
+    pub async fn export_blocks(
+        &self,
+        table: ExportTable,
+        format: ExportFormat,
+        from: u64,
+        to: u64,
+        out: &mut (impl Write + Send),
+    ) -> Result<usize> {
+        check_range(from, to, MAX_FETCHED_EXPORT_BLOCKS)?;
+        let last_proven = self.node.load().last_proven_block_number();
+        if last_proven.map_or(true, |last_proven| to > last_proven) {
+            return Err(eyre::eyre!("Block {to} is not proven on L1 yet"));
+        }
+
+        let mut rows = Vec::new();
+        for block_number in from..=to {
+            let (block, receipts) = self
+                .starknet_get_block_with_receipts(&BlockId::Number(block_number))
+                .await?
+                .into_parts();
+            self.node
+                .load()
+                .check_header(block_number, &block.block_hash)?;
+            verify_block_hash_with_receipts(&block, &receipts)?;
+            rows.extend(table_rows(table, &block, &receipts));
+        }
+        write_rows(table, format, &rows, out)
+    }
+
+    /// Recover from a corrupted payload or a misbehaving provider without restarting.
+    /// The retained blocks are dropped, or only the ones from `from_block` onwards, and the
+    /// syncer fetches them again, from `from_block` up to the latest block. The state roots
+    /// proven on L1 are kept.
+    /// # Arguments
+    /// * `from_block` - The first block to fetch again, only the latest block if `None`.
+    pub fn resync(&self, from_block: Option<u64>) {
+        self.node.rcu(|node| match from_block {
+            Some(from_block) => {
+                let mut node = NodeData::clone(node);
+                node.rewind_to(from_block);
+                node
+            }
+            None => NodeData {
+                proven_state_roots: node.proven_state_roots.clone(),
+                starknet_provider: node.starknet_provider.clone(),
+                payload: Payload::new(self.config.payload_compression_level)
+                    .with_headers_only(self.config.payload_headers_only),
+                memory_budget: node.memory_budget,
+                evicted_blocks: node.evicted_blocks,
+                evicted_receipts: node.evicted_receipts,
+                ..NodeData::new()
+            },
+        });
+        self.resync.request(from_block);
+        info!("Resync requested from block {from_block:?}");
+    }
+
+    /// Replace the StarkNet RPC endpoint serving the methods which are not routed elsewhere.
+    /// # Arguments
+    /// * `url` - The URL of the new endpoint.
+    /// # Returns
+    /// `Ok(())` if the endpoint was replaced.
+    /// `Err(eyre::Report)` if the URL is invalid.
+    pub fn rotate_starknet_provider(&self, url: &str) -> Result<()> {
+        self.starknet_lightclient.rotate_provider(url)?;
+        info!("StarkNet provider rotated");
+        Ok(())
+    }
+
+    /// Return a snapshot of the node data.
+    pub fn dump_state(&self) -> StateDump {
+        let node = self.node.load();
+        StateDump {
+            block_number: node.block_number,
+            state_root: node.state_root.clone(),
+            last_block_hash_check: node.last_block_hash_check.clone(),
+            proven_state_roots: BTreeMap::clone(&node.proven_state_roots),
+            block_hashes: node
+                .payload
+                .values()
+                .map(|block| (block.block_number, block.block_hash))
+                .collect(),
+            retention_stats: node.retention_stats(),
+        }
+    }
+
+    /// Return the state root proven on L1 for a given StarkNet block, if it was observed.
+    /// # Arguments
+    /// * `block_number` - The StarkNet block number.
+    /// # Returns
+    /// `Some(FieldElement)` with the proven state root, `None` if it is unknown.
+    pub async fn starknet_proven_state_root(&self, block_number: u64) -> Option<FieldElement> {
+        self.node
+            .load()
+            .proven_state_roots
+            .get(&block_number)
+            .copied()
+    }
+
+    /// Fetch the `LogStateUpdate` events emitted by the StarkNet core contract in a range of
+    /// L1 blocks and record the proven state roots.
+    /// # Arguments
+    /// * `from_block` - The first L1 block of the range.
+    /// * `to_block` - The last L1 block of the range.
+    /// # Returns
+    /// `Ok(usize)` with the number of recorded state roots.
+    /// `Err(eyre::Report)` if the operation failed.
+    pub async fn starknet_sync_proven_state_roots(
+        &self,
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<usize> {
+        let event = self.starknet_core_abi.event("LogStateUpdate")?;
+        let logs = self
+            .ethereum_lightclient
+            .read()
+            .await
+            .get_logs(
+                &Some(format!("{from_block:#x}")),
+                &Some(format!("{to_block:#x}")),
+                &Some(format!("{:?}", self.starknet_core_contract_address)),
+                &Some(vec![format!("{:?}", event.signature())]),
+                &None,
+            )
+            .await?;
+
+        let state_roots = logs
+            .iter()
+            .map(|log| self.parse_log_state_update(log))
+            .collect::<Result<Vec<_>>>()?;
+        self.node.rcu(|node| {
+            let mut node = NodeData::clone(node);
+            Arc::make_mut(&mut node.proven_state_roots).extend(state_roots.iter().copied());
+            node
+        });
+        Ok(logs.len())
+    }
+
+    /// Decode a `LogStateUpdate` event into a (block number, state root) pair.
+    fn parse_log_state_update(&self, log: &Log) -> Result<(u64, FieldElement)> {
+        let event = self.starknet_core_abi.event("LogStateUpdate")?;
+        let parsed = event.parse_log(RawLog {
+            topics: log.topics.clone(),
+            data: log.data.to_vec(),
+        })?;
+        match (
+            parsed.params.get(0).map(|p| &p.value),
+            parsed.params.get(1).map(|p| &p.value),
+        ) {
+            (Some(Token::Uint(state_root)), Some(Token::Int(block_number))) => {
+                let state_root = ethers_helper::u256_to_felt(*state_root)
+                    .map_err(|_| eyre::eyre!("Invalid state root: {}", state_root))?;
+                Ok((block_number.as_u64(), state_root))
+            }
+            _ => Err(eyre::eyre!("Invalid LogStateUpdate event")),
+        }
+    }
+
+    /// Get the storage at a given address/key.
+    /// This function is used to get the storage at a given address and key.
+    ///
+    /// # Arguments
+    ///
+    /// * `contract_address` - The StarkNet contract address.
+    /// * `storage_key` - The storage key.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(FieldElement)` if the operation was successful.
+    /// `Err(eyre::Report)` if the operation failed.
+    pub async fn starknet_get_storage_at(
+        &self,
+        contract_address: FieldElement,
+        storage_key: FieldElement,
+    ) -> Result<FieldElement> {
         let last_block = self
             .ethereum_lightclient
             .read()
@@ -245,6 +1572,34 @@ impl BeerusLightClient {
         self.starknet_lightclient.call(opts, last_block).await
     }
 
+    /// Call a view function of a StarkNet contract by name.
+    /// The arguments are encoded, and the result decoded, following the types of the ABI.
+    ///
+    /// # Arguments
+    /// * `contract_address` - The StarkNet contract address.
+    /// * `abi` - The ABI of the contract, Cairo 0 or Sierra.
+    /// * `function` - The name of the function.
+    /// * `args` - The arguments, one per input of the function.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(Vec<AbiValue>)` with one value per output of the function.
+    /// `Err(eyre::Report)` if the arguments don't match the ABI, or the call failed.
+    pub async fn starknet_call_function(
+        &self,
+        contract_address: FieldElement,
+        abi: &ContractAbi,
+        function: &str,
+        args: &[AbiValue],
+    ) -> Result<Vec<AbiValue>> {
+        let selector = abi.selector(function)?;
+        let calldata = abi.encode(function, args)?;
+        let result = self
+            .starknet_call_contract(contract_address, selector, calldata)
+            .await?;
+        abi.decode(function, &result)
+    }
+
     /// Estimate the fee for a given StarkNet transaction
     /// This function is used to estimate the fee for a given StarkNet transaction.
     ///
@@ -267,6 +1622,87 @@ impl BeerusLightClient {
             .await
     }
 
+    /// Estimate the fees of a list of StarkNet transactions, executed in order on top of a block.
+    ///
+    /// # Arguments
+    /// * `requests` - The broadcasted transactions.
+    /// * `simulation_flags` - The simulation flags, e.g. `SKIP_VALIDATE`.
+    /// * `block_id` - The block identifier.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(Vec<FeeEstimate>)` with one estimate per transaction if the operation was successful.
+    /// `Err(eyre::Report)` if the operation failed.
+    pub async fn starknet_estimate_fee_bulk(
+        &self,
+        requests: Vec<BroadcastedTransaction>,
+        simulation_flags: Vec<SimulationFlag>,
+        block_id: &BlockId,
+    ) -> Result<Vec<FeeEstimate>> {
+        let expected = requests.len();
+        let estimates = self
+            .starknet_lightclient
+            .estimate_fee_bulk(requests, simulation_flags, block_id)
+            .await?;
+        if estimates.len() != expected {
+            return Err(eyre::eyre!(
+                "Expected {} fee estimates, got {}",
+                expected,
+                estimates.len()
+            ));
+        }
+        Ok(estimates)
+    }
+
+    /// Estimate the fees of a list of StarkNet transactions of any version, along with the
+    /// unit of each fee: fri for v3 transactions, which pay in STRK, wei for the older ones.
+    /// Lists without v3 transactions are estimated as by [`Self::starknet_estimate_fee_bulk`].
+    ///
+    /// # Arguments
+    /// * `requests` - The broadcasted transactions.
+    /// * `simulation_flags` - The simulation flags, e.g. `SKIP_VALIDATE`.
+    /// * `block_id` - The block identifier.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(Vec<FeeEstimateWithUnit>)` with one estimate per transaction if the operation was
+    /// successful.
+    /// `Err(eyre::Report)` if the operation failed.
+    pub async fn starknet_estimate_fee_any(
+        &self,
+        requests: Vec<AnyBroadcastedTransaction>,
+        simulation_flags: Vec<SimulationFlag>,
+        block_id: &BlockId,
+    ) -> Result<Vec<FeeEstimateWithUnit>> {
+        let legacy = requests
+            .iter()
+            .map(|request| match request {
+                AnyBroadcastedTransaction::Legacy(request) => Some(request.clone()),
+                AnyBroadcastedTransaction::V3(_) => None,
+            })
+            .collect::<Option<Vec<_>>>();
+        if let Some(legacy) = legacy {
+            let estimates = self
+                .starknet_estimate_fee_bulk(legacy, simulation_flags, block_id)
+                .await?;
+            return Ok(estimates.into_iter().map(Into::into).collect());
+        }
+
+        let expected = requests.len();
+        let estimates = self
+            .starknet_lightclient
+            .estimate_fee_any(requests, simulation_flags, block_id)
+            .await?;
+        if estimates.len() != expected {
+            return Err(eyre::eyre!(
+                "Expected {} fee estimates, got {}",
+                expected,
+                estimates.len()
+            ));
+        }
+        Ok(estimates)
+    }
+
     /// Get the nonce at a given address.
     /// This function is used to get the nonce at a given address.
     ///
@@ -329,7 +1765,7 @@ impl BeerusLightClient {
             .await
             .call(&call_opts, BlockTag::Latest)
             .await?;
-        Ok(U256::from_big_endian(&call_response))
+        ethers_helper::bytes_to_u256(&call_response)
     }
 
     /// Return the msg_fee + 1 from the L1ToL2Message hash'. 0 if there is no matching msg_hash
@@ -369,7 +1805,7 @@ impl BeerusLightClient {
             .await
             .call(&call_opts, BlockTag::Latest)
             .await?;
-        Ok(U256::from_big_endian(&call_response))
+        ethers_helper::bytes_to_u256(&call_response)
     }
 
     ///  Returns the msg_fee + 1 for the message with the given 'msgHash', or 0 if no message with such a hash is pending.
@@ -409,7 +1845,237 @@ impl BeerusLightClient {
             .await
             .call(&call_opts, BlockTag::Latest)
             .await?;
-        Ok(U256::from_big_endian(&call_response))
+        ethers_helper::bytes_to_u256(&call_response)
+    }
+
+    /// Prepare the consumption of an L2 to L1 message on L1.
+    /// Checks that the message is ready with `l2ToL1Messages` and builds the
+    /// `consumeMessageFromL2` calldata the L1 contract has to send to the StarkNet core contract.
+    /// # Arguments
+    /// * `message` - The L2 to L1 message.
+    /// * `estimate_gas` - Whether to estimate the gas of the `consumeMessageFromL2` call, sent
+    /// from the L1 contract. Only done when the message is ready.
+    /// # Returns
+    /// `Ok(L2ToL1MessageConsumption)` if the operation was successful.
+    /// `Err(eyre::Report)` if the operation failed.
+    pub async fn prepare_l2_to_l1_message_consumption(
+        &self,
+        message: &L2ToL1Message,
+        estimate_gas: bool,
+    ) -> Result<L2ToL1MessageConsumption> {
+        let message_hash = message.hash();
+        let pending_count = self
+            .starknet_l2_to_l1_messages(ethers_helper::h256_to_u256(message_hash))
+            .await?;
+        let ready = !pending_count.is_zero();
+        let calldata = message.consume_calldata(self.starknet_core_abi.clone())?;
+
+        let gas_estimate = match ready && estimate_gas {
+            true => {
+                let call_opts = CallOpts {
+                    from: Some(message.to_address),
+                    to: self.starknet_core_contract_address,
+                    gas: None,
+                    gas_price: None,
+                    value: None,
+                    data: Some(calldata.to_vec()),
+                };
+                Some(
+                    self.ethereum_lightclient
+                        .read()
+                        .await
+                        .estimate_gas(&call_opts)
+                        .await?,
+                )
+            }
+            false => None,
+        };
+
+        Ok(L2ToL1MessageConsumption {
+            message_hash,
+            ready,
+            pending_count,
+            to: self.starknet_core_contract_address,
+            calldata,
+            gas_estimate,
+        })
+    }
+
+    /// Wait for an L2 to L1 message to be consumed on L1.
+    /// The returned receiver resolves once a `ConsumedMessageToL1` event is seen for the
+    /// message, after the light client was started. Earlier consumptions are not reported, the
+    /// readiness of the message can be checked first with
+    /// [`prepare_l2_to_l1_message_consumption`](Self::prepare_l2_to_l1_message_consumption).
+    /// # Arguments
+    /// * `message` - The L2 to L1 message.
+    /// # Returns
+    /// The receiver of the consumption.
+    pub fn watch_l2_to_l1_message(
+        &self,
+        message: &L2ToL1Message,
+    ) -> oneshot::Receiver<ConsumedMessage> {
+        self.message_watcher.subscribe(message.hash())
+    }
+
+    /// Prepare an L1 to L2 message: build the `sendMessageToL2` calldata and compute the
+    /// hash the message will have, using the current nonce of the StarkNet core contract.
+    /// The nonce is only valid if no other message is sent in between, the hash should be
+    /// checked against the one returned by the L1 transaction.
+    /// # Arguments
+    /// * `from_address` - The L1 address sending the message.
+    /// * `to_address` - The StarkNet contract receiving the message.
+    /// * `selector` - Selector of the L1 handler.
+    /// * `payload` - The message payload.
+    /// * `fee` - The L1 handler fee, paid as the value of the L1 transaction.
+    /// # Returns
+    /// `Ok(L1ToL2MessageDeposit)` if the operation was successful.
+    /// `Err(eyre::Report)` if the fee is out of bounds or the operation failed.
+    pub async fn prepare_l1_to_l2_message(
+        &self,
+        from_address: Address,
+        to_address: FieldElement,
+        selector: FieldElement,
+        payload: Vec<FieldElement>,
+        fee: U256,
+    ) -> Result<L1ToL2MessageDeposit> {
+        if fee.is_zero() || fee > U256::from(MAX_L1_TO_L2_MESSAGE_FEE) {
+            return Err(eyre::eyre!(
+                "Invalid L1 to L2 message fee {fee}, it must be positive and at most {MAX_L1_TO_L2_MESSAGE_FEE} wei"
+            ));
+        }
+        let message = L1ToL2Message {
+            from_address,
+            to_address,
+            selector,
+            payload,
+            nonce: self.starknet_l1_to_l2_message_nonce().await?,
+        };
+        Ok(L1ToL2MessageDeposit {
+            message_hash: message.hash(),
+            to: self.starknet_core_contract_address,
+            calldata: message.send_calldata(self.starknet_core_abi.clone())?,
+            value: fee,
+            message,
+        })
+    }
+
+    /// Look for the L1 handler transaction delivering an L1 to L2 message in the payload.
+    /// # Arguments
+    /// * `message` - The L1 to L2 message, with its nonce.
+    /// # Returns
+    /// `Some(L1ToL2MessageDelivery)` if the message landed in a synced block, `None` otherwise.
+    pub fn find_l1_to_l2_message_delivery(
+        &self,
+        message: &L1ToL2Message,
+    ) -> Option<L1ToL2MessageDelivery> {
+        let node = self.node.load();
+        node.payload.values().find_map(|block| {
+            block
+                .transactions
+                .iter()
+                .find_map(|transaction| match transaction {
+                    Transaction::L1Handler(transaction) if message.is_delivered_by(transaction) => {
+                        Some(L1ToL2MessageDelivery {
+                            block_number: block.block_number,
+                            transaction_hash: transaction.transaction_hash,
+                        })
+                    }
+                    _ => None,
+                })
+        })
+    }
+
+    /// Return the messages an L1 transaction sent to StarkNet with `sendMessageToL2`, along
+    /// with the L1 handler transactions delivering them, so that bridges can follow a deposit
+    /// end to end.
+    /// The messages are decoded from the `LogMessageToL2` events of the L1 transaction. Their
+    /// delivery is looked up in the retained blocks, then asked to the StarkNet provider by the
+    /// hash of the L1 handler transaction, and only reported once its block is verified.
+    /// # Arguments
+    /// * `l1_tx_hash` - Hash of the L1 transaction.
+    /// # Returns
+    /// `Ok(Vec<L1ToL2MessageStatus>)` with a status per message sent, in emission order.
+    /// `Err(eyre::Report)` if the L1 transaction is unknown or pending, or the operation failed.
+    pub async fn get_l1_to_l2_message_status(
+        &self,
+        l1_tx_hash: H256,
+    ) -> Result<Vec<L1ToL2MessageStatus>> {
+        let signature = self.starknet_core_abi.event("LogMessageToL2")?.signature();
+        let logs = {
+            let ethereum_lightclient = self.ethereum_lightclient.read().await;
+            let transaction = ethereum_lightclient
+                .get_transaction_by_hash(&l1_tx_hash)
+                .await?
+                .ok_or_else(|| eyre::eyre!("L1 transaction {l1_tx_hash:?} not found"))?;
+            let block_hash = transaction
+                .block_hash
+                .ok_or_else(|| eyre::eyre!("L1 transaction {l1_tx_hash:?} is pending"))?;
+            ethereum_lightclient
+                .get_logs(
+                    &None,
+                    &None,
+                    &Some(format!("{:?}", self.starknet_core_contract_address)),
+                    &Some(vec![format!("{signature:?}")]),
+                    &Some(format!("{block_hash:?}")),
+                )
+                .await?
+        };
+        let messages = logs
+            .iter()
+            .filter(|log| log.transaction_hash == Some(l1_tx_hash))
+            .map(|log| L1ToL2Message::from_log(&self.starknet_core_abi, log))
+            .collect::<Result<Vec<_>>>()?;
+        if messages.is_empty() {
+            return Ok(vec![]);
+        }
+        let chain_id = self.starknet_lightclient.chain_id().await?;
+        try_join_all(
+            messages
+                .into_iter()
+                .map(|message| self.l1_to_l2_message_status(message, l1_tx_hash, chain_id)),
+        )
+        .await
+    }
+
+    /// Look for the delivery of a message sent by an L1 transaction, see
+    /// [`Self::get_l1_to_l2_message_status`].
+    async fn l1_to_l2_message_status(
+        &self,
+        message: L1ToL2Message,
+        l1_tx_hash: H256,
+        chain_id: FieldElement,
+    ) -> Result<L1ToL2MessageStatus> {
+        let l2_transaction_hash = message.l1_handler_transaction_hash(chain_id)?;
+        let delivery = match self.find_l1_to_l2_message_delivery(&message) {
+            Some(delivery) => Some(delivery),
+            None => match self.fetch_verified_transaction(l2_transaction_hash).await {
+                Ok((block_number, Transaction::L1Handler(transaction)))
+                    if message.is_delivered_by(&transaction) =>
+                {
+                    Some(L1ToL2MessageDelivery {
+                        block_number,
+                        transaction_hash: transaction.transaction_hash,
+                    })
+                }
+                Ok(_) => None,
+                // Not delivered yet, or not in a block proven on L1 yet.
+                Err(err)
+                    if err.downcast_ref::<TransactionNotVerified>().is_some()
+                        || err.downcast_ref::<StarknetError>().map(|err| err.code)
+                            == Some(StarknetErrorCode::InvalidTransactionHash) =>
+                {
+                    None
+                }
+                Err(err) => return Err(err),
+            },
+        };
+        Ok(L1ToL2MessageStatus {
+            message_hash: message.hash(),
+            message,
+            l1_transaction_hash: l1_tx_hash,
+            l2_transaction_hash,
+            delivery,
+        })
     }
 
     /// Return the nonce for the L1ToL2Message bridge.
@@ -444,165 +2110,1469 @@ impl BeerusLightClient {
             .await
             .call(&call_opts, BlockTag::Latest)
             .await?;
-        Ok(U256::from_big_endian(&call_response))
+        ethers_helper::bytes_to_u256(&call_response)
     }
 
-    /// Return block hash and number of latest block.
-    /// See https://github.com/starknet-io/starknet-addresses for the StarkNet core contract address on different networks.
-    /// # Arguments
-    /// None
+    /// Return the hash of the Cairo program whose proofs the StarkNet core contract accepts.
+    /// A change of the program hash is an upgrade of the StarkNet OS.
     /// # Returns
-    /// `Ok(BlockHashAndNumber)` if the operation was successful.
+    /// `Ok(U256)` if the operation was successful.
     /// `Err(eyre::Report)` if the operation failed.
-    pub async fn get_block_hash_and_number(&self) -> Result<BlockHashAndNumber> {
-        let cloned_node = self.node.read().await;
-        let payload = cloned_node.payload.clone();
+    pub async fn starknet_program_hash(&self) -> Result<U256> {
+        let call_response = self.call_starknet_core_contract("programHash").await?;
+        ethers_helper::bytes_to_u256(&call_response)
+    }
 
-        let block = payload.get(&cloned_node.block_number);
-        match block {
-            Some(block) => Ok(BlockHashAndNumber {
-                block_hash: block.block_hash,
-                block_number: block.block_number,
-            }),
-            _ => Err(eyre::eyre!("Block not found")),
-        }
+    /// Return the hash of the StarkNet OS configuration accepted by the core contract.
+    /// # Returns
+    /// `Ok(U256)` if the operation was successful.
+    /// `Err(eyre::Report)` if the operation failed.
+    pub async fn starknet_config_hash(&self) -> Result<U256> {
+        let call_response = self.call_starknet_core_contract("configHash").await?;
+        ethers_helper::bytes_to_u256(&call_response)
     }
 
-    /// Return transaction receipt of a transaction.
-    /// # Arguments
-    /// * `tx_hash` - The transaction hash as String.
+    /// Return whether the initialization of the StarkNet core contract is finalized, after
+    /// which its governors can no longer re-initialize it.
     /// # Returns
-    /// `Ok(MaybePendingTransactionReceipt)` if the operation was successful.
+    /// `Ok(bool)` if the operation was successful.
     /// `Err(eyre::Report)` if the operation failed.
-    pub async fn starknet_get_transaction_receipt(
-        &self,
-        tx_hash: String,
-    ) -> Result<MaybePendingTransactionReceipt> {
-        let cloned_node = self.node.read().await;
-        let state_root = self
-            .ethereum_lightclient
-            .read()
-            .await
-            .starknet_state_root()
-            .await?
-            .to_string();
+    pub async fn starknet_is_finalized(&self) -> Result<bool> {
+        let call_response = self.call_starknet_core_contract("isFinalized").await?;
+        Ok(!ethers_helper::bytes_to_u256(&call_response)?.is_zero())
+    }
 
-        if cloned_node.state_root != state_root {
-            return Err(eyre::eyre!("State root mismatch"));
-        }
+    /// Return whether the StarkNet core contract is frozen, in which case it accepts no state
+    /// update.
+    /// # Returns
+    /// `Ok(bool)` if the operation was successful.
+    /// `Err(eyre::Report)` if the operation failed.
+    pub async fn starknet_is_frozen(&self) -> Result<bool> {
+        let call_response = self.call_starknet_core_contract("isFrozen").await?;
+        Ok(!ethers_helper::bytes_to_u256(&call_response)?.is_zero())
+    }
 
-        let tx_hash_felt = FieldElement::from_hex_be(&tx_hash).unwrap();
-        let tx_receipt = self
-            .starknet_lightclient
-            .get_transaction_receipt(tx_hash_felt)
+    /// Return the delay, in seconds, between the start of the cancellation of an L1 to L2
+    /// message and its completion.
+    /// # Returns
+    /// `Ok(U256)` if the operation was successful.
+    /// `Err(eyre::Report)` if the operation failed.
+    pub async fn starknet_message_cancellation_delay(&self) -> Result<U256> {
+        let call_response = self
+            .call_starknet_core_contract("messageCancellationDelay")
             .await?;
-        Ok(tx_receipt)
+        ethers_helper::bytes_to_u256(&call_response)
     }
-    /// Return block with transaction hashes.
-    /// See https://github.com/starknet-io/starknet-addresses for the StarkNet core contract address on different networks.
-    /// # Arguments
-    /// BlockId
+
+    /// Return the version string the StarkNet core contract identifies itself with, such as
+    /// `StarkWare_Starknet_2022_4`.
     /// # Returns
-    /// `Ok(MaybePendingBlockWithTxHashes)` if the operation was successful.
+    /// `Ok(String)` if the operation was successful.
+    /// `Err(eyre::Report)` if the operation failed or the response is not a string.
+    pub async fn starknet_core_contract_version(&self) -> Result<String> {
+        let call_response = self.call_starknet_core_contract("identify").await?;
+        let tokens = self
+            .starknet_core_abi
+            .function("identify")?
+            .decode_output(&call_response)?;
+        match tokens.into_iter().next() {
+            Some(Token::String(version)) => Ok(version),
+            _ => Err(eyre::eyre!("Invalid identify response: {call_response:?}")),
+        }
+    }
+
+    /// Return the address of the verifier whose facts the StarkNet core contract accepts.
+    /// The core contract has no getter for it, so it is read from its named storage slot, and
+    /// the slot is verified by the Ethereum light client.
+    /// # Returns
+    /// `Ok(Address)` if the operation was successful.
     /// `Err(eyre::Report)` if the operation failed.
-    pub async fn get_block_with_tx_hashes(
-        &self,
+    pub async fn starknet_verifier_address(&self) -> Result<Address> {
+        let slot = H256::from(keccak256(STARKNET_VERIFIER_ADDRESS_TAG));
+        let value = self
+            .ethereum_get_storage_at(&self.starknet_core_contract_address, slot, BlockTag::Latest)
+            .await?;
+        ethers_helper::u256_to_address(value)
+    }
+
+    /// Call a view function without arguments of the StarkNet core contract, at the latest
+    /// block, and return the raw response.
+    async fn call_starknet_core_contract(&self, function_name: &str) -> Result<Vec<u8>> {
+        let data =
+            ethers_helper::encode_function_data((), self.starknet_core_abi.clone(), function_name)?;
+        let call_opts = CallOpts {
+            from: None,
+            to: self.starknet_core_contract_address,
+            gas: None,
+            gas_price: None,
+            value: None,
+            data: Some(data.to_vec()),
+        };
+        self.ethereum_lightclient
+            .read()
+            .await
+            .call(&call_opts, BlockTag::Latest)
+            .await
+    }
+
+    /// Return the balance of an Ethereum account, verified by the Ethereum light client.
+    /// # Arguments
+    /// * `address` - The Ethereum address.
+    /// * `block` - The block at which the balance is read.
+    /// # Returns
+    /// `Ok(U256)` with the balance in wei if the operation was successful.
+    /// `Err(eyre::Report)` if the operation failed.
+    pub async fn ethereum_get_balance(&self, address: &Address, block: BlockTag) -> Result<U256> {
+        self.ethereum_lightclient
+            .read()
+            .await
+            .get_balance(address, block)
+            .await
+    }
+
+    /// Return the current gas price on L1, in wei.
+    /// # Returns
+    /// `Ok(U256)` if the operation was successful.
+    /// `Err(eyre::Report)` if the operation failed.
+    pub async fn ethereum_gas_price(&self) -> Result<U256> {
+        self.ethereum_lightclient.read().await.get_gas_price().await
+    }
+
+    /// Return the priority fee per gas, in wei, for a transaction to be included on L1.
+    /// # Returns
+    /// `Ok(U256)` if the operation was successful.
+    /// `Err(eyre::Report)` if the operation failed.
+    pub async fn ethereum_max_priority_fee_per_gas(&self) -> Result<U256> {
+        self.ethereum_lightclient
+            .read()
+            .await
+            .get_priority_fee()
+            .await
+    }
+
+    /// Return the code of an Ethereum account, verified by the Ethereum light client.
+    /// # Arguments
+    /// * `address` - The Ethereum address.
+    /// * `block` - The block at which the code is read.
+    /// # Returns
+    /// `Ok(Vec<u8>)` with the bytecode, empty for externally owned accounts.
+    /// `Err(eyre::Report)` if the operation failed.
+    pub async fn ethereum_get_code(&self, address: &Address, block: BlockTag) -> Result<Vec<u8>> {
+        self.ethereum_lightclient
+            .read()
+            .await
+            .get_code(address, block)
+            .await
+    }
+
+    /// Return the value of a storage slot of an Ethereum account, verified by the Ethereum
+    /// light client against the state root of the block.
+    /// # Arguments
+    /// * `address` - The Ethereum address.
+    /// * `slot` - The storage slot.
+    /// * `block` - The block at which the slot is read.
+    /// # Returns
+    /// `Ok(U256)` if the operation was successful.
+    /// `Err(eyre::Report)` if the operation failed.
+    pub async fn ethereum_get_storage_at(
+        &self,
+        address: &Address,
+        slot: H256,
+        block: BlockTag,
+    ) -> Result<U256> {
+        self.ethereum_lightclient
+            .read()
+            .await
+            .get_storage_at(address, slot, block)
+            .await
+    }
+
+    /// Broadcast a signed Ethereum transaction through the Ethereum light client.
+    /// # Arguments
+    /// * `bytes` - The RLP encoded signed transaction.
+    /// # Returns
+    /// `Ok(H256)` with the transaction hash if the operation was successful.
+    /// `Err(eyre::Report)` if the operation failed.
+    pub async fn ethereum_send_raw_transaction(&self, bytes: &[u8]) -> Result<H256> {
+        self.ethereum_lightclient
+            .read()
+            .await
+            .send_raw_transaction(bytes)
+            .await
+    }
+
+    /// Return the logs matching a filter, verified by the Ethereum light client.
+    /// # Arguments
+    /// * `from_block` - First block of the range, a block tag or a hex block number.
+    /// * `to_block` - Last block of the range, a block tag or a hex block number.
+    /// * `address` - Address of the contract emitting the logs.
+    /// * `topics` - Topics of the logs, in order.
+    /// * `block_hash` - Hash of the block, exclusive with the block range.
+    /// # Returns
+    /// `Ok(Vec<Log>)` if the operation was successful.
+    /// `Err(eyre::Report)` if the operation failed.
+    pub async fn ethereum_get_logs(
+        &self,
+        from_block: &Option<String>,
+        to_block: &Option<String>,
+        address: &Option<String>,
+        topics: &Option<Vec<String>>,
+        block_hash: &Option<String>,
+    ) -> Result<Vec<Log>> {
+        self.ethereum_lightclient
+            .read()
+            .await
+            .get_logs(from_block, to_block, address, topics, block_hash)
+            .await
+    }
+
+    /// Return block hash and number of latest block.
+    /// Until the latest block has been fetched from StarkNet, the last block proven on L1 is
+    /// read from the StarkNet core contract instead.
+    /// See https://github.com/starknet-io/starknet-addresses for the StarkNet core contract address on different networks.
+    /// # Arguments
+    /// None
+    /// # Returns
+    /// `Ok(BlockHashAndNumber)` if the operation was successful.
+    /// `Err(eyre::Report)` if the operation failed.
+    pub async fn get_block_hash_and_number(&self) -> Result<BlockHashAndNumber> {
+        let block_hash = {
+            let node = self.node.load();
+            node.payload
+                .block_hash(&node.block_number)
+                .map(|block_hash| (block_hash, node.block_number))
+        };
+        match block_hash {
+            Some((block_hash, block_number)) => Ok(BlockHashAndNumber {
+                block_hash,
+                block_number,
+            }),
+            None => self.l1_block_hash_and_number().await,
+        }
+    }
+
+    /// Return block hash and number of the last block proven on L1.
+    /// The block is read from the payload if it retains the last proven block observed by the
+    /// syncer, from the StarkNet core contract otherwise.
+    /// # Returns
+    /// `Ok(BlockHashAndNumber)` if the operation was successful.
+    /// `Err(eyre::Report)` if the operation failed.
+    pub async fn get_proven_block_hash_and_number(&self) -> Result<BlockHashAndNumber> {
+        let block_hash = {
+            let node = self.node.load();
+            node.last_proven_block_number().and_then(|block_number| {
+                Some((node.payload.block_hash(&block_number)?, block_number))
+            })
+        };
+        match block_hash {
+            Some((block_hash, block_number)) => Ok(BlockHashAndNumber {
+                block_hash,
+                block_number,
+            }),
+            None => self.l1_block_hash_and_number().await,
+        }
+    }
+
+    /// Read the hash and number of the last block proven on L1 from the StarkNet core contract.
+    async fn l1_block_hash_and_number(&self) -> Result<BlockHashAndNumber> {
+        let ethereum_lightclient = self.ethereum_lightclient.read().await;
+        let (block_number, block_hash) = try_join!(
+            ethereum_lightclient.starknet_last_proven_block(),
+            ethereum_lightclient.starknet_last_proven_block_hash()
+        )?;
+        let block_hash = ethers_helper::u256_to_felt(block_hash)
+            .map_err(|_| eyre::eyre!("Invalid block hash: {}", block_hash))?;
+        Ok(BlockHashAndNumber {
+            block_hash,
+            block_number: block_number.as_u64(),
+        })
+    }
+
+    /// Read the last block proven on L1 and the state root stored along with it, at the same
+    /// L1 block.
+    async fn l1_proven_block_and_state_root(&self) -> Result<(U256, U256)> {
+        self.ethereum_lightclient
+            .read()
+            .await
+            .starknet_proven_state()
+            .await
+    }
+
+    /// Fan a query out alongside the read of the state proven on L1, for the queries which
+    /// need both and don't depend on one another.
+    /// # Arguments
+    /// * `query` - The query, typically of the StarkNet provider.
+    /// # Returns
+    /// `Ok(((u64, FieldElement), T))` with the last proven block, its state root and the result
+    /// of the query.
+    /// `Err(eyre::Report)` if either failed.
+    async fn with_l1_proven_state<T>(
+        &self,
+        query: impl Future<Output = Result<T>>,
+    ) -> Result<((u64, FieldElement), T)> {
+        try_join!(self.starknet_l1_proven_state(), query)
+    }
+
+    /// Return the latest block hash and number, from the source set by
+    /// `Config::block_number_source`: the last block proven on L1 by default, or the head of
+    /// the StarkNet provider. While the provider is unavailable, its head is replaced by the
+    /// payload or L1, see [`Self::get_block_hash_and_number`].
+    /// # Returns
+    /// `Ok(BlockHashAndNumber)` if the operation was successful.
+    /// `Err(eyre::Report)` if the operation failed.
+    pub async fn starknet_block_hash_and_number(&self) -> Result<BlockHashAndNumber> {
+        if self.config.block_number_source == BlockNumberSource::Proven {
+            return self.get_proven_block_hash_and_number().await;
+        }
+        match self.starknet_lightclient.block_hash_and_number().await {
+            Err(err) if is_upstream_unavailable(&err) => {
+                warn!("{err}, answering from the payload or L1");
+                self.get_block_hash_and_number().await
+            }
+            result => result,
+        }
+    }
+
+    /// Return the latest block number, from the same source as
+    /// [`Self::starknet_block_hash_and_number`].
+    /// # Returns
+    /// `Ok(u64)` if the operation was successful.
+    /// `Err(eyre::Report)` if the operation failed.
+    pub async fn starknet_block_number(&self) -> Result<u64> {
+        if self.config.block_number_source == BlockNumberSource::Proven {
+            return Ok(self.get_proven_block_hash_and_number().await?.block_number);
+        }
+        match self.starknet_lightclient.block_number().await {
+            Err(err) if is_upstream_unavailable(&err) => {
+                warn!("{err}, answering from the payload or L1");
+                Ok(self.get_block_hash_and_number().await?.block_number)
+            }
+            result => result,
+        }
+    }
+
+    /// Return the events matching a filter, by pages of `chunk_size` events.
+    /// Ranges of blocks whose events are retained are answered from the event index, the other
+    /// ones by the StarkNet provider. The continuation tokens of the pages answered locally
+    /// start with `LOCAL_EVENTS_TOKEN_PREFIX` and point to a block and to the number of its
+    /// matching events already returned, so that the pages stay consistent while new blocks
+    /// are synced.
+    /// # Arguments
+    /// * `filter` - The blocks, emitting contract and keys to match.
+    /// * `continuation_token` - The token returned with the previous page, if any.
+    /// * `chunk_size` - The maximum number of events of the page.
+    /// # Returns
+    /// `Ok(EventsPage)` if the operation was successful.
+    /// `Err(eyre::Report)` if the operation failed, with `PageSizeTooBig` if a page answered
+    /// locally is larger than `MAX_EVENTS_CHUNK_SIZE` events, and `InvalidContinuationToken`
+    /// if a local token is malformed or points to a block which is no longer retained.
+    pub async fn starknet_get_events(
+        &self,
+        filter: EventFilter,
+        continuation_token: Option<String>,
+        chunk_size: u64,
+    ) -> Result<EventsPage> {
+        let node = self.node.load_full();
+        let range = match (&filter.from_block, &filter.to_block) {
+            (Some(from), Some(to)) => node
+                .retained_block_number(from)
+                .zip(node.retained_block_number(to))
+                .filter(|(from, to)| node.retains_events(*from, *to)),
+            _ => None,
+        };
+        let local_token = continuation_token
+            .as_deref()
+            .and_then(|token| token.strip_prefix(LOCAL_EVENTS_TOKEN_PREFIX));
+        let (from, to) = match (range, local_token) {
+            (Some(range), _) if continuation_token.is_none() || local_token.is_some() => range,
+            (_, Some(_)) => return Err(invalid_continuation_token()),
+            _ => {
+                return self
+                    .starknet_lightclient
+                    .get_events(filter, continuation_token, chunk_size)
+                    .await
+            }
+        };
+        if chunk_size == 0 || chunk_size > MAX_EVENTS_CHUNK_SIZE {
+            return Err(StarknetError::new(
+                StarknetErrorCode::PageSizeTooBig,
+                format!("The chunk size must be between 1 and {MAX_EVENTS_CHUNK_SIZE}"),
+            )
+            .into());
+        }
+        let (from, skip) = match local_token {
+            Some(token) => match parse_local_events_token(token) {
+                Some((block_number, skip)) if (from..=to).contains(&block_number) => {
+                    (block_number, skip)
+                }
+                _ => return Err(invalid_continuation_token()),
+            },
+            None => (from, 0),
+        };
+
+        let events =
+            node.matching_events(from, to, filter.address.as_ref(), filter.keys.as_deref());
+        let start = skip.min(events.len());
+        let end = (start + chunk_size as usize).min(events.len());
+        // The next page resumes at the block of its first event, after the matching events of
+        // that block which were already returned.
+        let continuation_token = events.get(end).map(|next| {
+            let returned = events[..end]
+                .iter()
+                .filter(|event| event.block_number == next.block_number)
+                .count();
+            format!(
+                "{LOCAL_EVENTS_TOKEN_PREFIX}{}-{returned}",
+                next.block_number
+            )
+        });
+        Ok(EventsPage {
+            events: events[start..end]
+                .iter()
+                .map(|event| EmittedEvent::clone(event))
+                .collect(),
+            continuation_token,
+        })
+    }
+
+    /// Return transaction receipt of a transaction.
+    /// The receipts cached for the retained blocks are answered locally.
+    /// # Arguments
+    /// * `tx_hash` - The transaction hash.
+    /// # Returns
+    /// `Ok(MaybePendingTransactionReceipt)` if the operation was successful.
+    /// `Err(eyre::Report)` if the operation failed.
+    pub async fn starknet_get_transaction_receipt(
+        &self,
+        tx_hash: FieldElement,
+    ) -> Result<MaybePendingTransactionReceipt> {
+        if let Some(receipt) = self.node.load().cached_receipt(&tx_hash) {
+            return Ok(receipt);
+        }
+        self.check_state_root().await?;
+
+        let tx_receipt = self
+            .starknet_lightclient
+            .get_transaction_receipt(tx_hash)
+            .await?;
+        Ok(tx_receipt)
+    }
+
+    /// Check that the last synced block has the last state root proven on L1.
+    /// On a mismatch, the syncer is woken up and the check is run again on the refreshed
+    /// payload, for up to `STATE_ROOT_REFRESH_TIMEOUT`.
+    /// # Errors
+    /// * [`StateRootMismatch`] if the roots still differ.
+    async fn check_state_root(&self) -> Result<()> {
+        let mut mismatch = match self.state_root_mismatch().await? {
+            Some(mismatch) => mismatch,
+            None => return Ok(()),
+        };
+        // A refresh doesn't make L1 prove the block any sooner.
+        if mismatch.is_ahead_of_l1() {
+            return Err(mismatch.into());
+        }
+        warn!("{mismatch}, refreshing the payload");
+        self.resync.request(None);
+        let deadline = runtime::now_millis() + STATE_ROOT_REFRESH_TIMEOUT.as_millis() as u64;
+        let mut snapshot = self.node.load_full();
+        while runtime::now_millis() < deadline {
+            runtime::sleep(STATE_ROOT_REFRESH_POLL).await;
+            if Arc::ptr_eq(&snapshot, &*self.node.load()) {
+                continue;
+            }
+            snapshot = self.node.load_full();
+            mismatch = match self.state_root_mismatch().await? {
+                Some(mismatch) => mismatch,
+                None => return Ok(()),
+            };
+        }
+        Err(mismatch.into())
+    }
+
+    /// Compare the state root of the last synced block with the last one proven on L1.
+    async fn state_root_mismatch(&self) -> Result<Option<StateRootMismatch>> {
+        let (l1_block_number, l1_state_root) = self.l1_proven_block_and_state_root().await?;
+        let node = self.node.load();
+        if node.state_root == l1_state_root.to_string() {
+            return Ok(None);
+        }
+        Ok(Some(StateRootMismatch {
+            l1_state_root: ethers_helper::u256_to_felt(l1_state_root)
+                .map_err(|_| eyre::eyre!("Invalid state root: {}", l1_state_root))?,
+            l1_block_number: l1_block_number.as_u64(),
+            local_state_root: FieldElement::from_dec_str(&node.state_root).ok(),
+            local_block_number: node.block_number,
+        }))
+    }
+
+    /// Return a block along with the receipts of its transactions, for one-shot ingestion.
+    /// Blocks retained in the payload are served from it and their receipts are cached, the
+    /// other ones are fetched from StarkNet.
+    /// # Arguments
+    /// * `block_id` - The block identifier.
+    /// # Returns
+    /// `Ok(BlockWithReceipts)` if the operation was successful.
+    /// `Err(eyre::Report)` if the block is pending or the operation failed.
+    pub async fn starknet_get_block_with_receipts(
+        &self,
+        block_id: &BlockId,
+    ) -> Result<BlockWithReceipts> {
+        let node = self.node.load_full();
+        let retained = node.block(block_id);
+        let is_retained = retained.is_some();
+        let (block, cached) = match retained {
+            Some(block) => {
+                let cached = node.receipts.get(&block.block_number).cloned();
+                (block, cached)
+            }
+            None => match self
+                .starknet_lightclient
+                .get_block_with_txs(block_id)
+                .await?
+            {
+                MaybePendingBlockWithTxs::Block(block) => (Arc::new(block), None),
+                MaybePendingBlockWithTxs::PendingBlock(_) => {
+                    return Err(eyre::eyre!("Receipts of the pending block are not served"))
+                }
+            },
+        };
+        if let Some(receipts) = cached {
+            return Ok(BlockWithReceipts::new(&block, &receipts));
+        }
+
+        let receipts =
+            Arc::new(fetch_receipts(self.starknet_lightclient.as_ref().as_ref(), &block).await?);
+        // Only the receipts of the retained blocks are cached, so they are dropped with them.
+        if is_retained {
+            self.node.rcu(|node| {
+                let mut node = NodeData::clone(node);
+                node.cache_receipts(block.block_number, block.block_hash, receipts.clone());
+                node
+            });
+        }
+        Ok(BlockWithReceipts::new(&block, &receipts))
+    }
+
+    /// Return a block with its transactions, from the payload if it retains the block, from the
+    /// StarkNet provider otherwise.
+    /// # Arguments
+    /// * `block_id` - The block identifier.
+    /// # Returns
+    /// `Ok(MaybePendingBlockWithTxs)` if the operation was successful.
+    /// `Err(eyre::Report)` if the operation failed.
+    pub async fn get_block_with_txs(&self, block_id: &BlockId) -> Result<MaybePendingBlockWithTxs> {
+        let node = self.node.load_full();
+        if let Some(block) = node.block(block_id) {
+            return Ok(MaybePendingBlockWithTxs::Block(BlockWithTxs::clone(&block)));
+        }
+        let block = self
+            .starknet_lightclient
+            .get_block_with_txs(block_id)
+            .await?;
+        if let MaybePendingBlockWithTxs::Block(block) = &block {
+            node.check_header(block.block_number, &block.block_hash)?;
+        }
+        Ok(block)
+    }
+
+    /// Return a block with its transactions, relayed as the provider wrote it unless the payload
+    /// retains it, see `Config::starknet_rpc_passthrough`. In header-only mode, only the number
+    /// and the hash of the block are parsed, to check them against the retained header.
+    /// # Arguments
+    /// * `block_id` - The block identifier.
+    /// # Returns
+    /// `Ok(Passthrough<MaybePendingBlockWithTxs>)` if the operation was successful.
+    /// `Err(eyre::Report)` if the operation failed.
+    pub async fn get_block_with_txs_passthrough(
+        &self,
+        block_id: &BlockId,
+    ) -> Result<Passthrough<MaybePendingBlockWithTxs>> {
+        let node = self.node.load_full();
+        if let Some(block) = node.block(block_id) {
+            return Ok(Passthrough::Parsed(MaybePendingBlockWithTxs::Block(
+                BlockWithTxs::clone(&block),
+            )));
+        }
+        let block = self
+            .starknet_lightclient
+            .request_raw("starknet_getBlockWithTxs", json!([block_id]))
+            .await?;
+        if node.payload.is_headers_only() {
+            let header: RelayedBlockHeader = serde_json::from_str(block.get())?;
+            if let (Some(block_number), Some(block_hash)) = (header.block_number, header.block_hash)
+            {
+                node.check_header(block_number, &FieldElement::from_hex_be(&block_hash)?)?;
+            }
+        }
+        Ok(Passthrough::Raw(block))
+    }
+
+    /// Return block with transaction hashes.
+    /// See https://github.com/starknet-io/starknet-addresses for the StarkNet core contract address on different networks.
+    /// The pending block is never retained, it is fetched from the StarkNet provider and returned
+    /// as a `PendingBlock`, which can't be verified.
+    /// # Arguments
+    /// BlockId
+    /// # Returns
+    /// `Ok(MaybePendingBlockWithTxHashes)` if the operation was successful.
+    /// `Err(eyre::Report)` if the operation failed.
+    pub async fn get_block_with_tx_hashes(
+        &self,
         block_id: &BlockId,
     ) -> Result<MaybePendingBlockWithTxHashes> {
-        let cloned_node = self.node.read().await;
-        let payload = cloned_node.payload.clone();
+        if let BlockId::Tag(StarknetBlockTag::Pending) = block_id {
+            return self.get_pending_block_with_tx_hashes().await;
+        }
+        let node = self.node.load_full();
+        let payload = &node.payload;
+        // Only the headers are retained, the block is fetched and checked against its header.
+        if payload.is_headers_only() {
+            let block = self
+                .starknet_lightclient
+                .get_block_with_tx_hashes(block_id)
+                .await?;
+            if let MaybePendingBlockWithTxHashes::Block(block) = &block {
+                node.check_header(block.block_number, &block.block_hash)?;
+            }
+            return Ok(block);
+        }
 
         let block = match block_id {
             BlockId::Number(block_number) => payload.get(block_number),
             BlockId::Hash(block_hash) => {
-                let block = payload
-                    .values()
-                    .find(|block| block.block_hash == *block_hash);
+                let block = payload.get_by_hash(block_hash);
                 match block {
                     Some(block) => Some(block),
                     None => {
-                        return Err(eyre::eyre!(
-                            "Block with hash {} not found in the payload.",
-                            block_hash
-                        ))
+                        return Err(StarknetError::new(
+                            StarknetErrorCode::BlockNotFound,
+                            format!("Block with hash {block_hash} not found in the payload."),
+                        )
+                        .into())
                     }
                 }
             }
-            BlockId::Tag(tag) => match tag {
-                StarknetBlockTag::Latest => payload.get(&cloned_node.block_number),
-                StarknetBlockTag::Pending => {
-                    let block = payload
-                        .values()
-                        .find(|block| block.status == BlockStatus::Pending);
-                    match block {
-                        Some(block) => Some(block),
-                        None => {
-                            return Err(eyre::eyre!(
-                                "Block with pending status not found in the payload."
-                            ))
-                        }
-                    }
-                }
-            },
+            BlockId::Tag(StarknetBlockTag::Latest) => payload.get(&node.block_number),
+            BlockId::Tag(StarknetBlockTag::Pending) => None,
         };
 
         match block {
-            Some(block) => {
-                let tx_hashes = block
-                    .clone()
-                    .transactions
-                    .into_iter()
-                    .map(|transaction| match transaction {
-                        Transaction::Invoke(tx) => match tx {
-                            InvokeTransaction::V0(v0_tx) => v0_tx.transaction_hash,
-                            InvokeTransaction::V1(v1_tx) => v1_tx.transaction_hash,
-                        },
-                        Transaction::L1Handler(L1HandlerTransaction {
-                            transaction_hash, ..
-                        })
-                        | Transaction::Declare(DeclareTransaction {
-                            transaction_hash, ..
-                        })
-                        | Transaction::Deploy(DeployTransaction {
-                            transaction_hash, ..
-                        })
-                        | Transaction::DeployAccount(DeployAccountTransaction {
-                            transaction_hash,
-                            ..
-                        }) => transaction_hash,
-                    })
-                    .collect();
-                let block_with_tx_hashes = BlockWithTxHashes {
-                    transactions: tx_hashes,
-                    status: block.status.clone(),
-                    block_hash: block.block_hash,
-                    parent_hash: block.parent_hash,
-                    block_number: block.block_number,
-                    new_root: block.new_root,
-                    timestamp: block.timestamp,
-                    sequencer_address: block.sequencer_address,
-                };
-                Ok(MaybePendingBlockWithTxHashes::Block(block_with_tx_hashes))
-            }
-            _ => Err(eyre::eyre!("Error while retrieving block.")),
+            Some(block) => Ok(MaybePendingBlockWithTxHashes::Block(block_with_tx_hashes(
+                block,
+            ))),
+            _ => Err(StarknetError::new(
+                StarknetErrorCode::BlockNotFound,
+                "Error while retrieving block.",
+            )
+            .into()),
         }
     }
 
-    /// Return transaction by inputed hash
-    /// See https://github.com/starknet-io/starknet-addresses for the StarkNet core contract address on different networks.
+    /// Fetch the pending block from the StarkNet provider, with the hashes of its transactions.
+    /// Providers still returning it as a block with the `PENDING` status are answered with a
+    /// `PendingBlock` too, so that it is never mistaken for a verified block.
+    async fn get_pending_block_with_tx_hashes(&self) -> Result<MaybePendingBlockWithTxHashes> {
+        let block = self
+            .starknet_lightclient
+            .get_block_with_txs(&BlockId::Tag(StarknetBlockTag::Pending))
+            .await?;
+        let (transactions, timestamp, sequencer_address, parent_hash) = match block {
+            MaybePendingBlockWithTxs::PendingBlock(block) => (
+                block.transactions,
+                block.timestamp,
+                block.sequencer_address,
+                block.parent_hash,
+            ),
+            MaybePendingBlockWithTxs::Block(block) => (
+                block.transactions,
+                block.timestamp,
+                block.sequencer_address,
+                block.parent_hash,
+            ),
+        };
+        Ok(MaybePendingBlockWithTxHashes::PendingBlock(
+            PendingBlockWithTxHashes {
+                transactions: transactions.iter().map(transaction_hash).collect(),
+                timestamp,
+                sequencer_address,
+                parent_hash,
+            },
+        ))
+    }
+
+    /// Return a transaction by hash, once its inclusion in a block is verified.
+    /// Transactions of the retained blocks are answered locally. The other ones are read from
+    /// the block the provider claims includes them, whose hash is recomputed. That block must
+    /// be proven on L1, unless the light client follows the head of the provider, see
+    /// [`BlockNumberSource`].
+    /// The inclusion is only checked against the block hash: the state root of the block is not
+    /// compared with the one proven on L1, see [`Self::get_transaction_proof`] for a proof
+    /// carrying the L1 state root.
     /// # Arguments
-    /// tx_hash: String
+    /// * `tx_hash` - The transaction hash.
     /// # Returns
-    /// Transaction
-    pub async fn get_transaction_by_hash(&self, tx_hash: String) -> Result<Transaction> {
-        let hash = FieldElement::from_str(&tx_hash)?;
+    /// `Ok(Transaction)` if the transaction is included in a verified block.
+    /// `Err(eyre::Report)` with [`TransactionNotVerified`] if its inclusion couldn't be
+    /// verified, or the error of the provider.
+    pub async fn get_transaction_by_hash(&self, tx_hash: FieldElement) -> Result<Transaction> {
+        if let Some(transaction) = self.node.load().transaction(&tx_hash) {
+            return Ok(transaction);
+        }
+        self.fetch_verified_transaction(tx_hash)
+            .await
+            .map(|(_, transaction)| transaction)
+    }
 
-        let transaction = self
+    /// Read a transaction from the provider along with the number of its block, once its
+    /// inclusion is verified, see [`Self::get_transaction_by_hash`].
+    async fn fetch_verified_transaction(
+        &self,
+        tx_hash: FieldElement,
+    ) -> Result<(u64, Transaction)> {
+        let receipt = self
             .starknet_lightclient
-            .get_transaction_by_hash(hash)
+            .get_transaction_receipt(tx_hash)
+            .await?;
+        let block_number = receipt_block_number(&receipt).ok_or_else(|| {
+            TransactionNotVerified::new(tx_hash, None, "the transaction is pending")
+        })?;
+        let not_verified = |reason: String| {
+            eyre::Report::from(TransactionNotVerified::new(
+                tx_hash,
+                Some(block_number),
+                reason,
+            ))
+        };
+
+        // The block is fetched while its proof on L1 is checked.
+        let last_proven_block = async {
+            match self.config.block_number_source {
+                BlockNumberSource::Proven => self
+                    .ethereum_lightclient
+                    .read()
+                    .await
+                    .starknet_last_proven_block()
+                    .await
+                    .map(|last_proven_block| Some(last_proven_block.as_u64())),
+                BlockNumberSource::Upstream => Ok(None),
+            }
+        };
+        let (last_proven_block, block) = try_join!(
+            last_proven_block,
+            self.starknet_lightclient
+                .get_block_with_txs(&BlockId::Number(block_number))
+        )?;
+        if let Some(last_proven_block) = last_proven_block {
+            if block_number > last_proven_block {
+                return Err(not_verified(format!(
+                    "the block is not proven on L1 yet, the last proven block is {last_proven_block}"
+                )));
+            }
+        }
+
+        let block = match block {
+            MaybePendingBlockWithTxs::Block(block) => block,
+            MaybePendingBlockWithTxs::PendingBlock(_) => {
+                return Err(not_verified("the block is pending".to_string()))
+            }
+        };
+        let transaction = block
+            .transactions
+            .iter()
+            .find(|transaction| transaction_hash(transaction) == tx_hash)
+            .cloned()
+            .ok_or_else(|| not_verified("the block doesn't include it".to_string()))?;
+        verify_block_hash(self.starknet_lightclient.as_ref().as_ref(), &block)
+            .await
+            .map_err(|err| not_verified(err.to_string()))?;
+        Ok((block_number, transaction))
+    }
+
+    /// Rebuild the local StarkNet state from a state diff published on L1 as calldata.
+    /// The state diff must belong to the last block proven on L1, so that the local state
+    /// can be tagged with the matching L1 state root, its memory pages must be valid facts
+    /// of the memory page fact registry, and their words must be the data accepted by the
+    /// core contract in the state update of the block. State diffs published in blobs are not
+    /// supported.
+    /// # Arguments
+    /// * `memory_page_tx_hashes` - The L1 transactions registering the state diff memory pages, in order.
+    /// * `state_update_tx_hash` - The L1 transaction updating the state to the block.
+    /// * `block_number` - The StarkNet block the state diff belongs to.
+    /// # Returns
+    /// `Ok(())` if the operation was successful.
+    /// `Err(eyre::Report)` if the operation failed.
+    pub async fn starknet_apply_l1_state_diff(
+        &self,
+        memory_page_tx_hashes: &[H256],
+        state_update_tx_hash: H256,
+        block_number: u64,
+    ) -> Result<()> {
+        let ethereum_lightclient = self.ethereum_lightclient.read().await;
+        let (last_proven_block, state_root) = ethereum_lightclient.starknet_proven_state().await?;
+        let last_proven_block = last_proven_block.as_u64();
+        if block_number != last_proven_block {
+            return Err(eyre::eyre!(
+                "State diff for block {} does not match the last proven block {}",
+                block_number,
+                last_proven_block
+            ));
+        }
+        let (state_update, transactions) = try_join!(
+            ethereum_lightclient.get_transaction_by_hash(&state_update_tx_hash),
+            try_join_all(
+                memory_page_tx_hashes
+                    .iter()
+                    .map(|tx_hash| ethereum_lightclient.get_transaction_by_hash(tx_hash)),
+            )
+        )?;
+        let state_root = ethers_helper::u256_to_felt(state_root)
+            .map_err(|_| eyre::eyre!("Invalid state root: {}", state_root))?;
+
+        // The state update must be the one of the block, its data availability fact is then
+        // the one covered by the proof the core contract accepted.
+        let state_update = state_update
+            .ok_or_else(|| eyre::eyre!("Transaction {:?} not found", state_update_tx_hash))?;
+        let data_availability =
+            decode_update_state_transaction(&state_update, self.starknet_core_contract_address)?;
+        let updated_block = self
+            .starknet_state_update_block(ethereum_lightclient.as_ref(), &state_update)
+            .await?;
+        if updated_block != block_number {
+            return Err(eyre::eyre!(
+                "Transaction {:?} updates the state to block {}, not {}",
+                state_update_tx_hash,
+                updated_block,
+                block_number
+            ));
+        }
+
+        // Collect the words of every memory page, in order, once the fact registry vouches for
+        // them.
+        let fact_registry = self.config.memory_page_fact_registry_address()?;
+        let mut words = Vec::new();
+        for (tx_hash, transaction) in memory_page_tx_hashes.iter().zip(transactions) {
+            let transaction =
+                transaction.ok_or_else(|| eyre::eyre!("Transaction {:?} not found", tx_hash))?;
+            let page = decode_memory_page_transaction(&transaction, fact_registry)?;
+            check_memory_page_fact(ethereum_lightclient.as_ref(), fact_registry, &transaction)
+                .await?;
+            words.extend(page);
+        }
+
+        data_availability.check(&words)?;
+
+        let state_diff = StateDiff::decode(&words)?;
+        self.local_state
+            .write()
             .await
-            .unwrap();
+            .apply(&state_diff, block_number, state_root, true)
+    }
 
-        Ok(transaction)
+    /// Return the StarkNet block a state update transaction moved the core contract to, from
+    /// the `LogStateUpdate` event it emitted.
+    async fn starknet_state_update_block(
+        &self,
+        ethereum_lightclient: &dyn EthereumLightClient,
+        state_update: &ethers::types::Transaction,
+    ) -> Result<u64> {
+        let block_number = state_update
+            .block_number
+            .ok_or_else(|| eyre::eyre!("Transaction {:?} is not mined", state_update.hash))?;
+        let block = Some(format!("{:#x}", block_number.as_u64()));
+        let event = self.starknet_core_abi.event("LogStateUpdate")?;
+        let logs = ethereum_lightclient
+            .get_logs(
+                &block,
+                &block,
+                &Some(format!("{:?}", self.starknet_core_contract_address)),
+                &Some(vec![format!("{:?}", event.signature())]),
+                &None,
+            )
+            .await?;
+        let log = logs
+            .iter()
+            .find(|log| log.transaction_hash == Some(state_update.hash))
+            .ok_or_else(|| eyre::eyre!("Transaction {:?} updated no state", state_update.hash))?;
+        let (block_number, _) = self.parse_log_state_update(log)?;
+        Ok(block_number)
     }
+
+    /// Get the storage at a given address/key from the state rebuilt from L1 data.
+    /// This does not involve any StarkNet provider.
+    /// # Arguments
+    /// * `contract_address` - The StarkNet contract address.
+    /// * `storage_key` - The storage key.
+    /// # Returns
+    /// `Ok(FieldElement)` if the operation was successful.
+    /// `Err(eyre::Report)` if the local state does not match the current L1 state root, or
+    /// doesn't hold the key.
+    pub async fn starknet_get_storage_at_from_l1_state(
+        &self,
+        contract_address: FieldElement,
+        storage_key: FieldElement,
+    ) -> Result<FieldElement> {
+        let state_root = self
+            .ethereum_lightclient
+            .read()
+            .await
+            .starknet_state_root()
+            .await?;
+        let state_root = ethers_helper::u256_to_felt(state_root)
+            .map_err(|_| eyre::eyre!("Invalid state root: {}", state_root))?;
+
+        let local_state = self.local_state.read().await;
+        if local_state.state_root != state_root {
+            return Err(eyre::eyre!(
+                "Local state at block {} is behind the L1 state root",
+                local_state.block_number
+            ));
+        }
+        local_state.get_storage_at(contract_address, storage_key)
+    }
+
+    /// Return the execution trace of a transaction.
+    /// WARNING: Traces can't be verified against the L1 state, they are relayed as is.
+    /// # Arguments
+    /// * `transaction_hash` - The transaction hash.
+    /// # Returns
+    /// `Ok(Unverified<Value>)` if the operation was successful.
+    /// `Err(eyre::Report)` if the operation failed.
+    pub async fn starknet_trace_transaction(
+        &self,
+        transaction_hash: FieldElement,
+    ) -> Result<Unverified<Value>> {
+        self.starknet_lightclient
+            .trace_transaction(transaction_hash)
+            .await
+            .map(Unverified::new)
+    }
+
+    /// Return the execution traces of the transactions of a block.
+    /// WARNING: Traces can't be verified against the L1 state, they are relayed as is.
+    /// # Arguments
+    /// * `block_id` - The block identifier.
+    /// # Returns
+    /// `Ok(Unverified<Value>)` if the operation was successful.
+    /// `Err(eyre::Report)` if the operation failed.
+    pub async fn starknet_trace_block_transactions(
+        &self,
+        block_id: &BlockId,
+    ) -> Result<Unverified<Value>> {
+        self.starknet_lightclient
+            .trace_block_transactions(block_id)
+            .await
+            .map(Unverified::new)
+    }
+
+    /// Simulate transactions on top of a block and return their traces and fee estimates.
+    /// WARNING: Simulations are run by the provider, they are relayed as is.
+    /// # Arguments
+    /// * `block_id` - The block identifier.
+    /// * `transactions` - The transactions to simulate.
+    /// * `simulation_flags` - The simulation flags.
+    /// # Returns
+    /// `Ok(Unverified<Value>)` if the operation was successful.
+    /// `Err(eyre::Report)` if the operation failed.
+    pub async fn starknet_simulate_transactions(
+        &self,
+        block_id: &BlockId,
+        transactions: Vec<AnyBroadcastedTransaction>,
+        simulation_flags: Vec<SimulationFlag>,
+    ) -> Result<Unverified<Value>> {
+        self.starknet_lightclient
+            .simulate_transactions(block_id, transactions, simulation_flags)
+            .await
+            .map(Unverified::new)
+    }
+
+    /// Relay a call to the StarkNet provider, and its result as the provider wrote it.
+    /// WARNING: The result is not verified, use it for the methods relayed as is anyway.
+    /// # Arguments
+    /// * `method` - The JSON-RPC method, e.g. `starknet_traceTransaction`.
+    /// * `params` - The parameters of the call.
+    /// # Returns
+    /// `Ok(Unverified<Box<RawValue>>)` if the operation was successful.
+    /// `Err(eyre::Report)` if the operation failed.
+    pub async fn starknet_passthrough(
+        &self,
+        method: &str,
+        params: Value,
+    ) -> Result<Unverified<Box<RawValue>>> {
+        self.starknet_lightclient
+            .request_raw(method, params)
+            .await
+            .map(Unverified::new)
+    }
+
+    /// Return the last StarkNet block number proven on L1.
+    /// # Returns
+    /// `Ok(u64)` if the operation was successful.
+    /// `Err(eyre::Report)` if the operation failed.
+    pub async fn starknet_l1_proven_block_number(&self) -> Result<u64> {
+        Ok(self
+            .ethereum_lightclient
+            .read()
+            .await
+            .starknet_last_proven_block()
+            .await?
+            .as_u64())
+    }
+
+    /// Return the StarkNet state root currently stored in the core contract on L1.
+    /// # Returns
+    /// `Ok(FieldElement)` if the operation was successful.
+    /// `Err(eyre::Report)` if the operation failed.
+    pub async fn starknet_l1_state_root(&self) -> Result<FieldElement> {
+        let state_root = self
+            .ethereum_lightclient
+            .read()
+            .await
+            .starknet_state_root()
+            .await?;
+        ethers_helper::u256_to_felt(state_root)
+            .map_err(|_| eyre::eyre!("Invalid state root: {}", state_root))
+    }
+
+    /// Return the last StarkNet block proven on L1 and its state root.
+    /// # Returns
+    /// `Ok((u64, FieldElement))` if the operation was successful.
+    /// `Err(eyre::Report)` if the operation failed.
+    pub async fn starknet_l1_proven_state(&self) -> Result<(u64, FieldElement)> {
+        let (block_number, state_root) = self.l1_proven_block_and_state_root().await?;
+        let state_root = ethers_helper::u256_to_felt(state_root)
+            .map_err(|_| eyre::eyre!("Invalid state root: {}", state_root))?;
+        Ok((block_number.as_u64(), state_root))
+    }
+
+    /// Convert a block identifier string into a `BlockId`, resolving the `l1_accepted` tag to
+    /// the number of the last block proven on L1.
+    /// # Arguments
+    /// * `block_id_type` - The type of block identifier: `hash`, `number` or `tag`.
+    /// * `block_id` - The block identifier.
+    /// # Returns
+    /// `Ok(BlockId)` if the operation was successful.
+    /// `Err(eyre::Report)` if the identifier is invalid or the proven block can't be read.
+    pub async fn resolve_block_id(&self, block_id_type: &str, block_id: &str) -> Result<BlockId> {
+        if block_id_type.eq_ignore_ascii_case("tag")
+            && block_id.eq_ignore_ascii_case(L1_ACCEPTED_TAG)
+        {
+            return Ok(BlockId::Number(
+                self.starknet_l1_proven_block_number().await?,
+            ));
+        }
+        block_id_string_to_block_id_type(block_id_type, block_id)
+    }
+
+    /// Block the `beerus_` state queries without a block tag are answered at, set by the
+    /// configured finality.
+    pub fn default_target(&self) -> BlockTarget {
+        self.config.finality.into()
+    }
+
+    /// Return the block a state query targets, along with the last block proven on L1 and its
+    /// state root.
+    async fn target_state(&self, target: BlockTarget) -> Result<(u64, u64, FieldElement)> {
+        match target {
+            BlockTarget::StateRoot(state_root) => {
+                let block_number = self.proven_block_of_state_root(state_root).await?;
+                return Ok((block_number, block_number, state_root));
+            }
+            BlockTarget::Pending => {
+                return Err(eyre::eyre!("The pending block is only served for nonces"));
+            }
+            _ => {}
+        }
+        let ((proven_block_number, state_root), block_number) = match target {
+            BlockTarget::Latest => {
+                let (proven_state, latest) = self
+                    .with_l1_proven_state(self.get_block_hash_and_number())
+                    .await?;
+                (proven_state, latest.block_number)
+            }
+            _ => {
+                let proven_state = self.starknet_l1_proven_state().await?;
+                (proven_state, proven_state.0)
+            }
+        };
+        Ok((block_number, proven_block_number, state_root))
+    }
+
+    /// Return the block a state root was proven on L1 for.
+    /// The proven state roots observed by the light client are looked up first, then the one
+    /// currently proven on L1.
+    /// # Arguments
+    /// * `state_root` - The state root.
+    /// # Returns
+    /// `Ok(u64)` with the StarkNet block number if the state root was proven.
+    /// `Err(eyre::Report)` if it was not proven, or is no longer retained.
+    pub async fn proven_block_of_state_root(&self, state_root: FieldElement) -> Result<u64> {
+        let retained = self
+            .node
+            .load()
+            .proven_state_roots
+            .iter()
+            .rev()
+            .find(|(_, root)| **root == state_root)
+            .map(|(block_number, _)| *block_number);
+        if let Some(block_number) = retained {
+            return Ok(block_number);
+        }
+        match self.starknet_l1_proven_state().await? {
+            (block_number, root) if root == state_root => Ok(block_number),
+            _ => Err(eyre::eyre!(
+                "State root {state_root} was not proven on L1, or is no longer retained"
+            )),
+        }
+    }
+
+    /// Get the storage at a given address/key along with verification metadata.
+    /// The value is checked with a storage proof against the state root proven on L1. If the
+    /// provider can't serve proofs, the value is returned unverified.
+    /// # Arguments
+    /// * `contract_address` - The StarkNet contract address.
+    /// * `storage_key` - The storage key.
+    /// * `target` - The block to read the storage at.
+    /// # Returns
+    /// `Ok(WithMetadata<FieldElement>)` if the operation was successful.
+    /// `Err(eyre::Report)` if the operation failed or the proof does not match the value.
+    pub async fn starknet_get_storage_at_with_metadata(
+        &self,
+        contract_address: FieldElement,
+        storage_key: FieldElement,
+        target: BlockTarget,
+    ) -> Result<WithMetadata<FieldElement>> {
+        let WithMetadata { result, metadata } = self
+            .starknet_get_storage_at_many(contract_address, vec![storage_key], target)
+            .await?;
+        Ok(WithMetadata {
+            result: result[0],
+            metadata,
+        })
+    }
+
+    /// Get the storage of a contract at several keys, all read at the same block.
+    /// The values are fetched concurrently and checked with a single multi-key storage proof.
+    /// If the provider can't serve proofs, or the block is not proven on L1 yet, the values are
+    /// returned unverified.
+    /// # Arguments
+    /// * `contract_address` - The StarkNet contract address.
+    /// * `storage_keys` - The storage keys.
+    /// * `target` - The block to read the storage at.
+    /// # Returns
+    /// `Ok(WithMetadata<Vec<FieldElement>>)` with one value per key if the operation was successful.
+    /// `Err(eyre::Report)` if the operation failed or the proof does not match one of the values.
+    pub async fn starknet_get_storage_at_many(
+        &self,
+        contract_address: FieldElement,
+        storage_keys: Vec<FieldElement>,
+        target: BlockTarget,
+    ) -> Result<WithMetadata<Vec<FieldElement>>> {
+        let (block_number, proven_block_number, state_root) = self.target_state(target).await?;
+        let values = try_join_all(storage_keys.iter().map(|storage_key| {
+            self.starknet_lightclient
+                .get_storage_at(contract_address, *storage_key, block_number)
+        }))
+        .await?;
+
+        let verified = block_number == proven_block_number
+            && match self
+                .starknet_lightclient
+                .get_contract_storage_proof(
+                    contract_address,
+                    storage_keys.clone(),
+                    &BlockId::Number(block_number),
+                )
+                .await
+            {
+                Ok(proof) => {
+                    check_storage_proof(
+                        &proof,
+                        state_root,
+                        contract_address,
+                        &storage_keys,
+                        &values,
+                        block_number,
+                    )?;
+                    true
+                }
+                Err(err) => {
+                    warn!("Storage proof unavailable: {}", err);
+                    false
+                }
+            };
+
+        Ok(WithMetadata {
+            result: values,
+            metadata: ResponseMetadata {
+                block_number,
+                proven_block_number,
+                state_root,
+                verified,
+            },
+        })
+    }
+
+    /// Get the contract proof and the storage proofs of a contract, so that external verifiers
+    /// and L1 contracts can check them against the state root proven on L1.
+    /// The proofs are checked before being returned. Unlike the storage reads, they are never
+    /// returned when the provider can't serve them.
+    /// # Arguments
+    /// * `contract_address` - The StarkNet contract address.
+    /// * `storage_keys` - The storage keys to prove, possibly none.
+    /// * `target` - The block to read the proofs at.
+    /// # Returns
+    /// `Ok(WithMetadata<ContractProof>)` if the operation was successful, `verified` if the
+    /// block is proven on L1.
+    /// `Err(eyre::Report)` if the operation failed or the proofs do not match the values.
+    pub async fn get_proof(
+        &self,
+        contract_address: FieldElement,
+        storage_keys: Vec<FieldElement>,
+        target: BlockTarget,
+    ) -> Result<WithMetadata<ContractProof>> {
+        let (block_number, proven_block_number, state_root) = self.target_state(target).await?;
+        let (storage_values, proof) = futures::future::try_join(
+            try_join_all(storage_keys.iter().map(|storage_key| {
+                self.starknet_lightclient.get_storage_at(
+                    contract_address,
+                    *storage_key,
+                    block_number,
+                )
+            })),
+            self.starknet_lightclient.get_contract_storage_proof(
+                contract_address,
+                storage_keys.clone(),
+                &BlockId::Number(block_number),
+            ),
+        )
+        .await?;
+
+        let verified = block_number == proven_block_number;
+        if verified {
+            check_storage_proof(
+                &proof,
+                state_root,
+                contract_address,
+                &storage_keys,
+                &storage_values,
+                block_number,
+            )?;
+        }
+
+        Ok(WithMetadata {
+            result: ContractProof {
+                contract_address,
+                storage_keys,
+                storage_values,
+                proof,
+            },
+            metadata: ResponseMetadata {
+                block_number,
+                proven_block_number,
+                state_root,
+                verified,
+            },
+        })
+    }
+
+    /// Get the nonce of a contract along with verification metadata.
+    /// The nonce is checked with a contract proof against the state root proven on L1. If the
+    /// provider can't serve proofs, the nonce is returned unverified.
+    /// # Arguments
+    /// * `address` - The StarkNet contract address.
+    /// * `target` - The block to read the nonce at.
+    /// # Returns
+    /// `Ok(WithMetadata<FieldElement>)` if the operation was successful.
+    /// `Err(eyre::Report)` if the operation failed or the proof does not match the nonce.
+    pub async fn starknet_get_nonce_with_metadata(
+        &self,
+        address: FieldElement,
+        target: BlockTarget,
+    ) -> Result<WithMetadata<FieldElement>> {
+        if target == BlockTarget::Pending {
+            return self.starknet_get_pending_nonce(address).await;
+        }
+        let (block_number, proven_block_number, state_root) = self.target_state(target).await?;
+        let nonce = self
+            .starknet_lightclient
+            .get_nonce(block_number, address)
+            .await?;
+
+        let verified = block_number == proven_block_number
+            && match self
+                .starknet_lightclient
+                .get_contract_storage_proof(address, vec![], &BlockId::Number(block_number))
+                .await
+            {
+                Ok(proof) => {
+                    let proven_nonce = proof.contract_data.as_ref().map(|data| data.nonce);
+                    if proof.verify(state_root, address, &[], &[]).is_none()
+                        || proven_nonce != Some(nonce)
+                    {
+                        return Err(eyre::eyre!(
+                            "Contract proof does not match nonce {} at block {}",
+                            nonce,
+                            block_number
+                        ));
+                    }
+                    true
+                }
+                Err(err) => {
+                    warn!("Contract proof unavailable: {}", err);
+                    false
+                }
+            };
+
+        Ok(WithMetadata {
+            result: nonce,
+            metadata: ResponseMetadata {
+                block_number,
+                proven_block_number,
+                state_root,
+                verified,
+            },
+        })
+    }
+
+    /// Get the nonce of a contract in the pending block, for wallets sending transactions back
+    /// to back. The pending block is not proven, so the nonce is marked as unverified.
+    /// # Arguments
+    /// * `address` - The StarkNet contract address.
+    /// # Returns
+    /// `Ok(WithMetadata<FieldElement>)` with the block after the latest one as block number.
+    /// `Err(eyre::Report)` if the operation failed.
+    pub async fn starknet_get_pending_nonce(
+        &self,
+        address: FieldElement,
+    ) -> Result<WithMetadata<FieldElement>> {
+        let ((proven_block_number, state_root), (latest, nonce)) = self
+            .with_l1_proven_state(async {
+                try_join!(
+                    self.get_block_hash_and_number(),
+                    self.starknet_lightclient
+                        .get_nonce_at(&BlockId::Tag(StarknetBlockTag::Pending), address)
+                )
+            })
+            .await?;
+
+        Ok(WithMetadata {
+            result: nonce,
+            metadata: ResponseMetadata {
+                block_number: latest.block_number + 1,
+                proven_block_number,
+                state_root,
+                verified: false,
+            },
+        })
+    }
+
+    /// Call a contract view function, along with metadata.
+    /// Calls can't be proven, so the result is always marked as unverified.
+    /// # Arguments
+    /// * `contract_address` - The StarkNet contract address.
+    /// * `entry_point_selector` - The entry point selector.
+    /// * `calldata` - The calldata.
+    /// * `target` - The block to run the call at.
+    /// # Returns
+    /// `Ok(WithMetadata<Vec<FieldElement>>)` if the operation was successful.
+    /// `Err(eyre::Report)` if the operation failed.
+    pub async fn starknet_call_contract_with_metadata(
+        &self,
+        contract_address: FieldElement,
+        entry_point_selector: FieldElement,
+        calldata: Vec<FieldElement>,
+        target: BlockTarget,
+    ) -> Result<WithMetadata<Vec<FieldElement>>> {
+        let (block_number, proven_block_number, state_root) = self.target_state(target).await?;
+        let opts = FunctionCall {
+            contract_address,
+            entry_point_selector,
+            calldata,
+        };
+        let result = self.starknet_lightclient.call(opts, block_number).await?;
+
+        Ok(WithMetadata {
+            result,
+            metadata: ResponseMetadata {
+                block_number,
+                proven_block_number,
+                state_root,
+                verified: false,
+            },
+        })
+    }
+}
+
+/// Return a block with the hashes of its transactions in place of the transactions.
+/// Keep the entries of a map shared between snapshots from `key` onwards.
+fn retain_from<K: Ord + Clone, V: Clone>(map: &mut Arc<BTreeMap<K, V>>, key: &K) {
+    let map = Arc::make_mut(map);
+    *map = map.split_off(key);
+}
+
+fn block_with_tx_hashes(block: &BlockWithTxs) -> BlockWithTxHashes {
+    BlockWithTxHashes {
+        transactions: block.transactions.iter().map(transaction_hash).collect(),
+        status: block.status.clone(),
+        block_hash: block.block_hash,
+        parent_hash: block.parent_hash,
+        block_number: block.block_number,
+        new_root: block.new_root,
+        timestamp: block.timestamp,
+        sequencer_address: block.sequencer_address,
+    }
+}
+
+/// Check a contract proof and its storage proofs against a state root, a value of zero being
+/// proven by a non-membership proof.
+fn check_storage_proof(
+    proof: &GetProofOutput,
+    state_root: FieldElement,
+    contract_address: FieldElement,
+    storage_keys: &[FieldElement],
+    values: &[FieldElement],
+    block_number: u64,
+) -> Result<()> {
+    let memberships = match proof.verify(state_root, contract_address, storage_keys, values) {
+        Some(memberships) => memberships,
+        None if values.is_empty() => {
+            return Err(eyre::eyre!(
+                "Contract proof of {} does not match state root {} at block {}",
+                contract_address,
+                state_root,
+                block_number
+            ))
+        }
+        None => vec![],
+    };
+    for (index, value) in values.iter().enumerate() {
+        match memberships.get(index) {
+            Some(Some(Membership::Member)) => {}
+            Some(Some(Membership::NonMember)) if *value == FieldElement::ZERO => {}
+            _ => {
+                return Err(eyre::eyre!(
+                    "Storage proof does not match value {} at block {}",
+                    value,
+                    block_number
+                ))
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Parse the block number and the number of events to skip of a local continuation token,
+/// without its prefix.
+fn parse_local_events_token(token: &str) -> Option<(u64, usize)> {
+    let (block_number, skip) = token.split_once('-')?;
+    Some((block_number.parse().ok()?, skip.parse().ok()?))
+}
+
+fn invalid_continuation_token() -> eyre::Report {
+    StarknetError::new(
+        StarknetErrorCode::InvalidContinuationToken,
+        "The continuation token is invalid, or its blocks are no longer retained",
+    )
+    .into()
 }
@@ -0,0 +1,41 @@
+use serde::{Deserialize, Serialize};
+use starknet::{
+    core::types::FieldElement,
+    providers::jsonrpc::models::{BlockWithTxs, DeclareTransaction, Transaction},
+};
+
+/// Declaration of a class by a `Declare` transaction of a synced block.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClassDeclaration {
+    pub class_hash: FieldElement,
+    pub sender_address: FieldElement,
+    pub transaction_hash: FieldElement,
+    pub block_number: u64,
+    pub block_hash: FieldElement,
+    /// Whether the state root of the block, or of a later one, was proven on L1 when queried.
+    pub proven: bool,
+}
+
+/// Return the classes declared by the transactions of a block, in transaction order.
+pub fn class_declarations(block: &BlockWithTxs) -> Vec<ClassDeclaration> {
+    block
+        .transactions
+        .iter()
+        .filter_map(|transaction| match transaction {
+            Transaction::Declare(DeclareTransaction {
+                class_hash,
+                sender_address,
+                transaction_hash,
+                ..
+            }) => Some(ClassDeclaration {
+                class_hash: *class_hash,
+                sender_address: *sender_address,
+                transaction_hash: *transaction_hash,
+                block_number: block.block_number,
+                block_hash: block.block_hash,
+                proven: false,
+            }),
+            _ => None,
+        })
+        .collect()
+}
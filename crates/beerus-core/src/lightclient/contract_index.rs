@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+use starknet::{
+    core::{types::FieldElement, utils::get_contract_address},
+    macros::selector,
+    providers::jsonrpc::models::{
+        BlockWithTxs, DeployAccountTransaction, DeployTransaction, EmittedEvent, Transaction,
+    },
+};
+
+/// Address of the Universal Deployer Contract, whose `ContractDeployed` events report the
+/// contracts it deploys.
+pub const UNIVERSAL_DEPLOYER_ADDRESS: &str =
+    "0x041a78e741e5af2fec34b695679bc6891742439f7afb8484ecd7766661ad02bf";
+
+/// Deployment of a contract observed in a synced block.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContractDeployment {
+    pub address: FieldElement,
+    pub class_hash: FieldElement,
+    pub transaction_hash: FieldElement,
+    pub block_number: u64,
+    pub block_hash: FieldElement,
+    /// Whether the state root of the block, or of a later one, was proven on L1 when queried.
+    pub proven: bool,
+}
+
+/// Return the contracts deployed by a block, from its `Deploy` and `DeployAccount` transactions
+/// and from the `ContractDeployed` events of the Universal Deployer Contract.
+/// Contracts deployed by other contracts through the `deploy` syscall are not reported.
+pub fn contract_deployments(
+    block: &BlockWithTxs,
+    events: &[EmittedEvent],
+) -> Vec<ContractDeployment> {
+    let deployment = |address, class_hash, transaction_hash| ContractDeployment {
+        address,
+        class_hash,
+        transaction_hash,
+        block_number: block.block_number,
+        block_hash: block.block_hash,
+        proven: false,
+    };
+    let deployed_by_transactions =
+        block
+            .transactions
+            .iter()
+            .filter_map(|transaction| match transaction {
+                Transaction::Deploy(DeployTransaction {
+                    transaction_hash,
+                    class_hash,
+                    contract_address_salt,
+                    constructor_calldata,
+                    ..
+                })
+                | Transaction::DeployAccount(DeployAccountTransaction {
+                    transaction_hash,
+                    class_hash,
+                    contract_address_salt,
+                    constructor_calldata,
+                    ..
+                }) => Some(deployment(
+                    get_contract_address(
+                        *contract_address_salt,
+                        *class_hash,
+                        constructor_calldata,
+                        FieldElement::ZERO,
+                    ),
+                    *class_hash,
+                    *transaction_hash,
+                )),
+                _ => None,
+            });
+    // The address is a valid constant, parsing it never fails.
+    let universal_deployer = FieldElement::from_hex_be(UNIVERSAL_DEPLOYER_ADDRESS).unwrap();
+    // The data of the event starts with the address, the deployer, whether the address is
+    // unique, and the class hash.
+    let deployed_by_udc = events
+        .iter()
+        .filter(|event| {
+            event.from_address == universal_deployer
+                && event.keys.first() == Some(&selector!("ContractDeployed"))
+                && event.data.len() >= 4
+        })
+        .map(|event| deployment(event.data[0], event.data[3], event.transaction_hash));
+    deployed_by_transactions.chain(deployed_by_udc).collect()
+}
@@ -1,24 +1,39 @@
-use crate::config::Config;
+use crate::{config::Config, ethers_helper};
 use async_trait::async_trait;
-use ethers::types::{Address, BlockNumber, Filter, Log, Topic, Transaction, H256, U256};
+use ethers::{
+    types::{Address, BlockNumber, Filter, Log, Topic, Transaction, H256, U256},
+    utils::id,
+};
 use eyre::{eyre, Result};
+#[cfg(target_arch = "wasm32")]
+use helios::client::ConfigDB;
+#[cfg(not(target_arch = "wasm32"))]
+use helios::client::FileDB;
 use helios::{
-    client::{Client, ClientBuilder, FileDB},
+    client::{Client, ClientBuilder},
     types::{BlockTag, CallOpts, ExecutionBlock},
 };
 use std::{primitive::u64, str::FromStr};
 
-use super::EthereumLightClient;
+use super::{read_starknet_proven_state, EthereumLightClient};
+
+/// Database used by Helios to persist its checkpoints.
+/// There is no file system in the browser, so the checkpoint only lives in the config there.
+#[cfg(not(target_arch = "wasm32"))]
+pub type HeliosDB = FileDB;
+#[cfg(target_arch = "wasm32")]
+pub type HeliosDB = ConfigDB;
 
 /// Helios implementation of `EthereumLightClient`.
 pub struct HeliosLightClient {
     /// The wrapped Helios client.
-    pub helios_light_client: Client<FileDB>,
+    pub helios_light_client: Client<HeliosDB>,
     pub starknet_core_contract_address: Address,
 }
 
 /// Implementation of `EthereumLightClient` for Helios.
-#[async_trait]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
 impl EthereumLightClient for HeliosLightClient {
     async fn start(&mut self) -> eyre::Result<()> {
         // Start the Helios light client.
@@ -58,6 +73,12 @@ impl EthereumLightClient for HeliosLightClient {
         self.helios_light_client.get_code(address, block).await
     }
 
+    async fn get_storage_at(&self, address: &Address, slot: H256, block: BlockTag) -> Result<U256> {
+        self.helios_light_client
+            .get_storage_at(address, slot, block)
+            .await
+    }
+
     async fn get_transaction_count(&self, address: &Address, block: BlockTag) -> Result<u64> {
         // TODO: Rename after it has been renamed https://github.com/a16z/helios/pull/166#issuecomment-1379587761
         self.helios_light_client.get_nonce(address, block).await
@@ -148,7 +169,7 @@ impl EthereumLightClient for HeliosLightClient {
         let starknet_root = self.call(&call_opts, BlockTag::Latest).await?;
 
         // Convert the response bytes to a U256.
-        let starknet_root = U256::from_big_endian(&starknet_root);
+        let starknet_root = ethers_helper::bytes_to_u256(&starknet_root)?;
 
         Ok(starknet_root)
     }
@@ -176,10 +197,44 @@ impl EthereumLightClient for HeliosLightClient {
         let starknet_root = self.call(&call_opts, BlockTag::Latest).await?;
 
         // Convert the response bytes to a U256.
-        let starknet_root = U256::from_big_endian(&starknet_root);
+        let starknet_root = ethers_helper::bytes_to_u256(&starknet_root)?;
 
         Ok(starknet_root)
     }
+
+    /// Get the hash of the StarkNet last proven block.
+    ///
+    /// # Returns
+    /// `Ok(U256)` if the operation was successful.
+    /// `Err(eyre::Report)` if the operation failed.
+    async fn starknet_last_proven_block_hash(&self) -> Result<U256> {
+        // Corresponds to the StarkNet core contract function `stateBlockHash`.
+        let data = id("stateBlockHash()").to_vec();
+
+        // Build the call options.
+        let call_opts = CallOpts {
+            from: None,
+            to: self.starknet_core_contract_address,
+            gas: None,
+            gas_price: None,
+            value: None,
+            data: Some(data),
+        };
+
+        // Call the StarkNet core contract.
+        let block_hash = self.call(&call_opts, BlockTag::Latest).await?;
+
+        ethers_helper::bytes_to_u256(&block_hash)
+    }
+
+    /// Get the StarkNet last proven block number and state root, at the same L1 block.
+    ///
+    /// # Returns
+    /// `Ok((U256, U256))` if the operation was successful.
+    /// `Err(eyre::Report)` if the operation failed.
+    async fn starknet_proven_state(&self) -> Result<(U256, U256)> {
+        read_starknet_proven_state(self, self.starknet_core_contract_address).await
+    }
 }
 
 /// HeliosLightClient non-trait functions.
@@ -187,13 +242,19 @@ impl HeliosLightClient {
     /// Create a new HeliosLightClient.
     pub async fn new(config: Config) -> eyre::Result<Self> {
         // Build the Helios wrapped light client.
-        let helios_light_client: Client<FileDB> = ClientBuilder::new()
+        let builder = ClientBuilder::new()
             .network(config.ethereum_network()?)
             .consensus_rpc(config.ethereum_consensus_rpc.as_str())
             .execution_rpc(config.ethereum_execution_rpc.as_str())
-            .load_external_fallback()
-            .data_dir(config.data_dir.unwrap())
-            .build()?;
+            .load_external_fallback();
+        // The checkpoints are stored in the data directory, laid out and checked first.
+        #[cfg(not(target_arch = "wasm32"))]
+        let builder = {
+            let data_dir = config.data_layout();
+            data_dir.init()?;
+            builder.data_dir(data_dir.helios())
+        };
+        let helios_light_client: Client<HeliosDB> = builder.build()?;
 
         Ok(Self {
             helios_light_client,
@@ -202,7 +263,8 @@ impl HeliosLightClient {
     }
 }
 
-fn build_logs_filter(
+/// Filter of `eth_getLogs`, from the block range or hash and the address and topics given.
+pub fn build_logs_filter(
     from_block: &Option<String>,
     to_block: &Option<String>,
     address: &Option<String>,
@@ -1,8 +1,13 @@
 pub mod helios_lightclient;
 
+use crate::ethers_helper::bytes_to_u256;
 use async_trait::async_trait;
-use ethers::types::{Address, Log, Transaction, H256, U256};
+use ethers::{
+    types::{Address, Log, Transaction, H256, U256},
+    utils::id,
+};
 use eyre::Result;
+use futures::try_join;
 use helios::types::{BlockTag, CallOpts, ExecutionBlock};
 use mockall::automock;
 use std::u8;
@@ -12,7 +17,8 @@ use std::u8;
 // TODO: For now there is a dependency on Helios types, we should abstract this away eventually.
 // TODO: Maybe we can let the possibility to get access to the underlying light client anyway.
 #[automock]
-#[async_trait]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
 pub trait EthereumLightClient: Send + Sync {
     /// Start and synchronize the Ethereum light client.
     /// This function should be called before any other function.
@@ -101,6 +107,14 @@ pub trait EthereumLightClient: Send + Sync {
     /// Add examples.
     async fn get_code(&self, address: &Address, block: BlockTag) -> Result<Vec<u8>>;
 
+    /// Get the value of a storage slot of a given address.
+    /// This function should be called after `start`.
+    /// # Returns
+    /// The value of the storage slot, verified against the state root of the block.
+    /// # Errors
+    /// If the call fails.
+    async fn get_storage_at(&self, address: &Address, slot: H256, block: BlockTag) -> Result<U256>;
+
     /// Get the txs counts of an Ethereum address from a given block.
     /// This function should be called after `start`.
     /// # Returns
@@ -222,5 +236,44 @@ pub trait EthereumLightClient: Send + Sync {
     ) -> Result<Vec<Log>>;
 
     async fn starknet_last_proven_block(&self) -> Result<U256>;
+    async fn starknet_last_proven_block_hash(&self) -> Result<U256>;
     async fn starknet_state_root(&self) -> Result<U256>;
+    /// Read the last StarkNet block proven on L1 and its state root at the same L1 block.
+    /// Separate reads could straddle a state update and pair a block with the root of another.
+    /// # Returns
+    /// `Ok((U256, U256))` with the block number and the state root.
+    /// `Err(eyre::Report)` if the operation failed.
+    async fn starknet_proven_state(&self) -> Result<(U256, U256)>;
+}
+
+/// Read the last StarkNet block proven on L1 and its state root from the core contract, both
+/// at the latest L1 block, for the implementations of
+/// [`EthereumLightClient::starknet_proven_state`].
+/// # Arguments
+/// * `ethereum_lightclient` - The Ethereum light client calling the core contract.
+/// * `starknet_core_contract_address` - The address of the StarkNet core contract.
+/// # Returns
+/// `Ok((U256, U256))` with the block number and the state root.
+/// `Err(eyre::Report)` if the operation failed.
+pub async fn read_starknet_proven_state<C: EthereumLightClient + ?Sized>(
+    ethereum_lightclient: &C,
+    starknet_core_contract_address: Address,
+) -> Result<(U256, U256)> {
+    let l1_block_number = ethereum_lightclient.get_block_number().await?;
+    let call_opts = |function: &str| CallOpts {
+        from: None,
+        to: starknet_core_contract_address,
+        gas: None,
+        gas_price: None,
+        value: None,
+        data: Some(id(function).to_vec()),
+    };
+    let (block_number, state_root) = try_join!(
+        ethereum_lightclient.call(
+            &call_opts("stateBlockNumber()"),
+            BlockTag::Number(l1_block_number)
+        ),
+        ethereum_lightclient.call(&call_opts("stateRoot()"), BlockTag::Number(l1_block_number))
+    )?;
+    Ok((bytes_to_u256(&block_number)?, bytes_to_u256(&state_root)?))
 }
@@ -0,0 +1,115 @@
+use starknet::{core::types::FieldElement, providers::jsonrpc::models::EmittedEvent};
+use std::{collections::HashMap, mem::size_of};
+
+/// Positions of the events of a block, by emitting contract and by first key, so that the
+/// events of the retained blocks can be filtered without scanning all of them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BlockEventIndex {
+    /// Number of events of the block.
+    len: usize,
+    by_address: HashMap<[u8; 32], Vec<usize>>,
+    by_key: HashMap<[u8; 32], Vec<usize>>,
+}
+
+impl BlockEventIndex {
+    pub fn new(events: &[EmittedEvent]) -> Self {
+        let mut index = Self {
+            len: events.len(),
+            ..Self::default()
+        };
+        for (position, event) in events.iter().enumerate() {
+            index
+                .by_address
+                .entry(event.from_address.to_bytes_be())
+                .or_default()
+                .push(position);
+            if let Some(key) = event.keys.first() {
+                index
+                    .by_key
+                    .entry(key.to_bytes_be())
+                    .or_default()
+                    .push(position);
+            }
+        }
+        index
+    }
+
+    /// Approximate memory used by the index.
+    pub fn memory_bytes(&self) -> usize {
+        let entry = 32 + size_of::<Vec<usize>>();
+        let positions: usize = self
+            .by_address
+            .values()
+            .chain(self.by_key.values())
+            .map(Vec::len)
+            .sum();
+        size_of::<Self>()
+            + (self.by_address.len() + self.by_key.len()) * entry
+            + positions * size_of::<usize>()
+    }
+
+    /// Return the positions of the events emitted by `address`, if set, whose first key is one
+    /// of `keys`, if set, in emission order.
+    pub fn matching(
+        &self,
+        address: Option<&FieldElement>,
+        keys: Option<&[FieldElement]>,
+    ) -> Vec<usize> {
+        let by_address = address.map(|address| {
+            self.by_address
+                .get(&address.to_bytes_be())
+                .map(Vec::as_slice)
+                .unwrap_or_default()
+        });
+        let by_key = keys.map(|keys| {
+            let mut positions: Vec<usize> = keys
+                .iter()
+                .filter_map(|key| self.by_key.get(&key.to_bytes_be()))
+                .flatten()
+                .copied()
+                .collect();
+            positions.sort_unstable();
+            positions.dedup();
+            positions
+        });
+        match (by_address, by_key) {
+            (Some(by_address), Some(by_key)) => by_address
+                .iter()
+                .filter(|position| by_key.binary_search(*position).is_ok())
+                .copied()
+                .collect(),
+            (Some(by_address), None) => by_address.to_vec(),
+            (None, Some(by_key)) => by_key,
+            (None, None) => (0..self.len).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(from_address: u64, key: u64) -> EmittedEvent {
+        EmittedEvent {
+            from_address: FieldElement::from(from_address),
+            keys: vec![FieldElement::from(key)],
+            data: vec![],
+            block_hash: FieldElement::ZERO,
+            block_number: 1,
+            transaction_hash: FieldElement::ZERO,
+        }
+    }
+
+    #[test]
+    fn test_matching_intersects_address_and_keys() {
+        let index = BlockEventIndex::new(&[event(1, 10), event(2, 10), event(1, 20)]);
+
+        let address = FieldElement::from(1_u64);
+        let keys = [FieldElement::from(10_u64), FieldElement::from(20_u64)];
+        assert_eq!(index.matching(Some(&address), None), vec![0, 2]);
+        assert_eq!(index.matching(None, Some(&keys[..1])), vec![0, 1]);
+        assert_eq!(index.matching(Some(&address), Some(&keys[..1])), vec![0]);
+        assert_eq!(index.matching(None, Some(&keys)), vec![0, 1, 2]);
+        assert_eq!(index.matching(None, None), vec![0, 1, 2]);
+    }
+}
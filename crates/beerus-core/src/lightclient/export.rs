@@ -0,0 +1,439 @@
+//! Tabular exports of verified blocks for data pipelines: one row per block, transaction,
+//! receipt or event, written as CSV or Parquet.
+//!
+//! Felts are written as hex strings and the keys and data of the events as hex strings joined
+//! with `;`, so that no CSV value needs quoting. In Parquet, numbers are `INT64` columns and
+//! the other values `UTF8` ones.
+use super::starknet::block_hash::{
+    receipt_actual_fee, receipt_events, receipt_transaction_hash, transaction_hash,
+};
+use eyre::{eyre, Result};
+use starknet::{
+    core::types::FieldElement,
+    providers::jsonrpc::models::{BlockWithTxs, MaybePendingTransactionReceipt, Transaction},
+};
+use std::{fmt, io::Write, str::FromStr};
+
+/// Maximum number of blocks exported at once.
+pub const MAX_EXPORT_BLOCKS: u64 = 100_000;
+
+/// Table exported, one row per block, transaction, receipt or event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportTable {
+    Blocks,
+    Transactions,
+    Receipts,
+    Events,
+}
+
+impl FromStr for ExportTable {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "blocks" => Ok(Self::Blocks),
+            "transactions" => Ok(Self::Transactions),
+            "receipts" => Ok(Self::Receipts),
+            "events" => Ok(Self::Events),
+            _ => Err(eyre!("Invalid export table: {s}")),
+        }
+    }
+}
+
+impl fmt::Display for ExportTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Blocks => "blocks",
+            Self::Transactions => "transactions",
+            Self::Receipts => "receipts",
+            Self::Events => "events",
+        })
+    }
+}
+
+/// Format of an export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Parquet,
+}
+
+impl FromStr for ExportFormat {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "csv" => Ok(Self::Csv),
+            "parquet" => Ok(Self::Parquet),
+            _ => Err(eyre!("Invalid export format: {s}")),
+        }
+    }
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Csv => "csv",
+            Self::Parquet => "parquet",
+        })
+    }
+}
+
+/// Type of the values of a column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnKind {
+    Int,
+    Text,
+}
+
+/// Value of a row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Cell {
+    Int(u64),
+    Felt(FieldElement),
+    Text(String),
+}
+
+impl fmt::Display for Cell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Int(value) => write!(f, "{value}"),
+            Self::Felt(value) => write!(f, "{value:#x}"),
+            Self::Text(value) => f.write_str(value),
+        }
+    }
+}
+
+/// Row of a table, one value per column.
+pub type Row = Vec<Cell>;
+
+/// Columns of a table, with the type of their values.
+pub fn columns(table: ExportTable) -> &'static [(&'static str, ColumnKind)] {
+    use ColumnKind::{Int, Text};
+    match table {
+        ExportTable::Blocks => &[
+            ("block_number", Int),
+            ("block_hash", Text),
+            ("parent_hash", Text),
+            ("new_root", Text),
+            ("timestamp", Int),
+            ("sequencer_address", Text),
+            ("transaction_count", Int),
+        ],
+        ExportTable::Transactions => &[
+            ("block_number", Int),
+            ("transaction_index", Int),
+            ("transaction_hash", Text),
+            ("type", Text),
+        ],
+        ExportTable::Receipts => &[
+            ("block_number", Int),
+            ("transaction_index", Int),
+            ("transaction_hash", Text),
+            ("actual_fee", Text),
+            ("event_count", Int),
+        ],
+        ExportTable::Events => &[
+            ("block_number", Int),
+            ("transaction_index", Int),
+            ("transaction_hash", Text),
+            ("event_index", Int),
+            ("from_address", Text),
+            ("keys", Text),
+            ("data", Text),
+        ],
+    }
+}
+
+/// Check the range of blocks of an export.
+/// # Errors
+/// * If the range is empty or longer than `max_blocks`.
+pub fn check_range(from: u64, to: u64, max_blocks: u64) -> Result<()> {
+    if from > to || to - from >= max_blocks {
+        return Err(eyre!(
+            "Invalid range {from} to {to}, at most {max_blocks} blocks are exported at once"
+        ));
+    }
+    Ok(())
+}
+
+/// Rows of a table for a block and the receipts of its transactions, in order.
+/// Pending receipts are skipped.
+pub fn table_rows(
+    table: ExportTable,
+    block: &BlockWithTxs,
+    receipts: &[MaybePendingTransactionReceipt],
+) -> Vec<Row> {
+    let receipts = || {
+        receipts
+            .iter()
+            .enumerate()
+            .filter_map(|(index, maybe_pending)| match maybe_pending {
+                MaybePendingTransactionReceipt::Receipt(receipt) => {
+                    Some((index, receipt_transaction_hash(maybe_pending)?, receipt))
+                }
+                MaybePendingTransactionReceipt::PendingReceipt(_) => None,
+            })
+    };
+    let block_number = Cell::Int(block.block_number);
+    match table {
+        ExportTable::Blocks => vec![vec![
+            block_number,
+            Cell::Felt(block.block_hash),
+            Cell::Felt(block.parent_hash),
+            Cell::Felt(block.new_root),
+            Cell::Int(block.timestamp),
+            Cell::Felt(block.sequencer_address),
+            Cell::Int(block.transactions.len() as u64),
+        ]],
+        ExportTable::Transactions => block
+            .transactions
+            .iter()
+            .enumerate()
+            .map(|(index, transaction)| {
+                vec![
+                    block_number.clone(),
+                    Cell::Int(index as u64),
+                    Cell::Felt(transaction_hash(transaction)),
+                    Cell::Text(transaction_type(transaction).to_string()),
+                ]
+            })
+            .collect(),
+        ExportTable::Receipts => receipts()
+            .map(|(index, transaction_hash, receipt)| {
+                vec![
+                    block_number.clone(),
+                    Cell::Int(index as u64),
+                    Cell::Felt(transaction_hash),
+                    Cell::Felt(receipt_actual_fee(receipt)),
+                    Cell::Int(receipt_events(receipt).len() as u64),
+                ]
+            })
+            .collect(),
+        ExportTable::Events => receipts()
+            .flat_map(|(index, transaction_hash, receipt)| {
+                let block_number = block_number.clone();
+                receipt_events(receipt)
+                    .iter()
+                    .enumerate()
+                    .map(move |(event_index, event)| {
+                        vec![
+                            block_number.clone(),
+                            Cell::Int(index as u64),
+                            Cell::Felt(transaction_hash),
+                            Cell::Int(event_index as u64),
+                            Cell::Felt(event.from_address),
+                            Cell::Text(join_felts(&event.keys)),
+                            Cell::Text(join_felts(&event.data)),
+                        ]
+                    })
+            })
+            .collect(),
+    }
+}
+
+/// Write the rows of a table in a format.
+/// # Returns
+/// * The number of rows written.
+/// # Errors
+/// * If the output can't be written, or Parquet isn't available.
+pub fn write_rows(
+    table: ExportTable,
+    format: ExportFormat,
+    rows: &[Row],
+    out: &mut (impl Write + Send),
+) -> Result<usize> {
+    match format {
+        ExportFormat::Csv => write_csv(table, rows, out),
+        ExportFormat::Parquet => write_parquet(table, rows, out),
+    }
+}
+
+/// Write the rows as CSV, with a header row.
+fn write_csv(table: ExportTable, rows: &[Row], out: &mut impl Write) -> Result<usize> {
+    let header: Vec<&str> = columns(table).iter().map(|(name, _)| *name).collect();
+    writeln!(out, "{}", header.join(","))?;
+    for row in rows {
+        let values: Vec<String> = row.iter().map(Cell::to_string).collect();
+        writeln!(out, "{}", values.join(","))?;
+    }
+    Ok(rows.len())
+}
+
+/// Write the rows as a Parquet file of a single row group, uncompressed.
+#[cfg(not(target_arch = "wasm32"))]
+fn write_parquet(table: ExportTable, rows: &[Row], out: &mut (impl Write + Send)) -> Result<usize> {
+    use parquet::{
+        data_type::{ByteArray, ByteArrayType, Int64Type},
+        file::{properties::WriterProperties, writer::SerializedFileWriter},
+        schema::parser::parse_message_type,
+    };
+    use std::sync::Arc;
+
+    let columns = columns(table);
+    let fields: Vec<String> = columns
+        .iter()
+        .map(|(name, kind)| match kind {
+            ColumnKind::Int => format!("REQUIRED INT64 {name};"),
+            ColumnKind::Text => format!("REQUIRED BYTE_ARRAY {name} (UTF8);"),
+        })
+        .collect();
+    let schema = parse_message_type(&format!("message {table} {{ {} }}", fields.join(" ")))?;
+    let mut writer = SerializedFileWriter::new(
+        out,
+        Arc::new(schema),
+        Arc::new(WriterProperties::builder().build()),
+    )?;
+
+    let mut row_group = writer.next_row_group()?;
+    let mut index = 0;
+    while let Some(mut column) = row_group.next_column()? {
+        match columns[index].1 {
+            ColumnKind::Int => {
+                let values: Vec<i64> = rows
+                    .iter()
+                    .map(|row| match &row[index] {
+                        Cell::Int(value) => *value as i64,
+                        _ => 0,
+                    })
+                    .collect();
+                column
+                    .typed::<Int64Type>()
+                    .write_batch(&values, None, None)?;
+            }
+            ColumnKind::Text => {
+                let values: Vec<ByteArray> = rows
+                    .iter()
+                    .map(|row| ByteArray::from(row[index].to_string().into_bytes()))
+                    .collect();
+                column
+                    .typed::<ByteArrayType>()
+                    .write_batch(&values, None, None)?;
+            }
+        }
+        column.close()?;
+        index += 1;
+    }
+    row_group.close()?;
+    writer.close()?;
+    Ok(rows.len())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn write_parquet(
+    _table: ExportTable,
+    _rows: &[Row],
+    _out: &mut (impl Write + Send),
+) -> Result<usize> {
+    Err(eyre!("Parquet exports are not available in the browser"))
+}
+
+fn join_felts(felts: &[FieldElement]) -> String {
+    let felts: Vec<String> = felts.iter().map(|felt| format!("{felt:#x}")).collect();
+    felts.join(";")
+}
+
+fn transaction_type(transaction: &Transaction) -> &'static str {
+    match transaction {
+        Transaction::Invoke(_) => "invoke",
+        Transaction::Declare(_) => "declare",
+        Transaction::Deploy(_) => "deploy",
+        Transaction::DeployAccount(_) => "deploy_account",
+        Transaction::L1Handler(_) => "l1_handler",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use starknet::providers::jsonrpc::models::{
+        BlockStatus, Event, InvokeTransaction, InvokeTransactionReceipt, InvokeTransactionV0,
+        TransactionReceipt, TransactionStatus,
+    };
+
+    fn block_with_event() -> (BlockWithTxs, Vec<MaybePendingTransactionReceipt>) {
+        let block = BlockWithTxs {
+            status: BlockStatus::AcceptedOnL1,
+            block_hash: FieldElement::from(0xb10c_u64),
+            parent_hash: FieldElement::ZERO,
+            block_number: 10,
+            new_root: FieldElement::ONE,
+            timestamp: 1_000_000,
+            sequencer_address: FieldElement::TWO,
+            transactions: vec![Transaction::Invoke(InvokeTransaction::V0(
+                InvokeTransactionV0 {
+                    transaction_hash: FieldElement::from(0x1234_u64),
+                    max_fee: FieldElement::ZERO,
+                    signature: vec![],
+                    nonce: FieldElement::ZERO,
+                    contract_address: FieldElement::ONE,
+                    entry_point_selector: FieldElement::TWO,
+                    calldata: vec![],
+                },
+            ))],
+        };
+        let receipt = MaybePendingTransactionReceipt::Receipt(TransactionReceipt::Invoke(
+            InvokeTransactionReceipt {
+                transaction_hash: FieldElement::from(0x1234_u64),
+                actual_fee: FieldElement::from(0x10_u64),
+                status: TransactionStatus::AcceptedOnL1,
+                block_hash: block.block_hash,
+                block_number: 10,
+                messages_sent: vec![],
+                events: vec![Event {
+                    from_address: FieldElement::from(0x42_u64),
+                    keys: vec![FieldElement::ONE, FieldElement::TWO],
+                    data: vec![FieldElement::from(3_u64)],
+                }],
+            },
+        ));
+        (block, vec![receipt])
+    }
+
+    #[test]
+    fn test_csv_export_writes_a_row_per_event() {
+        let (block, receipts) = block_with_event();
+        let rows = table_rows(ExportTable::Events, &block, &receipts);
+
+        let mut csv = Vec::new();
+        assert_eq!(
+            write_rows(ExportTable::Events, ExportFormat::Csv, &rows, &mut csv).unwrap(),
+            1
+        );
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "block_number,transaction_index,transaction_hash,event_index,from_address,keys,data\n\
+             10,0,0x1234,0,0x42,0x1;0x2,0x3\n"
+        );
+        assert_eq!(
+            table_rows(ExportTable::Receipts, &block, &receipts)[0][3],
+            Cell::Felt(FieldElement::from(0x10_u64))
+        );
+        assert!(check_range(11, 10, MAX_EXPORT_BLOCKS).is_err());
+        assert_eq!(
+            "Events".parse::<ExportTable>().unwrap(),
+            ExportTable::Events
+        );
+    }
+
+    #[test]
+    fn test_parquet_export_is_read_back() {
+        use parquet::file::reader::{FileReader, SerializedFileReader};
+
+        let (block, receipts) = block_with_event();
+        let rows = table_rows(ExportTable::Blocks, &block, &receipts);
+        let path =
+            std::env::temp_dir().join(format!("beerus-export-{}.parquet", std::process::id()));
+        let mut file = std::fs::File::create(&path).unwrap();
+        write_rows(ExportTable::Blocks, ExportFormat::Parquet, &rows, &mut file).unwrap();
+
+        let reader = SerializedFileReader::new(std::fs::File::open(&path).unwrap()).unwrap();
+        let metadata = reader.metadata().file_metadata();
+        assert_eq!(metadata.num_rows(), 1);
+        assert_eq!(metadata.schema_descr().column(1).name(), "block_hash");
+        let row = reader.get_row_iter(None).unwrap().next().unwrap();
+        assert_eq!(row.get_long(0).unwrap(), 10);
+        assert_eq!(row.get_string(1).unwrap(), "0xb10c");
+        std::fs::remove_file(path).unwrap();
+    }
+}
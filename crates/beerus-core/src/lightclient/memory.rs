@@ -0,0 +1,94 @@
+//! Approximate accounting of the memory used by the node data, against which the memory budget
+//! is enforced. The blocks are measured by their serialized size, the events, receipts and
+//! indexes by the size of their entries and of the field elements they hold.
+use super::starknet::block_hash::receipt_events;
+use serde::{Deserialize, Serialize};
+use starknet::{
+    core::types::FieldElement,
+    providers::jsonrpc::models::{EmittedEvent, Event, MaybePendingTransactionReceipt},
+};
+use std::mem::size_of;
+
+/// Memory used by the node data, as exposed by `beerus_status`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemoryUsage {
+    /// Retained blocks, or their headers, as stored.
+    pub payload_bytes: usize,
+    /// Events of the retained blocks.
+    pub events_bytes: usize,
+    /// Receipts cached for the retained blocks.
+    pub receipts_bytes: usize,
+    /// Event indexes, class declarations, contract deployments and proven state roots.
+    pub indexes_bytes: usize,
+    pub total_bytes: usize,
+    /// Budget the total is kept within, if any.
+    pub budget_bytes: Option<usize>,
+    /// Blocks evicted to stay within the budget since the start.
+    pub evicted_blocks: u64,
+    /// Blocks whose cached receipts were evicted to stay within the budget since the start.
+    pub evicted_receipts: u64,
+}
+
+/// Approximate memory used by the events of a block.
+pub fn events_bytes(events: &[EmittedEvent]) -> usize {
+    events
+        .iter()
+        .map(|event| size_of::<EmittedEvent>() + felts_bytes(event.keys.len() + event.data.len()))
+        .sum()
+}
+
+/// Approximate memory used by the receipts of a block, with the events they hold.
+pub fn receipts_bytes(receipts: &[MaybePendingTransactionReceipt]) -> usize {
+    receipts
+        .iter()
+        .map(|receipt| {
+            let events = match receipt {
+                MaybePendingTransactionReceipt::Receipt(receipt) => receipt_events(receipt)
+                    .iter()
+                    .map(|event| {
+                        size_of::<Event>() + felts_bytes(event.keys.len() + event.data.len())
+                    })
+                    .sum(),
+                MaybePendingTransactionReceipt::PendingReceipt(_) => 0,
+            };
+            size_of::<MaybePendingTransactionReceipt>() + events
+        })
+        .sum()
+}
+
+/// Approximate memory used by a map of `len` entries.
+pub fn map_bytes<K, V>(len: usize) -> usize {
+    len * (size_of::<K>() + size_of::<V>())
+}
+
+fn felts_bytes(len: usize) -> usize {
+    len * size_of::<FieldElement>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events_are_measured_by_their_field_elements() {
+        let event = |len: usize| EmittedEvent {
+            from_address: FieldElement::ONE,
+            keys: vec![FieldElement::TWO; len],
+            data: vec![FieldElement::THREE; len],
+            block_hash: FieldElement::ONE,
+            block_number: 1,
+            transaction_hash: FieldElement::ONE,
+        };
+
+        let small = events_bytes(&[event(1)]);
+        let large = events_bytes(&[event(3)]);
+
+        assert_eq!(
+            small,
+            size_of::<EmittedEvent>() + 2 * size_of::<FieldElement>()
+        );
+        assert_eq!(large - small, 4 * size_of::<FieldElement>());
+        assert_eq!(events_bytes(&[event(1), event(3)]), small + large);
+        assert_eq!(receipts_bytes(&[]), 0);
+    }
+}
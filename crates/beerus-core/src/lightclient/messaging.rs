@@ -0,0 +1,485 @@
+use ethers::{
+    abi::{Abi, RawLog, Token},
+    types::{Address, Bytes, Log, H256, U256},
+    utils::keccak256,
+};
+use eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
+use starknet::{
+    core::{crypto::compute_hash_on_elements, types::FieldElement},
+    providers::jsonrpc::models::L1HandlerTransaction,
+};
+use std::{collections::HashMap, sync::Mutex};
+use tokio::sync::oneshot;
+
+use crate::ethers_helper::{self, felt_to_u256, u256_to_felt};
+
+/// Message sent from a StarkNet contract to an L1 contract.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct L2ToL1Message {
+    /// The StarkNet contract which sent the message.
+    pub from_address: FieldElement,
+    /// The L1 contract consuming the message.
+    pub to_address: Address,
+    pub payload: Vec<FieldElement>,
+}
+
+impl L2ToL1Message {
+    /// Compute the hash under which the StarkNet core contract stores the message:
+    /// `keccak256(from_address, to_address, payload.length, payload)`, each as a uint256.
+    pub fn hash(&self) -> H256 {
+        let mut data = Vec::with_capacity(32 * (3 + self.payload.len()));
+        data.extend_from_slice(&self.from_address.to_bytes_be());
+        data.extend_from_slice(H256::from(self.to_address).as_bytes());
+        data.extend_from_slice(&ethers_helper::u256_to_bytes32_slice(U256::from(
+            self.payload.len(),
+        )));
+        for value in self.payload.iter() {
+            data.extend_from_slice(&value.to_bytes_be());
+        }
+        H256::from(keccak256(data))
+    }
+
+    /// Build the calldata of the `consumeMessageFromL2` call the L1 contract has to make to
+    /// the StarkNet core contract.
+    pub fn consume_calldata(&self, starknet_core_abi: Abi) -> Result<Bytes> {
+        let from_address = Token::Uint(felt_to_u256(self.from_address));
+        let payload = Token::Array(
+            self.payload
+                .iter()
+                .map(|value| Token::Uint(felt_to_u256(*value)))
+                .collect(),
+        );
+        Ok(ethers_helper::encode_function_data(
+            (from_address, payload),
+            starknet_core_abi,
+            "consumeMessageFromL2",
+        )?)
+    }
+}
+
+/// Everything needed to consume an L2 to L1 message on L1.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct L2ToL1MessageConsumption {
+    pub message_hash: H256,
+    /// Whether the message was proven on L1 and can be consumed.
+    pub ready: bool,
+    /// Number of copies of the message waiting to be consumed.
+    pub pending_count: U256,
+    /// The StarkNet core contract, target of the `consumeMessageFromL2` call.
+    pub to: Address,
+    pub calldata: Bytes,
+    /// Gas used by the `consumeMessageFromL2` call alone, when requested and the message is
+    /// ready. The transaction of the L1 contract will use more.
+    pub gas_estimate: Option<u64>,
+}
+
+/// Maximum fee accepted by the StarkNet core contract for an L1 to L2 message: 1 ether.
+pub const MAX_L1_TO_L2_MESSAGE_FEE: u64 = 1_000_000_000_000_000_000;
+
+/// Message sent from an L1 contract to a StarkNet contract.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct L1ToL2Message {
+    /// The L1 contract sending the message.
+    pub from_address: Address,
+    /// The StarkNet contract receiving the message.
+    pub to_address: FieldElement,
+    /// Selector of the L1 handler called on the StarkNet contract.
+    pub selector: FieldElement,
+    pub payload: Vec<FieldElement>,
+    /// Nonce given to the message by the StarkNet core contract.
+    pub nonce: U256,
+}
+
+impl L1ToL2Message {
+    /// Compute the hash under which the StarkNet core contract stores the message:
+    /// `keccak256(from_address, to_address, nonce, selector, payload.length, payload)`, each as
+    /// a uint256.
+    pub fn hash(&self) -> H256 {
+        let mut data = Vec::with_capacity(32 * (5 + self.payload.len()));
+        data.extend_from_slice(H256::from(self.from_address).as_bytes());
+        data.extend_from_slice(&self.to_address.to_bytes_be());
+        data.extend_from_slice(&ethers_helper::u256_to_bytes32_slice(self.nonce));
+        data.extend_from_slice(&self.selector.to_bytes_be());
+        data.extend_from_slice(&ethers_helper::u256_to_bytes32_slice(U256::from(
+            self.payload.len(),
+        )));
+        for value in self.payload.iter() {
+            data.extend_from_slice(&value.to_bytes_be());
+        }
+        H256::from(keccak256(data))
+    }
+
+    /// Build the calldata of the `sendMessageToL2` call to the StarkNet core contract.
+    pub fn send_calldata(&self, starknet_core_abi: Abi) -> Result<Bytes> {
+        let to_address = Token::Uint(felt_to_u256(self.to_address));
+        let selector = Token::Uint(felt_to_u256(self.selector));
+        let payload = Token::Array(
+            self.payload
+                .iter()
+                .map(|value| Token::Uint(felt_to_u256(*value)))
+                .collect(),
+        );
+        Ok(ethers_helper::encode_function_data(
+            (to_address, selector, payload),
+            starknet_core_abi,
+            "sendMessageToL2",
+        )?)
+    }
+
+    /// Decode a `LogMessageToL2` event emitted by the StarkNet core contract when the message
+    /// was sent.
+    pub fn from_log(starknet_core_abi: &Abi, log: &Log) -> Result<Self> {
+        let event = starknet_core_abi.event("LogMessageToL2")?;
+        let parsed = event.parse_log(RawLog {
+            topics: log.topics.clone(),
+            data: log.data.to_vec(),
+        })?;
+        let param = |index: usize| parsed.params.get(index).map(|param| &param.value);
+        match (param(0), param(1), param(2), param(3), param(4)) {
+            (
+                Some(Token::Address(from_address)),
+                Some(Token::Uint(to_address)),
+                Some(Token::Uint(selector)),
+                Some(Token::Array(payload)),
+                Some(Token::Uint(nonce)),
+            ) => Ok(Self {
+                from_address: *from_address,
+                to_address: u256_to_felt(*to_address)?,
+                selector: u256_to_felt(*selector)?,
+                payload: payload
+                    .iter()
+                    .map(|value| match value {
+                        Token::Uint(value) => u256_to_felt(*value),
+                        _ => Err(eyre!("Invalid LogMessageToL2 payload")),
+                    })
+                    .collect::<Result<_>>()?,
+                nonce: *nonce,
+            }),
+            _ => Err(eyre!("Invalid LogMessageToL2 event")),
+        }
+    }
+
+    /// Compute the hash of the L1 handler transaction delivering this message on StarkNet:
+    /// the Pedersen hash of the `l1_handler` prefix, version 0, the receiving contract, the
+    /// selector, the hash of the calldata, a max fee of 0, the chain id and the nonce. The
+    /// calldata is the sender followed by the payload.
+    pub fn l1_handler_transaction_hash(&self, chain_id: FieldElement) -> Result<FieldElement> {
+        let mut calldata = vec![FieldElement::from_byte_slice_be(
+            self.from_address.as_bytes(),
+        )?];
+        calldata.extend_from_slice(&self.payload);
+        Ok(compute_hash_on_elements(&[
+            FieldElement::from_byte_slice_be(b"l1_handler")?,
+            FieldElement::ZERO,
+            self.to_address,
+            self.selector,
+            compute_hash_on_elements(&calldata),
+            FieldElement::ZERO,
+            chain_id,
+            u256_to_felt(self.nonce)?,
+        ]))
+    }
+
+    /// Whether an L1 handler transaction is the delivery of this message on StarkNet.
+    /// The sender of the message is the first element of the L1 handler calldata.
+    pub fn is_delivered_by(&self, transaction: &L1HandlerTransaction) -> bool {
+        let from_address = match FieldElement::from_byte_slice_be(self.from_address.as_bytes()) {
+            Ok(from_address) => from_address,
+            Err(_) => return false,
+        };
+        transaction.contract_address == self.to_address
+            && transaction.entry_point_selector == self.selector
+            && U256::from(transaction.nonce) == self.nonce
+            && transaction.calldata.split_first() == Some((&from_address, self.payload.as_slice()))
+    }
+}
+
+/// Everything needed to send an L1 to L2 message, and to track it afterwards.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct L1ToL2MessageDeposit {
+    /// The message, with the nonce it will be given if sent now.
+    pub message: L1ToL2Message,
+    pub message_hash: H256,
+    /// The StarkNet core contract, target of the `sendMessageToL2` call.
+    pub to: Address,
+    pub calldata: Bytes,
+    /// Value of the `sendMessageToL2` call, paying the L1 handler fee.
+    pub value: U256,
+}
+
+/// L1 handler transaction which delivered an L1 to L2 message.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct L1ToL2MessageDelivery {
+    pub block_number: u64,
+    pub transaction_hash: FieldElement,
+}
+
+/// Messages sent to StarkNet by an L1 transaction, with their delivery, as returned by
+/// `beerus_getL1ToL2MessageStatus`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct L1ToL2MessageStatus {
+    pub message: L1ToL2Message,
+    pub message_hash: H256,
+    pub l1_transaction_hash: H256,
+    /// Hash of the L1 handler transaction which delivers the message on StarkNet.
+    pub l2_transaction_hash: FieldElement,
+    /// The verified delivery of the message, `None` until it lands in a block proven on L1.
+    pub delivery: Option<L1ToL2MessageDelivery>,
+}
+
+/// Consumption of an L2 to L1 message on L1, seen in a `ConsumedMessageToL1` event.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConsumedMessage {
+    pub message: L2ToL1Message,
+    pub message_hash: H256,
+    pub l1_block_number: Option<u64>,
+    pub l1_transaction_hash: Option<H256>,
+}
+
+impl ConsumedMessage {
+    /// Decode a `ConsumedMessageToL1` event emitted by the StarkNet core contract.
+    pub fn from_log(starknet_core_abi: &Abi, log: &Log) -> Result<Self> {
+        let event = starknet_core_abi.event("ConsumedMessageToL1")?;
+        let parsed = event.parse_log(RawLog {
+            topics: log.topics.clone(),
+            data: log.data.to_vec(),
+        })?;
+        let message = match (
+            parsed.params.get(0).map(|p| &p.value),
+            parsed.params.get(1).map(|p| &p.value),
+            parsed.params.get(2).map(|p| &p.value),
+        ) {
+            (
+                Some(Token::Uint(from_address)),
+                Some(Token::Address(to_address)),
+                Some(Token::Array(payload)),
+            ) => L2ToL1Message {
+                from_address: u256_to_felt(*from_address)?,
+                to_address: *to_address,
+                payload: payload
+                    .iter()
+                    .map(|value| match value {
+                        Token::Uint(value) => u256_to_felt(*value),
+                        _ => Err(eyre!("Invalid ConsumedMessageToL1 payload")),
+                    })
+                    .collect::<Result<_>>()?,
+            },
+            _ => return Err(eyre!("Invalid ConsumedMessageToL1 event")),
+        };
+        Ok(Self {
+            message_hash: message.hash(),
+            message,
+            l1_block_number: log.block_number.map(|number| number.as_u64()),
+            l1_transaction_hash: log.transaction_hash,
+        })
+    }
+}
+
+/// Subscriptions to the consumption of L2 to L1 messages on L1, by message hash.
+/// The subscribers are notified by the message watcher task, see
+/// [`BeerusLightClient::watch_l2_to_l1_message`](super::beerus::BeerusLightClient::watch_l2_to_l1_message).
+#[derive(Debug, Default)]
+pub struct MessageWatcher {
+    subscribers: Mutex<HashMap<H256, Vec<oneshot::Sender<ConsumedMessage>>>>,
+}
+
+impl MessageWatcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Subscribe to the consumption of a message.
+    /// Only the consumptions happening after the subscription are reported.
+    pub fn subscribe(&self, message_hash: H256) -> oneshot::Receiver<ConsumedMessage> {
+        let (sender, receiver) = oneshot::channel();
+        self.subscribers
+            .lock()
+            .unwrap()
+            .entry(message_hash)
+            .or_default()
+            .push(sender);
+        receiver
+    }
+
+    /// Whether a message is still awaited. Dropped subscriptions are pruned.
+    pub fn has_subscribers(&self) -> bool {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        subscribers.retain(|_, senders| {
+            senders.retain(|sender| !sender.is_closed());
+            !senders.is_empty()
+        });
+        !subscribers.is_empty()
+    }
+
+    /// Notify the subscribers of a consumed message.
+    /// # Returns
+    /// The number of notified subscribers.
+    pub fn notify(&self, consumed: &ConsumedMessage) -> usize {
+        let senders = self
+            .subscribers
+            .lock()
+            .unwrap()
+            .remove(&consumed.message_hash)
+            .unwrap_or_default();
+        senders
+            .into_iter()
+            .map(|sender| sender.send(consumed.clone()).is_ok())
+            .filter(|sent| *sent)
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message() -> L2ToL1Message {
+        L2ToL1Message {
+            from_address: FieldElement::from_hex_be("0x123").unwrap(),
+            to_address: Address::from_low_u64_be(0x456),
+            payload: vec![FieldElement::ONE, FieldElement::TWO],
+        }
+    }
+
+    #[test]
+    fn test_message_hash_packs_every_field_as_uint256() {
+        let mut data = vec![0u8; 32 * 5];
+        data[30..32].copy_from_slice(&[0x01, 0x23]);
+        data[62..64].copy_from_slice(&[0x04, 0x56]);
+        data[95] = 2;
+        data[127] = 1;
+        data[159] = 2;
+
+        assert_eq!(message().hash(), H256::from(keccak256(data)));
+    }
+
+    #[test]
+    fn test_l1_to_l2_message_is_delivered_by_matching_l1_handler() {
+        let message = L1ToL2Message {
+            from_address: Address::from_low_u64_be(0x456),
+            to_address: FieldElement::from_hex_be("0x123").unwrap(),
+            selector: FieldElement::from_hex_be("0x789").unwrap(),
+            payload: vec![FieldElement::ONE],
+            nonce: U256::from(7),
+        };
+        let mut transaction = L1HandlerTransaction {
+            transaction_hash: FieldElement::from_hex_be("0xabc").unwrap(),
+            version: 0,
+            nonce: 7,
+            contract_address: message.to_address,
+            entry_point_selector: message.selector,
+            calldata: vec![
+                FieldElement::from_hex_be("0x456").unwrap(),
+                FieldElement::ONE,
+            ],
+        };
+        assert!(message.is_delivered_by(&transaction));
+
+        transaction.nonce = 8;
+        assert!(!message.is_delivered_by(&transaction));
+    }
+
+    #[test]
+    fn test_consume_calldata_starts_with_selector() {
+        let abi: Abi =
+            serde_json::from_str(include_str!("../resources/starknet_core_abi.json")).unwrap();
+
+        let calldata = message().consume_calldata(abi).unwrap();
+
+        assert_eq!(
+            calldata[..4],
+            ethers::utils::id("consumeMessageFromL2(uint256,uint256[])")
+        );
+    }
+
+    #[test]
+    fn test_consumed_message_from_log() {
+        let abi: Abi =
+            serde_json::from_str(include_str!("../resources/starknet_core_abi.json")).unwrap();
+        let event = abi.event("ConsumedMessageToL1").unwrap();
+        let log = Log {
+            topics: vec![
+                event.signature(),
+                H256::from_low_u64_be(0x123),
+                H256::from(Address::from_low_u64_be(0x456)),
+            ],
+            data: ethers::abi::encode(&[Token::Array(vec![
+                Token::Uint(U256::one()),
+                Token::Uint(U256::from(2)),
+            ])])
+            .into(),
+            block_number: Some(16_000_000.into()),
+            ..Default::default()
+        };
+
+        let consumed = ConsumedMessage::from_log(&abi, &log).unwrap();
+
+        assert_eq!(consumed.message, message());
+        assert_eq!(consumed.message_hash, message().hash());
+        assert_eq!(consumed.l1_block_number, Some(16_000_000));
+    }
+
+    #[test]
+    fn test_l1_to_l2_message_from_log() {
+        let abi: Abi =
+            serde_json::from_str(include_str!("../resources/starknet_core_abi.json")).unwrap();
+        let event = abi.event("LogMessageToL2").unwrap();
+        let log = Log {
+            topics: vec![
+                event.signature(),
+                H256::from(Address::from_low_u64_be(0x456)),
+                H256::from_low_u64_be(0x123),
+                H256::from_low_u64_be(0x789),
+            ],
+            data: ethers::abi::encode(&[
+                Token::Array(vec![Token::Uint(U256::one())]),
+                Token::Uint(U256::from(7)),
+                Token::Uint(U256::from(1_000)),
+            ])
+            .into(),
+            ..Default::default()
+        };
+
+        let message = L1ToL2Message::from_log(&abi, &log).unwrap();
+
+        assert_eq!(
+            message,
+            L1ToL2Message {
+                from_address: Address::from_low_u64_be(0x456),
+                to_address: FieldElement::from_hex_be("0x123").unwrap(),
+                selector: FieldElement::from_hex_be("0x789").unwrap(),
+                payload: vec![FieldElement::ONE],
+                nonce: U256::from(7),
+            }
+        );
+        assert_ne!(
+            message
+                .l1_handler_transaction_hash(FieldElement::ONE)
+                .unwrap(),
+            message
+                .l1_handler_transaction_hash(FieldElement::TWO)
+                .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_message_watcher_notifies_subscribers_once() {
+        let watcher = MessageWatcher::new();
+        let receiver = watcher.subscribe(message().hash());
+        let dropped = watcher.subscribe(H256::from_low_u64_be(1));
+        drop(dropped);
+        let consumed = ConsumedMessage {
+            message: message(),
+            message_hash: message().hash(),
+            l1_block_number: None,
+            l1_transaction_hash: None,
+        };
+
+        assert!(watcher.has_subscribers());
+        assert_eq!(watcher.notify(&consumed), 1);
+        assert_eq!(watcher.notify(&consumed), 0);
+        assert_eq!(receiver.await.unwrap(), consumed);
+        assert!(!watcher.has_subscribers());
+    }
+}
@@ -1,3 +1,17 @@
+pub mod abi;
+pub mod alerts;
+pub mod archive;
 pub mod beerus;
+pub mod class_index;
+pub mod contract_index;
 pub mod ethereum;
+pub mod event_index;
+pub mod export;
+pub mod memory;
+pub mod messaging;
+pub mod nonce_manager;
+pub mod payload;
+pub mod service;
 pub mod starknet;
+pub mod transport;
+pub mod upgrade_monitor;
@@ -0,0 +1,225 @@
+//! Nonces of the transactions submitted through the light client. The nonce proven on L1 lags
+//! behind the transactions in flight, so the ones submitted locally are tracked per account
+//! until the chain catches up with them.
+use super::{beerus::BeerusLightClient, starknet::fee::BroadcastedTransactionV3};
+use eyre::{eyre, Result};
+use log::warn;
+use starknet::{
+    core::types::FieldElement,
+    providers::jsonrpc::models::{
+        BlockId, BlockTag as StarknetBlockTag, BroadcastedInvokeTransaction,
+        InvokeTransactionResult,
+    },
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Mutex,
+};
+
+/// Tracks the transactions submitted per account, and suggests the nonce of the next one.
+#[derive(Debug, Default)]
+pub struct NonceManager {
+    /// Hashes of the transactions submitted by each account, by nonce.
+    submitted: Mutex<HashMap<FieldElement, BTreeMap<FieldElement, FieldElement>>>,
+}
+
+impl NonceManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Suggest the next nonce of an account, reading its proven and pending nonces.
+    /// The pending nonce is skipped if the provider fails to serve it.
+    /// # Arguments
+    /// * `beerus` - The light client the nonces are read from.
+    /// * `account` - The account address.
+    pub async fn next_nonce(
+        &self,
+        beerus: &BeerusLightClient,
+        account: FieldElement,
+    ) -> Result<FieldElement> {
+        let proven = beerus.starknet_get_nonce(account).await?;
+        let pending = match beerus
+            .starknet_lightclient
+            .get_nonce_at(&BlockId::Tag(StarknetBlockTag::Pending), account)
+            .await
+        {
+            Ok(pending) => Some(pending),
+            Err(err) => {
+                warn!("Failed to read the pending nonce of {account:#x}: {err}");
+                None
+            }
+        };
+        Ok(self.reconcile(account, proven, pending))
+    }
+
+    /// Suggest the next nonce of an account from the nonces read on chain.
+    /// The transactions the chain went past are forgotten, the next nonce is the first one
+    /// from the chain nonce which wasn't submitted. A transaction which was dropped leaves a
+    /// gap, which is filled first.
+    /// # Arguments
+    /// * `account` - The account address.
+    /// * `proven` - The nonce of the account at the last block proven on L1.
+    /// * `pending` - The nonce of the account at the pending block, if known.
+    pub fn reconcile(
+        &self,
+        account: FieldElement,
+        proven: FieldElement,
+        pending: Option<FieldElement>,
+    ) -> FieldElement {
+        let chain_nonce = pending.map_or(proven, |pending| pending.max(proven));
+        let mut submitted = self.submitted.lock().unwrap();
+        let mut next = chain_nonce;
+        if let Some(transactions) = submitted.get_mut(&account) {
+            *transactions = transactions.split_off(&chain_nonce);
+            while transactions.contains_key(&next) {
+                next += FieldElement::ONE;
+            }
+            if transactions.is_empty() {
+                submitted.remove(&account);
+            }
+        }
+        next
+    }
+
+    /// Record a transaction accepted by the provider.
+    pub fn record_submitted(
+        &self,
+        account: FieldElement,
+        nonce: FieldElement,
+        transaction_hash: FieldElement,
+    ) {
+        self.submitted
+            .lock()
+            .unwrap()
+            .entry(account)
+            .or_default()
+            .insert(nonce, transaction_hash);
+    }
+
+    /// Forget the transactions of an account, the next nonce is then read from the chain only.
+    pub fn reset(&self, account: FieldElement) {
+        self.submitted.lock().unwrap().remove(&account);
+    }
+
+    /// Return the transactions of an account not yet seen on chain, as nonce and hash.
+    pub fn submitted(&self, account: FieldElement) -> Vec<(FieldElement, FieldElement)> {
+        self.submitted
+            .lock()
+            .unwrap()
+            .get(&account)
+            .map(|transactions| {
+                transactions
+                    .iter()
+                    .map(|(nonce, hash)| (*nonce, *hash))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Submit an invoke transaction and track its nonce.
+    /// The transactions of the account are forgotten when the provider rejects the nonce, so
+    /// that the next suggestion starts over from the chain.
+    /// # Arguments
+    /// * `beerus` - The light client the transaction is submitted through.
+    /// * `transaction` - The invoke transaction.
+    pub async fn add_invoke_transaction(
+        &self,
+        beerus: &BeerusLightClient,
+        transaction: &BroadcastedInvokeTransaction,
+    ) -> Result<InvokeTransactionResult> {
+        let (account, nonce) = match transaction {
+            BroadcastedInvokeTransaction::V0(transaction) => {
+                (transaction.contract_address, transaction.nonce)
+            }
+            BroadcastedInvokeTransaction::V1(transaction) => {
+                (transaction.sender_address, transaction.nonce)
+            }
+        };
+        let result = beerus
+            .starknet_lightclient
+            .add_invoke_transaction(transaction)
+            .await;
+        self.track(account, nonce, result)
+    }
+
+    /// Submit an invoke transaction of version 3, paying its fee in STRK, and track its nonce.
+    /// # Arguments
+    /// * `beerus` - The light client the transaction is submitted through.
+    /// * `transaction` - The invoke transaction.
+    pub async fn add_invoke_transaction_v3(
+        &self,
+        beerus: &BeerusLightClient,
+        transaction: &BroadcastedTransactionV3,
+    ) -> Result<InvokeTransactionResult> {
+        let (account, nonce) = match (transaction.sender_address(), transaction.nonce()) {
+            (Some(account), Some(nonce)) => (account, nonce),
+            _ => return Err(eyre!("Invoke transaction without sender address or nonce")),
+        };
+        let result = beerus
+            .starknet_lightclient
+            .add_invoke_transaction_v3(transaction)
+            .await;
+        self.track(account, nonce, result)
+    }
+
+    /// Record a submitted transaction, or forget the account if its nonce was rejected.
+    fn track(
+        &self,
+        account: FieldElement,
+        nonce: FieldElement,
+        result: Result<InvokeTransactionResult>,
+    ) -> Result<InvokeTransactionResult> {
+        match result {
+            Ok(result) => {
+                self.record_submitted(account, nonce, result.transaction_hash);
+                Ok(result)
+            }
+            Err(err) => {
+                if err.to_string().to_lowercase().contains("nonce") {
+                    self.reset(account);
+                }
+                Err(err)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn felt(value: u64) -> FieldElement {
+        FieldElement::from(value)
+    }
+
+    #[test]
+    fn test_next_nonce_follows_the_submitted_transactions() {
+        let manager = NonceManager::new();
+        let account = felt(1);
+        assert_eq!(manager.reconcile(account, felt(3), None), felt(3));
+
+        manager.record_submitted(account, felt(3), felt(0x33));
+        manager.record_submitted(account, felt(4), felt(0x44));
+        assert_eq!(manager.reconcile(account, felt(3), None), felt(5));
+        assert_eq!(manager.reconcile(account, felt(3), Some(felt(4))), felt(5));
+        assert_eq!(manager.submitted(account), vec![(felt(4), felt(0x44))]);
+
+        assert_eq!(manager.reconcile(account, felt(5), Some(felt(5))), felt(5));
+        assert!(manager.submitted(account).is_empty());
+    }
+
+    #[test]
+    fn test_dropped_transaction_leaves_a_gap_filled_first() {
+        let manager = NonceManager::new();
+        let account = felt(1);
+        manager.record_submitted(account, felt(3), felt(0x33));
+        manager.record_submitted(account, felt(5), felt(0x55));
+        assert_eq!(manager.reconcile(account, felt(3), None), felt(4));
+        assert_eq!(manager.reconcile(account, felt(2), Some(felt(4))), felt(4));
+
+        manager.reset(account);
+        assert_eq!(manager.reconcile(account, felt(2), Some(felt(4))), felt(4));
+        assert!(manager.submitted(account).is_empty());
+    }
+}
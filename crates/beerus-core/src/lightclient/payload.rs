@@ -0,0 +1,532 @@
+use super::starknet::block_hash::{transaction_hash, BlockHeader};
+use log::error;
+use serde::{Deserialize, Serialize};
+use starknet::{core::types::FieldElement, providers::jsonrpc::models::BlockWithTxs};
+use std::{collections::BTreeMap, ops::RangeBounds, sync::Arc};
+
+/// Memory usage of the payload blocks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PayloadStats {
+    pub blocks: usize,
+    pub compressed_blocks: usize,
+    /// Serialized size of the compressed blocks.
+    pub raw_bytes: usize,
+    /// Size of the compressed blocks once compressed.
+    pub stored_bytes: usize,
+}
+
+impl PayloadStats {
+    /// Memory saved by the compression.
+    pub fn saved_bytes(&self) -> usize {
+        self.raw_bytes.saturating_sub(self.stored_bytes)
+    }
+}
+
+/// Memory used by a single block of the payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockUsage {
+    pub block_number: u64,
+    pub compressed: bool,
+    /// Serialized size of the block.
+    pub raw_bytes: usize,
+    /// Size of the block as stored, the serialized size for blocks which are not compressed.
+    pub stored_bytes: usize,
+}
+
+/// A stored block, with the size of the block or header once serialized, measured on insertion.
+#[derive(Debug, Clone)]
+enum StoredBlock {
+    Plain(Arc<BlockWithTxs>, usize),
+    Compressed { bytes: Arc<[u8]>, raw_size: usize },
+    Header(Arc<BlockHeader>, usize),
+}
+
+/// Blocks fetched by the light client, by block number.
+/// When a compression level is set, the blocks are stored compressed with zstd and
+/// decompressed on access. In header-only mode, only the headers and the commitments of the
+/// blocks are stored, the blocks themselves are never returned.
+/// The maps are shared between clones until one of them is modified.
+#[derive(Debug, Clone, Default)]
+pub struct Payload {
+    blocks: Arc<BTreeMap<u64, StoredBlock>>,
+    hashes: Arc<BTreeMap<FieldElement, u64>>,
+    /// Block number and index of the transactions of the stored blocks, by hash.
+    transactions: Arc<BTreeMap<FieldElement, (u64, usize)>>,
+    compression_level: Option<i32>,
+    headers_only: bool,
+}
+
+impl Payload {
+    pub fn new(compression_level: Option<i32>) -> Self {
+        Self {
+            blocks: Arc::default(),
+            hashes: Arc::default(),
+            transactions: Arc::default(),
+            compression_level,
+            headers_only: false,
+        }
+    }
+
+    /// Store only the headers of the blocks, see [`Self::insert_header`].
+    pub fn with_headers_only(mut self, headers_only: bool) -> Self {
+        self.headers_only = headers_only;
+        self
+    }
+
+    pub fn is_headers_only(&self) -> bool {
+        self.headers_only
+    }
+
+    /// Insert the header of a block, in place of the block if it was stored.
+    pub fn insert_header(&mut self, header: BlockHeader) {
+        self.remove_indexes_of(header.block_number);
+        Arc::make_mut(&mut self.hashes).insert(header.block_hash, header.block_number);
+        let size = serialized_size(&header);
+        Arc::make_mut(&mut self.blocks).insert(
+            header.block_number,
+            StoredBlock::Header(Arc::new(header), size),
+        );
+    }
+
+    /// Return the header of the block with the given number, if only its header is stored.
+    pub fn get_header(&self, block_number: &u64) -> Option<Arc<BlockHeader>> {
+        match self.blocks.get(block_number)? {
+            StoredBlock::Header(header, _) => Some(header.clone()),
+            _ => None,
+        }
+    }
+
+    /// Return the hash of the block with the given number, whether it or its header is stored.
+    pub fn block_hash(&self, block_number: &u64) -> Option<FieldElement> {
+        match self.blocks.get(block_number)? {
+            StoredBlock::Header(header, _) => Some(header.block_hash),
+            stored => load(stored).map(|block| block.block_hash),
+        }
+    }
+
+    /// Return the state root of the block with the given number, whether it or its header is
+    /// stored.
+    pub fn new_root(&self, block_number: &u64) -> Option<FieldElement> {
+        match self.blocks.get(block_number)? {
+            StoredBlock::Header(header, _) => Some(header.new_root),
+            stored => load(stored).map(|block| block.new_root),
+        }
+    }
+
+    /// Insert a block, compressing it if compression is enabled.
+    pub fn insert(&mut self, block: Arc<BlockWithTxs>) {
+        self.remove_indexes_of(block.block_number);
+        Arc::make_mut(&mut self.transactions).extend(block.transactions.iter().enumerate().map(
+            |(index, transaction)| (transaction_hash(transaction), (block.block_number, index)),
+        ));
+        Arc::make_mut(&mut self.hashes).insert(block.block_hash, block.block_number);
+        let stored = match self
+            .compression_level
+            .and_then(|level| compress(&block, level))
+        {
+            Some((bytes, raw_size)) => StoredBlock::Compressed {
+                bytes: bytes.into(),
+                raw_size,
+            },
+            None => StoredBlock::Plain(block.clone(), serialized_size(block.as_ref())),
+        };
+        Arc::make_mut(&mut self.blocks).insert(block.block_number, stored);
+    }
+
+    /// Drop the hash and the transactions of the block stored with the given number from the
+    /// indexes, before it is replaced.
+    fn remove_indexes_of(&mut self, block_number: u64) {
+        let previous_hash = match self.block_hash(&block_number) {
+            Some(previous_hash) => previous_hash,
+            None => return,
+        };
+        if self.hashes.get(&previous_hash) == Some(&block_number) {
+            Arc::make_mut(&mut self.hashes).remove(&previous_hash);
+        }
+        Arc::make_mut(&mut self.transactions).retain(|_, (number, _)| *number != block_number);
+    }
+
+    /// Return the number of the block including a transaction and the index of the transaction
+    /// in it, from the transaction index, without loading the block.
+    pub fn transaction_position(&self, tx_hash: &FieldElement) -> Option<(u64, usize)> {
+        self.transactions.get(tx_hash).copied()
+    }
+
+    /// Return the block with the given number.
+    pub fn get(&self, block_number: &u64) -> Option<Arc<BlockWithTxs>> {
+        self.blocks.get(block_number).and_then(load)
+    }
+
+    /// Return the block with the given hash.
+    pub fn get_by_hash(&self, block_hash: &FieldElement) -> Option<Arc<BlockWithTxs>> {
+        self.hashes
+            .get(block_hash)
+            .and_then(|block_number| self.get(block_number))
+            // The block number may have been replaced by a block with another hash.
+            .filter(|block| block.block_hash == *block_hash)
+    }
+
+    /// Return the number of the block with the given hash, from the hash index, without loading
+    /// the block.
+    pub fn block_number_by_hash(&self, block_hash: &FieldElement) -> Option<u64> {
+        self.hashes.get(block_hash).copied()
+    }
+
+    pub fn contains_key(&self, block_number: &u64) -> bool {
+        self.blocks.contains_key(block_number)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Iterate over the blocks in a range of block numbers, by ascending number.
+    pub fn range<R: RangeBounds<u64>>(
+        &self,
+        range: R,
+    ) -> impl Iterator<Item = Arc<BlockWithTxs>> + '_ {
+        self.blocks
+            .range(range)
+            .filter_map(|(_, stored)| load(stored))
+    }
+
+    /// Iterate over all the blocks, by ascending number.
+    pub fn values(&self) -> impl Iterator<Item = Arc<BlockWithTxs>> + '_ {
+        self.range(..)
+    }
+
+    /// Number of the oldest block retained.
+    pub fn first_block_number(&self) -> Option<u64> {
+        self.blocks.keys().next().copied()
+    }
+
+    /// Number of the newest block retained.
+    pub fn last_block_number(&self) -> Option<u64> {
+        self.blocks.keys().next_back().copied()
+    }
+
+    /// Return the memory used by each block, by ascending number.
+    /// Blocks which are not compressed are measured by their serialized size.
+    pub fn block_usage(&self) -> Vec<BlockUsage> {
+        self.blocks
+            .iter()
+            .map(|(block_number, stored)| match stored {
+                StoredBlock::Plain(_, size) | StoredBlock::Header(_, size) => BlockUsage {
+                    block_number: *block_number,
+                    compressed: false,
+                    raw_bytes: *size,
+                    stored_bytes: *size,
+                },
+                StoredBlock::Compressed { bytes, raw_size } => BlockUsage {
+                    block_number: *block_number,
+                    compressed: true,
+                    raw_bytes: *raw_size,
+                    stored_bytes: bytes.len(),
+                },
+            })
+            .collect()
+    }
+
+    /// Approximate memory used by the stored blocks, see [`Self::block_usage`].
+    pub fn stored_bytes(&self) -> usize {
+        self.blocks
+            .values()
+            .map(|stored| match stored {
+                StoredBlock::Plain(_, size) | StoredBlock::Header(_, size) => *size,
+                StoredBlock::Compressed { bytes, .. } => bytes.len(),
+            })
+            .sum()
+    }
+
+    /// Drop the blocks below `block_number`.
+    /// Returns the number of blocks dropped.
+    pub fn prune_to(&mut self, block_number: u64) -> usize {
+        let blocks = Arc::make_mut(&mut self.blocks);
+        let retained = blocks.split_off(&block_number);
+        let pruned = std::mem::replace(blocks, retained).len();
+        Arc::make_mut(&mut self.hashes).retain(|_, number| *number >= block_number);
+        Arc::make_mut(&mut self.transactions).retain(|_, (number, _)| *number >= block_number);
+        pruned
+    }
+
+    /// Drop the blocks from `block_number` onwards.
+    pub fn truncate(&mut self, block_number: u64) {
+        Arc::make_mut(&mut self.blocks).retain(|number, _| *number < block_number);
+        Arc::make_mut(&mut self.hashes).retain(|_, number| *number < block_number);
+        Arc::make_mut(&mut self.transactions).retain(|_, (number, _)| *number < block_number);
+    }
+
+    /// Compress the blocks which are stored as is while compression is enabled, and drop the
+    /// hashes of the blocks which were replaced.
+    /// Returns the number of blocks compressed.
+    pub fn compact(&mut self) -> usize {
+        let mut compressed = 0;
+        if let Some(level) = self.compression_level {
+            for stored in Arc::make_mut(&mut self.blocks).values_mut() {
+                if let StoredBlock::Plain(block, _) = stored {
+                    if let Some((bytes, raw_size)) = compress(block, level) {
+                        *stored = StoredBlock::Compressed {
+                            bytes: bytes.into(),
+                            raw_size,
+                        };
+                        compressed += 1;
+                    }
+                }
+            }
+        }
+        self.hashes = Arc::new(
+            self.blocks
+                .keys()
+                .filter_map(|block_number| {
+                    self.block_hash(block_number)
+                        .map(|block_hash| (block_hash, *block_number))
+                })
+                .collect(),
+        );
+        compressed
+    }
+
+    pub fn stats(&self) -> PayloadStats {
+        let mut stats = PayloadStats {
+            blocks: self.blocks.len(),
+            ..Default::default()
+        };
+        for stored in self.blocks.values() {
+            if let StoredBlock::Compressed { bytes, raw_size } = stored {
+                stats.compressed_blocks += 1;
+                stats.raw_bytes += raw_size;
+                stats.stored_bytes += bytes.len();
+            }
+        }
+        stats
+    }
+}
+
+fn load(stored: &StoredBlock) -> Option<Arc<BlockWithTxs>> {
+    match stored {
+        StoredBlock::Plain(block, _) => Some(block.clone()),
+        StoredBlock::Header(..) => None,
+        StoredBlock::Compressed { bytes, raw_size } => match decompress(bytes, *raw_size) {
+            Ok(block) => Some(Arc::new(block)),
+            Err(err) => {
+                error!("Corrupted payload block: {}", err);
+                None
+            }
+        },
+    }
+}
+
+fn serialized_size<T: Serialize>(value: &T) -> usize {
+    serde_json::to_vec(value).map_or(0, |raw| raw.len())
+}
+
+/// Compress a block, returning the compressed bytes and the size of the serialized block.
+#[cfg(not(target_arch = "wasm32"))]
+fn compress(block: &BlockWithTxs, level: i32) -> Option<(Vec<u8>, usize)> {
+    let raw = serde_json::to_vec(block).ok()?;
+    match zstd::bulk::compress(&raw, level) {
+        Ok(bytes) => Some((bytes, raw.len())),
+        Err(err) => {
+            error!("Failed to compress block {}: {}", block.block_number, err);
+            None
+        }
+    }
+}
+
+/// zstd is not available in the browser, blocks are always stored as is.
+#[cfg(target_arch = "wasm32")]
+fn compress(_block: &BlockWithTxs, _level: i32) -> Option<(Vec<u8>, usize)> {
+    None
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn decompress(bytes: &[u8], raw_size: usize) -> eyre::Result<BlockWithTxs> {
+    let raw = zstd::bulk::decompress(bytes, raw_size)?;
+    Ok(serde_json::from_slice(&raw)?)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn decompress(_bytes: &[u8], _raw_size: usize) -> eyre::Result<BlockWithTxs> {
+    Err(eyre::eyre!("Compressed blocks are not supported"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use starknet::providers::jsonrpc::models::{
+        BlockStatus, InvokeTransaction, InvokeTransactionV0, Transaction,
+    };
+
+    fn block(block_number: u64, block_hash: u64) -> Arc<BlockWithTxs> {
+        Arc::new(BlockWithTxs {
+            status: BlockStatus::AcceptedOnL2,
+            block_hash: FieldElement::from(block_hash),
+            parent_hash: FieldElement::ZERO,
+            block_number,
+            new_root: FieldElement::ONE,
+            timestamp: 1_000_000,
+            sequencer_address: FieldElement::TWO,
+            transactions: vec![],
+        })
+    }
+
+    #[test]
+    fn test_compressed_blocks_are_decompressed_on_access() {
+        let mut payload = Payload::new(Some(3));
+        payload.insert(block(10, 0x10));
+
+        let stored = payload.get(&10).unwrap();
+        assert_eq!(stored.block_hash, FieldElement::from(0x10_u64));
+        assert_eq!(stored.timestamp, 1_000_000);
+        let stored = payload.get_by_hash(&FieldElement::from(0x10_u64)).unwrap();
+        assert_eq!(stored.block_number, 10);
+        let stats = payload.stats();
+        assert_eq!(stats.blocks, 1);
+        assert_eq!(stats.compressed_blocks, 1);
+        assert!(stats.raw_bytes > 0);
+    }
+
+    #[test]
+    fn test_replaced_block_is_not_found_by_its_old_hash() {
+        let mut payload = Payload::default();
+        payload.insert(block(10, 0x10));
+        payload.insert(block(10, 0x11));
+
+        assert!(payload.get_by_hash(&FieldElement::from(0x10_u64)).is_none());
+        assert_eq!(payload.stats().compressed_blocks, 0);
+    }
+
+    #[test]
+    fn test_transactions_are_indexed_until_their_block_is_replaced_or_dropped() {
+        let transaction = |hash: u64| {
+            Transaction::Invoke(InvokeTransaction::V0(InvokeTransactionV0 {
+                transaction_hash: FieldElement::from(hash),
+                max_fee: FieldElement::ONE,
+                signature: vec![],
+                nonce: FieldElement::ZERO,
+                contract_address: FieldElement::TWO,
+                entry_point_selector: FieldElement::THREE,
+                calldata: vec![],
+            }))
+        };
+        let block_with = |block_number: u64, block_hash: u64, hashes: &[u64]| {
+            let mut block = BlockWithTxs::clone(&block(block_number, block_hash));
+            block.transactions = hashes.iter().copied().map(transaction).collect();
+            Arc::new(block)
+        };
+        let mut payload = Payload::new(Some(3));
+        payload.insert(block_with(10, 0x10, &[0xa1, 0xa2]));
+        payload.insert(block_with(11, 0x11, &[0xb1]));
+
+        assert_eq!(
+            payload.transaction_position(&FieldElement::from(0xa2_u64)),
+            Some((10, 1))
+        );
+
+        payload.insert(block_with(10, 0x12, &[0xc1]));
+        assert!(payload
+            .transaction_position(&FieldElement::from(0xa1_u64))
+            .is_none());
+        assert_eq!(
+            payload.transaction_position(&FieldElement::from(0xc1_u64)),
+            Some((10, 0))
+        );
+
+        payload.truncate(11);
+        assert!(payload
+            .transaction_position(&FieldElement::from(0xb1_u64))
+            .is_none());
+        assert_eq!(payload.prune_to(11), 1);
+        assert!(payload
+            .transaction_position(&FieldElement::from(0xc1_u64))
+            .is_none());
+    }
+
+    #[test]
+    fn test_replaced_block_hash_is_dropped_from_the_index() {
+        let mut payload = Payload::new(Some(3));
+        payload.insert(block(10, 0x10));
+        payload.insert(block(10, 0x11));
+        payload.insert_header(BlockHeader::new(&block(11, 0x20), &[]));
+        payload.insert_header(BlockHeader::new(&block(11, 0x21), &[]));
+
+        assert!(payload
+            .block_number_by_hash(&FieldElement::from(0x10_u64))
+            .is_none());
+        assert!(payload
+            .block_number_by_hash(&FieldElement::from(0x20_u64))
+            .is_none());
+        assert_eq!(
+            payload.block_number_by_hash(&FieldElement::from(0x11_u64)),
+            Some(10)
+        );
+        assert_eq!(
+            payload.block_number_by_hash(&FieldElement::from(0x21_u64)),
+            Some(11)
+        );
+        assert_eq!(payload.hashes.len(), 2);
+    }
+
+    #[test]
+    fn test_prune_to_drops_older_blocks_and_their_hashes() {
+        let mut payload = Payload::new(Some(3));
+        for block_number in 10..15 {
+            payload.insert(block(block_number, block_number));
+        }
+
+        assert_eq!(payload.prune_to(12), 2);
+
+        assert_eq!(payload.first_block_number(), Some(12));
+        assert_eq!(payload.last_block_number(), Some(14));
+        assert!(payload.get_by_hash(&FieldElement::from(10_u64)).is_none());
+        assert!(payload.get_by_hash(&FieldElement::from(12_u64)).is_some());
+        let usage = payload.block_usage();
+        assert_eq!(usage.len(), 3);
+        assert!(usage
+            .iter()
+            .all(|usage| usage.compressed && usage.raw_bytes > 0));
+    }
+
+    #[test]
+    fn test_compact_drops_the_hashes_of_replaced_blocks() {
+        let mut payload = Payload::default();
+        payload.insert(block(10, 0x10));
+        payload.insert(block(10, 0x11));
+
+        assert_eq!(payload.compact(), 0);
+
+        assert_eq!(payload.hashes.len(), 1);
+        assert!(payload.get_by_hash(&FieldElement::from(0x11_u64)).is_some());
+        let usage = payload.block_usage();
+        assert!(!usage[0].compressed);
+        assert_eq!(usage[0].raw_bytes, usage[0].stored_bytes);
+    }
+
+    #[test]
+    fn test_headers_are_found_by_hash_but_never_returned_as_blocks() {
+        let mut payload = Payload::new(Some(3)).with_headers_only(true);
+        payload.insert_header(BlockHeader::new(&block(10, 0x10), &[]));
+        payload.insert(block(11, 0x11));
+
+        assert!(payload.is_headers_only());
+        assert!(payload.get(&10).is_none());
+        assert!(payload.get_by_hash(&FieldElement::from(0x10_u64)).is_none());
+        assert_eq!(payload.get_header(&10).unwrap().new_root, FieldElement::ONE);
+        assert!(payload.get_header(&11).is_none());
+        assert_eq!(
+            payload.block_number_by_hash(&FieldElement::from(0x10_u64)),
+            Some(10)
+        );
+        assert_eq!(payload.block_hash(&10), Some(FieldElement::from(0x10_u64)));
+        assert_eq!(payload.block_hash(&11), Some(FieldElement::from(0x11_u64)));
+        assert_eq!(payload.new_root(&10), Some(FieldElement::ONE));
+
+        payload.compact();
+        assert_eq!(payload.hashes.len(), 2);
+        assert_eq!(payload.range(..).count(), 1);
+    }
+}
@@ -0,0 +1,971 @@
+//! Background tasks of the light client.
+//!
+//! Syncing is split into three tasks communicating over channels:
+//! * the syncer polls Ethereum and StarkNet and forwards new blocks,
+//! * the verifier recomputes the hash of every new block,
+//! * the store publishes the new snapshots of [`NodeData`].
+//!
+//! The three tasks are supervised: when one of them exits or panics, the others stop as their
+//! channels close, and they are restarted together after a backoff.
+//!
+//! A separate task watches the L2 to L1 messages consumed on L1 while some are awaited,
+//! another one the upgrades of the StarkNet core contract, and in archival mode a last one
+//! persists the proven blocks.
+//!
+//! The syncer and the store fire the alerts on reorgs, state root mismatches, provider
+//! divergences and sync stalls.
+//!
+//! Queries are served through a [`BeerusHandle`] from the last snapshot, so they never wait on
+//! the sync tasks.
+use super::{
+    alerts::{Alert, AlertHooks, AlertKind},
+    archive::Archive,
+    beerus::{BeerusLightClient, NodeData, ProviderStatus, SyncProgress, SyncStatus},
+    ethereum::EthereumLightClient,
+    messaging::{ConsumedMessage, MessageWatcher},
+    starknet::{
+        block_hash::{
+            fetch_receipts, verify_block_hash_with_receipts, BlockHashCheck, BlockHeader,
+        },
+        health::ProviderHealth,
+        StarkNetLightClient,
+    },
+    upgrade_monitor::{CoreContractParameters, UpgradeMonitor},
+};
+use crate::{ethers_helper, runtime};
+use arc_swap::ArcSwap;
+use ethers::{abi::Abi, types::Address};
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
+use starknet::{
+    core::types::FieldElement,
+    providers::jsonrpc::models::{
+        BlockId, BlockTag, BlockWithTxs, EmittedEvent, Event, MaybePendingBlockWithTxs,
+        MaybePendingTransactionReceipt,
+    },
+};
+use std::{
+    collections::HashSet,
+    ops::Deref,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::sync::{mpsc, watch, Notify, RwLock};
+
+/// Interval between two polls of the syncer.
+//TODO: Make this configurable
+const SYNC_INTERVAL: Duration = Duration::from_secs(5);
+/// Interval between two reads of the parameters of the StarkNet core contract.
+const UPGRADE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+/// Interval between two compactions of the archive.
+const ARCHIVE_COMPACTION_INTERVAL: Duration = Duration::from_secs(600);
+/// Capacity of the channels between the tasks.
+const CHANNEL_CAPACITY: usize = 16;
+/// Maximum number of blocks backfilled before a new latest block, the oldest ones are skipped.
+pub const MAX_BACKFILL_BLOCKS: u64 = 1_000;
+/// Number of backfilled blocks between two measures of the sync progress.
+const PROGRESS_INTERVAL_BLOCKS: u64 = 100;
+/// Delay before restarting the sync tasks after a first failure, doubled on every failure.
+pub const RESTART_MIN_BACKOFF: Duration = Duration::from_secs(1);
+/// Longest delay before restarting the sync tasks. Tasks which ran for longer than that
+/// before failing are restarted after the shortest delay again.
+pub const RESTART_MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Health of the sync tasks, as reported by their supervisor.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncTasksHealth {
+    /// Whether the sync tasks run, they don't before the start and while awaiting a restart.
+    pub running: bool,
+    /// Number of times the sync tasks stopped since the start.
+    pub failures: u64,
+    /// Cause of the last stop, if any.
+    pub last_failure: Option<String>,
+    /// Delay before the pending restart, or applied to the last one, in milliseconds.
+    pub restart_backoff_ms: u64,
+}
+
+/// Resync requests, from the light client API to the syncer.
+#[derive(Debug, Default)]
+pub struct ResyncTrigger {
+    /// Block to backfill from, if any, of the last request.
+    from_block: Mutex<Option<u64>>,
+    notify: Notify,
+}
+
+impl ResyncTrigger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wake the syncer up to fetch the latest block again, and the blocks from `from_block`
+    /// if set. A request replaces the one not handled yet, if any.
+    pub fn request(&self, from_block: Option<u64>) {
+        *self.from_block.lock().unwrap() = from_block;
+        self.notify.notify_one();
+    }
+
+    /// Wait for the next request, returning the block to backfill from.
+    async fn requested(&self) -> Option<u64> {
+        self.notify.notified().await;
+        self.from_block.lock().unwrap().take()
+    }
+}
+
+/// Updates applied by the store task.
+#[derive(Debug, Clone)]
+pub enum StoreCommand {
+    /// A state root was proven on L1 for the given StarkNet block.
+    ProvenStateRoot {
+        block_number: u64,
+        state_root: FieldElement,
+    },
+    /// A block passed the block hash verification.
+    VerifiedBlock {
+        block: Arc<BlockWithTxs>,
+        events: Arc<Vec<EmittedEvent>>,
+    },
+    /// A block failed the block hash verification.
+    RejectedBlock(BlockHashCheck),
+    /// The receipts of a verified block were prefetched.
+    Receipts {
+        block_number: u64,
+        block_hash: FieldElement,
+        receipts: Arc<Vec<MaybePendingTransactionReceipt>>,
+    },
+    /// The availability of the StarkNet provider changed.
+    StarknetProvider(ProviderStatus),
+    /// The catch-up progress was measured.
+    SyncProgress(SyncProgress),
+}
+
+/// Samples the counters of the syncer to measure its progress.
+#[derive(Debug, Default)]
+struct SyncMeter {
+    /// Time, fetched StarkNet blocks and L1 block number of the last sample.
+    last_sample: Option<(u64, u64, u64)>,
+    /// StarkNet blocks fetched since the syncer started.
+    fetched_blocks: u64,
+    /// Last L1 block number followed by the Ethereum light client.
+    l1_block_number: u64,
+}
+
+impl SyncMeter {
+    /// Take a sample and measure the progress since the previous one, if any.
+    fn sample(&mut self, blocks_behind: u64) -> Option<SyncProgress> {
+        let now = runtime::now_millis();
+        let progress = self
+            .last_sample
+            .map(|(time, fetched_blocks, l1_block_number)| {
+                SyncProgress::measure(
+                    now.saturating_sub(time),
+                    self.fetched_blocks - fetched_blocks,
+                    self.l1_block_number.saturating_sub(l1_block_number),
+                    blocks_behind,
+                )
+            });
+        self.last_sample = Some((now, self.fetched_blocks, self.l1_block_number));
+        progress
+    }
+}
+
+/// Watches the syncer for the stalls and the newly down-ranked providers.
+#[derive(Debug)]
+struct AlertWatch {
+    /// Time without a new block after which the sync is stalled, 0 disables it.
+    sync_stall_ms: u64,
+    /// Time of the last new block, or of the start.
+    last_progress_ms: u64,
+    /// Whether the current stall was reported.
+    stalled: bool,
+    /// Providers down-ranked when last checked.
+    down_ranked: HashSet<String>,
+}
+
+impl AlertWatch {
+    fn new(sync_stall_secs: u64) -> Self {
+        Self {
+            sync_stall_ms: sync_stall_secs.saturating_mul(1_000),
+            last_progress_ms: runtime::now_millis(),
+            stalled: false,
+            down_ranked: HashSet::new(),
+        }
+    }
+
+    /// Record a new block.
+    fn progress(&mut self) {
+        self.last_progress_ms = runtime::now_millis();
+        if self.stalled {
+            info!("Sync resumed");
+            self.stalled = false;
+        }
+    }
+
+    /// Return the alerts of a stall starting, or of providers down-ranked since the last check.
+    fn check(&mut self, last_block_number: u64, providers: &[ProviderHealth]) -> Vec<Alert> {
+        let mut alerts = Vec::new();
+        let idle_ms = runtime::now_millis().saturating_sub(self.last_progress_ms);
+        if self.sync_stall_ms > 0 && idle_ms >= self.sync_stall_ms && !self.stalled {
+            self.stalled = true;
+            alerts.push(Alert::new(
+                AlertKind::SyncStall,
+                format!(
+                    "No new block synced for {} seconds, the last one is {last_block_number}",
+                    idle_ms / 1_000
+                ),
+                Some(last_block_number),
+            ));
+        }
+        for health in providers {
+            if !health.down_ranked {
+                self.down_ranked.remove(&health.provider);
+            } else if self.down_ranked.insert(health.provider.clone()) {
+                alerts.push(Alert::new(
+                    AlertKind::ProviderDivergence,
+                    format!(
+                        "{} returned an older latest block than the other sources {} times in a row",
+                        health.provider, health.consecutive_divergences
+                    ),
+                    None,
+                ));
+            }
+        }
+        alerts
+    }
+}
+
+/// Spawn the syncer, verifier and store tasks, under a supervisor restarting them.
+/// With `prefetch_receipts`, the receipts fetched to verify a block are kept along with it.
+/// The sync is reported stalled after `sync_stall_secs` without a new block.
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_sync_tasks(
+    ethereum_lightclient: Arc<RwLock<Box<dyn EthereumLightClient>>>,
+    starknet_lightclient: Arc<Box<dyn StarkNetLightClient>>,
+    node: Arc<ArcSwap<NodeData>>,
+    sync_status: Arc<watch::Sender<SyncStatus>>,
+    tasks_health: Arc<Mutex<SyncTasksHealth>>,
+    resync: Arc<ResyncTrigger>,
+    alerts: Arc<AlertHooks>,
+    prefetch_receipts: bool,
+    sync_stall_secs: u64,
+) {
+    runtime::spawn(supervise_sync_tasks(
+        ethereum_lightclient,
+        starknet_lightclient,
+        node,
+        sync_status,
+        tasks_health,
+        resync,
+        alerts,
+        prefetch_receipts,
+        sync_stall_secs,
+    ));
+}
+
+/// Run the sync tasks until they stop, and restart them with an exponential backoff.
+/// Meanwhile the sync status is `Restarting`, the store publishes the actual one again once
+/// restarted.
+#[allow(clippy::too_many_arguments)]
+async fn supervise_sync_tasks(
+    ethereum_lightclient: Arc<RwLock<Box<dyn EthereumLightClient>>>,
+    starknet_lightclient: Arc<Box<dyn StarkNetLightClient>>,
+    node: Arc<ArcSwap<NodeData>>,
+    sync_status: Arc<watch::Sender<SyncStatus>>,
+    tasks_health: Arc<Mutex<SyncTasksHealth>>,
+    resync: Arc<ResyncTrigger>,
+    alerts: Arc<AlertHooks>,
+    prefetch_receipts: bool,
+    sync_stall_secs: u64,
+) {
+    let mut backoff = RESTART_MIN_BACKOFF;
+    loop {
+        tasks_health.lock().unwrap().running = true;
+        let started_ms = runtime::now_millis();
+        let (store_tx, store_rx) = mpsc::channel(CHANNEL_CAPACITY);
+        let (verifier_tx, verifier_rx) = mpsc::channel(CHANNEL_CAPACITY);
+        let (store, verifier, syncer) = futures::join!(
+            runtime::spawn_watched(run_store(
+                node.clone(),
+                sync_status.clone(),
+                store_rx,
+                alerts.clone(),
+            )),
+            runtime::spawn_watched(run_verifier(
+                starknet_lightclient.clone(),
+                verifier_rx,
+                store_tx.clone(),
+                prefetch_receipts,
+            )),
+            runtime::spawn_watched(run_syncer(
+                ethereum_lightclient.clone(),
+                starknet_lightclient.clone(),
+                verifier_tx,
+                store_tx,
+                resync.clone(),
+                alerts.clone(),
+                AlertWatch::new(sync_stall_secs),
+            )),
+        );
+
+        let panics: Vec<String> = [("syncer", syncer), ("verifier", verifier), ("store", store)]
+            .into_iter()
+            .filter_map(|(task, outcome)| {
+                outcome
+                    .err()
+                    .map(|panic| format!("{task} panicked: {panic}"))
+            })
+            .collect();
+        let failure = match panics.is_empty() {
+            true => "the sync tasks exited".to_string(),
+            false => panics.join(", "),
+        };
+        if runtime::now_millis().saturating_sub(started_ms) > RESTART_MAX_BACKOFF.as_millis() as u64
+        {
+            backoff = RESTART_MIN_BACKOFF;
+        }
+        error!(
+            "Sync tasks stopped, {failure}, restarting them in {}s",
+            backoff.as_secs()
+        );
+        {
+            let mut health = tasks_health.lock().unwrap();
+            health.running = false;
+            health.failures += 1;
+            health.last_failure = Some(failure);
+            health.restart_backoff_ms = backoff.as_millis() as u64;
+        }
+        sync_status.send_replace(SyncStatus::Restarting);
+
+        runtime::sleep(backoff).await;
+        backoff = (backoff * 2).min(RESTART_MAX_BACKOFF);
+    }
+}
+
+/// Spawn the task notifying the subscribers of the consumed L2 to L1 messages.
+pub fn spawn_message_watcher(
+    ethereum_lightclient: Arc<RwLock<Box<dyn EthereumLightClient>>>,
+    starknet_core_abi: Abi,
+    starknet_core_contract_address: Address,
+    watcher: Arc<MessageWatcher>,
+) {
+    runtime::spawn(run_message_watcher(
+        ethereum_lightclient,
+        starknet_core_abi,
+        starknet_core_contract_address,
+        watcher,
+    ));
+}
+
+/// Poll the `ConsumedMessageToL1` events of the StarkNet core contract while messages are
+/// awaited. L1 blocks are scanned from the first poll with a subscriber onwards.
+async fn run_message_watcher(
+    ethereum_lightclient: Arc<RwLock<Box<dyn EthereumLightClient>>>,
+    starknet_core_abi: Abi,
+    starknet_core_contract_address: Address,
+    watcher: Arc<MessageWatcher>,
+) {
+    let signature = match starknet_core_abi.event("ConsumedMessageToL1") {
+        Ok(event) => event.signature(),
+        Err(err) => {
+            error!("Message watcher stopped: {err}");
+            return;
+        }
+    };
+    let mut next_block = None;
+    loop {
+        runtime::sleep(SYNC_INTERVAL).await;
+        if !watcher.has_subscribers() {
+            next_block = None;
+            continue;
+        }
+
+        let ethereum_lightclient = ethereum_lightclient.read().await;
+        let latest_block = match ethereum_lightclient.get_block_number().await {
+            Ok(block_number) => block_number,
+            Err(err) => {
+                warn!("Error getting L1 block number: {err}");
+                continue;
+            }
+        };
+        let from_block = next_block.unwrap_or(latest_block);
+        if from_block > latest_block {
+            continue;
+        }
+        let logs = ethereum_lightclient
+            .get_logs(
+                &Some(format!("{from_block:#x}")),
+                &Some(format!("{latest_block:#x}")),
+                &Some(format!("{starknet_core_contract_address:?}")),
+                &Some(vec![format!("{signature:?}")]),
+                &None,
+            )
+            .await;
+        match logs {
+            Ok(logs) => {
+                for log in logs.iter() {
+                    match ConsumedMessage::from_log(&starknet_core_abi, log) {
+                        Ok(consumed) => {
+                            let notified = watcher.notify(&consumed);
+                            if notified > 0 {
+                                info!("Message {:?} consumed on L1", consumed.message_hash);
+                            }
+                        }
+                        Err(err) => warn!("Invalid ConsumedMessageToL1 event: {err}"),
+                    }
+                }
+                next_block = Some(latest_block + 1);
+            }
+            Err(err) => warn!("Error getting ConsumedMessageToL1 events: {err}"),
+        }
+    }
+}
+
+/// Spawn the task watching the upgrades of the StarkNet core contract.
+pub fn spawn_upgrade_monitor(
+    ethereum_lightclient: Arc<RwLock<Box<dyn EthereumLightClient>>>,
+    starknet_core_abi: Abi,
+    starknet_core_contract_address: Address,
+    monitor: Arc<UpgradeMonitor>,
+) {
+    runtime::spawn(run_upgrade_monitor(
+        ethereum_lightclient,
+        starknet_core_abi,
+        starknet_core_contract_address,
+        monitor,
+    ));
+}
+
+/// Read the implementation, program hash and config hash of the StarkNet core contract
+/// periodically, and warn when they change.
+async fn run_upgrade_monitor(
+    ethereum_lightclient: Arc<RwLock<Box<dyn EthereumLightClient>>>,
+    starknet_core_abi: Abi,
+    starknet_core_contract_address: Address,
+    monitor: Arc<UpgradeMonitor>,
+) {
+    loop {
+        let parameters = CoreContractParameters::fetch(
+            ethereum_lightclient.read().await.as_ref(),
+            &starknet_core_abi,
+            starknet_core_contract_address,
+        )
+        .await;
+        match parameters {
+            Ok(parameters) => {
+                if let Some(upgrade) = monitor.record(parameters) {
+                    warn!(
+                        "StarkNet core contract upgraded, changed {}: {:?} -> {:?}",
+                        upgrade.changes.join(", "),
+                        upgrade.previous,
+                        upgrade.current
+                    );
+                }
+            }
+            Err(err) => warn!("Error reading the StarkNet core contract parameters: {err}"),
+        }
+        runtime::sleep(UPGRADE_CHECK_INTERVAL).await;
+    }
+}
+
+/// Return the first block to backfill before a new latest block: the one a resync requested,
+/// or else the first one missed since the previous latest block, if any.
+/// At most `MAX_BACKFILL_BLOCKS` are backfilled, the oldest ones are skipped.
+pub fn backfill_from(
+    requested: Option<u64>,
+    last_block_number: u64,
+    latest_block_number: u64,
+) -> Option<u64> {
+    let missed_from = (last_block_number > 0).then_some(last_block_number + 1);
+    requested
+        .or(missed_from)
+        .filter(|from_block| *from_block < latest_block_number)
+        .map(|from_block| from_block.max(latest_block_number.saturating_sub(MAX_BACKFILL_BLOCKS)))
+}
+
+/// Spawn the task persisting the proven blocks to the archive.
+pub fn spawn_archiver(
+    starknet_lightclient: Arc<Box<dyn StarkNetLightClient>>,
+    node: Arc<ArcSwap<NodeData>>,
+    archive: Arc<Archive>,
+) {
+    runtime::spawn(run_archiver(starknet_lightclient, node, archive));
+}
+
+/// Append the retained blocks to the archive once they are proven on L1, with their receipts,
+/// fetched unless cached, and compact the archive periodically.
+/// Archiving resumes after the newest block already archived.
+async fn run_archiver(
+    starknet_lightclient: Arc<Box<dyn StarkNetLightClient>>,
+    node: Arc<ArcSwap<NodeData>>,
+    archive: Arc<Archive>,
+) {
+    let mut next_block = match archive.last_block_number() {
+        Ok(last_block) => last_block.map_or(0, |block_number| block_number + 1),
+        Err(err) => {
+            error!("Archiver stopped: {err}");
+            return;
+        }
+    };
+    let mut last_compaction_ms = runtime::now_millis();
+    loop {
+        runtime::sleep(SYNC_INTERVAL).await;
+        let snapshot = node.load_full();
+        let last_proven = match snapshot.last_proven_block_number() {
+            Some(block_number) if block_number >= next_block => block_number,
+            _ => continue,
+        };
+        for block in snapshot.payload.range(next_block..=last_proven) {
+            let receipts = match snapshot.receipts.get(&block.block_number) {
+                Some(receipts) => receipts.to_vec(),
+                None => {
+                    match fetch_receipts(starknet_lightclient.as_ref().as_ref(), &block).await {
+                        Ok(receipts) => receipts,
+                        Err(err) => {
+                            warn!(
+                                "Error fetching the receipts of block {}: {err}",
+                                block.block_number
+                            );
+                            break;
+                        }
+                    }
+                }
+            };
+            if let Err(err) = archive.append(&block, &receipts) {
+                error!("Error archiving block {}: {err}", block.block_number);
+                break;
+            }
+            next_block = block.block_number + 1;
+        }
+
+        let now = runtime::now_millis();
+        if now.saturating_sub(last_compaction_ms) >= ARCHIVE_COMPACTION_INTERVAL.as_millis() as u64
+        {
+            last_compaction_ms = now;
+            match archive.compact() {
+                Ok(0) => {}
+                Ok(compacted) => info!("Compacted {compacted} archive segments"),
+                Err(err) => warn!("Error compacting the archive: {err}"),
+            }
+        }
+    }
+}
+
+/// Poll the L1 state and the latest StarkNet block.
+/// New blocks are sent to the verifier, a rejected block is not retried until a resync is
+/// requested. The blocks missed since the previous poll, from the last proven one at the start,
+/// or from the one a resync requested, are sent first so that the payload stays contiguous.
+async fn run_syncer(
+    ethereum_lightclient: Arc<RwLock<Box<dyn EthereumLightClient>>>,
+    starknet_lightclient: Arc<Box<dyn StarkNetLightClient>>,
+    verifier: mpsc::Sender<BlockWithTxs>,
+    store: mpsc::Sender<StoreCommand>,
+    resync: Arc<ResyncTrigger>,
+    alerts: Arc<AlertHooks>,
+    mut alert_watch: AlertWatch,
+) {
+    let mut last_block_number = 0;
+    let mut requested_backfill = None;
+    let mut started = false;
+    let mut provider_status = ProviderStatus::default();
+    let mut meter = SyncMeter::default();
+    loop {
+        let l1_state = {
+            let ethereum_lightclient = ethereum_lightclient.read().await;
+            if let Ok(l1_block_number) = ethereum_lightclient.get_block_number().await {
+                meter.l1_block_number = l1_block_number;
+            }
+            ethereum_lightclient.starknet_proven_state().await
+        };
+        // A transient L1 error is retried on the next poll rather than restarting the tasks.
+        let (last_proven_block, state_root) = match l1_state {
+            Ok(l1_state) => l1_state,
+            Err(err) => {
+                error!("Error reading the proven StarkNet state on L1: {err}");
+                wait_next_poll(&resync, &mut last_block_number, &mut requested_backfill).await;
+                continue;
+            }
+        };
+        info!("State Root: {state_root}");
+        info!("Block Number: {last_proven_block}");
+        // Catch up from the last block proven on L1, the light client may have been off for a
+        // while and the proven blocks are the ones it can verify.
+        if !started {
+            started = true;
+            requested_backfill = Some(last_proven_block.as_u64());
+        }
+
+        // Keep track of every root proven on L1.
+        if let Ok(state_root) = ethers_helper::u256_to_felt(state_root) {
+            let command = StoreCommand::ProvenStateRoot {
+                block_number: last_proven_block.as_u64(),
+                state_root,
+            };
+            if store.send(command).await.is_err() {
+                return;
+            }
+        }
+
+        let latest_block = starknet_lightclient
+            .get_block_with_txs(&BlockId::Tag(BlockTag::Latest))
+            .await;
+
+        // Queries backed by L1 or by the payload are still answered while the provider is
+        // down, report the outage once instead of failing every poll.
+        let status = provider_status.record(&latest_block);
+        match (&provider_status.error, &status.error) {
+            (None, Some(err)) => warn!("StarkNet provider unavailable: {err}"),
+            (Some(_), None) => info!("StarkNet provider available again"),
+            _ => {}
+        }
+        if status != provider_status {
+            provider_status = status;
+            let command = StoreCommand::StarknetProvider(provider_status.clone());
+            if store.send(command).await.is_err() {
+                return;
+            }
+        }
+
+        let caught_up = matches!(latest_block, Ok(MaybePendingBlockWithTxs::Block(_)));
+        match latest_block {
+            Ok(MaybePendingBlockWithTxs::Block(block)) => {
+                let from_block = backfill_from(
+                    requested_backfill.take(),
+                    last_block_number,
+                    block.block_number,
+                );
+                if let Some(from_block) = from_block {
+                    info!("Backfilling blocks {from_block} to {}", block.block_number);
+                    for block_number in from_block..block.block_number {
+                        match starknet_lightclient
+                            .get_block_with_txs(&BlockId::Number(block_number))
+                            .await
+                        {
+                            Ok(MaybePendingBlockWithTxs::Block(backfilled)) => {
+                                if verifier.send(backfilled).await.is_err() {
+                                    return;
+                                }
+                                meter.fetched_blocks += 1;
+                                if meter.fetched_blocks % PROGRESS_INTERVAL_BLOCKS == 0 {
+                                    let blocks_behind = block.block_number - block_number;
+                                    if let Some(progress) = meter.sample(blocks_behind) {
+                                        let command = StoreCommand::SyncProgress(progress);
+                                        if store.send(command).await.is_err() {
+                                            return;
+                                        }
+                                    }
+                                }
+                            }
+                            Ok(MaybePendingBlockWithTxs::PendingBlock(_)) => break,
+                            Err(err) => {
+                                // Resumed from there on the next poll.
+                                error!("Error backfilling block {block_number}: {err}");
+                                requested_backfill = Some(block_number);
+                                break;
+                            }
+                        }
+                    }
+                }
+                if block.block_number > last_block_number && 0 < block.block_number {
+                    last_block_number = block.block_number;
+                    meter.fetched_blocks += 1;
+                    alert_watch.progress();
+                    if verifier.send(block).await.is_err() {
+                        return;
+                    }
+                }
+            }
+            Ok(MaybePendingBlockWithTxs::PendingBlock(_)) => warn!("Pending Block"),
+            Err(err) => debug!("Error getting block: {}", err),
+        }
+        for alert in alert_watch.check(last_block_number, &starknet_lightclient.provider_health()) {
+            alerts.fire(alert);
+        }
+        // Caught up with the latest block, measure the throughput since the previous sample.
+        if caught_up {
+            if let Some(progress) = meter.sample(0) {
+                if store
+                    .send(StoreCommand::SyncProgress(progress))
+                    .await
+                    .is_err()
+                {
+                    return;
+                }
+            }
+        }
+        wait_next_poll(&resync, &mut last_block_number, &mut requested_backfill).await;
+    }
+}
+
+/// Wait for the next poll of the syncer, or for a resync request which restarts it from
+/// scratch, or from the requested block.
+async fn wait_next_poll(
+    resync: &ResyncTrigger,
+    last_block_number: &mut u64,
+    requested_backfill: &mut Option<u64>,
+) {
+    tokio::select! {
+        _ = runtime::sleep(SYNC_INTERVAL) => {}
+        from_block = resync.requested() => {
+            info!("Resync requested");
+            *last_block_number = 0;
+            *requested_backfill = from_block;
+        }
+    }
+}
+
+/// Recompute the hash of every new block before handing it to the store.
+async fn run_verifier(
+    starknet_lightclient: Arc<Box<dyn StarkNetLightClient>>,
+    mut blocks: mpsc::Receiver<BlockWithTxs>,
+    store: mpsc::Sender<StoreCommand>,
+    prefetch_receipts: bool,
+) {
+    while let Some(block) = blocks.recv().await {
+        let block = Arc::new(block);
+        let verified = fetch_receipts(starknet_lightclient.as_ref().as_ref(), &block)
+            .await
+            .and_then(|receipts| {
+                let events = verify_block_hash_with_receipts(&block, &receipts)?;
+                Ok((receipts, events))
+            });
+        let mut commands = Vec::new();
+        match verified {
+            Ok((receipts, events)) => {
+                commands.push(StoreCommand::VerifiedBlock {
+                    block: block.clone(),
+                    events: Arc::new(events),
+                });
+                // Sent after the block, so that the store caches them along with it.
+                if prefetch_receipts {
+                    commands.push(StoreCommand::Receipts {
+                        block_number: block.block_number,
+                        block_hash: block.block_hash,
+                        receipts: Arc::new(receipts),
+                    });
+                }
+            }
+            Err(err) => commands.push(StoreCommand::RejectedBlock(BlockHashCheck {
+                block_number: block.block_number,
+                block_hash: block.block_hash,
+                valid: false,
+                error: Some(err.to_string()),
+            })),
+        }
+        for command in commands {
+            if store.send(command).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// Apply the updates to the node data and publish the resulting snapshots.
+async fn run_store(
+    node: Arc<ArcSwap<NodeData>>,
+    sync_status: Arc<watch::Sender<SyncStatus>>,
+    mut commands: mpsc::Receiver<StoreCommand>,
+    alerts: Arc<AlertHooks>,
+) {
+    while let Some(command) = commands.recv().await {
+        // Queries swap the snapshot too, to cache receipts, prune or resync, so the command is
+        // applied in a read-modify-write of the current snapshot, retried on a concurrent swap.
+        // The syncer repeats the proven root and the progress on every poll, the unchanged
+        // node data is not copied.
+        let previous = node.rcu(|current| match changes_node(current, &command) {
+            true => {
+                let mut next = NodeData::clone(current);
+                apply(&mut next, command.clone());
+                Arc::new(next)
+            }
+            false => Arc::clone(current),
+        });
+        // The alerts are raised against the node data the command was applied to.
+        for alert in store_alerts(&previous, &command) {
+            alerts.fire(alert);
+        }
+        // Only the transitions are published to the subscribers.
+        let status = node.load().sync_status();
+        sync_status.send_if_modified(|current| {
+            if *current == status {
+                return false;
+            }
+            info!("Sync status: {status:?}");
+            *current = status;
+            true
+        });
+    }
+}
+
+/// Return the alerts raised by an update, given the node data it applies to.
+pub fn store_alerts(node: &NodeData, command: &StoreCommand) -> Vec<Alert> {
+    let mismatch = |block_number: u64, synced: &FieldElement, proven: &FieldElement| {
+        Alert::new(
+            AlertKind::StateRootMismatch,
+            format!(
+                "State root of block {block_number} is {synced:#x}, but {proven:#x} was proven on L1"
+            ),
+            Some(block_number),
+        )
+    };
+    let mut alerts = Vec::new();
+    match command {
+        StoreCommand::ProvenStateRoot {
+            block_number,
+            state_root,
+        } => {
+            // The syncer reports the last proven root on every poll, it is checked once.
+            let known = node.proven_state_roots.get(block_number) == Some(state_root);
+            match node.payload.new_root(block_number) {
+                Some(new_root) if !known && new_root != *state_root => {
+                    alerts.push(mismatch(*block_number, &new_root, state_root))
+                }
+                _ => {}
+            }
+        }
+        StoreCommand::VerifiedBlock { block, .. } => {
+            if let Some(fork_point) = node.fork_point(block) {
+                alerts.push(Alert::new(
+                    AlertKind::Reorg,
+                    format!(
+                        "Block {} does not extend the retained chain, the blocks from {fork_point} were dropped",
+                        block.block_number
+                    ),
+                    Some(fork_point),
+                ));
+            }
+            match node.proven_state_roots.get(&block.block_number) {
+                Some(proven) if *proven != block.new_root => {
+                    alerts.push(mismatch(block.block_number, &block.new_root, proven))
+                }
+                _ => {}
+            }
+        }
+        _ => {}
+    }
+    alerts
+}
+
+/// Return whether an update changes the node data, given the node data it applies to.
+pub fn changes_node(node: &NodeData, command: &StoreCommand) -> bool {
+    match command {
+        StoreCommand::ProvenStateRoot {
+            block_number,
+            state_root,
+        } => node.proven_state_roots.get(block_number) != Some(state_root),
+        StoreCommand::StarknetProvider(status) => node.starknet_provider != *status,
+        StoreCommand::SyncProgress(progress) => node.sync_progress != *progress,
+        _ => true,
+    }
+}
+
+/// Apply a single update to the node data.
+pub fn apply(node: &mut NodeData, command: StoreCommand) {
+    match command {
+        StoreCommand::ProvenStateRoot {
+            block_number,
+            state_root,
+        } => {
+            Arc::make_mut(&mut node.proven_state_roots).insert(block_number, state_root);
+        }
+        StoreCommand::VerifiedBlock { block, events } => {
+            if let Some(fork_point) = node.fork_point(&block) {
+                warn!(
+                    "Block {} does not extend the retained chain, dropping the blocks from {fork_point}",
+                    block.block_number
+                );
+                node.rewind_to(fork_point);
+            }
+            node.last_block_hash_check = Some(BlockHashCheck {
+                block_number: block.block_number,
+                block_hash: block.block_hash,
+                valid: true,
+                error: None,
+            });
+            // Backfilled blocks are older than the head, which only moves forward.
+            if block.block_number >= node.block_number {
+                node.block_number = block.block_number;
+                node.state_root = block.new_root.to_string();
+            }
+            node.insert_contract_deployments(&block, &events);
+            node.insert_class_declarations(&block);
+            if node.receipts.contains_key(&block.block_number) {
+                Arc::make_mut(&mut node.receipts).remove(&block.block_number);
+            }
+            if node.payload.is_headers_only() {
+                // The transactions and events are fetched from the provider when queried.
+                let events: Vec<Event> = events
+                    .iter()
+                    .map(|event| Event {
+                        from_address: event.from_address,
+                        keys: event.keys.clone(),
+                        data: event.data.clone(),
+                    })
+                    .collect();
+                node.payload
+                    .insert_header(BlockHeader::new(&block, &events));
+            } else {
+                node.insert_events(block.block_number, events);
+                node.payload.insert(block);
+            }
+            node.enforce_memory_budget();
+            info!("New Block Added to Payload:");
+            info!("Block Number {:?}", &node.block_number);
+            info!("Block Root {:?}", &node.state_root);
+        }
+        StoreCommand::StarknetProvider(status) => {
+            node.starknet_provider = status;
+        }
+        StoreCommand::SyncProgress(progress) => {
+            node.sync_progress = progress;
+        }
+        StoreCommand::Receipts {
+            block_number,
+            block_hash,
+            receipts,
+        } => {
+            node.cache_receipts(block_number, block_hash, receipts);
+        }
+        StoreCommand::RejectedBlock(check) => {
+            error!(
+                "Rejected block: {}",
+                check.error.as_deref().unwrap_or_default()
+            );
+            node.last_block_hash_check = Some(check);
+        }
+    }
+}
+
+/// Cheap, clonable handle on a light client, used to serve queries from several tasks.
+#[derive(Clone)]
+pub struct BeerusHandle {
+    beerus: Arc<BeerusLightClient>,
+}
+
+impl From<BeerusLightClient> for BeerusHandle {
+    fn from(beerus: BeerusLightClient) -> Self {
+        Self {
+            beerus: Arc::new(beerus),
+        }
+    }
+}
+
+impl BeerusHandle {
+    /// Start the light client in the background and return immediately.
+    /// Until it is started, the status is `NotSynced` and [`BeerusLightClient::ensure_synced`]
+    /// fails, see [`BeerusLightClient::await_synced`] to wait for it.
+    pub fn start_in_background(&self) {
+        let beerus = self.beerus.clone();
+        runtime::spawn(async move {
+            if let Err(err) = beerus.run_start().await {
+                error!("Failed to start the light client: {err}");
+            }
+        });
+    }
+}
+
+impl Deref for BeerusHandle {
+    type Target = BeerusLightClient;
+
+    fn deref(&self) -> &Self::Target {
+        &self.beerus
+    }
+}
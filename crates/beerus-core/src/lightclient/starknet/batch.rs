@@ -0,0 +1,197 @@
+use super::errors::{StarknetError, StarknetErrorCode, UpstreamUnavailable};
+use crate::{lightclient::transport::Transport, runtime};
+use eyre::{eyre, Result};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use std::{sync::Arc, time::Duration};
+use tokio::sync::{mpsc, oneshot};
+
+/// Request waiting to be sent in a batch, along with the channel its response is sent to.
+struct PendingRequest {
+    method: String,
+    params: Value,
+    response: oneshot::Sender<Result<Value>>,
+}
+
+/// JSON-RPC client grouping the requests made at the same time into batches.
+/// A batch is sent once it holds `max_batch_size` requests, or `flush_interval` after its first
+/// request.
+#[derive(Clone)]
+pub struct BatchClient {
+    requests: mpsc::UnboundedSender<PendingRequest>,
+}
+
+impl BatchClient {
+    /// Create a new batch client and spawn the task sending the batches.
+    pub fn new(
+        transport: Arc<dyn Transport>,
+        max_batch_size: usize,
+        flush_interval: Duration,
+    ) -> Self {
+        let (requests, receiver) = mpsc::unbounded_channel();
+        runtime::spawn(run_batcher(
+            transport,
+            max_batch_size.max(1),
+            flush_interval,
+            receiver,
+        ));
+        Self { requests }
+    }
+
+    /// Send a request as part of the next batch and wait for its result.
+    /// # Arguments
+    /// * `method` - The JSON-RPC method.
+    /// * `params` - The JSON-RPC params.
+    /// # Returns
+    /// `Ok(R)` with the deserialized result.
+    /// `Err(eyre::Report)` if the batch could not be sent or the request failed.
+    pub async fn request<R: DeserializeOwned>(&self, method: &str, params: Value) -> Result<R> {
+        let (response, receiver) = oneshot::channel();
+        self.requests
+            .send(PendingRequest {
+                method: method.to_string(),
+                params,
+                response,
+            })
+            .map_err(|_| eyre!("Batch client stopped"))?;
+        let result = receiver
+            .await
+            .map_err(|_| eyre!("Batch client stopped"))??;
+        Ok(serde_json::from_value(result)?)
+    }
+}
+
+async fn run_batcher(
+    transport: Arc<dyn Transport>,
+    max_batch_size: usize,
+    flush_interval: Duration,
+    mut receiver: mpsc::UnboundedReceiver<PendingRequest>,
+) {
+    while let Some(first) = receiver.recv().await {
+        let mut batch = vec![first];
+        let flush = runtime::sleep(flush_interval);
+        tokio::pin!(flush);
+        while batch.len() < max_batch_size {
+            tokio::select! {
+                _ = &mut flush => break,
+                request = receiver.recv() => match request {
+                    Some(request) => batch.push(request),
+                    None => break,
+                },
+            }
+        }
+        runtime::spawn(send_batch(transport.clone(), batch));
+    }
+}
+
+/// Send a batch and dispatch the responses to the pending requests.
+async fn send_batch(transport: Arc<dyn Transport>, batch: Vec<PendingRequest>) {
+    let body: Vec<Value> = batch
+        .iter()
+        .enumerate()
+        .map(|(id, request)| {
+            json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "method": request.method,
+                "params": request.params,
+            })
+        })
+        .collect();
+
+    let responses = match post_batch(transport.as_ref(), body).await {
+        Ok(responses) => responses,
+        Err(err) => {
+            let unavailable = err.downcast_ref::<UpstreamUnavailable>().cloned();
+            let message = err.to_string();
+            for request in batch {
+                let error = match &unavailable {
+                    Some(unavailable) => unavailable.clone().into(),
+                    None => eyre!(message.clone()),
+                };
+                let _ = request.response.send(Err(error));
+            }
+            return;
+        }
+    };
+
+    let mut batch: Vec<Option<PendingRequest>> = batch.into_iter().map(Some).collect();
+    for response in responses {
+        let pending = response["id"]
+            .as_u64()
+            .and_then(|id| batch.get_mut(id as usize))
+            .and_then(Option::take);
+        if let Some(pending) = pending {
+            let _ = pending.response.send(parse_response(response));
+        }
+    }
+    for pending in batch.into_iter().flatten() {
+        let _ = pending
+            .response
+            .send(Err(eyre!("Missing response in JSON-RPC batch")));
+    }
+}
+
+async fn post_batch(transport: &dyn Transport, body: Vec<Value>) -> Result<Vec<Value>> {
+    match transport.send(Value::Array(body)).await? {
+        Value::Array(responses) => Ok(responses),
+        response => Err(eyre!("Invalid JSON-RPC batch response: {response}")),
+    }
+}
+
+/// Extract the result of a single JSON-RPC response.
+pub(crate) fn parse_response(mut response: Value) -> Result<Value> {
+    if let Some(error) = response.get("error") {
+        return Err(response_error(error));
+    }
+    match response.get_mut("result") {
+        Some(result) => Ok(result.take()),
+        None => Err(eyre!("Invalid JSON-RPC response: {response}")),
+    }
+}
+
+/// Convert the error of a JSON-RPC response, the StarkNet errors by their code.
+pub(crate) fn response_error(error: &Value) -> eyre::Report {
+    let message = error["message"].as_str().unwrap_or_default();
+    match error["code"]
+        .as_i64()
+        .and_then(StarknetErrorCode::from_code)
+    {
+        Some(code) => StarknetError::new(code, message).into(),
+        None => eyre!("JSON-RPC error {}: {}", error["code"], message),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_response() {
+        let result = parse_response(json!({"jsonrpc": "2.0", "id": 0, "result": "0x1"}));
+        assert_eq!(result.unwrap(), json!("0x1"));
+
+        let error = parse_response(json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "error": {"code": 20, "message": "Contract not found"}
+        }));
+        assert_eq!(
+            error.unwrap_err().downcast_ref::<StarknetError>(),
+            Some(&StarknetError::new(
+                StarknetErrorCode::ContractNotFound,
+                "Contract not found"
+            ))
+        );
+
+        let error = parse_response(json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "error": {"code": -32603, "message": "Internal error"}
+        }));
+        assert_eq!(
+            error.unwrap_err().to_string(),
+            "JSON-RPC error -32603: Internal error"
+        );
+    }
+}
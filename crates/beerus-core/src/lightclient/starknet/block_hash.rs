@@ -0,0 +1,314 @@
+use super::{storage_proof::ProofNode, trie::PatriciaTrie, StarkNetLightClient};
+use eyre::{eyre, Result};
+use futures::future::try_join_all;
+use serde::{Deserialize, Serialize};
+use starknet::{
+    core::{crypto::compute_hash_on_elements, types::FieldElement},
+    providers::jsonrpc::models::{
+        BlockWithTxs, DeclareTransaction, DeployAccountTransaction, EmittedEvent, Event,
+        InvokeTransaction, MaybePendingTransactionReceipt, Transaction, TransactionReceipt,
+    },
+};
+
+/// Height of the transaction and event commitment tries.
+pub const COMMITMENT_TRIE_HEIGHT: usize = 64;
+
+/// Result of the last block hash verification.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockHashCheck {
+    pub block_number: u64,
+    /// Block hash claimed by the StarkNet provider.
+    pub block_hash: FieldElement,
+    pub valid: bool,
+    /// Reason of the failure, if any.
+    pub error: Option<String>,
+}
+
+/// Compute the root of the transaction commitment trie.
+/// Each leaf is `h(transaction_hash, h(signature))`, transactions without a signature use an
+/// empty one.
+pub fn transaction_commitment(transactions: &[Transaction]) -> FieldElement {
+    transaction_trie(transactions).root()
+}
+
+/// Build the proof that the transaction at `index` is a leaf of the transaction commitment
+/// trie, keyed by its index in the block.
+pub fn transaction_commitment_proof(transactions: &[Transaction], index: usize) -> Vec<ProofNode> {
+    transaction_trie(transactions).get_proof(FieldElement::from(index as u64))
+}
+
+/// Compute the leaf of a transaction in the transaction commitment trie.
+pub fn transaction_commitment_leaf(
+    transaction_hash: FieldElement,
+    signature: &[FieldElement],
+) -> FieldElement {
+    compute_hash_on_elements(&[transaction_hash, compute_hash_on_elements(signature)])
+}
+
+fn transaction_trie(transactions: &[Transaction]) -> PatriciaTrie {
+    let mut trie: PatriciaTrie = PatriciaTrie::new(COMMITMENT_TRIE_HEIGHT);
+    for (index, transaction) in transactions.iter().enumerate() {
+        let (transaction_hash, signature) = transaction_hash_and_signature(transaction);
+        trie.insert(
+            FieldElement::from(index as u64),
+            transaction_commitment_leaf(transaction_hash, signature),
+        );
+    }
+    trie
+}
+
+/// Compute the root of the event commitment trie.
+/// Each leaf is `h(from_address, h(keys), h(data))`.
+pub fn event_commitment(events: &[Event]) -> FieldElement {
+    let mut trie: PatriciaTrie = PatriciaTrie::new(COMMITMENT_TRIE_HEIGHT);
+    for (index, event) in events.iter().enumerate() {
+        let leaf = compute_hash_on_elements(&[
+            event.from_address,
+            compute_hash_on_elements(&event.keys),
+            compute_hash_on_elements(&event.data),
+        ]);
+        trie.insert(FieldElement::from(index as u64), leaf);
+    }
+    trie.root()
+}
+
+/// Header of a block along with the commitments of its transactions and events, which is all
+/// that is needed to recompute its hash.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockHeader {
+    pub block_hash: FieldElement,
+    pub block_number: u64,
+    pub parent_hash: FieldElement,
+    pub new_root: FieldElement,
+    pub timestamp: u64,
+    pub sequencer_address: FieldElement,
+    pub transaction_count: u64,
+    pub transaction_commitment: FieldElement,
+    pub event_count: u64,
+    pub event_commitment: FieldElement,
+}
+
+impl BlockHeader {
+    /// Build the header of a block from its transactions and the events they emitted, in order.
+    pub fn new(block: &BlockWithTxs, events: &[Event]) -> Self {
+        Self {
+            block_hash: block.block_hash,
+            block_number: block.block_number,
+            parent_hash: block.parent_hash,
+            new_root: block.new_root,
+            timestamp: block.timestamp,
+            sequencer_address: block.sequencer_address,
+            transaction_count: block.transactions.len() as u64,
+            transaction_commitment: transaction_commitment(&block.transactions),
+            event_count: events.len() as u64,
+            event_commitment: event_commitment(events),
+        }
+    }
+
+    /// Compute the hash of the block, which follows the block hash definition of StarkNet v0.7
+    /// to v0.12.
+    pub fn compute_hash(&self) -> FieldElement {
+        compute_hash_on_elements(&[
+            FieldElement::from(self.block_number),
+            self.new_root,
+            self.sequencer_address,
+            FieldElement::from(self.timestamp),
+            FieldElement::from(self.transaction_count),
+            self.transaction_commitment,
+            FieldElement::from(self.event_count),
+            self.event_commitment,
+            // Protocol version and extra data, both unused.
+            FieldElement::ZERO,
+            FieldElement::ZERO,
+            self.parent_hash,
+        ])
+    }
+}
+
+/// Compute the hash of a block from its header, its transactions and the events they emitted.
+/// This follows the block hash definition of StarkNet v0.7 to v0.12.
+/// # Arguments
+/// * `block` - The block.
+/// * `events` - The events emitted by the block's transactions, in order.
+/// # Returns
+/// The block hash.
+pub fn compute_block_hash(block: &BlockWithTxs, events: &[Event]) -> FieldElement {
+    BlockHeader::new(block, events).compute_hash()
+}
+
+/// Fetch the receipts of a block and check the hash claimed by the provider against the one
+/// recomputed locally.
+/// # Arguments
+/// * `starknet_lightclient` - The StarkNet light client used to fetch the receipts.
+/// * `block` - The block to verify.
+/// # Returns
+/// `Ok(Vec<EmittedEvent>)` with the events of the block if the block hash matches.
+/// `Err(eyre::Report)` if the receipts could not be fetched or the hash does not match.
+pub async fn verify_block_hash(
+    starknet_lightclient: &dyn StarkNetLightClient,
+    block: &BlockWithTxs,
+) -> Result<Vec<EmittedEvent>> {
+    let receipts = fetch_receipts(starknet_lightclient, block).await?;
+    verify_block_hash_with_receipts(block, &receipts)
+}
+
+/// Fetch the receipts of the transactions of a block, in the same order.
+/// They are fetched concurrently, so that they can be batched.
+pub async fn fetch_receipts(
+    starknet_lightclient: &dyn StarkNetLightClient,
+    block: &BlockWithTxs,
+) -> Result<Vec<MaybePendingTransactionReceipt>> {
+    try_join_all(block.transactions.iter().map(|transaction| {
+        starknet_lightclient.get_transaction_receipt(transaction_hash(transaction))
+    }))
+    .await
+}
+
+/// Check the hash claimed by the provider against the one recomputed locally from the
+/// receipts of the block, see [`verify_block_hash`].
+pub fn verify_block_hash_with_receipts(
+    block: &BlockWithTxs,
+    receipts: &[MaybePendingTransactionReceipt],
+) -> Result<Vec<EmittedEvent>> {
+    let mut events = Vec::new();
+    let mut emitted_events = Vec::new();
+    for (transaction, receipt) in block.transactions.iter().zip(receipts) {
+        let transaction_hash = transaction_hash(transaction);
+        match receipt {
+            MaybePendingTransactionReceipt::Receipt(receipt) => {
+                for event in receipt_events(receipt) {
+                    events.push(event.clone());
+                    emitted_events.push(EmittedEvent {
+                        from_address: event.from_address,
+                        keys: event.keys.clone(),
+                        data: event.data.clone(),
+                        block_hash: block.block_hash,
+                        block_number: block.block_number,
+                        transaction_hash,
+                    });
+                }
+            }
+            MaybePendingTransactionReceipt::PendingReceipt(_) => {
+                return Err(eyre!(
+                    "Transaction {transaction_hash} of block {} is pending",
+                    block.block_number
+                ))
+            }
+        }
+    }
+
+    let computed = compute_block_hash(block, &events);
+    if computed != block.block_hash {
+        return Err(eyre!(
+            "Block hash mismatch for block {}: provider claims {}, computed {}",
+            block.block_number,
+            block.block_hash,
+            computed
+        ));
+    }
+    Ok(emitted_events)
+}
+
+/// Return the signature of a transaction, empty for the transactions which are not signed.
+pub fn transaction_signature(transaction: &Transaction) -> &[FieldElement] {
+    transaction_hash_and_signature(transaction).1
+}
+
+/// Return the hash of a transaction.
+pub fn transaction_hash(transaction: &Transaction) -> FieldElement {
+    transaction_hash_and_signature(transaction).0
+}
+
+fn transaction_hash_and_signature(transaction: &Transaction) -> (FieldElement, &[FieldElement]) {
+    match transaction {
+        Transaction::Invoke(InvokeTransaction::V0(tx)) => (tx.transaction_hash, &tx.signature),
+        Transaction::Invoke(InvokeTransaction::V1(tx)) => (tx.transaction_hash, &tx.signature),
+        Transaction::Declare(DeclareTransaction {
+            transaction_hash,
+            signature,
+            ..
+        })
+        | Transaction::DeployAccount(DeployAccountTransaction {
+            transaction_hash,
+            signature,
+            ..
+        }) => (*transaction_hash, signature),
+        Transaction::Deploy(tx) => (tx.transaction_hash, &[]),
+        Transaction::L1Handler(tx) => (tx.transaction_hash, &[]),
+    }
+}
+
+/// Return the hash of the transaction of an accepted receipt, `None` for a pending one.
+pub fn receipt_transaction_hash(receipt: &MaybePendingTransactionReceipt) -> Option<FieldElement> {
+    match receipt {
+        MaybePendingTransactionReceipt::Receipt(receipt) => Some(match receipt {
+            TransactionReceipt::Invoke(receipt) => receipt.transaction_hash,
+            TransactionReceipt::L1Handler(receipt) => receipt.transaction_hash,
+            TransactionReceipt::Declare(receipt) => receipt.transaction_hash,
+            TransactionReceipt::Deploy(receipt) => receipt.transaction_hash,
+            TransactionReceipt::DeployAccount(receipt) => receipt.transaction_hash,
+        }),
+        MaybePendingTransactionReceipt::PendingReceipt(_) => None,
+    }
+}
+
+/// Return the number of the block of an accepted receipt, `None` for a pending one.
+pub fn receipt_block_number(receipt: &MaybePendingTransactionReceipt) -> Option<u64> {
+    match receipt {
+        MaybePendingTransactionReceipt::Receipt(receipt) => Some(match receipt {
+            TransactionReceipt::Invoke(receipt) => receipt.block_number,
+            TransactionReceipt::L1Handler(receipt) => receipt.block_number,
+            TransactionReceipt::Declare(receipt) => receipt.block_number,
+            TransactionReceipt::Deploy(receipt) => receipt.block_number,
+            TransactionReceipt::DeployAccount(receipt) => receipt.block_number,
+        }),
+        MaybePendingTransactionReceipt::PendingReceipt(_) => None,
+    }
+}
+
+/// Return the fee charged for the transaction of a receipt.
+pub fn receipt_actual_fee(receipt: &TransactionReceipt) -> FieldElement {
+    match receipt {
+        TransactionReceipt::Invoke(receipt) => receipt.actual_fee,
+        TransactionReceipt::L1Handler(receipt) => receipt.actual_fee,
+        TransactionReceipt::Declare(receipt) => receipt.actual_fee,
+        TransactionReceipt::Deploy(receipt) => receipt.actual_fee,
+        TransactionReceipt::DeployAccount(receipt) => receipt.actual_fee,
+    }
+}
+
+/// Return the events emitted by the transaction of a receipt.
+pub fn receipt_events(receipt: &TransactionReceipt) -> &[Event] {
+    match receipt {
+        TransactionReceipt::Invoke(receipt) => &receipt.events,
+        TransactionReceipt::L1Handler(receipt) => &receipt.events,
+        TransactionReceipt::Declare(receipt) => &receipt.events,
+        TransactionReceipt::Deploy(receipt) => &receipt.events,
+        TransactionReceipt::DeployAccount(receipt) => &receipt.events,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_commitments_are_zero() {
+        assert_eq!(transaction_commitment(&[]), FieldElement::ZERO);
+        assert_eq!(event_commitment(&[]), FieldElement::ZERO);
+    }
+
+    #[test]
+    fn test_event_commitment_depends_on_order() {
+        let event = |from: u64| Event {
+            from_address: FieldElement::from(from),
+            keys: vec![FieldElement::ONE],
+            data: vec![FieldElement::TWO],
+        };
+
+        assert_ne!(
+            event_commitment(&[event(1), event(2)]),
+            event_commitment(&[event(2), event(1)])
+        );
+    }
+}
@@ -0,0 +1,784 @@
+use super::trie::{
+    class_leaf_hash, contract_state_hash, global_state_root, ClassTrie, PatriciaTrie,
+    STATE_TRIE_HEIGHT,
+};
+use crate::{ethers_helper::bytes_to_u256, lightclient::ethereum::EthereumLightClient};
+use ethers::{
+    abi::{self, ParamType, Token},
+    types::{Address, Transaction, H256, U256},
+    utils::{id, keccak256},
+};
+use eyre::{eyre, Result};
+use helios::types::{BlockTag, CallOpts};
+use starknet::core::types::FieldElement;
+use std::collections::BTreeMap;
+
+/// Signature of the memory page fact registry function used to publish state diffs as calldata.
+/// State diffs are split across one or more of these calls.
+pub const REGISTER_CONTINUOUS_MEMORY_PAGE_SIGNATURE: &str =
+    "registerContinuousMemoryPage(uint256,uint256[],uint256,uint256,uint256)";
+
+/// Signature of the event emitted by the memory page fact registry for every registered page.
+pub const LOG_MEMORY_PAGE_FACT_CONTINUOUS_SIGNATURE: &str =
+    "LogMemoryPageFactContinuous(bytes32,uint256,uint256)";
+
+/// Signature of the fact registry function telling whether a fact was registered.
+pub const IS_VALID_SIGNATURE: &str = "isValid(bytes32)";
+
+/// Signature of the StarkNet core contract function accepting the state update of a block.
+pub const UPDATE_STATE_SIGNATURE: &str = "updateState(uint256[],uint256,uint256)";
+
+/// Type of the EIP-4844 transactions, which publish the state diffs in blobs.
+pub const BLOB_TRANSACTION_TYPE: u64 = 3;
+
+/// Storage, nonce and class updates of a single contract, as published on L1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContractUpdate {
+    pub address: FieldElement,
+    pub nonce: FieldElement,
+    /// Only present if the contract was deployed or replaced its class in the block.
+    pub class_hash: Option<FieldElement>,
+    pub storage_updates: Vec<(FieldElement, FieldElement)>,
+}
+
+/// StarkNet state diff as published on L1 for data availability.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct StateDiff {
+    pub contract_updates: Vec<ContractUpdate>,
+    /// Pairs of (class hash, compiled class hash).
+    pub declared_classes: Vec<(FieldElement, FieldElement)>,
+}
+
+impl StateDiff {
+    /// Decode a state diff from the words published on L1.
+    /// The expected layout is the one used since StarkNet v0.11:
+    /// `n_contracts, [address, packed(class_flag, nonce, n_updates), class_hash?, [key, value]*]*,
+    /// n_declared_classes, [class_hash, compiled_class_hash]*`.
+    /// # Arguments
+    /// * `words` - The published words, in order.
+    /// # Returns
+    /// `Ok(StateDiff)` if the words could be decoded.
+    /// `Err(eyre::Report)` if the data is truncated or malformed.
+    pub fn decode(words: &[FieldElement]) -> Result<Self> {
+        let mut words = words.iter();
+        let mut next = |what: &str| {
+            words
+                .next()
+                .copied()
+                .ok_or_else(|| eyre!("Unexpected end of state diff while reading {what}"))
+        };
+
+        let n_contracts = felt_to_u64(next("number of contracts")?)?;
+        let mut contract_updates = Vec::new();
+        for _ in 0..n_contracts {
+            let address = next("contract address")?;
+            let packed = next("contract header")?.to_bytes_be();
+
+            // Bit 128 is the class flag, bits 64..128 the nonce and bits 0..64 the number of
+            // storage updates.
+            let class_flag = packed[15] & 1 == 1;
+            let nonce = u64::from_be_bytes(packed[16..24].try_into()?);
+            let n_updates = u64::from_be_bytes(packed[24..32].try_into()?);
+
+            let class_hash = match class_flag {
+                true => Some(next("class hash")?),
+                false => None,
+            };
+            let mut storage_updates = Vec::new();
+            for _ in 0..n_updates {
+                let key = next("storage key")?;
+                let value = next("storage value")?;
+                storage_updates.push((key, value));
+            }
+            contract_updates.push(ContractUpdate {
+                address,
+                nonce: FieldElement::from(nonce),
+                class_hash,
+                storage_updates,
+            });
+        }
+
+        let n_declared_classes = felt_to_u64(next("number of declared classes")?)?;
+        let mut declared_classes = Vec::new();
+        for _ in 0..n_declared_classes {
+            let class_hash = next("declared class hash")?;
+            let compiled_class_hash = next("compiled class hash")?;
+            declared_classes.push((class_hash, compiled_class_hash));
+        }
+
+        Ok(Self {
+            contract_updates,
+            declared_classes,
+        })
+    }
+}
+
+/// Extract the published words from the calldata of a `registerContinuousMemoryPage` call.
+/// # Arguments
+/// * `input` - The raw calldata of the L1 transaction.
+/// # Returns
+/// `Ok(Vec<FieldElement>)` with the memory page values.
+/// `Err(eyre::Report)` if the calldata is not a memory page registration.
+pub fn decode_memory_page_calldata(input: &[u8]) -> Result<Vec<FieldElement>> {
+    if input.len() < 4 || input[..4] != id(REGISTER_CONTINUOUS_MEMORY_PAGE_SIGNATURE) {
+        return Err(eyre!("Not a registerContinuousMemoryPage call"));
+    }
+    let tokens = abi::decode(
+        &[
+            ParamType::Uint(256),
+            ParamType::Array(Box::new(ParamType::Uint(256))),
+            ParamType::Uint(256),
+            ParamType::Uint(256),
+            ParamType::Uint(256),
+        ],
+        &input[4..],
+    )?;
+    match tokens.get(1) {
+        Some(Token::Array(values)) => values
+            .iter()
+            .map(|value| match value {
+                Token::Uint(value) => u256_to_felt(*value),
+                _ => Err(eyre!("Invalid memory page value")),
+            })
+            .collect(),
+        _ => Err(eyre!("Invalid memory page values")),
+    }
+}
+
+/// Extract the published words from a memory page registration sent to the fact registry.
+/// State diffs published in blobs are not supported: blobs hold the same words but evaluated
+/// over the BLS12-381 scalar field, so decoding them requires an inverse FFT first.
+/// # Arguments
+/// * `transaction` - The L1 transaction registering the memory page.
+/// * `fact_registry` - The address of the memory page fact registry.
+/// # Returns
+/// `Ok(Vec<FieldElement>)` with the memory page values.
+/// `Err(eyre::Report)` if the transaction is a blob transaction, or not a memory page
+/// registration sent to the fact registry.
+pub fn decode_memory_page_transaction(
+    transaction: &Transaction,
+    fact_registry: Address,
+) -> Result<Vec<FieldElement>> {
+    if transaction.transaction_type == Some(BLOB_TRANSACTION_TYPE.into()) {
+        return Err(eyre!(
+            "Transaction {:?} publishes the state diff in blobs, which is not supported",
+            transaction.hash
+        ));
+    }
+    if transaction.to != Some(fact_registry) {
+        return Err(eyre!(
+            "Transaction {:?} was not sent to the memory page fact registry {:?}",
+            transaction.hash,
+            fact_registry
+        ));
+    }
+    decode_memory_page_calldata(transaction.input.as_ref())
+}
+
+/// Check that the fact of a memory page registration is valid in the fact registry.
+/// # Arguments
+/// * `ethereum_lightclient` - The Ethereum light client.
+/// * `fact_registry` - The address of the memory page fact registry.
+/// * `transaction` - The L1 transaction registering the memory page.
+/// # Returns
+/// `Ok(())` if the fact registry holds the fact of the memory page.
+/// `Err(eyre::Report)` if the transaction registered no fact, or the fact is not valid.
+pub async fn check_memory_page_fact<C: EthereumLightClient + ?Sized>(
+    ethereum_lightclient: &C,
+    fact_registry: Address,
+    transaction: &Transaction,
+) -> Result<()> {
+    let block_number = transaction
+        .block_number
+        .ok_or_else(|| eyre!("Transaction {:?} is not mined", transaction.hash))?;
+    let block = Some(format!("{:#x}", block_number.as_u64()));
+    let logs = ethereum_lightclient
+        .get_logs(
+            &block,
+            &block,
+            &Some(format!("{fact_registry:?}")),
+            &Some(vec![format!(
+                "{:?}",
+                H256::from(keccak256(LOG_MEMORY_PAGE_FACT_CONTINUOUS_SIGNATURE))
+            )]),
+            &None,
+        )
+        .await?;
+    let fact = logs
+        .iter()
+        .find(|log| log.transaction_hash == Some(transaction.hash))
+        .and_then(|log| log.data.get(..32))
+        .ok_or_else(|| {
+            eyre!(
+                "Transaction {:?} registered no memory page",
+                transaction.hash
+            )
+        })?;
+
+    let mut data = id(IS_VALID_SIGNATURE).to_vec();
+    data.extend_from_slice(fact);
+    let call_opts = CallOpts {
+        from: None,
+        to: fact_registry,
+        gas: None,
+        gas_price: None,
+        value: None,
+        data: Some(data),
+    };
+    let valid = ethereum_lightclient
+        .call(&call_opts, BlockTag::Latest)
+        .await?;
+    match bytes_to_u256(&valid)? == U256::one() {
+        true => Ok(()),
+        false => Err(eyre!(
+            "The memory page of transaction {:?} is not a valid fact",
+            transaction.hash
+        )),
+    }
+}
+
+/// Hash and size of the data published for a block, as accepted by the StarkNet core contract.
+/// The state transition fact checked by the core contract covers them, so they bind the
+/// published words to the state update of the block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DataAvailabilityFact {
+    pub onchain_data_hash: U256,
+    pub onchain_data_size: U256,
+}
+
+impl DataAvailabilityFact {
+    /// Compute the fact of the published words, i.e. the keccak of their packed encoding and
+    /// their number.
+    pub fn of(words: &[FieldElement]) -> Self {
+        let data: Vec<u8> = words.iter().flat_map(|word| word.to_bytes_be()).collect();
+        Self {
+            onchain_data_hash: U256::from_big_endian(&keccak256(data)),
+            onchain_data_size: U256::from(words.len()),
+        }
+    }
+
+    /// Check that the words are the data of this fact.
+    /// # Returns
+    /// `Ok(())` if the hash and size of the words match the fact.
+    /// `Err(eyre::Report)` otherwise.
+    pub fn check(&self, words: &[FieldElement]) -> Result<()> {
+        let published = Self::of(words);
+        match published == *self {
+            true => Ok(()),
+            false => Err(eyre!(
+                "Memory pages hash to {:#x} ({} words), the state update accepted {:#x} ({} words)",
+                published.onchain_data_hash,
+                published.onchain_data_size,
+                self.onchain_data_hash,
+                self.onchain_data_size
+            )),
+        }
+    }
+}
+
+/// Extract the data availability fact from an `updateState` call to the StarkNet core contract.
+/// # Arguments
+/// * `transaction` - The L1 transaction updating the state.
+/// * `core_contract` - The address of the StarkNet core contract.
+/// # Returns
+/// `Ok(DataAvailabilityFact)` with the hash and size of the published data.
+/// `Err(eyre::Report)` if the transaction is not an `updateState` call to the core contract.
+pub fn decode_update_state_transaction(
+    transaction: &Transaction,
+    core_contract: Address,
+) -> Result<DataAvailabilityFact> {
+    let input = transaction.input.as_ref();
+    if transaction.to != Some(core_contract)
+        || input.len() < 4
+        || input[..4] != id(UPDATE_STATE_SIGNATURE)
+    {
+        return Err(eyre!(
+            "Transaction {:?} is not an updateState call to the StarkNet core contract",
+            transaction.hash
+        ));
+    }
+    let tokens = abi::decode(
+        &[
+            ParamType::Array(Box::new(ParamType::Uint(256))),
+            ParamType::Uint(256),
+            ParamType::Uint(256),
+        ],
+        &input[4..],
+    )?;
+    match (tokens.get(1), tokens.get(2)) {
+        (Some(Token::Uint(onchain_data_hash)), Some(Token::Uint(onchain_data_size))) => {
+            Ok(DataAvailabilityFact {
+                onchain_data_hash: *onchain_data_hash,
+                onchain_data_size: *onchain_data_size,
+            })
+        }
+        _ => Err(eyre!("Invalid updateState call")),
+    }
+}
+
+/// State of a single contract, rebuilt from the applied state diffs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContractState {
+    pub nonce: FieldElement,
+    pub class_hash: FieldElement,
+    pub storage: BTreeMap<FieldElement, FieldElement>,
+}
+
+impl ContractState {
+    /// Compute the root of the contract storage trie.
+    pub fn storage_root(&self) -> FieldElement {
+        let mut trie: PatriciaTrie = PatriciaTrie::new(STATE_TRIE_HEIGHT);
+        for (key, value) in self.storage.iter() {
+            trie.insert(*key, *value);
+        }
+        trie.root()
+    }
+
+    /// Compute the contract state hash, i.e. the leaf of this contract in the contract trie.
+    pub fn state_hash(&self) -> FieldElement {
+        contract_state_hash(self.class_hash, self.storage_root(), self.nonce)
+    }
+}
+
+impl Default for ContractState {
+    fn default() -> Self {
+        Self {
+            nonce: FieldElement::ZERO,
+            class_hash: FieldElement::ZERO,
+            storage: BTreeMap::new(),
+        }
+    }
+}
+
+/// Local StarkNet state rebuilt from the state diffs posted on L1.
+/// The state is tagged with the L1 proven block and state root of the last applied diff.
+/// Unless every diff since genesis was applied, the state only holds the contracts and keys
+/// written by the diffs applied since it was started, and nothing is known of the others.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalState {
+    pub block_number: u64,
+    pub state_root: FieldElement,
+    pub contracts: BTreeMap<FieldElement, ContractState>,
+    pub declared_classes: BTreeMap<FieldElement, FieldElement>,
+    /// Whether every diff since genesis was applied, in which case the state is complete and its
+    /// root is checked against the L1 state root.
+    pub from_genesis: bool,
+}
+
+impl LocalState {
+    pub fn new() -> Self {
+        Self {
+            block_number: 0,
+            state_root: FieldElement::ZERO,
+            contracts: BTreeMap::new(),
+            declared_classes: BTreeMap::new(),
+            from_genesis: false,
+        }
+    }
+
+    /// Apply a state diff proven on L1 at the given block and state root.
+    /// A diff which doesn't follow the last applied one starts the state over, from genesis if
+    /// it is the diff of block 0.
+    /// Only a state rebuilt from genesis can be checked against the L1 state root, so a partial
+    /// state only accepts diffs checked against the [`DataAvailabilityFact`] of their block.
+    /// # Arguments
+    /// * `diff` - The state diff of the block.
+    /// * `block_number` - The StarkNet block of the diff.
+    /// * `state_root` - The state root proven on L1 for the block.
+    /// * `data_availability_checked` - Whether the words of the diff match the data availability
+    /// fact accepted by the core contract for the block.
+    /// # Returns
+    /// `Ok(())` if the diff was applied.
+    /// `Err(eyre::Report)` if the state was rebuilt from genesis and its root doesn't match the
+    /// L1 state root, in which case the state is dropped, or if the state is partial and the
+    /// diff was not checked, in which case the state is left untouched.
+    pub fn apply(
+        &mut self,
+        diff: &StateDiff,
+        block_number: u64,
+        state_root: FieldElement,
+        data_availability_checked: bool,
+    ) -> Result<()> {
+        // A diff is published for every block, a gap would leave stale values behind.
+        let follows =
+            self.state_root != FieldElement::ZERO && block_number == self.block_number + 1;
+        let from_genesis = match follows {
+            true => self.from_genesis,
+            false => block_number == 0,
+        };
+        if !from_genesis && !data_availability_checked {
+            return Err(eyre!(
+                "State diff of block {block_number} was not checked against its data availability fact, and a partial state root cannot be checked"
+            ));
+        }
+        if !follows {
+            *self = Self {
+                from_genesis: block_number == 0,
+                ..Self::new()
+            };
+        }
+        for update in diff.contract_updates.iter() {
+            let contract = self.contracts.entry(update.address).or_default();
+            contract.nonce = update.nonce;
+            if let Some(class_hash) = update.class_hash {
+                contract.class_hash = class_hash;
+            }
+            for (key, value) in update.storage_updates.iter() {
+                contract.storage.insert(*key, *value);
+            }
+        }
+        for (class_hash, compiled_class_hash) in diff.declared_classes.iter() {
+            self.declared_classes
+                .insert(*class_hash, *compiled_class_hash);
+        }
+        if self.from_genesis {
+            let rebuilt_root = self.global_root();
+            if rebuilt_root != state_root {
+                *self = Self::new();
+                return Err(eyre!(
+                    "Rebuilt state root {rebuilt_root:#x} of block {block_number} does not match the L1 state root {state_root:#x}"
+                ));
+            }
+        }
+        self.block_number = block_number;
+        self.state_root = state_root;
+        Ok(())
+    }
+
+    /// Return the storage value of a contract, zero if it was never written.
+    /// # Returns
+    /// `Ok(FieldElement)` with the storage value.
+    /// `Err(eyre::Report)` if the key was not written by the applied diffs and the state was
+    /// not rebuilt from genesis.
+    pub fn get_storage_at(&self, address: FieldElement, key: FieldElement) -> Result<FieldElement> {
+        let value = self
+            .contract(address)?
+            .and_then(|contract| contract.storage.get(&key));
+        match (value, self.from_genesis) {
+            (Some(value), _) => Ok(*value),
+            (None, true) => Ok(FieldElement::ZERO),
+            (None, false) => Err(eyre!(
+                "Storage key {key:#x} of contract {address:#x} was not written since the local state was started"
+            )),
+        }
+    }
+
+    /// Return the nonce of a contract, zero if it is not deployed.
+    pub fn get_nonce(&self, address: FieldElement) -> Result<FieldElement> {
+        Ok(self
+            .contract(address)?
+            .map(|contract| contract.nonce)
+            .unwrap_or(FieldElement::ZERO))
+    }
+
+    /// Return the class hash of a contract, `None` if it is not deployed.
+    pub fn get_class_hash_at(&self, address: FieldElement) -> Result<Option<FieldElement>> {
+        match self.contract(address)? {
+            Some(contract) if !self.from_genesis && contract.class_hash == FieldElement::ZERO => {
+                Err(eyre!(
+                    "Class of contract {address:#x} was not written since the local state was started"
+                ))
+            }
+            contract => Ok(contract.map(|contract| contract.class_hash)),
+        }
+    }
+
+    /// Return the state of a contract, `None` if it is not deployed.
+    fn contract(&self, address: FieldElement) -> Result<Option<&ContractState>> {
+        match (self.contracts.get(&address), self.from_genesis) {
+            (None, false) => Err(eyre!(
+                "Contract {address:#x} was not written since the local state was started"
+            )),
+            (contract, _) => Ok(contract),
+        }
+    }
+
+    /// Compute the root of the contract trie from the rebuilt contract states.
+    /// It only matches the L1 contracts root once every diff since genesis has been applied.
+    pub fn contracts_root(&self) -> FieldElement {
+        let mut trie: PatriciaTrie = PatriciaTrie::new(STATE_TRIE_HEIGHT);
+        for (address, contract) in self.contracts.iter() {
+            trie.insert(*address, contract.state_hash());
+        }
+        trie.root()
+    }
+
+    /// Compute the root of the class trie from the declared classes.
+    pub fn classes_root(&self) -> FieldElement {
+        let mut trie = ClassTrie::new(STATE_TRIE_HEIGHT);
+        for (class_hash, compiled_class_hash) in self.declared_classes.iter() {
+            trie.insert(*class_hash, class_leaf_hash(*compiled_class_hash));
+        }
+        trie.root()
+    }
+
+    /// Compute the global state root committed on L1 from the rebuilt state.
+    pub fn global_root(&self) -> FieldElement {
+        global_state_root(self.contracts_root(), self.classes_root())
+    }
+}
+
+impl Default for LocalState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn u256_to_felt(value: U256) -> Result<FieldElement> {
+    let mut bytes = [0u8; 32];
+    value.to_big_endian(&mut bytes);
+    FieldElement::from_bytes_be(&bytes).map_err(|_| eyre!("Invalid field element: {value}"))
+}
+
+fn felt_to_u64(value: FieldElement) -> Result<u64> {
+    let bytes = value.to_bytes_be();
+    if bytes[..24].iter().any(|b| *b != 0) {
+        return Err(eyre!("Value {value} does not fit in a u64"));
+    }
+    Ok(u64::from_be_bytes(bytes[24..32].try_into()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::abi::{encode, Token};
+
+    fn felt(value: &str) -> FieldElement {
+        FieldElement::from_hex_be(value).unwrap()
+    }
+
+    #[test]
+    fn test_decode_state_diff() {
+        // Given
+        // One contract with a class update, nonce 2 and a single storage update,
+        // followed by one declared class.
+        let words = vec![
+            felt("0x1"),
+            felt("0x123"),
+            felt("0x100000000000000020000000000000001"),
+            felt("0xabc"),
+            felt("0x5"),
+            felt("0x6"),
+            felt("0x1"),
+            felt("0x777"),
+            felt("0x888"),
+        ];
+
+        // When
+        let diff = StateDiff::decode(&words).unwrap();
+
+        // Then
+        assert_eq!(
+            diff,
+            StateDiff {
+                contract_updates: vec![ContractUpdate {
+                    address: felt("0x123"),
+                    nonce: felt("0x2"),
+                    class_hash: Some(felt("0xabc")),
+                    storage_updates: vec![(felt("0x5"), felt("0x6"))],
+                }],
+                declared_classes: vec![(felt("0x777"), felt("0x888"))],
+            }
+        );
+    }
+
+    #[test]
+    fn test_decode_truncated_state_diff_should_return_error() {
+        let words = vec![felt("0x1"), felt("0x123")];
+
+        match StateDiff::decode(&words) {
+            Err(e) => assert_eq!(
+                "Unexpected end of state diff while reading contract header",
+                e.to_string()
+            ),
+            Ok(_) => panic!("Expected error, got ok"),
+        }
+    }
+
+    #[test]
+    fn test_decode_memory_page_calldata() {
+        let mut input = id(REGISTER_CONTINUOUS_MEMORY_PAGE_SIGNATURE).to_vec();
+        input.extend(encode(&[
+            Token::Uint(U256::zero()),
+            Token::Array(vec![Token::Uint(U256::from(1)), Token::Uint(U256::from(2))]),
+            Token::Uint(U256::zero()),
+            Token::Uint(U256::zero()),
+            Token::Uint(U256::zero()),
+        ]));
+
+        let words = decode_memory_page_calldata(&input).unwrap();
+
+        assert_eq!(words, vec![felt("0x1"), felt("0x2")]);
+    }
+
+    fn storage_diff(address: &str, key: &str, value: &str) -> StateDiff {
+        StateDiff {
+            contract_updates: vec![ContractUpdate {
+                address: felt(address),
+                nonce: felt("0x2"),
+                class_hash: Some(felt("0xabc")),
+                storage_updates: vec![(felt(key), felt(value))],
+            }],
+            declared_classes: vec![(felt("0x777"), felt("0x888"))],
+        }
+    }
+
+    #[test]
+    fn test_decode_blob_transaction_should_return_error() {
+        let transaction = Transaction {
+            transaction_type: Some(BLOB_TRANSACTION_TYPE.into()),
+            to: Some(Address::zero()),
+            ..Default::default()
+        };
+
+        match decode_memory_page_transaction(&transaction, Address::zero()) {
+            Err(e) => assert!(e.to_string().contains("blobs, which is not supported")),
+            Ok(_) => panic!("Expected error, got ok"),
+        }
+    }
+
+    #[test]
+    fn test_decode_update_state_transaction() {
+        let words = vec![felt("0x1"), felt("0x2")];
+        let fact = DataAvailabilityFact::of(&words);
+        let mut input = id(UPDATE_STATE_SIGNATURE).to_vec();
+        input.extend(encode(&[
+            Token::Array(vec![Token::Uint(U256::from(3))]),
+            Token::Uint(fact.onchain_data_hash),
+            Token::Uint(fact.onchain_data_size),
+        ]));
+        let transaction = Transaction {
+            to: Some(Address::zero()),
+            input: input.into(),
+            ..Default::default()
+        };
+
+        let accepted = decode_update_state_transaction(&transaction, Address::zero()).unwrap();
+
+        assert_eq!(accepted, fact);
+        assert_eq!(accepted.onchain_data_size, U256::from(2));
+        assert!(accepted.check(&words).is_ok());
+        assert!(accepted.check(&[felt("0x1"), felt("0x3")]).is_err());
+        assert!(decode_update_state_transaction(&transaction, Address::repeat_byte(1)).is_err());
+    }
+
+    #[test]
+    fn test_apply_unchecked_state_diff_to_partial_state_should_return_error() {
+        let mut state = LocalState::new();
+
+        let result = state.apply(
+            &storage_diff("0x123", "0x5", "0x6"),
+            10,
+            felt("0x99"),
+            false,
+        );
+
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .starts_with("State diff of block 10 was not checked"));
+        assert_eq!(state, LocalState::new());
+    }
+
+    #[test]
+    fn test_apply_state_diff() {
+        let mut state = LocalState::new();
+        let diff = StateDiff {
+            contract_updates: vec![ContractUpdate {
+                address: felt("0x123"),
+                nonce: felt("0x2"),
+                class_hash: None,
+                storage_updates: vec![(felt("0x5"), felt("0x6"))],
+            }],
+            declared_classes: vec![],
+        };
+
+        state.apply(&diff, 10, felt("0x99"), true).unwrap();
+
+        assert_eq!(
+            state.get_storage_at(felt("0x123"), felt("0x5")).unwrap(),
+            felt("0x6")
+        );
+        assert_eq!(state.get_nonce(felt("0x123")).unwrap(), felt("0x2"));
+        assert_eq!(state.block_number, 10);
+        assert_eq!(state.state_root, felt("0x99"));
+        assert!(!state.from_genesis);
+    }
+
+    #[test]
+    fn test_untouched_key_of_partial_state_should_return_error() {
+        let mut state = LocalState::new();
+        state
+            .apply(&storage_diff("0x123", "0x5", "0x6"), 10, felt("0x99"), true)
+            .unwrap();
+
+        assert_eq!(
+            state
+                .get_storage_at(felt("0x123"), felt("0x7"))
+                .unwrap_err()
+                .to_string(),
+            "Storage key 0x7 of contract 0x123 was not written since the local state was started"
+        );
+        assert!(state.get_nonce(felt("0x456")).is_err());
+    }
+
+    #[test]
+    fn test_apply_state_diff_with_gap_starts_state_over() {
+        let mut state = LocalState::new();
+        state
+            .apply(&storage_diff("0x123", "0x5", "0x6"), 10, felt("0x99"), true)
+            .unwrap();
+
+        state
+            .apply(&storage_diff("0x456", "0x5", "0x7"), 12, felt("0x9a"), true)
+            .unwrap();
+
+        assert!(state.get_storage_at(felt("0x123"), felt("0x5")).is_err());
+        assert_eq!(
+            state.get_storage_at(felt("0x456"), felt("0x5")).unwrap(),
+            felt("0x7")
+        );
+    }
+
+    #[test]
+    fn test_apply_state_diff_from_genesis_checks_state_root() {
+        // Given
+        let diff = storage_diff("0x123", "0x5", "0x6");
+        let contract = ContractState {
+            nonce: felt("0x2"),
+            class_hash: felt("0xabc"),
+            storage: BTreeMap::from([(felt("0x5"), felt("0x6"))]),
+        };
+        let mut contracts: PatriciaTrie = PatriciaTrie::new(STATE_TRIE_HEIGHT);
+        contracts.insert(felt("0x123"), contract.state_hash());
+        let mut classes = ClassTrie::new(STATE_TRIE_HEIGHT);
+        classes.insert(felt("0x777"), class_leaf_hash(felt("0x888")));
+        let state_root = global_state_root(contracts.root(), classes.root());
+
+        // When
+        let mut state = LocalState::new();
+        state.apply(&diff, 0, state_root, false).unwrap();
+
+        // Then
+        assert!(state.from_genesis);
+        assert_eq!(state.state_root, state_root);
+        assert_eq!(
+            state.get_storage_at(felt("0x123"), felt("0x7")).unwrap(),
+            FieldElement::ZERO
+        );
+        assert_eq!(state.get_nonce(felt("0x456")).unwrap(), FieldElement::ZERO);
+    }
+
+    #[test]
+    fn test_apply_state_diff_from_genesis_with_wrong_root_should_return_error() {
+        let mut state = LocalState::new();
+
+        let result = state.apply(&storage_diff("0x123", "0x5", "0x6"), 0, felt("0x99"), false);
+
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .ends_with("of block 0 does not match the L1 state root 0x99"));
+        assert_eq!(state, LocalState::new());
+    }
+}
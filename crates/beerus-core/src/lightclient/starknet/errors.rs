@@ -0,0 +1,197 @@
+use crate::lightclient::transport::TransportError;
+use starknet::providers::jsonrpc::{ErrorCode, JsonRpcClientError, RpcError};
+use std::fmt;
+
+/// Error codes defined by the StarkNet JSON-RPC specification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StarknetErrorCode {
+    FailedToReceiveTransaction = 1,
+    ContractNotFound = 20,
+    InvalidMessageSelector = 21,
+    InvalidCallData = 22,
+    BlockNotFound = 24,
+    InvalidTransactionHash = 25,
+    InvalidBlockHash = 26,
+    InvalidTransactionIndex = 27,
+    ClassHashNotFound = 28,
+    PageSizeTooBig = 31,
+    NoBlocks = 32,
+    InvalidContinuationToken = 33,
+    ContractError = 40,
+}
+
+impl StarknetErrorCode {
+    /// Return the JSON-RPC error code.
+    pub fn code(&self) -> i32 {
+        *self as i32
+    }
+
+    /// Return the spec error matching a JSON-RPC error code, if any.
+    pub fn from_code(code: i64) -> Option<Self> {
+        match code {
+            1 => Some(Self::FailedToReceiveTransaction),
+            20 => Some(Self::ContractNotFound),
+            21 => Some(Self::InvalidMessageSelector),
+            22 => Some(Self::InvalidCallData),
+            24 => Some(Self::BlockNotFound),
+            25 => Some(Self::InvalidTransactionHash),
+            26 => Some(Self::InvalidBlockHash),
+            27 => Some(Self::InvalidTransactionIndex),
+            28 => Some(Self::ClassHashNotFound),
+            31 => Some(Self::PageSizeTooBig),
+            32 => Some(Self::NoBlocks),
+            33 => Some(Self::InvalidContinuationToken),
+            40 => Some(Self::ContractError),
+            _ => None,
+        }
+    }
+}
+
+/// Error defined by the StarkNet JSON-RPC specification, either returned by the StarkNet
+/// provider or raised by Beerus itself.
+/// It is wrapped in the `eyre::Report` of the failed operation, so that the RPC server can
+/// downcast it and answer with the spec error code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StarknetError {
+    pub code: StarknetErrorCode,
+    pub message: String,
+}
+
+impl StarknetError {
+    pub fn new(code: StarknetErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for StarknetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for StarknetError {}
+
+/// JSON-RPC error code returned when the StarkNet provider can't be reached.
+pub const UPSTREAM_UNAVAILABLE_CODE: i32 = -32002;
+
+/// The StarkNet provider can't be reached. Only the queries answered from L1 or from the
+/// payload can be served until it is back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpstreamUnavailable {
+    pub message: String,
+}
+
+impl UpstreamUnavailable {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for UpstreamUnavailable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "StarkNet provider unavailable: {}", self.message)
+    }
+}
+
+impl std::error::Error for UpstreamUnavailable {}
+
+/// Whether an operation failed because the StarkNet provider can't be reached.
+pub fn is_upstream_unavailable(error: &eyre::Report) -> bool {
+    error.downcast_ref::<UpstreamUnavailable>().is_some()
+}
+
+/// Convert an HTTP error, connection failures and timeouts meaning that the provider is
+/// unavailable.
+pub fn http_error(error: reqwest::Error) -> eyre::Report {
+    if error.is_connect() || error.is_timeout() {
+        return UpstreamUnavailable::new(error.to_string()).into();
+    }
+    error.into()
+}
+
+/// Convert an error of the StarkNet JSON-RPC client, keeping the spec error code if any.
+/// Transport errors are returned as raised by the transport.
+pub fn provider_error(error: JsonRpcClientError<TransportError>) -> eyre::Report {
+    let error = match error {
+        JsonRpcClientError::TransportError(error) => return error.into_report(),
+        error => error,
+    };
+    let spec_error = match &error {
+        JsonRpcClientError::RpcError(RpcError::Code(code)) => {
+            Some(StarknetError::new(spec_code(code), error.to_string()))
+        }
+        JsonRpcClientError::RpcError(RpcError::Unknown(error)) => {
+            StarknetErrorCode::from_code(error.code)
+                .map(|code| StarknetError::new(code, error.message.clone()))
+        }
+        _ => None,
+    };
+    match spec_error {
+        Some(spec_error) => spec_error.into(),
+        None => eyre::eyre!(error),
+    }
+}
+
+/// Spec error code of an error of the StarkNet JSON-RPC client, every code it knows is one.
+fn spec_code(code: &ErrorCode) -> StarknetErrorCode {
+    match code {
+        ErrorCode::FailedToReceiveTransaction => StarknetErrorCode::FailedToReceiveTransaction,
+        ErrorCode::ContractNotFound => StarknetErrorCode::ContractNotFound,
+        ErrorCode::InvalidMessageSelector => StarknetErrorCode::InvalidMessageSelector,
+        ErrorCode::InvalidCallData => StarknetErrorCode::InvalidCallData,
+        ErrorCode::BlockNotFound => StarknetErrorCode::BlockNotFound,
+        ErrorCode::TransactionHashNotFound => StarknetErrorCode::InvalidTransactionHash,
+        ErrorCode::InvalidTransactionIndex => StarknetErrorCode::InvalidTransactionIndex,
+        ErrorCode::ClassHashNotFound => StarknetErrorCode::ClassHashNotFound,
+        ErrorCode::PageSizeTooBig => StarknetErrorCode::PageSizeTooBig,
+        ErrorCode::NoBlocks => StarknetErrorCode::NoBlocks,
+        ErrorCode::InvalidContinuationToken => StarknetErrorCode::InvalidContinuationToken,
+        ErrorCode::ContractError => StarknetErrorCode::ContractError,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_codes_round_trip() {
+        for code in [
+            StarknetErrorCode::ContractNotFound,
+            StarknetErrorCode::BlockNotFound,
+            StarknetErrorCode::InvalidTransactionHash,
+        ] {
+            assert_eq!(StarknetErrorCode::from_code(code.code() as i64), Some(code));
+        }
+        assert_eq!(StarknetErrorCode::from_code(-32603), None);
+    }
+
+    #[test]
+    fn test_starknet_error_is_kept_in_report() {
+        let report: eyre::Report =
+            StarknetError::new(StarknetErrorCode::BlockNotFound, "Block not found").into();
+
+        assert_eq!(report.to_string(), "Block not found");
+        assert_eq!(
+            report.downcast_ref::<StarknetError>().map(|e| e.code),
+            Some(StarknetErrorCode::BlockNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_connection_failure_is_upstream_unavailable() {
+        // Nothing listens on the discard port.
+        let error = reqwest::Client::new()
+            .post("http://127.0.0.1:9")
+            .send()
+            .await
+            .unwrap_err();
+
+        assert!(is_upstream_unavailable(&http_error(error)));
+    }
+}
@@ -0,0 +1,248 @@
+//! Transactions of version 3, which pay their fee in STRK within resource bounds, and the fee
+//! estimates carrying the unit they are denominated in. The StarkNet models this crate is
+//! built on predate them, so v3 transactions are relayed as received: only the fields the
+//! light client reads are typed.
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use starknet::{
+    core::types::FieldElement,
+    providers::jsonrpc::models::{BroadcastedTransaction, FeeEstimate},
+};
+use std::str::FromStr;
+
+/// Unit a fee is denominated in: wei for ETH, fri for STRK.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum PriceUnit {
+    /// Fees of the transactions prior to version 3, and of providers which don't report a unit.
+    #[default]
+    Wei,
+    Fri,
+}
+
+/// Flags of the fee estimates and simulations, which skip parts of the execution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SimulationFlag {
+    /// Skip the `__validate__` entrypoint of the account, so unsigned transactions can be run.
+    SkipValidate,
+    /// Don't charge the fee, so accounts without funds can be run. Simulations only.
+    SkipFeeCharge,
+}
+
+/// Most a transaction may consume of a resource, and pay for each unit of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResourceBounds {
+    #[serde(with = "hex_felt")]
+    pub max_amount: FieldElement,
+    #[serde(with = "hex_felt")]
+    pub max_price_per_unit: FieldElement,
+}
+
+/// Bounds of the resources of a v3 transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResourceBoundsMapping {
+    pub l1_gas: ResourceBounds,
+    pub l2_gas: ResourceBounds,
+}
+
+/// Broadcasted transaction of version 3, whose fee is paid in STRK.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BroadcastedTransactionV3 {
+    #[serde(rename = "type")]
+    pub transaction_type: String,
+    #[serde(with = "hex_felt")]
+    pub version: FieldElement,
+    pub resource_bounds: ResourceBoundsMapping,
+    /// Fields relayed as received.
+    #[serde(flatten)]
+    pub fields: Map<String, Value>,
+}
+
+impl BroadcastedTransactionV3 {
+    /// Most the transaction may pay, in fri.
+    pub fn max_fee(&self) -> FieldElement {
+        let l1_gas = &self.resource_bounds.l1_gas;
+        l1_gas.max_amount * l1_gas.max_price_per_unit
+    }
+
+    /// Address of the account sending the transaction.
+    pub fn sender_address(&self) -> Option<FieldElement> {
+        self.felt("sender_address")
+    }
+
+    pub fn nonce(&self) -> Option<FieldElement> {
+        self.felt("nonce")
+    }
+
+    fn felt(&self, name: &str) -> Option<FieldElement> {
+        self.fields
+            .get(name)
+            .and_then(Value::as_str)
+            .and_then(|value| FieldElement::from_str(value).ok())
+    }
+}
+
+/// Broadcasted transaction of any version.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum AnyBroadcastedTransaction {
+    /// Recognized by its resource bounds.
+    V3(BroadcastedTransactionV3),
+    Legacy(BroadcastedTransaction),
+}
+
+/// Fee estimate along with the unit it is denominated in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeEstimateWithUnit {
+    #[serde(with = "hex_felt")]
+    pub gas_consumed: FieldElement,
+    #[serde(with = "hex_felt")]
+    pub gas_price: FieldElement,
+    /// Data availability gas, reported by providers from spec v0.7.
+    #[serde(
+        default,
+        with = "hex_felt_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub data_gas_consumed: Option<FieldElement>,
+    #[serde(
+        default,
+        with = "hex_felt_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub data_gas_price: Option<FieldElement>,
+    #[serde(with = "hex_felt")]
+    pub overall_fee: FieldElement,
+    #[serde(default)]
+    pub unit: PriceUnit,
+}
+
+impl From<FeeEstimate> for FeeEstimateWithUnit {
+    fn from(estimate: FeeEstimate) -> Self {
+        Self {
+            gas_consumed: FieldElement::from(estimate.gas_consumed),
+            gas_price: FieldElement::from(estimate.gas_price),
+            data_gas_consumed: None,
+            data_gas_price: None,
+            overall_fee: FieldElement::from(estimate.overall_fee),
+            unit: PriceUnit::Wei,
+        }
+    }
+}
+
+/// Felts written in hexadecimal, as in the JSON-RPC spec.
+mod hex_felt {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+    use starknet::core::types::FieldElement;
+    use std::str::FromStr;
+
+    pub fn serialize<S: Serializer>(
+        value: &FieldElement,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{value:#x}"))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<FieldElement, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        FieldElement::from_str(&value).map_err(de::Error::custom)
+    }
+}
+
+mod hex_felt_option {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use starknet::core::types::FieldElement;
+
+    pub fn serialize<S: Serializer>(
+        value: &Option<FieldElement>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => super::hex_felt::serialize(value, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<FieldElement>, D::Error> {
+        #[derive(Deserialize)]
+        struct Hex(#[serde(with = "super::hex_felt")] FieldElement);
+        Ok(Option::<Hex>::deserialize(deserializer)?.map(|Hex(value)| value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_v3_transaction_is_relayed_as_received() {
+        let transaction = json!({
+            "type": "INVOKE",
+            "version": "0x3",
+            "sender_address": "0x123",
+            "calldata": ["0x1"],
+            "signature": ["0x2", "0x3"],
+            "nonce": "0x4",
+            "resource_bounds": {
+                "l1_gas": {"max_amount": "0x100", "max_price_per_unit": "0x10"},
+                "l2_gas": {"max_amount": "0x0", "max_price_per_unit": "0x0"}
+            },
+            "tip": "0x0",
+            "paymaster_data": [],
+            "account_deployment_data": [],
+            "nonce_data_availability_mode": "L1",
+            "fee_data_availability_mode": "L1"
+        });
+
+        let parsed: AnyBroadcastedTransaction =
+            serde_json::from_value(transaction.clone()).unwrap();
+        let v3 = match &parsed {
+            AnyBroadcastedTransaction::V3(v3) => v3,
+            AnyBroadcastedTransaction::Legacy(_) => panic!("Expected a v3 transaction"),
+        };
+        assert_eq!(v3.max_fee(), FieldElement::from(0x1000_u64));
+        assert_eq!(v3.sender_address(), Some(FieldElement::from(0x123_u64)));
+        assert_eq!(v3.nonce(), Some(FieldElement::from(4_u64)));
+        assert_eq!(serde_json::to_value(&parsed).unwrap(), transaction);
+    }
+
+    #[test]
+    fn test_simulation_flags_follow_the_spec() {
+        let flags: Vec<SimulationFlag> =
+            serde_json::from_value(json!(["SKIP_VALIDATE", "SKIP_FEE_CHARGE"])).unwrap();
+        assert_eq!(
+            flags,
+            vec![SimulationFlag::SkipValidate, SimulationFlag::SkipFeeCharge]
+        );
+        assert!(serde_json::from_value::<SimulationFlag>(json!("SKIP_EXECUTE")).is_err());
+    }
+
+    #[test]
+    fn test_fee_estimate_without_unit_is_in_wei() {
+        let estimate: FeeEstimateWithUnit = serde_json::from_value(json!({
+            "gas_consumed": "0x10",
+            "gas_price": "0x2",
+            "overall_fee": "0x20"
+        }))
+        .unwrap();
+        assert_eq!(estimate.unit, PriceUnit::Wei);
+
+        let estimate: FeeEstimateWithUnit = serde_json::from_value(json!({
+            "gas_consumed": "0x10",
+            "gas_price": "0x2",
+            "data_gas_consumed": "0x1",
+            "data_gas_price": "0x1",
+            "overall_fee": "0x21",
+            "unit": "FRI"
+        }))
+        .unwrap();
+        assert_eq!(estimate.unit, PriceUnit::Fri);
+        assert_eq!(estimate.overall_fee, FieldElement::from(0x21_u64));
+    }
+}
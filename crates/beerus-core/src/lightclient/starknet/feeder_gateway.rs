@@ -0,0 +1,236 @@
+//! Client of the StarkNet feeder gateway, the HTTP API of the sequencer, used as a secondary
+//! source of blocks when the JSON-RPC provider fails or lags.
+//!
+//! The gateway serves blocks in its own format, they are converted to the JSON-RPC one so that
+//! the rest of the light client handles them like the blocks of the provider.
+use eyre::{eyre, Result};
+use serde_json::{json, Map, Value};
+use starknet::providers::jsonrpc::models::{BlockId, BlockTag, MaybePendingBlockWithTxs};
+use url::Url;
+
+/// Client of the feeder gateway of a StarkNet network.
+#[derive(Debug, Clone)]
+pub struct FeederGatewayClient {
+    url: Url,
+    http_client: reqwest::Client,
+}
+
+impl FeederGatewayClient {
+    /// # Arguments
+    /// * `url` - The base URL of the gateway, e.g. `https://alpha-mainnet.starknet.io`.
+    /// * `http_client` - The HTTP client sending the requests.
+    pub fn new(url: &str, http_client: reqwest::Client) -> Result<Self> {
+        Ok(Self {
+            url: Url::parse(url)?.join("feeder_gateway/")?,
+            http_client,
+        })
+    }
+
+    /// Get a block with its transactions.
+    /// # Returns
+    /// `Ok(MaybePendingBlockWithTxs)` if the gateway returned the block.
+    /// `Err(eyre::Report)` if the request failed or the block could not be converted.
+    pub async fn get_block_with_txs(&self, block_id: &BlockId) -> Result<MaybePendingBlockWithTxs> {
+        let query = match block_id {
+            BlockId::Hash(hash) => ("blockHash", format!("{hash:#x}")),
+            BlockId::Number(number) => ("blockNumber", number.to_string()),
+            BlockId::Tag(BlockTag::Latest) => ("blockNumber", "latest".to_string()),
+            BlockId::Tag(BlockTag::Pending) => ("blockNumber", "pending".to_string()),
+        };
+        let response = self
+            .http_client
+            .get(self.url.join("get_block")?)
+            .query(&[query])
+            .send()
+            .await?;
+        let status = response.status();
+        let block: Value = response.json().await?;
+        if !status.is_success() {
+            let message = block.get("message").and_then(Value::as_str).unwrap_or("");
+            return Err(eyre!("Feeder gateway error {status}: {message}"));
+        }
+        Ok(serde_json::from_value(block_to_rpc(&block)?)?)
+    }
+}
+
+/// Convert a block of the feeder gateway to the JSON-RPC format.
+pub fn block_to_rpc(block: &Value) -> Result<Value> {
+    let transactions = block
+        .get("transactions")
+        .and_then(Value::as_array)
+        .ok_or_else(|| eyre!("Feeder gateway block without transactions"))?
+        .iter()
+        .map(transaction_to_rpc)
+        .collect::<Result<Vec<_>>>()?;
+    let mut rpc = Map::new();
+    // Pending blocks have neither a hash nor a number yet.
+    if block.get("block_hash").is_some() {
+        for (rpc_field, field) in [
+            ("status", "status"),
+            ("block_hash", "block_hash"),
+            ("block_number", "block_number"),
+            ("new_root", "state_root"),
+        ] {
+            rpc.insert(rpc_field.to_string(), required(block, field)?.clone());
+        }
+    }
+    for (rpc_field, field) in [
+        ("parent_hash", "parent_block_hash"),
+        ("timestamp", "timestamp"),
+        ("sequencer_address", "sequencer_address"),
+    ] {
+        rpc.insert(rpc_field.to_string(), required(block, field)?.clone());
+    }
+    rpc.insert("transactions".to_string(), Value::Array(transactions));
+    Ok(Value::Object(rpc))
+}
+
+/// Convert a transaction of the feeder gateway to the JSON-RPC format.
+fn transaction_to_rpc(transaction: &Value) -> Result<Value> {
+    let kind = required(transaction, "type")?
+        .as_str()
+        .ok_or_else(|| eyre!("Invalid feeder gateway transaction type"))?;
+    let version = transaction.get("version").cloned().unwrap_or(json!("0x0"));
+    let (rpc_kind, fields): (_, &[&str]) = match kind {
+        "INVOKE_FUNCTION" if version == json!("0x0") => (
+            "INVOKE",
+            &[
+                "max_fee",
+                "contract_address",
+                "entry_point_selector",
+                "calldata",
+            ],
+        ),
+        "INVOKE_FUNCTION" => ("INVOKE", &["max_fee", "sender_address", "calldata"]),
+        "DECLARE" => ("DECLARE", &["max_fee", "class_hash", "sender_address"]),
+        "DEPLOY" => (
+            "DEPLOY",
+            &[
+                "class_hash",
+                "contract_address_salt",
+                "constructor_calldata",
+            ],
+        ),
+        "DEPLOY_ACCOUNT" => (
+            "DEPLOY_ACCOUNT",
+            &[
+                "max_fee",
+                "class_hash",
+                "contract_address_salt",
+                "constructor_calldata",
+            ],
+        ),
+        "L1_HANDLER" => (
+            "L1_HANDLER",
+            &["contract_address", "entry_point_selector", "calldata"],
+        ),
+        _ => return Err(eyre!("Unsupported feeder gateway transaction type: {kind}")),
+    };
+    let mut rpc = Map::new();
+    rpc.insert("type".to_string(), json!(rpc_kind));
+    rpc.insert(
+        "transaction_hash".to_string(),
+        required(transaction, "transaction_hash")?.clone(),
+    );
+    rpc.insert("version".to_string(), version);
+    if rpc_kind != "DEPLOY" {
+        rpc.insert(
+            "nonce".to_string(),
+            transaction.get("nonce").cloned().unwrap_or(json!("0x0")),
+        );
+    }
+    if rpc_kind != "DEPLOY" && rpc_kind != "L1_HANDLER" {
+        rpc.insert(
+            "signature".to_string(),
+            transaction.get("signature").cloned().unwrap_or(json!([])),
+        );
+    }
+    for field in fields {
+        let value = match (*field, transaction.get(*field)) {
+            (_, Some(value)) => value.clone(),
+            // Invoke transactions of version 1 used to name their sender `contract_address`.
+            ("sender_address", None) => required(transaction, "contract_address")?.clone(),
+            (field, None) => return Err(eyre!("Feeder gateway response without {field}")),
+        };
+        rpc.insert(field.to_string(), value);
+    }
+    Ok(Value::Object(rpc))
+}
+
+fn required<'a>(value: &'a Value, field: &str) -> Result<&'a Value> {
+    value
+        .get(field)
+        .ok_or_else(|| eyre!("Feeder gateway response without {field}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use starknet::{
+        core::types::FieldElement,
+        providers::jsonrpc::models::{InvokeTransaction, Transaction},
+    };
+
+    #[test]
+    fn test_feeder_gateway_block_is_converted_to_rpc() {
+        let block = json!({
+            "block_hash": "0x10",
+            "parent_block_hash": "0x9",
+            "block_number": 5,
+            "state_root": "0x123",
+            "status": "ACCEPTED_ON_L2",
+            "gas_price": "0x1",
+            "timestamp": 1000,
+            "sequencer_address": "0x2",
+            "transactions": [
+                {
+                    "transaction_hash": "0x100",
+                    "version": "0x1",
+                    "max_fee": "0x5",
+                    "signature": ["0x7"],
+                    "nonce": "0x3",
+                    "contract_address": "0x4",
+                    "calldata": ["0x1"],
+                    "type": "INVOKE_FUNCTION"
+                },
+                {
+                    "transaction_hash": "0x101",
+                    "version": "0x0",
+                    "contract_address": "0x4",
+                    "entry_point_selector": "0x6",
+                    "nonce": "0x1",
+                    "calldata": [],
+                    "type": "L1_HANDLER"
+                }
+            ],
+            "transaction_receipts": []
+        });
+        let block: MaybePendingBlockWithTxs =
+            serde_json::from_value(block_to_rpc(&block).unwrap()).unwrap();
+        let block = match block {
+            MaybePendingBlockWithTxs::Block(block) => block,
+            MaybePendingBlockWithTxs::PendingBlock(_) => panic!("Expected a block"),
+        };
+        assert_eq!(block.block_number, 5);
+        assert_eq!(block.parent_hash, FieldElement::from(9_u64));
+        assert_eq!(block.new_root, FieldElement::from(0x123_u64));
+        match &block.transactions[0] {
+            Transaction::Invoke(InvokeTransaction::V1(invoke)) => {
+                assert_eq!(invoke.sender_address, FieldElement::from(4_u64));
+            }
+            _ => panic!("Expected an invoke transaction"),
+        }
+        assert!(matches!(block.transactions[1], Transaction::L1Handler(_)));
+    }
+
+    #[test]
+    fn test_unsupported_feeder_gateway_transaction_is_rejected() {
+        let block = json!({
+            "parent_block_hash": "0x9",
+            "timestamp": 1000,
+            "sequencer_address": "0x2",
+            "transactions": [{ "transaction_hash": "0x100", "type": "UNKNOWN" }]
+        });
+        assert!(block_to_rpc(&block).is_err());
+    }
+}
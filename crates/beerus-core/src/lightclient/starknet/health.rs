@@ -0,0 +1,235 @@
+//! Health of the upstream providers: the outcome and latency of the requests sent to each of
+//! them, and how often a source of blocks disagrees with the others on the latest block.
+//! Providers are named after the host of their URL, which keeps the API keys carried in the
+//! paths out of the metrics.
+use crate::lightclient::transport::Transport;
+use async_trait::async_trait;
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::{value::RawValue, Value};
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    sync::{Arc, Mutex},
+};
+use url::Url;
+
+/// Consecutive divergences after which a source of blocks is tried after the others.
+pub const DOWN_RANK_DIVERGENCES: u64 = 3;
+
+/// Name of the feeder gateway among the providers.
+pub const FEEDER_GATEWAY_PROVIDER: &str = "feeder_gateway";
+
+/// Health of a provider since the start, as exposed by `beerus_status`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProviderHealth {
+    /// Host of the provider, or `feeder_gateway`.
+    pub provider: String,
+    pub requests: u64,
+    /// Requests which didn't get a response.
+    pub failures: u64,
+    /// Share of the requests which got a response, 1 before the first request.
+    pub success_rate: f64,
+    pub avg_latency_ms: f64,
+    pub last_latency_ms: u64,
+    /// Latest blocks older than the one already returned by another source.
+    pub divergences: u64,
+    /// Divergences since the provider last agreed with the others.
+    pub consecutive_divergences: u64,
+    /// Whether the provider is tried after the others as a source of blocks.
+    pub down_ranked: bool,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    requests: u64,
+    failures: u64,
+    total_latency_ms: u64,
+    last_latency_ms: u64,
+    divergences: u64,
+    consecutive_divergences: u64,
+}
+
+/// Health of the providers, shared by the transports of the StarkNet light client.
+#[derive(Debug, Default)]
+pub struct HealthTracker {
+    providers: Mutex<BTreeMap<String, Counters>>,
+}
+
+impl HealthTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the outcome of a request sent to a provider.
+    pub fn record_request(&self, provider: &str, success: bool, latency_ms: u64) {
+        self.update(provider, |counters| {
+            counters.requests += 1;
+            counters.failures += u64::from(!success);
+            counters.total_latency_ms += latency_ms;
+            counters.last_latency_ms = latency_ms;
+        });
+    }
+
+    /// Record a latest block older than the one returned by another source.
+    pub fn record_divergence(&self, provider: &str) {
+        self.update(provider, |counters| {
+            counters.divergences += 1;
+            counters.consecutive_divergences += 1;
+        });
+    }
+
+    /// Record a latest block at least as recent as the ones returned by the other sources.
+    pub fn record_agreement(&self, provider: &str) {
+        self.update(provider, |counters| counters.consecutive_divergences = 0);
+    }
+
+    /// Whether a provider disagreed with the others too many times in a row.
+    pub fn is_down_ranked(&self, provider: &str) -> bool {
+        self.providers
+            .lock()
+            .unwrap()
+            .get(provider)
+            .map_or(false, |counters| {
+                counters.consecutive_divergences >= DOWN_RANK_DIVERGENCES
+            })
+    }
+
+    /// Return the health of the providers, by name.
+    pub fn snapshot(&self) -> Vec<ProviderHealth> {
+        self.providers
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(provider, counters)| {
+                let answered = counters.requests - counters.failures;
+                let (success_rate, avg_latency_ms) = match counters.requests {
+                    0 => (1.0, 0.0),
+                    requests => (
+                        answered as f64 / requests as f64,
+                        counters.total_latency_ms as f64 / requests as f64,
+                    ),
+                };
+                ProviderHealth {
+                    provider: provider.clone(),
+                    requests: counters.requests,
+                    failures: counters.failures,
+                    success_rate,
+                    avg_latency_ms,
+                    last_latency_ms: counters.last_latency_ms,
+                    divergences: counters.divergences,
+                    consecutive_divergences: counters.consecutive_divergences,
+                    down_ranked: counters.consecutive_divergences >= DOWN_RANK_DIVERGENCES,
+                }
+            })
+            .collect()
+    }
+
+    fn update(&self, provider: &str, update: impl FnOnce(&mut Counters)) {
+        let mut providers = self.providers.lock().unwrap();
+        update(providers.entry(provider.to_string()).or_default());
+    }
+}
+
+/// Return the name of a provider: the host and port of its URL.
+pub fn provider_name(url: &str) -> String {
+    match Url::parse(url) {
+        Ok(url) => match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{host}:{port}"),
+            (Some(host), None) => host.to_string(),
+            _ => url.scheme().to_string(),
+        },
+        Err(_) => "unknown".to_string(),
+    }
+}
+
+/// Run a future, measuring its latency in milliseconds.
+/// There is no monotonic clock in the browser, the latency is 0 there.
+pub async fn timed<F: Future>(future: F) -> (F::Output, u64) {
+    #[cfg(not(target_arch = "wasm32"))]
+    let start = std::time::Instant::now();
+    let output = future.await;
+    #[cfg(not(target_arch = "wasm32"))]
+    let latency_ms = start.elapsed().as_millis() as u64;
+    #[cfg(target_arch = "wasm32")]
+    let latency_ms = 0;
+    (output, latency_ms)
+}
+
+/// Transport recording the outcome and latency of the requests it carries.
+#[derive(Debug)]
+pub struct HealthTransport {
+    provider: String,
+    inner: Arc<dyn Transport>,
+    health: Arc<HealthTracker>,
+}
+
+impl HealthTransport {
+    pub fn new(provider: String, inner: Arc<dyn Transport>, health: Arc<HealthTracker>) -> Self {
+        Self {
+            provider,
+            inner,
+            health,
+        }
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl Transport for HealthTransport {
+    async fn send(&self, message: Value) -> Result<Value> {
+        let (response, latency_ms) = timed(self.inner.send(message)).await;
+        self.health
+            .record_request(&self.provider, response.is_ok(), latency_ms);
+        response
+    }
+
+    async fn send_raw(&self, message: Value) -> Result<Box<RawValue>> {
+        let (response, latency_ms) = timed(self.inner.send_raw(message)).await;
+        self.health
+            .record_request(&self.provider, response.is_ok(), latency_ms);
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_provider_is_down_ranked_after_consecutive_divergences() {
+        let health = HealthTracker::new();
+        health.record_request("rpc.example", true, 10);
+        health.record_request("rpc.example", false, 30);
+        for _ in 0..DOWN_RANK_DIVERGENCES - 1 {
+            health.record_divergence(FEEDER_GATEWAY_PROVIDER);
+        }
+        assert!(!health.is_down_ranked(FEEDER_GATEWAY_PROVIDER));
+        health.record_divergence(FEEDER_GATEWAY_PROVIDER);
+        assert!(health.is_down_ranked(FEEDER_GATEWAY_PROVIDER));
+
+        let snapshot = health.snapshot();
+        assert_eq!(snapshot[0].provider, FEEDER_GATEWAY_PROVIDER);
+        assert_eq!(snapshot[0].divergences, DOWN_RANK_DIVERGENCES);
+        assert!(snapshot[0].down_ranked);
+        assert_eq!(snapshot[0].success_rate, 1.0);
+        assert_eq!(snapshot[1].requests, 2);
+        assert_eq!(snapshot[1].success_rate, 0.5);
+        assert_eq!(snapshot[1].avg_latency_ms, 20.0);
+        assert_eq!(snapshot[1].last_latency_ms, 30);
+
+        health.record_agreement(FEEDER_GATEWAY_PROVIDER);
+        assert!(!health.is_down_ranked(FEEDER_GATEWAY_PROVIDER));
+        assert_eq!(health.snapshot()[0].divergences, DOWN_RANK_DIVERGENCES);
+    }
+
+    #[test]
+    fn test_provider_name_leaves_the_path_out() {
+        assert_eq!(
+            provider_name("https://starknet-mainnet.infura.io/v3/secret"),
+            "starknet-mainnet.infura.io"
+        );
+        assert_eq!(provider_name("ws://127.0.0.1:9545/rpc"), "127.0.0.1:9545");
+        assert_eq!(provider_name("not a url"), "unknown");
+    }
+}
@@ -0,0 +1,160 @@
+//! Proofs that a transaction is included in a block, which third parties can check without
+//! trusting Beerus: the transaction is a leaf of the transaction commitment of the block, the
+//! header hashes to the block hash, and the state root of the header is the one proven on L1.
+use super::{
+    block_hash::{
+        transaction_commitment_leaf, transaction_commitment_proof, transaction_hash,
+        transaction_signature, BlockHeader, COMMITMENT_TRIE_HEIGHT,
+    },
+    storage_proof::{felt_to_bits_be, Membership, ProofNode, ProofRequest},
+};
+use eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
+use starknet::{
+    core::types::FieldElement,
+    providers::jsonrpc::models::{BlockWithTxs, Event},
+};
+
+/// Proof that a transaction is included in a block.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionProof {
+    pub transaction_hash: FieldElement,
+    pub signature: Vec<FieldElement>,
+    /// Position of the transaction in its block, which is its key in the commitment trie.
+    pub transaction_index: u64,
+    /// Path from the transaction commitment of the header down to the transaction.
+    pub commitment_proof: Vec<ProofNode>,
+    pub block_header: BlockHeader,
+    /// State root of the block proven on L1, `None` if the block is not proven yet.
+    pub l1_state_root: Option<FieldElement>,
+}
+
+impl TransactionProof {
+    /// Build the proof of the transaction at `index` in a block.
+    /// # Arguments
+    /// * `block` - The block of the transaction.
+    /// * `events` - The events emitted by the block's transactions, in order.
+    /// * `index` - The position of the transaction in the block.
+    /// * `l1_state_root` - The state root of the block proven on L1, if any.
+    pub fn new(
+        block: &BlockWithTxs,
+        events: &[Event],
+        index: usize,
+        l1_state_root: Option<FieldElement>,
+    ) -> Option<Self> {
+        let transaction = block.transactions.get(index)?;
+        Some(Self {
+            transaction_hash: transaction_hash(transaction),
+            signature: transaction_signature(transaction).to_vec(),
+            transaction_index: index as u64,
+            commitment_proof: transaction_commitment_proof(&block.transactions, index),
+            block_header: BlockHeader::new(block, events),
+            l1_state_root,
+        })
+    }
+
+    /// Check the proof, from the transaction up to the state root proven on L1.
+    /// # Returns
+    /// `Ok(())` if the transaction is included in a block proven on L1.
+    /// `Err(eyre::Report)` with the first check which failed otherwise.
+    pub fn verify(&self) -> Result<()> {
+        let leaf = transaction_commitment_leaf(self.transaction_hash, &self.signature);
+        let bits = felt_to_bits_be(FieldElement::from(self.transaction_index));
+        let request = ProofRequest::new(
+            self.block_header.transaction_commitment,
+            &bits[bits.len() - COMMITMENT_TRIE_HEIGHT..],
+            leaf,
+            &self.commitment_proof,
+        );
+        if request.verify() != Some(Membership::Member) {
+            return Err(eyre!(
+                "Transaction {} is not in the transaction commitment of block {}",
+                self.transaction_hash,
+                self.block_header.block_number
+            ));
+        }
+        let computed = self.block_header.compute_hash();
+        if computed != self.block_header.block_hash {
+            return Err(eyre!(
+                "Block hash mismatch for block {}: header claims {}, computed {}",
+                self.block_header.block_number,
+                self.block_header.block_hash,
+                computed
+            ));
+        }
+        match self.l1_state_root {
+            Some(state_root) if state_root == self.block_header.new_root => Ok(()),
+            Some(state_root) => Err(eyre!(
+                "State root mismatch for block {}: header claims {}, L1 proves {}",
+                self.block_header.block_number,
+                self.block_header.new_root,
+                state_root
+            )),
+            None => Err(eyre!(
+                "Block {} is not proven on L1",
+                self.block_header.block_number
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use starknet::providers::jsonrpc::models::{BlockStatus, L1HandlerTransaction, Transaction};
+
+    fn block() -> BlockWithTxs {
+        let l1_handler = |nonce: u64| {
+            Transaction::L1Handler(L1HandlerTransaction {
+                transaction_hash: FieldElement::from(0x100 + nonce),
+                version: 0,
+                nonce,
+                contract_address: FieldElement::ONE,
+                entry_point_selector: FieldElement::TWO,
+                calldata: vec![],
+            })
+        };
+        let mut block = BlockWithTxs {
+            status: BlockStatus::AcceptedOnL1,
+            block_hash: FieldElement::ZERO,
+            parent_hash: FieldElement::ONE,
+            block_number: 10,
+            new_root: FieldElement::from(0x123_u64),
+            timestamp: 1_000,
+            sequencer_address: FieldElement::TWO,
+            transactions: (0..3).map(l1_handler).collect(),
+        };
+        block.block_hash = BlockHeader::new(&block, &[]).compute_hash();
+        block
+    }
+
+    #[test]
+    fn test_proof_of_included_transaction_is_verified() {
+        let block = block();
+        for index in 0..block.transactions.len() {
+            let proof = TransactionProof::new(&block, &[], index, Some(block.new_root)).unwrap();
+            assert!(proof.verify().is_ok());
+        }
+        assert_eq!(TransactionProof::new(&block, &[], 3, None), None);
+    }
+
+    #[test]
+    fn test_proof_of_tampered_transaction_or_unproven_block_is_rejected() {
+        let block = block();
+        let proof = TransactionProof::new(&block, &[], 1, Some(block.new_root)).unwrap();
+
+        let mut tampered = proof.clone();
+        tampered.transaction_hash = FieldElement::from(0x200_u64);
+        assert!(tampered.verify().is_err());
+
+        let mut moved = proof.clone();
+        moved.transaction_index = 2;
+        assert!(moved.verify().is_err());
+
+        let unproven = TransactionProof {
+            l1_state_root: None,
+            ..proof
+        };
+        assert!(unproven.verify().is_err());
+    }
+}
@@ -1,37 +1,117 @@
-use crate::{config::Config, lightclient::starknet::storage_proof::GetProofOutput};
+use crate::{
+    config::{BlockSource, Config},
+    lightclient::starknet::{
+        batch::BatchClient,
+        errors::provider_error,
+        fee::{
+            AnyBroadcastedTransaction, BroadcastedTransactionV3, FeeEstimateWithUnit,
+            SimulationFlag,
+        },
+        feeder_gateway::FeederGatewayClient,
+        health::{
+            provider_name, timed, HealthTracker, HealthTransport, ProviderHealth,
+            FEEDER_GATEWAY_PROVIDER,
+        },
+        storage_proof::GetProofOutput,
+    },
+    lightclient::transport::{
+        faults::{FaultConfig, FaultyConnector},
+        Connector, DefaultConnector, RpcTransport, Transport,
+    },
+};
+use arc_swap::ArcSwap;
 use async_trait::async_trait;
-use ethers::providers::{Http, Provider};
-use eyre::Result;
+use ethers::providers::Provider;
+use eyre::{eyre, Result};
 use mockall::automock;
 use serde::Serialize;
+use serde_json::{json, value::RawValue, Value};
 use starknet::{
     core::types::FieldElement,
     providers::jsonrpc::{
         models::{
-            BlockHashAndNumber, BlockId, BroadcastedDeclareTransaction,
+            BlockHashAndNumber, BlockId, BlockTag, BlockWithTxs, BroadcastedDeclareTransaction,
             BroadcastedDeployTransaction, BroadcastedInvokeTransaction, BroadcastedTransaction,
             ContractClass, DeclareTransactionResult, DeployTransactionResult, EventFilter,
             EventsPage, FeeEstimate, FunctionCall, InvokeTransactionResult,
             MaybePendingBlockWithTxHashes, MaybePendingBlockWithTxs,
             MaybePendingTransactionReceipt, StateUpdate, SyncStatusType, Transaction,
         },
-        HttpTransport, JsonRpcClient,
+        JsonRpcClient,
+    },
+};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
     },
+    time::Duration,
 };
-use url::Url;
 
+pub mod batch;
+pub mod block_hash;
+pub mod data_availability;
+pub mod errors;
+pub mod fee;
+pub mod feeder_gateway;
+pub mod health;
+pub mod inclusion_proof;
+pub mod poseidon;
+pub mod replay;
 pub mod storage_proof;
+pub mod trie;
+
+#[cfg(not(target_arch = "wasm32"))]
+tokio::task_local! {
+    /// Calls made to the StarkNet provider and feeder gateway by the current task, while they
+    /// are counted.
+    static UPSTREAM_CALLS: std::cell::Cell<u64>;
+}
+
+/// Run a future, counting the calls it makes to the StarkNet provider and feeder gateway.
+/// The calls made by the tasks it spawns are not counted.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn count_upstream_calls<F: std::future::Future>(future: F) -> (F::Output, u64) {
+    UPSTREAM_CALLS
+        .scope(std::cell::Cell::new(0), async move {
+            let output = future.await;
+            (output, UPSTREAM_CALLS.with(std::cell::Cell::get))
+        })
+        .await
+}
+
+fn record_upstream_call() {
+    #[cfg(not(target_arch = "wasm32"))]
+    let _ = UPSTREAM_CALLS.try_with(|calls| calls.set(calls.get() + 1));
+}
 
 #[automock]
-#[async_trait]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
 pub trait StarkNetLightClient: Send + Sync {
     async fn start(&self) -> Result<()>;
+    fn rotate_provider(&self, url: &str) -> Result<()>;
+    /// Return the health of the providers reached since the start.
+    fn provider_health(&self) -> Vec<ProviderHealth>;
     async fn call(&self, opts: FunctionCall, block_number: u64) -> Result<Vec<FieldElement>>;
     async fn estimate_fee(
         &self,
         tx: BroadcastedTransaction,
         block_id: &BlockId,
     ) -> Result<FeeEstimate>;
+    async fn estimate_fee_bulk(
+        &self,
+        txs: Vec<BroadcastedTransaction>,
+        simulation_flags: Vec<SimulationFlag>,
+        block_id: &BlockId,
+    ) -> Result<Vec<FeeEstimate>>;
+    async fn estimate_fee_any(
+        &self,
+        txs: Vec<AnyBroadcastedTransaction>,
+        simulation_flags: Vec<SimulationFlag>,
+        block_id: &BlockId,
+    ) -> Result<Vec<FeeEstimateWithUnit>>;
     async fn get_storage_at(
         &self,
         address: FieldElement,
@@ -39,6 +119,8 @@ pub trait StarkNetLightClient: Send + Sync {
         block_number: u64,
     ) -> Result<FieldElement>;
     async fn get_nonce(&self, _block_number: u64, address: FieldElement) -> Result<FieldElement>;
+    async fn get_nonce_at(&self, block_id: &BlockId, address: FieldElement)
+        -> Result<FieldElement>;
     async fn chain_id(&self) -> Result<FieldElement>;
     async fn block_number(&self) -> Result<u64>;
     async fn block_hash_and_number(&self) -> Result<BlockHashAndNumber>;
@@ -70,6 +152,10 @@ pub trait StarkNetLightClient: Send + Sync {
         &self,
         invoke_transaction: &BroadcastedInvokeTransaction,
     ) -> Result<InvokeTransactionResult>;
+    async fn add_invoke_transaction_v3(
+        &self,
+        invoke_transaction: &BroadcastedTransactionV3,
+    ) -> Result<InvokeTransactionResult>;
     async fn add_deploy_transaction(
         &self,
         deploy_transaction: &BroadcastedDeployTransaction,
@@ -105,30 +191,193 @@ pub trait StarkNetLightClient: Send + Sync {
         &self,
         declare_transaction: &BroadcastedDeclareTransaction,
     ) -> Result<DeclareTransactionResult>;
+
+    async fn trace_transaction(&self, transaction_hash: FieldElement) -> Result<Value>;
+    async fn trace_block_transactions(&self, block_id: &BlockId) -> Result<Value>;
+    async fn simulate_transactions(
+        &self,
+        block_id: &BlockId,
+        transactions: Vec<AnyBroadcastedTransaction>,
+        simulation_flags: Vec<SimulationFlag>,
+    ) -> Result<Value>;
+    /// Call a method of the provider serving it and return the result as it was written,
+    /// without parsing it.
+    async fn request_raw(&self, method: &str, params: Value) -> Result<Box<RawValue>>;
 }
 
 pub struct StarkNetLightClientImpl {
-    client: JsonRpcClient<HttpTransport>,
-    provider: Provider<Http>,
+    /// Endpoint serving the methods which are not routed elsewhere.
+    /// It can be replaced at runtime with [`StarkNetLightClient::rotate_provider`].
+    default: ArcSwap<Upstream>,
+    /// Endpoints serving specific methods, by method name.
+    routes: HashMap<String, Arc<Upstream>>,
+    /// Opens the transport of a new endpoint.
+    connector: Arc<dyn Connector>,
+    /// Secondary source of blocks, if a feeder gateway is configured.
+    feeder_gateway: Option<FeederGatewayClient>,
+    /// Number of the latest block returned by any source, a source returning an older one
+    /// lags behind.
+    latest_block_number: AtomicU64,
+    /// Health of the providers, recorded by their transports and by the sources of blocks.
+    health: Arc<HealthTracker>,
+    config: Config,
+}
+
+/// Clients of a single StarkNet RPC endpoint.
+struct Upstream {
+    /// Name of the provider in the health records.
+    name: String,
+    client: JsonRpcClient<RpcTransport>,
+    provider: Provider<RpcTransport>,
+    /// Transport of the raw requests, relayed without parsing their result.
+    transport: RpcTransport,
+    /// Client batching the receipt and storage requests, if batching is enabled.
+    batch: Option<BatchClient>,
+}
+
+impl Upstream {
+    fn new(
+        url: &str,
+        connector: &dyn Connector,
+        config: &Config,
+        health: &Arc<HealthTracker>,
+    ) -> Result<Self> {
+        // The clients of an endpoint share its transport, which records its health.
+        let name = provider_name(url);
+        let transport: Arc<dyn Transport> = Arc::new(HealthTransport::new(
+            name.clone(),
+            connector.connect(url)?,
+            health.clone(),
+        ));
+        let batch = match config.starknet_batch_max_size > 1 {
+            true => Some(BatchClient::new(
+                transport.clone(),
+                config.starknet_batch_max_size,
+                Duration::from_millis(config.starknet_batch_flush_interval_ms),
+            )),
+            false => None,
+        };
+        Ok(Self {
+            name,
+            client: JsonRpcClient::new(RpcTransport(transport.clone())),
+            provider: Provider::new(RpcTransport(transport.clone())),
+            transport: RpcTransport(transport),
+            batch,
+        })
+    }
 }
 
 impl StarkNetLightClientImpl {
+    /// Create a client reaching the providers over HTTP, or over WebSocket for `ws` and `wss`
+    /// URLs.
     pub fn new(config: &Config) -> Result<Self> {
-        let url = Url::parse(config.starknet_rpc.clone().as_str())?;
-        let provider = Provider::try_from(config.starknet_rpc.clone().as_str())?;
+        // All the clients share the same connection pool.
+        let http_client = config.http.build_client()?;
+        let connector = Arc::new(DefaultConnector::new(
+            http_client.clone(),
+            Duration::from_millis(config.http.timeout_ms),
+        ));
+        Self::with_connector_and_client(config, connector, http_client)
+    }
+
+    /// Create a client whose requests to the providers fail, answer garbage or lag as set by
+    /// `faults`, to test the light client under adverse conditions.
+    pub fn with_faults(config: &Config, faults: FaultConfig) -> Result<Self> {
+        let http_client = config.http.build_client()?;
+        let connector = Arc::new(FaultyConnector::new(
+            Arc::new(DefaultConnector::new(
+                http_client.clone(),
+                Duration::from_millis(config.http.timeout_ms),
+            )),
+            faults,
+        ));
+        Self::with_connector_and_client(config, connector, http_client)
+    }
+
+    /// Create a client reaching the providers through the transports opened by `connector`.
+    /// The feeder gateway, if any, is still reached over HTTP.
+    pub fn with_connector(config: &Config, connector: Arc<dyn Connector>) -> Result<Self> {
+        Self::with_connector_and_client(config, connector, config.http.build_client()?)
+    }
+
+    fn with_connector_and_client(
+        config: &Config,
+        connector: Arc<dyn Connector>,
+        http_client: reqwest::Client,
+    ) -> Result<Self> {
+        let health = Arc::new(HealthTracker::new());
+        let default = Arc::new(Upstream::new(
+            &config.starknet_rpc,
+            connector.as_ref(),
+            config,
+            &health,
+        )?);
+
+        // Methods routed to the same endpoint share its clients.
+        let mut upstreams: HashMap<&str, Arc<Upstream>> = HashMap::new();
+        upstreams.insert(&config.starknet_rpc, default.clone());
+        let mut routes = HashMap::new();
+        for (method, url) in config.starknet_rpc_routes.iter() {
+            let upstream = match upstreams.get(url.as_str()) {
+                Some(upstream) => upstream.clone(),
+                None => {
+                    let upstream =
+                        Arc::new(Upstream::new(url, connector.as_ref(), config, &health)?);
+                    upstreams.insert(url, upstream.clone());
+                    upstream
+                }
+            };
+            routes.insert(method.clone(), upstream);
+        }
+        let feeder_gateway = match &config.starknet_feeder_gateway {
+            Some(url) => Some(FeederGatewayClient::new(url, http_client)?),
+            None => None,
+        };
         Ok(Self {
-            client: JsonRpcClient::new(HttpTransport::new(url)),
-            provider,
+            default: ArcSwap::new(default),
+            routes,
+            connector,
+            feeder_gateway,
+            latest_block_number: AtomicU64::new(0),
+            health,
+            config: config.clone(),
         })
     }
+
+    /// Return the endpoint serving a method.
+    fn upstream(&self, method: &str) -> Arc<Upstream> {
+        record_upstream_call();
+        self.route(method)
+    }
+
+    /// Return the endpoint serving a method, without counting a call to it.
+    fn route(&self, method: &str) -> Arc<Upstream> {
+        match self.routes.get(method) {
+            Some(upstream) => upstream.clone(),
+            None => self.default.load_full(),
+        }
+    }
 }
 
-#[async_trait]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
 impl StarkNetLightClient for StarkNetLightClientImpl {
     async fn start(&self) -> Result<()> {
         Ok(())
     }
 
+    /// Replace the endpoint serving the methods which are not routed elsewhere.
+    /// Requests already sent complete on the previous endpoint.
+    fn rotate_provider(&self, url: &str) -> Result<()> {
+        let upstream = Upstream::new(url, self.connector.as_ref(), &self.config, &self.health)?;
+        self.default.store(Arc::new(upstream));
+        Ok(())
+    }
+
+    fn provider_health(&self) -> Vec<ProviderHealth> {
+        self.health.snapshot()
+    }
+
     /// Get the value at a specific key in a contract's storage.
     /// Returns the value at the key.
     ///
@@ -147,14 +396,24 @@ impl StarkNetLightClient for StarkNetLightClientImpl {
         key: FieldElement,
         block_number: u64,
     ) -> Result<FieldElement> {
-        self.client
+        let upstream = self.upstream("starknet_getStorageAt");
+        if let Some(batch) = &upstream.batch {
+            let params = json!([
+                format!("{address:#x}"),
+                format!("{key:#x}"),
+                { "block_number": block_number },
+            ]);
+            return batch.request("starknet_getStorageAt", params).await;
+        }
+        upstream
+            .client
             .get_storage_at(
                 address,
                 key,
                 &starknet::providers::jsonrpc::models::BlockId::Number(block_number),
             )
             .await
-            .map_err(|e| eyre::eyre!(e))
+            .map_err(provider_error)
     }
 
     /// Call a contract on StarkNet.
@@ -172,13 +431,14 @@ impl StarkNetLightClient for StarkNetLightClientImpl {
     /// `Ok(Vec<FieldElement>)` if the operation was successful.
     /// `Err(eyre::Report)` if the operation failed.
     async fn call(&self, request: FunctionCall, block_number: u64) -> Result<Vec<FieldElement>> {
-        self.client
+        self.upstream("starknet_call")
+            .client
             .call(
                 request,
                 &starknet::providers::jsonrpc::models::BlockId::Number(block_number),
             )
             .await
-            .map_err(|e| eyre::eyre!(e))
+            .map_err(provider_error)
     }
 
     /// Estimate the fee for a given StarkNet transaction
@@ -198,9 +458,91 @@ impl StarkNetLightClient for StarkNetLightClientImpl {
         tx: BroadcastedTransaction,
         block_id: &BlockId,
     ) -> Result<FeeEstimate> {
-        self.client
+        self.upstream("starknet_estimateFee")
+            .client
             .estimate_fee(tx, block_id)
             .await
+            .map_err(provider_error)
+    }
+
+    /// Estimate the fees of several StarkNet transactions, executed one after the other.
+    /// Returns one fee estimate per transaction.
+    ///
+    /// # Arguments
+    ///
+    /// * `txs` - The broadcasted transactions.
+    /// * `simulation_flags` - Flags such as `SKIP_VALIDATE`, omitted from the request when empty.
+    /// * `block_id` - The block identifier.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(Vec<FeeEstimate>)` if the operation was successful.
+    /// `Err(eyre::Report)` if the operation failed.
+    async fn estimate_fee_bulk(
+        &self,
+        txs: Vec<BroadcastedTransaction>,
+        simulation_flags: Vec<SimulationFlag>,
+        block_id: &BlockId,
+    ) -> Result<Vec<FeeEstimate>> {
+        #[derive(Debug, Serialize)]
+        struct Params<'a> {
+            request: Vec<BroadcastedTransaction>,
+            // Providers implementing spec versions prior to v0.6 don't know this parameter.
+            #[serde(skip_serializing_if = "Vec::is_empty")]
+            simulation_flags: Vec<SimulationFlag>,
+            block_id: &'a BlockId,
+        }
+
+        let params = Params {
+            request: txs,
+            simulation_flags,
+            block_id,
+        };
+
+        self.upstream("starknet_estimateFee")
+            .provider
+            .request::<Params, Vec<FeeEstimate>>("starknet_estimateFee", params)
+            .await
+            .map_err(|e| eyre::eyre!(e))
+    }
+
+    /// Estimate the fees of StarkNet transactions of any version, executed one after the other.
+    /// The estimates of v3 transactions are in fri, those of older ones in wei.
+    ///
+    /// # Arguments
+    ///
+    /// * `txs` - The broadcasted transactions.
+    /// * `simulation_flags` - Flags such as `SKIP_VALIDATE`, omitted from the request when empty.
+    /// * `block_id` - The block identifier.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(Vec<FeeEstimateWithUnit>)` if the operation was successful.
+    /// `Err(eyre::Report)` if the operation failed.
+    async fn estimate_fee_any(
+        &self,
+        txs: Vec<AnyBroadcastedTransaction>,
+        simulation_flags: Vec<SimulationFlag>,
+        block_id: &BlockId,
+    ) -> Result<Vec<FeeEstimateWithUnit>> {
+        #[derive(Debug, Serialize)]
+        struct Params<'a> {
+            request: Vec<AnyBroadcastedTransaction>,
+            #[serde(skip_serializing_if = "Vec::is_empty")]
+            simulation_flags: Vec<SimulationFlag>,
+            block_id: &'a BlockId,
+        }
+
+        let params = Params {
+            request: txs,
+            simulation_flags,
+            block_id,
+        };
+
+        self.upstream("starknet_estimateFee")
+            .provider
+            .request::<Params, Vec<FeeEstimateWithUnit>>("starknet_estimateFee", params)
+            .await
             .map_err(|e| eyre::eyre!(e))
     }
 
@@ -217,28 +559,61 @@ impl StarkNetLightClient for StarkNetLightClientImpl {
     /// `Ok(FieldElement)` if the operation was successful.
     /// `Err(eyre::Report)` if the operation failed.
     async fn get_nonce(&self, _block_number: u64, address: FieldElement) -> Result<FieldElement> {
-        self.client
+        self.upstream("starknet_getNonce")
+            .client
             .get_nonce(
                 &starknet::providers::jsonrpc::models::BlockId::Number(_block_number),
                 address,
             )
             .await
-            .map_err(|e| eyre::eyre!(e))
+            .map_err(provider_error)
+    }
+
+    /// Get the nonce of a contract at any block, including the pending one.
+    ///
+    /// # Arguments
+    ///
+    /// * `block_id` - The block identifier.
+    /// * `address` - Address of the contract.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(FieldElement)` if the operation was successful.
+    /// `Err(eyre::Report)` if the operation failed.
+    async fn get_nonce_at(
+        &self,
+        block_id: &BlockId,
+        address: FieldElement,
+    ) -> Result<FieldElement> {
+        self.upstream("starknet_getNonce")
+            .client
+            .get_nonce(block_id, address)
+            .await
+            .map_err(provider_error)
     }
 
     async fn chain_id(&self) -> Result<FieldElement> {
-        self.client.chain_id().await.map_err(|e| eyre::eyre!(e))
+        self.upstream("starknet_chainId")
+            .client
+            .chain_id()
+            .await
+            .map_err(provider_error)
     }
 
     async fn block_number(&self) -> Result<u64> {
-        self.client.block_number().await.map_err(|e| eyre::eyre!(e))
+        self.upstream("starknet_blockNumber")
+            .client
+            .block_number()
+            .await
+            .map_err(provider_error)
     }
 
     async fn block_hash_and_number(&self) -> Result<BlockHashAndNumber> {
-        self.client
+        self.upstream("starknet_blockHashAndNumber")
+            .client
             .block_hash_and_number()
             .await
-            .map_err(|e| eyre::eyre!(e))
+            .map_err(provider_error)
     }
 
     /// Get the contract class definition in the given block associated with the given hash.
@@ -258,10 +633,11 @@ impl StarkNetLightClient for StarkNetLightClientImpl {
         block_id: &BlockId,
         class_hash: FieldElement,
     ) -> Result<ContractClass> {
-        self.client
+        self.upstream("starknet_getClass")
+            .client
             .get_class(block_id, class_hash)
             .await
-            .map_err(|e| eyre::eyre!(e))
+            .map_err(provider_error)
     }
 
     /// Get the contract class hash given a block Id and contract_address;
@@ -281,10 +657,11 @@ impl StarkNetLightClient for StarkNetLightClientImpl {
         block_id: &BlockId,
         contract_address: FieldElement,
     ) -> Result<FieldElement> {
-        self.client
+        self.upstream("starknet_getClassHashAt")
+            .client
             .get_class_hash_at(block_id, contract_address)
             .await
-            .map_err(|e| eyre::eyre!(e))
+            .map_err(provider_error)
     }
 
     /// Get the contract class definition in the given block associated with the contract address.
@@ -304,10 +681,11 @@ impl StarkNetLightClient for StarkNetLightClientImpl {
         block_id: &BlockId,
         contract_address: FieldElement,
     ) -> Result<ContractClass> {
-        self.client
+        self.upstream("starknet_getClassAt")
+            .client
             .get_class_at(block_id, contract_address)
             .await
-            .map_err(|e| eyre::eyre!(e))
+            .map_err(provider_error)
     }
 
     /// Get the number of transactions in a block given a block id.
@@ -322,10 +700,11 @@ impl StarkNetLightClient for StarkNetLightClientImpl {
     /// `Ok(ContractClass)` if the operation was successful.
     /// `Err(eyre::Report)` if the operation failed.
     async fn get_block_transaction_count(&self, block_id: &BlockId) -> Result<u64> {
-        self.client
+        self.upstream("starknet_getBlockTransactionCount")
+            .client
             .get_block_transaction_count(block_id)
             .await
-            .map_err(|e| eyre::eyre!(e))
+            .map_err(provider_error)
     }
 
     /// Get the events.
@@ -345,10 +724,11 @@ impl StarkNetLightClient for StarkNetLightClientImpl {
         continuation_token: Option<String>,
         chunk_size: u64,
     ) -> Result<EventsPage> {
-        self.client
+        self.upstream("starknet_getEvents")
+            .client
             .get_events(filter, continuation_token, chunk_size)
             .await
-            .map_err(|e| eyre::eyre!(e))
+            .map_err(provider_error)
     }
 
     /// Get an object about the sync status, or false if the node is not synching.
@@ -361,7 +741,11 @@ impl StarkNetLightClient for StarkNetLightClientImpl {
     /// `Ok(SyncStatusType)` if the operation was successful.
     /// `Err(eyre::Report)` if the operation failed.
     async fn syncing(&self) -> Result<SyncStatusType> {
-        self.client.syncing().await.map_err(|e| eyre::eyre!(e))
+        self.upstream("starknet_syncing")
+            .client
+            .syncing()
+            .await
+            .map_err(provider_error)
     }
 
     /// Get information about the result of executing the requested block.
@@ -374,10 +758,11 @@ impl StarkNetLightClient for StarkNetLightClientImpl {
     /// `Ok(StateUpdate)` if the operation was successful.
     /// `Err(eyre::Report)` if the operation failed.
     async fn get_state_update(&self, block_id: &BlockId) -> Result<StateUpdate> {
-        self.client
+        self.upstream("starknet_getStateUpdate")
+            .client
             .get_state_update(block_id)
             .await
-            .map_err(|e| eyre::eyre!(e))
+            .map_err(provider_error)
     }
 
     /// Add an invoke transaction
@@ -397,9 +782,39 @@ impl StarkNetLightClient for StarkNetLightClientImpl {
         &self,
         invoke_transaction: &BroadcastedInvokeTransaction,
     ) -> Result<InvokeTransactionResult> {
-        self.client
+        self.upstream("starknet_addInvokeTransaction")
+            .client
             .add_invoke_transaction(invoke_transaction)
             .await
+            .map_err(provider_error)
+    }
+
+    /// Add an invoke transaction of version 3, paying its fee in STRK.
+    ///
+    /// # Arguments
+    ///
+    /// invoke_transaction : Transaction data
+    ///
+    /// # Returns
+    ///
+    /// `Ok(InvokeTransactionResult)` if the operation was successful.
+    /// `Err(eyre::Report)` if the operation failed.
+    async fn add_invoke_transaction_v3(
+        &self,
+        invoke_transaction: &BroadcastedTransactionV3,
+    ) -> Result<InvokeTransactionResult> {
+        #[derive(Debug, Serialize)]
+        struct Params<'a> {
+            invoke_transaction: &'a BroadcastedTransactionV3,
+        }
+
+        self.upstream("starknet_addInvokeTransaction")
+            .provider
+            .request::<Params, InvokeTransactionResult>(
+                "starknet_addInvokeTransaction",
+                Params { invoke_transaction },
+            )
+            .await
             .map_err(|e| eyre::eyre!(e))
     }
 
@@ -420,13 +835,17 @@ impl StarkNetLightClient for StarkNetLightClientImpl {
         &self,
         deploy_transaction: &BroadcastedDeployTransaction,
     ) -> Result<DeployTransactionResult> {
-        self.client
+        self.upstream("starknet_addDeployTransaction")
+            .client
             .add_deploy_transaction(deploy_transaction)
             .await
-            .map_err(|e| eyre::eyre!(e))
+            .map_err(provider_error)
     }
 
     /// Get the transactions of a given block.
+    /// The sources of blocks are tried in the configured order: the next one is asked when a
+    /// source fails, or when it returns a latest block older than one already seen. A source
+    /// which returned such an older block several times in a row is tried after the others.
     ///
     /// # Arguments
     ///
@@ -435,12 +854,70 @@ impl StarkNetLightClient for StarkNetLightClientImpl {
     /// # Returns
     ///
     /// `Ok(MaybePendingBlockWithTxs)` if the operation was successful.
-    /// `Err(eyre::Report)` if the operation failed.
+    /// `Err(eyre::Report)` with the error of the first source if every source failed.
     async fn get_block_with_txs(&self, block_id: &BlockId) -> Result<MaybePendingBlockWithTxs> {
-        self.client
-            .get_block_with_txs(block_id)
-            .await
-            .map_err(|e| eyre::eyre!(e))
+        let latest = matches!(block_id, BlockId::Tag(BlockTag::Latest));
+        let mut first_error = None;
+        let mut lagging_block = None;
+        let rpc = self.route("starknet_getBlockWithTxs");
+        let provider = |source: &BlockSource| match source {
+            BlockSource::Rpc => rpc.name.as_str(),
+            BlockSource::FeederGateway => FEEDER_GATEWAY_PROVIDER,
+        };
+        let mut sources = self.config.block_sources.0.clone();
+        sources.sort_by_key(|source| self.health.is_down_ranked(provider(source)));
+        for source in sources.iter() {
+            let block = match source {
+                BlockSource::Rpc => {
+                    record_upstream_call();
+                    rpc.client
+                        .get_block_with_txs(block_id)
+                        .await
+                        .map_err(provider_error)
+                }
+                BlockSource::FeederGateway => match &self.feeder_gateway {
+                    Some(feeder_gateway) => {
+                        record_upstream_call();
+                        let (block, latency_ms) =
+                            timed(feeder_gateway.get_block_with_txs(block_id)).await;
+                        self.health.record_request(
+                            FEEDER_GATEWAY_PROVIDER,
+                            block.is_ok(),
+                            latency_ms,
+                        );
+                        block
+                    }
+                    None => continue,
+                },
+            };
+            match block {
+                Ok(MaybePendingBlockWithTxs::Block(block)) if latest => {
+                    let seen = self
+                        .latest_block_number
+                        .fetch_max(block.block_number, Ordering::Relaxed);
+                    if block.block_number >= seen {
+                        self.health.record_agreement(provider(source));
+                        return Ok(MaybePendingBlockWithTxs::Block(block));
+                    }
+                    self.health.record_divergence(provider(source));
+                    let lagging_block_number = lagging_block
+                        .as_ref()
+                        .map(|lagging: &BlockWithTxs| lagging.block_number);
+                    if lagging_block_number < Some(block.block_number) {
+                        lagging_block = Some(block);
+                    }
+                }
+                Ok(block) => return Ok(block),
+                Err(err) => {
+                    first_error.get_or_insert(err);
+                }
+            }
+        }
+        // Every source lags, the most recent of their blocks is still the best answer.
+        if let Some(block) = lagging_block {
+            return Ok(MaybePendingBlockWithTxs::Block(block));
+        }
+        Err(first_error.unwrap_or_else(|| eyre!("No source of blocks is configured")))
     }
 
     /// Get the transaction that matches the
@@ -451,10 +928,11 @@ impl StarkNetLightClient for StarkNetLightClientImpl {
     /// `Ok(Transaction)` if the operation was successful.
     /// `Err(eyre::Report)` if the operation failed.
     async fn get_transaction_by_hash(&self, hash: FieldElement) -> Result<Transaction> {
-        self.client
+        self.upstream("starknet_getTransactionByHash")
+            .client
             .get_transaction_by_hash(hash)
             .await
-            .map_err(|e| eyre::eyre!(e))
+            .map_err(provider_error)
     }
 
     /// Get the transaction given a block id and index
@@ -473,10 +951,11 @@ impl StarkNetLightClient for StarkNetLightClientImpl {
         block_id: &BlockId,
         index: u64,
     ) -> Result<Transaction> {
-        self.client
+        self.upstream("starknet_getTransactionByBlockIdAndIndex")
+            .client
             .get_transaction_by_block_id_and_index(block_id, index)
             .await
-            .map_err(|e| eyre::eyre!(e))
+            .map_err(provider_error)
     }
 
     /// Get the pending transactions.
@@ -487,10 +966,11 @@ impl StarkNetLightClient for StarkNetLightClientImpl {
     /// Ok(Vec<Transaction>) if the operation was successful.
     /// Err(eyre::Report) if the operation failed.
     async fn pending_transactions(&self) -> Result<Vec<Transaction>> {
-        self.client
+        self.upstream("starknet_pendingTransactions")
+            .client
             .pending_transactions()
             .await
-            .map_err(|e| eyre::eyre!(e))
+            .map_err(provider_error)
     }
 
     /// Get a transaction's receipt, querying
@@ -507,10 +987,18 @@ impl StarkNetLightClient for StarkNetLightClientImpl {
         &self,
         hash: FieldElement,
     ) -> Result<MaybePendingTransactionReceipt> {
-        self.client
+        let upstream = self.upstream("starknet_getTransactionReceipt");
+        if let Some(batch) = &upstream.batch {
+            let params = json!([format!("{hash:#x}")]);
+            return batch
+                .request("starknet_getTransactionReceipt", params)
+                .await;
+        }
+        upstream
+            .client
             .get_transaction_receipt(hash)
             .await
-            .map_err(|e| eyre::eyre!(e))
+            .map_err(provider_error)
     }
 
     /// Get the block with tx hashes of a given block.
@@ -527,10 +1015,11 @@ impl StarkNetLightClient for StarkNetLightClientImpl {
         &self,
         block_id: &BlockId,
     ) -> Result<MaybePendingBlockWithTxHashes> {
-        self.client
+        self.upstream("starknet_getBlockWithTxHashes")
+            .client
             .get_block_with_tx_hashes(block_id)
             .await
-            .map_err(|e| eyre::eyre!(e))
+            .map_err(provider_error)
     }
 
     /// Get a contract storage storage proof
@@ -570,7 +1059,8 @@ impl StarkNetLightClient for StarkNetLightClientImpl {
             Param::Keys(keys_str),
         ];
 
-        self.provider
+        self.upstream("pathfinder_getProof")
+            .provider
             .request::<Vec<Param>, GetProofOutput>("pathfinder_getProof", Vec::from(params))
             .await
             .map_err(|e| eyre::eyre!(e))
@@ -593,9 +1083,108 @@ impl StarkNetLightClient for StarkNetLightClientImpl {
         &self,
         declare_transaction: &BroadcastedDeclareTransaction,
     ) -> Result<DeclareTransactionResult> {
-        self.client
+        self.upstream("starknet_addDeclareTransaction")
+            .client
             .add_declare_transaction(declare_transaction)
             .await
+            .map_err(provider_error)
+    }
+
+    /// Get the execution trace of a transaction.
+    /// The trace is returned as served by the provider, it is not part of the verified state.
+    ///
+    /// # Arguments
+    ///
+    /// * `transaction_hash` - Hash of the transaction.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(Value)` if the operation was successful.
+    /// `Err(eyre::Report)` if the operation failed.
+    async fn trace_transaction(&self, transaction_hash: FieldElement) -> Result<Value> {
+        self.upstream("starknet_traceTransaction")
+            .provider
+            .request::<[String; 1], Value>(
+                "starknet_traceTransaction",
+                [format!("0x{transaction_hash:x}")],
+            )
+            .await
             .map_err(|e| eyre::eyre!(e))
     }
+
+    /// Get the execution traces of all the transactions of a block.
+    ///
+    /// # Arguments
+    ///
+    /// * `block_id` - The block identifier.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(Value)` if the operation was successful.
+    /// `Err(eyre::Report)` if the operation failed.
+    async fn trace_block_transactions(&self, block_id: &BlockId) -> Result<Value> {
+        self.upstream("starknet_traceBlockTransactions")
+            .provider
+            .request::<[&BlockId; 1], Value>("starknet_traceBlockTransactions", [block_id])
+            .await
+            .map_err(|e| eyre::eyre!(e))
+    }
+
+    /// Simulate a list of transactions on top of a block.
+    ///
+    /// # Arguments
+    ///
+    /// * `block_id` - The block to simulate the transactions on.
+    /// * `transactions` - The transactions to simulate.
+    /// * `simulation_flags` - Flags such as `SKIP_VALIDATE` or `SKIP_FEE_CHARGE`.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(Value)` if the operation was successful.
+    /// `Err(eyre::Report)` if the operation failed.
+    async fn simulate_transactions(
+        &self,
+        block_id: &BlockId,
+        transactions: Vec<AnyBroadcastedTransaction>,
+        simulation_flags: Vec<SimulationFlag>,
+    ) -> Result<Value> {
+        #[derive(Debug, Serialize)]
+        #[serde(untagged)]
+        enum Param<'a> {
+            Block(&'a BlockId),
+            Transactions(Vec<AnyBroadcastedTransaction>),
+            Flags(Vec<SimulationFlag>),
+        }
+
+        let params = vec![
+            Param::Block(block_id),
+            Param::Transactions(transactions),
+            Param::Flags(simulation_flags),
+        ];
+
+        self.upstream("starknet_simulateTransactions")
+            .provider
+            .request::<Vec<Param>, Value>("starknet_simulateTransactions", params)
+            .await
+            .map_err(|e| eyre::eyre!(e))
+    }
+
+    /// Relay a call to the provider the method is routed to, the result is checked to be JSON
+    /// but not parsed.
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - The JSON-RPC method.
+    /// * `params` - The parameters of the call.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(Box<RawValue>)` the result as the provider wrote it.
+    /// `Err(eyre::Report)` if the call failed, with the StarkNet errors by their code.
+    async fn request_raw(&self, method: &str, params: Value) -> Result<Box<RawValue>> {
+        self.upstream(method)
+            .transport
+            .call_raw(method, params)
+            .await
+    }
 }
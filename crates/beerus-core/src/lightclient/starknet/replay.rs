@@ -0,0 +1,501 @@
+//! Deterministic replay of a scripted StarkNet chain, to drive the sync tasks without any
+//! network.
+//!
+//! A [`ReplayScript`] describes the chain as the provider serves it on each poll of the syncer:
+//! new blocks, reorgs replacing the tip with another fork, blocks missing from the provider for
+//! a while, and outages. Every block is minted with a valid hash, so that the verifier accepts
+//! it. The syncer polls the latest block once per poll, which moves the replay to the next step;
+//! the blocks queried by number are answered from the current step.
+use crate::lightclient::starknet::{
+    block_hash::compute_block_hash,
+    fee::{
+        AnyBroadcastedTransaction, BroadcastedTransactionV3, FeeEstimateWithUnit, SimulationFlag,
+    },
+    health::ProviderHealth,
+    storage_proof::GetProofOutput,
+    StarkNetLightClient,
+};
+use async_trait::async_trait;
+use eyre::{eyre, Result};
+use serde_json::{value::RawValue, Value};
+use starknet::{
+    core::types::FieldElement,
+    providers::jsonrpc::models::{
+        BlockHashAndNumber, BlockId, BlockStatus, BlockTag, BlockWithTxs,
+        BroadcastedDeclareTransaction, BroadcastedDeployTransaction, BroadcastedInvokeTransaction,
+        BroadcastedTransaction, ContractClass, DeclareTransactionResult, DeployTransactionResult,
+        EventFilter, EventsPage, FeeEstimate, FunctionCall, InvokeTransactionResult,
+        MaybePendingBlockWithTxHashes, MaybePendingBlockWithTxs, MaybePendingTransactionReceipt,
+        StateUpdate, SyncStatusType, Transaction,
+    },
+};
+use std::{collections::BTreeSet, ops::RangeInclusive, sync::Arc};
+use tokio::sync::watch;
+
+/// Chain served by the provider during one poll of the syncer.
+#[derive(Debug, Clone, Default)]
+pub struct ReplayStep {
+    /// Blocks of the chain, from the first one, in order.
+    pub chain: Vec<BlockWithTxs>,
+    /// Blocks the provider fails to serve by number during the step.
+    pub missing: BTreeSet<u64>,
+    /// Whether the provider answers at all.
+    pub available: bool,
+}
+
+impl ReplayStep {
+    fn block(&self, block_number: u64) -> Option<&BlockWithTxs> {
+        let first_block = self.chain.first()?.block_number;
+        self.chain
+            .get(usize::try_from(block_number.checked_sub(first_block)?).ok()?)
+    }
+}
+
+/// Builder of the steps of a replay, one per poll of the syncer.
+#[derive(Debug, Clone)]
+pub struct ReplayScript {
+    first_block: u64,
+    /// Number of forks minted so far, which tells the blocks of different forks apart.
+    forks: u64,
+    steps: Vec<ReplayStep>,
+}
+
+impl ReplayScript {
+    /// Start a script whose chain begins at `first_block`, served once the first step is added.
+    pub fn new(first_block: u64) -> Self {
+        Self {
+            first_block,
+            forks: 0,
+            steps: Vec::new(),
+        }
+    }
+
+    /// Add a step where the chain grows by `blocks` blocks.
+    pub fn extend(mut self, blocks: u64) -> Self {
+        let mut chain = self.last_chain();
+        self.mint(&mut chain, blocks);
+        self.push(chain);
+        self
+    }
+
+    /// Add a step where the blocks from `fork_point` onwards are replaced by `blocks` blocks of
+    /// another fork.
+    pub fn reorg(mut self, fork_point: u64, blocks: u64) -> Self {
+        let mut chain = self.last_chain();
+        chain.retain(|block| block.block_number < fork_point);
+        self.forks += 1;
+        self.mint(&mut chain, blocks);
+        self.push(chain);
+        self
+    }
+
+    /// Make some blocks of the last step unavailable when fetched by number, e.g. while the
+    /// provider lags behind its own latest block.
+    pub fn with_gap(mut self, block_numbers: RangeInclusive<u64>) -> Self {
+        if let Some(step) = self.steps.last_mut() {
+            step.missing.extend(block_numbers);
+        }
+        self
+    }
+
+    /// Add a step where the provider is unavailable.
+    pub fn outage(mut self) -> Self {
+        self.steps.push(ReplayStep {
+            chain: self.last_chain(),
+            missing: BTreeSet::new(),
+            available: false,
+        });
+        self
+    }
+
+    /// Return the steps of the script.
+    pub fn steps(&self) -> &[ReplayStep] {
+        &self.steps
+    }
+
+    fn last_chain(&self) -> Vec<BlockWithTxs> {
+        self.steps
+            .last()
+            .map(|step| step.chain.clone())
+            .unwrap_or_default()
+    }
+
+    fn push(&mut self, chain: Vec<BlockWithTxs>) {
+        self.steps.push(ReplayStep {
+            chain,
+            missing: BTreeSet::new(),
+            available: true,
+        });
+    }
+
+    /// Append blocks of the current fork to a chain.
+    fn mint(&self, chain: &mut Vec<BlockWithTxs>, blocks: u64) {
+        for _ in 0..blocks {
+            let (block_number, parent_hash) = match chain.last() {
+                Some(parent) => (parent.block_number + 1, parent.block_hash),
+                None => (self.first_block, FieldElement::ZERO),
+            };
+            chain.push(replay_block(block_number, parent_hash, self.forks));
+        }
+    }
+}
+
+/// Mint a block without transactions, with a valid hash. The blocks of different forks have
+/// different state roots, hence different hashes.
+pub fn replay_block(block_number: u64, parent_hash: FieldElement, fork: u64) -> BlockWithTxs {
+    let mut block = BlockWithTxs {
+        status: BlockStatus::AcceptedOnL2,
+        block_hash: FieldElement::ZERO,
+        parent_hash,
+        block_number,
+        new_root: FieldElement::from((fork << 32) | block_number),
+        timestamp: block_number,
+        sequencer_address: FieldElement::ONE,
+        transactions: vec![],
+    };
+    block.block_hash = compute_block_hash(&block, &[]);
+    block
+}
+
+/// StarkNet light client replaying a [`ReplayScript`]. Clones share the replay, so that a test
+/// keeps a handle on the client handed to the sync tasks.
+/// Only the blocks are replayed, the other methods fail.
+#[derive(Debug, Clone)]
+pub struct ReplayStarkNetLightClient {
+    steps: Arc<Vec<ReplayStep>>,
+    /// Number of polls of the latest block so far.
+    polls: Arc<watch::Sender<usize>>,
+}
+
+impl ReplayStarkNetLightClient {
+    pub fn new(script: ReplayScript) -> Self {
+        Self {
+            steps: Arc::new(script.steps),
+            polls: Arc::new(watch::channel(0).0),
+        }
+    }
+
+    /// Return a step of the replay, the last one is served once the script is exhausted.
+    pub fn step(&self, step: usize) -> Option<&ReplayStep> {
+        self.steps.get(step)
+    }
+
+    /// Return the number of polls of the latest block so far.
+    pub fn polls(&self) -> usize {
+        *self.polls.borrow()
+    }
+
+    /// Wait until the latest block was polled at least `polls` times.
+    pub async fn polled(&self, polls: usize) {
+        let mut receiver = self.polls.subscribe();
+        while *receiver.borrow() < polls {
+            if receiver.changed().await.is_err() {
+                return;
+            }
+        }
+    }
+
+    /// Step served since the last poll of the latest block.
+    fn current(&self) -> Result<&ReplayStep> {
+        let step = self
+            .steps
+            .get(
+                self.polls()
+                    .saturating_sub(1)
+                    .min(self.steps.len().saturating_sub(1)),
+            )
+            .ok_or_else(|| eyre!("The replay script is empty"))?;
+        match step.available {
+            true => Ok(step),
+            false => Err(eyre!("Replayed provider outage")),
+        }
+    }
+
+    fn block(&self, block_id: &BlockId) -> Result<&BlockWithTxs> {
+        let step = self.current()?;
+        let block = match block_id {
+            BlockId::Tag(BlockTag::Latest) => step.chain.last(),
+            BlockId::Tag(BlockTag::Pending) => None,
+            BlockId::Number(block_number) if step.missing.contains(block_number) => None,
+            BlockId::Number(block_number) => step.block(*block_number),
+            BlockId::Hash(block_hash) => step
+                .chain
+                .iter()
+                .find(|block| block.block_hash == *block_hash)
+                .filter(|block| !step.missing.contains(&block.block_number)),
+        };
+        block.ok_or_else(|| eyre!("Block not found"))
+    }
+}
+
+fn not_replayed<T>(method: &str) -> Result<T> {
+    Err(eyre!("{method} is not replayed"))
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl StarkNetLightClient for ReplayStarkNetLightClient {
+    async fn start(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn rotate_provider(&self, _url: &str) -> Result<()> {
+        not_replayed("rotate_provider")
+    }
+
+    fn provider_health(&self) -> Vec<ProviderHealth> {
+        vec![]
+    }
+
+    async fn call(&self, _opts: FunctionCall, _block_number: u64) -> Result<Vec<FieldElement>> {
+        not_replayed("call")
+    }
+
+    async fn estimate_fee(
+        &self,
+        _tx: BroadcastedTransaction,
+        _block_id: &BlockId,
+    ) -> Result<FeeEstimate> {
+        not_replayed("estimate_fee")
+    }
+
+    async fn estimate_fee_bulk(
+        &self,
+        _txs: Vec<BroadcastedTransaction>,
+        _simulation_flags: Vec<SimulationFlag>,
+        _block_id: &BlockId,
+    ) -> Result<Vec<FeeEstimate>> {
+        not_replayed("estimate_fee_bulk")
+    }
+
+    async fn estimate_fee_any(
+        &self,
+        _txs: Vec<AnyBroadcastedTransaction>,
+        _simulation_flags: Vec<SimulationFlag>,
+        _block_id: &BlockId,
+    ) -> Result<Vec<FeeEstimateWithUnit>> {
+        not_replayed("estimate_fee_any")
+    }
+
+    async fn get_storage_at(
+        &self,
+        _address: FieldElement,
+        _key: FieldElement,
+        _block_number: u64,
+    ) -> Result<FieldElement> {
+        not_replayed("get_storage_at")
+    }
+
+    async fn get_nonce(&self, _block_number: u64, _address: FieldElement) -> Result<FieldElement> {
+        not_replayed("get_nonce")
+    }
+
+    async fn get_nonce_at(
+        &self,
+        _block_id: &BlockId,
+        _address: FieldElement,
+    ) -> Result<FieldElement> {
+        not_replayed("get_nonce_at")
+    }
+
+    async fn chain_id(&self) -> Result<FieldElement> {
+        not_replayed("chain_id")
+    }
+
+    async fn block_number(&self) -> Result<u64> {
+        Ok(self.block(&BlockId::Tag(BlockTag::Latest))?.block_number)
+    }
+
+    async fn block_hash_and_number(&self) -> Result<BlockHashAndNumber> {
+        let block = self.block(&BlockId::Tag(BlockTag::Latest))?;
+        Ok(BlockHashAndNumber {
+            block_hash: block.block_hash,
+            block_number: block.block_number,
+        })
+    }
+
+    async fn get_class(
+        &self,
+        _block_id: &BlockId,
+        _class_hash: FieldElement,
+    ) -> Result<ContractClass> {
+        not_replayed("get_class")
+    }
+
+    async fn get_class_hash_at(
+        &self,
+        _block_id: &BlockId,
+        _contract_address: FieldElement,
+    ) -> Result<FieldElement> {
+        not_replayed("get_class_hash_at")
+    }
+
+    async fn get_class_at(
+        &self,
+        _block_id: &BlockId,
+        _contract_address: FieldElement,
+    ) -> Result<ContractClass> {
+        not_replayed("get_class_at")
+    }
+
+    async fn get_block_transaction_count(&self, block_id: &BlockId) -> Result<u64> {
+        Ok(self.block(block_id)?.transactions.len() as u64)
+    }
+
+    async fn get_state_update(&self, _block_id: &BlockId) -> Result<StateUpdate> {
+        not_replayed("get_state_update")
+    }
+
+    async fn get_events(
+        &self,
+        _filter: EventFilter,
+        _continuation_token: Option<String>,
+        _chunk_size: u64,
+    ) -> Result<EventsPage> {
+        not_replayed("get_events")
+    }
+
+    async fn syncing(&self) -> Result<SyncStatusType> {
+        not_replayed("syncing")
+    }
+
+    async fn add_invoke_transaction(
+        &self,
+        _invoke_transaction: &BroadcastedInvokeTransaction,
+    ) -> Result<InvokeTransactionResult> {
+        not_replayed("add_invoke_transaction")
+    }
+
+    async fn add_invoke_transaction_v3(
+        &self,
+        _invoke_transaction: &BroadcastedTransactionV3,
+    ) -> Result<InvokeTransactionResult> {
+        not_replayed("add_invoke_transaction_v3")
+    }
+
+    async fn add_deploy_transaction(
+        &self,
+        _deploy_transaction: &BroadcastedDeployTransaction,
+    ) -> Result<DeployTransactionResult> {
+        not_replayed("add_deploy_transaction")
+    }
+
+    async fn get_transaction_by_hash(&self, _hash: FieldElement) -> Result<Transaction> {
+        not_replayed("get_transaction_by_hash")
+    }
+
+    /// Every poll of the latest block moves the replay to the next step.
+    async fn get_block_with_txs(&self, block_id: &BlockId) -> Result<MaybePendingBlockWithTxs> {
+        if let BlockId::Tag(BlockTag::Latest) = block_id {
+            self.polls.send_modify(|polls| *polls += 1);
+        }
+        Ok(MaybePendingBlockWithTxs::Block(
+            self.block(block_id)?.clone(),
+        ))
+    }
+
+    async fn get_block_with_tx_hashes(
+        &self,
+        _block_id: &BlockId,
+    ) -> Result<MaybePendingBlockWithTxHashes> {
+        not_replayed("get_block_with_tx_hashes")
+    }
+
+    async fn get_transaction_receipt(
+        &self,
+        _hash: FieldElement,
+    ) -> Result<MaybePendingTransactionReceipt> {
+        not_replayed("get_transaction_receipt")
+    }
+
+    async fn get_transaction_by_block_id_and_index(
+        &self,
+        _block_id: &BlockId,
+        _index: u64,
+    ) -> Result<Transaction> {
+        not_replayed("get_transaction_by_block_id_and_index")
+    }
+
+    async fn pending_transactions(&self) -> Result<Vec<Transaction>> {
+        not_replayed("pending_transactions")
+    }
+
+    async fn get_contract_storage_proof(
+        &self,
+        _contract_address: FieldElement,
+        _keys: Vec<FieldElement>,
+        _block: &BlockId,
+    ) -> Result<GetProofOutput> {
+        not_replayed("get_contract_storage_proof")
+    }
+
+    async fn add_declare_transaction(
+        &self,
+        _declare_transaction: &BroadcastedDeclareTransaction,
+    ) -> Result<DeclareTransactionResult> {
+        not_replayed("add_declare_transaction")
+    }
+
+    async fn trace_transaction(&self, _transaction_hash: FieldElement) -> Result<Value> {
+        not_replayed("trace_transaction")
+    }
+
+    async fn trace_block_transactions(&self, _block_id: &BlockId) -> Result<Value> {
+        not_replayed("trace_block_transactions")
+    }
+
+    async fn simulate_transactions(
+        &self,
+        _block_id: &BlockId,
+        _transactions: Vec<AnyBroadcastedTransaction>,
+        _simulation_flags: Vec<SimulationFlag>,
+    ) -> Result<Value> {
+        not_replayed("simulate_transactions")
+    }
+
+    async fn request_raw(&self, method: &str, _params: Value) -> Result<Box<RawValue>> {
+        not_replayed(method)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reorg_replaces_the_tip_with_another_fork() {
+        let script = ReplayScript::new(1).extend(3).reorg(3, 2);
+        let (before, after) = (&script.steps()[0].chain, &script.steps()[1].chain);
+
+        assert_eq!(before.len(), 3);
+        assert_eq!(after.len(), 4);
+        assert_eq!(after[..2], before[..2]);
+        assert_eq!(after[2].block_number, 3);
+        assert_ne!(after[2].block_hash, before[2].block_hash);
+        assert_eq!(after[2].parent_hash, before[1].block_hash);
+        assert_eq!(after[3].parent_hash, after[2].block_hash);
+    }
+
+    #[tokio::test]
+    async fn test_polls_of_the_latest_block_move_to_the_next_step() {
+        let replay = ReplayStarkNetLightClient::new(
+            ReplayScript::new(10).extend(2).with_gap(10..=10).outage(),
+        );
+        let latest = BlockId::Tag(BlockTag::Latest);
+
+        assert!(replay.get_block_with_txs(&latest).await.is_ok());
+        assert_eq!(replay.block_number().await.unwrap(), 11);
+        assert!(replay
+            .get_block_with_txs(&BlockId::Number(10))
+            .await
+            .is_err());
+        assert!(replay
+            .get_block_with_txs(&BlockId::Number(11))
+            .await
+            .is_ok());
+        assert!(replay.get_block_with_txs(&latest).await.is_err());
+        // The last step is served once the script is exhausted.
+        assert!(replay.get_block_with_txs(&latest).await.is_err());
+        assert_eq!(replay.polls(), 3);
+    }
+}
@@ -1,11 +1,12 @@
+use super::trie::{binary_hash, edge_hash, PedersenHash};
 use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use starknet::core::{crypto::pedersen_hash, types::FieldElement};
 
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
 pub struct Path {
     #[serde(deserialize_with = "from_hex_deser", serialize_with = "to_hex_ser")]
-    value: FieldElement,
-    len: u8,
+    pub value: FieldElement,
+    pub len: u8,
 }
 
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
@@ -26,18 +27,14 @@ pub struct Binary {
 
 impl Edge {
     fn hash(&self) -> FieldElement {
-        let child_hash = self.child;
-
         // Length should be smaller than the maximum size of a stark hash.
-        let length = FieldElement::from(self.path.len);
-
-        pedersen_hash(&child_hash, &self.path.value) + length
+        edge_hash::<PedersenHash>(&self.child, &self.path.value, self.path.len)
     }
 }
 
 impl Binary {
     fn hash(&self) -> FieldElement {
-        pedersen_hash(&self.left, &self.right)
+        binary_hash::<PedersenHash>(&self.left, &self.right)
     }
 }
 
@@ -182,7 +179,7 @@ fn path_matches(value: FieldElement, remaining_path: &[bool]) -> bool {
 }
 
 /// Utility function to convert a [`FieldElement`] to a big endian bit representation
-fn felt_to_bits_be(value: FieldElement) -> [bool; 256] {
+pub(crate) fn felt_to_bits_be(value: FieldElement) -> [bool; 256] {
     let mut bits = value.to_bits_le();
     bits.reverse();
     bits
@@ -213,13 +210,10 @@ impl<'a> ProofRequest<'a> {
     }
 
     /// Verifies the proof request. Returns `None` if there's a hash mismatch or
-    /// if the key is too small; else returns a `Membership` variant.
-    fn verify(&self) -> Option<Membership> {
-        // Protect from ill-formed keys
-        if self.key.len() < 251 {
-            return None;
-        }
-
+    /// if the proof is longer than the key; else returns a `Membership` variant.
+    /// The key is as long as the height of the trie: 251 bits for the state tries, 64 bits for
+    /// the commitment tries of a block.
+    pub(crate) fn verify(&self) -> Option<Membership> {
         let mut expected_hash = self.root;
         let mut remaining_path = self.key;
 
@@ -232,7 +226,8 @@ impl<'a> ProofRequest<'a> {
                 ProofNode::Binary(bin) => {
                     // Direction will always correspond to the 0th index
                     // because we're removing bits on every iteration.
-                    let direction = remaining_path[0];
+                    // Protect from ill-formed keys.
+                    let direction = *remaining_path.first()?;
 
                     // Set the next hash to be the left or right hash,
                     // depending on the direction
@@ -245,6 +240,9 @@ impl<'a> ProofRequest<'a> {
                     remaining_path = &remaining_path[1..];
                 }
                 ProofNode::Edge(edge) => {
+                    if edge.path.len as usize > remaining_path.len() {
+                        return None;
+                    }
                     let path_matches =
                         path_matches(edge.path.value, &remaining_path[..edge.path.len as usize]);
                     if !path_matches {
@@ -0,0 +1,329 @@
+use super::{
+    poseidon::{poseidon_hash, poseidon_hash_many},
+    storage_proof::{felt_to_bits_be, Binary, Edge, Path, ProofNode},
+};
+use starknet::core::{crypto::pedersen_hash, types::FieldElement};
+use std::{collections::BTreeMap, marker::PhantomData};
+
+/// Height of the StarkNet contract, storage and class tries.
+pub const STATE_TRIE_HEIGHT: usize = 251;
+
+/// Hash function used to compute the nodes of a trie.
+pub trait TrieHash {
+    fn hash(a: &FieldElement, b: &FieldElement) -> FieldElement;
+}
+
+/// Pedersen hash, used by the contract and storage tries.
+pub struct PedersenHash;
+
+impl TrieHash for PedersenHash {
+    fn hash(a: &FieldElement, b: &FieldElement) -> FieldElement {
+        pedersen_hash(a, b)
+    }
+}
+
+/// Poseidon hash, used by the class trie.
+pub struct PoseidonHash;
+
+impl TrieHash for PoseidonHash {
+    fn hash(a: &FieldElement, b: &FieldElement) -> FieldElement {
+        poseidon_hash(a, b)
+    }
+}
+
+/// Hash of a binary node.
+pub fn binary_hash<H: TrieHash>(left: &FieldElement, right: &FieldElement) -> FieldElement {
+    H::hash(left, right)
+}
+
+/// Hash of an edge node: `H(child, path) + length`.
+pub fn edge_hash<H: TrieHash>(child: &FieldElement, path: &FieldElement, len: u8) -> FieldElement {
+    H::hash(child, path) + FieldElement::from(len)
+}
+
+/// Hash of a contract state, i.e. the value of a leaf in the contract trie.
+pub fn contract_state_hash(
+    class_hash: FieldElement,
+    storage_root: FieldElement,
+    nonce: FieldElement,
+) -> FieldElement {
+    // The last element is the contract state hash version, which is currently always 0.
+    let a = pedersen_hash(&class_hash, &storage_root);
+    let b = pedersen_hash(&a, &nonce);
+    pedersen_hash(&b, &FieldElement::ZERO)
+}
+
+/// Hash of a declared class, i.e. the value of a leaf in the class trie.
+pub fn class_leaf_hash(compiled_class_hash: FieldElement) -> FieldElement {
+    let version = FieldElement::from_byte_slice_be(b"CONTRACT_CLASS_LEAF_V0").unwrap();
+    poseidon_hash(&version, &compiled_class_hash)
+}
+
+/// Global state root committed on L1, given the roots of the contract and class tries.
+/// Before StarkNet v0.11 there was no class trie, and the state root is the contract trie root.
+pub fn global_state_root(contracts_root: FieldElement, classes_root: FieldElement) -> FieldElement {
+    if classes_root == FieldElement::ZERO {
+        return contracts_root;
+    }
+    let version = FieldElement::from_byte_slice_be(b"STARKNET_STATE_V0").unwrap();
+    poseidon_hash_many(&[version, contracts_root, classes_root])
+}
+
+/// In-memory binary Patricia-Merkle trie, as used by StarkNet for its state commitments.
+/// Leaves with a value of zero are considered absent.
+/// The contract and storage tries use the Pedersen hash. The class trie uses the same
+/// structure with the Poseidon hash, see [`ClassTrie`].
+pub struct PatriciaTrie<H: TrieHash = PedersenHash> {
+    height: usize,
+    leaves: BTreeMap<FieldElement, FieldElement>,
+    hash: PhantomData<H>,
+}
+
+/// Class trie, mapping the class hashes to their [`class_leaf_hash`].
+pub type ClassTrie = PatriciaTrie<PoseidonHash>;
+
+/// Sub-trie as seen from its parent: the hash of its top-most binary node (or leaf)
+/// and the path of the edge leading to it, which can be empty.
+struct SubTrie {
+    child: FieldElement,
+    path: Vec<bool>,
+}
+
+impl<H: TrieHash> PatriciaTrie<H> {
+    /// Create an empty trie of the given height (at most 251).
+    pub fn new(height: usize) -> Self {
+        assert!(
+            height <= STATE_TRIE_HEIGHT,
+            "Trie height must be at most 251"
+        );
+        Self {
+            height,
+            leaves: BTreeMap::new(),
+            hash: PhantomData,
+        }
+    }
+
+    /// Insert or update a leaf. Setting a leaf to zero removes it.
+    pub fn insert(&mut self, key: FieldElement, value: FieldElement) {
+        if value == FieldElement::ZERO {
+            self.leaves.remove(&key);
+        } else {
+            self.leaves.insert(key, value);
+        }
+    }
+
+    /// Return the value of a leaf, or zero if it does not exist.
+    pub fn get(&self, key: FieldElement) -> FieldElement {
+        self.leaves.get(&key).copied().unwrap_or(FieldElement::ZERO)
+    }
+
+    /// Compute the root of the trie. The root of an empty trie is zero.
+    pub fn root(&self) -> FieldElement {
+        let leaves = self.sorted_leaves();
+        if leaves.is_empty() {
+            return FieldElement::ZERO;
+        }
+        self.node_hash(&self.sub_trie(&leaves, 0))
+    }
+
+    /// Build a membership or non-membership proof for the given key.
+    /// The proof can be checked with the storage proof verifier.
+    pub fn get_proof(&self, key: FieldElement) -> Vec<ProofNode> {
+        let target = self.key_bits(key);
+        let all_leaves = self.sorted_leaves();
+        let mut leaves = all_leaves.as_slice();
+        let mut depth = 0;
+        let mut proof = Vec::new();
+
+        while !leaves.is_empty() && depth < self.height {
+            let sub_trie = self.sub_trie(leaves, depth);
+            if !sub_trie.path.is_empty() {
+                let len = sub_trie.path.len();
+                proof.push(ProofNode::Edge(Edge {
+                    path: Path {
+                        value: bits_to_felt(&sub_trie.path),
+                        len: len as u8,
+                    },
+                    child: sub_trie.child,
+                }));
+                if target[depth..depth + len] != sub_trie.path[..] {
+                    // The key leaves the edge: non-membership.
+                    break;
+                }
+                depth += len;
+                continue;
+            }
+
+            // Binary node: both sides are non-empty.
+            let split = leaves.partition_point(|(bits, _)| !bits[depth]);
+            let left = self.node_hash(&self.sub_trie(&leaves[..split], depth + 1));
+            let right = self.node_hash(&self.sub_trie(&leaves[split..], depth + 1));
+            proof.push(ProofNode::Binary(Binary { left, right }));
+            leaves = match target[depth] {
+                false => &leaves[..split],
+                true => &leaves[split..],
+            };
+            depth += 1;
+        }
+        proof
+    }
+
+    /// Leaves sorted by key, with their keys as `height` big endian bits.
+    fn sorted_leaves(&self) -> Vec<(Vec<bool>, FieldElement)> {
+        let mut leaves: Vec<(Vec<bool>, FieldElement)> = self
+            .leaves
+            .iter()
+            .map(|(key, value)| (self.key_bits(*key), *value))
+            .collect();
+        leaves.sort_by(|a, b| a.0.cmp(&b.0));
+        leaves
+    }
+
+    fn key_bits(&self, key: FieldElement) -> Vec<bool> {
+        let bits = felt_to_bits_be(key);
+        bits[bits.len() - self.height..].to_vec()
+    }
+
+    /// Compute the sub-trie holding `leaves`, which all share their first `depth` bits.
+    fn sub_trie(&self, leaves: &[(Vec<bool>, FieldElement)], depth: usize) -> SubTrie {
+        if leaves.len() == 1 {
+            let (bits, value) = &leaves[0];
+            return SubTrie {
+                child: *value,
+                path: bits[depth..].to_vec(),
+            };
+        }
+
+        let split = leaves.partition_point(|(bits, _)| !bits[depth]);
+        if split == 0 || split == leaves.len() {
+            // All the leaves are on the same side: extend the edge.
+            let mut sub_trie = self.sub_trie(leaves, depth + 1);
+            sub_trie.path.insert(0, leaves[0].0[depth]);
+            return sub_trie;
+        }
+
+        let left = self.node_hash(&self.sub_trie(&leaves[..split], depth + 1));
+        let right = self.node_hash(&self.sub_trie(&leaves[split..], depth + 1));
+        SubTrie {
+            child: binary_hash::<H>(&left, &right),
+            path: vec![],
+        }
+    }
+
+    fn node_hash(&self, sub_trie: &SubTrie) -> FieldElement {
+        match sub_trie.path.is_empty() {
+            true => sub_trie.child,
+            false => edge_hash::<H>(
+                &sub_trie.child,
+                &bits_to_felt(&sub_trie.path),
+                sub_trie.path.len() as u8,
+            ),
+        }
+    }
+}
+
+/// Convert big endian bits to a [`FieldElement`].
+fn bits_to_felt(bits: &[bool]) -> FieldElement {
+    bits.iter().fold(FieldElement::ZERO, |acc, bit| {
+        acc * FieldElement::TWO + FieldElement::from(*bit as u8)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lightclient::starknet::storage_proof::{Membership, ProofRequest};
+
+    fn felt(value: &str) -> FieldElement {
+        FieldElement::from_hex_be(value).unwrap()
+    }
+
+    #[test]
+    fn test_empty_trie_root_is_zero() {
+        let trie: PatriciaTrie = PatriciaTrie::new(STATE_TRIE_HEIGHT);
+        assert_eq!(trie.root(), FieldElement::ZERO);
+    }
+
+    #[test]
+    fn test_single_leaf_root_matches_mainnet_sub_trie() {
+        // Given
+        // The last edge of a mainnet storage proof: a sub-trie of height 247 holding a single leaf.
+        let mut trie: PatriciaTrie = PatriciaTrie::new(247);
+        trie.insert(
+            felt("0x22ef8244ea5435f7cc3f33de473761a1c6b5084bfccb20812dc13543a642dc"),
+            felt("0x7e5"),
+        );
+
+        // Then
+        // The root matches the child hash found in the parent binary node.
+        assert_eq!(
+            trie.root(),
+            felt("0x2d76491b56715dd05d4b4cea61b96c8ebddb9a1904c379da369218d3ecce81b")
+        );
+    }
+
+    #[test]
+    fn test_class_trie_root_uses_poseidon_leaves() {
+        // Given
+        let mut trie = ClassTrie::new(STATE_TRIE_HEIGHT);
+        trie.insert(felt("0x1"), class_leaf_hash(felt("0x10")));
+        trie.insert(felt("0x2"), class_leaf_hash(felt("0x20")));
+        trie.insert(felt("0x7ff"), class_leaf_hash(felt("0x30")));
+
+        // Then
+        assert_eq!(
+            class_leaf_hash(felt("0x10")),
+            felt("0x5c64e4505b4d0dcec64bb4d2c599687a00b38adfa6fc8b0cc2cb5199674c48d")
+        );
+        assert_eq!(
+            trie.root(),
+            felt("0x2f3653947ccfcd877551dfe9a18749e62f2b8ae4dbe24fb33c36f605dfb9a26")
+        );
+    }
+
+    #[test]
+    fn test_global_state_root_commits_to_both_tries() {
+        assert_eq!(
+            global_state_root(felt("0x1"), felt("0x2")),
+            felt("0x794df522f4bc0d998d32559d28ce3a436bfa592902b1bda398023bad7c1f71e")
+        );
+        assert_eq!(
+            global_state_root(felt("0x1"), FieldElement::ZERO),
+            felt("0x1")
+        );
+    }
+
+    #[test]
+    fn test_zero_value_removes_leaf() {
+        let mut trie: PatriciaTrie = PatriciaTrie::new(STATE_TRIE_HEIGHT);
+        trie.insert(felt("0x1"), felt("0x2"));
+        trie.insert(felt("0x1"), FieldElement::ZERO);
+        assert_eq!(trie.root(), FieldElement::ZERO);
+    }
+
+    #[test]
+    fn test_proofs_are_accepted_by_the_verifier() {
+        // Given
+        let mut trie: PatriciaTrie = PatriciaTrie::new(STATE_TRIE_HEIGHT);
+        trie.insert(felt("0x1"), felt("0x10"));
+        trie.insert(felt("0x2"), felt("0x20"));
+        trie.insert(felt("0x3"), felt("0x30"));
+        trie.insert(felt("0x7ff"), felt("0x40"));
+        let root = trie.root();
+
+        // Then
+        // Existing keys are members.
+        for (key, value) in [("0x1", "0x10"), ("0x3", "0x30"), ("0x7ff", "0x40")] {
+            let proof = trie.get_proof(felt(key));
+            let bits = felt_to_bits_be(felt(key));
+            let request = ProofRequest::new(root, &bits[256 - 251..], felt(value), &proof);
+            assert_eq!(request.verify(), Some(Membership::Member));
+        }
+
+        // Missing keys are non-members.
+        let proof = trie.get_proof(felt("0x400"));
+        let bits = felt_to_bits_be(felt("0x400"));
+        let request = ProofRequest::new(root, &bits[256 - 251..], FieldElement::ZERO, &proof);
+        assert_eq!(request.verify(), Some(Membership::NonMember));
+    }
+}
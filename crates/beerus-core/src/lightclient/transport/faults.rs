@@ -0,0 +1,303 @@
+//! Fault injection for soak and chaos testing: a [`FaultyConnector`] wraps the transports of
+//! another connector and makes their requests fail, answer garbage or lag, at the configured
+//! rates. The faults are drawn from a seeded generator, so that a run can be replayed.
+//!
+//! Only the StarkNet providers are affected, the Ethereum light client does not go through the
+//! connector.
+use super::{Connector, Transport};
+use crate::runtime;
+use async_trait::async_trait;
+use eyre::{eyre, Result};
+use serde_json::{value::RawValue, Value};
+use std::{
+    fmt,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Rates and latencies of the injected faults, written as `key=value` pairs separated by
+/// commas, e.g. `error_rate=0.1,malformed_rate=0.05,latency_ms=200,jitter_ms=100,seed=7`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FaultConfig {
+    /// Share of the requests failing as if the provider could not be reached.
+    pub error_rate: f64,
+    /// Share of the requests answered with a response which is not JSON-RPC.
+    pub malformed_rate: f64,
+    /// Latency added to every request, in milliseconds.
+    pub latency_ms: u64,
+    /// Random latency added on top of `latency_ms`, up to this many milliseconds.
+    pub jitter_ms: u64,
+    /// Seed of the generator drawing the faults.
+    pub seed: u64,
+}
+
+impl Default for FaultConfig {
+    fn default() -> Self {
+        Self {
+            error_rate: 0.0,
+            malformed_rate: 0.0,
+            latency_ms: 0,
+            jitter_ms: 0,
+            seed: 1,
+        }
+    }
+}
+
+impl FromStr for FaultConfig {
+    type Err = eyre::Report;
+
+    fn from_str(faults: &str) -> Result<Self> {
+        let mut config = Self::default();
+        for fault in faults
+            .split(',')
+            .map(str::trim)
+            .filter(|fault| !fault.is_empty())
+        {
+            let (key, value) = fault
+                .split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or_else(|| eyre!("Invalid fault {fault}, expected key=value"))?;
+            match key {
+                "error_rate" => config.error_rate = rate(parse(key, value)?)?,
+                "malformed_rate" => config.malformed_rate = rate(parse(key, value)?)?,
+                "latency_ms" => config.latency_ms = parse(key, value)?,
+                "jitter_ms" => config.jitter_ms = parse(key, value)?,
+                "seed" => config.seed = parse(key, value)?,
+                _ => return Err(eyre!("Unknown fault {key}")),
+            }
+        }
+        Ok(config)
+    }
+}
+
+fn parse<T: FromStr>(key: &str, value: &str) -> Result<T> {
+    value
+        .parse()
+        .map_err(|_| eyre!("Invalid value of fault {key}: {value}"))
+}
+
+fn rate(rate: f64) -> Result<f64> {
+    match (0.0..=1.0).contains(&rate) {
+        true => Ok(rate),
+        false => Err(eyre!("Fault rates are between 0 and 1, got {rate}")),
+    }
+}
+
+/// Response of the requests failing with a malformed response, a string instead of an object.
+const MALFORMED_RESPONSE: &str = "injected malformed response";
+
+/// Fault drawn for a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Fault {
+    Error,
+    Malformed,
+    None,
+}
+
+/// Xorshift generator, shared by the transports of a connector.
+#[derive(Debug)]
+struct FaultGenerator(Mutex<u64>);
+
+impl FaultGenerator {
+    fn new(seed: u64) -> Self {
+        // Xorshift is stuck on 0.
+        Self(Mutex::new(seed.max(1)))
+    }
+
+    fn next_u64(&self) -> u64 {
+        let mut state = self.0.lock().unwrap();
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        *state
+    }
+
+    /// Draw a number between 0 and 1.
+    fn sample(&self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1_u64 << 53) as f64
+    }
+
+    fn fault(&self, config: &FaultConfig) -> Fault {
+        let sample = self.sample();
+        if sample < config.error_rate {
+            Fault::Error
+        } else if sample < config.error_rate + config.malformed_rate {
+            Fault::Malformed
+        } else {
+            Fault::None
+        }
+    }
+
+    fn latency(&self, config: &FaultConfig) -> Duration {
+        let jitter = match config.jitter_ms {
+            0 => 0,
+            jitter_ms => self.next_u64() % (jitter_ms + 1),
+        };
+        Duration::from_millis(config.latency_ms + jitter)
+    }
+}
+
+/// Connector wrapping the transports opened by another one in a [`FaultyTransport`].
+pub struct FaultyConnector {
+    inner: Arc<dyn Connector>,
+    config: FaultConfig,
+    generator: Arc<FaultGenerator>,
+}
+
+impl FaultyConnector {
+    pub fn new(inner: Arc<dyn Connector>, config: FaultConfig) -> Self {
+        Self {
+            inner,
+            generator: Arc::new(FaultGenerator::new(config.seed)),
+            config,
+        }
+    }
+}
+
+impl Connector for FaultyConnector {
+    fn connect(&self, url: &str) -> Result<Arc<dyn Transport>> {
+        Ok(Arc::new(FaultyTransport {
+            inner: self.inner.connect(url)?,
+            config: self.config.clone(),
+            generator: self.generator.clone(),
+        }))
+    }
+}
+
+/// Transport injecting faults in the requests sent to a provider. A faulty request is not
+/// sent at all.
+pub struct FaultyTransport {
+    inner: Arc<dyn Transport>,
+    config: FaultConfig,
+    generator: Arc<FaultGenerator>,
+}
+
+impl FaultyTransport {
+    pub fn new(inner: Arc<dyn Transport>, config: FaultConfig) -> Self {
+        Self {
+            inner,
+            generator: Arc::new(FaultGenerator::new(config.seed)),
+            config,
+        }
+    }
+
+    /// Draw the fault of a request, after its latency.
+    async fn inject(&self) -> Fault {
+        let (fault, latency) = (
+            self.generator.fault(&self.config),
+            self.generator.latency(&self.config),
+        );
+        if !latency.is_zero() {
+            runtime::sleep(latency).await;
+        }
+        fault
+    }
+}
+
+impl fmt::Debug for FaultyTransport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FaultyTransport")
+            .field("inner", &self.inner)
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl Transport for FaultyTransport {
+    async fn send(&self, message: Value) -> Result<Value> {
+        match self.inject().await {
+            Fault::Error => Err(eyre!("Injected fault: provider unreachable")),
+            Fault::Malformed => Ok(Value::String(MALFORMED_RESPONSE.to_string())),
+            Fault::None => self.inner.send(message).await,
+        }
+    }
+
+    async fn send_raw(&self, message: Value) -> Result<Box<RawValue>> {
+        match self.inject().await {
+            Fault::Error => Err(eyre!("Injected fault: provider unreachable")),
+            Fault::Malformed => Ok(serde_json::value::to_raw_value(MALFORMED_RESPONSE)?),
+            Fault::None => self.inner.send_raw(message).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lightclient::transport::InMemoryTransport;
+    use serde_json::json;
+
+    fn transport(config: FaultConfig) -> FaultyTransport {
+        FaultyTransport::new(
+            Arc::new(InMemoryTransport::new(
+                |request| json!({"jsonrpc": "2.0", "id": request["id"], "result": "0x1"}),
+            )),
+            config,
+        )
+    }
+
+    #[test]
+    fn test_fault_config_is_parsed_from_key_value_pairs() {
+        let config: FaultConfig = "error_rate=0.1, malformed_rate=0.05,latency_ms=200,seed=7"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            config,
+            FaultConfig {
+                error_rate: 0.1,
+                malformed_rate: 0.05,
+                latency_ms: 200,
+                jitter_ms: 0,
+                seed: 7,
+            }
+        );
+        assert!("error_rate=2".parse::<FaultConfig>().is_err());
+        assert!("timeouts=0.1".parse::<FaultConfig>().is_err());
+        assert!("error_rate".parse::<FaultConfig>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_faults_are_injected_at_the_configured_rates() {
+        let request = json!({"jsonrpc": "2.0", "id": 1, "method": "starknet_blockNumber"});
+        let clean = transport(FaultConfig::default());
+        let failing = transport(FaultConfig {
+            error_rate: 1.0,
+            ..Default::default()
+        });
+        let malformed = transport(FaultConfig {
+            malformed_rate: 1.0,
+            ..Default::default()
+        });
+
+        assert_eq!(clean.send(request.clone()).await.unwrap()["result"], "0x1");
+        assert!(failing.send(request.clone()).await.is_err());
+        assert!(malformed.send(request).await.unwrap().is_string());
+    }
+
+    #[test]
+    fn test_faults_are_replayed_from_the_seed() {
+        let config = FaultConfig {
+            error_rate: 0.3,
+            malformed_rate: 0.3,
+            seed: 42,
+            ..Default::default()
+        };
+        let draw = || {
+            let generator = FaultGenerator::new(config.seed);
+            (0..1_000)
+                .map(|_| generator.fault(&config))
+                .collect::<Vec<_>>()
+        };
+        let faults = draw();
+
+        assert_eq!(faults, draw());
+        let errors = faults
+            .iter()
+            .filter(|fault| **fault == Fault::Error)
+            .count();
+        assert!((200..400).contains(&errors), "{errors} errors");
+    }
+}
@@ -0,0 +1,347 @@
+//! Transports carrying the JSON-RPC requests of the light client to the StarkNet provider.
+//! The StarkNet client, the raw requests and the batches all go through a [`Transport`], opened
+//! for each provider URL by a [`Connector`]. Embedders can pass their own connector to
+//! [`StarkNetLightClientImpl::with_connector`](crate::lightclient::starknet::StarkNetLightClientImpl::with_connector)
+//! to reach the providers over Tor or IPC, or with a custom authentication scheme.
+//!
+//! The Ethereum light client is built by helios from the URLs of the config, it does not go
+//! through the connector.
+use crate::lightclient::starknet::{
+    batch::{parse_response, response_error},
+    errors::http_error,
+};
+use async_trait::async_trait;
+use ethers::providers::{JsonRpcClient, ProviderError};
+use eyre::{eyre, Result};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
+use serde_json::{json, value::RawValue, Value};
+use starknet::providers::jsonrpc::{JsonRpcMethod, JsonRpcResponse, JsonRpcTransport};
+use std::{fmt, sync::Arc, time::Duration};
+use url::Url;
+
+pub mod faults;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod ws;
+
+/// Carries raw JSON-RPC messages to a provider: a request or a batch of requests, and the
+/// matching response.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait Transport: fmt::Debug + Send + Sync {
+    /// Send a message and wait for its response.
+    /// # Returns
+    /// `Ok(Value)` the response, which may hold a JSON-RPC error.
+    /// `Err(eyre::Report)` if the provider could not be reached or did not answer JSON.
+    async fn send(&self, message: Value) -> Result<Value>;
+
+    /// Send a message and return its response as the provider wrote it, checked to be JSON but
+    /// not parsed into a [`Value`]. Transports which receive text should avoid building the
+    /// tree, the default serializes the response of [`Transport::send`].
+    async fn send_raw(&self, message: Value) -> Result<Box<RawValue>> {
+        Ok(serde_json::value::to_raw_value(&self.send(message).await?)?)
+    }
+}
+
+/// Opens the transport of a provider URL. It is called for the default provider, for the
+/// routed ones, and for the providers set at runtime with `rotate_provider`.
+pub trait Connector: Send + Sync {
+    fn connect(&self, url: &str) -> Result<Arc<dyn Transport>>;
+}
+
+/// Transport posting the messages to a HTTP endpoint.
+#[derive(Debug, Clone)]
+pub struct HttpTransport {
+    client: reqwest::Client,
+    url: Url,
+}
+
+impl HttpTransport {
+    pub fn new(url: Url, client: reqwest::Client) -> Self {
+        Self { client, url }
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl Transport for HttpTransport {
+    async fn send(&self, message: Value) -> Result<Value> {
+        Ok(self
+            .client
+            .post(self.url.clone())
+            .json(&message)
+            .send()
+            .await
+            .map_err(http_error)?
+            .json()
+            .await?)
+    }
+
+    async fn send_raw(&self, message: Value) -> Result<Box<RawValue>> {
+        let body = self
+            .client
+            .post(self.url.clone())
+            .json(&message)
+            .send()
+            .await
+            .map_err(http_error)?
+            .text()
+            .await
+            .map_err(http_error)?;
+        Ok(RawValue::from_string(body)?)
+    }
+}
+
+/// Connector opening a [`HttpTransport`] for the `http` and `https` URLs, sharing a
+/// connection pool, and a [`ws::WsTransport`] for the `ws` and `wss` URLs outside the browser.
+#[derive(Debug, Clone)]
+pub struct DefaultConnector {
+    client: reqwest::Client,
+    /// Timeout of the requests sent over WebSocket, the HTTP client has its own.
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    request_timeout: Duration,
+}
+
+impl DefaultConnector {
+    pub fn new(client: reqwest::Client, request_timeout: Duration) -> Self {
+        Self {
+            client,
+            request_timeout,
+        }
+    }
+}
+
+impl Connector for DefaultConnector {
+    fn connect(&self, url: &str) -> Result<Arc<dyn Transport>> {
+        let parsed = Url::parse(url)?;
+        match parsed.scheme() {
+            "http" | "https" => Ok(Arc::new(HttpTransport::new(parsed, self.client.clone()))),
+            #[cfg(not(target_arch = "wasm32"))]
+            "ws" | "wss" => Ok(Arc::new(ws::WsTransport::new(url, self.request_timeout))),
+            scheme => Err(eyre!(
+                "Unsupported provider URL scheme {scheme}, a custom connector is needed"
+            )),
+        }
+    }
+}
+
+/// Transport answering the requests in process, to embed a provider or to stub one.
+pub struct InMemoryTransport {
+    handler: Box<dyn Fn(Value) -> Value + Send + Sync>,
+}
+
+impl InMemoryTransport {
+    /// Create a transport answering every request with `handler`. The requests of a batch are
+    /// handled one by one.
+    pub fn new(handler: impl Fn(Value) -> Value + Send + Sync + 'static) -> Self {
+        Self {
+            handler: Box::new(handler),
+        }
+    }
+}
+
+impl fmt::Debug for InMemoryTransport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InMemoryTransport").finish_non_exhaustive()
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl Transport for InMemoryTransport {
+    async fn send(&self, message: Value) -> Result<Value> {
+        Ok(match message {
+            Value::Array(requests) => {
+                Value::Array(requests.into_iter().map(&self.handler).collect())
+            }
+            request => (self.handler)(request),
+        })
+    }
+}
+
+/// Error of a request sent through a [`Transport`] by the StarkNet or ethers clients.
+#[derive(Debug)]
+pub struct TransportError(pub eyre::Report);
+
+impl TransportError {
+    /// Return the error of the transport, or of the provider.
+    pub fn into_report(self) -> eyre::Report {
+        self.0
+    }
+}
+
+impl fmt::Display for TransportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for TransportError {}
+
+impl From<TransportError> for ProviderError {
+    fn from(error: TransportError) -> Self {
+        ProviderError::JsonRpcClientError(Box::new(error))
+    }
+}
+
+/// Serves the StarkNet JSON-RPC client and the ethers provider over a [`Transport`].
+#[derive(Debug, Clone)]
+pub struct RpcTransport(pub Arc<dyn Transport>);
+
+impl RpcTransport {
+    async fn call<P: Serialize>(&self, method: &str, params: P) -> Result<Value> {
+        self.0.send(request(method, params)).await
+    }
+
+    /// Call a method and return its result as the provider wrote it. Only the envelope of the
+    /// response is parsed, the result is copied out of it as is.
+    pub async fn call_raw<P: Serialize>(&self, method: &str, params: P) -> Result<Box<RawValue>> {
+        let response = self.0.send_raw(request(method, params)).await?;
+        let envelope: RawResponse = serde_json::from_str(response.get())?;
+        match (envelope.result, envelope.error) {
+            (_, Some(error)) => Err(response_error(&error)),
+            (Some(result), None) => Ok(result.to_owned()),
+            (None, None) => Err(eyre!("Invalid JSON-RPC response: {}", response.get())),
+        }
+    }
+}
+
+/// Envelope of a JSON-RPC response, borrowing its result.
+#[derive(Deserialize)]
+struct RawResponse<'a> {
+    /// `None` if missing, a `null` result is kept.
+    #[serde(default, borrow, deserialize_with = "present")]
+    result: Option<&'a RawValue>,
+    error: Option<Value>,
+}
+
+fn present<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<&'de RawValue>, D::Error> {
+    <&RawValue>::deserialize(deserializer).map(Some)
+}
+
+fn request<P: Serialize>(method: &str, params: P) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": method,
+        "params": params,
+    })
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl JsonRpcTransport for RpcTransport {
+    type Error = TransportError;
+
+    async fn send_request<P, R>(
+        &self,
+        method: JsonRpcMethod,
+        params: P,
+    ) -> Result<JsonRpcResponse<R>, Self::Error>
+    where
+        P: Serialize + Send,
+        R: DeserializeOwned,
+    {
+        let method = serde_json::to_value(method).map_err(|err| TransportError(err.into()))?;
+        let response = self
+            .call(method.as_str().unwrap_or_default(), params)
+            .await
+            .map_err(TransportError)?;
+        serde_json::from_value(response).map_err(|err| TransportError(err.into()))
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl JsonRpcClient for RpcTransport {
+    type Error = TransportError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: fmt::Debug + Serialize + Send + Sync,
+        R: DeserializeOwned,
+    {
+        let response = self.call(method, params).await.map_err(TransportError)?;
+        let result = parse_response(response).map_err(TransportError)?;
+        serde_json::from_value(result).map_err(|err| TransportError(err.into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_in_memory_transport_answers_requests_and_batches() {
+        let transport = InMemoryTransport::new(
+            |request| json!({"jsonrpc": "2.0", "id": request["id"], "result": request["method"]}),
+        );
+
+        let response = transport
+            .send(json!({"jsonrpc": "2.0", "id": 1, "method": "starknet_chainId"}))
+            .await
+            .unwrap();
+        assert_eq!(response["result"], "starknet_chainId");
+
+        let responses = transport
+            .send(json!([
+                {"jsonrpc": "2.0", "id": 0, "method": "starknet_blockNumber"},
+                {"jsonrpc": "2.0", "id": 1, "method": "starknet_chainId"},
+            ]))
+            .await
+            .unwrap();
+        assert_eq!(responses[1]["id"], 1);
+        assert_eq!(responses[1]["result"], "starknet_chainId");
+    }
+
+    #[tokio::test]
+    async fn test_rpc_transport_returns_the_result_of_a_call() {
+        let transport = RpcTransport(Arc::new(InMemoryTransport::new(
+            |request| json!({"jsonrpc": "2.0", "id": request["id"], "result": request["params"][0]}),
+        )));
+
+        let result: String = transport
+            .request("starknet_traceTransaction", ["0x1"])
+            .await
+            .unwrap();
+        assert_eq!(result, "0x1");
+    }
+
+    #[tokio::test]
+    async fn test_rpc_transport_relays_the_raw_result_of_a_call() {
+        let transport = RpcTransport(Arc::new(InMemoryTransport::new(|request| {
+            match request["params"][0].as_str() {
+                Some("error") => json!({
+                    "jsonrpc": "2.0",
+                    "id": request["id"],
+                    "error": {"code": 24, "message": "Block not found"},
+                }),
+                Some("null") => json!({"jsonrpc": "2.0", "id": request["id"], "result": null}),
+                _ => json!({"jsonrpc": "2.0", "id": request["id"], "result": {"calls": [1, 2]}}),
+            }
+        })));
+
+        let result = transport
+            .call_raw("starknet_traceTransaction", ["0x1"])
+            .await
+            .unwrap();
+        assert_eq!(result.get(), r#"{"calls":[1,2]}"#);
+        let result = transport
+            .call_raw("starknet_traceTransaction", ["null"])
+            .await
+            .unwrap();
+        assert_eq!(result.get(), "null");
+        let error = transport
+            .call_raw("starknet_traceTransaction", ["error"])
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("Block not found"), "{error}");
+    }
+
+    #[test]
+    fn test_default_connector_opens_http_and_websocket_transports() {
+        let connector = DefaultConnector::new(reqwest::Client::new(), Duration::from_secs(1));
+        assert!(connector.connect("https://starknet.node").is_ok());
+        assert!(connector.connect("wss://starknet.node").is_ok());
+        assert!(connector.connect("ipc:///tmp/starknet.ipc").is_err());
+    }
+}
@@ -0,0 +1,261 @@
+//! WebSocket transport to a StarkNet provider. The requests are pipelined over a single
+//! connection, which is opened on the first request and opened again when it drops.
+use super::Transport;
+use crate::{lightclient::starknet::errors::UpstreamUnavailable, runtime};
+use async_trait::async_trait;
+use eyre::{eyre, Result};
+use futures::{future::join_all, StreamExt};
+use jsonrpsee::{
+    core::{
+        client::{ClientT, SubscriptionClientT},
+        traits::ToRpcParams,
+        Error as ClientError,
+    },
+    types::error::CallError,
+    ws_client::{WsClient, WsClientBuilder},
+};
+use serde_json::{json, value::RawValue, Value};
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::sync::{broadcast, Mutex as AsyncMutex};
+
+/// Delay before the first reconnection attempt, doubled after each failure.
+pub const RECONNECT_MIN_DELAY: Duration = Duration::from_millis(250);
+pub const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(30);
+/// Notifications buffered for a subscriber which is behind, older ones are dropped.
+pub const SUBSCRIPTION_CAPACITY: usize = 64;
+
+/// State of the connection to the provider.
+#[derive(Default)]
+struct Connection {
+    client: Option<Arc<WsClient>>,
+    /// After a failed attempt, the requests fail until this instant instead of reconnecting.
+    retry_at: Option<Instant>,
+    retry_delay: Duration,
+}
+
+/// Transport pipelining the requests over a WebSocket connection.
+pub struct WsTransport {
+    url: String,
+    request_timeout: Duration,
+    connection: AsyncMutex<Connection>,
+    /// Upstream subscriptions, by method and params, shared by their subscribers.
+    subscriptions: Mutex<HashMap<String, broadcast::Sender<Value>>>,
+}
+
+impl WsTransport {
+    /// Create a transport to a `ws` or `wss` URL. The connection is opened on the first request.
+    pub fn new(url: &str, request_timeout: Duration) -> Self {
+        Self {
+            url: url.to_string(),
+            request_timeout,
+            connection: AsyncMutex::new(Connection::default()),
+            subscriptions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Return the open connection, opening it again if it dropped.
+    /// Concurrent requests wait for the same attempt.
+    async fn client(&self) -> Result<Arc<WsClient>> {
+        let mut connection = self.connection.lock().await;
+        if let Some(client) = &connection.client {
+            if client.is_connected() {
+                return Ok(client.clone());
+            }
+            log::warn!("WebSocket connection to the StarkNet provider dropped, reconnecting");
+            connection.client = None;
+        }
+        if let Some(retry_at) = connection.retry_at {
+            if Instant::now() < retry_at {
+                return Err(UpstreamUnavailable::new("WebSocket connection lost").into());
+            }
+        }
+        match WsClientBuilder::default()
+            .request_timeout(self.request_timeout)
+            .connection_timeout(self.request_timeout)
+            .build(&self.url)
+            .await
+        {
+            Ok(client) => {
+                let client = Arc::new(client);
+                *connection = Connection {
+                    client: Some(client.clone()),
+                    ..Default::default()
+                };
+                Ok(client)
+            }
+            Err(err) => {
+                connection.retry_delay = (connection.retry_delay * 2)
+                    .max(RECONNECT_MIN_DELAY)
+                    .min(RECONNECT_MAX_DELAY);
+                connection.retry_at = Some(Instant::now() + connection.retry_delay);
+                Err(UpstreamUnavailable::new(err.to_string()).into())
+            }
+        }
+    }
+
+    /// Send a single request and build its JSON-RPC response.
+    async fn request(&self, request: Value) -> Result<Value> {
+        let id = request.get("id").cloned().unwrap_or(Value::Null);
+        let method = request
+            .get("method")
+            .and_then(Value::as_str)
+            .ok_or_else(|| eyre!("Invalid JSON-RPC request: {request}"))?;
+        let params = Params(request.get("params").cloned());
+        let result = self
+            .client()
+            .await?
+            .request::<Value, _>(method, params)
+            .await;
+        match result {
+            Ok(result) => Ok(json!({"jsonrpc": "2.0", "id": id, "result": result})),
+            Err(ClientError::Call(CallError::Custom(error))) => Ok(json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": {
+                    "code": error.code(),
+                    "message": error.message(),
+                    "data": error.data(),
+                },
+            })),
+            Err(err) => Err(client_error(err)),
+        }
+    }
+
+    /// Subscribe to the notifications of the provider.
+    /// The subscribers to the same method and params share an upstream subscription, which is
+    /// made again when the connection is opened again. It is dropped once they are all gone.
+    /// # Arguments
+    /// * `method` - The subscription method.
+    /// * `params` - The params of the subscription.
+    /// * `unsubscribe` - The method cancelling the subscription.
+    pub fn subscribe(
+        self: &Arc<Self>,
+        method: &str,
+        params: Value,
+        unsubscribe: &str,
+    ) -> broadcast::Receiver<Value> {
+        let key = format!("{method}{params}");
+        let mut subscriptions = self.subscriptions.lock().unwrap();
+        if let Some(sender) = subscriptions.get(&key) {
+            if sender.receiver_count() > 0 {
+                return sender.subscribe();
+            }
+        }
+        let (sender, receiver) = broadcast::channel(SUBSCRIPTION_CAPACITY);
+        subscriptions.insert(key.clone(), sender.clone());
+        runtime::spawn(forward_notifications(
+            self.clone(),
+            key,
+            method.to_string(),
+            params,
+            unsubscribe.to_string(),
+            sender,
+        ));
+        receiver
+    }
+}
+
+impl fmt::Debug for WsTransport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WsTransport")
+            .field("url", &self.url)
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl Transport for WsTransport {
+    /// The requests of a batch are pipelined rather than sent as a batch.
+    async fn send(&self, message: Value) -> Result<Value> {
+        match message {
+            Value::Array(requests) => {
+                let responses = join_all(requests.into_iter().map(|request| self.request(request)))
+                    .await
+                    .into_iter()
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(Value::Array(responses))
+            }
+            request => self.request(request).await,
+        }
+    }
+}
+
+/// Forward the notifications of an upstream subscription to its subscribers, subscribing
+/// again after a reconnection, until they are all gone.
+async fn forward_notifications(
+    transport: Arc<WsTransport>,
+    key: String,
+    method: String,
+    params: Value,
+    unsubscribe: String,
+    sender: broadcast::Sender<Value>,
+) {
+    let mut retry_delay = RECONNECT_MIN_DELAY;
+    while sender.receiver_count() > 0 {
+        let client = match transport.client().await {
+            Ok(client) => client,
+            Err(_) => {
+                runtime::sleep(retry_delay).await;
+                retry_delay = (retry_delay * 2).min(RECONNECT_MAX_DELAY);
+                continue;
+            }
+        };
+        let subscription = client
+            .subscribe::<Value, _>(&method, Params(Some(params.clone())), &unsubscribe)
+            .await;
+        let mut subscription = match subscription {
+            Ok(subscription) => subscription,
+            Err(err) => {
+                log::warn!("Failed to subscribe to {method}: {err}");
+                runtime::sleep(retry_delay).await;
+                retry_delay = (retry_delay * 2).min(RECONNECT_MAX_DELAY);
+                continue;
+            }
+        };
+        retry_delay = RECONNECT_MIN_DELAY;
+        while let Some(Ok(notification)) = subscription.next().await {
+            if sender.send(notification).is_err() {
+                // Dropping the subscription unsubscribes.
+                break;
+            }
+        }
+    }
+    // A subscriber may have come in the meantime, and started a new upstream subscription.
+    let mut subscriptions = transport.subscriptions.lock().unwrap();
+    if subscriptions
+        .get(&key)
+        .map_or(false, |current| current.receiver_count() == 0)
+    {
+        subscriptions.remove(&key);
+    }
+}
+
+/// Convert an error of the WebSocket client, a lost connection or a timeout meaning that the
+/// provider is unavailable.
+fn client_error(error: ClientError) -> eyre::Report {
+    match error {
+        ClientError::Transport(_) | ClientError::RestartNeeded(_) | ClientError::RequestTimeout => {
+            UpstreamUnavailable::new(error.to_string()).into()
+        }
+        error => eyre!(error),
+    }
+}
+
+/// Params of a request, forwarded as received.
+struct Params(Option<Value>);
+
+impl ToRpcParams for Params {
+    fn to_rpc_params(self) -> Result<Option<Box<RawValue>>, ClientError> {
+        match self.0 {
+            None | Some(Value::Null) => Ok(None),
+            Some(params) => serde_json::value::to_raw_value(&params)
+                .map(Some)
+                .map_err(ClientError::ParseError),
+        }
+    }
+}
@@ -0,0 +1,160 @@
+//! Monitoring of the upgrades of the StarkNet core contract.
+//!
+//! The core contract is a proxy: an upgrade of its implementation, or a new program hash,
+//! changes which state transitions L1 accepts, hence what the light client trusts. Such changes
+//! are reported by `beerus_status` until the node restarts.
+use super::ethereum::EthereumLightClient;
+use crate::ethers_helper;
+use ethers::{
+    abi::Abi,
+    types::{Address, H256, U256},
+};
+use eyre::Result;
+use helios::types::{BlockTag, CallOpts};
+use serde::{Deserialize, Serialize};
+use std::{str::FromStr, sync::Mutex};
+use tokio::sync::watch;
+
+/// Storage slot of the StarkWare proxy holding the address of the implementation,
+/// `keccak256("StarkWare2019.implemntation-slot")`.
+pub const PROXY_IMPLEMENTATION_SLOT: &str =
+    "0x177667240aeeea7e35eabe3a35e18306f336219e1386f7710a6bf8783f761b24";
+
+/// Parameters of the StarkNet core contract which define what L1 accepts.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoreContractParameters {
+    /// Address of the implementation behind the proxy.
+    pub implementation: Address,
+    /// Hash of the StarkNet OS program whose proofs are accepted.
+    pub program_hash: U256,
+    /// Hash of the StarkNet OS configuration.
+    pub config_hash: U256,
+}
+
+impl CoreContractParameters {
+    /// Read the parameters of the core contract at the latest L1 block.
+    /// # Arguments
+    /// * `ethereum_lightclient` - The Ethereum light client verifying the reads.
+    /// * `starknet_core_abi` - The ABI of the core contract.
+    /// * `starknet_core_contract_address` - The address of the proxy of the core contract.
+    pub async fn fetch(
+        ethereum_lightclient: &dyn EthereumLightClient,
+        starknet_core_abi: &Abi,
+        starknet_core_contract_address: Address,
+    ) -> Result<Self> {
+        let call = |function_name: &'static str| async move {
+            let data =
+                ethers_helper::encode_function_data((), starknet_core_abi.clone(), function_name)?;
+            let call_opts = CallOpts {
+                from: None,
+                to: starknet_core_contract_address,
+                gas: None,
+                gas_price: None,
+                value: None,
+                data: Some(data.to_vec()),
+            };
+            let call_response = ethereum_lightclient
+                .call(&call_opts, BlockTag::Latest)
+                .await?;
+            ethers_helper::bytes_to_u256(&call_response)
+        };
+        // The slot is a valid constant, parsing it never fails.
+        let slot = H256::from_str(PROXY_IMPLEMENTATION_SLOT).unwrap();
+        let implementation = ethereum_lightclient
+            .get_storage_at(&starknet_core_contract_address, slot, BlockTag::Latest)
+            .await?;
+        Ok(Self {
+            implementation: ethers_helper::u256_to_address(implementation)?,
+            program_hash: call("programHash").await?,
+            config_hash: call("configHash").await?,
+        })
+    }
+
+    /// Return the names of the parameters which differ from another set of parameters.
+    pub fn changes(&self, other: &Self) -> Vec<&'static str> {
+        let mut changes = Vec::new();
+        if self.implementation != other.implementation {
+            changes.push("implementation");
+        }
+        if self.program_hash != other.program_hash {
+            changes.push("program_hash");
+        }
+        if self.config_hash != other.config_hash {
+            changes.push("config_hash");
+        }
+        changes
+    }
+}
+
+/// Change of the parameters of the core contract observed while the light client runs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoreContractUpgrade {
+    /// Parameters observed before the change.
+    pub previous: CoreContractParameters,
+    /// Parameters observed after the change.
+    pub current: CoreContractParameters,
+    /// Names of the parameters which changed.
+    pub changes: Vec<String>,
+}
+
+/// Last parameters of the core contract and last upgrade observed, fed by the upgrade monitor
+/// task, see [`BeerusLightClient::subscribe_core_contract_upgrades`].
+///
+/// [`BeerusLightClient::subscribe_core_contract_upgrades`]: super::beerus::BeerusLightClient::subscribe_core_contract_upgrades
+#[derive(Debug)]
+pub struct UpgradeMonitor {
+    parameters: Mutex<Option<CoreContractParameters>>,
+    upgrade: watch::Sender<Option<CoreContractUpgrade>>,
+}
+
+impl Default for UpgradeMonitor {
+    fn default() -> Self {
+        Self {
+            parameters: Mutex::new(None),
+            upgrade: watch::channel(None).0,
+        }
+    }
+}
+
+impl UpgradeMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the parameters read from L1.
+    /// # Returns
+    /// The upgrade if the parameters changed since the last record, `None` otherwise.
+    pub fn record(&self, parameters: CoreContractParameters) -> Option<CoreContractUpgrade> {
+        let previous = self
+            .parameters
+            .lock()
+            .unwrap()
+            .replace(parameters.clone())?;
+        let changes = previous.changes(&parameters);
+        if changes.is_empty() {
+            return None;
+        }
+        let upgrade = CoreContractUpgrade {
+            previous,
+            current: parameters,
+            changes: changes.into_iter().map(String::from).collect(),
+        };
+        self.upgrade.send_replace(Some(upgrade.clone()));
+        Some(upgrade)
+    }
+
+    /// Return the last parameters read from L1, if any.
+    pub fn parameters(&self) -> Option<CoreContractParameters> {
+        self.parameters.lock().unwrap().clone()
+    }
+
+    /// Return the last upgrade observed, if any.
+    pub fn last_upgrade(&self) -> Option<CoreContractUpgrade> {
+        self.upgrade.borrow().clone()
+    }
+
+    /// Subscribe to the upgrades observed from now on.
+    pub fn subscribe(&self) -> watch::Receiver<Option<CoreContractUpgrade>> {
+        self.upgrade.subscribe()
+    }
+}
@@ -0,0 +1,101 @@
+//! Minimal async runtime abstraction, so that the light client runs both natively on tokio and
+//! in the browser on the JavaScript event loop.
+#[cfg(target_arch = "wasm32")]
+use futures::FutureExt;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{any::Any, future::Future, time::Duration};
+
+/// Outcome of a task spawned with [`spawn_watched`], the panic message if it panicked.
+pub type TaskOutcome = Result<(), String>;
+
+/// Spawn a future in the background.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn spawn<F>(future: F)
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    tokio::spawn(future);
+}
+
+/// Spawn a future in the background.
+#[cfg(target_arch = "wasm32")]
+pub fn spawn<F>(future: F)
+where
+    F: Future<Output = ()> + 'static,
+{
+    wasm_bindgen_futures::spawn_local(future);
+}
+
+/// Spawn a future in the background, returning a future which resolves once it exits.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn spawn_watched<F>(future: F) -> impl Future<Output = TaskOutcome>
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    let handle = tokio::spawn(future);
+    async move {
+        handle.await.map_err(|err| match err.try_into_panic() {
+            Ok(payload) => panic_message(payload.as_ref()),
+            Err(err) => err.to_string(),
+        })
+    }
+}
+
+/// Spawn a future in the background, returning a future which resolves once it exits.
+#[cfg(target_arch = "wasm32")]
+pub fn spawn_watched<F>(future: F) -> impl Future<Output = TaskOutcome>
+where
+    F: Future<Output = ()> + 'static,
+{
+    let (outcome_tx, outcome_rx) = tokio::sync::oneshot::channel();
+    wasm_bindgen_futures::spawn_local(async move {
+        let outcome = std::panic::AssertUnwindSafe(future)
+            .catch_unwind()
+            .await
+            .map_err(|payload| panic_message(payload.as_ref()));
+        let _ = outcome_tx.send(outcome);
+    });
+    async move {
+        outcome_rx
+            .await
+            .unwrap_or_else(|_| Err("task dropped".to_string()))
+    }
+}
+
+/// Message of a panic, as passed to `panic!`.
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "task panicked".to_string()
+    }
+}
+
+/// Wait for the given duration without blocking the runtime.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn sleep(duration: Duration) {
+    tokio::time::sleep(duration).await;
+}
+
+/// Wait for the given duration without blocking the runtime.
+#[cfg(target_arch = "wasm32")]
+pub async fn sleep(duration: Duration) {
+    gloo_timers::future::sleep(duration).await;
+}
+
+/// Milliseconds elapsed since the Unix epoch, used to measure durations.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
+
+/// Milliseconds elapsed since the Unix epoch, used to measure durations.
+#[cfg(target_arch = "wasm32")]
+pub fn now_millis() -> u64 {
+    js_sys::Date::now() as u64
+}
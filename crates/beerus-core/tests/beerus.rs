@@ -5,11 +5,35 @@ use common::mock_clients;
 mod tests {
     use super::*;
     use beerus_core::{
-        config::Config,
+        config::{
+            BlockNumberSource, Config, Finality, HttpConfig,
+            DEFAULT_STARKNET_BATCH_FLUSH_INTERVAL_MS, DEFAULT_STARKNET_BATCH_MAX_SIZE,
+        },
+        ethers_helper,
         lightclient::{
-            beerus::{BeerusLightClient, SyncStatus},
-            ethereum::helios_lightclient::HeliosLightClient,
-            starknet::{StarkNetLightClient, StarkNetLightClientImpl},
+            abi::ContractAbi,
+            alerts::AlertKind,
+            beerus::{
+                BeerusLightClient, BlockTarget, NodeData, Passthrough, ProviderStatus,
+                StateRootMismatch, SyncProgress, SyncStatus, TransactionNotVerified,
+            },
+            contract_index::UNIVERSAL_DEPLOYER_ADDRESS,
+            ethereum::{helios_lightclient::HeliosLightClient, read_starknet_proven_state},
+            export::{ExportFormat, ExportTable},
+            messaging::{L1ToL2Message, L1ToL2MessageDelivery, L2ToL1Message},
+            nonce_manager::NonceManager,
+            service::{
+                apply, backfill_from, changes_node, store_alerts, BeerusHandle, StoreCommand,
+                MAX_BACKFILL_BLOCKS, RESTART_MIN_BACKOFF,
+            },
+            starknet::{
+                block_hash::{compute_block_hash, verify_block_hash, BlockHashCheck},
+                errors::{StarknetError, StarknetErrorCode, UpstreamUnavailable},
+                fee::SimulationFlag,
+                inclusion_proof::TransactionProof,
+                StarkNetLightClient, StarkNetLightClientImpl,
+            },
+            upgrade_monitor::{CoreContractParameters, CoreContractUpgrade},
         },
         starknet_helper::{block_id_string_to_block_id_type, create_mock_broadcasted_transaction},
     };
@@ -17,21 +41,22 @@ mod tests {
     use eyre::eyre;
     use helios::types::{BlockTag, CallOpts, ExecutionBlock, Transactions};
     use starknet::{
-        core::types::FieldElement,
+        core::{types::FieldElement, utils::get_contract_address},
         macros::selector,
         providers::jsonrpc::models::{
             BlockHashAndNumber, BlockId, BlockStatus, BlockWithTxHashes, BlockWithTxs,
             BroadcastedDeclareTransaction, BroadcastedDeployTransaction,
             BroadcastedInvokeTransaction, BroadcastedInvokeTransactionV0, ContractClass,
-            ContractEntryPoint, DeclareTransactionResult, DeployTransactionResult,
-            EntryPointsByType, EventFilter, FeeEstimate, InvokeTransaction,
-            InvokeTransactionReceipt, InvokeTransactionResult, InvokeTransactionV0,
+            ContractEntryPoint, DeclareTransaction, DeclareTransactionResult,
+            DeployTransactionResult, EmittedEvent, EntryPointsByType, Event, EventFilter,
+            EventsPage, FeeEstimate, InvokeTransaction, InvokeTransactionReceipt,
+            InvokeTransactionResult, InvokeTransactionV0, L1HandlerTransaction,
             MaybePendingBlockWithTxHashes, MaybePendingBlockWithTxs,
             MaybePendingTransactionReceipt, StateDiff, StateUpdate, SyncStatusType,
             Transaction as StarknetTransaction, TransactionReceipt, TransactionStatus,
         },
     };
-    use std::{path::PathBuf, str::FromStr};
+    use std::{path::PathBuf, str::FromStr, sync::Arc};
 
     #[test]
     fn when_call_new_then_should_return_beerus_lightclient() {
@@ -125,6 +150,36 @@ mod tests {
         assert_eq!(beerus.sync_status().clone(), SyncStatus::NotSynced);
     }
 
+    /// Test that the light client started in the background reports its readiness.
+    #[tokio::test]
+    async fn given_start_in_background_when_call_await_synced_then_should_be_synced() {
+        // Given
+        let (config, mut ethereum_lightclient_mock, mut starknet_lightclient_mock) = mock_clients();
+        ethereum_lightclient_mock
+            .expect_start()
+            .times(1)
+            .return_once(move || Ok(()));
+        starknet_lightclient_mock
+            .expect_start()
+            .times(1)
+            .return_once(move || Ok(()));
+        let beerus = BeerusHandle::from(BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        ));
+
+        // When
+        beerus.start_in_background();
+
+        // Then
+        // The start runs once the test yields, queries are rejected until then.
+        assert!(beerus.ensure_synced().is_err());
+        beerus.await_synced().await;
+        assert_eq!(beerus.sync_status(), SyncStatus::Synced);
+        assert!(beerus.ensure_synced().is_ok());
+    }
+
     /// Test the `send_raw_transaction` method when everything is fine.
     /// This test mocks external dependencies.
     /// It does not test the `send_raw_transaction` method of the external dependencies.
@@ -1786,7 +1841,22 @@ mod tests {
             ethereum_consensus_rpc: "http://localhost:8545".to_string(),
             ethereum_execution_rpc: "http://localhost:8545".to_string(),
             starknet_rpc: "mainnet".to_string(),
+            starknet_rpc_routes: Default::default(),
             data_dir: Some(PathBuf::from("/tmp")),
+            starknet_batch_max_size: DEFAULT_STARKNET_BATCH_MAX_SIZE,
+            starknet_batch_flush_interval_ms: DEFAULT_STARKNET_BATCH_FLUSH_INTERVAL_MS,
+            payload_compression_level: None,
+            payload_headers_only: false,
+            archive_mode: false,
+            prefetch_receipts: false,
+            starknet_rpc_passthrough: false,
+            memory_budget_mb: None,
+            block_number_source: Default::default(),
+            finality: Default::default(),
+            starknet_feeder_gateway: None,
+            block_sources: Default::default(),
+            http: HttpConfig::default(),
+            alerts: Default::default(),
             starknet_core_contract_address: Address::from_str(
                 "0x0000000000000000000000000000000000000000",
             )
@@ -3738,6 +3808,57 @@ mod tests {
         // Assert that the sync status of the Beerus light client is `SyncStatus::NotSynced`.
         assert_eq!(beerus.sync_status().clone(), SyncStatus::NotSynced);
     }
+
+    /// Test that the pending block is fetched from the StarkNet provider, with the hashes of its
+    /// transactions, and returned as a pending block even when the provider returns a block.
+    #[tokio::test]
+    async fn given_pending_tag_when_call_get_block_with_tx_hashes_then_should_return_pending_block()
+    {
+        // Given
+        let (config, ethereum_lightclient_mock, mut starknet_lightclient_mock) = mock_clients();
+        let mut block = block_with_single_invoke(FieldElement::ZERO);
+        block.status = BlockStatus::Pending;
+        starknet_lightclient_mock
+            .expect_get_block_with_txs()
+            .withf(|block_id| {
+                matches!(
+                    block_id,
+                    BlockId::Tag(starknet::providers::jsonrpc::models::BlockTag::Pending)
+                )
+            })
+            .times(1)
+            .return_once(move |_| Ok(MaybePendingBlockWithTxs::Block(block)));
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+
+        // When
+        let result = beerus
+            .get_block_with_tx_hashes(&BlockId::Tag(
+                starknet::providers::jsonrpc::models::BlockTag::Pending,
+            ))
+            .await
+            .unwrap();
+
+        // Then
+        match result {
+            MaybePendingBlockWithTxHashes::PendingBlock(pending) => {
+                assert_eq!(
+                    pending.transactions,
+                    vec![FieldElement::from_hex_be("0x1234").unwrap()]
+                );
+                assert_eq!(
+                    pending.parent_hash,
+                    FieldElement::from_hex_be("0x99").unwrap()
+                );
+                assert_eq!(pending.timestamp, 1_000);
+            }
+            MaybePendingBlockWithTxHashes::Block(_) => panic!("Expected a pending block"),
+        }
+    }
+
     /// Test the `get_transaction_by_hash` method when the StarkNet light client returns an error.
     /// This test mocks external dependencies.
     /// It does not test the `get_transaction_by_hash` method of the external dependencies.
@@ -3957,4 +4078,3349 @@ mod tests {
         // Assert that the error returned by the `add_declare_transaction` method of the Beerus light client is the expected error.
         assert_eq!(result.unwrap_err().to_string(), expected_error.to_string());
     }
+
+    /// Test the `starknet_apply_l1_state_diff` method when everything is fine.
+    /// The state diff is read from the memory page registration calldata and
+    /// the storage can then be queried from the rebuilt state.
+    #[tokio::test]
+    async fn given_normal_conditions_when_apply_l1_state_diff_then_storage_is_served_locally() {
+        // Given
+        // Mock config, ethereum light client and starknet light client.
+        let (config, mut ethereum_lightclient_mock, starknet_lightclient_mock) = mock_clients();
+        let fact_registry = config.memory_page_fact_registry_address().unwrap();
+        mock_memory_page_fact(&mut ethereum_lightclient_mock, fact_registry, true);
+        mock_state_update(
+            &mut ethereum_lightclient_mock,
+            config.starknet_core_contract_address,
+            &memory_page_words(),
+            10,
+        );
+        mock_memory_page_transaction(&mut ethereum_lightclient_mock, fact_registry);
+
+        ethereum_lightclient_mock
+            .expect_starknet_proven_state()
+            .return_once(|| Ok((U256::from(10), U256::from(99))));
+        ethereum_lightclient_mock
+            .expect_starknet_state_root()
+            .times(1)
+            .returning(|| Ok(U256::from(99)));
+
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+
+        // When
+        beerus
+            .starknet_apply_l1_state_diff(&[H256::zero()], STATE_UPDATE_TX_HASH, 10)
+            .await
+            .unwrap();
+        let result = beerus
+            .starknet_get_storage_at_from_l1_state(
+                FieldElement::from_hex_be("0x123").unwrap(),
+                FieldElement::from_hex_be("0x5").unwrap(),
+            )
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(result, FieldElement::from_hex_be("0x6").unwrap());
+        assert_eq!(beerus.local_state.read().await.block_number, 10);
+    }
+
+    /// Test that a state diff whose memory page is not a valid fact of the fact registry is
+    /// rejected.
+    #[tokio::test]
+    async fn given_invalid_memory_page_fact_when_apply_l1_state_diff_then_should_return_error() {
+        // Given
+        let (config, mut ethereum_lightclient_mock, starknet_lightclient_mock) = mock_clients();
+        let fact_registry = config.memory_page_fact_registry_address().unwrap();
+        mock_memory_page_fact(&mut ethereum_lightclient_mock, fact_registry, false);
+        mock_state_update(
+            &mut ethereum_lightclient_mock,
+            config.starknet_core_contract_address,
+            &memory_page_words(),
+            10,
+        );
+        mock_memory_page_transaction(&mut ethereum_lightclient_mock, fact_registry);
+        ethereum_lightclient_mock
+            .expect_starknet_proven_state()
+            .return_once(|| Ok((U256::from(10), U256::from(99))));
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+
+        // When
+        let result = beerus
+            .starknet_apply_l1_state_diff(&[H256::zero()], STATE_UPDATE_TX_HASH, 10)
+            .await;
+
+        // Then
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            format!(
+                "The memory page of transaction {:?} is not a valid fact",
+                H256::zero()
+            )
+        );
+        assert_eq!(beerus.local_state.read().await.block_number, 0);
+    }
+
+    /// Test that valid memory pages which are not the data accepted by the core contract for
+    /// the block are rejected.
+    #[tokio::test]
+    async fn given_memory_pages_of_another_update_when_apply_l1_state_diff_then_should_return_error(
+    ) {
+        // Given
+        let (config, mut ethereum_lightclient_mock, starknet_lightclient_mock) = mock_clients();
+        let fact_registry = config.memory_page_fact_registry_address().unwrap();
+        mock_memory_page_fact(&mut ethereum_lightclient_mock, fact_registry, true);
+        mock_state_update(
+            &mut ethereum_lightclient_mock,
+            config.starknet_core_contract_address,
+            &[U256::zero(), U256::zero()],
+            10,
+        );
+        mock_memory_page_transaction(&mut ethereum_lightclient_mock, fact_registry);
+        ethereum_lightclient_mock
+            .expect_starknet_proven_state()
+            .return_once(|| Ok((U256::from(10), U256::from(99))));
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+
+        // When
+        let result = beerus
+            .starknet_apply_l1_state_diff(&[H256::zero()], STATE_UPDATE_TX_HASH, 10)
+            .await;
+
+        // Then
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .starts_with("Memory pages hash to"));
+        assert_eq!(beerus.local_state.read().await.block_number, 0);
+    }
+
+    /// Test that a state update moving the core contract to another block is rejected.
+    #[tokio::test]
+    async fn given_state_update_of_another_block_when_apply_l1_state_diff_then_should_return_error()
+    {
+        // Given
+        let (config, mut ethereum_lightclient_mock, starknet_lightclient_mock) = mock_clients();
+        let fact_registry = config.memory_page_fact_registry_address().unwrap();
+        mock_state_update(
+            &mut ethereum_lightclient_mock,
+            config.starknet_core_contract_address,
+            &memory_page_words(),
+            9,
+        );
+        mock_memory_page_transaction(&mut ethereum_lightclient_mock, fact_registry);
+        ethereum_lightclient_mock
+            .expect_starknet_proven_state()
+            .return_once(|| Ok((U256::from(10), U256::from(99))));
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+
+        // When
+        let result = beerus
+            .starknet_apply_l1_state_diff(&[H256::zero()], STATE_UPDATE_TX_HASH, 10)
+            .await;
+
+        // Then
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            format!(
+                "Transaction {:?} updates the state to block 9, not 10",
+                STATE_UPDATE_TX_HASH
+            )
+        );
+    }
+
+    /// Hash of the L1 transaction updating the state in the state diff tests.
+    const STATE_UPDATE_TX_HASH: H256 = H256::repeat_byte(1);
+
+    /// Words of a state diff with a single storage update.
+    fn memory_page_words() -> Vec<U256> {
+        vec![
+            U256::from(1),
+            U256::from(0x123),
+            U256::from((1u128 << 64) + 1),
+            U256::from(5),
+            U256::from(6),
+            U256::from(0),
+        ]
+    }
+
+    /// Mock the memory page registration of `memory_page_words` at hash zero.
+    fn mock_memory_page_transaction(
+        ethereum_lightclient_mock: &mut beerus_core::lightclient::ethereum::MockEthereumLightClient,
+        fact_registry: Address,
+    ) {
+        let words = memory_page_words()
+            .into_iter()
+            .map(ethers::abi::Token::Uint)
+            .collect();
+        let mut input = ethers::utils::id(
+            "registerContinuousMemoryPage(uint256,uint256[],uint256,uint256,uint256)",
+        )
+        .to_vec();
+        input.extend(ethers::abi::encode(&[
+            ethers::abi::Token::Uint(U256::zero()),
+            ethers::abi::Token::Array(words),
+            ethers::abi::Token::Uint(U256::zero()),
+            ethers::abi::Token::Uint(U256::zero()),
+            ethers::abi::Token::Uint(U256::zero()),
+        ]));
+        let transaction = Transaction {
+            hash: H256::zero(),
+            block_number: Some(100.into()),
+            to: Some(fact_registry),
+            input: input.into(),
+            ..Default::default()
+        };
+        ethereum_lightclient_mock
+            .expect_get_transaction_by_hash()
+            .withf(|hash| *hash == H256::zero())
+            .return_once(move |_| Ok(Some(transaction)));
+    }
+
+    /// Mock the registration of the memory page fact by the memory page transaction.
+    fn mock_memory_page_fact(
+        ethereum_lightclient_mock: &mut beerus_core::lightclient::ethereum::MockEthereumLightClient,
+        fact_registry: Address,
+        valid: bool,
+    ) {
+        let log = Log {
+            transaction_hash: Some(H256::zero()),
+            data: vec![0xfa; 96].into(),
+            ..Default::default()
+        };
+        ethereum_lightclient_mock
+            .expect_get_logs()
+            .withf(move |_, _, address, _, _| *address == Some(format!("{fact_registry:?}")))
+            .return_once(move |_, _, _, _, _| Ok(vec![log]));
+        ethereum_lightclient_mock
+            .expect_call()
+            .withf(|opts, _| {
+                opts.data
+                    .as_deref()
+                    .map_or(false, |data| data[4..] == [0xfa; 32])
+            })
+            .return_once(move |_, _| {
+                let mut word = [0u8; 32];
+                word[31] = valid as u8;
+                Ok(word.to_vec())
+            });
+    }
+
+    /// Mock the state update transaction moving the core contract to `block_number`, with the
+    /// data availability fact of `words`.
+    fn mock_state_update(
+        ethereum_lightclient_mock: &mut beerus_core::lightclient::ethereum::MockEthereumLightClient,
+        core_contract: Address,
+        words: &[U256],
+        block_number: u64,
+    ) {
+        let data: Vec<u8> = words
+            .iter()
+            .flat_map(|word| {
+                let mut bytes = [0u8; 32];
+                word.to_big_endian(&mut bytes);
+                bytes
+            })
+            .collect();
+        let mut input = ethers::utils::id("updateState(uint256[],uint256,uint256)").to_vec();
+        input.extend(ethers::abi::encode(&[
+            ethers::abi::Token::Array(vec![]),
+            ethers::abi::Token::Uint(U256::from_big_endian(&ethers::utils::keccak256(data))),
+            ethers::abi::Token::Uint(U256::from(words.len())),
+        ]));
+        let transaction = Transaction {
+            hash: STATE_UPDATE_TX_HASH,
+            block_number: Some(101.into()),
+            to: Some(core_contract),
+            input: input.into(),
+            ..Default::default()
+        };
+        ethereum_lightclient_mock
+            .expect_get_transaction_by_hash()
+            .withf(|hash| *hash == STATE_UPDATE_TX_HASH)
+            .return_once(move |_| Ok(Some(transaction)));
+
+        let abi: ethers::abi::Abi =
+            serde_json::from_str(include_str!("../src/resources/starknet_core_abi.json")).unwrap();
+        let log = Log {
+            topics: vec![abi.event("LogStateUpdate").unwrap().signature()],
+            data: ethers::abi::encode(&[
+                ethers::abi::Token::Uint(U256::from(99)),
+                ethers::abi::Token::Int(U256::from(block_number)),
+            ])
+            .into(),
+            transaction_hash: Some(STATE_UPDATE_TX_HASH),
+            ..Default::default()
+        };
+        ethereum_lightclient_mock
+            .expect_get_logs()
+            .withf(move |_, _, address, _, _| *address == Some(format!("{core_contract:?}")))
+            .return_once(move |_, _, _, _, _| Ok(vec![log]));
+    }
+
+    /// Test the `starknet_apply_l1_state_diff` method when the state diff does not belong to
+    /// the last proven block.
+    #[tokio::test]
+    async fn given_block_not_proven_when_apply_l1_state_diff_then_should_return_error() {
+        // Given
+        // Mock config, ethereum light client and starknet light client.
+        let (config, mut ethereum_lightclient_mock, starknet_lightclient_mock) = mock_clients();
+
+        ethereum_lightclient_mock
+            .expect_starknet_proven_state()
+            .return_once(|| Ok((U256::from(10), U256::from(99))));
+
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+
+        // When
+        let result = beerus
+            .starknet_apply_l1_state_diff(&[H256::zero()], STATE_UPDATE_TX_HASH, 11)
+            .await;
+
+        // Then
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "State diff for block 11 does not match the last proven block 10"
+        );
+    }
+
+    fn block_with_single_invoke(block_hash: FieldElement) -> BlockWithTxs {
+        let transaction = InvokeTransactionV0 {
+            transaction_hash: FieldElement::from_hex_be("0x1234").unwrap(),
+            max_fee: FieldElement::ONE,
+            signature: vec![FieldElement::TWO],
+            nonce: FieldElement::ZERO,
+            contract_address: FieldElement::from_hex_be("0x42").unwrap(),
+            entry_point_selector: selector!("transfer"),
+            calldata: vec![],
+        };
+        BlockWithTxs {
+            status: BlockStatus::AcceptedOnL2,
+            block_hash,
+            parent_hash: FieldElement::from_hex_be("0x99").unwrap(),
+            block_number: 10,
+            new_root: FieldElement::from_hex_be("0x123").unwrap(),
+            timestamp: 1_000,
+            sequencer_address: FieldElement::from_hex_be("0x5").unwrap(),
+            transactions: vec![StarknetTransaction::Invoke(InvokeTransaction::V0(
+                transaction,
+            ))],
+        }
+    }
+
+    fn receipt_with_event(event: Event) -> MaybePendingTransactionReceipt {
+        MaybePendingTransactionReceipt::Receipt(TransactionReceipt::Invoke(
+            InvokeTransactionReceipt {
+                transaction_hash: FieldElement::from_hex_be("0x1234").unwrap(),
+                actual_fee: FieldElement::ONE,
+                status: TransactionStatus::AcceptedOnL2,
+                block_hash: FieldElement::ZERO,
+                block_number: 10,
+                messages_sent: vec![],
+                events: vec![event],
+            },
+        ))
+    }
+
+    /// Test that a block whose hash matches its header, transactions and events is accepted.
+    #[tokio::test]
+    async fn given_matching_block_hash_when_verify_block_hash_then_should_return_ok() {
+        // Given
+        let (_, _, mut starknet_lightclient_mock) = mock_clients();
+        let event = Event {
+            from_address: FieldElement::from_hex_be("0x42").unwrap(),
+            keys: vec![selector!("Transfer")],
+            data: vec![FieldElement::ONE],
+        };
+        let block_hash = compute_block_hash(
+            &block_with_single_invoke(FieldElement::ZERO),
+            &[event.clone()],
+        );
+        let block = block_with_single_invoke(block_hash);
+        starknet_lightclient_mock
+            .expect_get_transaction_receipt()
+            .return_once(move |_| Ok(receipt_with_event(event)));
+
+        // When
+        let result = verify_block_hash(&starknet_lightclient_mock, &block).await;
+
+        // Then
+        // The events are returned along with the block they belong to.
+        let events = result.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(
+            events[0].from_address,
+            FieldElement::from_hex_be("0x42").unwrap()
+        );
+        assert_eq!(events[0].block_hash, block_hash);
+        assert_eq!(events[0].block_number, 10);
+    }
+
+    /// Test that a transaction of a retained block is answered without the provider.
+    #[tokio::test]
+    async fn given_retained_transaction_when_get_transaction_by_hash_then_answered_locally() {
+        // Given
+        let (config, ethereum_lightclient_mock, starknet_lightclient_mock) = mock_clients();
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+        let mut node = NodeData::new();
+        apply(
+            &mut node,
+            StoreCommand::VerifiedBlock {
+                block: Arc::new(block_with_single_invoke(FieldElement::from(0xabc_u64))),
+                events: Arc::new(vec![]),
+            },
+        );
+        beerus.node.store(Arc::new(node));
+
+        // When
+        let result = beerus
+            .get_transaction_by_hash(FieldElement::from_hex_be("0x1234").unwrap())
+            .await;
+
+        // Then
+        assert_eq!(
+            result.unwrap(),
+            block_with_single_invoke(FieldElement::ZERO).transactions[0]
+        );
+    }
+
+    /// Test that a transaction fetched from the provider is returned once its block is proven
+    /// on L1 and its hash recomputed, and rejected with a typed error before.
+    #[tokio::test]
+    async fn given_transaction_of_provider_when_get_transaction_by_hash_then_inclusion_is_verified()
+    {
+        for last_proven_block in [10, 9] {
+            // Given
+            let (config, mut ethereum_lightclient_mock, mut starknet_lightclient_mock) =
+                mock_clients();
+            let event = Event {
+                from_address: FieldElement::from_hex_be("0x42").unwrap(),
+                keys: vec![selector!("Transfer")],
+                data: vec![FieldElement::ONE],
+            };
+            let block_hash = compute_block_hash(
+                &block_with_single_invoke(FieldElement::ZERO),
+                &[event.clone()],
+            );
+            ethereum_lightclient_mock
+                .expect_starknet_last_proven_block()
+                .times(1)
+                .return_once(move || Ok(U256::from(last_proven_block)));
+            starknet_lightclient_mock
+                .expect_get_transaction_receipt()
+                .returning(move |_| Ok(receipt_with_event(event.clone())));
+            starknet_lightclient_mock
+                .expect_get_block_with_txs()
+                .withf(|block_id| *block_id == BlockId::Number(10))
+                .returning(move |_| {
+                    Ok(MaybePendingBlockWithTxs::Block(block_with_single_invoke(
+                        block_hash,
+                    )))
+                });
+            let beerus = BeerusLightClient::new(
+                config,
+                Box::new(ethereum_lightclient_mock),
+                Box::new(starknet_lightclient_mock),
+            );
+
+            // When
+            let result = beerus
+                .get_transaction_by_hash(FieldElement::from_hex_be("0x1234").unwrap())
+                .await;
+
+            // Then
+            match last_proven_block {
+                10 => assert_eq!(
+                    result.unwrap(),
+                    block_with_single_invoke(block_hash).transactions[0]
+                ),
+                _ => {
+                    let err = result.unwrap_err();
+                    let not_verified = err.downcast_ref::<TransactionNotVerified>().unwrap();
+                    assert_eq!(not_verified.block_number, Some(10));
+                    assert_eq!(
+                        err.to_string(),
+                        "Transaction 0x1234 of block 10 could not be verified: the block is not \
+                         proven on L1 yet, the last proven block is 9"
+                    );
+                }
+            }
+        }
+    }
+
+    /// Test that a block whose claimed hash does not match the recomputed one is rejected.
+    #[tokio::test]
+    async fn given_wrong_block_hash_when_verify_block_hash_then_should_return_error() {
+        // Given
+        let (_, _, mut starknet_lightclient_mock) = mock_clients();
+        let event = Event {
+            from_address: FieldElement::from_hex_be("0x42").unwrap(),
+            keys: vec![selector!("Transfer")],
+            data: vec![FieldElement::ONE],
+        };
+        let block = block_with_single_invoke(FieldElement::from_hex_be("0xbad").unwrap());
+        starknet_lightclient_mock
+            .expect_get_transaction_receipt()
+            .return_once(move |_| Ok(receipt_with_event(event)));
+
+        // When
+        let result = verify_block_hash(&starknet_lightclient_mock, &block).await;
+
+        // Then
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .starts_with("Block hash mismatch for block 10"));
+    }
+
+    /// Test that the status of a fresh light client reports no block hash check yet.
+    #[tokio::test]
+    async fn given_not_started_when_call_beerus_status_then_should_return_not_synced() {
+        // Given
+        let (config, ethereum_lightclient_mock, mut starknet_lightclient_mock) = mock_clients();
+        starknet_lightclient_mock
+            .expect_provider_health()
+            .times(1)
+            .returning(Vec::new);
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+
+        // When
+        let status = beerus.beerus_status().await;
+
+        // Then
+        assert_eq!(status.sync_status, SyncStatus::NotSynced);
+        assert_eq!(status.block_number, 0);
+        assert_eq!(status.last_block_hash_check, None);
+        assert!(status.providers.is_empty());
+    }
+
+    /// Test that the configured finality sets the default target of the queries and is
+    /// reported by the status, and that the block tags still override it.
+    #[tokio::test]
+    async fn given_l2_accepted_finality_when_call_beerus_status_then_finality_is_reported() {
+        // Given
+        let (mut config, ethereum_lightclient_mock, mut starknet_lightclient_mock) = mock_clients();
+        config.finality = Finality::L2Accepted;
+        starknet_lightclient_mock
+            .expect_provider_health()
+            .returning(Vec::new);
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+
+        // When
+        let status = beerus.beerus_status().await;
+
+        // Then
+        assert_eq!(status.finality, Finality::L2Accepted);
+        assert_eq!(beerus.default_target(), BlockTarget::Latest);
+        assert_eq!(
+            BlockTarget::from_str("l1_accepted").unwrap(),
+            BlockTarget::L1Accepted
+        );
+        assert_eq!(
+            BlockTarget::from_str("l2_accepted").unwrap(),
+            BlockTarget::Latest
+        );
+        assert_eq!(
+            serde_json::to_value(status).unwrap()["finality"],
+            "l2_accepted"
+        );
+    }
+
+    /// Test that the sync progress reported by the syncer is exposed by the status.
+    #[tokio::test]
+    async fn given_sync_progress_when_call_beerus_status_then_should_return_eta() {
+        // Given
+        let (config, ethereum_lightclient_mock, mut starknet_lightclient_mock) = mock_clients();
+        starknet_lightclient_mock
+            .expect_provider_health()
+            .returning(Vec::new);
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+        let mut node = NodeData::new();
+        // 200 blocks and 10 L1 blocks in 20 seconds, 1000 blocks left.
+        apply(
+            &mut node,
+            StoreCommand::SyncProgress(SyncProgress::measure(20_000, 200, 10, 1_000)),
+        );
+        beerus.node.store(Arc::new(node));
+
+        // When
+        let status = beerus.beerus_status().await;
+
+        // Then
+        assert_eq!(status.sync_progress.blocks_per_sec, 10.0);
+        assert_eq!(status.sync_progress.l1_slots_per_sec, 0.5);
+        assert_eq!(status.sync_progress.blocks_behind, 1_000);
+        assert_eq!(status.sync_progress.eta_secs, Some(100));
+        // No estimate without progress, none needed once caught up.
+        assert_eq!(SyncProgress::measure(20_000, 0, 0, 1_000).eta_secs, None);
+        assert_eq!(SyncProgress::measure(20_000, 0, 0, 0).eta_secs, Some(0));
+    }
+
+    /// Test that `LogStateUpdate` events are recorded as proven state roots.
+    #[tokio::test]
+    async fn given_normal_conditions_when_sync_proven_state_roots_then_roots_are_recorded() {
+        // Given
+        let (config, mut ethereum_lightclient_mock, starknet_lightclient_mock) = mock_clients();
+        let abi: ethers::abi::Abi =
+            serde_json::from_str(include_str!("../src/resources/starknet_core_abi.json")).unwrap();
+        let topic = abi.event("LogStateUpdate").unwrap().signature();
+        let log = Log {
+            topics: vec![topic],
+            data: ethers::abi::encode(&[
+                ethers::abi::Token::Uint(U256::from(0x1234)),
+                ethers::abi::Token::Int(U256::from(42)),
+            ])
+            .into(),
+            ..Default::default()
+        };
+        ethereum_lightclient_mock
+            .expect_get_logs()
+            .return_once(move |_, _, _, _, _| Ok(vec![log]));
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+
+        // When
+        let count = beerus
+            .starknet_sync_proven_state_roots(100, 200)
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(count, 1);
+        assert_eq!(
+            beerus.starknet_proven_state_root(42).await,
+            Some(FieldElement::from_hex_be("0x1234").unwrap())
+        );
+        assert_eq!(beerus.starknet_proven_state_root(43).await, None);
+    }
+
+    /// Test that traces are relayed and marked as unverified.
+    #[tokio::test]
+    async fn given_normal_conditions_when_trace_transaction_then_result_is_unverified() {
+        // Given
+        let (config, ethereum_lightclient_mock, mut starknet_lightclient_mock) = mock_clients();
+        let trace = serde_json::json!({"function_invocation": {"calldata": []}});
+        let expected_trace = trace.clone();
+        starknet_lightclient_mock
+            .expect_trace_transaction()
+            .return_once(move |_| Ok(trace));
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+
+        // When
+        let result = beerus
+            .starknet_trace_transaction(FieldElement::ONE)
+            .await
+            .unwrap();
+
+        // Then
+        assert!(!result.verified);
+        assert_eq!(result.result, expected_trace);
+    }
+
+    /// Test that provider errors are returned when simulating transactions.
+    #[tokio::test]
+    async fn given_starknet_lightclient_error_when_simulate_transactions_then_should_return_error()
+    {
+        // Given
+        let (config, ethereum_lightclient_mock, mut starknet_lightclient_mock) = mock_clients();
+        starknet_lightclient_mock
+            .expect_simulate_transactions()
+            .return_once(move |_, _, _| Err(eyre!("Method not found")));
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+
+        // When
+        let result = beerus
+            .starknet_simulate_transactions(
+                &BlockId::Tag(starknet::providers::jsonrpc::models::BlockTag::Latest),
+                vec![],
+                vec![SimulationFlag::SkipValidate],
+            )
+            .await;
+
+        // Then
+        assert_eq!(result.unwrap_err().to_string(), "Method not found");
+    }
+
+    /// Test that bulk fee estimation returns one estimate per transaction.
+    #[tokio::test]
+    async fn given_normal_conditions_when_estimate_fee_bulk_then_should_return_ok() {
+        // Given
+        let (config, ethereum_lightclient_mock, mut starknet_lightclient_mock) = mock_clients();
+        let estimate = || FeeEstimate {
+            gas_consumed: 5194,
+            gas_price: 25886605195,
+            overall_fee: 134455027382830,
+        };
+        let expected = serde_json::to_string(&vec![estimate(), estimate()]).unwrap();
+        starknet_lightclient_mock
+            .expect_estimate_fee_bulk()
+            .return_once(move |_, _, _| Ok(vec![estimate(), estimate()]));
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+        let tx = create_mock_broadcasted_transaction();
+
+        // When
+        let result = beerus
+            .starknet_estimate_fee_bulk(
+                vec![tx.0.clone(), tx.0],
+                vec![SimulationFlag::SkipValidate],
+                &BlockId::Number(10),
+            )
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(serde_json::to_string(&result).unwrap(), expected);
+    }
+
+    /// Test that a provider returning the wrong number of estimates is rejected.
+    #[tokio::test]
+    async fn given_missing_estimates_when_estimate_fee_bulk_then_should_return_error() {
+        // Given
+        let (config, ethereum_lightclient_mock, mut starknet_lightclient_mock) = mock_clients();
+        starknet_lightclient_mock
+            .expect_estimate_fee_bulk()
+            .return_once(move |_, _, _| Ok(vec![]));
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+        let tx = create_mock_broadcasted_transaction();
+
+        // When
+        let result = beerus
+            .starknet_estimate_fee_bulk(vec![tx.0], vec![], &BlockId::Number(10))
+            .await;
+
+        // Then
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Expected 1 fee estimates, got 0"
+        );
+    }
+
+    fn mainnet_proof() -> beerus_core::lightclient::starknet::storage_proof::GetProofOutput {
+        let json: serde_json::Value = serde_json::from_str(include_str!("data.json")).unwrap();
+        serde_json::from_value(json["result"].clone()).unwrap()
+    }
+
+    fn mock_mainnet_l1_state(
+        ethereum_lightclient_mock: &mut beerus_core::lightclient::ethereum::MockEthereumLightClient,
+    ) {
+        let state_root = U256::from_str_radix(
+            "47f25798a804800b657d4e1508776e3c3c70f0d7587d125a558208f88570aa7",
+            16,
+        )
+        .unwrap();
+        ethereum_lightclient_mock
+            .expect_starknet_last_proven_block()
+            .return_once(move || Ok(U256::from(10)));
+        ethereum_lightclient_mock
+            .expect_starknet_state_root()
+            .return_once(move || Ok(state_root));
+        ethereum_lightclient_mock
+            .expect_starknet_proven_state()
+            .returning(move || Ok((U256::from(10), state_root)));
+    }
+
+    /// Test that a storage value backed by a valid proof is marked as verified.
+    #[tokio::test]
+    async fn given_valid_proof_when_get_storage_at_with_metadata_then_result_is_verified() {
+        // Given
+        let (config, mut ethereum_lightclient_mock, mut starknet_lightclient_mock) = mock_clients();
+        mock_mainnet_l1_state(&mut ethereum_lightclient_mock);
+        starknet_lightclient_mock
+            .expect_get_storage_at()
+            .return_once(move |_, _, _| Ok(FieldElement::ZERO));
+        starknet_lightclient_mock
+            .expect_get_contract_storage_proof()
+            .return_once(move |_, _, _| Ok(mainnet_proof()));
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+        let contract_address = FieldElement::from_hex_be(
+            "0x4d4e07157aeb54abeb64f5792145f2e8db1c83bda01a8f06e050be18cfb8153",
+        )
+        .unwrap();
+
+        // When
+        let result = beerus
+            .starknet_get_storage_at_with_metadata(
+                contract_address,
+                FieldElement::ONE,
+                BlockTarget::L1Accepted,
+            )
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(result.result, FieldElement::ZERO);
+        assert_eq!(result.metadata.proven_block_number, 10);
+        assert!(result.metadata.verified);
+    }
+
+    /// Test that a storage value contradicting its proof is rejected.
+    #[tokio::test]
+    async fn given_invalid_value_when_get_storage_at_with_metadata_then_should_return_error() {
+        // Given
+        let (config, mut ethereum_lightclient_mock, mut starknet_lightclient_mock) = mock_clients();
+        mock_mainnet_l1_state(&mut ethereum_lightclient_mock);
+        starknet_lightclient_mock
+            .expect_get_storage_at()
+            .return_once(move |_, _, _| Ok(FieldElement::TWO));
+        starknet_lightclient_mock
+            .expect_get_contract_storage_proof()
+            .return_once(move |_, _, _| Ok(mainnet_proof()));
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+        let contract_address = FieldElement::from_hex_be(
+            "0x4d4e07157aeb54abeb64f5792145f2e8db1c83bda01a8f06e050be18cfb8153",
+        )
+        .unwrap();
+
+        // When
+        let result = beerus
+            .starknet_get_storage_at_with_metadata(
+                contract_address,
+                FieldElement::ONE,
+                BlockTarget::L1Accepted,
+            )
+            .await;
+
+        // Then
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Storage proof does not match value 2 at block 10"
+        );
+    }
+
+    /// Test that calls are always marked as unverified.
+    #[tokio::test]
+    async fn given_normal_conditions_when_call_contract_with_metadata_then_result_is_unverified() {
+        // Given
+        let (config, mut ethereum_lightclient_mock, mut starknet_lightclient_mock) = mock_clients();
+        mock_mainnet_l1_state(&mut ethereum_lightclient_mock);
+        starknet_lightclient_mock
+            .expect_call()
+            .return_once(move |_, _| Ok(vec![FieldElement::ONE]));
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+
+        // When
+        let result = beerus
+            .starknet_call_contract_with_metadata(
+                FieldElement::ONE,
+                selector!("balanceOf"),
+                vec![],
+                BlockTarget::L1Accepted,
+            )
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(result.result, vec![FieldElement::ONE]);
+        assert!(!result.metadata.verified);
+    }
+
+    /// Test the L1 proven block number and state root getters.
+    #[tokio::test]
+    async fn given_normal_conditions_when_get_l1_proven_state_then_should_return_ok() {
+        // Given
+        let (config, mut ethereum_lightclient_mock, starknet_lightclient_mock) = mock_clients();
+        mock_mainnet_l1_state(&mut ethereum_lightclient_mock);
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+
+        // When
+        let block_number = beerus.starknet_l1_proven_block_number().await.unwrap();
+        let state_root = beerus.starknet_l1_state_root().await.unwrap();
+
+        // Then
+        assert_eq!(block_number, 10);
+        assert_eq!(
+            state_root,
+            FieldElement::from_hex_be(
+                "0x47f25798a804800b657d4e1508776e3c3c70f0d7587d125a558208f88570aa7"
+            )
+            .unwrap()
+        );
+    }
+
+    /// Test that the L1 state root getter returns the Ethereum light client error.
+    #[tokio::test]
+    async fn given_ethereum_lightclient_error_when_get_l1_state_root_then_should_return_error() {
+        // Given
+        let (config, mut ethereum_lightclient_mock, starknet_lightclient_mock) = mock_clients();
+        ethereum_lightclient_mock
+            .expect_starknet_state_root()
+            .return_once(move || Err(eyre!("ethereum_lightclient_error")));
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+
+        // When
+        let result = beerus.starknet_l1_state_root().await;
+
+        // Then
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "ethereum_lightclient_error"
+        );
+    }
+
+    /// Test that the proven block number and state root are read at the same L1 block.
+    #[tokio::test]
+    async fn given_new_l1_blocks_when_read_proven_state_then_both_reads_are_pinned() {
+        // Given
+        let (_, mut ethereum_lightclient_mock, _) = mock_clients();
+        ethereum_lightclient_mock
+            .expect_get_block_number()
+            .times(1)
+            .return_once(|| Ok(101));
+        ethereum_lightclient_mock
+            .expect_call()
+            .times(2)
+            .withf(|_, block| matches!(block, BlockTag::Number(101)))
+            .returning(|opts, _| {
+                let word = match opts.data.as_deref() == Some(&ethers::utils::id("stateRoot()")[..])
+                {
+                    true => U256::from(0x123),
+                    false => U256::from(10),
+                };
+                let mut bytes = [0u8; 32];
+                word.to_big_endian(&mut bytes);
+                Ok(bytes.to_vec())
+            });
+
+        // When
+        let proven_state = read_starknet_proven_state(&ethereum_lightclient_mock, Address::zero())
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(proven_state, (U256::from(10), U256::from(0x123)));
+    }
+
+    /// Test that the store task adds verified blocks to the payload.
+    #[test]
+    fn given_verified_block_when_apply_then_should_add_block_to_payload() {
+        // Given
+        let mut node = NodeData::new();
+        let block = block_with_single_invoke(FieldElement::from_hex_be("0xabc").unwrap());
+
+        // When
+        apply(
+            &mut node,
+            StoreCommand::VerifiedBlock {
+                block: Arc::new(block),
+                events: Arc::new(vec![]),
+            },
+        );
+
+        // Then
+        assert_eq!(node.block_number, 10);
+        assert!(node.payload.contains_key(&10));
+        assert!(node.events.contains_key(&10));
+        assert!(node.last_block_hash_check.unwrap().valid);
+    }
+
+    /// Test that a backfilled block older than the head is retained without moving the head back.
+    #[test]
+    fn given_newer_block_when_apply_older_block_then_head_should_not_move_back() {
+        // Given
+        let mut node = NodeData::new();
+        let mut newer = block_with_single_invoke(FieldElement::from_hex_be("0xabc").unwrap());
+        newer.block_number = 12;
+        newer.new_root = FieldElement::from_hex_be("0x12").unwrap();
+        apply(
+            &mut node,
+            StoreCommand::VerifiedBlock {
+                block: Arc::new(newer),
+                events: Arc::new(vec![]),
+            },
+        );
+        let older = block_with_single_invoke(FieldElement::from_hex_be("0xdef").unwrap());
+
+        // When
+        apply(
+            &mut node,
+            StoreCommand::VerifiedBlock {
+                block: Arc::new(older),
+                events: Arc::new(vec![]),
+            },
+        );
+
+        // Then
+        assert_eq!(node.block_number, 12);
+        assert_eq!(
+            node.state_root,
+            FieldElement::from_hex_be("0x12").unwrap().to_string()
+        );
+        assert!(node.payload.contains_key(&10));
+        assert!(node.payload.contains_key(&12));
+    }
+
+    /// Test that applying an update to a snapshot only copies the maps the update changes.
+    #[test]
+    fn given_snapshot_when_apply_proven_state_root_then_other_maps_are_shared() {
+        // Given
+        let mut snapshot = NodeData::new();
+        apply(
+            &mut snapshot,
+            StoreCommand::VerifiedBlock {
+                block: Arc::new(block_with_single_invoke(
+                    FieldElement::from_hex_be("0xabc").unwrap(),
+                )),
+                events: Arc::new(vec![]),
+            },
+        );
+
+        // When
+        let mut next = snapshot.clone();
+        apply(
+            &mut next,
+            StoreCommand::ProvenStateRoot {
+                block_number: 10,
+                state_root: FieldElement::ONE,
+            },
+        );
+
+        // Then
+        assert!(snapshot.proven_state_roots.is_empty());
+        assert_eq!(next.proven_state_roots.get(&10), Some(&FieldElement::ONE));
+        assert!(Arc::ptr_eq(&snapshot.events, &next.events));
+        assert!(Arc::ptr_eq(&snapshot.event_index, &next.event_index));
+        assert!(Arc::ptr_eq(
+            &snapshot.contract_deployments,
+            &next.contract_deployments
+        ));
+    }
+
+    /// Test that the updates repeated by the syncer are detected as not changing the node data.
+    #[test]
+    fn given_known_proven_state_root_when_changes_node_then_should_return_false() {
+        // Given
+        let mut node = NodeData::new();
+        Arc::make_mut(&mut node.proven_state_roots).insert(10, FieldElement::ONE);
+
+        // When
+        let known = changes_node(
+            &node,
+            &StoreCommand::ProvenStateRoot {
+                block_number: 10,
+                state_root: FieldElement::ONE,
+            },
+        );
+        let new = changes_node(
+            &node,
+            &StoreCommand::ProvenStateRoot {
+                block_number: 11,
+                state_root: FieldElement::TWO,
+            },
+        );
+        let same_progress = changes_node(
+            &node,
+            &StoreCommand::SyncProgress(node.sync_progress.clone()),
+        );
+
+        // Then
+        assert!(!known);
+        assert!(new);
+        assert!(!same_progress);
+    }
+
+    /// Test that the store task only records the check of a rejected block.
+    #[test]
+    fn given_rejected_block_when_apply_then_should_not_add_block_to_payload() {
+        // Given
+        let mut node = NodeData::new();
+        let check = BlockHashCheck {
+            block_number: 10,
+            block_hash: FieldElement::from_hex_be("0xbad").unwrap(),
+            valid: false,
+            error: Some("Block hash mismatch".to_string()),
+        };
+
+        // When
+        apply(&mut node, StoreCommand::RejectedBlock(check.clone()));
+
+        // Then
+        assert_eq!(node.block_number, 0);
+        assert!(node.payload.is_empty());
+        assert_eq!(node.last_block_hash_check, Some(check));
+    }
+
+    /// Test that several storage keys are read at the same proven block, in order.
+    #[tokio::test]
+    async fn given_proof_unavailable_when_get_storage_at_many_then_results_are_unverified() {
+        // Given
+        let (config, mut ethereum_lightclient_mock, mut starknet_lightclient_mock) = mock_clients();
+        mock_mainnet_l1_state(&mut ethereum_lightclient_mock);
+        starknet_lightclient_mock
+            .expect_get_storage_at()
+            .times(2)
+            .returning(|_, key, block_number| {
+                assert_eq!(block_number, 10);
+                Ok(key + FieldElement::ONE)
+            });
+        starknet_lightclient_mock
+            .expect_get_contract_storage_proof()
+            .return_once(move |_, _, _| Err(eyre!("Method not found")));
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+
+        // When
+        let result = beerus
+            .starknet_get_storage_at_many(
+                FieldElement::ONE,
+                vec![FieldElement::ONE, FieldElement::TWO],
+                BlockTarget::L1Accepted,
+            )
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(result.result, vec![FieldElement::TWO, FieldElement::THREE]);
+        assert_eq!(result.metadata.proven_block_number, 10);
+        assert!(!result.metadata.verified);
+    }
+
+    /// Test that the last block proven on L1 is returned before the first block is fetched.
+    #[tokio::test]
+    async fn given_empty_payload_when_get_block_hash_and_number_then_returns_l1_proven_block() {
+        // Given
+        let (config, mut ethereum_lightclient_mock, starknet_lightclient_mock) = mock_clients();
+        ethereum_lightclient_mock
+            .expect_starknet_last_proven_block()
+            .return_once(move || Ok(U256::from(10)));
+        ethereum_lightclient_mock
+            .expect_starknet_last_proven_block_hash()
+            .return_once(move || Ok(U256::from(0x1234)));
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+
+        // When
+        let result = beerus.get_block_hash_and_number().await.unwrap();
+
+        // Then
+        assert_eq!(result.block_number, 10);
+        assert_eq!(result.block_hash, FieldElement::from(0x1234_u64));
+    }
+
+    /// Test that in header-only mode the payload keeps the headers of the verified blocks, which
+    /// answer the block hash queries, while the blocks are fetched and checked against them.
+    #[tokio::test]
+    async fn given_headers_only_payload_when_query_blocks_then_checked_against_headers() {
+        // Given
+        let (mut config, ethereum_lightclient_mock, mut starknet_lightclient_mock) = mock_clients();
+        config.payload_headers_only = true;
+        let verified_hash = compute_block_hash(&block_with_single_invoke(FieldElement::ZERO), &[]);
+        starknet_lightclient_mock
+            .expect_get_block_with_txs()
+            .times(2)
+            .returning(move |block_id| {
+                let block_hash = match block_id {
+                    BlockId::Number(10) => verified_hash,
+                    _ => FieldElement::from(0xbad_u64),
+                };
+                Ok(MaybePendingBlockWithTxs::Block(block_with_single_invoke(
+                    block_hash,
+                )))
+            });
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+        let mut node = NodeData::clone(&beerus.node.load());
+        apply(
+            &mut node,
+            StoreCommand::VerifiedBlock {
+                block: Arc::new(block_with_single_invoke(verified_hash)),
+                events: Arc::new(vec![]),
+            },
+        );
+        beerus.node.store(Arc::new(node));
+
+        // When
+        let hash_and_number = beerus.get_block_hash_and_number().await.unwrap();
+        let fetched = beerus.get_block_with_txs(&BlockId::Number(10)).await;
+        let forged = beerus.get_block_with_txs(&BlockId::Number(11)).await;
+
+        // Then
+        let node = beerus.node.load();
+        assert!(node.block(&BlockId::Number(10)).is_none());
+        assert!(node.events.is_empty());
+        assert_eq!(node.payload.get_header(&10).unwrap().transaction_count, 1);
+        assert_eq!(hash_and_number.block_hash, verified_hash);
+        assert_eq!(hash_and_number.block_number, 10);
+        assert!(fetched.is_ok());
+        assert!(forged.unwrap_err().to_string().contains("was verified"));
+    }
+
+    /// Test that storage read at the latest block is not checked against the L1 state.
+    #[tokio::test]
+    async fn given_latest_target_when_get_storage_at_with_metadata_then_reads_l2_head_unverified() {
+        // Given
+        let (config, mut ethereum_lightclient_mock, mut starknet_lightclient_mock) = mock_clients();
+        mock_mainnet_l1_state(&mut ethereum_lightclient_mock);
+        starknet_lightclient_mock
+            .expect_get_storage_at()
+            .return_once(move |_, _, block_number| {
+                assert_eq!(block_number, 12);
+                Ok(FieldElement::TWO)
+            });
+        starknet_lightclient_mock
+            .expect_get_contract_storage_proof()
+            .never();
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+        let mut block = block_with_single_invoke(FieldElement::from_hex_be("0xabc").unwrap());
+        block.block_number = 12;
+        let mut node = NodeData::new();
+        apply(
+            &mut node,
+            StoreCommand::VerifiedBlock {
+                block: Arc::new(block),
+                events: Arc::new(vec![]),
+            },
+        );
+        beerus.node.store(Arc::new(node));
+
+        // When
+        let result = beerus
+            .starknet_get_storage_at_with_metadata(
+                FieldElement::ONE,
+                FieldElement::ONE,
+                BlockTarget::Latest,
+            )
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(result.result, FieldElement::TWO);
+        assert_eq!(result.metadata.block_number, 12);
+        assert_eq!(result.metadata.proven_block_number, 10);
+        assert!(!result.metadata.verified);
+    }
+
+    /// Test that the `l1_accepted` tag resolves to the last block proven on L1.
+    #[tokio::test]
+    async fn given_l1_accepted_tag_when_resolve_block_id_then_returns_proven_block_number() {
+        // Given
+        let (config, mut ethereum_lightclient_mock, starknet_lightclient_mock) = mock_clients();
+        mock_mainnet_l1_state(&mut ethereum_lightclient_mock);
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+
+        // When
+        let block_id = beerus.resolve_block_id("tag", "l1_accepted").await.unwrap();
+
+        // Then
+        assert_eq!(block_id, BlockId::Number(10));
+    }
+
+    /// Test that Ethereum storage reads are forwarded to the Ethereum light client with the
+    /// requested block.
+    #[tokio::test]
+    async fn given_block_number_when_ethereum_get_storage_at_then_reads_slot_at_block() {
+        // Given
+        let (config, mut ethereum_lightclient_mock, starknet_lightclient_mock) = mock_clients();
+        let slot = H256::from_low_u64_be(3);
+        ethereum_lightclient_mock
+            .expect_get_storage_at()
+            .return_once(move |_, requested_slot, block| {
+                assert_eq!(requested_slot, slot);
+                assert!(matches!(block, BlockTag::Number(16_000_000)));
+                Ok(U256::from(42))
+            });
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+        let address = Address::from_str("0xc24215226336d22238a20a72f8e489c005b44c4a").unwrap();
+
+        // When
+        let result = beerus
+            .ethereum_get_storage_at(&address, slot, BlockTag::Number(16_000_000))
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(result, U256::from(42));
+    }
+
+    /// Test that raw Ethereum transactions are broadcast through the Ethereum light client.
+    #[tokio::test]
+    async fn given_signed_transaction_when_ethereum_send_raw_transaction_then_returns_hash() {
+        // Given
+        let (config, mut ethereum_lightclient_mock, starknet_lightclient_mock) = mock_clients();
+        let tx_hash = H256::from_low_u64_be(0xabc);
+        ethereum_lightclient_mock
+            .expect_send_raw_transaction()
+            .return_once(move |bytes| {
+                assert_eq!(bytes, &[0x02, 0xf8]);
+                Ok(tx_hash)
+            });
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+
+        // When
+        let result = beerus
+            .ethereum_send_raw_transaction(&[0x02, 0xf8])
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(result, tx_hash);
+    }
+
+    /// Test that a ready L2 to L1 message comes with its consumption calldata and gas estimate.
+    #[tokio::test]
+    async fn given_ready_message_when_prepare_l2_to_l1_message_consumption_then_estimates_gas() {
+        // Given
+        let (config, mut ethereum_lightclient_mock, starknet_lightclient_mock) = mock_clients();
+        let message = L2ToL1Message {
+            from_address: FieldElement::from_hex_be("0x123").unwrap(),
+            to_address: Address::from_low_u64_be(0x456),
+            payload: vec![FieldElement::ONE],
+        };
+        ethereum_lightclient_mock
+            .expect_call()
+            .return_once(|_, _| Ok(ethers_helper::u256_to_bytes32_slice(U256::one()).to_vec()));
+        ethereum_lightclient_mock
+            .expect_estimate_gas()
+            .return_once(move |opts| {
+                assert_eq!(opts.from, Some(Address::from_low_u64_be(0x456)));
+                Ok(42_000)
+            });
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+
+        // When
+        let consumption = beerus
+            .prepare_l2_to_l1_message_consumption(&message, true)
+            .await
+            .unwrap();
+
+        // Then
+        assert!(consumption.ready);
+        assert_eq!(consumption.message_hash, message.hash());
+        assert_eq!(consumption.to, beerus.starknet_core_contract_address);
+        assert_eq!(
+            consumption.calldata,
+            message
+                .consume_calldata(beerus.starknet_core_abi.clone())
+                .unwrap()
+        );
+        assert_eq!(consumption.gas_estimate, Some(42_000));
+    }
+
+    /// Test that no gas is estimated for a message which is not proven on L1 yet.
+    #[tokio::test]
+    async fn given_pending_message_when_prepare_l2_to_l1_message_consumption_then_not_ready() {
+        // Given
+        let (config, mut ethereum_lightclient_mock, starknet_lightclient_mock) = mock_clients();
+        let message = L2ToL1Message {
+            from_address: FieldElement::from_hex_be("0x123").unwrap(),
+            to_address: Address::from_low_u64_be(0x456),
+            payload: vec![],
+        };
+        ethereum_lightclient_mock
+            .expect_call()
+            .return_once(|_, _| Ok(vec![0; 32]));
+        ethereum_lightclient_mock.expect_estimate_gas().never();
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+
+        // When
+        let consumption = beerus
+            .prepare_l2_to_l1_message_consumption(&message, true)
+            .await
+            .unwrap();
+
+        // Then
+        assert!(!consumption.ready);
+        assert_eq!(consumption.gas_estimate, None);
+    }
+
+    /// Test that an L1 to L2 message is prepared with the current nonce of the core contract.
+    #[tokio::test]
+    async fn given_valid_fee_when_prepare_l1_to_l2_message_then_uses_current_nonce() {
+        // Given
+        let (config, mut ethereum_lightclient_mock, starknet_lightclient_mock) = mock_clients();
+        ethereum_lightclient_mock
+            .expect_call()
+            .return_once(|_, _| Ok(ethers_helper::u256_to_bytes32_slice(U256::from(5)).to_vec()));
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+
+        // When
+        let deposit = beerus
+            .prepare_l1_to_l2_message(
+                Address::from_low_u64_be(0x456),
+                FieldElement::from_hex_be("0x123").unwrap(),
+                selector!("handle_deposit"),
+                vec![FieldElement::ONE],
+                U256::from(1_000),
+            )
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(deposit.message.nonce, U256::from(5));
+        assert_eq!(deposit.message_hash, deposit.message.hash());
+        assert_eq!(deposit.value, U256::from(1_000));
+        assert_eq!(deposit.to, beerus.starknet_core_contract_address);
+    }
+
+    /// Test that an L1 to L2 message without fee is rejected, as the core contract would.
+    #[tokio::test]
+    async fn given_zero_fee_when_prepare_l1_to_l2_message_then_should_fail() {
+        // Given
+        let (config, mut ethereum_lightclient_mock, starknet_lightclient_mock) = mock_clients();
+        ethereum_lightclient_mock.expect_call().never();
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+
+        // When
+        let result = beerus
+            .prepare_l1_to_l2_message(
+                Address::from_low_u64_be(0x456),
+                FieldElement::from_hex_be("0x123").unwrap(),
+                selector!("handle_deposit"),
+                vec![],
+                U256::zero(),
+            )
+            .await;
+
+        // Then
+        assert!(result.is_err());
+    }
+
+    /// Test that the delivery of an L1 to L2 message is found in the synced blocks.
+    #[tokio::test]
+    async fn given_synced_l1_handler_when_find_l1_to_l2_message_delivery_then_returns_it() {
+        // Given
+        let (config, ethereum_lightclient_mock, starknet_lightclient_mock) = mock_clients();
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+        let message = L1ToL2Message {
+            from_address: Address::from_low_u64_be(0x456),
+            to_address: FieldElement::from_hex_be("0x123").unwrap(),
+            selector: selector!("handle_deposit"),
+            payload: vec![FieldElement::ONE],
+            nonce: U256::from(5),
+        };
+        let mut block = block_with_single_invoke(FieldElement::from_hex_be("0xabc").unwrap());
+        block
+            .transactions
+            .push(StarknetTransaction::L1Handler(L1HandlerTransaction {
+                transaction_hash: FieldElement::from_hex_be("0xdef").unwrap(),
+                version: 0,
+                nonce: 5,
+                contract_address: message.to_address,
+                entry_point_selector: message.selector,
+                calldata: vec![
+                    FieldElement::from_hex_be("0x456").unwrap(),
+                    FieldElement::ONE,
+                ],
+            }));
+        let mut node = NodeData::new();
+        apply(
+            &mut node,
+            StoreCommand::VerifiedBlock {
+                block: Arc::new(block),
+                events: Arc::new(vec![]),
+            },
+        );
+        beerus.node.store(Arc::new(node));
+
+        // When
+        let delivery = beerus.find_l1_to_l2_message_delivery(&message);
+
+        // Then
+        assert_eq!(
+            delivery,
+            Some(L1ToL2MessageDelivery {
+                block_number: 10,
+                transaction_hash: FieldElement::from_hex_be("0xdef").unwrap(),
+            })
+        );
+    }
+
+    /// Test that the messages sent by an L1 transaction are decoded from its logs and matched
+    /// with the L1 handler delivering them in the synced blocks.
+    #[tokio::test]
+    async fn given_l1_transaction_when_get_l1_to_l2_message_status_then_returns_delivery() {
+        // Given
+        let (config, mut ethereum_lightclient_mock, mut starknet_lightclient_mock) = mock_clients();
+        let l1_tx_hash = H256::from_low_u64_be(0x1);
+        let block_hash = H256::from_low_u64_be(0x2);
+        ethereum_lightclient_mock
+            .expect_get_transaction_by_hash()
+            .return_once(move |_| {
+                Ok(Some(Transaction {
+                    hash: l1_tx_hash,
+                    block_hash: Some(block_hash),
+                    ..Default::default()
+                }))
+            });
+        let abi: ethers::abi::Abi =
+            serde_json::from_str(include_str!("../src/resources/starknet_core_abi.json")).unwrap();
+        let log = Log {
+            topics: vec![
+                abi.event("LogMessageToL2").unwrap().signature(),
+                H256::from(Address::from_low_u64_be(0x456)),
+                H256::from_low_u64_be(0x123),
+                H256::from_slice(&selector!("handle_deposit").to_bytes_be()),
+            ],
+            data: ethers::abi::encode(&[
+                ethers::abi::Token::Array(vec![ethers::abi::Token::Uint(U256::one())]),
+                ethers::abi::Token::Uint(U256::from(5)),
+                ethers::abi::Token::Uint(U256::zero()),
+            ])
+            .into(),
+            block_hash: Some(block_hash),
+            transaction_hash: Some(l1_tx_hash),
+            ..Default::default()
+        };
+        let other_log = Log {
+            transaction_hash: Some(H256::from_low_u64_be(0x3)),
+            ..log.clone()
+        };
+        ethereum_lightclient_mock
+            .expect_get_logs()
+            .withf(move |_, _, _, _, hash| hash == &Some(format!("{block_hash:?}")))
+            .return_once(move |_, _, _, _, _| Ok(vec![other_log, log]));
+        starknet_lightclient_mock
+            .expect_chain_id()
+            .return_once(|| Ok(FieldElement::from_byte_slice_be(b"SN_GOERLI").unwrap()));
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+        let message = L1ToL2Message {
+            from_address: Address::from_low_u64_be(0x456),
+            to_address: FieldElement::from_hex_be("0x123").unwrap(),
+            selector: selector!("handle_deposit"),
+            payload: vec![FieldElement::ONE],
+            nonce: U256::from(5),
+        };
+        let mut block = block_with_single_invoke(FieldElement::from_hex_be("0xabc").unwrap());
+        block
+            .transactions
+            .push(StarknetTransaction::L1Handler(L1HandlerTransaction {
+                transaction_hash: FieldElement::from_hex_be("0xdef").unwrap(),
+                version: 0,
+                nonce: 5,
+                contract_address: message.to_address,
+                entry_point_selector: message.selector,
+                calldata: vec![
+                    FieldElement::from_hex_be("0x456").unwrap(),
+                    FieldElement::ONE,
+                ],
+            }));
+        let mut node = NodeData::new();
+        apply(
+            &mut node,
+            StoreCommand::VerifiedBlock {
+                block: Arc::new(block),
+                events: Arc::new(vec![]),
+            },
+        );
+        beerus.node.store(Arc::new(node));
+
+        // When
+        let statuses = beerus
+            .get_l1_to_l2_message_status(l1_tx_hash)
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(statuses.len(), 1);
+        assert_eq!(statuses[0].message, message);
+        assert_eq!(statuses[0].message_hash, message.hash());
+        assert_eq!(statuses[0].l1_transaction_hash, l1_tx_hash);
+        assert_eq!(
+            statuses[0].delivery,
+            Some(L1ToL2MessageDelivery {
+                block_number: 10,
+                transaction_hash: FieldElement::from_hex_be("0xdef").unwrap(),
+            })
+        );
+    }
+
+    /// Test that pruning keeps the last synchronized block and the last proven state root.
+    #[test]
+    fn given_blocks_when_prune_to_then_should_keep_last_block_and_proven_state_root() {
+        // Given
+        let mut node = NodeData::new();
+        for block_number in 10..13 {
+            let mut block = block_with_single_invoke(FieldElement::from(0xabc_u64 + block_number));
+            block.block_number = block_number;
+            apply(
+                &mut node,
+                StoreCommand::VerifiedBlock {
+                    block: Arc::new(block),
+                    events: Arc::new(vec![]),
+                },
+            );
+        }
+        Arc::make_mut(&mut node.proven_state_roots).insert(5, FieldElement::ONE);
+        Arc::make_mut(&mut node.proven_state_roots).insert(8, FieldElement::TWO);
+
+        // When
+        let pruned = node.prune_to(20);
+
+        // Then
+        assert_eq!(pruned, 2);
+        let stats = node.retention_stats();
+        assert_eq!(stats.first_block_number, Some(12));
+        assert_eq!(stats.last_block_number, Some(12));
+        assert_eq!(stats.event_blocks, 1);
+        assert_eq!(stats.proven_state_roots, 1);
+        assert_eq!(node.proven_state_roots.get(&8), Some(&FieldElement::TWO));
+    }
+
+    /// Test that a resync drops the retained blocks but keeps the proven state roots.
+    #[test]
+    fn given_blocks_when_resync_then_should_keep_only_proven_state_roots() {
+        // Given
+        let (config, ethereum_lightclient_mock, starknet_lightclient_mock) = mock_clients();
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+        let mut node = NodeData::new();
+        apply(
+            &mut node,
+            StoreCommand::VerifiedBlock {
+                block: Arc::new(block_with_single_invoke(
+                    FieldElement::from_hex_be("0xabc").unwrap(),
+                )),
+                events: Arc::new(vec![]),
+            },
+        );
+        Arc::make_mut(&mut node.proven_state_roots).insert(10, FieldElement::ONE);
+        beerus.node.store(Arc::new(node));
+
+        // When
+        beerus.resync(None);
+
+        // Then
+        let dump = beerus.dump_state();
+        assert_eq!(dump.block_number, 0);
+        assert!(dump.block_hashes.is_empty());
+        assert_eq!(dump.proven_state_roots.get(&10), Some(&FieldElement::ONE));
+        assert_eq!(dump.retention_stats.first_block_number, None);
+    }
+
+    /// Test that a resync from a block only drops the blocks from there onwards.
+    #[test]
+    fn given_blocks_when_resync_from_block_then_should_rewind_to_previous_block() {
+        // Given
+        let (config, ethereum_lightclient_mock, starknet_lightclient_mock) = mock_clients();
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+        let mut node = NodeData::new();
+        for block_number in 10..13 {
+            let mut block = block_with_single_invoke(FieldElement::from(0xabc_u64 + block_number));
+            block.block_number = block_number;
+            apply(
+                &mut node,
+                StoreCommand::VerifiedBlock {
+                    block: Arc::new(block),
+                    events: Arc::new(vec![]),
+                },
+            );
+        }
+        beerus.node.store(Arc::new(node));
+
+        // When
+        beerus.resync(Some(11));
+
+        // Then
+        let dump = beerus.dump_state();
+        assert_eq!(dump.block_number, 10);
+        assert_eq!(dump.block_hashes.len(), 1);
+        assert_eq!(dump.last_block_hash_check, None);
+        assert_eq!(beerus.node.load().events.len(), 1);
+    }
+
+    /// Test that the block number is answered from the payload while the provider is down.
+    #[tokio::test]
+    async fn given_unavailable_provider_when_starknet_block_number_then_should_read_payload() {
+        // Given
+        let (mut config, ethereum_lightclient_mock, mut starknet_lightclient_mock) = mock_clients();
+        config.block_number_source = BlockNumberSource::Upstream;
+        starknet_lightclient_mock
+            .expect_block_number()
+            .return_once(|| Err(UpstreamUnavailable::new("connection refused").into()));
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+        let mut node = NodeData::new();
+        apply(
+            &mut node,
+            StoreCommand::VerifiedBlock {
+                block: Arc::new(block_with_single_invoke(
+                    FieldElement::from_hex_be("0xabc").unwrap(),
+                )),
+                events: Arc::new(vec![]),
+            },
+        );
+        beerus.node.store(Arc::new(node));
+
+        // When
+        let result = beerus.starknet_block_number().await;
+
+        // Then
+        assert_eq!(result.unwrap(), 10);
+    }
+
+    /// Test that the events of a proven block are exported once the block hash is checked, and
+    /// that blocks not proven on L1 yet are not exported.
+    #[tokio::test]
+    async fn given_proven_block_when_export_events_then_rows_are_written() {
+        // Given
+        let (config, ethereum_lightclient_mock, mut starknet_lightclient_mock) = mock_clients();
+        let event = Event {
+            from_address: FieldElement::from_hex_be("0x42").unwrap(),
+            keys: vec![FieldElement::ONE],
+            data: vec![FieldElement::TWO, FieldElement::ONE],
+        };
+        let block_hash = compute_block_hash(
+            &block_with_single_invoke(FieldElement::ZERO),
+            &[event.clone()],
+        );
+        let receipt = receipt_with_event(event.clone());
+        starknet_lightclient_mock
+            .expect_get_transaction_receipt()
+            .return_once(move |_| Ok(receipt));
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+        let mut node = NodeData::new();
+        apply(
+            &mut node,
+            StoreCommand::VerifiedBlock {
+                block: Arc::new(block_with_single_invoke(block_hash)),
+                events: Arc::new(vec![event]),
+            },
+        );
+        Arc::make_mut(&mut node.proven_state_roots).insert(10, FieldElement::ONE);
+        beerus.node.store(Arc::new(node));
+
+        // When
+        let mut csv = Vec::new();
+        let rows = beerus
+            .export_blocks(ExportTable::Events, ExportFormat::Csv, 10, 10, &mut csv)
+            .await
+            .unwrap();
+        let unproven = beerus
+            .export_blocks(
+                ExportTable::Blocks,
+                ExportFormat::Csv,
+                10,
+                11,
+                &mut Vec::new(),
+            )
+            .await;
+
+        // Then
+        assert_eq!(rows, 1);
+        assert_eq!(
+            String::from_utf8(csv).unwrap().lines().nth(1).unwrap(),
+            "10,0,0x1234,0,0x42,0x1,0x2;0x1"
+        );
+        assert_eq!(
+            unproven.unwrap_err().to_string(),
+            "Block 11 is not proven on L1 yet"
+        );
+    }
+
+    /// Test that blocks are returned in bulk, and that only the ones proven on L1 are iterated.
+    #[test]
+    fn given_blocks_when_get_blocks_in_range_then_should_return_retained_blocks() {
+        // Given
+        let (config, ethereum_lightclient_mock, starknet_lightclient_mock) = mock_clients();
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+        let mut node = NodeData::new();
+        for block_number in 10..13 {
+            let mut block = block_with_single_invoke(FieldElement::from(0xabc_u64 + block_number));
+            block.block_number = block_number;
+            apply(
+                &mut node,
+                StoreCommand::VerifiedBlock {
+                    block: Arc::new(block),
+                    events: Arc::new(vec![]),
+                },
+            );
+        }
+        Arc::make_mut(&mut node.proven_state_roots).insert(11, FieldElement::ONE);
+        beerus.node.store(Arc::new(node));
+
+        // When
+        let blocks = beerus.get_blocks_in_range(0, 99).unwrap();
+        let proven_blocks: Vec<u64> = beerus
+            .proven_blocks(0)
+            .map(|block| block.block_number)
+            .collect();
+
+        // Then
+        let block_numbers: Vec<u64> = blocks.iter().map(|block| block.block_number).collect();
+        assert_eq!(block_numbers, vec![10, 11, 12]);
+        assert_eq!(proven_blocks, vec![10, 11]);
+        assert_eq!(beerus.proven_blocks(11).count(), 1);
+        assert!(beerus.get_blocks_in_range(0, 100).is_err());
+        assert!(beerus.get_blocks_in_range(12, 10).is_err());
+    }
+
+    /// Test that a storage read pinned to a retained state root is verified against it, without
+    /// reading the L1 state.
+    #[tokio::test]
+    async fn given_proven_state_root_when_get_storage_at_pinned_then_result_is_verified() {
+        // Given
+        let (config, ethereum_lightclient_mock, mut starknet_lightclient_mock) = mock_clients();
+        starknet_lightclient_mock
+            .expect_get_storage_at()
+            .withf(|_, _, block_number| *block_number == 10)
+            .return_once(move |_, _, _| Ok(FieldElement::ZERO));
+        starknet_lightclient_mock
+            .expect_get_contract_storage_proof()
+            .return_once(move |_, _, _| Ok(mainnet_proof()));
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+        let state_root = FieldElement::from_hex_be(
+            "0x47f25798a804800b657d4e1508776e3c3c70f0d7587d125a558208f88570aa7",
+        )
+        .unwrap();
+        let mut node = NodeData::new();
+        Arc::make_mut(&mut node.proven_state_roots).insert(10, state_root);
+        Arc::make_mut(&mut node.proven_state_roots).insert(11, FieldElement::ONE);
+        beerus.node.store(Arc::new(node));
+        let contract_address = FieldElement::from_hex_be(
+            "0x4d4e07157aeb54abeb64f5792145f2e8db1c83bda01a8f06e050be18cfb8153",
+        )
+        .unwrap();
+
+        // When
+        let result = beerus
+            .starknet_get_storage_at_with_metadata(
+                contract_address,
+                FieldElement::ONE,
+                BlockTarget::StateRoot(state_root),
+            )
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(result.result, FieldElement::ZERO);
+        assert_eq!(result.metadata.block_number, 10);
+        assert_eq!(result.metadata.state_root, state_root);
+        assert!(result.metadata.verified);
+    }
+
+    /// Test that the pending nonce is relayed from the provider and marked as unverified.
+    #[tokio::test]
+    async fn given_pending_target_when_get_nonce_with_metadata_then_relays_pending_nonce_unverified(
+    ) {
+        // Given
+        let (config, mut ethereum_lightclient_mock, mut starknet_lightclient_mock) = mock_clients();
+        mock_mainnet_l1_state(&mut ethereum_lightclient_mock);
+        starknet_lightclient_mock
+            .expect_get_nonce_at()
+            .return_once(move |block_id, _| {
+                assert_eq!(
+                    block_id,
+                    &BlockId::Tag(starknet::providers::jsonrpc::models::BlockTag::Pending)
+                );
+                Ok(FieldElement::from(5_u64))
+            });
+        starknet_lightclient_mock.expect_get_nonce().never();
+        starknet_lightclient_mock
+            .expect_get_contract_storage_proof()
+            .never();
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+        let mut block = block_with_single_invoke(FieldElement::from_hex_be("0xabc").unwrap());
+        block.block_number = 12;
+        let mut node = NodeData::new();
+        apply(
+            &mut node,
+            StoreCommand::VerifiedBlock {
+                block: Arc::new(block),
+                events: Arc::new(vec![]),
+            },
+        );
+        beerus.node.store(Arc::new(node));
+
+        // When
+        let result = beerus
+            .starknet_get_nonce_with_metadata(
+                FieldElement::ONE,
+                BlockTarget::from_str("pending").unwrap(),
+            )
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(result.result, FieldElement::from(5_u64));
+        assert_eq!(result.metadata.block_number, 13);
+        assert_eq!(result.metadata.proven_block_number, 10);
+        assert!(!result.metadata.verified);
+    }
+
+    /// Test that a read pinned to a state root which was never proven is rejected.
+    #[tokio::test]
+    async fn given_unknown_state_root_when_get_nonce_pinned_then_should_return_error() {
+        // Given
+        let (config, mut ethereum_lightclient_mock, starknet_lightclient_mock) = mock_clients();
+        mock_mainnet_l1_state(&mut ethereum_lightclient_mock);
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+
+        // When
+        let result = beerus
+            .starknet_get_nonce_with_metadata(
+                FieldElement::ONE,
+                BlockTarget::from_str("0x123").unwrap(),
+            )
+            .await;
+
+        // Then
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("was not proven on L1"));
+    }
+
+    /// Test that the receipts of a retained block are fetched once, then served from the cache.
+    #[tokio::test]
+    async fn given_retained_block_when_get_block_with_receipts_then_receipts_are_cached() {
+        // Given
+        let (config, ethereum_lightclient_mock, mut starknet_lightclient_mock) = mock_clients();
+        let receipt = MaybePendingTransactionReceipt::Receipt(TransactionReceipt::Invoke(
+            InvokeTransactionReceipt {
+                transaction_hash: FieldElement::from_hex_be("0x1234").unwrap(),
+                actual_fee: FieldElement::ONE,
+                status: TransactionStatus::AcceptedOnL2,
+                block_hash: FieldElement::from_hex_be("0xabc").unwrap(),
+                block_number: 10,
+                messages_sent: vec![],
+                events: vec![],
+            },
+        ));
+        let expected_receipt = receipt.clone();
+        starknet_lightclient_mock
+            .expect_get_transaction_receipt()
+            .withf(|hash| *hash == FieldElement::from_hex_be("0x1234").unwrap())
+            .times(1)
+            .return_once(move |_| Ok(receipt));
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+        let mut node = NodeData::new();
+        apply(
+            &mut node,
+            StoreCommand::VerifiedBlock {
+                block: Arc::new(block_with_single_invoke(
+                    FieldElement::from_hex_be("0xabc").unwrap(),
+                )),
+                events: Arc::new(vec![]),
+            },
+        );
+        beerus.node.store(Arc::new(node));
+
+        // When
+        let block = beerus
+            .starknet_get_block_with_receipts(&BlockId::Number(10))
+            .await
+            .unwrap();
+        let cached = beerus
+            .starknet_get_block_with_receipts(&BlockId::Tag(
+                starknet::providers::jsonrpc::models::BlockTag::Latest,
+            ))
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(block.block_number, 10);
+        assert_eq!(block.transactions.len(), 1);
+        assert_eq!(
+            format!("{:?}", block.transactions[0].receipt),
+            format!("{expected_receipt:?}")
+        );
+        assert_eq!(
+            format!("{:?}", cached.transactions[0].receipt),
+            format!("{expected_receipt:?}")
+        );
+        assert_eq!(beerus.node.load().receipts.len(), 1);
+    }
+
+    /// Test that prefetched receipts are cached for the retained blocks only, and answered
+    /// locally.
+    #[tokio::test]
+    async fn given_prefetched_receipts_when_get_transaction_receipt_then_answered_locally() {
+        // Given
+        let (config, ethereum_lightclient_mock, starknet_lightclient_mock) = mock_clients();
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+        let block_hash = FieldElement::from_hex_be("0xabc").unwrap();
+        let tx_hash = FieldElement::from_hex_be("0x1234").unwrap();
+        let receipts = Arc::new(vec![MaybePendingTransactionReceipt::Receipt(
+            TransactionReceipt::Invoke(InvokeTransactionReceipt {
+                transaction_hash: tx_hash,
+                actual_fee: FieldElement::ONE,
+                status: TransactionStatus::AcceptedOnL2,
+                block_hash,
+                block_number: 10,
+                messages_sent: vec![],
+                events: vec![],
+            }),
+        )]);
+        let mut node = NodeData::new();
+        apply(
+            &mut node,
+            StoreCommand::VerifiedBlock {
+                block: Arc::new(block_with_single_invoke(block_hash)),
+                events: Arc::new(vec![]),
+            },
+        );
+        apply(
+            &mut node,
+            StoreCommand::Receipts {
+                block_number: 10,
+                block_hash,
+                receipts: receipts.clone(),
+            },
+        );
+        // The receipts of a block which is not retained are dropped.
+        apply(
+            &mut node,
+            StoreCommand::Receipts {
+                block_number: 11,
+                block_hash,
+                receipts,
+            },
+        );
+        beerus.node.store(Arc::new(node));
+
+        // When
+        let receipt = beerus
+            .starknet_get_transaction_receipt(tx_hash)
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(beerus.node.load().receipts.len(), 1);
+        assert!(matches!(
+            receipt,
+            MaybePendingTransactionReceipt::Receipt(TransactionReceipt::Invoke(receipt))
+                if receipt.transaction_hash == tx_hash
+        ));
+    }
+
+    /// Test that a payload ahead of L1 fails the receipt query with a state root diagnostic.
+    #[tokio::test]
+    async fn given_payload_ahead_of_l1_when_get_transaction_receipt_then_should_return_diagnostic()
+    {
+        // Given
+        let (config, mut ethereum_lightclient_mock, starknet_lightclient_mock) = mock_clients();
+        ethereum_lightclient_mock
+            .expect_starknet_proven_state()
+            .times(1)
+            .return_once(|| Ok((U256::from(9), U256::from(0x456))));
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+        let mut node = NodeData::new();
+        apply(
+            &mut node,
+            StoreCommand::VerifiedBlock {
+                block: Arc::new(block_with_single_invoke(FieldElement::from(0xabc_u64))),
+                events: Arc::new(vec![]),
+            },
+        );
+        beerus.node.store(Arc::new(node));
+
+        // When
+        let result = beerus
+            .starknet_get_transaction_receipt(FieldElement::ONE)
+            .await;
+
+        // Then
+        let err = result.unwrap_err();
+        let mismatch = err.downcast_ref::<StateRootMismatch>().unwrap();
+        assert_eq!(
+            *mismatch,
+            StateRootMismatch {
+                l1_state_root: FieldElement::from(0x456_u64),
+                l1_block_number: 9,
+                local_state_root: Some(FieldElement::from(0x123_u64)),
+                local_block_number: 10,
+            }
+        );
+        assert!(mismatch.is_ahead_of_l1());
+        assert_eq!(
+            err.to_string(),
+            "State root mismatch: L1 proves 0x456 for block 9, the payload has 0x123 for block 10, \
+             the payload is ahead of L1, wait for L1 to prove its last block"
+        );
+    }
+
+    /// Test that a stale payload is refreshed before the receipt query fails.
+    #[tokio::test]
+    async fn given_stale_payload_when_get_transaction_receipt_then_should_refresh_and_return_ok() {
+        // Given
+        let (config, mut ethereum_lightclient_mock, mut starknet_lightclient_mock) = mock_clients();
+        ethereum_lightclient_mock
+            .expect_starknet_proven_state()
+            .returning(|| Ok((U256::from(10), U256::from(0x123))));
+        let receipt = MaybePendingTransactionReceipt::Receipt(TransactionReceipt::Invoke(
+            InvokeTransactionReceipt {
+                transaction_hash: FieldElement::ONE,
+                actual_fee: FieldElement::ONE,
+                status: TransactionStatus::AcceptedOnL2,
+                block_hash: FieldElement::from(0xabc_u64),
+                block_number: 10,
+                messages_sent: vec![],
+                events: vec![],
+            },
+        ));
+        let expected = receipt.clone();
+        starknet_lightclient_mock
+            .expect_get_transaction_receipt()
+            .times(1)
+            .return_once(move |_| Ok(receipt));
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+        // The syncer catches up with the block proven on L1 while the query waits.
+        let node = beerus.node.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            let mut next = NodeData::new();
+            apply(
+                &mut next,
+                StoreCommand::VerifiedBlock {
+                    block: Arc::new(block_with_single_invoke(FieldElement::from(0xabc_u64))),
+                    events: Arc::new(vec![]),
+                },
+            );
+            node.store(Arc::new(next));
+        });
+
+        // When
+        let result = beerus
+            .starknet_get_transaction_receipt(FieldElement::ONE)
+            .await;
+
+        // Then
+        assert_eq!(format!("{:?}", result.unwrap()), format!("{expected:?}"));
+    }
+
+    /// Test that the events of the retained blocks are answered from the event index, by pages.
+    #[tokio::test]
+    async fn given_retained_events_when_get_events_then_answered_from_index() {
+        // Given
+        let (config, ethereum_lightclient_mock, starknet_lightclient_mock) = mock_clients();
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+        let event = |from_address: u64, key: u64| EmittedEvent {
+            from_address: FieldElement::from(from_address),
+            keys: vec![FieldElement::from(key)],
+            data: vec![],
+            block_hash: FieldElement::ZERO,
+            block_number: 10,
+            transaction_hash: FieldElement::ZERO,
+        };
+        let mut node = NodeData::new();
+        apply(
+            &mut node,
+            StoreCommand::VerifiedBlock {
+                block: Arc::new(block_with_single_invoke(
+                    FieldElement::from_hex_be("0xabc").unwrap(),
+                )),
+                events: Arc::new(vec![event(1, 10), event(2, 10), event(1, 20), event(1, 30)]),
+            },
+        );
+        beerus.node.store(Arc::new(node));
+        let filter = EventFilter {
+            from_block: Some(BlockId::Number(10)),
+            to_block: Some(BlockId::Tag(
+                starknet::providers::jsonrpc::models::BlockTag::Latest,
+            )),
+            address: Some(FieldElement::ONE),
+            keys: None,
+        };
+
+        // When
+        let first_page = beerus
+            .starknet_get_events(filter.clone(), None, 2)
+            .await
+            .unwrap();
+        let last_page = beerus
+            .starknet_get_events(filter, first_page.continuation_token.clone(), 2)
+            .await
+            .unwrap();
+
+        // Then
+        let keys = |page: &EventsPage| -> Vec<FieldElement> {
+            page.events.iter().map(|event| event.keys[0]).collect()
+        };
+        assert_eq!(
+            keys(&first_page),
+            vec![FieldElement::from(10_u64), FieldElement::from(20_u64)]
+        );
+        assert_eq!(
+            first_page.continuation_token,
+            Some("beerus-10-2".to_string())
+        );
+        assert_eq!(keys(&last_page), vec![FieldElement::from(30_u64)]);
+        assert_eq!(last_page.continuation_token, None);
+    }
+
+    /// Test that the local continuation tokens keep pointing to the same events while new blocks
+    /// are synced, and that malformed ones are rejected.
+    #[tokio::test]
+    async fn given_new_block_between_pages_when_get_events_then_pages_are_consistent() {
+        // Given
+        let (config, ethereum_lightclient_mock, starknet_lightclient_mock) = mock_clients();
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+        let event = |block_number: u64, key: u64| EmittedEvent {
+            from_address: FieldElement::ONE,
+            keys: vec![FieldElement::from(key)],
+            data: vec![],
+            block_hash: FieldElement::ZERO,
+            block_number,
+            transaction_hash: FieldElement::ZERO,
+        };
+        let verified_block = |block_number: u64, events: Vec<EmittedEvent>| {
+            let mut block = block_with_single_invoke(FieldElement::from(0xabc_u64 + block_number));
+            block.block_number = block_number;
+            StoreCommand::VerifiedBlock {
+                block: Arc::new(block),
+                events: Arc::new(events),
+            }
+        };
+        let filter = || EventFilter {
+            from_block: Some(BlockId::Number(10)),
+            to_block: Some(BlockId::Tag(
+                starknet::providers::jsonrpc::models::BlockTag::Latest,
+            )),
+            address: None,
+            keys: None,
+        };
+        let mut node = NodeData::new();
+        apply(
+            &mut node,
+            verified_block(10, vec![event(10, 1), event(10, 2), event(10, 3)]),
+        );
+        beerus.node.store(Arc::new(node.clone()));
+        let first_page = beerus.starknet_get_events(filter(), None, 2).await.unwrap();
+
+        // When
+        apply(&mut node, verified_block(11, vec![event(11, 4)]));
+        beerus.node.store(Arc::new(node));
+        let last_page = beerus
+            .starknet_get_events(filter(), first_page.continuation_token, 2)
+            .await
+            .unwrap();
+        let malformed_token = beerus
+            .starknet_get_events(filter(), Some("beerus-10".to_string()), 2)
+            .await
+            .unwrap_err();
+        let too_large_page = beerus
+            .starknet_get_events(filter(), None, 10_000)
+            .await
+            .unwrap_err();
+
+        // Then
+        let keys: Vec<FieldElement> = last_page.events.iter().map(|event| event.keys[0]).collect();
+        assert_eq!(
+            keys,
+            vec![FieldElement::from(3_u64), FieldElement::from(4_u64)]
+        );
+        assert_eq!(last_page.continuation_token, None);
+        assert_eq!(
+            malformed_token
+                .downcast_ref::<StarknetError>()
+                .unwrap()
+                .code,
+            StarknetErrorCode::InvalidContinuationToken
+        );
+        assert_eq!(
+            too_large_page.downcast_ref::<StarknetError>().unwrap().code,
+            StarknetErrorCode::PageSizeTooBig
+        );
+    }
+
+    /// Test that the first declaration of a class is tracked, reported as proven once the state
+    /// root of its block is, kept when pruning and dropped when its block is rewound.
+    #[test]
+    fn given_declare_transactions_when_get_class_declaration_then_first_one_is_returned() {
+        // Given
+        let (config, ethereum_lightclient_mock, starknet_lightclient_mock) = mock_clients();
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+        let declaring_block = |block_number: u64, class_hashes: &[u64]| {
+            let mut block = block_with_single_invoke(FieldElement::from(0xabc_u64 + block_number));
+            block.block_number = block_number;
+            block.transactions = class_hashes
+                .iter()
+                .map(|class_hash| {
+                    StarknetTransaction::Declare(DeclareTransaction {
+                        transaction_hash: FieldElement::from(block_number * 100 + class_hash),
+                        max_fee: FieldElement::ONE,
+                        version: 1,
+                        signature: vec![],
+                        nonce: FieldElement::ZERO,
+                        class_hash: FieldElement::from(*class_hash),
+                        sender_address: FieldElement::from_hex_be("0x42").unwrap(),
+                    })
+                })
+                .collect();
+            StoreCommand::VerifiedBlock {
+                block: Arc::new(block),
+                events: Arc::new(vec![]),
+            }
+        };
+        let mut node = NodeData::new();
+        apply(&mut node, declaring_block(10, &[1]));
+        apply(&mut node, declaring_block(11, &[1, 2]));
+        Arc::make_mut(&mut node.proven_state_roots).insert(10, FieldElement::ONE);
+        beerus.node.store(Arc::new(node.clone()));
+
+        // When
+        let first_class = beerus.get_class_declaration(&FieldElement::ONE).unwrap();
+        let second_class = beerus.get_class_declaration(&FieldElement::TWO).unwrap();
+        node.prune_to(11);
+        let pruned = node.class_declaration(&FieldElement::ONE);
+        node.rewind_to(11);
+        let rewound = node.class_declaration(&FieldElement::TWO);
+
+        // Then
+        assert_eq!(first_class.block_number, 10);
+        assert_eq!(first_class.transaction_hash, FieldElement::from(1001_u64));
+        assert!(first_class.proven);
+        assert_eq!(second_class.block_number, 11);
+        assert!(!second_class.proven);
+        assert_eq!(beerus.get_class_declaration(&FieldElement::THREE), None);
+        assert_eq!(pruned, Some(first_class));
+        assert_eq!(rewound, None);
+    }
+
+    /// Test that the contracts deployed by account deployments and by the Universal Deployer
+    /// Contract are indexed, and that the same event emitted by another contract is ignored.
+    #[test]
+    fn given_deployments_when_get_contract_deployment_then_should_return_them() {
+        // Given
+        let (config, ethereum_lightclient_mock, starknet_lightclient_mock) = mock_clients();
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+        let account_class_hash = FieldElement::from_hex_be("0xacc").unwrap();
+        let mut block = block_with_single_invoke(FieldElement::from_hex_be("0xabc").unwrap());
+        block.transactions.push(StarknetTransaction::DeployAccount(
+            DeployAccountTransaction {
+                transaction_hash: FieldElement::from_hex_be("0x5678").unwrap(),
+                max_fee: FieldElement::ONE,
+                version: 1,
+                signature: vec![],
+                nonce: FieldElement::ZERO,
+                contract_address_salt: FieldElement::TWO,
+                constructor_calldata: vec![FieldElement::THREE],
+                class_hash: account_class_hash,
+            },
+        ));
+        let contract_deployed = |from_address: FieldElement, address: u64| EmittedEvent {
+            from_address,
+            keys: vec![selector!("ContractDeployed")],
+            data: vec![
+                FieldElement::from(address),
+                FieldElement::ONE,
+                FieldElement::ZERO,
+                FieldElement::from_hex_be("0xc1a55").unwrap(),
+            ],
+            block_hash: block.block_hash,
+            block_number: 10,
+            transaction_hash: FieldElement::from_hex_be("0x1234").unwrap(),
+        };
+        let universal_deployer = FieldElement::from_hex_be(UNIVERSAL_DEPLOYER_ADDRESS).unwrap();
+        let events = vec![
+            contract_deployed(universal_deployer, 0x111),
+            contract_deployed(FieldElement::from_hex_be("0x666").unwrap(), 0x222),
+        ];
+        let mut node = NodeData::new();
+        apply(
+            &mut node,
+            StoreCommand::VerifiedBlock {
+                block: Arc::new(block),
+                events: Arc::new(events),
+            },
+        );
+        beerus.node.store(Arc::new(node));
+
+        // When
+        let deployed_by_udc = beerus.get_contract_deployment(&FieldElement::from(0x111_u64));
+        let spoofed = beerus.get_contract_deployment(&FieldElement::from(0x222_u64));
+        let accounts = beerus.get_contracts_of_class(&account_class_hash);
+
+        // Then
+        let deployed_by_udc = deployed_by_udc.unwrap();
+        assert_eq!(
+            deployed_by_udc.class_hash,
+            FieldElement::from_hex_be("0xc1a55").unwrap()
+        );
+        assert_eq!(deployed_by_udc.block_number, 10);
+        assert!(!deployed_by_udc.proven);
+        assert_eq!(spoofed, None);
+        assert_eq!(accounts.len(), 1);
+        assert_eq!(
+            accounts[0].address,
+            get_contract_address(
+                FieldElement::TWO,
+                account_class_hash,
+                &[FieldElement::THREE],
+                FieldElement::ZERO
+            )
+        );
+        assert_eq!(
+            accounts[0].transaction_hash,
+            FieldElement::from_hex_be("0x5678").unwrap()
+        );
+    }
+
+    /// Test that the inclusion proof of a transaction of a proven block is exported and can be
+    /// checked from its JSON serialization alone.
+    #[test]
+    fn given_proven_block_when_get_transaction_proof_then_proof_is_verified() {
+        // Given
+        let (config, ethereum_lightclient_mock, starknet_lightclient_mock) = mock_clients();
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+        let mut block = block_with_single_invoke(FieldElement::ZERO);
+        block.block_hash = compute_block_hash(&block, &[]);
+        let mut node = NodeData::new();
+        Arc::make_mut(&mut node.proven_state_roots).insert(10, block.new_root);
+        apply(
+            &mut node,
+            StoreCommand::VerifiedBlock {
+                block: Arc::new(block),
+                events: Arc::new(vec![]),
+            },
+        );
+        beerus.node.store(Arc::new(node));
+
+        // When
+        let proof = beerus
+            .get_transaction_proof(&FieldElement::from_hex_be("0x1234").unwrap())
+            .unwrap();
+        let unknown = beerus.get_transaction_proof(&FieldElement::from_hex_be("0x5678").unwrap());
+
+        // Then
+        let json = serde_json::to_string(&proof).unwrap();
+        let exported: TransactionProof = serde_json::from_str(&json).unwrap();
+        assert!(exported.verify().is_ok());
+        assert_eq!(exported.block_header.block_number, 10);
+        assert_eq!(exported.transaction_index, 0);
+        assert!(unknown.is_err());
+    }
+
+    /// Test that the proofs of a contract are exported once checked against the state root
+    /// proven on L1, and that they are never exported unchecked.
+    #[tokio::test]
+    async fn given_valid_proof_when_get_proof_then_proof_is_exported() {
+        // Given
+        let (config, mut ethereum_lightclient_mock, mut starknet_lightclient_mock) = mock_clients();
+        mock_mainnet_l1_state(&mut ethereum_lightclient_mock);
+        starknet_lightclient_mock
+            .expect_get_storage_at()
+            .return_once(move |_, _, _| Ok(FieldElement::ZERO));
+        starknet_lightclient_mock
+            .expect_get_contract_storage_proof()
+            .return_once(move |_, _, _| Ok(mainnet_proof()));
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+        let contract_address = FieldElement::from_hex_be(
+            "0x4d4e07157aeb54abeb64f5792145f2e8db1c83bda01a8f06e050be18cfb8153",
+        )
+        .unwrap();
+
+        // When
+        let result = beerus
+            .get_proof(
+                contract_address,
+                vec![FieldElement::ONE],
+                BlockTarget::L1Accepted,
+            )
+            .await
+            .unwrap();
+
+        // Then
+        assert!(result.metadata.verified);
+        assert_eq!(result.result.storage_values, vec![FieldElement::ZERO]);
+        assert_eq!(result.result.proof, mainnet_proof());
+        let json = serde_json::to_value(&result.result).unwrap();
+        assert!(json["contract_proof"].is_array());
+        assert!(json["contract_data"]["storage_proofs"].is_array());
+    }
+
+    /// Test that no proof is exported when the provider can't serve them.
+    #[tokio::test]
+    async fn given_proof_unavailable_when_get_proof_then_should_return_error() {
+        // Given
+        let (config, mut ethereum_lightclient_mock, mut starknet_lightclient_mock) = mock_clients();
+        mock_mainnet_l1_state(&mut ethereum_lightclient_mock);
+        starknet_lightclient_mock
+            .expect_get_contract_storage_proof()
+            .return_once(move |_, _, _| Err(eyre!("Method not found")));
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+
+        // When
+        let result = beerus
+            .get_proof(FieldElement::ONE, vec![], BlockTarget::L1Accepted)
+            .await;
+
+        // Then
+        assert_eq!(result.unwrap_err().to_string(), "Method not found");
+    }
+
+    /// Test that the core contract getters decode the responses of the Ethereum light client.
+    #[tokio::test]
+    async fn given_normal_conditions_when_query_core_contract_parameters_then_should_decode_them() {
+        // Given
+        let (config, mut ethereum_lightclient_mock, starknet_lightclient_mock) = mock_clients();
+        let mut responses = vec![
+            ethers::abi::encode(&[ethers::abi::Token::Uint(U256::from(0x1234))]),
+            ethers::abi::encode(&[ethers::abi::Token::Bool(true)]),
+            ethers::abi::encode(&[ethers::abi::Token::String(
+                "StarkWare_Starknet_2022_4".to_string(),
+            )]),
+        ]
+        .into_iter();
+        ethereum_lightclient_mock
+            .expect_call()
+            .times(3)
+            .returning(move |_call_opts, _block_tag| Ok(responses.next().unwrap()));
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+
+        // When
+        let program_hash = beerus.starknet_program_hash().await.unwrap();
+        let is_finalized = beerus.starknet_is_finalized().await.unwrap();
+        let version = beerus.starknet_core_contract_version().await.unwrap();
+
+        // Then
+        assert_eq!(program_hash, U256::from(0x1234));
+        assert!(is_finalized);
+        assert_eq!(version, "StarkWare_Starknet_2022_4");
+    }
+
+    /// Test that the verifier address is read from the named storage slot of the core contract.
+    #[tokio::test]
+    async fn given_normal_conditions_when_query_verifier_address_then_should_read_named_slot() {
+        // Given
+        let (config, mut ethereum_lightclient_mock, starknet_lightclient_mock) = mock_clients();
+        let verifier = Address::from_str("0x47312450b3ac8b5b8e247a6bb6d523e7605bdb60").unwrap();
+        let expected_slot = H256::from(ethers::utils::keccak256(
+            beerus_core::lightclient::beerus::STARKNET_VERIFIER_ADDRESS_TAG,
+        ));
+        ethereum_lightclient_mock
+            .expect_get_storage_at()
+            .withf(move |_address, slot, _block| *slot == expected_slot)
+            .times(1)
+            .return_once(move |_address, _slot, _block| {
+                Ok(U256::from_big_endian(verifier.as_bytes()))
+            });
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+
+        // When
+        let result = beerus.starknet_verifier_address().await;
+
+        // Then
+        assert_eq!(result.unwrap(), verifier);
+    }
+
+    /// Test that a change of the core contract parameters is reported as an upgrade by the status.
+    #[tokio::test]
+    async fn given_core_contract_parameters_change_when_call_beerus_status_then_upgrade_is_reported(
+    ) {
+        // Given
+        let (config, mut ethereum_lightclient_mock, mut starknet_lightclient_mock) = mock_clients();
+        starknet_lightclient_mock
+            .expect_provider_health()
+            .returning(Vec::new);
+        let implementation =
+            Address::from_str("0x16a2d4f8c5ba2a15b5c0c5b42cd4bb5ac9b0e457").unwrap();
+        ethereum_lightclient_mock
+            .expect_get_storage_at()
+            .times(1)
+            .return_once(move |_address, _slot, _block| {
+                Ok(U256::from_big_endian(implementation.as_bytes()))
+            });
+        ethereum_lightclient_mock
+            .expect_call()
+            .times(2)
+            .returning(|_call_opts, _block_tag| {
+                Ok(ethers::abi::encode(&[ethers::abi::Token::Uint(
+                    U256::from(1),
+                )]))
+            });
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+        let parameters = CoreContractParameters::fetch(
+            beerus.ethereum_lightclient.read().await.as_ref(),
+            &beerus.starknet_core_abi,
+            beerus.starknet_core_contract_address,
+        )
+        .await
+        .unwrap();
+        let upgraded = CoreContractParameters {
+            program_hash: U256::from(2),
+            ..parameters.clone()
+        };
+        let mut upgrades = beerus.subscribe_core_contract_upgrades();
+
+        // When
+        let first = beerus.upgrade_monitor.record(parameters.clone());
+        let unchanged = beerus.upgrade_monitor.record(parameters.clone());
+        let changed = beerus.upgrade_monitor.record(upgraded.clone());
+        let status = beerus.beerus_status().await;
+
+        // Then
+        assert_eq!(parameters.implementation, implementation);
+        assert_eq!(parameters.config_hash, U256::from(1));
+        assert_eq!(first, None);
+        assert_eq!(unchanged, None);
+        let expected = CoreContractUpgrade {
+            previous: parameters,
+            current: upgraded,
+            changes: vec!["program_hash".to_string()],
+        };
+        assert_eq!(changed, Some(expected.clone()));
+        assert_eq!(status.core_contract_upgrade, Some(expected.clone()));
+        assert!(upgrades.has_changed().unwrap());
+        assert_eq!(*upgrades.borrow_and_update(), Some(expected));
+    }
+
+    /// Test that the L1 gas price and priority fee are returned from the Ethereum light client.
+    #[tokio::test]
+    async fn given_normal_conditions_when_query_l1_gas_prices_then_should_return_them() {
+        // Given
+        let (config, mut ethereum_lightclient_mock, starknet_lightclient_mock) = mock_clients();
+        ethereum_lightclient_mock
+            .expect_get_gas_price()
+            .times(1)
+            .return_once(|| Ok(U256::from(30_000_000_000_u64)));
+        ethereum_lightclient_mock
+            .expect_get_priority_fee()
+            .times(1)
+            .return_once(|| Ok(U256::from(1_500_000_000_u64)));
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+
+        // When
+        let gas_price = beerus.ethereum_gas_price().await.unwrap();
+        let priority_fee = beerus.ethereum_max_priority_fee_per_gas().await.unwrap();
+
+        // Then
+        assert_eq!(gas_price, U256::from(30_000_000_000_u64));
+        assert_eq!(priority_fee, U256::from(1_500_000_000_u64));
+    }
+
+    /// Test that the latest block is the last proven one by default, for both block number
+    /// methods, without asking the StarkNet provider.
+    #[tokio::test]
+    async fn given_default_source_when_starknet_block_number_then_should_return_last_proven_block()
+    {
+        // Given
+        let (config, ethereum_lightclient_mock, starknet_lightclient_mock) = mock_clients();
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+        let proven_hash = FieldElement::from_hex_be("0xabc").unwrap();
+        let mut latest = block_with_single_invoke(FieldElement::from_hex_be("0xdef").unwrap());
+        latest.block_number = 11;
+        let mut node = NodeData::new();
+        for block in [block_with_single_invoke(proven_hash), latest] {
+            apply(
+                &mut node,
+                StoreCommand::VerifiedBlock {
+                    block: Arc::new(block),
+                    events: Arc::new(vec![]),
+                },
+            );
+        }
+        apply(
+            &mut node,
+            StoreCommand::ProvenStateRoot {
+                block_number: 10,
+                state_root: FieldElement::from_hex_be("0x123").unwrap(),
+            },
+        );
+        beerus.node.store(Arc::new(node));
+
+        // When
+        let block_number = beerus.starknet_block_number().await;
+        let block_hash_and_number = beerus.starknet_block_hash_and_number().await;
+
+        // Then
+        assert_eq!(block_number.unwrap(), 10);
+        let block_hash_and_number = block_hash_and_number.unwrap();
+        assert_eq!(block_hash_and_number.block_number, 10);
+        assert_eq!(block_hash_and_number.block_hash, proven_hash);
+    }
+
+    /// Test that a block which doesn't extend the retained chain drops the reorged blocks.
+    #[test]
+    fn given_reorg_when_apply_verified_block_then_reorged_blocks_are_dropped() {
+        // Given
+        let block = |block_number, block_hash, parent_hash| StoreCommand::VerifiedBlock {
+            block: Arc::new(block_with_parent(block_number, block_hash, parent_hash)),
+            events: Arc::new(vec![]),
+        };
+        let mut node = NodeData::new();
+        apply(&mut node, block(10, 0xa, 0x9));
+        apply(&mut node, block(11, 0xb, 0xa));
+        apply(&mut node, block(12, 0xc, 0xb));
+
+        // When
+        let fork_at_parent = node.fork_point(&block_with_parent(12, 0xd, 0xe));
+        apply(&mut node, block(12, 0xd, 0xb));
+        let replaced = node.clone();
+        apply(&mut node, block(12, 0xf, 0xe));
+
+        // Then
+        assert_eq!(fork_at_parent, Some(11));
+        // The block replacing 12 extends 11, the old 12 is no longer found by hash.
+        assert_eq!(replaced.block_number, 12);
+        assert!(replaced
+            .payload
+            .get_by_hash(&FieldElement::from(0xc_u64))
+            .is_none());
+        assert!(replaced
+            .payload
+            .get_by_hash(&FieldElement::from(0xd_u64))
+            .is_some());
+        // The block whose parent is not 11 drops 11 and 12.
+        assert_eq!(node.block_number, 12);
+        assert_eq!(node.payload.len(), 2);
+        assert!(!node.payload.contains_key(&11));
+        assert_eq!(
+            node.payload
+                .block_number_by_hash(&FieldElement::from(0xf_u64)),
+            Some(12)
+        );
+        assert_eq!(
+            node.payload
+                .block_number_by_hash(&FieldElement::from(0xb_u64)),
+            None
+        );
+    }
+
+    /// Test that reorgs and state roots differing from the ones proven on L1 raise alerts.
+    #[test]
+    fn given_reorg_and_unproven_root_when_store_alerts_then_alerts_are_raised() {
+        // Given
+        let block = |block_number, block_hash, parent_hash| StoreCommand::VerifiedBlock {
+            block: Arc::new(block_with_parent(block_number, block_hash, parent_hash)),
+            events: Arc::new(vec![]),
+        };
+        let proven = |state_root| StoreCommand::ProvenStateRoot {
+            block_number: 11,
+            state_root: FieldElement::from(state_root),
+        };
+        let mut node = NodeData::new();
+        apply(&mut node, block(10, 0xa, 0x9));
+        apply(&mut node, block(11, 0xb, 0xa));
+
+        // When
+        let extending = store_alerts(&node, &block(12, 0xc, 0xb));
+        let reorg = store_alerts(&node, &block(12, 0xc, 0xe));
+        let matching = store_alerts(&node, &proven(0x123_u64));
+        let mismatch = store_alerts(&node, &proven(0x456_u64));
+        apply(&mut node, proven(0x456_u64));
+        let reported = store_alerts(&node, &proven(0x456_u64));
+
+        // Then
+        assert!(extending.is_empty());
+        assert_eq!(reorg.len(), 1);
+        assert_eq!(reorg[0].kind, AlertKind::Reorg);
+        assert_eq!(reorg[0].block_number, Some(11));
+        assert!(matching.is_empty());
+        assert_eq!(mismatch.len(), 1);
+        assert_eq!(mismatch[0].kind, AlertKind::StateRootMismatch);
+        assert_eq!(mismatch[0].block_number, Some(11));
+        // The root reported again on the next poll is not alerted on twice.
+        assert!(reported.is_empty());
+    }
+
+    fn block_with_parent(block_number: u64, block_hash: u64, parent_hash: u64) -> BlockWithTxs {
+        let mut block = block_with_single_invoke(FieldElement::from(block_hash));
+        block.block_number = block_number;
+        block.parent_hash = FieldElement::from(parent_hash);
+        block
+    }
+
+    /// Test that a retained block is answered from the payload, looked up by hash.
+    #[tokio::test]
+    async fn given_retained_block_when_get_block_with_txs_by_hash_then_should_read_payload() {
+        // Given
+        let (config, ethereum_lightclient_mock, starknet_lightclient_mock) = mock_clients();
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+        let block_hash = FieldElement::from_hex_be("0xabc").unwrap();
+        let mut node = NodeData::new();
+        apply(
+            &mut node,
+            StoreCommand::VerifiedBlock {
+                block: Arc::new(block_with_single_invoke(block_hash)),
+                events: Arc::new(vec![]),
+            },
+        );
+        beerus.node.store(Arc::new(node));
+
+        // When
+        let result = beerus.get_block_with_txs(&BlockId::Hash(block_hash)).await;
+
+        // Then
+        match result.unwrap() {
+            MaybePendingBlockWithTxs::Block(block) => {
+                assert_eq!(block, block_with_single_invoke(block_hash))
+            }
+            _ => panic!("Expected a block"),
+        }
+    }
+
+    /// Test that the oldest blocks are evicted to keep the node data within its memory budget,
+    /// and that the cached receipts are evicted before any block.
+    #[test]
+    fn given_memory_budget_when_blocks_are_stored_then_oldest_are_evicted() {
+        // Given
+        let blocks: Vec<Arc<BlockWithTxs>> = (1..=5)
+            .map(|block_number| {
+                Arc::new(block_with_parent(
+                    block_number,
+                    0x100 + block_number,
+                    0x100 + block_number - 1,
+                ))
+            })
+            .collect();
+        let store = |node: &mut NodeData| {
+            for block in &blocks {
+                apply(
+                    node,
+                    StoreCommand::VerifiedBlock {
+                        block: block.clone(),
+                        events: Arc::new(vec![]),
+                    },
+                );
+            }
+        };
+        let mut unbounded = NodeData::new();
+        store(&mut unbounded);
+        let usage = unbounded.memory_usage();
+        let block_bytes = usage.payload_bytes / blocks.len();
+        let budget = usage.total_bytes - 2 * block_bytes;
+        let mut node = NodeData {
+            memory_budget: Some(budget),
+            ..NodeData::new()
+        };
+
+        // When
+        store(&mut node);
+        let event = Event {
+            from_address: FieldElement::from_hex_be("0x42").unwrap(),
+            keys: vec![selector!("Transfer")],
+            data: vec![FieldElement::ONE],
+        };
+        let cached = node.cache_receipts(
+            5,
+            blocks[4].block_hash,
+            Arc::new(vec![receipt_with_event(event)]),
+        );
+
+        // Then
+        let retained: Vec<u64> = node
+            .payload
+            .values()
+            .map(|block| block.block_number)
+            .collect();
+        assert_eq!(retained, vec![3, 4, 5]);
+        assert!(cached);
+        assert!(node.receipts.is_empty());
+        let usage = node.memory_usage();
+        assert!(usage.total_bytes <= budget);
+        assert_eq!(usage.budget_bytes, Some(budget));
+        assert_eq!(usage.evicted_blocks, 2);
+        assert_eq!(usage.evicted_receipts, 1);
+        assert_eq!(unbounded.memory_usage().evicted_blocks, 0);
+    }
+
+    /// Test that a block which is not retained is relayed as the provider wrote it in passthrough
+    /// mode, while a retained one is still answered from the payload.
+    #[tokio::test]
+    async fn given_passthrough_when_get_block_with_txs_then_should_relay_raw_block() {
+        // Given
+        let (config, ethereum_lightclient_mock, mut starknet_lightclient_mock) = mock_clients();
+        let raw_block = r#"{"block_number":12,"transactions":[]}"#;
+        starknet_lightclient_mock
+            .expect_request_raw()
+            .withf(|method, _| method == "starknet_getBlockWithTxs")
+            .times(1)
+            .returning(move |_, _| {
+                Ok(serde_json::value::RawValue::from_string(raw_block.to_string()).unwrap())
+            });
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+        let block_hash = FieldElement::from_hex_be("0xabc").unwrap();
+        let mut node = NodeData::new();
+        apply(
+            &mut node,
+            StoreCommand::VerifiedBlock {
+                block: Arc::new(block_with_single_invoke(block_hash)),
+                events: Arc::new(vec![]),
+            },
+        );
+        beerus.node.store(Arc::new(node));
+
+        // When
+        let relayed = beerus
+            .get_block_with_txs_passthrough(&BlockId::Number(12))
+            .await;
+        let retained = beerus
+            .get_block_with_txs_passthrough(&BlockId::Hash(block_hash))
+            .await;
+
+        // Then
+        match relayed.unwrap() {
+            Passthrough::Raw(block) => assert_eq!(block.get(), raw_block),
+            _ => panic!("Expected a relayed block"),
+        }
+        assert!(matches!(retained.unwrap(), Passthrough::Parsed(_)));
+    }
+
+    /// Test that the sync status follows the progress of the syncer and the provider outages,
+    /// and that its transitions are published.
+    #[tokio::test]
+    async fn given_started_light_client_when_syncer_reports_then_sync_status_transitions() {
+        // Given
+        let (config, mut ethereum_lightclient_mock, mut starknet_lightclient_mock) = mock_clients();
+        ethereum_lightclient_mock
+            .expect_start()
+            .return_once(move || Ok(()));
+        starknet_lightclient_mock
+            .expect_start()
+            .return_once(move || Ok(()));
+        let beerus = BeerusHandle::from(BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        ));
+        let mut status = beerus.subscribe_sync_status();
+        let progress = SyncProgress::measure(20_000, 200, 10, 1_000);
+        let mut node = NodeData::new();
+
+        // When
+        beerus.start_in_background();
+        status.changed().await.unwrap();
+        let started = status.borrow_and_update().clone();
+        apply(&mut node, StoreCommand::SyncProgress(progress.clone()));
+        let catching_up = node.sync_status();
+        apply(
+            &mut node,
+            StoreCommand::StarknetProvider(
+                ProviderStatus::default().record::<(), _>(&Err("connection refused")),
+            ),
+        );
+        let degraded = node.sync_status();
+        apply(
+            &mut node,
+            StoreCommand::StarknetProvider(ProviderStatus::default()),
+        );
+        apply(
+            &mut node,
+            StoreCommand::SyncProgress(SyncProgress::measure(20_000, 1_000, 10, 0)),
+        );
+        let caught_up = node.sync_status();
+
+        // Then
+        assert_eq!(started, SyncStatus::Synced);
+        assert_eq!(catching_up, SyncStatus::Syncing(progress));
+        assert_eq!(degraded, SyncStatus::Degraded);
+        assert_eq!(caught_up, SyncStatus::Synced);
+    }
+
+    /// Test that the syncer backfills from the requested block, or else the first block missed
+    /// since the previous poll, and never more than `MAX_BACKFILL_BLOCKS` blocks.
+    #[test]
+    fn given_gap_before_latest_block_when_backfill_from_then_returns_first_missing_block() {
+        // Given
+        let latest_block_number = 5_000;
+
+        // When
+        let at_start = backfill_from(Some(4_500), 0, latest_block_number);
+        let after_outage = backfill_from(None, 4_990, latest_block_number);
+        let after_days_off = backfill_from(Some(10), 0, latest_block_number);
+        let next_block = backfill_from(None, 4_999, latest_block_number);
+        let after_resync = backfill_from(None, 0, latest_block_number);
+
+        // Then
+        assert_eq!(at_start, Some(4_500));
+        assert_eq!(after_outage, Some(4_991));
+        assert_eq!(
+            after_days_off,
+            Some(latest_block_number - MAX_BACKFILL_BLOCKS)
+        );
+        assert_eq!(next_block, None);
+        assert_eq!(after_resync, None);
+    }
+
+    /// Test that the sync tasks are restarted with a growing backoff when the syncer panics, and
+    /// that the failures are reported in the sync status and the task health.
+    #[tokio::test]
+    async fn given_panicking_syncer_when_started_then_sync_tasks_are_restarted() {
+        // Given
+        let (config, mut ethereum_lightclient_mock, mut starknet_lightclient_mock) = mock_clients();
+        ethereum_lightclient_mock
+            .expect_start()
+            .return_once(move || Ok(()));
+        ethereum_lightclient_mock
+            .expect_get_block_number()
+            .returning(move || Ok(1));
+        // L1 errors are retried by the syncer, a panic stops it.
+        ethereum_lightclient_mock
+            .expect_starknet_proven_state()
+            .returning(move || panic!("L1 unavailable"));
+        starknet_lightclient_mock
+            .expect_start()
+            .return_once(move || Ok(()));
+        let beerus = BeerusHandle::from(BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        ));
+        let mut status = beerus.subscribe_sync_status();
+
+        // When
+        beerus.start_in_background();
+        while *status.borrow_and_update() != SyncStatus::Restarting {
+            status.changed().await.unwrap();
+        }
+        let first_failure = beerus.sync_tasks_health();
+        // The restarted syncer panics again.
+        status.changed().await.unwrap();
+        let second_failure = beerus.beerus_status().await.sync_tasks;
+
+        // Then
+        assert!(!first_failure.running);
+        assert_eq!(first_failure.failures, 1);
+        assert_eq!(
+            first_failure.restart_backoff_ms,
+            RESTART_MIN_BACKOFF.as_millis() as u64
+        );
+        let last_failure = first_failure.last_failure.unwrap();
+        assert!(last_failure.starts_with("syncer panicked"));
+        assert!(last_failure.contains("L1 unavailable"));
+        assert_eq!(beerus.sync_status(), SyncStatus::Restarting);
+        assert_eq!(second_failure.failures, 2);
+        assert_eq!(
+            second_failure.restart_backoff_ms,
+            2 * RESTART_MIN_BACKOFF.as_millis() as u64
+        );
+    }
+
+    /// Test that the nonce manager suggests the nonce after the transactions it submitted, and
+    /// starts over from the chain once the provider rejects a nonce.
+    #[tokio::test]
+    async fn given_submitted_invoke_when_next_nonce_then_follows_it_until_nonce_rejected() {
+        // Given
+        let (config, mut ethereum_lightclient_mock, mut starknet_lightclient_mock) = mock_clients();
+        ethereum_lightclient_mock
+            .expect_starknet_last_proven_block()
+            .returning(move || Ok(U256::from(10)));
+        starknet_lightclient_mock
+            .expect_get_nonce()
+            .returning(move |_, _| Ok(FieldElement::from(3_u64)));
+        starknet_lightclient_mock
+            .expect_get_nonce_at()
+            .returning(move |_, _| Ok(FieldElement::from(3_u64)));
+        let mut submissions = 0;
+        starknet_lightclient_mock
+            .expect_add_invoke_transaction()
+            .returning(move |_| {
+                submissions += 1;
+                if submissions == 1 {
+                    Ok(InvokeTransactionResult {
+                        transaction_hash: FieldElement::from(0x33_u64),
+                    })
+                } else {
+                    Err(eyre!("StarknetErrorCode.INVALID_TRANSACTION_NONCE"))
+                }
+            });
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+        let manager = NonceManager::new();
+        let account = FieldElement::ONE;
+        let transaction = |nonce| {
+            BroadcastedInvokeTransaction::V0(BroadcastedInvokeTransactionV0 {
+                max_fee: FieldElement::ZERO,
+                signature: vec![],
+                nonce,
+                contract_address: account,
+                entry_point_selector: FieldElement::ZERO,
+                calldata: vec![],
+            })
+        };
+
+        // When
+        let nonce = manager.next_nonce(&beerus, account).await.unwrap();
+        manager
+            .add_invoke_transaction(&beerus, &transaction(nonce))
+            .await
+            .unwrap();
+        let next_nonce = manager.next_nonce(&beerus, account).await.unwrap();
+        let rejected = manager
+            .add_invoke_transaction(&beerus, &transaction(next_nonce))
+            .await;
+
+        // Then
+        assert_eq!(nonce, FieldElement::from(3_u64));
+        assert_eq!(next_nonce, FieldElement::from(4_u64));
+        assert!(rejected.is_err());
+        assert_eq!(
+            manager.next_nonce(&beerus, account).await.unwrap(),
+            FieldElement::from(3_u64)
+        );
+    }
+
+    /// Test that a function is called by name, with its arguments encoded and its result
+    /// decoded following the ABI of the contract.
+    #[tokio::test]
+    async fn given_abi_when_call_function_then_arguments_encoded_and_result_decoded() {
+        // Given
+        let (config, mut ethereum_lightclient_mock, mut starknet_lightclient_mock) = mock_clients();
+        let contract_address = FieldElement::from_hex_be("0x49d3").unwrap();
+        let account = FieldElement::from_hex_be("0x123").unwrap();
+        starknet_lightclient_mock
+            .expect_call()
+            .withf(move |req, block_number| {
+                req.contract_address == contract_address
+                    && req.entry_point_selector == selector!("balance_of")
+                    && req.calldata == vec![account]
+                    && *block_number == 10
+            })
+            .times(1)
+            .return_once(|_, _| Ok(vec![FieldElement::from(5_u64), FieldElement::ONE]));
+        ethereum_lightclient_mock
+            .expect_starknet_last_proven_block()
+            .return_once(|| Ok(U256::from(10)));
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient_mock),
+            Box::new(starknet_lightclient_mock),
+        );
+        let abi = ContractAbi::from_json(&serde_json::json!([
+            {"type": "struct", "name": "core::integer::u256", "members": [
+                {"name": "low", "type": "core::integer::u128"},
+                {"name": "high", "type": "core::integer::u128"}
+            ]},
+            {"type": "function", "name": "balance_of", "inputs": [
+                {"name": "account", "type": "core::starknet::contract_address::ContractAddress"}
+            ], "outputs": [{"type": "core::integer::u256"}], "state_mutability": "view"}
+        ]))
+        .unwrap();
+
+        // When
+        let result = beerus
+            .starknet_call_function(contract_address, &abi, "balance_of", &[account.into()])
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(result.len(), 1);
+        assert_eq!(
+            result[0].as_u256(),
+            Some((U256::from(1) << 128) + U256::from(5))
+        );
+        assert!(beerus
+            .starknet_call_function(contract_address, &abi, "transfer", &[])
+            .await
+            .is_err());
+    }
 }
@@ -1,5 +1,8 @@
 use beerus_core::{
-    config::Config,
+    config::{
+        Config, HttpConfig, DEFAULT_STARKNET_BATCH_FLUSH_INTERVAL_MS,
+        DEFAULT_STARKNET_BATCH_MAX_SIZE,
+    },
     lightclient::{
         ethereum::MockEthereumLightClient,
         starknet::{storage_proof::GetProofOutput, MockStarkNetLightClient},
@@ -20,7 +23,22 @@ pub fn mock_clients() -> (Config, MockEthereumLightClient, MockStarkNetLightClie
         ethereum_consensus_rpc: "http://localhost:8545".to_string(),
         ethereum_execution_rpc: "http://localhost:8545".to_string(),
         starknet_rpc: "http://localhost:8545".to_string(),
+        starknet_rpc_routes: Default::default(),
         data_dir: Some(PathBuf::from("/tmp")),
+        starknet_batch_max_size: DEFAULT_STARKNET_BATCH_MAX_SIZE,
+        starknet_batch_flush_interval_ms: DEFAULT_STARKNET_BATCH_FLUSH_INTERVAL_MS,
+        payload_compression_level: None,
+        payload_headers_only: false,
+        archive_mode: false,
+        prefetch_receipts: false,
+        starknet_rpc_passthrough: false,
+        memory_budget_mb: None,
+        block_number_source: Default::default(),
+        finality: Default::default(),
+        starknet_feeder_gateway: None,
+        block_sources: Default::default(),
+        http: HttpConfig::default(),
+        alerts: Default::default(),
         starknet_core_contract_address: Address::from_str(
             "0x0000000000000000000000000000000000000000",
         )
@@ -122,7 +140,22 @@ pub fn mock_config(server: &MockServer) -> Config {
         ethereum_consensus_rpc: server.base_url(),
         ethereum_execution_rpc: server.base_url(),
         data_dir: Some(PathBuf::from("/tmp")),
+        starknet_batch_max_size: DEFAULT_STARKNET_BATCH_MAX_SIZE,
+        starknet_batch_flush_interval_ms: DEFAULT_STARKNET_BATCH_FLUSH_INTERVAL_MS,
+        payload_compression_level: None,
+        payload_headers_only: false,
+        archive_mode: false,
+        prefetch_receipts: false,
+        starknet_rpc_passthrough: false,
+        memory_budget_mb: None,
+        block_number_source: Default::default(),
+        finality: Default::default(),
+        starknet_feeder_gateway: None,
+        block_sources: Default::default(),
+        http: HttpConfig::default(),
+        alerts: Default::default(),
         starknet_rpc: server.base_url(),
+        starknet_rpc_routes: Default::default(),
         starknet_core_contract_address: Address::from_str(
             "0x0000000000000000000000000000000000000000",
         )
@@ -1,6 +1,10 @@
 #[cfg(test)]
 mod tests {
-    use beerus_core::config::Config;
+    use beerus_core::config::{
+        parse_routes, AlertConfig, BlockNumberSource, BlockSource, BlockSources, Config, Finality,
+        HttpConfig, Profile, DEFAULT_ALERT_SYNC_STALL_SECS,
+        DEFAULT_STARKNET_BATCH_FLUSH_INTERVAL_MS, DEFAULT_STARKNET_BATCH_MAX_SIZE,
+    };
     use ethers::types::Address;
     use helios::config::networks::Network;
     use std::{path::PathBuf, str::FromStr};
@@ -136,7 +140,22 @@ mod tests {
             ethereum_consensus_rpc: "http://localhost:8545".to_string(),
             ethereum_execution_rpc: "http://localhost:8545".to_string(),
             starknet_rpc: "http://localhost:8545".to_string(),
+            starknet_rpc_routes: Default::default(),
             data_dir: Some(PathBuf::from("/tmp")),
+            starknet_batch_max_size: DEFAULT_STARKNET_BATCH_MAX_SIZE,
+            starknet_batch_flush_interval_ms: DEFAULT_STARKNET_BATCH_FLUSH_INTERVAL_MS,
+            payload_compression_level: None,
+            payload_headers_only: false,
+            archive_mode: false,
+            prefetch_receipts: false,
+            starknet_rpc_passthrough: false,
+            memory_budget_mb: None,
+            block_number_source: Default::default(),
+            finality: Default::default(),
+            starknet_feeder_gateway: None,
+            block_sources: Default::default(),
+            http: HttpConfig::default(),
+            alerts: Default::default(),
             starknet_core_contract_address: Address::from_str(
                 "0x0000000000000000000000000000000000000000",
             )
@@ -157,7 +176,22 @@ mod tests {
             ethereum_consensus_rpc: "http://localhost:8545".to_string(),
             ethereum_execution_rpc: "http://localhost:8545".to_string(),
             starknet_rpc: "http://localhost:8545".to_string(),
+            starknet_rpc_routes: Default::default(),
             data_dir: Some(PathBuf::from("/tmp")),
+            starknet_batch_max_size: DEFAULT_STARKNET_BATCH_MAX_SIZE,
+            starknet_batch_flush_interval_ms: DEFAULT_STARKNET_BATCH_FLUSH_INTERVAL_MS,
+            payload_compression_level: None,
+            payload_headers_only: false,
+            archive_mode: false,
+            prefetch_receipts: false,
+            starknet_rpc_passthrough: false,
+            memory_budget_mb: None,
+            block_number_source: Default::default(),
+            finality: Default::default(),
+            starknet_feeder_gateway: None,
+            block_sources: Default::default(),
+            http: HttpConfig::default(),
+            alerts: Default::default(),
             starknet_core_contract_address: Address::from_str(
                 "0x0000000000000000000000000000000000000000",
             )
@@ -178,7 +212,22 @@ mod tests {
             ethereum_consensus_rpc: "http://localhost:8545".to_string(),
             ethereum_execution_rpc: "http://localhost:8545".to_string(),
             starknet_rpc: "http://localhost:8545".to_string(),
+            starknet_rpc_routes: Default::default(),
             data_dir: Some(PathBuf::from("/tmp")),
+            starknet_batch_max_size: DEFAULT_STARKNET_BATCH_MAX_SIZE,
+            starknet_batch_flush_interval_ms: DEFAULT_STARKNET_BATCH_FLUSH_INTERVAL_MS,
+            payload_compression_level: None,
+            payload_headers_only: false,
+            archive_mode: false,
+            prefetch_receipts: false,
+            starknet_rpc_passthrough: false,
+            memory_budget_mb: None,
+            block_number_source: Default::default(),
+            finality: Default::default(),
+            starknet_feeder_gateway: None,
+            block_sources: Default::default(),
+            http: HttpConfig::default(),
+            alerts: Default::default(),
             starknet_core_contract_address: Address::from_str(
                 "0x0000000000000000000000000000000000000000",
             )
@@ -212,4 +261,387 @@ mod tests {
             },
         );
     }
+
+    /// Test `new_from_env` function when `STARKNET_BATCH_MAX_SIZE` is not a number.
+    /// It should return an error.
+    #[test]
+    fn given_invalid_batch_max_size_when_new_from_env_then_returns_error() {
+        temp_env::with_vars(
+            vec![
+                ("ETHEREUM_CONSENSUS_RPC_URL", Some("http://localhost:8545")),
+                ("ETHEREUM_EXECUTION_RPC_URL", Some("http://localhost:8545")),
+                ("STARKNET_RPC_URL", Some("http://localhost:8545")),
+                ("STARKNET_BATCH_MAX_SIZE", Some("many")),
+            ],
+            || match Config::new_from_env() {
+                Ok(_) => panic!("Should return an error"),
+                Err(err) => assert_eq!(err.to_string(), "Invalid STARKNET_BATCH_MAX_SIZE: many"),
+            },
+        );
+    }
+
+    /// Test `new_from_env` function when `PAYLOAD_COMPRESSION_LEVEL` is not a number.
+    /// It should return an error.
+    #[test]
+    fn given_invalid_payload_compression_level_when_new_from_env_then_returns_error() {
+        temp_env::with_vars(
+            vec![
+                ("ETHEREUM_CONSENSUS_RPC_URL", Some("http://localhost:8545")),
+                ("ETHEREUM_EXECUTION_RPC_URL", Some("http://localhost:8545")),
+                ("STARKNET_RPC_URL", Some("http://localhost:8545")),
+                ("PAYLOAD_COMPRESSION_LEVEL", Some("max")),
+            ],
+            || match Config::new_from_env() {
+                Ok(_) => panic!("Should return an error"),
+                Err(err) => assert_eq!(err.to_string(), "Invalid PAYLOAD_COMPRESSION_LEVEL: max"),
+            },
+        );
+    }
+
+    /// Test `new_from_env` function with HTTP client settings.
+    /// It should read them and use the defaults for the missing ones.
+    #[test]
+    fn given_http_settings_when_new_from_env_then_returns_http_config() {
+        temp_env::with_vars(
+            vec![
+                ("ETHEREUM_CONSENSUS_RPC_URL", Some("http://localhost:8545")),
+                ("ETHEREUM_EXECUTION_RPC_URL", Some("http://localhost:8545")),
+                ("STARKNET_RPC_URL", Some("http://localhost:8545")),
+                ("HTTP_TIMEOUT_MS", Some("5000")),
+                ("HTTP2_PRIOR_KNOWLEDGE", Some("true")),
+                ("HTTP_PROXY_URL", Some("socks5://127.0.0.1:1080")),
+            ],
+            || {
+                let config = Config::new_from_env().unwrap();
+                assert_eq!(
+                    config.http,
+                    HttpConfig {
+                        timeout_ms: 5000,
+                        http2_prior_knowledge: true,
+                        proxy: Some("socks5://127.0.0.1:1080".to_string()),
+                        ..HttpConfig::default()
+                    }
+                );
+                assert!(config.http.build_client().is_ok());
+            },
+        );
+    }
+
+    /// Test `new_from_env` function with alert hooks.
+    /// It should read the webhooks separated by commas and keep the default stall delay.
+    #[test]
+    fn given_alert_hooks_when_new_from_env_then_returns_alert_config() {
+        temp_env::with_vars(
+            vec![
+                ("ETHEREUM_CONSENSUS_RPC_URL", Some("http://localhost:8545")),
+                ("ETHEREUM_EXECUTION_RPC_URL", Some("http://localhost:8545")),
+                ("STARKNET_RPC_URL", Some("http://localhost:8545")),
+                (
+                    "ALERT_WEBHOOK_URLS",
+                    Some("https://hooks.example/a, https://hooks.example/b,"),
+                ),
+                ("ALERT_COMMAND", Some("notify-send beerus")),
+            ],
+            || {
+                let config = Config::new_from_env().unwrap();
+                assert_eq!(
+                    config.alerts,
+                    AlertConfig {
+                        webhook_urls: vec![
+                            "https://hooks.example/a".to_string(),
+                            "https://hooks.example/b".to_string()
+                        ],
+                        command: Some("notify-send beerus".to_string()),
+                        sync_stall_secs: DEFAULT_ALERT_SYNC_STALL_SECS,
+                    }
+                );
+            },
+        );
+    }
+
+    /// Test `build_client` function with an invalid proxy URL.
+    /// It should return an error.
+    #[test]
+    fn given_invalid_proxy_when_build_client_then_returns_error() {
+        let http = HttpConfig {
+            proxy: Some("not a url".to_string()),
+            ..HttpConfig::default()
+        };
+
+        match http.build_client() {
+            Ok(_) => panic!("Should return an error"),
+            Err(err) => assert_eq!(err.to_string(), "Invalid HTTP_PROXY_URL: not a url"),
+        }
+    }
+
+    /// Test `parse_routes` function.
+    /// It should return the url of each method.
+    #[test]
+    fn given_routes_when_parse_routes_then_returns_routes() {
+        let routes = parse_routes(
+            "starknet_traceTransaction=https://archive.node, starknet_call=https://cheap.node",
+        )
+        .unwrap();
+
+        assert_eq!(routes.len(), 2);
+        assert_eq!(routes["starknet_traceTransaction"], "https://archive.node");
+        assert_eq!(routes["starknet_call"], "https://cheap.node");
+    }
+
+    /// Test `parse_routes` function with an entry missing its url.
+    /// It should return an error.
+    #[test]
+    fn given_route_without_url_when_parse_routes_then_returns_error() {
+        match parse_routes("starknet_call") {
+            Ok(_) => panic!("Should return an error"),
+            Err(err) => assert_eq!(
+                err.to_string(),
+                "Invalid STARKNET_RPC_ROUTES entry: starknet_call"
+            ),
+        }
+    }
+
+    /// Test that `BLOCK_NUMBER_SOURCE` selects the source of the latest block.
+    #[test]
+    fn given_block_number_source_when_new_from_env_then_returns_config() {
+        temp_env::with_vars(
+            vec![
+                ("ETHEREUM_CONSENSUS_RPC_URL", Some("http://localhost:8545")),
+                ("ETHEREUM_EXECUTION_RPC_URL", Some("http://localhost:8545")),
+                ("STARKNET_RPC_URL", Some("http://localhost:8545")),
+                ("BLOCK_NUMBER_SOURCE", Some("upstream")),
+            ],
+            || {
+                let config = Config::new_from_env().unwrap();
+                assert_eq!(config.block_number_source, BlockNumberSource::Upstream);
+            },
+        );
+        assert_eq!(BlockNumberSource::default(), BlockNumberSource::Proven);
+        assert!(BlockNumberSource::from_str("head").is_err());
+    }
+
+    /// Test that `FINALITY` sets the finality of the queries, `l1_accepted` by default.
+    #[test]
+    fn given_finality_when_new_from_env_then_returns_config() {
+        temp_env::with_vars(
+            vec![
+                ("ETHEREUM_CONSENSUS_RPC_URL", Some("http://localhost:8545")),
+                ("ETHEREUM_EXECUTION_RPC_URL", Some("http://localhost:8545")),
+                ("STARKNET_RPC_URL", Some("http://localhost:8545")),
+                ("FINALITY", Some("L2_ACCEPTED")),
+            ],
+            || {
+                let config = Config::new_from_env().unwrap();
+                assert_eq!(config.finality, Finality::L2Accepted);
+            },
+        );
+        assert_eq!(Finality::default(), Finality::L1Accepted);
+        assert!(Finality::from_str("finalized").is_err());
+    }
+
+    /// Test that `BLOCK_SOURCES` sets the priority of the sources of blocks.
+    #[test]
+    fn given_block_sources_when_new_from_env_then_returns_config() {
+        temp_env::with_vars(
+            vec![
+                ("ETHEREUM_CONSENSUS_RPC_URL", Some("http://localhost:8545")),
+                ("ETHEREUM_EXECUTION_RPC_URL", Some("http://localhost:8545")),
+                ("STARKNET_RPC_URL", Some("http://localhost:8545")),
+                (
+                    "STARKNET_FEEDER_GATEWAY_URL",
+                    Some("https://alpha4.starknet.io"),
+                ),
+                ("BLOCK_SOURCES", Some("feeder_gateway, rpc")),
+            ],
+            || {
+                let config = Config::new_from_env().unwrap();
+                assert_eq!(
+                    config.starknet_feeder_gateway,
+                    Some("https://alpha4.starknet.io".to_string())
+                );
+                assert_eq!(
+                    config.block_sources,
+                    BlockSources(vec![BlockSource::FeederGateway, BlockSource::Rpc])
+                );
+            },
+        );
+        assert_eq!(
+            BlockSources::default(),
+            BlockSources(vec![BlockSource::Rpc, BlockSource::FeederGateway])
+        );
+        assert!(BlockSources::from_str("rpc,sequencer").is_err());
+        assert!(BlockSources::from_str("").is_err());
+    }
+
+    /// Test that `DATA_DIR` sets the root of the data directory, and its subdirectories.
+    #[test]
+    fn given_data_dir_when_new_from_env_then_returns_layout() {
+        temp_env::with_vars(
+            vec![
+                ("ETHEREUM_CONSENSUS_RPC_URL", Some("http://localhost:8545")),
+                ("ETHEREUM_EXECUTION_RPC_URL", Some("http://localhost:8545")),
+                ("STARKNET_RPC_URL", Some("http://localhost:8545")),
+                ("DATA_DIR", Some("/var/lib/beerus")),
+            ],
+            || {
+                let data_dir = Config::new_from_env().unwrap().data_layout();
+                assert_eq!(data_dir.root(), PathBuf::from("/var/lib/beerus"));
+                assert_eq!(data_dir.helios(), PathBuf::from("/var/lib/beerus/helios"));
+                assert_eq!(
+                    data_dir.keystores(),
+                    PathBuf::from("/var/lib/beerus/accounts")
+                );
+            },
+        );
+    }
+
+    const PROFILES: &str = r#"
+ethereum_consensus_rpc_url = "http://localhost:5052"
+ethereum_execution_rpc_url = "http://localhost:8545"
+finality = "l1_accepted"
+
+[profiles.mainnet]
+ethereum_network = "mainnet"
+starknet_rpc_url = "http://localhost:9545"
+data_dir = "/var/lib/beerus/mainnet"
+block_sources = ["feeder_gateway", "rpc"]
+http_timeout_ms = 5000
+
+[profiles.mainnet.starknet_rpc_routes]
+starknet_traceTransaction = "http://archive.node"
+
+[profiles.dev]
+starknet_rpc_url = "http://localhost:5050"
+finality = "l2_accepted"
+"#;
+
+    /// Test that a profile overrides the shared settings, and the environment variables both.
+    #[test]
+    fn given_profile_when_new_from_profile_then_returns_config() {
+        temp_env::with_vars(
+            vec![
+                ("ETHEREUM_NETWORK", None),
+                ("ETHEREUM_CONSENSUS_RPC_URL", None),
+                ("ETHEREUM_EXECUTION_RPC_URL", Some("http://execution.env")),
+                ("STARKNET_RPC_URL", None),
+                ("STARKNET_RPC_ROUTES", None),
+                ("DATA_DIR", None),
+                ("FINALITY", None),
+                ("BLOCK_SOURCES", None),
+                ("HTTP_TIMEOUT_MS", None),
+            ],
+            || {
+                let mainnet =
+                    Config::new_from_profile(&Profile::parse(PROFILES, "mainnet").unwrap())
+                        .unwrap();
+                assert_eq!(mainnet.ethereum_network, "mainnet");
+                assert_eq!(mainnet.ethereum_consensus_rpc, "http://localhost:5052");
+                assert_eq!(mainnet.ethereum_execution_rpc, "http://execution.env");
+                assert_eq!(mainnet.starknet_rpc, "http://localhost:9545");
+                assert_eq!(
+                    mainnet.starknet_rpc_routes.get("starknet_traceTransaction"),
+                    Some(&"http://archive.node".to_string())
+                );
+                assert_eq!(
+                    mainnet.data_dir,
+                    Some(PathBuf::from("/var/lib/beerus/mainnet"))
+                );
+                assert_eq!(mainnet.finality, Finality::L1Accepted);
+                assert_eq!(
+                    mainnet.block_sources,
+                    BlockSources(vec![BlockSource::FeederGateway, BlockSource::Rpc])
+                );
+                assert_eq!(mainnet.http.timeout_ms, 5_000);
+
+                let dev =
+                    Config::new_from_profile(&Profile::parse(PROFILES, "dev").unwrap()).unwrap();
+                assert_eq!(dev.ethereum_network, "goerli");
+                assert_eq!(dev.starknet_rpc, "http://localhost:5050");
+                assert_eq!(dev.finality, Finality::L2Accepted);
+                assert!(dev.starknet_rpc_routes.is_empty());
+            },
+        );
+    }
+
+    /// Test that an unknown profile is reported along with the available ones.
+    #[test]
+    fn given_unknown_profile_when_parse_then_returns_error() {
+        let err = Profile::parse(PROFILES, "testnet").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unknown profile testnet, available profiles: dev, mainnet"
+        );
+        assert!(Profile::parse("[profiles.dev]\nstarted = 1979-05-27", "dev").is_err());
+        assert!(Profile::load(None, None).unwrap().name.is_empty());
+    }
+
+    /// Test that `from_parts` keeps the given parts and defaults every other setting.
+    #[test]
+    fn given_parts_when_from_parts_then_returns_config_with_defaults() {
+        let address = Address::from_str("0x0000000000000000000000000000000000000000").unwrap();
+
+        let config = Config::from_parts(
+            "mainnet".to_string(),
+            "http://localhost:5052".to_string(),
+            "http://localhost:8545".to_string(),
+            "http://localhost:9545".to_string(),
+            address,
+            Some(PathBuf::from("/tmp")),
+        );
+
+        assert_eq!(config.ethereum_network, "mainnet");
+        assert_eq!(config.ethereum_consensus_rpc, "http://localhost:5052");
+        assert_eq!(config.ethereum_execution_rpc, "http://localhost:8545");
+        assert_eq!(config.starknet_rpc, "http://localhost:9545");
+        assert_eq!(config.starknet_core_contract_address, address);
+        assert_eq!(config.data_dir, Some(PathBuf::from("/tmp")));
+        assert!(config.starknet_rpc_routes.is_empty());
+        assert_eq!(
+            config.starknet_batch_max_size,
+            DEFAULT_STARKNET_BATCH_MAX_SIZE
+        );
+        assert_eq!(
+            config.starknet_batch_flush_interval_ms,
+            DEFAULT_STARKNET_BATCH_FLUSH_INTERVAL_MS
+        );
+        assert!(!config.archive_mode);
+        assert_eq!(config.memory_budget_mb, None);
+        assert_eq!(config.block_number_source, BlockNumberSource::default());
+        assert_eq!(config.finality, Finality::default());
+        assert_eq!(config.block_sources, BlockSources::default());
+        assert_eq!(config.http, HttpConfig::default());
+        assert_eq!(config.alerts, AlertConfig::default());
+    }
+
+    /// Test that the memory page fact registry is only known on goerli and mainnet.
+    #[test]
+    fn given_unknown_network_when_memory_page_fact_registry_address_then_returns_error() {
+        temp_env::with_vars(
+            vec![
+                ("ETHEREUM_NETWORK", Some("sepolia")),
+                ("ETHEREUM_CONSENSUS_RPC_URL", Some("http://localhost:8545")),
+                ("ETHEREUM_EXECUTION_RPC_URL", Some("http://localhost:8545")),
+                ("STARKNET_RPC_URL", Some("http://localhost:8545")),
+            ],
+            || {
+                let sepolia = Config::new_from_env().unwrap();
+                let mainnet = Config {
+                    ethereum_network: "mainnet".to_string(),
+                    ..sepolia.clone()
+                };
+
+                assert_eq!(
+                    mainnet.memory_page_fact_registry_address().unwrap(),
+                    Address::from_str("0x96375087b2F6eFc59e5e0dd5111B4d090EBFDD8B").unwrap()
+                );
+                assert_eq!(
+                    sepolia
+                        .memory_page_fact_registry_address()
+                        .unwrap_err()
+                        .to_string(),
+                    "No known memory page fact registry on the sepolia network"
+                );
+            },
+        );
+    }
 }
@@ -5,13 +5,19 @@ use common::{mock_call, mock_config, mock_get_contract_storage_proof, mock_get_s
 mod test {
     use super::*;
     use beerus_core::lightclient::{
-        beerus::BeerusLightClient, ethereum::MockEthereumLightClient,
-        starknet::StarkNetLightClientImpl,
+        beerus::BeerusLightClient,
+        ethereum::MockEthereumLightClient,
+        starknet::{StarkNetLightClient, StarkNetLightClientImpl},
+        transport::faults::FaultConfig,
     };
     use ethers::types::U256;
     use eyre::eyre;
     use httpmock::prelude::*;
-    use starknet::{core::types::FieldElement, providers::jsonrpc::models::BlockId};
+    use serde_json::json;
+    use starknet::{
+        core::types::FieldElement,
+        providers::jsonrpc::models::{BlockId, BlockTag, MaybePendingBlockWithTxs},
+    };
     use std::str::FromStr;
 
     #[tokio::test]
@@ -154,4 +160,110 @@ mod test {
         mock.assert();
         assert_eq!(proof.unwrap(), expected_proof);
     }
+
+    #[tokio::test]
+    async fn given_routed_method_when_starknet_get_storage_at_then_uses_routed_endpoint() {
+        // Given
+        let server = MockServer::start();
+        let routed_server = MockServer::start();
+        let default_request = mock_get_storage_at(&server);
+        let routed_request = mock_get_storage_at(&routed_server);
+        let mut config = mock_config(&server);
+        config.starknet_rpc_routes.insert(
+            "starknet_getStorageAt".to_string(),
+            routed_server.base_url(),
+        );
+
+        let starknet_lightclient = Box::new(StarkNetLightClientImpl::new(&config).unwrap());
+        let mut helios_lightclient = MockEthereumLightClient::new();
+        helios_lightclient
+            .expect_starknet_last_proven_block()
+            .return_once(move || Ok(U256::from(1)));
+        let beerus =
+            BeerusLightClient::new(config, Box::new(helios_lightclient), starknet_lightclient);
+
+        // When
+        let storage_var = beerus
+            .starknet_get_storage_at(
+                FieldElement::from_str("0x00").unwrap(),
+                FieldElement::from_str("0x00").unwrap(),
+            )
+            .await
+            .unwrap();
+
+        // Then
+        routed_request.assert();
+        assert_eq!(default_request.hits(), 0);
+        assert_eq!(storage_var, FieldElement::from_str("0x01").unwrap());
+    }
+
+    #[tokio::test]
+    async fn given_failing_provider_when_get_block_with_txs_then_falls_back_to_feeder_gateway() {
+        // Given
+        let server = MockServer::start();
+        let feeder_gateway = MockServer::start();
+        let gateway_request = feeder_gateway.mock(|when, then| {
+            when.method(GET)
+                .path("/feeder_gateway/get_block")
+                .query_param("blockNumber", "latest");
+            then.status(200)
+                .header("content-type", "application/json")
+                .json_body(json!({
+                    "block_hash": "0x10",
+                    "parent_block_hash": "0x9",
+                    "block_number": 5,
+                    "state_root": "0x123",
+                    "status": "ACCEPTED_ON_L2",
+                    "timestamp": 1000,
+                    "sequencer_address": "0x2",
+                    "transactions": [],
+                    "transaction_receipts": []
+                }));
+        });
+        let mut config = mock_config(&server);
+        config.starknet_feeder_gateway = Some(feeder_gateway.base_url());
+        let starknet_lightclient = StarkNetLightClientImpl::new(&config).unwrap();
+
+        // When
+        let block = starknet_lightclient
+            .get_block_with_txs(&BlockId::Tag(BlockTag::Latest))
+            .await
+            .unwrap();
+
+        // Then
+        gateway_request.assert();
+        match block {
+            MaybePendingBlockWithTxs::Block(block) => assert_eq!(block.block_number, 5),
+            MaybePendingBlockWithTxs::PendingBlock(_) => panic!("Expected a block"),
+        }
+    }
+
+    /// Test that the injected faults fail the requests before they reach the provider, and are
+    /// counted in its health.
+    #[tokio::test]
+    async fn given_injected_faults_when_starknet_get_storage_at_then_should_fail() {
+        // Given
+        let server = MockServer::start();
+        let mock_request = mock_get_storage_at(&server);
+        let config = mock_config(&server);
+        let faults: FaultConfig = "error_rate=0.5,malformed_rate=0.5".parse().unwrap();
+        let starknet_lightclient = StarkNetLightClientImpl::with_faults(&config, faults).unwrap();
+
+        // When
+        let mut results = Vec::new();
+        for _ in 0..10 {
+            results.push(
+                starknet_lightclient
+                    .get_storage_at(FieldElement::ZERO, FieldElement::ZERO, 1)
+                    .await,
+            );
+        }
+
+        // Then
+        mock_request.assert_hits(0);
+        assert!(results.iter().all(|result| result.is_err()));
+        let health = starknet_lightclient.provider_health();
+        assert_eq!(health[0].requests, 10);
+        assert!(0 < health[0].failures && health[0].failures < 10);
+    }
 }
@@ -1,4 +1,7 @@
-use beerus_core::lightclient::starknet::storage_proof::{GetProofOutput, Membership};
+use beerus_core::lightclient::starknet::{
+    storage_proof::{GetProofOutput, Membership, ProofNode},
+    trie::{binary_hash, global_state_root, PedersenHash},
+};
 use serde::Deserialize;
 use starknet::core::types::FieldElement;
 use std::fs;
@@ -30,3 +33,25 @@ fn non_membership() {
 
     assert_eq!(memberships, Some(vec![Some(Membership::NonMember)]));
 }
+
+#[test]
+fn legacy_global_state_root() {
+    let path = "tests/data.json";
+    let s = fs::read_to_string(path).unwrap();
+
+    // Mainnet block proven before StarkNet v0.11, whose state has no class trie.
+    let state_root = FieldElement::from_hex_be(
+        "0x47f25798a804800b657d4e1508776e3c3c70f0d7587d125a558208f88570aa7",
+    )
+    .unwrap();
+    let j: JsonOutput = serde_json::from_str(&s).unwrap();
+    let contracts_root = match &j.result.contract_proof[0] {
+        ProofNode::Binary(node) => binary_hash::<PedersenHash>(&node.left, &node.right),
+        ProofNode::Edge(_) => panic!("The contract trie root is a binary node"),
+    };
+
+    assert_eq!(
+        global_state_root(contracts_root, FieldElement::ZERO),
+        state_root
+    );
+}
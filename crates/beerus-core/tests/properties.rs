@@ -0,0 +1,163 @@
+//! Property-based tests of the parsing and conversion code sitting between the providers and
+//! the proofs. The fuzz targets of `fuzz/` drive the same functions with arbitrary bytes.
+use beerus_core::{
+    ethers_helper::{
+        address_to_u256, bytes_to_u256, felt_to_h256, felt_to_u256, h256_to_felt, u256_to_address,
+        u256_to_felt,
+    },
+    lightclient::{
+        messaging::{L1ToL2Message, L2ToL1Message},
+        starknet::storage_proof::{Binary, Edge, GetProofOutput, Path, ProofNode},
+    },
+    starknet_helper::block_id_string_to_block_id_type,
+};
+use ethers::{
+    abi::{encode, Token},
+    types::{Address, H256, U256},
+    utils::keccak256,
+};
+use proptest::{collection::vec, prelude::*};
+use starknet::{
+    core::types::FieldElement,
+    providers::jsonrpc::models::{BlockId, BlockTag},
+};
+
+/// Felts below 2^251, the bulk of the field.
+fn felt() -> impl Strategy<Value = FieldElement> {
+    any::<[u8; 32]>().prop_map(|mut bytes| {
+        bytes[0] &= 0x07;
+        FieldElement::from_bytes_be(&bytes).unwrap()
+    })
+}
+
+fn address() -> impl Strategy<Value = Address> {
+    any::<[u8; 20]>().prop_map(Address::from)
+}
+
+fn proof_node() -> impl Strategy<Value = ProofNode> {
+    prop_oneof![
+        (felt(), felt()).prop_map(|(left, right)| ProofNode::Binary(Binary { left, right })),
+        (felt(), any::<u8>(), felt()).prop_map(|(value, len, child)| {
+            ProofNode::Edge(Edge {
+                path: Path { value, len },
+                child,
+            })
+        }),
+    ]
+}
+
+fn uint(value: U256) -> Token {
+    Token::Uint(value)
+}
+
+proptest! {
+    #[test]
+    fn felts_round_trip_through_u256_and_h256(felt in felt()) {
+        prop_assert_eq!(u256_to_felt(felt_to_u256(felt)).unwrap(), felt);
+        prop_assert_eq!(h256_to_felt(felt_to_h256(felt)).unwrap(), felt);
+        prop_assert_eq!(felt_to_h256(felt).as_bytes(), &felt.to_bytes_be()[..]);
+    }
+
+    #[test]
+    fn values_beyond_the_field_are_rejected(mut bytes in any::<[u8; 32]>()) {
+        bytes[0] |= 0x09;
+        prop_assert!(u256_to_felt(U256::from_big_endian(&bytes)).is_err());
+        prop_assert!(h256_to_felt(H256::from(bytes)).is_err());
+    }
+
+    #[test]
+    fn addresses_round_trip_through_u256(address in address(), high in 1_u8..=u8::MAX) {
+        prop_assert_eq!(u256_to_address(address_to_u256(address)).unwrap(), address);
+        let mut bytes = [0_u8; 32];
+        bytes[0] = high;
+        bytes[12..].copy_from_slice(address.as_bytes());
+        prop_assert!(u256_to_address(U256::from_big_endian(&bytes)).is_err());
+    }
+
+    #[test]
+    fn bytes_are_read_as_left_padded_u256(bytes in vec(any::<u8>(), 0..64)) {
+        match bytes.len() <= 32 {
+            true => prop_assert_eq!(bytes_to_u256(&bytes).unwrap(), U256::from_big_endian(&bytes)),
+            false => prop_assert!(bytes_to_u256(&bytes).is_err()),
+        }
+    }
+
+    #[test]
+    fn block_ids_are_parsed_back(number in any::<u64>(), hash in felt(), upper in any::<bool>()) {
+        prop_assert_eq!(
+            block_id_string_to_block_id_type("number", &number.to_string()).unwrap(),
+            BlockId::Number(number)
+        );
+        prop_assert_eq!(
+            block_id_string_to_block_id_type("hash", &format!("{hash:#x}")).unwrap(),
+            BlockId::Hash(hash)
+        );
+        let tag = match upper {
+            true => "PENDING",
+            false => "pending",
+        };
+        prop_assert_eq!(
+            block_id_string_to_block_id_type("tag", tag).unwrap(),
+            BlockId::Tag(BlockTag::Pending)
+        );
+    }
+
+    #[test]
+    fn block_id_parsing_never_panics(block_id_type in ".{0,8}", block_id in ".{0,80}") {
+        let _ = block_id_string_to_block_id_type(&block_id_type, &block_id);
+    }
+
+    #[test]
+    fn l2_to_l1_message_hash_is_the_hash_of_its_abi_encoding(
+        from_address in felt(),
+        to_address in address(),
+        payload in vec(felt(), 0..8),
+    ) {
+        let message = L2ToL1Message { from_address, to_address, payload: payload.clone() };
+        let mut tokens = vec![
+            uint(felt_to_u256(from_address)),
+            uint(address_to_u256(to_address)),
+            uint(payload.len().into()),
+        ];
+        tokens.extend(payload.into_iter().map(|value| uint(felt_to_u256(value))));
+        prop_assert_eq!(message.hash(), H256::from(keccak256(encode(&tokens))));
+    }
+
+    #[test]
+    fn l1_to_l2_message_hash_is_the_hash_of_its_abi_encoding(
+        from_address in address(),
+        to_address in felt(),
+        selector in felt(),
+        nonce in any::<u64>(),
+        payload in vec(felt(), 0..8),
+    ) {
+        let message = L1ToL2Message {
+            from_address,
+            to_address,
+            selector,
+            payload: payload.clone(),
+            nonce: nonce.into(),
+        };
+        let mut tokens = vec![
+            uint(address_to_u256(from_address)),
+            uint(felt_to_u256(to_address)),
+            uint(nonce.into()),
+            uint(felt_to_u256(selector)),
+            uint(payload.len().into()),
+        ];
+        tokens.extend(payload.into_iter().map(|value| uint(felt_to_u256(value))));
+        prop_assert_eq!(message.hash(), H256::from(keccak256(encode(&tokens))));
+    }
+
+    #[test]
+    fn proofs_round_trip_through_json(contract_proof in vec(proof_node(), 0..8)) {
+        let output = GetProofOutput { contract_proof, contract_data: None };
+        let json = serde_json::to_string(&output).unwrap();
+        prop_assert_eq!(serde_json::from_str::<GetProofOutput>(&json).unwrap(), output);
+    }
+
+    #[test]
+    fn proof_deserialization_never_panics(bytes in vec(any::<u8>(), 0..256)) {
+        let _ = serde_json::from_slice::<GetProofOutput>(&bytes);
+    }
+}
@@ -0,0 +1,244 @@
+//! Sync loop driven by a replayed block stream: the transitions of the node data, pruning and
+//! reorg rollback, without any network. The clock is paused, the sync tasks run a poll each
+//! time it reaches the next sync interval.
+use arc_swap::ArcSwap;
+use beerus_core::{
+    config::AlertConfig,
+    lightclient::{
+        alerts::{Alert, AlertHooks, AlertKind},
+        beerus::{NodeData, SyncStatus},
+        ethereum::{EthereumLightClient, MockEthereumLightClient},
+        service::{spawn_sync_tasks, ResyncTrigger, SyncTasksHealth},
+        starknet::{
+            replay::{ReplayScript, ReplayStarkNetLightClient},
+            StarkNetLightClient,
+        },
+    },
+};
+use ethers::types::U256;
+use eyre::eyre;
+use starknet::core::types::FieldElement;
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use tokio::sync::{broadcast, watch, RwLock};
+
+/// Sync tasks running against a replay.
+struct ReplaySync {
+    replay: ReplayStarkNetLightClient,
+    node: Arc<ArcSwap<NodeData>>,
+    resync: Arc<ResyncTrigger>,
+    alerts: broadcast::Receiver<Alert>,
+    health: Arc<Mutex<SyncTasksHealth>>,
+}
+
+impl ReplaySync {
+    /// Spawn the sync tasks, with the first block of the script proven on L1.
+    fn start(script: ReplayScript) -> Self {
+        Self::start_with_l1_errors(script, 0)
+    }
+
+    /// Spawn the sync tasks, with the first reads of the proven state on L1 failing.
+    fn start_with_l1_errors(script: ReplayScript, l1_errors: usize) -> Self {
+        let proven = script.steps()[0].chain[0].clone();
+        let mut ethereum_lightclient = MockEthereumLightClient::new();
+        ethereum_lightclient
+            .expect_get_block_number()
+            .returning(|| Ok(1));
+        let proven_state = (
+            U256::from(proven.block_number),
+            U256::from_big_endian(&proven.new_root.to_bytes_be()),
+        );
+        let l1_reads = AtomicUsize::new(0);
+        ethereum_lightclient
+            .expect_starknet_proven_state()
+            .returning(
+                move || match l1_reads.fetch_add(1, Ordering::SeqCst) < l1_errors {
+                    true => Err(eyre!("L1 provider unavailable")),
+                    false => Ok(proven_state),
+                },
+            );
+        let ethereum_lightclient: Box<dyn EthereumLightClient> = Box::new(ethereum_lightclient);
+
+        let replay = ReplayStarkNetLightClient::new(script);
+        let starknet_lightclient: Box<dyn StarkNetLightClient> = Box::new(replay.clone());
+        let node = Arc::new(ArcSwap::from_pointee(NodeData::new()));
+        let resync = Arc::new(ResyncTrigger::new());
+        let alerts = Arc::new(AlertHooks::new(
+            &AlertConfig::default(),
+            reqwest::Client::new(),
+        ));
+        let subscription = alerts.subscribe();
+        let health = Arc::new(Mutex::new(SyncTasksHealth::default()));
+        spawn_sync_tasks(
+            Arc::new(RwLock::new(ethereum_lightclient)),
+            Arc::new(starknet_lightclient),
+            node.clone(),
+            Arc::new(watch::channel(SyncStatus::NotSynced).0),
+            health.clone(),
+            resync.clone(),
+            alerts,
+            false,
+            0,
+        );
+        Self {
+            replay,
+            node,
+            resync,
+            alerts: subscription,
+            health,
+        }
+    }
+
+    /// Wait until the syncer polled the given step, and the blocks it fetched were stored.
+    async fn replayed(&self, step: usize) -> Arc<NodeData> {
+        self.replay.polled(step + 1).await;
+        // Shorter than the sync interval, the tasks are idle again long before.
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        self.node.load_full()
+    }
+
+    /// Hash of a block of a step of the replay.
+    fn block_hash(&self, step: usize, block_number: u64) -> FieldElement {
+        self.replay.step(step).unwrap().chain[block_number as usize - 1].block_hash
+    }
+}
+
+fn retained(node: &NodeData) -> Vec<u64> {
+    node.payload
+        .values()
+        .map(|block| block.block_number)
+        .collect()
+}
+
+/// Test that the node follows the replayed chain from the last proven block.
+#[tokio::test(start_paused = true)]
+async fn given_growing_chain_when_replayed_then_node_follows_the_latest_block() {
+    // Given
+    let sync = ReplaySync::start(ReplayScript::new(1).extend(3).extend(2));
+
+    // When
+    let first = sync.replayed(0).await;
+    let second = sync.replayed(1).await;
+
+    // Then
+    assert_eq!(retained(&first), vec![1, 2, 3]);
+    assert_eq!(first.block_number, 3);
+    assert_eq!(retained(&second), vec![1, 2, 3, 4, 5]);
+    assert_eq!(second.block_number, 5);
+    assert_eq!(second.payload.block_hash(&5), Some(sync.block_hash(1, 5)));
+    assert!(second.last_block_hash_check.as_ref().unwrap().valid);
+    assert_eq!(second.last_proven_block_number(), Some(1));
+    assert_eq!(second.sync_status(), SyncStatus::Synced);
+}
+
+/// Test that a new block on another fork rolls the retained tip back, and that a resync
+/// fetches the block of the new fork in its place.
+#[tokio::test(start_paused = true)]
+async fn given_reorg_when_replayed_then_tip_is_rolled_back_and_resynced() {
+    // Given
+    let mut sync = ReplaySync::start(ReplayScript::new(1).extend(5).reorg(5, 2));
+    sync.replayed(0).await;
+
+    // When
+    let reorged = sync.replayed(1).await;
+    sync.resync.request(Some(5));
+    let resynced = sync.replayed(2).await;
+
+    // Then
+    let alert = sync.alerts.try_recv().unwrap();
+    assert_eq!(alert.kind, AlertKind::Reorg);
+    assert_eq!(alert.block_number, Some(5));
+    assert_eq!(retained(&reorged), vec![1, 2, 3, 4, 6]);
+    assert_eq!(reorged.block_number, 6);
+    assert_eq!(retained(&resynced), vec![1, 2, 3, 4, 5, 6]);
+    assert_eq!(resynced.payload.block_hash(&5), Some(sync.block_hash(1, 5)));
+    assert_ne!(sync.block_hash(0, 5), sync.block_hash(1, 5));
+    assert_eq!(resynced.payload.block_hash(&6), Some(sync.block_hash(1, 6)));
+}
+
+/// Test that the blocks the provider failed to serve are backfilled on the next poll.
+#[tokio::test(start_paused = true)]
+async fn given_gap_when_replayed_then_missing_blocks_are_backfilled_next_poll() {
+    // Given
+    let sync = ReplaySync::start(
+        ReplayScript::new(1)
+            .extend(3)
+            .extend(4)
+            .with_gap(5..=5)
+            .extend(0),
+    );
+    sync.replayed(0).await;
+
+    // When
+    let gapped = sync.replayed(1).await;
+    let backfilled = sync.replayed(2).await;
+
+    // Then
+    assert_eq!(retained(&gapped), vec![1, 2, 3, 4, 7]);
+    assert_eq!(retained(&backfilled), vec![1, 2, 3, 4, 5, 6, 7]);
+    assert_eq!(
+        backfilled.payload.block_hash(&7),
+        Some(sync.block_hash(2, 7))
+    );
+}
+
+/// Test that the blocks synced after pruning extend the pruned payload, and that the last
+/// proven state root is kept.
+#[tokio::test(start_paused = true)]
+async fn given_pruned_payload_when_replayed_then_new_blocks_are_appended() {
+    // Given
+    let sync = ReplaySync::start(ReplayScript::new(1).extend(5).extend(2));
+    sync.replayed(0).await;
+
+    // When
+    sync.node.rcu(|node| {
+        let mut node = NodeData::clone(node);
+        node.prune_to(4);
+        node
+    });
+    let synced = sync.replayed(1).await;
+
+    // Then
+    assert_eq!(retained(&synced), vec![4, 5, 6, 7]);
+    assert_eq!(synced.block_number, 7);
+    assert_eq!(synced.last_proven_block_number(), Some(1));
+}
+
+/// Test that an outage of the provider degrades the node, which recovers with the next block.
+#[tokio::test(start_paused = true)]
+async fn given_outage_when_replayed_then_node_is_degraded_until_recovered() {
+    // Given
+    let sync = ReplaySync::start(ReplayScript::new(1).extend(2).outage().extend(1));
+    sync.replayed(0).await;
+
+    // When
+    let degraded = sync.replayed(1).await;
+    let recovered = sync.replayed(2).await;
+
+    // Then
+    assert_eq!(degraded.sync_status(), SyncStatus::Degraded);
+    assert_eq!(retained(&degraded), vec![1, 2]);
+    assert_eq!(recovered.sync_status(), SyncStatus::Synced);
+    assert_eq!(retained(&recovered), vec![1, 2, 3]);
+}
+
+/// Test that an error reading the proven state on L1 is retried on the next poll, without
+/// restarting the sync tasks.
+#[tokio::test(start_paused = true)]
+async fn given_l1_error_when_replayed_then_syncer_retries_on_next_poll() {
+    // Given
+    let sync = ReplaySync::start_with_l1_errors(ReplayScript::new(1).extend(2), 1);
+
+    // When
+    let synced = sync.replayed(0).await;
+
+    // Then
+    assert_eq!(retained(&synced), vec![1, 2, 3]);
+    assert_eq!(synced.sync_status(), SyncStatus::Synced);
+    assert_eq!(sync.health.lock().unwrap().failures, 0);
+}
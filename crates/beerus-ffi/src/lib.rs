@@ -0,0 +1,256 @@
+//! Kotlin and Swift bindings for the Beerus light client, generated with uniffi.
+//! The handle owns its own tokio runtime, so calls are blocking and should be made off the main
+//! thread. Field elements and hashes are passed around as hex strings.
+use beerus_core::{
+    config::Config,
+    lightclient::{
+        beerus::{BeerusLightClient, BlockTarget},
+        ethereum::helios_lightclient::HeliosLightClient,
+        starknet::StarkNetLightClientImpl,
+    },
+};
+use ethers::types::{Address, U256};
+use starknet::core::types::FieldElement;
+use std::{future::Future, path::PathBuf, str::FromStr, sync::Arc, sync::Mutex};
+use tokio::{runtime::Runtime, sync::RwLock};
+
+uniffi::setup_scaffolding!();
+
+#[derive(Debug, thiserror::Error, uniffi::Error)]
+#[uniffi(flat_error)]
+pub enum BeerusError {
+    #[error("Invalid argument: {0}")]
+    InvalidArgument(String),
+    #[error("{0}")]
+    Client(String),
+    #[error("Beerus is stopped")]
+    Stopped,
+}
+
+impl From<eyre::Report> for BeerusError {
+    fn from(e: eyre::Report) -> Self {
+        BeerusError::Client(e.to_string())
+    }
+}
+
+/// Configuration of the light client.
+#[derive(uniffi::Record)]
+pub struct BeerusConfig {
+    /// `mainnet` or `goerli`.
+    pub ethereum_network: String,
+    pub ethereum_consensus_rpc: String,
+    pub ethereum_execution_rpc: String,
+    pub starknet_rpc: String,
+    pub starknet_core_contract_address: String,
+    /// Directory where the Ethereum checkpoint is stored, usually the app data directory.
+    pub data_dir: String,
+}
+
+/// Verified value along with the L1 state it was checked against.
+#[derive(uniffi::Record)]
+pub struct VerifiedValue {
+    pub value: Vec<String>,
+    pub proven_block_number: u64,
+    pub state_root: String,
+    pub verified: bool,
+}
+
+/// Status of a message between L1 and StarkNet, as seen by the StarkNet core contract.
+#[derive(Debug, PartialEq, Eq, uniffi::Enum)]
+pub enum MessageStatus {
+    /// The L1 to L2 message is waiting to be consumed on L2.
+    Pending,
+    /// The cancellation of the L1 to L2 message was requested.
+    CancellationRequested,
+    /// The L2 to L1 message was proven on L1 and can be consumed.
+    ReadyToConsume,
+    /// The message is unknown or was already consumed.
+    NotFound,
+}
+
+/// Handle on a running Beerus light client.
+#[derive(uniffi::Object)]
+pub struct Beerus {
+    runtime: Mutex<Option<Runtime>>,
+    beerus: RwLock<BeerusLightClient>,
+}
+
+#[uniffi::export]
+impl Beerus {
+    /// Create the light client. `start` must be called before querying it.
+    #[uniffi::constructor]
+    pub fn new(config: BeerusConfig) -> Result<Arc<Self>, BeerusError> {
+        let config = Config::from_parts(
+            config.ethereum_network,
+            config.ethereum_consensus_rpc,
+            config.ethereum_execution_rpc,
+            config.starknet_rpc,
+            Address::from_str(&config.starknet_core_contract_address)
+                .map_err(|e| BeerusError::InvalidArgument(e.to_string()))?,
+            Some(PathBuf::from(config.data_dir)),
+        );
+        let runtime = Runtime::new().map_err(|e| BeerusError::Client(e.to_string()))?;
+        let beerus = runtime.block_on(async {
+            let ethereum_lightclient = HeliosLightClient::new(config.clone()).await?;
+            let starknet_lightclient = StarkNetLightClientImpl::new(&config)?;
+            Ok::<_, eyre::Report>(BeerusLightClient::new(
+                config,
+                Box::new(ethereum_lightclient),
+                Box::new(starknet_lightclient),
+            ))
+        })?;
+        Ok(Arc::new(Self {
+            runtime: Mutex::new(Some(runtime)),
+            beerus: RwLock::new(beerus),
+        }))
+    }
+
+    /// Sync the Ethereum light client and start following the StarkNet chain.
+    pub fn start(&self) -> Result<(), BeerusError> {
+        self.block_on(async { self.beerus.write().await.start().await })??;
+        Ok(())
+    }
+
+    /// Stop the background sync. The handle can't be used anymore afterwards.
+    pub fn stop(&self) {
+        if let Some(runtime) = self.runtime.lock().unwrap().take() {
+            runtime.shutdown_background();
+        }
+    }
+
+    /// Return the storage value at the given key, checked against the state proven on L1.
+    pub fn get_storage(
+        &self,
+        contract_address: String,
+        storage_key: String,
+    ) -> Result<VerifiedValue, BeerusError> {
+        let contract_address = parse_felt(&contract_address)?;
+        let storage_key = parse_felt(&storage_key)?;
+        let result = self.block_on(async {
+            self.beerus
+                .read()
+                .await
+                .starknet_get_storage_at_with_metadata(
+                    contract_address,
+                    storage_key,
+                    BlockTarget::L1Accepted,
+                )
+                .await
+        })??;
+        Ok(VerifiedValue {
+            value: vec![format!("{:#x}", result.result)],
+            proven_block_number: result.metadata.proven_block_number,
+            state_root: format!("{:#x}", result.metadata.state_root),
+            verified: result.metadata.verified,
+        })
+    }
+
+    /// Return the nonce of a contract, checked against the state proven on L1.
+    pub fn get_nonce(&self, contract_address: String) -> Result<VerifiedValue, BeerusError> {
+        let contract_address = parse_felt(&contract_address)?;
+        let result = self.block_on(async {
+            self.beerus
+                .read()
+                .await
+                .starknet_get_nonce_with_metadata(contract_address, BlockTarget::L1Accepted)
+                .await
+        })??;
+        Ok(VerifiedValue {
+            value: vec![format!("{:#x}", result.result)],
+            proven_block_number: result.metadata.proven_block_number,
+            state_root: format!("{:#x}", result.metadata.state_root),
+            verified: result.metadata.verified,
+        })
+    }
+
+    /// Call a contract function.
+    pub fn call(
+        &self,
+        contract_address: String,
+        entry_point_selector: String,
+        calldata: Vec<String>,
+    ) -> Result<VerifiedValue, BeerusError> {
+        let contract_address = parse_felt(&contract_address)?;
+        let entry_point_selector = parse_felt(&entry_point_selector)?;
+        let calldata = calldata
+            .iter()
+            .map(|value| parse_felt(value))
+            .collect::<Result<Vec<_>, _>>()?;
+        let result = self.block_on(async {
+            self.beerus
+                .read()
+                .await
+                .starknet_call_contract_with_metadata(
+                    contract_address,
+                    entry_point_selector,
+                    calldata,
+                    BlockTarget::L1Accepted,
+                )
+                .await
+        })??;
+        Ok(VerifiedValue {
+            value: result
+                .result
+                .iter()
+                .map(|value| format!("{value:#x}"))
+                .collect(),
+            proven_block_number: result.metadata.proven_block_number,
+            state_root: format!("{:#x}", result.metadata.state_root),
+            verified: result.metadata.verified,
+        })
+    }
+
+    /// Return the status of an L1 to L2 message.
+    pub fn l1_to_l2_message_status(&self, msg_hash: String) -> Result<MessageStatus, BeerusError> {
+        let msg_hash = parse_u256(&msg_hash)?;
+        let (fee, cancellation) = self.block_on(async {
+            let beerus = self.beerus.read().await;
+            let fee = beerus.starknet_l1_to_l2_messages(msg_hash).await?;
+            let cancellation = beerus
+                .starknet_l1_to_l2_message_cancellations(msg_hash)
+                .await?;
+            Ok::<_, eyre::Report>((fee, cancellation))
+        })??;
+        Ok(match (fee.is_zero(), cancellation.is_zero()) {
+            (true, _) => MessageStatus::NotFound,
+            (false, true) => MessageStatus::Pending,
+            (false, false) => MessageStatus::CancellationRequested,
+        })
+    }
+
+    /// Return the status of an L2 to L1 message.
+    pub fn l2_to_l1_message_status(&self, msg_hash: String) -> Result<MessageStatus, BeerusError> {
+        let msg_hash = parse_u256(&msg_hash)?;
+        let count = self.block_on(async {
+            self.beerus
+                .read()
+                .await
+                .starknet_l2_to_l1_messages(msg_hash)
+                .await
+        })??;
+        Ok(match count.is_zero() {
+            true => MessageStatus::NotFound,
+            false => MessageStatus::ReadyToConsume,
+        })
+    }
+}
+
+impl Beerus {
+    /// Run a future on the runtime of the handle, if it wasn't stopped.
+    fn block_on<F: Future>(&self, future: F) -> Result<F::Output, BeerusError> {
+        let handle = match self.runtime.lock().unwrap().as_ref() {
+            Some(runtime) => runtime.handle().clone(),
+            None => return Err(BeerusError::Stopped),
+        };
+        Ok(handle.block_on(future))
+    }
+}
+
+fn parse_felt(value: &str) -> Result<FieldElement, BeerusError> {
+    FieldElement::from_hex_be(value)
+        .map_err(|e| BeerusError::InvalidArgument(format!("{value}: {e}")))
+}
+
+fn parse_u256(value: &str) -> Result<U256, BeerusError> {
+    U256::from_str(value).map_err(|e| BeerusError::InvalidArgument(format!("{value}: {e}")))
+}
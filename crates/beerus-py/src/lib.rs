@@ -0,0 +1,209 @@
+//! Python bindings for the Beerus light client.
+//! Every query is a coroutine. Field elements are passed around as hex strings.
+use beerus_core::{
+    config::Config,
+    lightclient::{
+        beerus::{BeerusLightClient, BlockTarget, WithMetadata},
+        ethereum::helios_lightclient::HeliosLightClient,
+        starknet::StarkNetLightClientImpl,
+    },
+};
+use ethers::types::Address;
+use pyo3::{
+    exceptions::{PyRuntimeError, PyValueError},
+    prelude::*,
+};
+use starknet::core::types::FieldElement;
+use std::{path::PathBuf, str::FromStr, sync::Arc};
+use tokio::sync::RwLock;
+
+/// Value along with the L1 state it was checked against.
+#[pyclass]
+pub struct VerifiedValue {
+    #[pyo3(get)]
+    pub value: Vec<String>,
+    #[pyo3(get)]
+    pub proven_block_number: u64,
+    #[pyo3(get)]
+    pub state_root: String,
+    #[pyo3(get)]
+    pub verified: bool,
+}
+
+impl VerifiedValue {
+    fn new(value: Vec<FieldElement>, with_metadata: &WithMetadata<impl Sized>) -> Self {
+        Self {
+            value: value.iter().map(|value| format!("{value:#x}")).collect(),
+            proven_block_number: with_metadata.metadata.proven_block_number,
+            state_root: format!("{:#x}", with_metadata.metadata.state_root),
+            verified: with_metadata.metadata.verified,
+        }
+    }
+}
+
+/// Beerus light client.
+#[pyclass]
+pub struct BeerusClient {
+    beerus: Arc<RwLock<BeerusLightClient>>,
+}
+
+#[pymethods]
+impl BeerusClient {
+    /// Create a new client. `start` must be awaited before querying it.
+    #[staticmethod]
+    #[pyo3(signature = (ethereum_network, ethereum_consensus_rpc, ethereum_execution_rpc, starknet_rpc, starknet_core_contract_address, data_dir = "/tmp".to_string()))]
+    fn create(
+        py: Python<'_>,
+        ethereum_network: String,
+        ethereum_consensus_rpc: String,
+        ethereum_execution_rpc: String,
+        starknet_rpc: String,
+        starknet_core_contract_address: String,
+        data_dir: String,
+    ) -> PyResult<&PyAny> {
+        let config = Config::from_parts(
+            ethereum_network,
+            ethereum_consensus_rpc,
+            ethereum_execution_rpc,
+            starknet_rpc,
+            Address::from_str(&starknet_core_contract_address)
+                .map_err(|e| PyValueError::new_err(e.to_string()))?,
+            Some(PathBuf::from(data_dir)),
+        );
+        pyo3_asyncio::tokio::future_into_py(py, async move {
+            let ethereum_lightclient = HeliosLightClient::new(config.clone())
+                .await
+                .map_err(to_py_err)?;
+            let starknet_lightclient = StarkNetLightClientImpl::new(&config).map_err(to_py_err)?;
+            let beerus = BeerusLightClient::new(
+                config,
+                Box::new(ethereum_lightclient),
+                Box::new(starknet_lightclient),
+            );
+            Ok(BeerusClient {
+                beerus: Arc::new(RwLock::new(beerus)),
+            })
+        })
+    }
+
+    /// Sync the Ethereum light client and start following the StarkNet chain.
+    fn start<'p>(&self, py: Python<'p>) -> PyResult<&'p PyAny> {
+        let beerus = self.beerus.clone();
+        pyo3_asyncio::tokio::future_into_py(py, async move {
+            beerus.write().await.start().await.map_err(to_py_err)
+        })
+    }
+
+    /// Return the last StarkNet block number proven on L1.
+    fn l1_proven_block_number<'p>(&self, py: Python<'p>) -> PyResult<&'p PyAny> {
+        let beerus = self.beerus.clone();
+        pyo3_asyncio::tokio::future_into_py(py, async move {
+            beerus
+                .read()
+                .await
+                .starknet_l1_proven_block_number()
+                .await
+                .map_err(to_py_err)
+        })
+    }
+
+    /// Return the StarkNet state root proven on L1.
+    fn l1_state_root<'p>(&self, py: Python<'p>) -> PyResult<&'p PyAny> {
+        let beerus = self.beerus.clone();
+        pyo3_asyncio::tokio::future_into_py(py, async move {
+            let state_root = beerus
+                .read()
+                .await
+                .starknet_l1_state_root()
+                .await
+                .map_err(to_py_err)?;
+            Ok(format!("{state_root:#x}"))
+        })
+    }
+
+    /// Return the storage value at the given key, checked against the state proven on L1.
+    fn get_storage_at<'p>(
+        &self,
+        py: Python<'p>,
+        contract_address: &str,
+        storage_key: &str,
+    ) -> PyResult<&'p PyAny> {
+        let beerus = self.beerus.clone();
+        let contract_address = parse_felt(contract_address)?;
+        let storage_key = parse_felt(storage_key)?;
+        pyo3_asyncio::tokio::future_into_py(py, async move {
+            let result = beerus
+                .read()
+                .await
+                .starknet_get_storage_at_with_metadata(
+                    contract_address,
+                    storage_key,
+                    BlockTarget::L1Accepted,
+                )
+                .await
+                .map_err(to_py_err)?;
+            Ok(VerifiedValue::new(vec![result.result], &result))
+        })
+    }
+
+    /// Return the nonce of a contract, checked against the state proven on L1.
+    fn get_nonce<'p>(&self, py: Python<'p>, contract_address: &str) -> PyResult<&'p PyAny> {
+        let beerus = self.beerus.clone();
+        let contract_address = parse_felt(contract_address)?;
+        pyo3_asyncio::tokio::future_into_py(py, async move {
+            let result = beerus
+                .read()
+                .await
+                .starknet_get_nonce_with_metadata(contract_address, BlockTarget::L1Accepted)
+                .await
+                .map_err(to_py_err)?;
+            Ok(VerifiedValue::new(vec![result.result], &result))
+        })
+    }
+
+    /// Call a contract function.
+    fn call<'p>(
+        &self,
+        py: Python<'p>,
+        contract_address: &str,
+        entry_point_selector: &str,
+        calldata: Vec<String>,
+    ) -> PyResult<&'p PyAny> {
+        let beerus = self.beerus.clone();
+        let contract_address = parse_felt(contract_address)?;
+        let entry_point_selector = parse_felt(entry_point_selector)?;
+        let calldata = calldata
+            .iter()
+            .map(|value| parse_felt(value))
+            .collect::<PyResult<Vec<_>>>()?;
+        pyo3_asyncio::tokio::future_into_py(py, async move {
+            let result = beerus
+                .read()
+                .await
+                .starknet_call_contract_with_metadata(
+                    contract_address,
+                    entry_point_selector,
+                    calldata,
+                    BlockTarget::L1Accepted,
+                )
+                .await
+                .map_err(to_py_err)?;
+            Ok(VerifiedValue::new(result.result.clone(), &result))
+        })
+    }
+}
+
+fn parse_felt(value: &str) -> PyResult<FieldElement> {
+    FieldElement::from_hex_be(value).map_err(|e| PyValueError::new_err(format!("{value}: {e}")))
+}
+
+fn to_py_err(e: eyre::Report) -> PyErr {
+    PyRuntimeError::new_err(e.to_string())
+}
+
+#[pymodule]
+fn beerus(_py: Python, m: &PyModule) -> PyResult<()> {
+    m.add_class::<BeerusClient>()?;
+    m.add_class::<VerifiedValue>()?;
+    Ok(())
+}
@@ -0,0 +1,340 @@
+//! Privileged methods of the `admin_` namespace, for operators managing long-running nodes.
+//! They are never served to anonymous clients of the public server: they are either served on
+//! a separate bind address, set with `RPC_ADMIN_ADDR` and only loopback without a token, or on
+//! the public server to the requests carrying the token set with `RPC_ADMIN_TOKEN`.
+use crate::{
+    clients::{ClientRegistry, ClientStats},
+    error_response,
+};
+use beerus_core::lightclient::{
+    beerus::{RetentionStats, StateDump},
+    export::ExportTable,
+    payload::BlockUsage,
+    service::BeerusHandle,
+};
+use hyper::{header, Body, Method, Request, Response, StatusCode};
+use jsonrpsee::{
+    core::{async_trait, RpcResult as Result},
+    proc_macros::rpc,
+    types::error::CallError,
+};
+use log::LevelFilter;
+use serde_json::Value;
+use std::{
+    error::Error,
+    future::Future,
+    net::SocketAddr,
+    pin::Pin,
+    str::FromStr,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tower::{Layer, Service};
+
+/// Environment variable used to serve the `admin_` namespace on a separate bind address.
+pub const ADMIN_ADDR_ENV: &str = "RPC_ADMIN_ADDR";
+/// Environment variable used to set the bearer token of the `admin_` namespace.
+pub const ADMIN_TOKEN_ENV: &str = "RPC_ADMIN_TOKEN";
+/// JSON-RPC error code returned to the unauthenticated calls of the `admin_` namespace.
+pub const UNAUTHORIZED_CODE: i64 = -32001;
+
+/// Read the bind address of the admin server from the environment, if any.
+pub fn admin_addr_from_env() -> Option<SocketAddr> {
+    let value = std::env::var(ADMIN_ADDR_ENV).ok()?;
+    match value.parse() {
+        Ok(addr) => Some(addr),
+        Err(_) => {
+            log::warn!("Invalid {ADMIN_ADDR_ENV}: {value}, the admin server is disabled");
+            None
+        }
+    }
+}
+
+/// Read the admin token from the environment, an empty token disables authentication.
+pub fn admin_token_from_env() -> Option<String> {
+    std::env::var(ADMIN_TOKEN_ENV)
+        .ok()
+        .filter(|token| !token.is_empty())
+}
+
+/// Whether the admin server may listen on an address. Without a token it is only reachable
+/// from the host itself, on a loopback address.
+pub fn is_admin_addr_allowed(addr: &SocketAddr, admin_token: Option<&str>) -> bool {
+    admin_token.is_some() || addr.ip().is_loopback()
+}
+
+#[rpc(server, client)]
+trait AdminApi {
+    #[method(name = "admin_retentionStats")]
+    async fn admin_retention_stats(&self) -> Result<RetentionStats>;
+
+    #[method(name = "admin_blockUsage")]
+    async fn admin_block_usage(&self) -> Result<Vec<BlockUsage>>;
+
+    #[method(name = "admin_pruneTo")]
+    async fn admin_prune_to(&self, block_number: u64) -> Result<usize>;
+
+    #[method(name = "admin_compact")]
+    async fn admin_compact(&self) -> Result<usize>;
+
+    #[method(name = "admin_setLogLevel")]
+    async fn admin_set_log_level(&self, level: String) -> Result<()>;
+
+    #[method(name = "admin_resync")]
+    async fn admin_resync(&self, from_block: Option<u64>) -> Result<()>;
+
+    #[method(name = "admin_rotateProvider")]
+    async fn admin_rotate_provider(&self, url: String) -> Result<()>;
+
+    #[method(name = "admin_dumpState")]
+    async fn admin_dump_state(&self) -> Result<StateDump>;
+
+    #[method(name = "admin_exportArchive")]
+    async fn admin_export_archive(&self, table: String, from: u64, to: u64) -> Result<String>;
+
+    #[method(name = "admin_clientStats")]
+    async fn admin_client_stats(&self) -> Result<Vec<ClientStats>>;
+}
+
+pub struct AdminRpc {
+    beerus: BeerusHandle,
+    clients: Option<Arc<ClientRegistry>>,
+}
+
+impl AdminRpc {
+    pub fn new(beerus: BeerusHandle) -> Self {
+        Self {
+            beerus,
+            clients: None,
+        }
+    }
+
+    /// Report the usage of the clients of the public server.
+    pub fn with_clients(mut self, clients: Option<Arc<ClientRegistry>>) -> Self {
+        self.clients = clients;
+        self
+    }
+}
+
+#[async_trait]
+impl AdminApiServer for AdminRpc {
+    async fn admin_retention_stats(&self) -> Result<RetentionStats> {
+        Ok(self.beerus.retention_stats())
+    }
+
+    async fn admin_block_usage(&self) -> Result<Vec<BlockUsage>> {
+        Ok(self.beerus.block_usage())
+    }
+
+    async fn admin_prune_to(&self, block_number: u64) -> Result<usize> {
+        Ok(self.beerus.prune_to(block_number))
+    }
+
+    async fn admin_compact(&self) -> Result<usize> {
+        Ok(self.beerus.compact())
+    }
+
+    /// The level can only be lowered below the filter set with `RUST_LOG`, which is applied
+    /// by the logger itself.
+    async fn admin_set_log_level(&self, level: String) -> Result<()> {
+        let level = LevelFilter::from_str(&level).map_err(|e| {
+            jsonrpsee::core::Error::Call(CallError::InvalidParams(anyhow::anyhow!(e.to_string())))
+        })?;
+        log::set_max_level(level);
+        log::info!("Log level set to {level}");
+        Ok(())
+    }
+
+    async fn admin_resync(&self, from_block: Option<u64>) -> Result<()> {
+        self.beerus.resync(from_block);
+        Ok(())
+    }
+
+    async fn admin_rotate_provider(&self, url: String) -> Result<()> {
+        self.beerus.rotate_starknet_provider(&url).map_err(|e| {
+            jsonrpsee::core::Error::Call(CallError::InvalidParams(anyhow::anyhow!(e.to_string())))
+        })
+    }
+
+    async fn admin_dump_state(&self) -> Result<StateDump> {
+        Ok(self.beerus.dump_state())
+    }
+
+    /// The table is `blocks`, `transactions`, `receipts` or `events`, exported as CSV.
+    async fn admin_export_archive(&self, table: String, from: u64, to: u64) -> Result<String> {
+        ExportTable::from_str(&table)
+            .and_then(|table| self.beerus.export_archive(table, from, to))
+            .map_err(|e| {
+                jsonrpsee::core::Error::Call(CallError::InvalidParams(anyhow::anyhow!(
+                    e.to_string()
+                )))
+            })
+    }
+
+    /// Empty when API keys are disabled.
+    async fn admin_client_stats(&self) -> Result<Vec<ClientStats>> {
+        Ok(self
+            .clients
+            .as_ref()
+            .map_or_else(Vec::new, |clients| clients.stats()))
+    }
+}
+
+/// Whether a JSON-RPC request, or one of the calls of a batch, targets the `admin_` namespace.
+pub fn is_admin_request(request: &Value) -> bool {
+    let is_admin_call = |call: &Value| {
+        call.get("method")
+            .and_then(Value::as_str)
+            .map_or(false, |method| method.starts_with("admin_"))
+    };
+    match request {
+        Value::Array(calls) => calls.iter().any(is_admin_call),
+        call => is_admin_call(call),
+    }
+}
+
+/// Check the `Authorization: Bearer <token>` header of a request.
+pub fn is_authorized(authorization: Option<&str>, token: &str) -> bool {
+    match authorization.and_then(|value| value.strip_prefix("Bearer ")) {
+        // Compare in constant time, so that the token can't be guessed from response times.
+        Some(candidate) => {
+            candidate.len() == token.len()
+                && candidate
+                    .bytes()
+                    .zip(token.bytes())
+                    .fold(0, |diff, (a, b)| diff | (a ^ b))
+                    == 0
+        }
+        None => false,
+    }
+}
+
+/// Build the response sent to an unauthenticated request: one error per call of the request.
+fn unauthorized_response(request: &Value) -> Value {
+    error_response(request, UNAUTHORIZED_CODE, "Unauthorized")
+}
+
+/// Layer that applies [`AdminAuth`] to the HTTP service of the RPC server.
+#[derive(Debug, Clone)]
+pub struct AdminAuthLayer {
+    token: String,
+}
+
+impl AdminAuthLayer {
+    pub fn new(token: String) -> Self {
+        Self { token }
+    }
+}
+
+impl<S> Layer<S> for AdminAuthLayer {
+    type Service = AdminAuth<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        AdminAuth {
+            inner,
+            token: self.token.clone(),
+        }
+    }
+}
+
+/// Rejects the calls of the `admin_` namespace which don't carry the admin token.
+/// WebSocket connections can't be filtered call by call, so their upgrade requires the token.
+#[derive(Debug, Clone)]
+pub struct AdminAuth<S> {
+    inner: S,
+    token: String,
+}
+
+impl<S> Service<Request<Body>> for AdminAuth<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>> + Clone + Send + 'static,
+    S::Error: Into<Box<dyn Error + Send + Sync>> + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = Box<dyn Error + Send + Sync + 'static>;
+    type Future = Pin<
+        Box<dyn Future<Output = std::result::Result<Self::Response, Self::Error>> + Send + 'static>,
+    >;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<std::result::Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let authorized = is_authorized(
+            req.headers()
+                .get(header::AUTHORIZATION)
+                .and_then(|value| value.to_str().ok()),
+            &self.token,
+        );
+        // The inner service was polled ready, keep it and leave a fresh clone in its place.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+
+        Box::pin(async move {
+            if authorized {
+                return inner.call(req).await.map_err(Into::into);
+            }
+            if req.headers().contains_key(header::UPGRADE) {
+                return Ok(Response::builder()
+                    .status(StatusCode::UNAUTHORIZED)
+                    .body(Body::empty())?);
+            }
+            if req.method() != Method::POST {
+                return inner.call(req).await.map_err(Into::into);
+            }
+
+            let (parts, body) = req.into_parts();
+            let bytes = hyper::body::to_bytes(body).await?;
+            let request: Value = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+            if is_admin_request(&request) {
+                log::warn!("Unauthorized admin request");
+                let body = serde_json::to_vec(&unauthorized_response(&request))?;
+                return Ok(Response::builder()
+                    .status(StatusCode::UNAUTHORIZED)
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(body))?);
+            }
+            inner
+                .call(Request::from_parts(parts, Body::from(bytes)))
+                .await
+                .map_err(Into::into)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_batch_with_an_admin_call_is_an_admin_request() {
+        let request = json!([
+            {"jsonrpc": "2.0", "id": 1, "method": "starknet_blockNumber"},
+            {"jsonrpc": "2.0", "id": 2, "method": "admin_resync"},
+        ]);
+
+        assert!(is_admin_request(&request));
+        assert!(!is_admin_request(&request[0]));
+    }
+
+    #[test]
+    fn test_only_the_admin_token_is_authorized() {
+        assert!(is_authorized(Some("Bearer secret"), "secret"));
+        assert!(!is_authorized(Some("Bearer secre"), "secret"));
+        assert!(!is_authorized(Some("secret"), "secret"));
+        assert!(!is_authorized(None, "secret"));
+    }
+
+    #[test]
+    fn test_admin_server_without_token_only_listens_on_loopback() {
+        let loopback: SocketAddr = "127.0.0.1:3031".parse().unwrap();
+        let public: SocketAddr = "0.0.0.0:3031".parse().unwrap();
+
+        assert!(is_admin_addr_allowed(&loopback, None));
+        assert!(!is_admin_addr_allowed(&public, None));
+        assert!(is_admin_addr_allowed(&public, Some("secret")));
+    }
+}
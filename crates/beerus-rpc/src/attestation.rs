@@ -0,0 +1,184 @@
+//! Signed responses, so that the caches and gateways in front of a Beerus instance can attribute
+//! and audit the answers it served. When `RPC_SIGNING_KEY` is set, every HTTP response to a
+//! JSON-RPC request carries the last state root proven on L1 when it was answered, and a
+//! signature of the body and of that root by the key.
+use beerus_core::lightclient::service::BeerusHandle;
+use ethers::{
+    signers::{LocalWallet, Signer},
+    types::{Address, Signature, H256},
+    utils::{hash_message, keccak256},
+};
+use hyper::{header::HeaderValue, Body, Method, Request, Response};
+use starknet::core::types::FieldElement;
+use std::{
+    error::Error,
+    future::Future,
+    pin::Pin,
+    str::FromStr,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tower::{Layer, Service};
+
+/// Environment variable holding the hex encoded secp256k1 key the responses are signed with.
+pub const SIGNING_KEY_ENV: &str = "RPC_SIGNING_KEY";
+/// Header carrying the address of the signing key.
+pub const SIGNER_HEADER: &str = "x-beerus-signer";
+/// Header carrying the signature of the response.
+pub const SIGNATURE_HEADER: &str = "x-beerus-signature";
+/// Header carrying the last state root proven on L1 when the response was built.
+pub const STATE_ROOT_HEADER: &str = "x-beerus-state-root";
+/// Header carrying the number of the block of that state root.
+pub const BLOCK_NUMBER_HEADER: &str = "x-beerus-block-number";
+
+/// Read the signing key from the environment, if any.
+pub fn signing_key_from_env() -> Option<LocalWallet> {
+    let value = std::env::var(SIGNING_KEY_ENV).ok()?;
+    match LocalWallet::from_str(value.trim_start_matches("0x")) {
+        Ok(wallet) => Some(wallet),
+        Err(_) => {
+            log::warn!("Invalid {SIGNING_KEY_ENV}, the responses are not signed");
+            None
+        }
+    }
+}
+
+/// Digest signed for a response: the EIP-191 hash of the keccak256 hash of the body, followed
+/// by the state root and by the big-endian block number. Both are zero before a state root is
+/// proven.
+pub fn attestation_digest(body: &[u8], state_root: FieldElement, block_number: u64) -> H256 {
+    let mut message = keccak256(body).to_vec();
+    message.extend_from_slice(&state_root.to_bytes_be());
+    message.extend_from_slice(&block_number.to_be_bytes());
+    hash_message(message)
+}
+
+/// Recover the address which signed a response.
+/// # Returns
+/// `Ok(Address)` the signer, to compare with the one expected.
+/// `Err(ethers::types::SignatureError)` if the signature is malformed.
+pub fn recover_signer(
+    body: &[u8],
+    state_root: FieldElement,
+    block_number: u64,
+    signature: &Signature,
+) -> Result<Address, ethers::types::SignatureError> {
+    signature.recover(attestation_digest(body, state_root, block_number))
+}
+
+/// Layer that applies [`Attestation`] to the HTTP service of the RPC server.
+#[derive(Clone)]
+pub struct AttestationLayer {
+    wallet: Arc<LocalWallet>,
+    beerus: BeerusHandle,
+}
+
+impl AttestationLayer {
+    pub fn new(wallet: LocalWallet, beerus: BeerusHandle) -> Self {
+        Self {
+            wallet: Arc::new(wallet),
+            beerus,
+        }
+    }
+}
+
+impl<S> Layer<S> for AttestationLayer {
+    type Service = Attestation<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Attestation {
+            inner,
+            wallet: self.wallet.clone(),
+            beerus: self.beerus.clone(),
+        }
+    }
+}
+
+/// Signs the responses to the JSON-RPC requests. WebSocket messages are not signed.
+#[derive(Clone)]
+pub struct Attestation<S> {
+    inner: S,
+    wallet: Arc<LocalWallet>,
+    beerus: BeerusHandle,
+}
+
+impl<S> Service<Request<Body>> for Attestation<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>> + Clone + Send + 'static,
+    S::Error: Into<Box<dyn Error + Send + Sync>> + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = Box<dyn Error + Send + Sync + 'static>;
+    type Future =
+        Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send + 'static>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let wallet = self.wallet.clone();
+        let beerus = self.beerus.clone();
+        // The inner service was polled ready, keep it and leave a fresh clone in its place.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+
+        Box::pin(async move {
+            if req.method() != Method::POST {
+                return inner.call(req).await.map_err(Into::into);
+            }
+            let response = inner.call(req).await.map_err(Into::into)?;
+            let (mut parts, body) = response.into_parts();
+            let bytes = hyper::body::to_bytes(body).await?;
+
+            let (block_number, state_root) = beerus
+                .node
+                .load()
+                .last_proven_state_root()
+                .unwrap_or((0, FieldElement::ZERO));
+            let signature = wallet.sign_hash(attestation_digest(&bytes, state_root, block_number));
+            let headers = [
+                (SIGNER_HEADER, format!("{:#x}", wallet.address())),
+                (SIGNATURE_HEADER, format!("0x{signature}")),
+                (STATE_ROOT_HEADER, format!("{state_root:#x}")),
+                (BLOCK_NUMBER_HEADER, block_number.to_string()),
+            ];
+            for (name, value) in headers {
+                parts.headers.insert(name, HeaderValue::from_str(&value)?);
+            }
+            Ok(Response::from_parts(parts, Body::from(bytes)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signed_response_recovers_the_signer() {
+        let wallet = LocalWallet::from_str(
+            "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318",
+        )
+        .unwrap();
+        let body = br#"{"jsonrpc":"2.0","id":1,"result":10}"#;
+        let state_root = FieldElement::from(0x123_u64);
+        let signature = wallet.sign_hash(attestation_digest(body, state_root, 10));
+
+        assert_eq!(
+            recover_signer(body, state_root, 10, &signature).unwrap(),
+            wallet.address()
+        );
+        // The same signature does not vouch for another answer or another root.
+        let tampered = br#"{"jsonrpc":"2.0","id":1,"result":11}"#;
+        assert_ne!(
+            recover_signer(tampered, state_root, 10, &signature).unwrap(),
+            wallet.address()
+        );
+        assert_ne!(
+            recover_signer(body, FieldElement::ONE, 10, &signature).unwrap(),
+            wallet.address()
+        );
+    }
+}
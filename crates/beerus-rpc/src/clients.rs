@@ -0,0 +1,460 @@
+//! API keys of the clients of the public server, with per-client accounting and quotas.
+//! When `RPC_API_KEYS` is set, every request on `PORT` must carry one of the keys in the
+//! `x-api-key` header, except the probes of `/readyz` and `/metrics`. The requests, errors and
+//! bytes of every client are counted, and the requests beyond the quota of a client in the
+//! current minute are rejected.
+use crate::{admin::UNAUTHORIZED_CODE, error_response, read_request_body};
+use eyre::{eyre, Result};
+use hyper::{header, Body, Method, Request, Response, StatusCode};
+use jsonrpsee::types::error::{OVERSIZED_REQUEST_CODE, OVERSIZED_REQUEST_MSG};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt::Write,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+use tower::{Layer, Service};
+
+/// Environment variable listing the clients, as `name:key` or `name:key:requests_per_minute`
+/// separated by commas.
+pub const API_KEYS_ENV: &str = "RPC_API_KEYS";
+/// Header carrying the API key of a request.
+pub const API_KEY_HEADER: &str = "x-api-key";
+/// JSON-RPC error code returned to the requests beyond the quota of their client.
+pub const QUOTA_EXCEEDED_CODE: i64 = -32005;
+/// Length of the quota window.
+const QUOTA_WINDOW: Duration = Duration::from_secs(60);
+/// Paths answered without a key, so that probes need no credentials.
+const UNAUTHENTICATED_PATHS: [&str; 2] =
+    [crate::readiness::READYZ_PATH, crate::metrics::METRICS_PATH];
+
+/// Usage of the public server by a client since the start.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClientStats {
+    pub client: String,
+    /// HTTP requests and WebSocket connections accepted.
+    pub requests: u64,
+    /// Calls answered with an error, counted call by call in batches.
+    pub errors: u64,
+    /// Requests rejected because the quota was exceeded.
+    pub rejected: u64,
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    /// Requests allowed per minute, `None` if unlimited.
+    pub requests_per_minute: Option<u64>,
+}
+
+#[derive(Debug)]
+struct Client {
+    stats: ClientStats,
+    window_start: Instant,
+    window_requests: u64,
+}
+
+/// Clients of the public server, by API key.
+#[derive(Debug, Default)]
+pub struct ClientRegistry {
+    clients: HashMap<String, Mutex<Client>>,
+}
+
+impl ClientRegistry {
+    /// Parse the clients, written as `name:key` or `name:key:requests_per_minute` pairs
+    /// separated by commas, e.g. `wallet:k3y:600,indexer:0th3r`.
+    /// Names are limited to ASCII letters, digits, `-`, `_` and `.`, as they are written
+    /// unescaped in the metric labels.
+    pub fn parse(clients: &str) -> Result<Self> {
+        let mut registry = Self::default();
+        for entry in clients.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let mut fields = entry.split(':');
+            let (name, key) = match (fields.next(), fields.next()) {
+                (Some(name), Some(key)) if !name.is_empty() && !key.is_empty() => (name, key),
+                // The entry holds a key, leave it out of the error.
+                _ => {
+                    return Err(eyre!(
+                        "Invalid {API_KEYS_ENV} entry, expected name:key[:requests_per_minute]"
+                    ))
+                }
+            };
+            if !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+            {
+                return Err(eyre!(
+                    "Invalid client name {name:?}, expected ASCII letters, digits, '-', '_' or '.'"
+                ));
+            }
+            let requests_per_minute = match fields.next() {
+                Some(quota) => Some(
+                    quota
+                        .parse()
+                        .map_err(|_| eyre!("Invalid quota for client {name}: {quota}"))?,
+                ),
+                None => None,
+            };
+            let client = Client {
+                stats: ClientStats {
+                    client: name.to_string(),
+                    requests_per_minute,
+                    ..ClientStats::default()
+                },
+                window_start: Instant::now(),
+                window_requests: 0,
+            };
+            if registry
+                .clients
+                .insert(key.to_string(), Mutex::new(client))
+                .is_some()
+            {
+                return Err(eyre!("Duplicate API key for client {name}"));
+            }
+        }
+        Ok(registry)
+    }
+
+    /// Read the clients from the environment, `None` if API keys are disabled.
+    /// A malformed list is an error rather than a server open to everyone.
+    pub fn from_env() -> Result<Option<Self>> {
+        match std::env::var(API_KEYS_ENV) {
+            Ok(clients) if !clients.trim().is_empty() => Ok(Some(Self::parse(&clients)?)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Whether a client holds `key`.
+    pub fn is_known(&self, key: &str) -> bool {
+        self.clients.contains_key(key)
+    }
+
+    /// Count a request of the client holding `key`.
+    /// # Returns
+    /// `Some(true)` if the request is within the quota of the client, `Some(false)` if it
+    /// exceeds it, `None` if the key is unknown.
+    pub fn admit(&self, key: &str, bytes_in: u64) -> Option<bool> {
+        self.admit_at(key, bytes_in, Instant::now())
+    }
+
+    fn admit_at(&self, key: &str, bytes_in: u64, now: Instant) -> Option<bool> {
+        let mut client = self.clients.get(key)?.lock().unwrap();
+        if now.duration_since(client.window_start) >= QUOTA_WINDOW {
+            client.window_start = now;
+            client.window_requests = 0;
+        }
+        let within_quota = client
+            .stats
+            .requests_per_minute
+            .map_or(true, |quota| client.window_requests < quota);
+        if !within_quota {
+            client.stats.rejected += 1;
+            return Some(false);
+        }
+        client.window_requests += 1;
+        client.stats.requests += 1;
+        client.stats.bytes_in += bytes_in;
+        Some(true)
+    }
+
+    /// Count the response to a request of the client holding `key`.
+    pub fn record_response(&self, key: &str, errors: u64, bytes_out: u64) {
+        if let Some(client) = self.clients.get(key) {
+            let mut client = client.lock().unwrap();
+            client.stats.errors += errors;
+            client.stats.bytes_out += bytes_out;
+        }
+    }
+
+    /// Return the usage of every client, sorted by name.
+    pub fn stats(&self) -> Vec<ClientStats> {
+        let mut stats: Vec<_> = self
+            .clients
+            .values()
+            .map(|client| client.lock().unwrap().stats.clone())
+            .collect();
+        stats.sort_by(|a, b| a.client.cmp(&b.client));
+        stats
+    }
+}
+
+/// Number of calls answered with an error in a JSON-RPC response.
+pub fn count_errors(response: &Value) -> u64 {
+    match response {
+        Value::Array(responses) => responses.iter().map(count_errors).sum(),
+        response => u64::from(response.get("error").is_some()),
+    }
+}
+
+/// Render the usage of the clients as Prometheus counters, labelled by client name.
+pub fn render_client_metrics(stats: &[ClientStats]) -> String {
+    let counters: [(&str, &str, fn(&ClientStats) -> u64); 5] = [
+        (
+            "beerus_client_requests_total",
+            "Requests accepted from the client.",
+            |stats| stats.requests,
+        ),
+        (
+            "beerus_client_errors_total",
+            "Calls of the client answered with an error.",
+            |stats| stats.errors,
+        ),
+        (
+            "beerus_client_rejected_total",
+            "Requests of the client rejected by its quota.",
+            |stats| stats.rejected,
+        ),
+        (
+            "beerus_client_bytes_in_total",
+            "Bytes received from the client.",
+            |stats| stats.bytes_in,
+        ),
+        (
+            "beerus_client_bytes_out_total",
+            "Bytes sent to the client.",
+            |stats| stats.bytes_out,
+        ),
+    ];
+    let mut metrics = String::new();
+    for (name, help, value) in counters {
+        let _ = writeln!(metrics, "# HELP {name} {help}");
+        let _ = writeln!(metrics, "# TYPE {name} counter");
+        for client in stats {
+            let _ = writeln!(
+                metrics,
+                "{name}{{client=\"{}\"}} {}",
+                client.client,
+                value(client)
+            );
+        }
+    }
+    metrics
+}
+
+/// Layer that applies [`ClientAuth`] to the HTTP service of the RPC server.
+#[derive(Debug, Clone)]
+pub struct ClientAuthLayer {
+    clients: Arc<ClientRegistry>,
+}
+
+impl ClientAuthLayer {
+    pub fn new(clients: Arc<ClientRegistry>) -> Self {
+        Self { clients }
+    }
+}
+
+impl<S> Layer<S> for ClientAuthLayer {
+    type Service = ClientAuth<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ClientAuth {
+            inner,
+            clients: self.clients.clone(),
+        }
+    }
+}
+
+/// Rejects the requests without a known API key or beyond the quota of their client, and
+/// counts the usage of every client.
+#[derive(Debug, Clone)]
+pub struct ClientAuth<S> {
+    inner: S,
+    clients: Arc<ClientRegistry>,
+}
+
+impl<S> Service<Request<Body>> for ClientAuth<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>> + Clone + Send + 'static,
+    S::Error: Into<Box<dyn Error + Send + Sync>> + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = Box<dyn Error + Send + Sync + 'static>;
+    type Future =
+        Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send + 'static>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let clients = self.clients.clone();
+        let key = req
+            .headers()
+            .get(API_KEY_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(String::from);
+        // The inner service was polled ready, keep it and leave a fresh clone in its place.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+
+        Box::pin(async move {
+            if req.method() == Method::GET && UNAUTHENTICATED_PATHS.contains(&req.uri().path()) {
+                return inner.call(req).await.map_err(Into::into);
+            }
+            // Unknown keys are rejected before the body is read, so that only clients can make
+            // the server buffer a body.
+            let key = match key.filter(|key| clients.is_known(key)) {
+                Some(key) => key,
+                None => {
+                    return rejection(
+                        StatusCode::UNAUTHORIZED,
+                        &Value::Null,
+                        UNAUTHORIZED_CODE,
+                        "Unauthorized",
+                    )
+                }
+            };
+            let (parts, body) = req.into_parts();
+            let (body, bytes_in, request) = match parts.method == Method::POST {
+                true => match read_request_body(body).await? {
+                    Some(bytes) => {
+                        let request = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+                        (Body::from(bytes.clone()), bytes.len() as u64, request)
+                    }
+                    None => {
+                        return rejection(
+                            StatusCode::PAYLOAD_TOO_LARGE,
+                            &Value::Null,
+                            OVERSIZED_REQUEST_CODE.into(),
+                            OVERSIZED_REQUEST_MSG,
+                        )
+                    }
+                },
+                false => (body, 0, Value::Null),
+            };
+
+            match clients.admit(&key, bytes_in) {
+                Some(true) => {
+                    let response = inner
+                        .call(Request::from_parts(parts, body))
+                        .await
+                        .map_err(Into::into)?;
+                    // WebSocket upgrades are counted once, their messages are not accounted.
+                    if response.status() == StatusCode::SWITCHING_PROTOCOLS {
+                        return Ok(response);
+                    }
+                    let (parts, body) = response.into_parts();
+                    let bytes = hyper::body::to_bytes(body).await?;
+                    let response: Value = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+                    clients.record_response(&key, count_errors(&response), bytes.len() as u64);
+                    Ok(Response::from_parts(parts, Body::from(bytes)))
+                }
+                // The key is known, the registry never drops a client.
+                Some(false) | None => rejection(
+                    StatusCode::TOO_MANY_REQUESTS,
+                    &request,
+                    QUOTA_EXCEEDED_CODE,
+                    "Quota exceeded",
+                ),
+            }
+        })
+    }
+}
+
+/// Answer a request with a JSON-RPC error in place of the server.
+fn rejection(
+    status: StatusCode,
+    request: &Value,
+    code: i64,
+    message: &str,
+) -> Result<Response<Body>, Box<dyn Error + Send + Sync + 'static>> {
+    let body = serde_json::to_vec(&error_response(request, code, message))?;
+    Ok(Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_unknown_key_is_rejected_before_the_body_is_read() {
+        let clients = Arc::new(ClientRegistry::parse("wallet:k3y").unwrap());
+        let mut service =
+            ClientAuthLayer::new(clients).layer(tower::service_fn(|_: Request<Body>| async {
+                Ok::<_, hyper::Error>(Response::new(Body::from("{}")))
+            }));
+        // The body never ends, reading it would never return.
+        let (_sender, body) = Body::channel();
+        let request = Request::post("/")
+            .header(API_KEY_HEADER, "unknown")
+            .body(body)
+            .unwrap();
+
+        let response = tokio::time::timeout(
+            Duration::from_secs(5),
+            service.ready().await.unwrap().call(request),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_body_beyond_the_limit_is_rejected() {
+        let clients = Arc::new(ClientRegistry::parse("wallet:k3y").unwrap());
+        let mut service = ClientAuthLayer::new(clients.clone()).layer(tower::service_fn(
+            |_: Request<Body>| async { Ok::<_, hyper::Error>(Response::new(Body::from("{}"))) },
+        ));
+        let body = vec![b' '; crate::MAX_REQUEST_BODY_SIZE as usize + 1];
+        let request = Request::post("/")
+            .header(API_KEY_HEADER, "k3y")
+            .body(Body::from(body))
+            .unwrap();
+
+        let response = service.ready().await.unwrap().call(request).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(clients.stats()[0].requests, 0);
+    }
+
+    #[test]
+    fn test_requests_beyond_the_quota_are_rejected_until_the_next_window() {
+        let clients = ClientRegistry::parse("wallet:k3y:2,indexer:0th3r").unwrap();
+        let start = Instant::now();
+
+        assert_eq!(clients.admit_at("k3y", 10, start), Some(true));
+        assert_eq!(clients.admit_at("k3y", 10, start), Some(true));
+        assert_eq!(clients.admit_at("k3y", 10, start), Some(false));
+        assert_eq!(
+            clients.admit_at("k3y", 10, start + QUOTA_WINDOW),
+            Some(true)
+        );
+        assert_eq!(clients.admit_at("0th3r", 10, start), Some(true));
+        assert_eq!(clients.admit_at("unknown", 10, start), None);
+
+        clients.record_response("k3y", 1, 100);
+        let stats = clients.stats();
+        assert_eq!(stats[1].client, "wallet");
+        assert_eq!(stats[1].requests, 3);
+        assert_eq!(stats[1].rejected, 1);
+        assert_eq!(stats[1].errors, 1);
+        assert_eq!(stats[1].bytes_in, 30);
+        assert_eq!(stats[1].bytes_out, 100);
+        assert!(render_client_metrics(&stats)
+            .contains("beerus_client_rejected_total{client=\"wallet\"} 1\n"));
+    }
+
+    #[test]
+    fn test_malformed_api_keys_are_rejected() {
+        assert!(ClientRegistry::parse("wallet").is_err());
+        assert!(ClientRegistry::parse("wallet:k3y:many").is_err());
+        assert!(ClientRegistry::parse("wallet:k3y,indexer:k3y").is_err());
+        assert!(ClientRegistry::parse("wal\"let:k3y").is_err());
+        assert!(ClientRegistry::parse("wal}let:k3y").is_err());
+        assert!(ClientRegistry::parse("wallet-1.eu_west:k3y").is_ok());
+        assert_eq!(
+            count_errors(&json!([{"id": 1, "result": 1}, {"id": 2, "error": {}}])),
+            1
+        );
+    }
+}
@@ -0,0 +1,435 @@
+//! Compatibility layer between the StarkNet JSON-RPC spec versions spoken by wallets and the
+//! request and response shapes served by Beerus.
+//! Requests are rewritten before reaching the RPC methods and responses are rewritten on their
+//! way back, so the RPC methods themselves only deal with the internal types.
+use hyper::{Body, Request, Response};
+use serde_json::{json, Map, Value};
+use std::{
+    error::Error,
+    fmt,
+    future::Future,
+    pin::Pin,
+    str::FromStr,
+    task::{Context, Poll},
+};
+use tower::{Layer, Service};
+
+/// Environment variable used to select the served spec version.
+pub const SPEC_VERSION_ENV: &str = "RPC_SPEC_VERSION";
+
+/// StarkNet JSON-RPC spec versions Beerus can serve.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum SpecVersion {
+    /// Shapes used internally by Beerus.
+    #[default]
+    V0_2,
+    V0_4,
+    V0_5,
+    V0_6,
+}
+
+impl SpecVersion {
+    /// Read the spec version from the environment, defaulting to the internal one.
+    pub fn from_env() -> Self {
+        std::env::var(SPEC_VERSION_ENV)
+            .ok()
+            .and_then(|version| version.parse().ok())
+            .unwrap_or_default()
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SpecVersion::V0_2 => "0.2.1",
+            SpecVersion::V0_4 => "0.4.0",
+            SpecVersion::V0_5 => "0.5.1",
+            SpecVersion::V0_6 => "0.6.0",
+        }
+    }
+}
+
+impl FromStr for SpecVersion {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim_start_matches('v') {
+            "0.2" | "0.2.1" => Ok(SpecVersion::V0_2),
+            "0.4" | "0.4.0" => Ok(SpecVersion::V0_4),
+            "0.5" | "0.5.0" | "0.5.1" => Ok(SpecVersion::V0_5),
+            "0.6" | "0.6.0" => Ok(SpecVersion::V0_6),
+            _ => Err(eyre::eyre!("Unsupported spec version: {s}")),
+        }
+    }
+}
+
+impl fmt::Display for SpecVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Parameter names of the methods taking a `block_id`, in the spec order.
+/// Internally the block id always comes first, split in two parameters.
+/// Before v0.6, `starknet_traceBlockTransactions` takes a plain `block_hash` instead.
+/// `starknet_estimateMessageFee` is not served, so it has no entry.
+fn block_id_method_params(method: &str, version: SpecVersion) -> Option<&'static [&'static str]> {
+    match method {
+        "starknet_traceBlockTransactions" if version < SpecVersion::V0_6 => Some(&["block_hash"]),
+        "starknet_getBlockWithTxHashes"
+        | "starknet_getBlockWithTxs"
+        | "starknet_getStateUpdate"
+        | "starknet_getBlockTransactionCount"
+        | "starknet_traceBlockTransactions" => Some(&["block_id"]),
+        "starknet_getTransactionByBlockIdAndIndex" => Some(&["block_id", "index"]),
+        "starknet_getClassAt" | "starknet_getClassHashAt" | "starknet_getNonce" => {
+            Some(&["block_id", "contract_address"])
+        }
+        "starknet_simulateTransactions" => Some(&["block_id", "transactions", "simulation_flags"]),
+        // `simulation_flags` only exists since v0.6, it is omitted by older clients.
+        "starknet_estimateFee" => Some(&["request", "simulation_flags", "block_id"]),
+        _ => None,
+    }
+}
+
+/// Convert a spec `block_id` into the `(block_id_type, block_id)` pair used internally.
+fn split_block_id(block_id: &Value) -> Option<(Value, Value)> {
+    match block_id {
+        Value::String(tag) => Some((json!("tag"), json!(tag))),
+        Value::Object(object) => {
+            if let Some(hash) = object.get("block_hash") {
+                Some((json!("hash"), hash.clone()))
+            } else {
+                object
+                    .get("block_number")
+                    .map(|number| (json!("number"), json!(number.to_string())))
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Rewrite a single JSON-RPC call from the spec shape into the internal one.
+/// Calls which don't need any translation are left untouched.
+pub fn translate_request(call: &mut Value, version: SpecVersion) {
+    if version == SpecVersion::V0_2 {
+        return;
+    }
+    let method = match call.get("method").and_then(Value::as_str) {
+        Some(method) => method.to_string(),
+        None => return,
+    };
+    let names = match block_id_method_params(&method, version) {
+        Some(names) => names,
+        None => return,
+    };
+
+    // Params can be passed by position or by name.
+    let params: Vec<Value> = match call.get("params") {
+        Some(Value::Array(params)) => params.clone(),
+        Some(Value::Object(params)) => names
+            .iter()
+            .map(|name| params.get(*name).cloned().unwrap_or(Value::Null))
+            .collect(),
+        _ => return,
+    };
+    // A trailing block id stays last when optional parameters before it are omitted.
+    let block_id_index = match names
+        .iter()
+        .position(|name| *name == "block_id" || *name == "block_hash")
+    {
+        Some(index) if index == names.len() - 1 => params.len().saturating_sub(1),
+        Some(index) => index,
+        None => return,
+    };
+    let block_id = match (names.contains(&"block_hash"), params.get(block_id_index)) {
+        (true, Some(hash @ Value::String(_))) => Some((json!("hash"), hash.clone())),
+        (false, Some(block_id)) => split_block_id(block_id),
+        _ => None,
+    };
+    let (block_id_type, block_id) = match block_id {
+        Some(block_id) => block_id,
+        None => return,
+    };
+
+    let mut internal = vec![block_id_type, block_id];
+    let others = params
+        .into_iter()
+        .enumerate()
+        .filter(|(index, _)| *index != block_id_index)
+        .map(|(_, param)| param);
+    for param in others {
+        internal.push(match (method.as_str(), param) {
+            // The index is a number in the spec but a string internally.
+            ("starknet_getTransactionByBlockIdAndIndex", Value::Number(index)) => {
+                json!(index.to_string())
+            }
+            (_, param) => param,
+        });
+    }
+    call["params"] = Value::Array(internal);
+    if method == "starknet_getClassHashAt" {
+        call["method"] = json!("starknet_getClassHash");
+    }
+}
+
+/// Rewrite the result of a call from the internal shape into the spec one.
+pub fn translate_response(method: &str, result: &mut Value, version: SpecVersion) {
+    if method != "starknet_getTransactionReceipt" || version < SpecVersion::V0_5 {
+        return;
+    }
+    let receipt = match result.as_object_mut() {
+        Some(receipt) => receipt,
+        None => return,
+    };
+
+    // Since v0.5 the status is split between finality and execution.
+    if let Some(status) = receipt.remove("status") {
+        let execution_status = match status.as_str() {
+            Some("REJECTED") => "REVERTED",
+            _ => "SUCCEEDED",
+        };
+        receipt.insert("finality_status".to_string(), status);
+        receipt.insert("execution_status".to_string(), json!(execution_status));
+    }
+
+    // Since v0.6 fees come with their unit. Only transactions paying in wei exist here.
+    if version >= SpecVersion::V0_6 {
+        if let Some(fee) = receipt.remove("actual_fee") {
+            let mut actual_fee = Map::new();
+            actual_fee.insert("amount".to_string(), fee);
+            actual_fee.insert("unit".to_string(), json!("WEI"));
+            receipt.insert("actual_fee".to_string(), Value::Object(actual_fee));
+        }
+    }
+}
+
+/// Layer that applies [`SpecCompat`] to the HTTP service of the RPC server.
+#[derive(Debug, Clone, Copy)]
+pub struct SpecCompatLayer {
+    version: SpecVersion,
+}
+
+impl SpecCompatLayer {
+    pub fn new(version: SpecVersion) -> Self {
+        Self { version }
+    }
+}
+
+impl<S> Layer<S> for SpecCompatLayer {
+    type Service = SpecCompat<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        SpecCompat {
+            inner,
+            version: self.version,
+        }
+    }
+}
+
+/// Translates JSON-RPC requests and responses between a spec version and the internal shapes.
+#[derive(Debug, Clone)]
+pub struct SpecCompat<S> {
+    inner: S,
+    version: SpecVersion,
+}
+
+impl<S> Service<Request<Body>> for SpecCompat<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>> + Clone + Send + 'static,
+    S::Error: Into<Box<dyn Error + Send + Sync>> + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = Box<dyn Error + Send + Sync + 'static>;
+    type Future =
+        Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send + 'static>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let version = self.version;
+        // The inner service was polled ready, keep it and leave a fresh clone in its place.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+
+        Box::pin(async move {
+            if version == SpecVersion::V0_2 {
+                return inner.call(req).await.map_err(Into::into);
+            }
+
+            let (parts, body) = req.into_parts();
+            let bytes = hyper::body::to_bytes(body).await?;
+            let mut payload: Value = match serde_json::from_slice(&bytes) {
+                Ok(payload) => payload,
+                // Let the server answer with the appropriate parse error.
+                Err(_) => {
+                    return inner
+                        .call(Request::from_parts(parts, Body::from(bytes)))
+                        .await
+                        .map_err(Into::into)
+                }
+            };
+
+            // Remember the spec method of every call to translate the matching response.
+            let mut methods = Vec::new();
+            for call in calls_mut(&mut payload) {
+                if let (Some(id), Some(method)) = (call.get("id"), call.get("method")) {
+                    methods.push((id.clone(), method.as_str().unwrap_or_default().to_string()));
+                }
+                translate_request(call, version);
+            }
+
+            let req = Request::from_parts(parts, Body::from(serde_json::to_vec(&payload)?));
+            let res = inner.call(req).await.map_err(Into::into)?;
+
+            let (parts, body) = res.into_parts();
+            let bytes = hyper::body::to_bytes(body).await?;
+            let mut payload: Value = match serde_json::from_slice(&bytes) {
+                Ok(payload) => payload,
+                Err(_) => return Ok(Response::from_parts(parts, Body::from(bytes))),
+            };
+            for response in calls_mut(&mut payload) {
+                let method = methods
+                    .iter()
+                    .find(|(id, _)| Some(id) == response.get("id"))
+                    .map(|(_, method)| method.clone());
+                if let (Some(method), Some(result)) = (method, response.get_mut("result")) {
+                    translate_response(&method, result, version);
+                }
+            }
+            let body = serde_json::to_vec(&payload)?;
+            let mut res = Response::from_parts(parts, Body::from(body));
+            // The body length changed.
+            res.headers_mut().remove(hyper::header::CONTENT_LENGTH);
+            Ok(res)
+        })
+    }
+}
+
+/// Return the calls of a single or batch JSON-RPC payload.
+fn calls_mut(payload: &mut Value) -> Vec<&mut Value> {
+    match payload {
+        Value::Array(calls) => calls.iter_mut().collect(),
+        call => vec![call],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_translate_block_id_request() {
+        let mut call = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "starknet_getBlockWithTxs",
+            "params": {"block_id": {"block_number": 42}}
+        });
+
+        translate_request(&mut call, SpecVersion::V0_4);
+
+        assert_eq!(call["params"], json!(["number", "42"]));
+    }
+
+    #[test]
+    fn test_translate_class_hash_at_request() {
+        let mut call = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "starknet_getClassHashAt",
+            "params": ["latest", "0x123"]
+        });
+
+        translate_request(&mut call, SpecVersion::V0_6);
+
+        assert_eq!(call["method"], json!("starknet_getClassHash"));
+        assert_eq!(call["params"], json!(["tag", "latest", "0x123"]));
+    }
+
+    #[test]
+    fn test_translate_estimate_fee_request() {
+        let mut call = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "starknet_estimateFee",
+            "params": [[{"type": "INVOKE"}], ["SKIP_VALIDATE"], {"block_hash": "0x1"}]
+        });
+
+        translate_request(&mut call, SpecVersion::V0_6);
+
+        assert_eq!(
+            call["params"],
+            json!(["hash", "0x1", [{"type": "INVOKE"}], ["SKIP_VALIDATE"]])
+        );
+    }
+
+    #[test]
+    fn test_translate_v0_4_estimate_fee_request() {
+        let mut call = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "starknet_estimateFee",
+            "params": [[{"type": "INVOKE"}], {"block_number": 5}]
+        });
+
+        translate_request(&mut call, SpecVersion::V0_4);
+
+        assert_eq!(call["params"], json!(["number", "5", [{"type": "INVOKE"}]]));
+    }
+
+    #[test]
+    fn test_translate_v0_5_trace_block_transactions_request() {
+        let mut call = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "starknet_traceBlockTransactions",
+            "params": {"block_hash": "0x1"}
+        });
+
+        translate_request(&mut call, SpecVersion::V0_5);
+
+        assert_eq!(call["params"], json!(["hash", "0x1"]));
+    }
+
+    #[test]
+    fn test_internal_version_is_left_untouched() {
+        let mut call = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "starknet_getBlockWithTxs",
+            "params": ["tag", "latest"]
+        });
+        let expected = call.clone();
+
+        translate_request(&mut call, SpecVersion::V0_2);
+
+        assert_eq!(call, expected);
+    }
+
+    #[test]
+    fn test_translate_receipt_response() {
+        let mut receipt = json!({
+            "transaction_hash": "0x1",
+            "actual_fee": "0x10",
+            "status": "ACCEPTED_ON_L2"
+        });
+
+        translate_response(
+            "starknet_getTransactionReceipt",
+            &mut receipt,
+            SpecVersion::V0_6,
+        );
+
+        assert_eq!(
+            receipt,
+            json!({
+                "transaction_hash": "0x1",
+                "actual_fee": {"amount": "0x10", "unit": "WEI"},
+                "finality_status": "ACCEPTED_ON_L2",
+                "execution_status": "SUCCEEDED"
+            })
+        );
+    }
+}
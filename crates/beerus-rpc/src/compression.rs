@@ -0,0 +1,218 @@
+//! Compression of the HTTP responses, negotiated with the `Accept-Encoding` header of the
+//! client. Large blocks and traces dominate the bandwidth of the server, the small responses
+//! are sent as is since compressing them saves little.
+use hyper::{
+    header::{self, HeaderValue},
+    Body, Request, Response, StatusCode,
+};
+use std::{
+    error::Error,
+    future::Future,
+    io::Write,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tower::{Layer, Service};
+
+/// Environment variable disabling the compression when set to `false`.
+pub const COMPRESSION_ENV: &str = "RPC_COMPRESSION";
+/// Environment variable setting the size from which responses are compressed, in bytes.
+pub const COMPRESSION_MIN_BYTES_ENV: &str = "RPC_COMPRESSION_MIN_BYTES";
+pub const DEFAULT_COMPRESSION_MIN_BYTES: usize = 1024;
+
+/// Encodings the server compresses with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Brotli,
+    Gzip,
+}
+
+impl Encoding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Brotli => "br",
+            Self::Gzip => "gzip",
+        }
+    }
+}
+
+/// Read the compression threshold from the environment, `None` if compression is disabled.
+pub fn compression_min_bytes_from_env() -> Option<usize> {
+    if std::env::var(COMPRESSION_ENV).map_or(false, |value| value == "false") {
+        return None;
+    }
+    let min_bytes = match std::env::var(COMPRESSION_MIN_BYTES_ENV) {
+        Ok(value) => value.parse().unwrap_or_else(|_| {
+            log::warn!("Invalid {COMPRESSION_MIN_BYTES_ENV}: {value}, using the default");
+            DEFAULT_COMPRESSION_MIN_BYTES
+        }),
+        Err(_) => DEFAULT_COMPRESSION_MIN_BYTES,
+    };
+    Some(min_bytes)
+}
+
+/// Pick the encoding of a response from the `Accept-Encoding` header of the request: the one
+/// with the highest quality, brotli on a tie. `None` if the client accepts neither.
+pub fn preferred_encoding(accept_encoding: &str) -> Option<Encoding> {
+    let mut preferred: Option<(Encoding, f32)> = None;
+    for item in accept_encoding.split(',') {
+        let mut parts = item.split(';').map(str::trim);
+        let encoding = match parts.next() {
+            Some("br") => Encoding::Brotli,
+            Some("gzip") | Some("*") => Encoding::Gzip,
+            _ => continue,
+        };
+        let quality = parts
+            .find_map(|param| param.strip_prefix("q="))
+            .map_or(1.0, |quality| quality.parse().unwrap_or(0.0));
+        if quality <= 0.0 {
+            continue;
+        }
+        let better = match preferred {
+            Some((_, current_quality)) => {
+                quality > current_quality
+                    || (quality == current_quality && encoding == Encoding::Brotli)
+            }
+            None => true,
+        };
+        if better {
+            preferred = Some((encoding, quality));
+        }
+    }
+    preferred.map(|(encoding, _)| encoding)
+}
+
+/// Compress a body with an encoding.
+pub fn compress(encoding: Encoding, body: &[u8]) -> std::io::Result<Vec<u8>> {
+    match encoding {
+        Encoding::Brotli => {
+            // Quality 5 compresses JSON close to the maximum at a fraction of its cost.
+            let mut writer = brotli::CompressorWriter::new(Vec::new(), 4096, 5, 22);
+            writer.write_all(body)?;
+            Ok(writer.into_inner())
+        }
+        Encoding::Gzip => {
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(body)?;
+            encoder.finish()
+        }
+    }
+}
+
+/// Layer that applies [`Compression`] to the HTTP service of the RPC server.
+#[derive(Debug, Clone, Copy)]
+pub struct CompressionLayer {
+    min_bytes: usize,
+}
+
+impl CompressionLayer {
+    pub fn new(min_bytes: usize) -> Self {
+        Self { min_bytes }
+    }
+}
+
+impl<S> Layer<S> for CompressionLayer {
+    type Service = Compression<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Compression {
+            inner,
+            min_bytes: self.min_bytes,
+        }
+    }
+}
+
+/// Compresses the responses of at least `min_bytes` for the clients accepting it.
+#[derive(Debug, Clone)]
+pub struct Compression<S> {
+    inner: S,
+    min_bytes: usize,
+}
+
+impl<S> Service<Request<Body>> for Compression<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>> + Clone + Send + 'static,
+    S::Error: Into<Box<dyn Error + Send + Sync>> + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = Box<dyn Error + Send + Sync + 'static>;
+    type Future =
+        Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send + 'static>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let encoding = req
+            .headers()
+            .get(header::ACCEPT_ENCODING)
+            .and_then(|value| value.to_str().ok())
+            .and_then(preferred_encoding);
+        let min_bytes = self.min_bytes;
+        // The inner service was polled ready, keep it and leave a fresh clone in its place.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+
+        Box::pin(async move {
+            let response = inner.call(req).await.map_err(Into::into)?;
+            let encoding = match encoding {
+                Some(encoding)
+                    if response.status() != StatusCode::SWITCHING_PROTOCOLS
+                        && !response.headers().contains_key(header::CONTENT_ENCODING) =>
+                {
+                    encoding
+                }
+                _ => return Ok(response),
+            };
+            let (mut parts, body) = response.into_parts();
+            let bytes = hyper::body::to_bytes(body).await?;
+            if bytes.len() < min_bytes {
+                return Ok(Response::from_parts(parts, Body::from(bytes)));
+            }
+            let compressed = compress(encoding, &bytes)?;
+            parts.headers.remove(header::CONTENT_LENGTH);
+            parts.headers.insert(
+                header::CONTENT_ENCODING,
+                HeaderValue::from_static(encoding.as_str()),
+            );
+            parts
+                .headers
+                .append(header::VARY, HeaderValue::from_static("accept-encoding"));
+            Ok(Response::from_parts(parts, Body::from(compressed)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn test_encoding_is_negotiated_by_quality() {
+        assert_eq!(
+            preferred_encoding("gzip, deflate, br"),
+            Some(Encoding::Brotli)
+        );
+        assert_eq!(preferred_encoding("br;q=0.5, gzip"), Some(Encoding::Gzip));
+        assert_eq!(preferred_encoding("gzip;q=0, br;q=0"), None);
+        assert_eq!(preferred_encoding("*"), Some(Encoding::Gzip));
+        assert_eq!(preferred_encoding("identity"), None);
+    }
+
+    #[test]
+    fn test_gzip_body_decompresses_to_the_response() {
+        let body = br#"{"jsonrpc":"2.0","id":1,"result":"0x1"}"#.repeat(100);
+        let compressed = compress(Encoding::Gzip, &body).unwrap();
+        assert!(compressed.len() < body.len());
+
+        let mut decompressed = Vec::new();
+        flate2::read::GzDecoder::new(compressed.as_slice())
+            .read_to_end(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, body);
+    }
+}
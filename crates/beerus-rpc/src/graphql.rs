@@ -0,0 +1,275 @@
+use async_graphql::{
+    connection::{query, Connection, Edge},
+    EmptyMutation, EmptySubscription, Enum, InputObject, Object, Result, Schema, SimpleObject,
+};
+use async_graphql_axum::{GraphQLRequest, GraphQLResponse};
+use axum::{extract::Extension, routing::post, Router};
+use beerus_core::lightclient::{service::BeerusHandle, starknet::block_hash::transaction_hash};
+use starknet::{
+    core::types::FieldElement,
+    providers::jsonrpc::models::{BlockWithTxs, EmittedEvent, Transaction as StarknetTransaction},
+};
+use std::{net::SocketAddr, sync::Arc};
+
+/// Maximum number of items returned in a single page.
+const MAX_PAGE_SIZE: usize = 100;
+
+pub type BeerusSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+pub enum TransactionType {
+    Invoke,
+    Declare,
+    Deploy,
+    DeployAccount,
+    L1Handler,
+}
+
+#[derive(SimpleObject)]
+pub struct Transaction {
+    hash: String,
+    #[graphql(name = "type")]
+    transaction_type: TransactionType,
+    block_number: u64,
+    /// Position of the transaction in its block.
+    index: usize,
+}
+
+#[derive(SimpleObject)]
+pub struct Block {
+    number: u64,
+    hash: String,
+    parent_hash: String,
+    new_root: String,
+    timestamp: u64,
+    sequencer_address: String,
+    transactions: Vec<Transaction>,
+}
+
+#[derive(SimpleObject)]
+pub struct Event {
+    from_address: String,
+    keys: Vec<String>,
+    data: Vec<String>,
+    block_number: u64,
+    block_hash: String,
+    transaction_hash: String,
+}
+
+#[derive(InputObject, Default)]
+pub struct BlockFilter {
+    from_block: Option<u64>,
+    to_block: Option<u64>,
+}
+
+#[derive(InputObject, Default)]
+pub struct EventFilter {
+    from_address: Option<String>,
+    /// Only return the events whose first key is one of these.
+    keys: Option<Vec<String>>,
+    from_block: Option<u64>,
+    to_block: Option<u64>,
+}
+
+pub struct QueryRoot {
+    beerus: BeerusHandle,
+}
+
+#[Object]
+impl QueryRoot {
+    /// Return a block by number or hash, or the latest block if neither is given.
+    async fn block(&self, number: Option<u64>, hash: Option<String>) -> Result<Option<Block>> {
+        let node = self.beerus.node.load_full();
+        let block = match (number, hash) {
+            (Some(number), _) => node.payload.get(&number),
+            (None, Some(hash)) => {
+                let hash = parse_felt(&hash)?;
+                node.payload.get_by_hash(&hash)
+            }
+            (None, None) => node.payload.get(&node.block_number),
+        };
+        Ok(block.map(|block| to_block(&block)))
+    }
+
+    /// Return the blocks of the payload, by ascending number.
+    async fn blocks(
+        &self,
+        filter: Option<BlockFilter>,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> Result<Connection<usize, Block>> {
+        let filter = filter.unwrap_or_default();
+        let node = self.beerus.node.load_full();
+        let blocks: Vec<Arc<BlockWithTxs>> = node
+            .payload
+            .range(filter.from_block.unwrap_or(0)..=filter.to_block.unwrap_or(u64::MAX))
+            .collect();
+        paginate(&blocks, after, before, first, last, |block| to_block(block)).await
+    }
+
+    /// Return a transaction of the payload by hash.
+    async fn transaction(&self, hash: String) -> Result<Option<Transaction>> {
+        let hash = parse_felt(&hash)?;
+        let node = self.beerus.node.load_full();
+        Ok(node.payload.values().find_map(|block| {
+            block
+                .transactions
+                .iter()
+                .enumerate()
+                .find(|(_, transaction)| transaction_hash(transaction) == hash)
+                .map(|(index, transaction)| to_transaction(block.block_number, index, transaction))
+        }))
+    }
+
+    /// Return the events emitted by the blocks of the payload, in order.
+    async fn events(
+        &self,
+        filter: Option<EventFilter>,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> Result<Connection<usize, Event>> {
+        let filter = filter.unwrap_or_default();
+        let from_address = filter.from_address.as_deref().map(parse_felt).transpose()?;
+        let keys = filter
+            .keys
+            .map(|keys| {
+                keys.iter()
+                    .map(|key| parse_felt(key))
+                    .collect::<Result<Vec<_>>>()
+            })
+            .transpose()?;
+        let node = self.beerus.node.load_full();
+        let events: Vec<&EmittedEvent> = node.matching_events(
+            filter.from_block.unwrap_or(0),
+            filter.to_block.unwrap_or(u64::MAX),
+            from_address.as_ref(),
+            keys.as_deref(),
+        );
+        paginate(&events, after, before, first, last, |event| to_event(event)).await
+    }
+}
+
+/// Serve the GraphQL API until the server fails.
+pub async fn run_graphql_server(beerus: BeerusHandle, addr: SocketAddr) -> eyre::Result<()> {
+    let schema =
+        BeerusSchema::build(QueryRoot { beerus }, EmptyMutation, EmptySubscription).finish();
+    let app = Router::new()
+        .route("/graphql", post(graphql_handler))
+        .layer(Extension(schema));
+    axum::Server::bind(&addr)
+        .serve(app.into_make_service())
+        .await?;
+    Ok(())
+}
+
+async fn graphql_handler(
+    Extension(schema): Extension<BeerusSchema>,
+    request: GraphQLRequest,
+) -> GraphQLResponse {
+    schema.execute(request.into_inner()).await.into()
+}
+
+/// Build a page of `items` from the standard connection arguments, using offsets as cursors.
+async fn paginate<T, N, F>(
+    items: &[T],
+    after: Option<String>,
+    before: Option<String>,
+    first: Option<i32>,
+    last: Option<i32>,
+    to_node: F,
+) -> Result<Connection<usize, N>>
+where
+    N: async_graphql::OutputType,
+    F: Fn(&T) -> N,
+{
+    query(
+        after,
+        before,
+        first,
+        last,
+        |after: Option<usize>, before: Option<usize>, first, last| async move {
+            let mut start = after.map(|after| after + 1).unwrap_or(0);
+            let mut end = before.unwrap_or(items.len()).min(items.len());
+            if let Some(first) = first {
+                end = end.min(start + first.min(MAX_PAGE_SIZE));
+            }
+            if let Some(last) = last {
+                start = start.max(end.saturating_sub(last.min(MAX_PAGE_SIZE)));
+            }
+            if first.is_none() && last.is_none() {
+                end = end.min(start + MAX_PAGE_SIZE);
+            }
+            start = start.min(end);
+
+            let mut connection = Connection::new(start > 0, end < items.len());
+            connection.edges.extend(
+                items[start..end]
+                    .iter()
+                    .enumerate()
+                    .map(|(offset, item)| Edge::new(start + offset, to_node(item))),
+            );
+            Ok::<_, async_graphql::Error>(connection)
+        },
+    )
+    .await
+}
+
+fn to_block(block: &BlockWithTxs) -> Block {
+    Block {
+        number: block.block_number,
+        hash: format!("{:#x}", block.block_hash),
+        parent_hash: format!("{:#x}", block.parent_hash),
+        new_root: format!("{:#x}", block.new_root),
+        timestamp: block.timestamp,
+        sequencer_address: format!("{:#x}", block.sequencer_address),
+        transactions: block
+            .transactions
+            .iter()
+            .enumerate()
+            .map(|(index, transaction)| to_transaction(block.block_number, index, transaction))
+            .collect(),
+    }
+}
+
+fn to_transaction(
+    block_number: u64,
+    index: usize,
+    transaction: &StarknetTransaction,
+) -> Transaction {
+    let transaction_type = match transaction {
+        StarknetTransaction::Invoke(_) => TransactionType::Invoke,
+        StarknetTransaction::Declare(_) => TransactionType::Declare,
+        StarknetTransaction::Deploy(_) => TransactionType::Deploy,
+        StarknetTransaction::DeployAccount(_) => TransactionType::DeployAccount,
+        StarknetTransaction::L1Handler(_) => TransactionType::L1Handler,
+    };
+    Transaction {
+        hash: format!("{:#x}", transaction_hash(transaction)),
+        transaction_type,
+        block_number,
+        index,
+    }
+}
+
+fn to_event(event: &EmittedEvent) -> Event {
+    Event {
+        from_address: format!("{:#x}", event.from_address),
+        keys: event.keys.iter().map(|key| format!("{key:#x}")).collect(),
+        data: event
+            .data
+            .iter()
+            .map(|value| format!("{value:#x}"))
+            .collect(),
+        block_number: event.block_number,
+        block_hash: format!("{:#x}", event.block_hash),
+        transaction_hash: format!("{:#x}", event.transaction_hash),
+    }
+}
+
+fn parse_felt(value: &str) -> Result<FieldElement> {
+    FieldElement::from_hex_be(value).map_err(|e| format!("{value}: {e}").into())
+}
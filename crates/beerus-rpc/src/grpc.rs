@@ -0,0 +1,331 @@
+use beerus_core::lightclient::{
+    beerus::{BlockTarget, WithMetadata},
+    service::BeerusHandle,
+};
+use ethers::types::U256;
+use starknet::{
+    core::types::FieldElement,
+    providers::jsonrpc::models::{
+        BlockId as StarknetBlockId, BlockTag, BlockWithTxHashes, MaybePendingBlockWithTxHashes,
+        PendingBlockWithTxHashes,
+    },
+};
+use std::{net::SocketAddr, str::FromStr, time::Duration};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{transport::Server, Request, Response, Status};
+
+pub mod proto {
+    tonic::include_proto!("beerus.v1");
+}
+
+use proto::{
+    beerus_server::{Beerus, BeerusServer},
+    block_id::Id,
+    Block, BlockHashAndNumberResponse, BlockId, BlockNumberResponse, CallRequest, Empty,
+    GetNonceRequest, GetStorageAtRequest, MessageRequest, U256Response, VerifiedResponse,
+};
+
+/// Interval at which `SubscribeBlocks` checks for new blocks.
+const SUBSCRIPTION_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+pub struct BeerusGrpc {
+    beerus: BeerusHandle,
+}
+
+impl BeerusGrpc {
+    pub fn new(beerus: BeerusHandle) -> Self {
+        Self { beerus }
+    }
+}
+
+/// Serve the gRPC API until the server fails.
+pub async fn run_grpc_server(
+    beerus: BeerusHandle,
+    addr: SocketAddr,
+) -> Result<(), tonic::transport::Error> {
+    Server::builder()
+        .add_service(BeerusServer::new(BeerusGrpc::new(beerus)))
+        .serve(addr)
+        .await
+}
+
+#[tonic::async_trait]
+impl Beerus for BeerusGrpc {
+    type SubscribeBlocksStream = ReceiverStream<Result<Block, Status>>;
+
+    async fn block_number(
+        &self,
+        _request: Request<Empty>,
+    ) -> Result<Response<BlockNumberResponse>, Status> {
+        let block_number = self.beerus.node.load().block_number;
+        Ok(Response::new(BlockNumberResponse { block_number }))
+    }
+
+    async fn block_hash_and_number(
+        &self,
+        _request: Request<Empty>,
+    ) -> Result<Response<BlockHashAndNumberResponse>, Status> {
+        let block = self
+            .beerus
+            .get_block_hash_and_number()
+            .await
+            .map_err(|e| Status::not_found(e.to_string()))?;
+        Ok(Response::new(BlockHashAndNumberResponse {
+            block_hash: format!("{:#x}", block.block_hash),
+            block_number: block.block_number,
+        }))
+    }
+
+    async fn get_block_with_tx_hashes(
+        &self,
+        request: Request<BlockId>,
+    ) -> Result<Response<Block>, Status> {
+        let block_id = match request.into_inner().id {
+            Some(Id::Number(number)) => StarknetBlockId::Number(number),
+            Some(Id::Hash(hash)) => StarknetBlockId::Hash(parse_felt(&hash)?),
+            Some(Id::Tag(tag)) => match tag.to_lowercase().as_str() {
+                "latest" => StarknetBlockId::Tag(BlockTag::Latest),
+                "pending" => StarknetBlockId::Tag(BlockTag::Pending),
+                _ => return Err(Status::invalid_argument("Invalid Tag")),
+            },
+            None => return Err(Status::invalid_argument("Missing block id")),
+        };
+        match self.beerus.get_block_with_tx_hashes(&block_id).await {
+            Ok(MaybePendingBlockWithTxHashes::Block(block)) => Ok(Response::new(to_proto(&block))),
+            Ok(MaybePendingBlockWithTxHashes::PendingBlock(block)) => {
+                Ok(Response::new(pending_to_proto(&block)))
+            }
+            Err(e) => Err(Status::not_found(e.to_string())),
+        }
+    }
+
+    async fn subscribe_blocks(
+        &self,
+        _request: Request<Empty>,
+    ) -> Result<Response<Self::SubscribeBlocksStream>, Status> {
+        let (tx, rx) = mpsc::channel(16);
+        let node = self.beerus.node.clone();
+        tokio::spawn(async move {
+            let mut last_sent = node.load().block_number;
+            loop {
+                tokio::time::sleep(SUBSCRIPTION_POLL_INTERVAL).await;
+                let blocks: Vec<Block> = node
+                    .load()
+                    .payload
+                    .range(last_sent + 1..)
+                    .map(|block| {
+                        to_proto(&BlockWithTxHashes {
+                            status: block.status.clone(),
+                            block_hash: block.block_hash,
+                            parent_hash: block.parent_hash,
+                            block_number: block.block_number,
+                            new_root: block.new_root,
+                            timestamp: block.timestamp,
+                            sequencer_address: block.sequencer_address,
+                            transactions: vec![],
+                        })
+                    })
+                    .collect();
+                for block in blocks {
+                    last_sent = block.block_number;
+                    if tx.send(Ok(block)).await.is_err() {
+                        // The client went away.
+                        return;
+                    }
+                }
+            }
+        });
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
+    async fn get_storage_at(
+        &self,
+        request: Request<GetStorageAtRequest>,
+    ) -> Result<Response<VerifiedResponse>, Status> {
+        let request = request.into_inner();
+        let result = self
+            .beerus
+            .starknet_get_storage_at_with_metadata(
+                parse_felt(&request.contract_address)?,
+                parse_felt(&request.key)?,
+                parse_target(&request.block_tag, self.beerus.default_target())?,
+            )
+            .await
+            .map_err(to_status)?;
+        Ok(Response::new(verified_response(
+            vec![result.result],
+            &result,
+        )))
+    }
+
+    async fn get_nonce(
+        &self,
+        request: Request<GetNonceRequest>,
+    ) -> Result<Response<VerifiedResponse>, Status> {
+        let request = request.into_inner();
+        let result = self
+            .beerus
+            .starknet_get_nonce_with_metadata(
+                parse_felt(&request.contract_address)?,
+                parse_target(&request.block_tag, self.beerus.default_target())?,
+            )
+            .await
+            .map_err(to_status)?;
+        Ok(Response::new(verified_response(
+            vec![result.result],
+            &result,
+        )))
+    }
+
+    async fn call(
+        &self,
+        request: Request<CallRequest>,
+    ) -> Result<Response<VerifiedResponse>, Status> {
+        let request = request.into_inner();
+        let calldata = request
+            .calldata
+            .iter()
+            .map(|value| parse_felt(value))
+            .collect::<Result<Vec<_>, _>>()?;
+        let result = self
+            .beerus
+            .starknet_call_contract_with_metadata(
+                parse_felt(&request.contract_address)?,
+                parse_felt(&request.entry_point_selector)?,
+                calldata,
+                parse_target(&request.block_tag, self.beerus.default_target())?,
+            )
+            .await
+            .map_err(to_status)?;
+        Ok(Response::new(verified_response(
+            result.result.clone(),
+            &result,
+        )))
+    }
+
+    async fn l1_to_l2_messages(
+        &self,
+        request: Request<MessageRequest>,
+    ) -> Result<Response<U256Response>, Status> {
+        let msg_hash = parse_u256(&request.into_inner().msg_hash)?;
+        let value = self
+            .beerus
+            .starknet_l1_to_l2_messages(msg_hash)
+            .await
+            .map_err(to_status)?;
+        Ok(Response::new(U256Response {
+            value: format!("{value:#x}"),
+        }))
+    }
+
+    async fn l1_to_l2_message_cancellations(
+        &self,
+        request: Request<MessageRequest>,
+    ) -> Result<Response<U256Response>, Status> {
+        let msg_hash = parse_u256(&request.into_inner().msg_hash)?;
+        let value = self
+            .beerus
+            .starknet_l1_to_l2_message_cancellations(msg_hash)
+            .await
+            .map_err(to_status)?;
+        Ok(Response::new(U256Response {
+            value: format!("{value:#x}"),
+        }))
+    }
+
+    async fn l1_to_l2_message_nonce(
+        &self,
+        _request: Request<Empty>,
+    ) -> Result<Response<U256Response>, Status> {
+        let value = self
+            .beerus
+            .starknet_l1_to_l2_message_nonce()
+            .await
+            .map_err(to_status)?;
+        Ok(Response::new(U256Response {
+            value: format!("{value:#x}"),
+        }))
+    }
+
+    async fn l2_to_l1_messages(
+        &self,
+        request: Request<MessageRequest>,
+    ) -> Result<Response<U256Response>, Status> {
+        let msg_hash = parse_u256(&request.into_inner().msg_hash)?;
+        let value = self
+            .beerus
+            .starknet_l2_to_l1_messages(msg_hash)
+            .await
+            .map_err(to_status)?;
+        Ok(Response::new(U256Response {
+            value: format!("{value:#x}"),
+        }))
+    }
+}
+
+fn to_proto(block: &BlockWithTxHashes) -> Block {
+    Block {
+        block_hash: format!("{:#x}", block.block_hash),
+        parent_hash: format!("{:#x}", block.parent_hash),
+        block_number: block.block_number,
+        new_root: format!("{:#x}", block.new_root),
+        timestamp: block.timestamp,
+        sequencer_address: format!("{:#x}", block.sequencer_address),
+        transactions: block
+            .transactions
+            .iter()
+            .map(|hash| format!("{hash:#x}"))
+            .collect(),
+        pending: false,
+    }
+}
+
+fn pending_to_proto(block: &PendingBlockWithTxHashes) -> Block {
+    Block {
+        parent_hash: format!("{:#x}", block.parent_hash),
+        timestamp: block.timestamp,
+        sequencer_address: format!("{:#x}", block.sequencer_address),
+        transactions: block
+            .transactions
+            .iter()
+            .map(|hash| format!("{hash:#x}"))
+            .collect(),
+        pending: true,
+        ..Block::default()
+    }
+}
+
+fn verified_response<T>(
+    result: Vec<FieldElement>,
+    with_metadata: &WithMetadata<T>,
+) -> VerifiedResponse {
+    VerifiedResponse {
+        result: result.iter().map(|value| format!("{value:#x}")).collect(),
+        proven_block_number: with_metadata.metadata.proven_block_number,
+        state_root: format!("{:#x}", with_metadata.metadata.state_root),
+        verified: with_metadata.metadata.verified,
+        block_number: with_metadata.metadata.block_number,
+    }
+}
+
+fn parse_felt(value: &str) -> Result<FieldElement, Status> {
+    FieldElement::from_hex_be(value).map_err(|e| Status::invalid_argument(e.to_string()))
+}
+
+fn parse_target(block_tag: &str, default: BlockTarget) -> Result<BlockTarget, Status> {
+    match block_tag {
+        "" => Ok(default),
+        block_tag => {
+            BlockTarget::from_str(block_tag).map_err(|e| Status::invalid_argument(e.to_string()))
+        }
+    }
+}
+
+fn parse_u256(value: &str) -> Result<U256, Status> {
+    U256::from_str(value).map_err(|e| Status::invalid_argument(e.to_string()))
+}
+
+fn to_status(e: eyre::Report) -> Status {
+    Status::internal(e.to_string())
+}
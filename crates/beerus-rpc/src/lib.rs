@@ -1,11 +1,86 @@
 use eyre::Result;
 use jsonrpsee::server::{ServerBuilder, ServerHandle};
-use std::net::{AddrParseError, SocketAddr};
+use serde_json::{json, Value};
+use std::{
+    net::{AddrParseError, SocketAddr},
+    sync::Arc,
+};
 use thiserror::Error;
+pub mod admin;
+pub mod attestation;
+pub mod clients;
+pub mod compat;
+pub mod compression;
+#[cfg(feature = "graphql")]
+pub mod graphql;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod limits;
+pub mod metrics;
+pub mod networks;
+pub mod readiness;
+pub mod request_log;
+pub mod response_headers;
 pub mod server;
+pub mod timeout;
 pub mod utils;
-use beerus_core::lightclient::beerus::BeerusLightClient;
+use admin::{
+    admin_token_from_env, is_admin_addr_allowed, AdminApiServer, AdminAuthLayer, AdminRpc,
+};
+use attestation::{signing_key_from_env, AttestationLayer};
+use beerus_core::lightclient::service::BeerusHandle;
+use clients::{ClientAuthLayer, ClientRegistry};
+use compat::{SpecCompatLayer, SpecVersion};
+use compression::{compression_min_bytes_from_env, CompressionLayer};
+use ethers::signers::Signer;
+use limits::{ConnectionLimitLayer, ConnectionLimits};
+use log::warn;
+use metrics::MetricsLayer;
+use networks::NetworkRouterLayer;
+use readiness::ReadinessLayer;
+use request_log::{RequestLogConfig, RequestLogLayer};
+use response_headers::{response_headers_from_env, ResponseHeadersLayer};
 use server::{BeerusApiServer, BeerusRpc};
+use timeout::{request_timeout_from_env, RequestTimeoutLayer};
+
+/// Build a JSON-RPC error response to a request: one error per call of a batch, notifications
+/// don't get any. Used by the middlewares answering in place of the server.
+pub(crate) fn error_response(request: &Value, code: i64, message: &str) -> Value {
+    let error = |id: &Value| {
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": code, "message": message }
+        })
+    };
+    match request {
+        Value::Array(calls) => Value::Array(
+            calls
+                .iter()
+                .filter_map(|call| call.get("id"))
+                .map(error)
+                .collect(),
+        ),
+        call => error(call.get("id").unwrap_or(&Value::Null)),
+    }
+}
+
+/// Largest request body read by the middlewares, the default limit of the jsonrpsee server.
+pub(crate) const MAX_REQUEST_BODY_SIZE: u32 = 10 * 1024 * 1024;
+
+/// Read a request body, up to `MAX_REQUEST_BODY_SIZE` bytes.
+/// # Returns
+/// `Ok(None)` if the body is larger, it is then left unread past the limit.
+pub(crate) async fn read_request_body(
+    body: hyper::Body,
+) -> Result<Option<hyper::body::Bytes>, Box<dyn std::error::Error + Send + Sync>> {
+    let body = http_body::Limited::new(body, MAX_REQUEST_BODY_SIZE as usize);
+    match hyper::body::to_bytes(body).await {
+        Ok(bytes) => Ok(Some(bytes)),
+        Err(err) if err.is::<http_body::LengthLimitError>() => Ok(None),
+        Err(err) => Err(err),
+    }
+}
 
 #[derive(Error, Debug)]
 pub enum RpcError {
@@ -13,20 +88,151 @@ pub enum RpcError {
     JsonRpcServerError(#[from] jsonrpsee::core::Error),
     #[error(transparent)]
     ParseError(#[from] AddrParseError),
+    #[error("The admin server listens on {0} without RPC_ADMIN_TOKEN, use a loopback address or set a token")]
+    UnauthenticatedAdminServer(SocketAddr),
+}
+
+/// Serve the JSON-RPC API on `PORT`.
+/// # Arguments
+/// * `beerus` - The light client answering the calls.
+/// * `clients` - The clients allowed to call the server, anyone if `None`.
+pub async fn run_server(
+    beerus: BeerusHandle,
+    clients: Option<Arc<ClientRegistry>>,
+) -> Result<(SocketAddr, ServerHandle), RpcError> {
+    serve(beerus, clients, port_addr()?, None).await
+}
+
+/// Serve the JSON-RPC API on an address, e.g. an ephemeral port of the loopback interface.
+pub async fn run_server_on(
+    beerus: BeerusHandle,
+    clients: Option<Arc<ClientRegistry>>,
+    socket_addr: SocketAddr,
+) -> Result<(SocketAddr, ServerHandle), RpcError> {
+    serve(beerus, clients, socket_addr, None).await
 }
 
-pub async fn run_server(beerus: BeerusLightClient) -> Result<(SocketAddr, ServerHandle), RpcError> {
-    let socket_addr = format!(
+/// Serve the JSON-RPC API of several networks on `PORT`, see [`networks`]. The other networks
+/// are served on ephemeral ports of the loopback interface, the requests for them proxied there.
+/// # Arguments
+/// * `default` - The network answering the requests which don't select one, and its name.
+/// * `others` - The other networks, by name.
+/// * `clients` - The clients allowed to call the servers, anyone if `None`.
+pub async fn run_networks_server(
+    default: (String, BeerusHandle),
+    others: Vec<(String, BeerusHandle)>,
+    clients: Option<Arc<ClientRegistry>>,
+) -> Result<(SocketAddr, ServerHandle), RpcError> {
+    let mut backends = Vec::new();
+    for (network, beerus) in others {
+        let (addr, handle) = serve(
+            beerus,
+            clients.clone(),
+            SocketAddr::from(([127, 0, 0, 1], 0)),
+            None,
+        )
+        .await?;
+        backends.push((network.clone(), addr));
+        // The server runs as long as its handle is held.
+        tokio::spawn(async move {
+            handle.stopped().await;
+            warn!("The server of network {network} stopped");
+        });
+    }
+    let (network, beerus) = default;
+    let router = NetworkRouterLayer::new(network, backends);
+    serve(beerus, clients, port_addr()?, Some(router)).await
+}
+
+/// Address of the server on `PORT`, 3030 by default.
+fn port_addr() -> Result<SocketAddr, AddrParseError> {
+    format!(
         "0.0.0.0:{}",
         std::env::var("PORT").unwrap_or_else(|_| "3030".to_owned())
     )
-    .parse::<SocketAddr>()
-    .unwrap();
+    .parse()
+}
+
+/// Serve the JSON-RPC API of a light client on an address.
+async fn serve(
+    beerus: BeerusHandle,
+    clients: Option<Arc<ClientRegistry>>,
+    socket_addr: SocketAddr,
+    router: Option<NetworkRouterLayer>,
+) -> Result<(SocketAddr, ServerHandle), RpcError> {
+    let spec_version = SpecVersion::from_env();
+    // The admin namespace is only served on the public server behind authentication.
+    let admin_token = admin_token_from_env();
+    let signing_key = signing_key_from_env();
+    let signer = signing_key
+        .as_ref()
+        .map(|signing_key| signing_key.address());
+    let limits = ConnectionLimits::from_env();
+    // The router is outermost, the requests for the other networks go through the middlewares
+    // of their own servers. The connection limits come next so that the write timeout applies
+    // to the body sent. Compression comes next, and signing right below it, so that the
+    // signature covers the body the client decodes.
+    let middleware = tower::ServiceBuilder::new()
+        .option_layer(router)
+        .layer(ConnectionLimitLayer::new(limits))
+        .option_layer(compression_min_bytes_from_env().map(CompressionLayer::new))
+        .option_layer(
+            signing_key.map(|signing_key| AttestationLayer::new(signing_key, beerus.clone())),
+        )
+        .option_layer(
+            response_headers_from_env().then(|| ResponseHeadersLayer::new(beerus.clone())),
+        )
+        .option_layer(RequestLogConfig::from_env().map(RequestLogLayer::new))
+        .option_layer(clients.clone().map(ClientAuthLayer::new))
+        .option_layer(admin_token.clone().map(AdminAuthLayer::new))
+        .layer(MetricsLayer::new(beerus.clone()).with_clients(clients.clone()))
+        .layer(ReadinessLayer::new(beerus.subscribe_sync_status()))
+        .layer(RequestTimeoutLayer::new(request_timeout_from_env()))
+        .layer(SpecCompatLayer::new(spec_version));
+    let server = ServerBuilder::default()
+        .max_request_body_size(MAX_REQUEST_BODY_SIZE)
+        .max_connections(limits.max_connections)
+        .max_subscriptions_per_connection(limits.max_subscriptions_per_connection)
+        .set_middleware(middleware)
+        .build(socket_addr)
+        .await?;
+    let addr = server.local_addr()?;
+    let mut rpc_calls = BeerusRpc::new(beerus.clone())
+        .with_spec_version(spec_version)
+        .with_signer(signer)
+        .into_rpc();
+    if admin_token.is_some() {
+        rpc_calls.merge(AdminRpc::new(beerus).with_clients(clients).into_rpc())?;
+    }
+    let handle = server.start(rpc_calls).unwrap();
+
+    Ok((addr, handle))
+}
 
-    let server = ServerBuilder::default().build(socket_addr).await?;
+/// Serve the `admin_` namespace alone on a separate bind address, over HTTP only.
+/// The admin token is required there too when it is set, and the server refuses to listen on
+/// an address other than loopback without it.
+pub async fn run_admin_server(
+    beerus: BeerusHandle,
+    socket_addr: SocketAddr,
+    clients: Option<Arc<ClientRegistry>>,
+) -> Result<(SocketAddr, ServerHandle), RpcError> {
+    let admin_token = admin_token_from_env();
+    if !is_admin_addr_allowed(&socket_addr, admin_token.as_deref()) {
+        return Err(RpcError::UnauthenticatedAdminServer(socket_addr));
+    }
+    let middleware = tower::ServiceBuilder::new()
+        .option_layer(admin_token.map(AdminAuthLayer::new))
+        .layer(RequestTimeoutLayer::new(request_timeout_from_env()));
+    let server = ServerBuilder::default()
+        .http_only()
+        .set_middleware(middleware)
+        .build(socket_addr)
+        .await?;
     let addr = server.local_addr()?;
-    let rpc_calls = BeerusRpc::new(beerus);
-    let handle = server.start(rpc_calls.into_rpc()).unwrap();
+    let handle = server
+        .start(AdminRpc::new(beerus).with_clients(clients).into_rpc())
+        .unwrap();
 
     Ok((addr, handle))
 }
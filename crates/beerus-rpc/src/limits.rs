@@ -0,0 +1,289 @@
+//! Limits protecting the RPC server from slow or abusive clients: the number of connections,
+//! the subscriptions and the requests in flight on each connection, and the time a client is
+//! given to read a response.
+use crate::{error_response, read_request_body};
+use hyper::{
+    body::Bytes,
+    header::{self, HeaderValue},
+    Body, Request, Response, StatusCode,
+};
+use serde_json::Value;
+use std::{
+    error::Error,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::sync::Semaphore;
+use tower::{Layer, Service};
+
+/// Environment variable setting the maximum number of open connections.
+pub const MAX_CONNECTIONS_ENV: &str = "RPC_MAX_CONNECTIONS";
+pub const DEFAULT_MAX_CONNECTIONS: u32 = 100;
+/// Environment variable setting the maximum number of subscriptions of a WebSocket connection.
+pub const MAX_SUBSCRIPTIONS_ENV: &str = "RPC_MAX_SUBSCRIPTIONS_PER_CONNECTION";
+pub const DEFAULT_MAX_SUBSCRIPTIONS: u32 = 1024;
+/// Environment variable setting the maximum number of requests in flight on a connection.
+pub const MAX_IN_FLIGHT_ENV: &str = "RPC_MAX_IN_FLIGHT_PER_CONNECTION";
+pub const DEFAULT_MAX_IN_FLIGHT: usize = 16;
+/// Environment variable setting the time a client has to read a response, in milliseconds.
+pub const WRITE_TIMEOUT_ENV: &str = "RPC_WRITE_TIMEOUT_MS";
+pub const DEFAULT_WRITE_TIMEOUT_MS: u64 = 30_000;
+/// JSON-RPC error code returned when a connection has too many requests in flight.
+pub const TOO_MANY_IN_FLIGHT_CODE: i64 = -32006;
+/// Size of the chunks a response is written in, the write timeout is checked between them.
+pub const WRITE_CHUNK_BYTES: usize = 64 * 1024;
+
+/// Limits of the RPC server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConnectionLimits {
+    pub max_connections: u32,
+    pub max_subscriptions_per_connection: u32,
+    pub max_in_flight_per_connection: usize,
+    pub write_timeout: Duration,
+}
+
+impl Default for ConnectionLimits {
+    fn default() -> Self {
+        Self {
+            max_connections: DEFAULT_MAX_CONNECTIONS,
+            max_subscriptions_per_connection: DEFAULT_MAX_SUBSCRIPTIONS,
+            max_in_flight_per_connection: DEFAULT_MAX_IN_FLIGHT,
+            write_timeout: Duration::from_millis(DEFAULT_WRITE_TIMEOUT_MS),
+        }
+    }
+}
+
+impl ConnectionLimits {
+    /// Read the limits from the environment, the invalid values are replaced by the defaults.
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            max_connections: limit_from_env(MAX_CONNECTIONS_ENV, defaults.max_connections),
+            max_subscriptions_per_connection: limit_from_env(
+                MAX_SUBSCRIPTIONS_ENV,
+                defaults.max_subscriptions_per_connection,
+            ),
+            max_in_flight_per_connection: limit_from_env(
+                MAX_IN_FLIGHT_ENV,
+                defaults.max_in_flight_per_connection,
+            ),
+            write_timeout: Duration::from_millis(limit_from_env(
+                WRITE_TIMEOUT_ENV,
+                DEFAULT_WRITE_TIMEOUT_MS,
+            )),
+        }
+    }
+}
+
+/// Read a positive limit from the environment.
+fn limit_from_env<T>(name: &str, default: T) -> T
+where
+    T: std::str::FromStr + PartialOrd + Default,
+{
+    match std::env::var(name) {
+        Ok(value) => match value.parse::<T>() {
+            Ok(limit) if limit > T::default() => limit,
+            _ => {
+                log::warn!("Invalid {name}: {value}, using the default");
+                default
+            }
+        },
+        Err(_) => default,
+    }
+}
+
+/// Stream a response to the client, giving up when it isn't read within `timeout`.
+/// Responses fitting in a chunk are handed to the socket at once.
+pub fn with_write_timeout(bytes: Bytes, timeout: Duration) -> Body {
+    if bytes.len() <= WRITE_CHUNK_BYTES {
+        return Body::from(bytes);
+    }
+    let (mut sender, body) = Body::channel();
+    tokio::spawn(async move {
+        let deadline = tokio::time::Instant::now() + timeout;
+        for start in (0..bytes.len()).step_by(WRITE_CHUNK_BYTES) {
+            let chunk = bytes.slice(start..(start + WRITE_CHUNK_BYTES).min(bytes.len()));
+            match tokio::time::timeout_at(deadline, sender.send_data(chunk)).await {
+                Ok(Ok(())) => {}
+                // The client closed the connection.
+                Ok(Err(_)) => return,
+                Err(_) => {
+                    log::warn!(
+                        "Client did not read a response within {}ms, closing",
+                        timeout.as_millis()
+                    );
+                    sender.abort();
+                    return;
+                }
+            }
+        }
+    });
+    body
+}
+
+/// Layer that applies [`ConnectionLimit`] to the HTTP service of the RPC server.
+#[derive(Debug, Clone, Copy)]
+pub struct ConnectionLimitLayer {
+    limits: ConnectionLimits,
+}
+
+impl ConnectionLimitLayer {
+    pub fn new(limits: ConnectionLimits) -> Self {
+        Self { limits }
+    }
+}
+
+impl<S> Layer<S> for ConnectionLimitLayer {
+    type Service = ConnectionLimit<S>;
+
+    /// The server applies its middleware to every connection it accepts, the requests in
+    /// flight are counted per connection.
+    fn layer(&self, inner: S) -> Self::Service {
+        ConnectionLimit {
+            inner,
+            in_flight: Arc::new(Semaphore::new(self.limits.max_in_flight_per_connection)),
+            write_timeout: self.limits.write_timeout,
+        }
+    }
+}
+
+/// Rejects the requests beyond the limit in flight on a connection, and closes the
+/// connections of the clients which don't read their responses.
+#[derive(Debug, Clone)]
+pub struct ConnectionLimit<S> {
+    inner: S,
+    in_flight: Arc<Semaphore>,
+    write_timeout: Duration,
+}
+
+impl<S> Service<Request<Body>> for ConnectionLimit<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>> + Clone + Send + 'static,
+    S::Error: Into<Box<dyn Error + Send + Sync>> + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = Box<dyn Error + Send + Sync + 'static>;
+    type Future =
+        Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send + 'static>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let permit = self.in_flight.clone().try_acquire_owned();
+        let write_timeout = self.write_timeout;
+        // The inner service was polled ready, keep it and leave a fresh clone in its place.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+
+        Box::pin(async move {
+            let _permit = match permit {
+                Ok(permit) => permit,
+                Err(_) => {
+                    // The body is only read for the ids of the calls, up to the size the server
+                    // accepts.
+                    let request = match read_request_body(req.into_body()).await? {
+                        Some(bytes) => serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+                        None => Value::Null,
+                    };
+                    let body = serde_json::to_vec(&error_response(
+                        &request,
+                        TOO_MANY_IN_FLIGHT_CODE,
+                        "Too many requests in flight on this connection",
+                    ))?;
+                    return Ok(Response::builder()
+                        .status(StatusCode::TOO_MANY_REQUESTS)
+                        .header(
+                            header::CONTENT_TYPE,
+                            HeaderValue::from_static("application/json"),
+                        )
+                        .body(Body::from(body))?);
+                }
+            };
+            let response = inner.call(req).await.map_err(Into::into)?;
+            if response.status() == StatusCode::SWITCHING_PROTOCOLS {
+                return Ok(response);
+            }
+            let (parts, body) = response.into_parts();
+            let bytes = hyper::body::to_bytes(body).await?;
+            Ok(Response::from_parts(
+                parts,
+                with_write_timeout(bytes, write_timeout),
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_requests_beyond_the_limit_in_flight_are_rejected() {
+        let limits = ConnectionLimits {
+            max_in_flight_per_connection: 1,
+            ..Default::default()
+        };
+        let (release, released) = tokio::sync::oneshot::channel::<()>();
+        let released = Arc::new(tokio::sync::Mutex::new(Some(released)));
+        let mut service =
+            ConnectionLimitLayer::new(limits).layer(tower::service_fn(move |_: Request<Body>| {
+                let released = released.clone();
+                async move {
+                    if let Some(released) = released.lock().await.take() {
+                        released.await.ok();
+                    }
+                    Ok::<_, hyper::Error>(Response::new(Body::from("{}")))
+                }
+            }));
+        let request = || {
+            Request::new(Body::from(
+                json!({"jsonrpc": "2.0", "id": 1, "method": "starknet_blockNumber"}).to_string(),
+            ))
+        };
+
+        let pending = service.ready().await.unwrap().call(request());
+        let rejected = service
+            .ready()
+            .await
+            .unwrap()
+            .call(request())
+            .await
+            .unwrap();
+        assert_eq!(rejected.status(), StatusCode::TOO_MANY_REQUESTS);
+        let body: Value =
+            serde_json::from_slice(&hyper::body::to_bytes(rejected.into_body()).await.unwrap())
+                .unwrap();
+        assert_eq!(body["error"]["code"], TOO_MANY_IN_FLIGHT_CODE);
+
+        release.send(()).unwrap();
+        assert_eq!(pending.await.unwrap().status(), StatusCode::OK);
+        let admitted = service
+            .ready()
+            .await
+            .unwrap()
+            .call(request())
+            .await
+            .unwrap();
+        assert_eq!(admitted.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_response_not_read_in_time_is_aborted() {
+        let bytes = Bytes::from(vec![b'0'; 3 * WRITE_CHUNK_BYTES]);
+        let body = with_write_timeout(bytes, Duration::from_millis(10));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(hyper::body::to_bytes(body).await.is_err());
+
+        let bytes = Bytes::from(vec![b'0'; 3 * WRITE_CHUNK_BYTES]);
+        let body = with_write_timeout(bytes.clone(), Duration::from_secs(5));
+        assert_eq!(hyper::body::to_bytes(body).await.unwrap(), bytes);
+    }
+}
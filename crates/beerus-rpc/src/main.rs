@@ -1,56 +1,112 @@
 use beerus_core::{
-    config::Config,
+    config::{Config, Profile, DEFAULT_CONFIG_FILE},
     lightclient::{
         beerus::BeerusLightClient, ethereum::helios_lightclient::HeliosLightClient,
-        starknet::StarkNetLightClientImpl,
+        service::BeerusHandle, starknet::StarkNetLightClientImpl,
     },
 };
-use beerus_rpc::run_server;
+use beerus_rpc::{
+    clients::ClientRegistry, networks::networks_from_env, run_networks_server, run_server,
+};
 use dotenv::dotenv;
 use env_logger::Env;
+use eyre::{eyre, Result};
 use log::{error, info};
-use std::process::exit;
+use std::{
+    path::{Path, PathBuf},
+    process::exit,
+    sync::Arc,
+};
 
 #[tokio::main]
 async fn main() {
     env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
 
     dotenv().ok();
-    let config = Config::default();
-
-    info!("creating ethereum(helios) lightclient...");
-    let ethereum_lightclient = match HeliosLightClient::new(config.clone()).await {
-        Ok(ethereum_lightclient) => ethereum_lightclient,
+    // A profile of a config file is selected with `BEERUS_CONFIG` and `BEERUS_PROFILE`, or
+    // several with `BEERUS_NETWORKS`.
+    let config_file = std::env::var("BEERUS_CONFIG").ok().map(PathBuf::from);
+    let configs = match network_configs(config_file.as_deref()) {
+        Ok(configs) => configs,
         Err(err) => {
             error! {"{}", err};
             exit(1);
         }
     };
 
-    info!("creating starknet lightclient...");
-    let starknet_lightclient = match StarkNetLightClientImpl::new(&config) {
-        Ok(starknet_lightclient) => starknet_lightclient,
+    let mut networks = Vec::new();
+    for (network, config) in configs {
+        match create_light_client(config).await {
+            Ok(beerus) => networks.push((network, BeerusHandle::from(beerus))),
+            Err(err) => {
+                error! {"{}", err};
+                exit(1);
+            }
+        }
+    }
+
+    let clients = match ClientRegistry::from_env() {
+        Ok(clients) => clients.map(Arc::new),
         Err(err) => {
             error! {"{}", err};
             exit(1);
         }
     };
 
-    info!("creating beerus lightclient");
-    let mut beerus = BeerusLightClient::new(
-        config,
-        Box::new(ethereum_lightclient),
-        Box::new(starknet_lightclient),
-    );
+    // The servers are up while the light client syncs, see `/readyz` for its readiness.
+    info!("starting the Beerus light client in the background...");
+    for (_, beerus) in networks.iter() {
+        beerus.start_in_background();
+    }
+    // The other servers only serve the first network.
+    let (network, beerus) = networks.remove(0);
 
-    info!("starting the Beerus light client...");
-    if let Err(err) = beerus.start().await {
-        error!("{}", err);
+    #[cfg(feature = "grpc")]
+    if let Some(addr) = listen_addr_from_env("GRPC_PORT").unwrap_or_else(|err| {
+        error! {"{}", err};
         exit(1);
-    };
+    }) {
+        let beerus = beerus.clone();
+        info!("starting beerus grpc server on {addr}...");
+        tokio::spawn(async move {
+            if let Err(err) = beerus_rpc::grpc::run_grpc_server(beerus, addr).await {
+                error!("{}", err);
+            }
+        });
+    }
+
+    #[cfg(feature = "graphql")]
+    if let Some(addr) = listen_addr_from_env("GRAPHQL_PORT").unwrap_or_else(|err| {
+        error! {"{}", err};
+        exit(1);
+    }) {
+        let beerus = beerus.clone();
+        info!("starting beerus graphql server on {addr}...");
+        tokio::spawn(async move {
+            if let Err(err) = beerus_rpc::graphql::run_graphql_server(beerus, addr).await {
+                error!("{}", err);
+            }
+        });
+    }
+
+    if let Some(addr) = beerus_rpc::admin::admin_addr_from_env() {
+        let beerus = beerus.clone();
+        let clients = clients.clone();
+        info!("starting beerus admin server on {addr}...");
+        tokio::spawn(async move {
+            match beerus_rpc::run_admin_server(beerus, addr, clients).await {
+                Ok((_, handle)) => handle.stopped().await,
+                Err(err) => error!("{}", err),
+            }
+        });
+    }
 
     info!("starting beerus rpc server...");
-    match run_server(beerus).await {
+    let server = match networks.is_empty() {
+        true => run_server(beerus, clients).await,
+        false => run_networks_server((network, beerus), networks, clients).await,
+    };
+    match server {
         Ok((addr, server_handle)) => {
             info!("===================================================");
             info!("Beerus JSON-RPC Server started: http://{addr}");
@@ -64,3 +120,66 @@ async fn main() {
         }
     };
 }
+
+/// Read the configuration of every network: the profiles listed in `BEERUS_NETWORKS`, or the one
+/// selected by `BEERUS_PROFILE` alone. Environment variables apply to all of them.
+fn network_configs(config_file: Option<&Path>) -> Result<Vec<(String, Config)>> {
+    let networks = networks_from_env();
+    if networks.is_empty() {
+        let profile = std::env::var("BEERUS_PROFILE").ok();
+        let profile = Profile::load(config_file, profile.as_deref())?;
+        return Ok(vec![(
+            profile.name.clone(),
+            Config::new_from_profile(&profile)?,
+        )]);
+    }
+
+    let config_file = config_file.unwrap_or_else(|| Path::new(DEFAULT_CONFIG_FILE));
+    let mut configs: Vec<(String, Config)> = Vec::new();
+    for network in networks {
+        let config = Config::new_from_profile(&Profile::from_file(config_file, &network)?)?;
+        let data_dir = config.data_layout();
+        // Helios and the payload database can't share a directory.
+        if let Some((other, _)) = configs
+            .iter()
+            .find(|(_, other)| other.data_layout().root() == data_dir.root())
+        {
+            return Err(eyre!(
+                "Networks {other} and {network} share the data directory {}",
+                data_dir.root().display()
+            ));
+        }
+        configs.push((network, config));
+    }
+    Ok(configs)
+}
+
+/// Read the port of an optional server from the environment, it listens on every interface.
+#[cfg(any(feature = "grpc", feature = "graphql"))]
+fn listen_addr_from_env(var: &str) -> Result<Option<std::net::SocketAddr>> {
+    match std::env::var(var) {
+        Ok(port) => {
+            let port: u16 = port
+                .parse()
+                .map_err(|_| eyre!("Invalid {var}: {port}, expected a port number"))?;
+            Ok(Some(std::net::SocketAddr::from(([0, 0, 0, 0], port))))
+        }
+        Err(_) => Ok(None),
+    }
+}
+
+/// Create the light clients of a network.
+async fn create_light_client(config: Config) -> Result<BeerusLightClient> {
+    info!("creating ethereum(helios) lightclient...");
+    let ethereum_lightclient = HeliosLightClient::new(config.clone()).await?;
+
+    info!("creating starknet lightclient...");
+    let starknet_lightclient = StarkNetLightClientImpl::new(&config)?;
+
+    info!("creating beerus lightclient");
+    Ok(BeerusLightClient::new(
+        config,
+        Box::new(ethereum_lightclient),
+        Box::new(starknet_lightclient),
+    ))
+}
@@ -0,0 +1,325 @@
+//! Metrics of the light client in the Prometheus text format, served on `GET /metrics` by the
+//! RPC server so that operators can tell whether a node keeps up with the chains.
+use crate::clients::{render_client_metrics, ClientRegistry};
+use beerus_core::lightclient::{
+    beerus::{BeerusStatus, SyncStatus},
+    service::BeerusHandle,
+    starknet::health::ProviderHealth,
+};
+use hyper::{header, Body, Method, Request, Response, StatusCode};
+use std::{
+    error::Error,
+    fmt::Write,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tower::{Layer, Service};
+
+/// Path of the metrics endpoint.
+pub const METRICS_PATH: &str = "/metrics";
+
+/// Render the status of the light client as Prometheus gauges.
+pub fn render_metrics(status: &BeerusStatus) -> String {
+    let progress = &status.sync_progress;
+    let mut gauges = vec![
+        (
+            "beerus_synced",
+            "Whether the light client is synced.",
+            f64::from(u8::from(status.sync_status == SyncStatus::Synced)),
+        ),
+        (
+            "beerus_block_number",
+            "Number of the last verified StarkNet block.",
+            status.block_number as f64,
+        ),
+        (
+            "beerus_payload_blocks",
+            "Number of StarkNet blocks retained.",
+            status.payload_stats.blocks as f64,
+        ),
+        (
+            "beerus_memory_bytes",
+            "Approximate memory used by the retained blocks, events, receipts and indexes.",
+            status.memory_usage.total_bytes as f64,
+        ),
+        (
+            "beerus_starknet_provider_available",
+            "Whether the StarkNet provider answered the last poll.",
+            f64::from(u8::from(status.starknet_provider.available)),
+        ),
+        (
+            "beerus_sync_blocks_per_second",
+            "StarkNet blocks fetched per second.",
+            progress.blocks_per_sec,
+        ),
+        (
+            "beerus_sync_l1_slots_per_second",
+            "L1 blocks followed per second.",
+            progress.l1_slots_per_sec,
+        ),
+        (
+            "beerus_sync_blocks_behind",
+            "StarkNet blocks left to fetch.",
+            progress.blocks_behind as f64,
+        ),
+        (
+            "beerus_core_contract_upgraded",
+            "Whether the StarkNet core contract was upgraded since the start.",
+            f64::from(u8::from(status.core_contract_upgrade.is_some())),
+        ),
+        (
+            "beerus_sync_tasks_running",
+            "Whether the sync tasks run, they don't while awaiting a restart.",
+            f64::from(u8::from(status.sync_tasks.running)),
+        ),
+    ];
+    if let Some(budget_bytes) = status.memory_usage.budget_bytes {
+        gauges.push((
+            "beerus_memory_budget_bytes",
+            "Memory budget the node data is kept within.",
+            budget_bytes as f64,
+        ));
+    }
+    // The estimate is left out while the syncer makes no progress.
+    if let Some(eta_secs) = progress.eta_secs {
+        gauges.push((
+            "beerus_sync_eta_seconds",
+            "Estimated seconds left to sync.",
+            eta_secs as f64,
+        ));
+    }
+
+    let mut metrics = String::new();
+    for (name, help, value) in gauges {
+        let _ = writeln!(metrics, "# HELP {name} {help}");
+        let _ = writeln!(metrics, "# TYPE {name} gauge");
+        let _ = writeln!(metrics, "{name} {value}");
+    }
+    let _ = writeln!(
+        metrics,
+        "# HELP beerus_sync_task_failures_total Times the sync tasks stopped and were restarted."
+    );
+    let _ = writeln!(metrics, "# TYPE beerus_sync_task_failures_total counter");
+    let _ = writeln!(
+        metrics,
+        "beerus_sync_task_failures_total {}",
+        status.sync_tasks.failures
+    );
+    let _ = writeln!(
+        metrics,
+        "# HELP beerus_memory_evicted_blocks_total Blocks evicted to stay within the memory budget."
+    );
+    let _ = writeln!(metrics, "# TYPE beerus_memory_evicted_blocks_total counter");
+    let _ = writeln!(
+        metrics,
+        "beerus_memory_evicted_blocks_total {}",
+        status.memory_usage.evicted_blocks
+    );
+    metrics.push_str(&render_provider_metrics(&status.providers));
+    metrics
+}
+
+/// Render the health of the upstream providers, labelled by provider.
+fn render_provider_metrics(providers: &[ProviderHealth]) -> String {
+    if providers.is_empty() {
+        return String::new();
+    }
+    let series: [(&str, &str, &str, fn(&ProviderHealth) -> f64); 6] = [
+        (
+            "beerus_provider_requests_total",
+            "Requests sent to the provider.",
+            "counter",
+            |health| health.requests as f64,
+        ),
+        (
+            "beerus_provider_failures_total",
+            "Requests to the provider which got no response.",
+            "counter",
+            |health| health.failures as f64,
+        ),
+        (
+            "beerus_provider_success_rate",
+            "Share of the requests to the provider which got a response.",
+            "gauge",
+            |health| health.success_rate,
+        ),
+        (
+            "beerus_provider_latency_ms_avg",
+            "Average latency of the provider in milliseconds.",
+            "gauge",
+            |health| health.avg_latency_ms,
+        ),
+        (
+            "beerus_provider_divergences_total",
+            "Latest blocks of the provider older than another source's.",
+            "counter",
+            |health| health.divergences as f64,
+        ),
+        (
+            "beerus_provider_down_ranked",
+            "Whether the provider is tried after the other sources of blocks.",
+            "gauge",
+            |health| f64::from(u8::from(health.down_ranked)),
+        ),
+    ];
+    let mut metrics = String::new();
+    for (name, help, kind, value) in series {
+        let _ = writeln!(metrics, "# HELP {name} {help}");
+        let _ = writeln!(metrics, "# TYPE {name} {kind}");
+        for health in providers {
+            let _ = writeln!(
+                metrics,
+                "{name}{{provider=\"{}\"}} {}",
+                health.provider,
+                value(health)
+            );
+        }
+    }
+    metrics
+}
+
+/// Layer that applies [`Metrics`] to the HTTP service of the RPC server.
+#[derive(Clone)]
+pub struct MetricsLayer {
+    beerus: BeerusHandle,
+    clients: Option<Arc<ClientRegistry>>,
+}
+
+impl MetricsLayer {
+    pub fn new(beerus: BeerusHandle) -> Self {
+        Self {
+            beerus,
+            clients: None,
+        }
+    }
+
+    /// Expose the usage of the clients along with the status of the light client.
+    pub fn with_clients(mut self, clients: Option<Arc<ClientRegistry>>) -> Self {
+        self.clients = clients;
+        self
+    }
+}
+
+impl<S> Layer<S> for MetricsLayer {
+    type Service = Metrics<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Metrics {
+            inner,
+            beerus: self.beerus.clone(),
+            clients: self.clients.clone(),
+        }
+    }
+}
+
+/// Serves the metrics endpoint, and forwards the other requests to the RPC server.
+#[derive(Clone)]
+pub struct Metrics<S> {
+    inner: S,
+    beerus: BeerusHandle,
+    clients: Option<Arc<ClientRegistry>>,
+}
+
+impl<S> Service<Request<Body>> for Metrics<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>> + Clone + Send + 'static,
+    S::Error: Into<Box<dyn Error + Send + Sync>> + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = Box<dyn Error + Send + Sync + 'static>;
+    type Future =
+        Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send + 'static>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        // The inner service was polled ready, keep it and leave a fresh clone in its place.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let beerus = self.beerus.clone();
+        let clients = self.clients.clone();
+
+        Box::pin(async move {
+            if req.method() != Method::GET || req.uri().path() != METRICS_PATH {
+                return inner.call(req).await.map_err(Into::into);
+            }
+            let mut metrics = render_metrics(&beerus.beerus_status().await);
+            if let Some(clients) = clients {
+                metrics.push_str(&render_client_metrics(&clients.stats()));
+            }
+            Ok(Response::builder()
+                .status(StatusCode::OK)
+                .header(header::CONTENT_TYPE, "text/plain; version=0.0.4")
+                .body(Body::from(metrics))?)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use beerus_core::lightclient::{
+        beerus::{ProviderStatus, SyncProgress},
+        memory::MemoryUsage,
+        payload::PayloadStats,
+        service::SyncTasksHealth,
+    };
+
+    #[test]
+    fn test_eta_is_rendered_only_when_known() {
+        let mut status = BeerusStatus {
+            sync_status: SyncStatus::Synced,
+            block_number: 10,
+            state_root: "".to_string(),
+            last_block_hash_check: None,
+            payload_stats: PayloadStats::default(),
+            memory_usage: MemoryUsage {
+                total_bytes: 4096,
+                budget_bytes: Some(8192),
+                evicted_blocks: 3,
+                ..Default::default()
+            },
+            starknet_provider: ProviderStatus::default(),
+            sync_progress: SyncProgress::measure(10_000, 50, 20, 100),
+            core_contract_upgrade: None,
+            providers: vec![ProviderHealth {
+                provider: "rpc.example".to_string(),
+                requests: 4,
+                failures: 1,
+                success_rate: 0.75,
+                divergences: 3,
+                down_ranked: true,
+                ..Default::default()
+            }],
+            sync_tasks: SyncTasksHealth {
+                running: true,
+                failures: 2,
+                ..Default::default()
+            },
+            finality: Default::default(),
+        };
+
+        let metrics = render_metrics(&status);
+        assert!(metrics.contains("beerus_synced 1\n"));
+        assert!(metrics.contains("beerus_sync_blocks_per_second 5\n"));
+        assert!(metrics.contains("beerus_sync_l1_slots_per_second 2\n"));
+        assert!(metrics.contains("beerus_sync_eta_seconds 20\n"));
+        assert!(metrics.contains("beerus_core_contract_upgraded 0\n"));
+        assert!(metrics.contains("beerus_sync_tasks_running 1\n"));
+        assert!(metrics.contains("beerus_sync_task_failures_total 2\n"));
+        assert!(metrics.contains("beerus_memory_bytes 4096\n"));
+        assert!(metrics.contains("beerus_memory_budget_bytes 8192\n"));
+        assert!(metrics.contains("beerus_memory_evicted_blocks_total 3\n"));
+        assert!(metrics.contains("beerus_provider_requests_total{provider=\"rpc.example\"} 4\n"));
+        assert!(metrics.contains("beerus_provider_success_rate{provider=\"rpc.example\"} 0.75\n"));
+        assert!(metrics.contains("beerus_provider_down_ranked{provider=\"rpc.example\"} 1\n"));
+
+        status.sync_progress = SyncProgress::measure(10_000, 0, 0, 100);
+        assert!(!render_metrics(&status).contains("beerus_sync_eta_seconds"));
+    }
+}
@@ -0,0 +1,250 @@
+//! Several networks served by a single process, one light client per profile of the config
+//! file listed in `BEERUS_NETWORKS`, e.g. `mainnet,testnet`.
+//!
+//! The first network is answered by the server on `PORT`. Every other one gets a server of its
+//! own on the loopback interface, with the same middlewares, and the requests for it are
+//! proxied there. A request picks its network with the `/<network>` path prefix, stripped
+//! before it is answered, or the `x-beerus-network` header; the others go to the first network.
+use hyper::{client::HttpConnector, header, Body, Client, Request, Response, StatusCode, Uri};
+use log::warn;
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    future::Future,
+    net::SocketAddr,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tower::{Layer, Service};
+
+/// Environment variable listing the profiles served by the process, separated by commas.
+pub const NETWORKS_ENV: &str = "BEERUS_NETWORKS";
+/// Header selecting the network of a request, by profile name.
+pub const NETWORK_ROUTE_HEADER: &str = "x-beerus-network";
+
+/// Profiles listed in `BEERUS_NETWORKS`, empty if unset.
+pub fn networks_from_env() -> Vec<String> {
+    std::env::var(NETWORKS_ENV)
+        .map(|networks| {
+            networks
+                .split(',')
+                .map(str::trim)
+                .filter(|network| !network.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Network a request is routed to.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Route {
+    /// No network was selected, the request goes to the first one as is.
+    Default,
+    /// The request goes to a network, with the path left once the prefix is stripped.
+    Network { network: String, path: String },
+    /// The header names a network which is not served.
+    Unknown(String),
+}
+
+/// Route a request by the prefix of its path, then by its header.
+/// # Arguments
+/// * `networks` - The networks served.
+/// * `path_and_query` - The path of the request, with its query.
+/// * `header` - The `x-beerus-network` header, if any.
+pub fn route<'a>(
+    networks: impl IntoIterator<Item = &'a String>,
+    path_and_query: &str,
+    header: Option<&str>,
+) -> Route {
+    let networks: Vec<_> = networks.into_iter().collect();
+    let trimmed = path_and_query.trim_start_matches('/');
+    let end = trimmed.find(['/', '?']).unwrap_or(trimmed.len());
+    let (prefix, rest) = trimmed.split_at(end);
+    if let Some(network) = networks.iter().find(|network| network.as_str() == prefix) {
+        let path = match rest.starts_with('/') {
+            true => rest.to_string(),
+            false => format!("/{rest}"),
+        };
+        return Route::Network {
+            network: network.to_string(),
+            path,
+        };
+    }
+    match header {
+        Some(header) if networks.iter().any(|network| network.as_str() == header) => {
+            Route::Network {
+                network: header.to_string(),
+                path: path_and_query.to_string(),
+            }
+        }
+        Some(header) => Route::Unknown(header.to_string()),
+        None => Route::Default,
+    }
+}
+
+/// Layer that applies [`NetworkRouter`] to the HTTP service of the server on `PORT`.
+#[derive(Clone)]
+pub struct NetworkRouterLayer {
+    /// Address of the server of every network, `None` for the network served locally.
+    backends: Arc<BTreeMap<String, Option<SocketAddr>>>,
+    client: Client<HttpConnector>,
+}
+
+impl NetworkRouterLayer {
+    /// # Arguments
+    /// * `local` - The network answered by the server the layer is applied to.
+    /// * `backends` - The address of the server of each of the other networks.
+    pub fn new(local: String, backends: impl IntoIterator<Item = (String, SocketAddr)>) -> Self {
+        let mut routes: BTreeMap<_, _> = backends
+            .into_iter()
+            .map(|(network, addr)| (network, Some(addr)))
+            .collect();
+        routes.insert(local, None);
+        Self {
+            backends: Arc::new(routes),
+            client: Client::new(),
+        }
+    }
+}
+
+impl<S> Layer<S> for NetworkRouterLayer {
+    type Service = NetworkRouter<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        NetworkRouter {
+            inner,
+            backends: self.backends.clone(),
+            client: self.client.clone(),
+        }
+    }
+}
+
+/// Proxies the requests for the other networks to their servers, and strips the prefix of the
+/// ones for the local network.
+#[derive(Clone)]
+pub struct NetworkRouter<S> {
+    inner: S,
+    backends: Arc<BTreeMap<String, Option<SocketAddr>>>,
+    client: Client<HttpConnector>,
+}
+
+impl<S> Service<Request<Body>> for NetworkRouter<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>> + Clone + Send + 'static,
+    S::Error: Into<Box<dyn Error + Send + Sync>> + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = Box<dyn Error + Send + Sync + 'static>;
+    type Future =
+        Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send + 'static>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, mut req: Request<Body>) -> Self::Future {
+        // The inner service was polled ready, keep it and leave a fresh clone in its place.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let backends = self.backends.clone();
+        let client = self.client.clone();
+
+        Box::pin(async move {
+            let path_and_query = req
+                .uri()
+                .path_and_query()
+                .map_or("/", |path_and_query| path_and_query.as_str())
+                .to_string();
+            let header = req
+                .headers()
+                .get(NETWORK_ROUTE_HEADER)
+                .and_then(|value| value.to_str().ok());
+            let (network, path) = match route(backends.keys(), &path_and_query, header) {
+                Route::Default => return inner.call(req).await.map_err(Into::into),
+                Route::Network { network, path } => (network, path),
+                Route::Unknown(network) => {
+                    return Ok(Response::builder()
+                        .status(StatusCode::NOT_FOUND)
+                        .body(Body::from(format!("Unknown network {network}")))?)
+                }
+            };
+            match backends.get(&network).copied().flatten() {
+                Some(addr) => proxy(&client, addr, &path, req).await,
+                None => {
+                    *req.uri_mut() = path.parse::<Uri>()?;
+                    inner.call(req).await.map_err(Into::into)
+                }
+            }
+        })
+    }
+}
+
+/// Forward a request to the server of another network. WebSocket handshakes are forwarded too,
+/// and the upgraded connections piped together once both sides switched protocols.
+async fn proxy(
+    client: &Client<HttpConnector>,
+    addr: SocketAddr,
+    path: &str,
+    mut req: Request<Body>,
+) -> Result<Response<Body>, Box<dyn Error + Send + Sync + 'static>> {
+    *req.uri_mut() = format!("http://{addr}{path}").parse::<Uri>()?;
+    req.headers_mut().remove(header::HOST);
+    if !req.headers().contains_key(header::UPGRADE) {
+        return Ok(client.request(req).await?);
+    }
+
+    let upgrade = hyper::upgrade::on(&mut req);
+    let mut response = client.request(req).await?;
+    if response.status() == StatusCode::SWITCHING_PROTOCOLS {
+        let backend_upgrade = hyper::upgrade::on(&mut response);
+        tokio::spawn(async move {
+            match tokio::try_join!(upgrade, backend_upgrade) {
+                Ok((mut upgraded, mut backend)) => {
+                    let _ = tokio::io::copy_bidirectional(&mut upgraded, &mut backend).await;
+                }
+                Err(err) => warn!("Failed to proxy a WebSocket connection to {addr}: {err}"),
+            }
+        });
+    }
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_requests_are_routed_by_prefix_then_header() {
+        let networks = vec!["mainnet".to_string(), "testnet".to_string()];
+
+        assert_eq!(
+            route(&networks, "/testnet", None),
+            Route::Network {
+                network: "testnet".to_string(),
+                path: "/".to_string()
+            }
+        );
+        assert_eq!(
+            route(&networks, "/testnet/metrics?x=1", Some("mainnet")),
+            Route::Network {
+                network: "testnet".to_string(),
+                path: "/metrics?x=1".to_string()
+            }
+        );
+        assert_eq!(
+            route(&networks, "/readyz", Some("testnet")),
+            Route::Network {
+                network: "testnet".to_string(),
+                path: "/readyz".to_string()
+            }
+        );
+        assert_eq!(route(&networks, "/testnets", None), Route::Default);
+        assert_eq!(
+            route(&networks, "/", Some("goerli")),
+            Route::Unknown("goerli".to_string())
+        );
+    }
+}
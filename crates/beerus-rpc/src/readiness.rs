@@ -0,0 +1,156 @@
+//! Readiness of the RPC server while the light client starts in the background.
+//! The server binds before the light client is synced: `GET /readyz` reports the sync status,
+//! and the calls which need the light client are answered with a `NOT_SYNCED_CODE` error until
+//! it is synced, instead of hanging.
+use crate::error_response;
+use beerus_core::lightclient::beerus::{SyncStatus, NOT_SYNCED_CODE};
+use hyper::{header, Body, Method, Request, Response, StatusCode};
+use serde_json::{json, Value};
+use std::{
+    error::Error,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::sync::watch;
+use tower::{Layer, Service};
+
+/// Path of the readiness probe.
+pub const READYZ_PATH: &str = "/readyz";
+/// Methods answered before the light client is synced, along with the `admin_` namespace.
+const METHODS_SERVED_WHILE_SYNCING: [&str; 4] = [
+    "hello_world",
+    "beerus_status",
+    "beerus_handshake",
+    "starknet_specVersion",
+];
+
+/// Whether a JSON-RPC request, or one of the calls of a batch, needs the light client to be
+/// synced.
+pub fn requires_sync(request: &Value) -> bool {
+    let call_requires_sync = |call: &Value| match call.get("method").and_then(Value::as_str) {
+        Some(method) => {
+            !method.starts_with("admin_") && !METHODS_SERVED_WHILE_SYNCING.contains(&method)
+        }
+        None => false,
+    };
+    match request {
+        Value::Array(calls) => calls.iter().any(call_requires_sync),
+        call => call_requires_sync(call),
+    }
+}
+
+/// Build the answer of the readiness probe: `503` until the light clients are started, `200`
+/// once they are, while the syncer catches up or the StarkNet provider is down included. The
+/// body carries the status, so that probes can tell these apart.
+fn readyz_response(status: &SyncStatus) -> Result<Response<Body>, hyper::http::Error> {
+    let code = match status {
+        SyncStatus::NotSynced => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::OK,
+    };
+    Response::builder()
+        .status(code)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(json!({ "status": status }).to_string()))
+}
+
+/// Layer that applies [`Readiness`] to the HTTP service of the RPC server.
+#[derive(Debug, Clone)]
+pub struct ReadinessLayer {
+    status: watch::Receiver<SyncStatus>,
+}
+
+impl ReadinessLayer {
+    pub fn new(status: watch::Receiver<SyncStatus>) -> Self {
+        Self { status }
+    }
+}
+
+impl<S> Layer<S> for ReadinessLayer {
+    type Service = Readiness<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Readiness {
+            inner,
+            status: self.status.clone(),
+        }
+    }
+}
+
+/// Serves the readiness probe and rejects the calls which need the light client until it is
+/// synced.
+#[derive(Debug, Clone)]
+pub struct Readiness<S> {
+    inner: S,
+    status: watch::Receiver<SyncStatus>,
+}
+
+impl<S> Service<Request<Body>> for Readiness<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>> + Clone + Send + 'static,
+    S::Error: Into<Box<dyn Error + Send + Sync>> + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = Box<dyn Error + Send + Sync + 'static>;
+    type Future =
+        Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send + 'static>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let status = self.status.borrow().clone();
+        // The inner service was polled ready, keep it and leave a fresh clone in its place.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+
+        Box::pin(async move {
+            if req.method() == Method::GET && req.uri().path() == READYZ_PATH {
+                return Ok(readyz_response(&status)?);
+            }
+            if status != SyncStatus::NotSynced || req.method() != Method::POST {
+                return inner.call(req).await.map_err(Into::into);
+            }
+
+            let (parts, body) = req.into_parts();
+            let bytes = hyper::body::to_bytes(body).await?;
+            let request: Value = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+            if requires_sync(&request) {
+                let message = format!("Beerus is not synced yet, status: {status:?}");
+                let body = serde_json::to_vec(&error_response(
+                    &request,
+                    NOT_SYNCED_CODE.into(),
+                    &message,
+                ))?;
+                return Ok(Response::builder()
+                    .status(StatusCode::OK)
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(body))?);
+            }
+            inner
+                .call(Request::from_parts(parts, Body::from(bytes)))
+                .await
+                .map_err(Into::into)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_and_admin_calls_are_served_while_syncing() {
+        let request = json!([
+            {"jsonrpc": "2.0", "id": 1, "method": "beerus_status"},
+            {"jsonrpc": "2.0", "id": 2, "method": "admin_resync"},
+        ]);
+
+        assert!(!requires_sync(&request));
+        assert!(requires_sync(
+            &json!({"jsonrpc": "2.0", "id": 1, "method": "starknet_blockNumber"})
+        ));
+    }
+}
@@ -0,0 +1,308 @@
+//! Structured logs of the JSON-RPC requests, for debugging production issues.
+//! A sample of the requests is logged, one JSON line per call under the `beerus_rpc::requests`
+//! target, with the method, a hash of the params, the duration, the outcome and the number of
+//! calls made to the StarkNet upstreams. Params are only logged in clear when enabled, and never
+//! for the redacted methods.
+use beerus_core::lightclient::starknet::count_upstream_calls;
+use ethers::utils::keccak256;
+use hyper::{Body, Method, Request, Response};
+use serde_json::{json, Value};
+use std::{
+    error::Error,
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::Instant,
+};
+use tower::{Layer, Service};
+
+/// Environment variable setting the share of the requests logged, from `0` (default, disabled)
+/// to `1`.
+pub const LOG_SAMPLE_RATE_ENV: &str = "RPC_LOG_SAMPLE_RATE";
+/// Environment variable enabling the logging of the params in clear.
+pub const LOG_PARAMS_ENV: &str = "RPC_LOG_PARAMS";
+/// Environment variable listing the methods whose params are never logged, separated by
+/// commas. A trailing `*` matches a prefix.
+pub const LOG_REDACTED_METHODS_ENV: &str = "RPC_LOG_REDACTED_METHODS";
+/// Methods whose params are never logged by default: signed transactions, and admin params
+/// such as provider URLs which may embed API keys.
+pub const DEFAULT_REDACTED_METHODS: [&str; 6] = [
+    "eth_sendRawTransaction",
+    "starknet_addInvokeTransaction",
+    "starknet_addDeclareTransaction",
+    "starknet_addDeployTransaction",
+    "starknet_addDeployAccountTransaction",
+    "admin_*",
+];
+/// Target of the request logs, so that they can be filtered with `RUST_LOG`.
+pub const LOG_TARGET: &str = "beerus_rpc::requests";
+
+/// Settings of the request logs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestLogConfig {
+    pub sample_rate: f64,
+    pub log_params: bool,
+    pub redacted_methods: Vec<String>,
+}
+
+impl RequestLogConfig {
+    /// Read the settings from the environment, `None` if no request is sampled.
+    pub fn from_env() -> Option<Self> {
+        let sample_rate = match std::env::var(LOG_SAMPLE_RATE_ENV) {
+            Ok(value) => match value.parse::<f64>() {
+                Ok(rate) if (0.0..=1.0).contains(&rate) => rate,
+                _ => {
+                    log::warn!("Invalid {LOG_SAMPLE_RATE_ENV}: {value}, requests are not logged");
+                    0.0
+                }
+            },
+            Err(_) => 0.0,
+        };
+        if sample_rate == 0.0 {
+            return None;
+        }
+        let redacted_methods = match std::env::var(LOG_REDACTED_METHODS_ENV) {
+            Ok(methods) => methods
+                .split(',')
+                .map(str::trim)
+                .filter(|method| !method.is_empty())
+                .map(String::from)
+                .collect(),
+            Err(_) => DEFAULT_REDACTED_METHODS.map(String::from).to_vec(),
+        };
+        Some(Self {
+            sample_rate,
+            log_params: std::env::var(LOG_PARAMS_ENV).map_or(false, |value| value == "true"),
+            redacted_methods,
+        })
+    }
+
+    /// Whether the params of a method must not be logged.
+    pub fn is_redacted(&self, method: &str) -> bool {
+        self.redacted_methods
+            .iter()
+            .any(|redacted| match redacted.strip_suffix('*') {
+                Some(prefix) => method.starts_with(prefix),
+                None => method == redacted,
+            })
+    }
+}
+
+/// Whether the `index`th request is logged: the sampled requests are spread evenly.
+pub fn is_sampled(index: u64, sample_rate: f64) -> bool {
+    ((index + 1) as f64 * sample_rate).floor() > (index as f64 * sample_rate).floor()
+}
+
+/// Short hash of the params of a call, to tell identical calls apart without logging them.
+pub fn params_hash(params: &Value) -> String {
+    let hash = keccak256(params.to_string());
+    let prefix: String = hash[..8].iter().map(|byte| format!("{byte:02x}")).collect();
+    format!("0x{prefix}")
+}
+
+/// Build the log entries of a request, one per call of a batch.
+/// # Arguments
+/// * `request` - The JSON-RPC request.
+/// * `response` - The JSON-RPC response, matched to the calls by id.
+/// * `duration_ms` - The time taken to answer the whole request.
+/// * `upstream_calls` - The calls made to the StarkNet upstreams to answer the whole request.
+pub fn log_entries(
+    config: &RequestLogConfig,
+    request: &Value,
+    response: &Value,
+    duration_ms: f64,
+    upstream_calls: u64,
+) -> Vec<Value> {
+    let calls = match request {
+        Value::Array(calls) => calls.iter().collect(),
+        call => vec![call],
+    };
+    let responses = match response {
+        Value::Array(responses) => responses.iter().collect(),
+        response => vec![response],
+    };
+    let batch_size = calls.len();
+    calls
+        .into_iter()
+        .map(|call| {
+            let method = call.get("method").and_then(Value::as_str).unwrap_or("");
+            let id = call.get("id").cloned().unwrap_or(Value::Null);
+            let params = call.get("params").cloned().unwrap_or(Value::Null);
+            let response = responses
+                .iter()
+                .find(|response| response.get("id") == Some(&id));
+            let error_code = response
+                .and_then(|response| response.get("error"))
+                .map(|error| error.get("code").cloned().unwrap_or(Value::Null));
+            let outcome = match (response, &error_code) {
+                (None, _) => "no_response",
+                (Some(_), Some(_)) => "error",
+                (Some(_), None) => "ok",
+            };
+            let mut entry = json!({
+                "method": method,
+                "id": id,
+                "params_hash": params_hash(&params),
+                "duration_ms": duration_ms,
+                "outcome": outcome,
+                "upstream_calls": upstream_calls,
+                "batch_size": batch_size,
+            });
+            if let Some(error_code) = error_code {
+                entry["error_code"] = error_code;
+            }
+            if config.log_params && !config.is_redacted(method) {
+                entry["params"] = params;
+            }
+            entry
+        })
+        .collect()
+}
+
+/// Layer that applies [`RequestLog`] to the HTTP service of the RPC server.
+#[derive(Debug, Clone)]
+pub struct RequestLogLayer {
+    config: Arc<RequestLogConfig>,
+    requests: Arc<AtomicU64>,
+}
+
+impl RequestLogLayer {
+    pub fn new(config: RequestLogConfig) -> Self {
+        Self {
+            config: Arc::new(config),
+            requests: Arc::new(AtomicU64::new(0)),
+        }
+    }
+}
+
+impl<S> Layer<S> for RequestLogLayer {
+    type Service = RequestLog<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequestLog {
+            inner,
+            config: self.config.clone(),
+            requests: self.requests.clone(),
+        }
+    }
+}
+
+/// Logs a sample of the JSON-RPC requests served over HTTP.
+#[derive(Debug, Clone)]
+pub struct RequestLog<S> {
+    inner: S,
+    config: Arc<RequestLogConfig>,
+    requests: Arc<AtomicU64>,
+}
+
+impl<S> Service<Request<Body>> for RequestLog<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>> + Clone + Send + 'static,
+    S::Error: Into<Box<dyn Error + Send + Sync>> + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = Box<dyn Error + Send + Sync + 'static>;
+    type Future =
+        Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send + 'static>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let config = self.config.clone();
+        let sampled = req.method() == Method::POST
+            && is_sampled(
+                self.requests.fetch_add(1, Ordering::Relaxed),
+                config.sample_rate,
+            );
+        // The inner service was polled ready, keep it and leave a fresh clone in its place.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+
+        Box::pin(async move {
+            if !sampled {
+                return inner.call(req).await.map_err(Into::into);
+            }
+            let (parts, body) = req.into_parts();
+            let bytes = hyper::body::to_bytes(body).await?;
+            let request: Value = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+
+            let started = Instant::now();
+            let (response, upstream_calls) =
+                count_upstream_calls(inner.call(Request::from_parts(parts, Body::from(bytes))))
+                    .await;
+            let duration_ms = started.elapsed().as_secs_f64() * 1000.0;
+            let (parts, body) = response.map_err(Into::into)?.into_parts();
+            let bytes = hyper::body::to_bytes(body).await?;
+
+            let response: Value = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+            for entry in log_entries(&config, &request, &response, duration_ms, upstream_calls) {
+                log::info!(target: LOG_TARGET, "{entry}");
+            }
+            Ok(Response::from_parts(parts, Body::from(bytes)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log_config(log_params: bool) -> RequestLogConfig {
+        RequestLogConfig {
+            sample_rate: 1.0,
+            log_params,
+            redacted_methods: DEFAULT_REDACTED_METHODS.map(String::from).to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_requests_are_sampled_evenly() {
+        let sampled = (0..100).filter(|index| is_sampled(*index, 0.1)).count();
+        assert_eq!(sampled, 10);
+        assert!((0..10).all(|index| is_sampled(index, 1.0)));
+    }
+
+    #[test]
+    fn test_log_entries_report_the_outcome_of_every_call() {
+        let request = json!([
+            {"jsonrpc": "2.0", "id": 1, "method": "starknet_blockNumber", "params": []},
+            {"jsonrpc": "2.0", "id": 2, "method": "eth_sendRawTransaction", "params": ["0x02f8"]},
+        ]);
+        let response = json!([
+            {"jsonrpc": "2.0", "id": 1, "result": 10},
+            {"jsonrpc": "2.0", "id": 2, "error": {"code": -32602, "message": "Invalid params"}},
+        ]);
+
+        let entries = log_entries(&log_config(true), &request, &response, 5.0, 3);
+
+        assert_eq!(entries[0]["outcome"], "ok");
+        assert_eq!(entries[0]["params"], json!([]));
+        assert_eq!(entries[0]["upstream_calls"], 3);
+        assert_eq!(entries[1]["outcome"], "error");
+        assert_eq!(entries[1]["error_code"], -32602);
+        // Signed transactions are never logged, only their hash.
+        assert_eq!(entries[1].get("params"), None);
+        assert_eq!(entries[1]["params_hash"], params_hash(&json!(["0x02f8"])));
+    }
+
+    #[test]
+    fn test_params_are_not_logged_unless_enabled() {
+        let request = json!({"jsonrpc": "2.0", "id": 1, "method": "admin_rotateProvider", "params": ["https://key@node"]});
+        let config = log_config(true);
+        assert!(config.is_redacted("admin_rotateProvider"));
+        let entries = log_entries(&config, &request, &Value::Null, 1.0, 0);
+        assert_eq!(entries[0].get("params"), None);
+        assert_eq!(entries[0]["outcome"], "no_response");
+
+        let request = json!({"jsonrpc": "2.0", "id": 1, "method": "starknet_call", "params": []});
+        let entries = log_entries(&log_config(false), &request, &Value::Null, 1.0, 0);
+        assert_eq!(entries[0].get("params"), None);
+    }
+}
@@ -0,0 +1,158 @@
+//! Headers identifying the instance which served a response, so that the gateways in front of
+//! several Beerus instances, and whoever debugs them, can tell the network and the height an
+//! answer came from without calling the instance again. Set `RPC_RESPONSE_HEADERS=true` to add
+//! them to every HTTP response.
+use beerus_core::{encoding::felt_to_short_string, lightclient::service::BeerusHandle};
+use hyper::{header::HeaderValue, Body, Request, Response};
+use std::{
+    error::Error,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tokio::sync::OnceCell;
+use tower::{Layer, Service};
+
+/// Environment variable enabling the response headers.
+pub const RESPONSE_HEADERS_ENV: &str = "RPC_RESPONSE_HEADERS";
+/// Header carrying the Ethereum network the instance follows, e.g. `mainnet`.
+pub const NETWORK_HEADER: &str = "x-beerus-network";
+/// Header carrying the StarkNet chain id, e.g. `SN_MAIN`.
+pub const CHAIN_ID_HEADER: &str = "x-beerus-chain-id";
+/// Header carrying the number of the last block proven on L1, absent before the first one.
+pub const PROVEN_BLOCK_HEADER: &str = "x-beerus-proven-block";
+/// Header carrying the version of Beerus.
+pub const VERSION_HEADER: &str = "x-beerus-version";
+
+/// Whether the response headers are enabled, `false` by default.
+pub fn response_headers_from_env() -> bool {
+    std::env::var(RESPONSE_HEADERS_ENV).map_or(false, |value| value.parse().unwrap_or(false))
+}
+
+/// Headers of a response, the chain id once it is known.
+pub fn response_headers(
+    network: &str,
+    chain_id: Option<&str>,
+    proven_block: Option<u64>,
+) -> Vec<(&'static str, String)> {
+    let mut headers = vec![
+        (NETWORK_HEADER, network.to_lowercase()),
+        (VERSION_HEADER, env!("CARGO_PKG_VERSION").to_string()),
+    ];
+    if let Some(chain_id) = chain_id {
+        headers.push((CHAIN_ID_HEADER, chain_id.to_string()));
+    }
+    if let Some(proven_block) = proven_block {
+        headers.push((PROVEN_BLOCK_HEADER, proven_block.to_string()));
+    }
+    headers
+}
+
+/// Layer that applies [`ResponseHeaders`] to the HTTP service of the RPC server.
+#[derive(Clone)]
+pub struct ResponseHeadersLayer {
+    beerus: BeerusHandle,
+    chain_id: Arc<OnceCell<String>>,
+}
+
+impl ResponseHeadersLayer {
+    pub fn new(beerus: BeerusHandle) -> Self {
+        Self {
+            beerus,
+            chain_id: Arc::new(OnceCell::new()),
+        }
+    }
+}
+
+impl<S> Layer<S> for ResponseHeadersLayer {
+    type Service = ResponseHeaders<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ResponseHeaders {
+            inner,
+            beerus: self.beerus.clone(),
+            chain_id: self.chain_id.clone(),
+        }
+    }
+}
+
+/// Adds the network, chain id, last proven block and version to the responses. The chain id is
+/// fetched from the provider once, it is left out until that succeeds.
+#[derive(Clone)]
+pub struct ResponseHeaders<S> {
+    inner: S,
+    beerus: BeerusHandle,
+    chain_id: Arc<OnceCell<String>>,
+}
+
+impl<S> Service<Request<Body>> for ResponseHeaders<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>> + Clone + Send + 'static,
+    S::Error: Into<Box<dyn Error + Send + Sync>> + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = Box<dyn Error + Send + Sync + 'static>;
+    type Future =
+        Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send + 'static>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let beerus = self.beerus.clone();
+        let chain_id = self.chain_id.clone();
+        // The inner service was polled ready, keep it and leave a fresh clone in its place.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+
+        Box::pin(async move {
+            let mut response = inner.call(req).await.map_err(Into::into)?;
+            let chain_id = chain_id
+                .get_or_try_init(|| async {
+                    let chain_id = beerus.starknet_lightclient.chain_id().await?;
+                    Ok::<_, eyre::Report>(
+                        felt_to_short_string(chain_id).unwrap_or_else(|_| format!("{chain_id:#x}")),
+                    )
+                })
+                .await
+                .ok();
+            let proven_block = beerus.node.load().last_proven_block_number();
+            for (name, value) in response_headers(
+                &beerus.config.ethereum_network,
+                chain_id.map(String::as_str),
+                proven_block,
+            ) {
+                response
+                    .headers_mut()
+                    .insert(name, HeaderValue::from_str(&value)?);
+            }
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unknown_values_are_left_out() {
+        let headers = response_headers("Mainnet", Some("SN_MAIN"), Some(10));
+        assert_eq!(
+            headers,
+            vec![
+                (NETWORK_HEADER, "mainnet".to_string()),
+                (VERSION_HEADER, env!("CARGO_PKG_VERSION").to_string()),
+                (CHAIN_ID_HEADER, "SN_MAIN".to_string()),
+                (PROVEN_BLOCK_HEADER, "10".to_string()),
+            ]
+        );
+
+        let headers = response_headers("goerli", None, None);
+        assert_eq!(headers.len(), 2);
+        assert!(headers.iter().all(|(name, _)| *name != CHAIN_ID_HEADER));
+    }
+}
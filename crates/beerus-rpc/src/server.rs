@@ -1,25 +1,87 @@
-use std::str::FromStr;
-
-use beerus_core::lightclient::beerus::BeerusLightClient;
+use std::{future::Future, str::FromStr};
+
+use beerus_core::{
+    ethers_helper::{block_string_to_block_tag_type, u256_to_h256},
+    lightclient::{
+        beerus::{
+            BeerusStatus, BlockTarget, BlockWithReceipts, ContractProof, Passthrough, Unverified,
+            WithMetadata,
+        },
+        class_index::ClassDeclaration,
+        contract_index::ContractDeployment,
+        messaging::L1ToL2MessageStatus,
+        service::BeerusHandle,
+        starknet::{
+            errors::{is_upstream_unavailable, StarknetError, UPSTREAM_UNAVAILABLE_CODE},
+            fee::{AnyBroadcastedTransaction, FeeEstimateWithUnit, SimulationFlag},
+            inclusion_proof::TransactionProof,
+        },
+    },
+};
 /// The RPC module for the Ethereum protocol required by Kakarot.
 use jsonrpsee::{
     core::{async_trait, RpcResult as Result},
     proc_macros::rpc,
-    types::error::CallError,
+    types::error::{CallError, ErrorObject},
 };
 
-use beerus_core::starknet_helper::block_id_string_to_block_id_type;
-use ethers::types::U256;
+use ethers::types::{Address, Bytes, Log, H256, U256};
+use helios::types::BlockTag;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 use starknet::{
     core::types::FieldElement,
     providers::jsonrpc::models::{
-        BlockHashAndNumber, ContractClass, MaybePendingBlockWithTxHashes, MaybePendingBlockWithTxs,
-        MaybePendingTransactionReceipt, StateUpdate, SyncStatusType, Transaction,
+        BlockHashAndNumber, BlockId, BlockWithTxs, ContractClass, EventFilter, EventsPage,
+        MaybePendingBlockWithTxHashes, MaybePendingBlockWithTxs, MaybePendingTransactionReceipt,
+        StateUpdate, SyncStatusType, Transaction,
     },
 };
 
+use crate::compat::SpecVersion;
+
 pub struct BeerusRpc {
-    _beerus: BeerusLightClient,
+    _beerus: BeerusHandle,
+    spec_version: SpecVersion,
+    /// Address of the key signing the responses, if any.
+    signer: Option<Address>,
+}
+
+/// Identity of a Beerus instance, see `beerus_handshake`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Handshake {
+    pub version: String,
+    pub spec_version: String,
+    pub ethereum_network: String,
+    pub starknet_core_contract_address: Address,
+    /// Address of the key signing the responses, `None` if they are not signed.
+    pub signer: Option<Address>,
+    /// Last block whose state root was proven on L1, with that root.
+    pub proven_block_number: Option<u64>,
+    pub proven_state_root: Option<FieldElement>,
+    /// Challenge of the client, echoed so that the signature of the response proves that it
+    /// was built for this handshake.
+    pub challenge: Option<String>,
+}
+
+/// Filter of `eth_getLogs`, as defined by the Ethereum JSON-RPC API.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogFilter {
+    pub from_block: Option<String>,
+    pub to_block: Option<String>,
+    pub address: Option<String>,
+    pub topics: Option<Vec<String>>,
+    pub block_hash: Option<String>,
+}
+
+/// Filter of `starknet_getEvents`, along with the page requested.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventFilterWithPage {
+    #[serde(flatten)]
+    pub filter: EventFilter,
+    pub chunk_size: u64,
+    pub continuation_token: Option<String>,
 }
 
 #[rpc(server, client)]
@@ -33,6 +95,9 @@ trait BeerusApi {
     #[method(name = "starknet_chainId")]
     async fn starknet_chain_id(&self) -> Result<String>;
 
+    #[method(name = "starknet_specVersion")]
+    async fn starknet_spec_version(&self) -> Result<String>;
+
     #[method(name = "starknet_blockNumber")]
     async fn starknet_block_number(&self) -> Result<u64>;
 
@@ -74,7 +139,17 @@ trait BeerusApi {
         &self,
         block_id_type: &str,
         block_id: &str,
-    ) -> Result<MaybePendingBlockWithTxs>;
+    ) -> Result<Passthrough<MaybePendingBlockWithTxs>>;
+
+    #[method(name = "starknet_getBlockWithReceipts")]
+    async fn starknet_get_block_with_receipts(
+        &self,
+        block_id_type: &str,
+        block_id: &str,
+    ) -> Result<BlockWithReceipts>;
+
+    #[method(name = "starknet_getEvents")]
+    async fn starknet_get_events(&self, filter: EventFilterWithPage) -> Result<EventsPage>;
 
     #[method(name = "starknet_getStateUpdate")]
     async fn starknet_get_state_update(
@@ -108,6 +183,160 @@ trait BeerusApi {
         block_id: String,
         contract_address: String,
     ) -> Result<FieldElement>;
+
+    #[method(name = "starknet_getNonce")]
+    async fn starknet_get_nonce(
+        &self,
+        block_id_type: String,
+        block_id: String,
+        contract_address: String,
+    ) -> Result<FieldElement>;
+
+    #[method(name = "eth_getBalance")]
+    async fn eth_get_balance(&self, address: String, block: Option<String>) -> Result<U256>;
+
+    #[method(name = "eth_getCode")]
+    async fn eth_get_code(&self, address: String, block: Option<String>) -> Result<Bytes>;
+
+    #[method(name = "eth_getStorageAt")]
+    async fn eth_get_storage_at(
+        &self,
+        address: String,
+        slot: String,
+        block: Option<String>,
+    ) -> Result<H256>;
+
+    #[method(name = "eth_getLogs")]
+    async fn eth_get_logs(&self, filter: LogFilter) -> Result<Vec<Log>>;
+
+    #[method(name = "eth_sendRawTransaction")]
+    async fn eth_send_raw_transaction(&self, bytes: Bytes) -> Result<H256>;
+
+    #[method(name = "eth_gasPrice")]
+    async fn eth_gas_price(&self) -> Result<U256>;
+
+    #[method(name = "eth_maxPriorityFeePerGas")]
+    async fn eth_max_priority_fee_per_gas(&self) -> Result<U256>;
+
+    #[method(name = "beerus_status")]
+    async fn beerus_status(&self) -> Result<BeerusStatus>;
+
+    #[method(name = "beerus_handshake")]
+    async fn beerus_handshake(&self, challenge: Option<String>) -> Result<Handshake>;
+
+    #[method(name = "beerus_l1ProvenBlockNumber")]
+    async fn beerus_l1_proven_block_number(&self) -> Result<u64>;
+
+    #[method(name = "beerus_l1StateRoot")]
+    async fn beerus_l1_state_root(&self) -> Result<FieldElement>;
+
+    #[method(name = "beerus_getStorageAt")]
+    async fn beerus_get_storage_at(
+        &self,
+        contract_address: String,
+        key: String,
+        block_tag: Option<String>,
+    ) -> Result<WithMetadata<FieldElement>>;
+
+    #[method(name = "beerus_getStorageBatch")]
+    async fn beerus_get_storage_batch(
+        &self,
+        contract_address: String,
+        keys: Vec<String>,
+        block_tag: Option<String>,
+    ) -> Result<WithMetadata<Vec<FieldElement>>>;
+
+    #[method(name = "beerus_getProof")]
+    async fn beerus_get_proof(
+        &self,
+        contract_address: String,
+        keys: Vec<String>,
+        block_tag: Option<String>,
+    ) -> Result<WithMetadata<ContractProof>>;
+
+    #[method(name = "beerus_getNonce")]
+    async fn beerus_get_nonce(
+        &self,
+        contract_address: String,
+        block_tag: Option<String>,
+    ) -> Result<WithMetadata<FieldElement>>;
+
+    #[method(name = "beerus_call")]
+    async fn beerus_call(
+        &self,
+        contract_address: String,
+        entry_point_selector: String,
+        calldata: Vec<String>,
+        block_tag: Option<String>,
+    ) -> Result<WithMetadata<Vec<FieldElement>>>;
+
+    #[method(name = "beerus_getBlockRange")]
+    async fn beerus_get_block_range(
+        &self,
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<Vec<BlockWithTxs>>;
+
+    #[method(name = "beerus_getClassDeclaration")]
+    async fn beerus_get_class_declaration(
+        &self,
+        class_hash: String,
+    ) -> Result<Option<ClassDeclaration>>;
+
+    #[method(name = "beerus_getContractDeployment")]
+    async fn beerus_get_contract_deployment(
+        &self,
+        contract_address: String,
+    ) -> Result<Option<ContractDeployment>>;
+
+    #[method(name = "beerus_getContractsByClass")]
+    async fn beerus_get_contracts_by_class(
+        &self,
+        class_hash: String,
+    ) -> Result<Vec<ContractDeployment>>;
+
+    #[method(name = "beerus_getTransactionProof")]
+    async fn beerus_get_transaction_proof(
+        &self,
+        transaction_hash: String,
+    ) -> Result<TransactionProof>;
+
+    #[method(name = "beerus_getL1ToL2MessageStatus")]
+    async fn beerus_get_l1_to_l2_message_status(
+        &self,
+        l1_transaction_hash: String,
+    ) -> Result<Vec<L1ToL2MessageStatus>>;
+
+    #[method(name = "starknet_estimateFee")]
+    async fn starknet_estimate_fee(
+        &self,
+        block_id_type: String,
+        block_id: String,
+        request: Vec<AnyBroadcastedTransaction>,
+        simulation_flags: Option<Vec<SimulationFlag>>,
+    ) -> Result<Vec<FeeEstimateWithUnit>>;
+
+    #[method(name = "starknet_traceTransaction")]
+    async fn starknet_trace_transaction(
+        &self,
+        transaction_hash: String,
+    ) -> Result<Unverified<Passthrough<Value>>>;
+
+    #[method(name = "starknet_traceBlockTransactions")]
+    async fn starknet_trace_block_transactions(
+        &self,
+        block_id_type: String,
+        block_id: String,
+    ) -> Result<Unverified<Passthrough<Value>>>;
+
+    #[method(name = "starknet_simulateTransactions")]
+    async fn starknet_simulate_transactions(
+        &self,
+        block_id_type: String,
+        block_id: String,
+        transactions: Vec<AnyBroadcastedTransaction>,
+        simulation_flags: Vec<SimulationFlag>,
+    ) -> Result<Unverified<Passthrough<Value>>>;
 }
 
 #[async_trait]
@@ -117,11 +346,10 @@ impl BeerusApiServer for BeerusRpc {
     }
 
     async fn starknet_l2_to_l1_messages(&self, msg_hash: U256) -> Result<U256> {
-        Ok(self
-            ._beerus
+        self._beerus
             .starknet_l2_to_l1_messages(msg_hash)
             .await
-            .unwrap())
+            .map_err(rpc_error)
     }
 
     async fn starknet_chain_id(&self) -> Result<String> {
@@ -130,19 +358,22 @@ impl BeerusApiServer for BeerusRpc {
             .starknet_lightclient
             .chain_id()
             .await
-            .unwrap()
+            .map_err(rpc_error)?
             .to_string();
 
         Ok(chain_id)
     }
 
+    async fn starknet_spec_version(&self) -> Result<String> {
+        Ok(self.spec_version.to_string())
+    }
+
     async fn starknet_block_number(&self) -> Result<u64> {
         let block_number = self
             ._beerus
-            .starknet_lightclient
-            .block_number()
+            .starknet_block_number()
             .await
-            .unwrap();
+            .map_err(rpc_error)?;
 
         Ok(block_number)
     }
@@ -152,24 +383,22 @@ impl BeerusApiServer for BeerusRpc {
         block_id_type: String,
         block_id: String,
     ) -> Result<u64> {
-        let block_id = block_id_string_to_block_id_type(&block_id_type, &block_id).unwrap();
+        let block_id = self.parse_block_id(&block_id_type, &block_id).await?;
         let block_transaction_count = self
             ._beerus
             .starknet_lightclient
             .get_block_transaction_count(&block_id)
             .await
-            .unwrap();
+            .map_err(rpc_error)?;
 
         Ok(block_transaction_count)
     }
 
     async fn starknet_block_hash_and_number(&self) -> Result<BlockHashAndNumber> {
-        Ok(self
-            ._beerus
-            .starknet_lightclient
-            .block_hash_and_number()
+        self._beerus
+            .starknet_block_hash_and_number()
             .await
-            .unwrap())
+            .map_err(rpc_error)
     }
 
     async fn starknet_get_class_at(
@@ -178,14 +407,13 @@ impl BeerusApiServer for BeerusRpc {
         block_id: String,
         contract_address: String,
     ) -> Result<ContractClass> {
-        let block_id = block_id_string_to_block_id_type(&block_id_type, &block_id).unwrap();
-        let contract_address = FieldElement::from_str(&contract_address).unwrap();
-        Ok(self
-            ._beerus
+        let block_id = self.parse_block_id(&block_id_type, &block_id).await?;
+        let contract_address = parse_felt(&contract_address)?;
+        self._beerus
             .starknet_lightclient
             .get_class_at(&block_id, contract_address)
             .await
-            .unwrap())
+            .map_err(rpc_error)
     }
 
     async fn stark_get_block_with_tx_hashes(
@@ -193,13 +421,12 @@ impl BeerusApiServer for BeerusRpc {
         block_id_type: String,
         block_id: String,
     ) -> Result<MaybePendingBlockWithTxHashes> {
-        let block_id = block_id_string_to_block_id_type(&block_id_type, &block_id).unwrap();
-        Ok(self
-            ._beerus
+        let block_id = self.parse_block_id(&block_id_type, &block_id).await?;
+        self._beerus
             .starknet_lightclient
             .get_block_with_tx_hashes(&block_id)
             .await
-            .unwrap())
+            .map_err(rpc_error)
     }
 
     async fn starknet_get_transaction_by_block_id_and_index(
@@ -208,13 +435,7 @@ impl BeerusApiServer for BeerusRpc {
         block_id: &str,
         index: &str,
     ) -> Result<Transaction> {
-        let block_id =
-            beerus_core::starknet_helper::block_id_string_to_block_id_type(block_id_type, block_id)
-                .map_err(|e| {
-                    jsonrpsee::core::Error::Call(CallError::InvalidParams(anyhow::anyhow!(
-                        e.to_string()
-                    )))
-                })?;
+        let block_id = self.parse_block_id(block_id_type, block_id).await?;
         let index = u64::from_str(index).map_err(|e| {
             jsonrpsee::core::Error::Call(CallError::InvalidParams(anyhow::anyhow!(e.to_string())))
         })?;
@@ -223,32 +444,43 @@ impl BeerusApiServer for BeerusRpc {
             .starknet_lightclient
             .get_transaction_by_block_id_and_index(&block_id, index)
             .await
-            .map_err(|e| {
-                jsonrpsee::core::Error::Call(CallError::Failed(anyhow::anyhow!(e.to_string())))
-            })?;
+            .map_err(rpc_error)?;
         Ok(result)
     }
     async fn starknet_get_block_with_txs(
         &self,
         block_id_type: &str,
         block_id: &str,
-    ) -> Result<MaybePendingBlockWithTxs> {
-        let block_id =
-            beerus_core::starknet_helper::block_id_string_to_block_id_type(block_id_type, block_id)
-                .map_err(|e| {
-                    jsonrpsee::core::Error::Call(CallError::InvalidParams(anyhow::anyhow!(
-                        e.to_string()
-                    )))
-                })?;
-        let result = self
-            ._beerus
-            .starknet_lightclient
-            .get_block_with_txs(&block_id)
+    ) -> Result<Passthrough<MaybePendingBlockWithTxs>> {
+        let block_id = self.parse_block_id(block_id_type, block_id).await?;
+        let result = match self._beerus.config.starknet_rpc_passthrough {
+            true => self._beerus.get_block_with_txs_passthrough(&block_id).await,
+            false => self
+                ._beerus
+                .get_block_with_txs(&block_id)
+                .await
+                .map(Passthrough::Parsed),
+        };
+        result.map_err(rpc_error)
+    }
+
+    async fn starknet_get_block_with_receipts(
+        &self,
+        block_id_type: &str,
+        block_id: &str,
+    ) -> Result<BlockWithReceipts> {
+        let block_id = self.parse_block_id(block_id_type, block_id).await?;
+        self._beerus
+            .starknet_get_block_with_receipts(&block_id)
             .await
-            .map_err(|e| {
-                jsonrpsee::core::Error::Call(CallError::Failed(anyhow::anyhow!(e.to_string())))
-            })?;
-        Ok(result)
+            .map_err(rpc_error)
+    }
+
+    async fn starknet_get_events(&self, filter: EventFilterWithPage) -> Result<EventsPage> {
+        self._beerus
+            .starknet_get_events(filter.filter, filter.continuation_token, filter.chunk_size)
+            .await
+            .map_err(rpc_error)
     }
 
     async fn starknet_get_state_update(
@@ -256,26 +488,29 @@ impl BeerusApiServer for BeerusRpc {
         block_id_type: String,
         block_id: String,
     ) -> Result<StateUpdate> {
-        let block_id = block_id_string_to_block_id_type(&block_id_type, &block_id).unwrap();
-        Ok(self
-            ._beerus
+        let block_id = self.parse_block_id(&block_id_type, &block_id).await?;
+        self._beerus
             .starknet_lightclient
             .get_state_update(&block_id)
             .await
-            .unwrap())
+            .map_err(rpc_error)
     }
 
     async fn starknet_syncing(&self) -> Result<SyncStatusType> {
-        let sync_status_type = self._beerus.starknet_lightclient.syncing().await.unwrap();
+        let sync_status_type = self
+            ._beerus
+            .starknet_lightclient
+            .syncing()
+            .await
+            .map_err(rpc_error)?;
         Ok(sync_status_type)
     }
 
     async fn starknet_l1_to_l2_messages(&self, msg_hash: U256) -> Result<U256> {
-        Ok(self
-            ._beerus
+        self._beerus
             .starknet_l1_to_l2_messages(msg_hash)
             .await
-            .unwrap())
+            .map_err(rpc_error)
     }
 
     async fn starknet_l1_to_l2_message_nonce(&self) -> Result<U256> {
@@ -283,29 +518,26 @@ impl BeerusApiServer for BeerusRpc {
             ._beerus
             .starknet_l1_to_l2_message_nonce()
             .await
-            .unwrap();
+            .map_err(rpc_error)?;
         Ok(nonce)
     }
 
     async fn starknet_l1_to_l2_message_cancellations(&self, msg_hash: U256) -> Result<U256> {
-        Ok(self
-            ._beerus
+        self._beerus
             .starknet_l1_to_l2_message_cancellations(msg_hash)
             .await
-            .unwrap())
+            .map_err(rpc_error)
     }
 
     async fn starknet_get_transaction_receipt(
         &self,
         tx_hash: String,
     ) -> Result<MaybePendingTransactionReceipt> {
-        let tx_hash_felt = FieldElement::from_hex_be(&tx_hash).unwrap();
-        Ok(self
-            ._beerus
-            .starknet_lightclient
-            .get_transaction_receipt(tx_hash_felt)
+        let tx_hash = parse_felt(&tx_hash)?;
+        self._beerus
+            .starknet_get_transaction_receipt(tx_hash)
             .await
-            .unwrap())
+            .map_err(rpc_error)
     }
 
     async fn starknet_get_class_hash(
@@ -314,20 +546,471 @@ impl BeerusApiServer for BeerusRpc {
         block_id: String,
         contract_address: String,
     ) -> Result<FieldElement> {
-        let block_id = block_id_string_to_block_id_type(&block_id_type, &block_id).unwrap();
-        let contract_address = FieldElement::from_str(&contract_address).unwrap();
+        let block_id = self.parse_block_id(&block_id_type, &block_id).await?;
+        let contract_address = parse_felt(&contract_address)?;
 
-        Ok(self
-            ._beerus
+        self._beerus
             .starknet_lightclient
             .get_class_hash_at(&block_id, contract_address)
             .await
-            .unwrap())
+            .map_err(rpc_error)
+    }
+
+    /// Relayed from the provider, at the pending block too. `beerus_getNonce` serves the nonce
+    /// checked against L1.
+    async fn starknet_get_nonce(
+        &self,
+        block_id_type: String,
+        block_id: String,
+        contract_address: String,
+    ) -> Result<FieldElement> {
+        let block_id = self.parse_block_id(&block_id_type, &block_id).await?;
+        let contract_address = parse_felt(&contract_address)?;
+
+        self._beerus
+            .starknet_lightclient
+            .get_nonce_at(&block_id, contract_address)
+            .await
+            .map_err(rpc_error)
+    }
+
+    async fn eth_get_balance(&self, address: String, block: Option<String>) -> Result<U256> {
+        let address = parse_address(&address)?;
+        let block = parse_block_tag(block)?;
+        self._beerus
+            .ethereum_get_balance(&address, block)
+            .await
+            .map_err(rpc_error)
+    }
+
+    async fn eth_get_code(&self, address: String, block: Option<String>) -> Result<Bytes> {
+        let address = parse_address(&address)?;
+        let block = parse_block_tag(block)?;
+        self._beerus
+            .ethereum_get_code(&address, block)
+            .await
+            .map(Bytes::from)
+            .map_err(rpc_error)
+    }
+
+    async fn eth_get_storage_at(
+        &self,
+        address: String,
+        slot: String,
+        block: Option<String>,
+    ) -> Result<H256> {
+        let address = parse_address(&address)?;
+        let slot = H256::from_str(&slot).map_err(|e| {
+            jsonrpsee::core::Error::Call(CallError::InvalidParams(anyhow::anyhow!(e.to_string())))
+        })?;
+        let block = parse_block_tag(block)?;
+        self._beerus
+            .ethereum_get_storage_at(&address, slot, block)
+            .await
+            .map(u256_to_h256)
+            .map_err(rpc_error)
+    }
+
+    async fn eth_get_logs(&self, filter: LogFilter) -> Result<Vec<Log>> {
+        if filter.block_hash.is_some() && (filter.from_block.is_some() || filter.to_block.is_some())
+        {
+            return Err(jsonrpsee::core::Error::Call(CallError::InvalidParams(
+                anyhow::anyhow!("blockHash can't be used together with fromBlock or toBlock"),
+            )));
+        }
+        self._beerus
+            .ethereum_get_logs(
+                &filter.from_block,
+                &filter.to_block,
+                &filter.address,
+                &filter.topics,
+                &filter.block_hash,
+            )
+            .await
+            .map_err(rpc_error)
+    }
+
+    async fn eth_send_raw_transaction(&self, bytes: Bytes) -> Result<H256> {
+        self._beerus
+            .ethereum_send_raw_transaction(&bytes)
+            .await
+            .map_err(rpc_error)
+    }
+
+    async fn eth_gas_price(&self) -> Result<U256> {
+        self._beerus.ethereum_gas_price().await.map_err(rpc_error)
+    }
+
+    async fn eth_max_priority_fee_per_gas(&self) -> Result<U256> {
+        self._beerus
+            .ethereum_max_priority_fee_per_gas()
+            .await
+            .map_err(rpc_error)
+    }
+
+    async fn beerus_status(&self) -> Result<BeerusStatus> {
+        Ok(self._beerus.beerus_status().await)
+    }
+
+    async fn beerus_handshake(&self, challenge: Option<String>) -> Result<Handshake> {
+        let proven = self._beerus.node.load().last_proven_state_root();
+        Ok(Handshake {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            spec_version: self.spec_version.to_string(),
+            ethereum_network: self._beerus.config.ethereum_network.clone(),
+            starknet_core_contract_address: self._beerus.starknet_core_contract_address,
+            signer: self.signer,
+            proven_block_number: proven.map(|(block_number, _)| block_number),
+            proven_state_root: proven.map(|(_, state_root)| state_root),
+            challenge,
+        })
+    }
+
+    async fn beerus_l1_proven_block_number(&self) -> Result<u64> {
+        self._beerus
+            .starknet_l1_proven_block_number()
+            .await
+            .map_err(rpc_error)
+    }
+
+    async fn beerus_l1_state_root(&self) -> Result<FieldElement> {
+        self._beerus
+            .starknet_l1_state_root()
+            .await
+            .map_err(rpc_error)
+    }
+
+    async fn beerus_get_storage_at(
+        &self,
+        contract_address: String,
+        key: String,
+        block_tag: Option<String>,
+    ) -> Result<WithMetadata<FieldElement>> {
+        let contract_address = parse_felt(&contract_address)?;
+        let key = parse_felt(&key)?;
+        let target = parse_target(block_tag, self._beerus.default_target())?;
+        self._beerus
+            .starknet_get_storage_at_with_metadata(contract_address, key, target)
+            .await
+            .map_err(rpc_error)
+    }
+
+    async fn beerus_get_storage_batch(
+        &self,
+        contract_address: String,
+        keys: Vec<String>,
+        block_tag: Option<String>,
+    ) -> Result<WithMetadata<Vec<FieldElement>>> {
+        let contract_address = parse_felt(&contract_address)?;
+        let keys = keys
+            .iter()
+            .map(|key| parse_felt(key))
+            .collect::<Result<Vec<_>>>()?;
+        let target = parse_target(block_tag, self._beerus.default_target())?;
+        self._beerus
+            .starknet_get_storage_at_many(contract_address, keys, target)
+            .await
+            .map_err(rpc_error)
+    }
+
+    async fn beerus_get_proof(
+        &self,
+        contract_address: String,
+        keys: Vec<String>,
+        block_tag: Option<String>,
+    ) -> Result<WithMetadata<ContractProof>> {
+        let contract_address = parse_felt(&contract_address)?;
+        let keys = keys
+            .iter()
+            .map(|key| parse_felt(key))
+            .collect::<Result<Vec<_>>>()?;
+        let target = parse_target(block_tag, self._beerus.default_target())?;
+        self._beerus
+            .get_proof(contract_address, keys, target)
+            .await
+            .map_err(rpc_error)
+    }
+
+    async fn beerus_get_nonce(
+        &self,
+        contract_address: String,
+        block_tag: Option<String>,
+    ) -> Result<WithMetadata<FieldElement>> {
+        let contract_address = parse_felt(&contract_address)?;
+        let target = parse_target(block_tag, self._beerus.default_target())?;
+        self._beerus
+            .starknet_get_nonce_with_metadata(contract_address, target)
+            .await
+            .map_err(rpc_error)
+    }
+
+    async fn beerus_call(
+        &self,
+        contract_address: String,
+        entry_point_selector: String,
+        calldata: Vec<String>,
+        block_tag: Option<String>,
+    ) -> Result<WithMetadata<Vec<FieldElement>>> {
+        let contract_address = parse_felt(&contract_address)?;
+        let entry_point_selector = parse_felt(&entry_point_selector)?;
+        let calldata = calldata
+            .iter()
+            .map(|value| parse_felt(value))
+            .collect::<Result<Vec<FieldElement>>>()?;
+        let target = parse_target(block_tag, self._beerus.default_target())?;
+        self._beerus
+            .starknet_call_contract_with_metadata(
+                contract_address,
+                entry_point_selector,
+                calldata,
+                target,
+            )
+            .await
+            .map_err(rpc_error)
+    }
+
+    async fn beerus_get_block_range(
+        &self,
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<Vec<BlockWithTxs>> {
+        let blocks = self
+            ._beerus
+            .get_blocks_in_range(from_block, to_block)
+            .map_err(rpc_error)?;
+        Ok(blocks
+            .iter()
+            .map(|block| BlockWithTxs::clone(block))
+            .collect())
+    }
+
+    async fn beerus_get_class_declaration(
+        &self,
+        class_hash: String,
+    ) -> Result<Option<ClassDeclaration>> {
+        let class_hash = parse_felt(&class_hash)?;
+        Ok(self._beerus.get_class_declaration(&class_hash))
+    }
+
+    async fn beerus_get_contract_deployment(
+        &self,
+        contract_address: String,
+    ) -> Result<Option<ContractDeployment>> {
+        let contract_address = parse_felt(&contract_address)?;
+        Ok(self._beerus.get_contract_deployment(&contract_address))
+    }
+
+    async fn beerus_get_contracts_by_class(
+        &self,
+        class_hash: String,
+    ) -> Result<Vec<ContractDeployment>> {
+        let class_hash = parse_felt(&class_hash)?;
+        Ok(self._beerus.get_contracts_of_class(&class_hash))
+    }
+
+    async fn beerus_get_transaction_proof(
+        &self,
+        transaction_hash: String,
+    ) -> Result<TransactionProof> {
+        let transaction_hash = parse_felt(&transaction_hash)?;
+        self._beerus
+            .get_transaction_proof(&transaction_hash)
+            .map_err(rpc_error)
+    }
+
+    async fn beerus_get_l1_to_l2_message_status(
+        &self,
+        l1_transaction_hash: String,
+    ) -> Result<Vec<L1ToL2MessageStatus>> {
+        let l1_transaction_hash = H256::from_str(&l1_transaction_hash).map_err(|e| {
+            jsonrpsee::core::Error::Call(CallError::InvalidParams(anyhow::anyhow!(e.to_string())))
+        })?;
+        self._beerus
+            .get_l1_to_l2_message_status(l1_transaction_hash)
+            .await
+            .map_err(rpc_error)
+    }
+
+    async fn starknet_estimate_fee(
+        &self,
+        block_id_type: String,
+        block_id: String,
+        request: Vec<AnyBroadcastedTransaction>,
+        simulation_flags: Option<Vec<SimulationFlag>>,
+    ) -> Result<Vec<FeeEstimateWithUnit>> {
+        let simulation_flags = simulation_flags.unwrap_or_default();
+        if simulation_flags.contains(&SimulationFlag::SkipFeeCharge) {
+            return Err(jsonrpsee::core::Error::Call(CallError::InvalidParams(
+                anyhow::anyhow!("SKIP_FEE_CHARGE only applies to starknet_simulateTransactions"),
+            )));
+        }
+        let block_id = self.parse_block_id(&block_id_type, &block_id).await?;
+        self._beerus
+            .starknet_estimate_fee_any(request, simulation_flags, &block_id)
+            .await
+            .map_err(rpc_error)
+    }
+
+    async fn starknet_trace_transaction(
+        &self,
+        transaction_hash: String,
+    ) -> Result<Unverified<Passthrough<Value>>> {
+        let transaction_hash = parse_felt(&transaction_hash)?;
+        self.relay(
+            "starknet_traceTransaction",
+            json!([format!("0x{transaction_hash:x}")]),
+            self._beerus.starknet_trace_transaction(transaction_hash),
+        )
+        .await
+    }
+
+    async fn starknet_trace_block_transactions(
+        &self,
+        block_id_type: String,
+        block_id: String,
+    ) -> Result<Unverified<Passthrough<Value>>> {
+        let block_id = self.parse_block_id(&block_id_type, &block_id).await?;
+        self.relay(
+            "starknet_traceBlockTransactions",
+            json!([&block_id]),
+            self._beerus.starknet_trace_block_transactions(&block_id),
+        )
+        .await
+    }
+
+    async fn starknet_simulate_transactions(
+        &self,
+        block_id_type: String,
+        block_id: String,
+        transactions: Vec<AnyBroadcastedTransaction>,
+        simulation_flags: Vec<SimulationFlag>,
+    ) -> Result<Unverified<Passthrough<Value>>> {
+        let block_id = self.parse_block_id(&block_id_type, &block_id).await?;
+        let params = json!([&block_id, &transactions, &simulation_flags]);
+        self.relay(
+            "starknet_simulateTransactions",
+            params,
+            self._beerus
+                .starknet_simulate_transactions(&block_id, transactions, simulation_flags),
+        )
+        .await
     }
 }
 
 impl BeerusRpc {
-    pub fn new(beerus: BeerusLightClient) -> Self {
-        Self { _beerus: beerus }
+    pub fn new(beerus: BeerusHandle) -> Self {
+        Self {
+            _beerus: beerus,
+            spec_version: SpecVersion::default(),
+            signer: None,
+        }
+    }
+
+    /// Set the JSON-RPC spec version reported by `starknet_specVersion`.
+    pub fn with_spec_version(mut self, spec_version: SpecVersion) -> Self {
+        self.spec_version = spec_version;
+        self
+    }
+
+    /// Set the address of the key signing the responses, reported by `beerus_handshake`.
+    pub fn with_signer(mut self, signer: Option<Address>) -> Self {
+        self.signer = signer;
+        self
+    }
+
+    /// Resolve the block id of a request, rejecting malformed ones with `INVALID_PARAMS`.
+    async fn parse_block_id(&self, block_id_type: &str, block_id: &str) -> Result<BlockId> {
+        self._beerus
+            .resolve_block_id(block_id_type, block_id)
+            .await
+            .map_err(|e| {
+                jsonrpsee::core::Error::Call(CallError::InvalidParams(anyhow::anyhow!(
+                    e.to_string()
+                )))
+            })
+    }
+
+    /// Relay the response of an unverified method as the StarkNet provider wrote it in
+    /// passthrough mode, or answer with its parsed result otherwise.
+    async fn relay(
+        &self,
+        method: &str,
+        params: Value,
+        parsed: impl Future<Output = eyre::Result<Unverified<Value>>>,
+    ) -> Result<Unverified<Passthrough<Value>>> {
+        let result = match self._beerus.config.starknet_rpc_passthrough {
+            true => self
+                ._beerus
+                .starknet_passthrough(method, params)
+                .await
+                .map(|relayed| Unverified::new(Passthrough::Raw(relayed.result))),
+            false => parsed
+                .await
+                .map(|parsed| Unverified::new(Passthrough::Parsed(parsed.result))),
+        };
+        result.map_err(rpc_error)
+    }
+}
+
+/// Convert an error of the light client, answering with the StarkNet spec error code if any, or
+/// with `UPSTREAM_UNAVAILABLE_CODE` when the StarkNet provider can't be reached.
+fn rpc_error(error: eyre::Report) -> jsonrpsee::core::Error {
+    if is_upstream_unavailable(&error) {
+        return jsonrpsee::core::Error::Call(CallError::Custom(ErrorObject::owned(
+            UPSTREAM_UNAVAILABLE_CODE,
+            error.to_string(),
+            None::<()>,
+        )));
+    }
+    match error.downcast_ref::<StarknetError>() {
+        Some(error) => jsonrpsee::core::Error::Call(CallError::Custom(ErrorObject::owned(
+            error.code.code(),
+            error.message.clone(),
+            None::<()>,
+        ))),
+        None => jsonrpsee::core::Error::Call(CallError::Failed(anyhow::anyhow!(error.to_string()))),
+    }
+}
+
+/// Parse a felt parameter, rejecting malformed ones with `INVALID_PARAMS`.
+fn parse_felt(value: &str) -> Result<FieldElement> {
+    FieldElement::from_str(value).map_err(|e| {
+        jsonrpsee::core::Error::Call(CallError::InvalidParams(anyhow::anyhow!(e.to_string())))
+    })
+}
+
+/// Parse an Ethereum address parameter, rejecting malformed ones with `INVALID_PARAMS`.
+fn parse_address(value: &str) -> Result<Address> {
+    Address::from_str(value).map_err(|e| {
+        jsonrpsee::core::Error::Call(CallError::InvalidParams(anyhow::anyhow!(e.to_string())))
+    })
+}
+
+/// Parse the optional Ethereum block of the `eth_` queries, `latest` by default.
+/// Block numbers can be given in hex, as in the Ethereum JSON-RPC API, or in decimal.
+fn parse_block_tag(block: Option<String>) -> Result<BlockTag> {
+    let block = match block {
+        Some(block) => block,
+        None => return Ok(BlockTag::Latest),
+    };
+    let block_tag = match block.strip_prefix("0x") {
+        Some(number) => u64::from_str_radix(number, 16)
+            .map(BlockTag::Number)
+            .map_err(|e| eyre::eyre!("Invalid BlockTag: {e}")),
+        None => block_string_to_block_tag_type(&block),
+    };
+    block_tag.map_err(|e| {
+        jsonrpsee::core::Error::Call(CallError::InvalidParams(anyhow::anyhow!(e.to_string())))
+    })
+}
+
+/// Parse the optional block tag of the `beerus_` state queries, the target of the configured
+/// finality by default. A `0x` prefixed value pins the query to a state root proven on L1.
+fn parse_target(block_tag: Option<String>, default: BlockTarget) -> Result<BlockTarget> {
+    match block_tag {
+        Some(block_tag) => BlockTarget::from_str(&block_tag).map_err(|e| {
+            jsonrpsee::core::Error::Call(CallError::InvalidParams(anyhow::anyhow!(e.to_string())))
+        }),
+        None => Ok(default),
     }
 }
@@ -0,0 +1,152 @@
+//! Server-side deadline of the JSON-RPC requests.
+//! A request which is not answered in time is dropped, which cancels the upstream calls it was
+//! waiting on, and the client receives a JSON-RPC error instead of a hanging connection.
+use crate::error_response;
+use hyper::{Body, Method, Request, Response, StatusCode};
+use serde_json::Value;
+use std::{
+    error::Error,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tower::{Layer, Service};
+
+/// Environment variable used to set the request timeout, in milliseconds.
+pub const REQUEST_TIMEOUT_ENV: &str = "RPC_REQUEST_TIMEOUT_MS";
+pub const DEFAULT_REQUEST_TIMEOUT_MS: u64 = 60_000;
+/// JSON-RPC error code returned when a request times out.
+pub const REQUEST_TIMEOUT_CODE: i64 = -32000;
+
+/// Read the request timeout from the environment, defaulting to one minute.
+pub fn request_timeout_from_env() -> Duration {
+    let timeout_ms = match std::env::var(REQUEST_TIMEOUT_ENV) {
+        Ok(value) => value.parse().unwrap_or_else(|_| {
+            log::warn!("Invalid {REQUEST_TIMEOUT_ENV}: {value}, using the default");
+            DEFAULT_REQUEST_TIMEOUT_MS
+        }),
+        Err(_) => DEFAULT_REQUEST_TIMEOUT_MS,
+    };
+    Duration::from_millis(timeout_ms)
+}
+
+/// Build the response sent when a request times out: one error per call of the request.
+/// Notifications don't get any.
+pub fn timeout_response(request: &Value, timeout: Duration) -> Value {
+    error_response(
+        request,
+        REQUEST_TIMEOUT_CODE,
+        &format!("Request timed out after {}ms", timeout.as_millis()),
+    )
+}
+
+/// Layer that applies [`RequestTimeout`] to the HTTP service of the RPC server.
+#[derive(Debug, Clone, Copy)]
+pub struct RequestTimeoutLayer {
+    timeout: Duration,
+}
+
+impl RequestTimeoutLayer {
+    pub fn new(timeout: Duration) -> Self {
+        Self { timeout }
+    }
+}
+
+impl<S> Layer<S> for RequestTimeoutLayer {
+    type Service = RequestTimeout<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequestTimeout {
+            inner,
+            timeout: self.timeout,
+        }
+    }
+}
+
+/// Answers with a timeout error the JSON-RPC requests which are not handled in time.
+#[derive(Debug, Clone)]
+pub struct RequestTimeout<S> {
+    inner: S,
+    timeout: Duration,
+}
+
+impl<S> Service<Request<Body>> for RequestTimeout<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>> + Clone + Send + 'static,
+    S::Error: Into<Box<dyn Error + Send + Sync>> + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = Box<dyn Error + Send + Sync + 'static>;
+    type Future =
+        Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send + 'static>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let timeout = self.timeout;
+        // The inner service was polled ready, keep it and leave a fresh clone in its place.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+
+        Box::pin(async move {
+            // WebSocket upgrades and health checks are not JSON-RPC calls.
+            if req.method() != Method::POST {
+                return inner.call(req).await.map_err(Into::into);
+            }
+
+            let (parts, body) = req.into_parts();
+            let bytes = hyper::body::to_bytes(body).await?;
+            let request: Value = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+            let call = inner.call(Request::from_parts(parts, Body::from(bytes)));
+
+            match tokio::time::timeout(timeout, call).await {
+                Ok(res) => res.map_err(Into::into),
+                Err(_) => {
+                    log::warn!("Request timed out after {}ms", timeout.as_millis());
+                    let body = serde_json::to_vec(&timeout_response(&request, timeout))?;
+                    Ok(Response::builder()
+                        .status(StatusCode::OK)
+                        .header(hyper::header::CONTENT_TYPE, "application/json")
+                        .body(Body::from(body))?)
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_timeout_response_keeps_the_request_id() {
+        let request = json!({"jsonrpc": "2.0", "id": 7, "method": "starknet_blockNumber"});
+
+        let response = timeout_response(&request, Duration::from_millis(500));
+
+        assert_eq!(response["id"], json!(7));
+        assert_eq!(response["error"]["code"], json!(REQUEST_TIMEOUT_CODE));
+        assert_eq!(
+            response["error"]["message"],
+            json!("Request timed out after 500ms")
+        );
+    }
+
+    #[test]
+    fn test_timeout_response_skips_batch_notifications() {
+        let request = json!([
+            {"jsonrpc": "2.0", "id": 1, "method": "starknet_blockNumber"},
+            {"jsonrpc": "2.0", "method": "starknet_chainId"},
+        ]);
+
+        let response = timeout_response(&request, Duration::from_millis(500));
+
+        assert_eq!(response.as_array().unwrap().len(), 1);
+        assert_eq!(response[0]["id"], json!(1));
+    }
+}
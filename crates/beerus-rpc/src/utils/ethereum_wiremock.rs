@@ -0,0 +1,378 @@
+//! Ethereum side of the mocks, mirroring [`super::wiremock`] for the StarkNet provider: a
+//! consensus and an execution RPC answering from [`EthereumFixtures`], and an
+//! [`EthereumLightClient`] reading from them, so that the integration tests run requests
+//! through to L1, proven blocks included, without network access.
+//!
+//! Helios can't follow mocked endpoints since it verifies the sync committee signatures, so
+//! [`WiremockEthereumLightClient`] trusts the execution RPC and only asks the consensus RPC
+//! whether it is synced when started.
+use super::wiremock::response_template_with_status;
+use async_trait::async_trait;
+use beerus_core::{
+    config::STARKNET_GOERLI_CC_ADDRESS,
+    ethers_helper::{bytes_to_u256, h256_to_u256},
+    lightclient::ethereum::{
+        helios_lightclient::build_logs_filter, read_starknet_proven_state, EthereumLightClient,
+    },
+};
+use ethers::{
+    providers::{Http, Middleware, Provider},
+    types::{
+        transaction::eip2718::TypedTransaction, Address, BlockId, BlockNumber, Bytes, Log,
+        Transaction, TransactionRequest, H256, U256, U64,
+    },
+    utils::id,
+};
+use eyre::{eyre, Result};
+use helios::types::{BlockTag, CallOpts, ExecutionBlock};
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+use wiremock::{
+    matchers::{body_partial_json, method, path},
+    Mock, MockServer,
+};
+
+/// State of L1 the mocks answer with.
+#[derive(Debug, Clone)]
+pub struct EthereumFixtures {
+    pub chain_id: u64,
+    pub block_number: u64,
+    pub gas_price: U256,
+    pub balances: Vec<(Address, U256)>,
+    pub starknet_core_contract_address: Address,
+    /// Last StarkNet block proven in the core contract, along with its hash and state root.
+    pub proven_block_number: u64,
+    pub proven_block_hash: U256,
+    pub state_root: U256,
+}
+
+impl Default for EthereumFixtures {
+    /// Goerli, with the core contract the default config points to.
+    fn default() -> Self {
+        Self {
+            chain_id: 5,
+            block_number: 8_500_000,
+            gas_price: U256::from(100_000_000_000_u64),
+            balances: vec![],
+            starknet_core_contract_address: STARKNET_GOERLI_CC_ADDRESS
+                .parse()
+                .expect("Valid address"),
+            proven_block_number: 19_600,
+            proven_block_hash: U256::from(0x1960_u64),
+            state_root: U256::from(0x5ca1ab1e_u64),
+        }
+    }
+}
+
+/// Consensus and execution RPC servers, the mocks are dropped along with them.
+pub struct EthereumWiremock {
+    pub consensus: MockServer,
+    pub execution: MockServer,
+}
+
+impl EthereumWiremock {
+    pub fn consensus_rpc(&self) -> String {
+        self.consensus.uri()
+    }
+
+    pub fn execution_rpc(&self) -> String {
+        self.execution.uri()
+    }
+}
+
+pub async fn setup_ethereum_wiremock(fixtures: &EthereumFixtures) -> EthereumWiremock {
+    let consensus = MockServer::start().await;
+    mock_syncing().mount(&consensus).await;
+
+    let execution = MockServer::start().await;
+    for (name, result) in [
+        ("eth_chainId", json!(U64::from(fixtures.chain_id))),
+        ("eth_blockNumber", json!(U64::from(fixtures.block_number))),
+        ("eth_gasPrice", json!(fixtures.gas_price)),
+    ] {
+        mock_execution(json!({ "method": name }), result)
+            .mount(&execution)
+            .await;
+    }
+    for (address, balance) in fixtures.balances.iter() {
+        mock_execution(
+            json!({ "method": "eth_getBalance", "params": [address] }),
+            json!(balance),
+        )
+        .mount(&execution)
+        .await;
+    }
+    for (function, value) in [
+        (
+            "stateBlockNumber()",
+            U256::from(fixtures.proven_block_number),
+        ),
+        ("stateBlockHash()", fixtures.proven_block_hash),
+        ("stateRoot()", fixtures.state_root),
+    ] {
+        mock_core_contract_call(fixtures.starknet_core_contract_address, function, value)
+            .mount(&execution)
+            .await;
+    }
+    EthereumWiremock {
+        consensus,
+        execution,
+    }
+}
+
+fn mock_syncing() -> Mock {
+    Mock::given(method("GET"))
+        .and(path("/eth/v1/node/syncing"))
+        .respond_with(
+            response_template_with_status(StatusCode::OK).set_body_json(json!({
+                "data": {
+                    "head_slot": "5000000",
+                    "sync_distance": "0",
+                    "is_syncing": false,
+                    "is_optimistic": false
+                }
+            })),
+        )
+}
+
+fn mock_execution(request: Value, result: Value) -> Mock {
+    Mock::given(method("POST"))
+        .and(body_partial_json(request))
+        .respond_with(
+            response_template_with_status(StatusCode::OK)
+                .set_body_json(json!({ "jsonrpc": "2.0", "id": 1, "result": result })),
+        )
+}
+
+/// `eth_call` of a getter of the StarkNet core contract, returning a single word.
+fn mock_core_contract_call(contract: Address, function: &str, value: U256) -> Mock {
+    let mut word = [0_u8; 32];
+    value.to_big_endian(&mut word);
+    mock_execution(
+        json!({
+            "method": "eth_call",
+            "params": [{ "to": contract, "data": Bytes::from(id(function).to_vec()) }]
+        }),
+        json!(Bytes::from(word.to_vec())),
+    )
+}
+
+/// `EthereumLightClient` reading from the mocked consensus and execution RPC.
+pub struct WiremockEthereumLightClient {
+    consensus_rpc: String,
+    http_client: reqwest::Client,
+    provider: Provider<Http>,
+    starknet_core_contract_address: Address,
+}
+
+impl WiremockEthereumLightClient {
+    pub fn new(mocks: &EthereumWiremock, starknet_core_contract_address: Address) -> Result<Self> {
+        Ok(Self {
+            consensus_rpc: mocks.consensus_rpc(),
+            http_client: reqwest::Client::new(),
+            provider: Provider::<Http>::try_from(mocks.execution_rpc())?,
+            starknet_core_contract_address,
+        })
+    }
+
+    async fn core_contract_word(&self, function: &str) -> Result<U256> {
+        let call_opts = CallOpts {
+            from: None,
+            to: self.starknet_core_contract_address,
+            gas: None,
+            gas_price: None,
+            value: None,
+            data: Some(id(function).to_vec()),
+        };
+        bytes_to_u256(&self.call(&call_opts, BlockTag::Latest).await?)
+    }
+}
+
+fn block_number(block: BlockTag) -> BlockNumber {
+    match block {
+        BlockTag::Latest => BlockNumber::Latest,
+        BlockTag::Finalized => BlockNumber::Finalized,
+        BlockTag::Number(number) => BlockNumber::Number(number.into()),
+    }
+}
+
+fn block_id(block: BlockTag) -> BlockId {
+    BlockId::Number(block_number(block))
+}
+
+fn transaction_request(opts: &CallOpts) -> TransactionRequest {
+    let mut request = TransactionRequest::new().to(opts.to);
+    request.from = opts.from;
+    request.gas = opts.gas;
+    request.gas_price = opts.gas_price;
+    request.value = opts.value;
+    request.data = opts.data.clone().map(Bytes::from);
+    request
+}
+
+#[async_trait]
+impl EthereumLightClient for WiremockEthereumLightClient {
+    async fn start(&mut self) -> Result<()> {
+        let syncing: Value = self
+            .http_client
+            .get(format!("{}/eth/v1/node/syncing", self.consensus_rpc))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        match syncing["data"]["is_syncing"].as_bool() {
+            Some(false) => Ok(()),
+            _ => Err(eyre!("The consensus node is not synced: {syncing}")),
+        }
+    }
+
+    async fn call(&self, opts: &CallOpts, block: BlockTag) -> Result<Vec<u8>> {
+        let request: TypedTransaction = transaction_request(opts).into();
+        Ok(self
+            .provider
+            .call(&request, Some(block_id(block)))
+            .await?
+            .to_vec())
+    }
+
+    async fn send_raw_transaction(&self, bytes: &[u8]) -> Result<H256> {
+        Ok(self
+            .provider
+            .send_raw_transaction(Bytes::from(bytes.to_vec()))
+            .await?
+            .tx_hash())
+    }
+
+    async fn get_balance(&self, address: &Address, block: BlockTag) -> Result<U256> {
+        Ok(self
+            .provider
+            .get_balance(*address, Some(block_id(block)))
+            .await?)
+    }
+
+    async fn get_nonce(&self, address: &Address, block: BlockTag) -> Result<u64> {
+        self.get_transaction_count(address, block).await
+    }
+
+    async fn get_block_number(&self) -> Result<u64> {
+        Ok(self.provider.get_block_number().await?.as_u64())
+    }
+
+    async fn chain_id(&self) -> u64 {
+        self.provider
+            .get_chainid()
+            .await
+            .map_or(0, |chain_id| chain_id.as_u64())
+    }
+
+    async fn get_code(&self, address: &Address, block: BlockTag) -> Result<Vec<u8>> {
+        Ok(self
+            .provider
+            .get_code(*address, Some(block_id(block)))
+            .await?
+            .to_vec())
+    }
+
+    async fn get_storage_at(&self, address: &Address, slot: H256, block: BlockTag) -> Result<U256> {
+        let value = self
+            .provider
+            .get_storage_at(*address, slot, Some(block_id(block)))
+            .await?;
+        Ok(h256_to_u256(value))
+    }
+
+    async fn get_transaction_count(&self, address: &Address, block: BlockTag) -> Result<u64> {
+        Ok(self
+            .provider
+            .get_transaction_count(*address, Some(block_id(block)))
+            .await?
+            .as_u64())
+    }
+
+    async fn get_block_transaction_count_by_number(&self, block: BlockTag) -> Result<u64> {
+        let count: U64 = self
+            .provider
+            .request(
+                "eth_getBlockTransactionCountByNumber",
+                [block_number(block)],
+            )
+            .await?;
+        Ok(count.as_u64())
+    }
+
+    async fn get_block_transaction_count_by_hash(&self, hash: &[u8]) -> Result<u64> {
+        let count: U64 = self
+            .provider
+            .request(
+                "eth_getBlockTransactionCountByHash",
+                [H256::from_slice(hash)],
+            )
+            .await?;
+        Ok(count.as_u64())
+    }
+
+    async fn get_transaction_by_hash(&self, tx_hash: &H256) -> Result<Option<Transaction>> {
+        Ok(self.provider.get_transaction(*tx_hash).await?)
+    }
+
+    async fn get_gas_price(&self) -> Result<U256> {
+        Ok(self.provider.get_gas_price().await?)
+    }
+
+    async fn estimate_gas(&self, opts: &CallOpts) -> Result<u64> {
+        let request: TypedTransaction = transaction_request(opts).into();
+        Ok(self.provider.estimate_gas(&request, None).await?.as_u64())
+    }
+
+    async fn get_block_by_hash(
+        &self,
+        _hash: &[u8],
+        _full_tx: bool,
+    ) -> Result<Option<ExecutionBlock>> {
+        Err(eyre!("Execution blocks are not mocked"))
+    }
+
+    async fn get_priority_fee(&self) -> Result<U256> {
+        Ok(self
+            .provider
+            .request("eth_maxPriorityFeePerGas", ())
+            .await?)
+    }
+
+    async fn get_block_by_number(
+        &self,
+        _block: BlockTag,
+        _full_tx: bool,
+    ) -> Result<Option<ExecutionBlock>> {
+        Err(eyre!("Execution blocks are not mocked"))
+    }
+
+    async fn get_logs(
+        &self,
+        from_block: &Option<String>,
+        to_block: &Option<String>,
+        address: &Option<String>,
+        topics: &Option<Vec<String>>,
+        block_hash: &Option<String>,
+    ) -> Result<Vec<Log>> {
+        let filter = build_logs_filter(from_block, to_block, address, topics, block_hash)?;
+        Ok(self.provider.get_logs(&filter).await?)
+    }
+
+    async fn starknet_last_proven_block(&self) -> Result<U256> {
+        self.core_contract_word("stateBlockNumber()").await
+    }
+
+    async fn starknet_last_proven_block_hash(&self) -> Result<U256> {
+        self.core_contract_word("stateBlockHash()").await
+    }
+
+    async fn starknet_state_root(&self) -> Result<U256> {
+        self.core_contract_word("stateRoot()").await
+    }
+
+    async fn starknet_proven_state(&self) -> Result<(U256, U256)> {
+        read_starknet_proven_state(self, self.starknet_core_contract_address).await
+    }
+}
@@ -1 +1,3 @@
+pub mod ethereum_wiremock;
+pub mod openrpc;
 pub mod wiremock;
@@ -0,0 +1,420 @@
+//! Fixtures generated from the StarkNet OpenRPC specification, so that the mocks of
+//! [`super::wiremock`] follow the spec rather than hand-maintained JSON files.
+//!
+//! The spec is an excerpt of `starknet_api_openrpc.json` kept in `data/spec`, to be updated
+//! along with the version of the API served. A generated result is the smallest value valid
+//! for its schema: the first variant of a `oneOf`, the required properties of an object, one
+//! item per array and the shortest string matching a pattern. Results chosen by a test are
+//! checked against the schema instead.
+use eyre::{eyre, Result};
+use regex::Regex;
+use serde_json::{json, Map, Value};
+
+const STARKNET_SPEC: &str = include_str!("data/spec/starknet_api_openrpc.json");
+/// Id of the requests sent by the clients under test, echoed in the responses.
+const REQUEST_ID: u64 = 1;
+
+/// OpenRPC specification of a JSON-RPC API.
+#[derive(Debug, Clone)]
+pub struct OpenRpcSpec {
+    spec: Value,
+}
+
+impl OpenRpcSpec {
+    pub fn new(spec: Value) -> Self {
+        Self { spec }
+    }
+
+    /// The StarkNet spec vendored in `data/spec`.
+    pub fn starknet() -> Self {
+        Self::new(serde_json::from_str(STARKNET_SPEC).expect("The vendored spec is valid JSON"))
+    }
+
+    /// Names of the methods of the spec.
+    pub fn methods(&self) -> Vec<&str> {
+        self.spec["methods"]
+            .as_array()
+            .map(|methods| {
+                methods
+                    .iter()
+                    .filter_map(|method| method["name"].as_str())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Smallest result of a method valid for its schema.
+    /// # Errors
+    /// * If the method is not in the spec, or its schema uses an unsupported construct.
+    pub fn result(&self, method: &str) -> Result<Value> {
+        self.generate(self.result_schema(method)?)
+    }
+
+    /// Response of a method with a generated result.
+    pub fn response(&self, method: &str) -> Result<Value> {
+        Ok(envelope("result", self.result(method)?))
+    }
+
+    /// Response of a method with the given result.
+    /// # Errors
+    /// * If the result doesn't match the schema of the method.
+    pub fn response_with(&self, method: &str, result: Value) -> Result<Value> {
+        self.validate(self.result_schema(method)?, &result)
+            .map_err(|err| eyre!("Invalid result for {method}: {err}"))?;
+        Ok(envelope("result", result))
+    }
+
+    /// Error response of a method, e.g. `BLOCK_NOT_FOUND`.
+    /// # Errors
+    /// * If the error is not one the method declares.
+    pub fn error_response(&self, method: &str, error: &str) -> Result<Value> {
+        let reference = format!("#/components/errors/{error}");
+        let declared = self.method(method)?["errors"]
+            .as_array()
+            .map_or(false, |errors| {
+                errors
+                    .iter()
+                    .any(|declared| declared["$ref"].as_str() == Some(reference.as_str()))
+            });
+        if !declared {
+            return Err(eyre!("{method} doesn't declare the error {error}"));
+        }
+        Ok(envelope(
+            "error",
+            self.resolve(&json!({ "$ref": reference }))?.clone(),
+        ))
+    }
+
+    /// Check a value against a schema of the spec.
+    /// # Errors
+    /// * The path of the first part of the value which doesn't match.
+    pub fn validate(&self, schema: &Value, value: &Value) -> Result<()> {
+        self.check(schema, value, "result")
+    }
+
+    fn method(&self, name: &str) -> Result<&Value> {
+        self.spec["methods"]
+            .as_array()
+            .and_then(|methods| methods.iter().find(|method| method["name"] == name))
+            .ok_or_else(|| eyre!("Unknown method {name}"))
+    }
+
+    fn result_schema(&self, method: &str) -> Result<&Value> {
+        Ok(&self.method(method)?["result"]["schema"])
+    }
+
+    /// Follow the `$ref` of a schema, its siblings are annotations.
+    fn resolve<'a>(&'a self, mut schema: &'a Value) -> Result<&'a Value> {
+        while let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            schema = reference
+                .strip_prefix('#')
+                .and_then(|pointer| self.spec.pointer(pointer))
+                .ok_or_else(|| eyre!("Unresolved reference {reference}"))?;
+        }
+        Ok(schema)
+    }
+
+    fn generate(&self, schema: &Value) -> Result<Value> {
+        let schema = self.resolve(schema)?;
+        if let Some(value) = schema.get("const") {
+            return Ok(value.clone());
+        }
+        if let Some(value) = schema["enum"].as_array().and_then(|values| values.first()) {
+            return Ok(value.clone());
+        }
+        if let Some(variant) = variants(schema).and_then(|variants| variants.first()) {
+            return self.generate(variant);
+        }
+        if let Some(parts) = schema["allOf"].as_array() {
+            let mut merged = Map::new();
+            for part in parts {
+                match self.generate(part)? {
+                    Value::Object(object) => merged.extend(object),
+                    other => return Err(eyre!("allOf of a non object schema: {other}")),
+                }
+            }
+            return Ok(Value::Object(merged));
+        }
+        match schema["type"].as_str() {
+            Some("object") => {
+                let mut object = Map::new();
+                for name in schema["required"].as_array().into_iter().flatten() {
+                    let name = name
+                        .as_str()
+                        .ok_or_else(|| eyre!("Invalid property {name}"))?;
+                    let property = schema["properties"]
+                        .get(name)
+                        .ok_or_else(|| eyre!("Required property {name} is not defined"))?;
+                    object.insert(name.to_string(), self.generate(property)?);
+                }
+                Ok(Value::Object(object))
+            }
+            Some("array") => match schema.get("items") {
+                Some(items) => Ok(json!([self.generate(items)?])),
+                None => Ok(json!([])),
+            },
+            Some("string") => match schema["pattern"].as_str() {
+                Some(pattern) => Ok(json!(sample_pattern(pattern)?)),
+                None => Ok(json!("")),
+            },
+            Some("integer") | Some("number") => {
+                Ok(schema.get("minimum").cloned().unwrap_or_else(|| json!(0)))
+            }
+            Some("boolean") => Ok(json!(false)),
+            Some("null") => Ok(Value::Null),
+            other => Err(eyre!("Unsupported schema type {other:?}")),
+        }
+    }
+
+    fn check(&self, schema: &Value, value: &Value, path: &str) -> Result<()> {
+        let schema = self.resolve(schema)?;
+        if let Some(expected) = schema.get("const") {
+            if expected != value {
+                return Err(eyre!("{path}: expected {expected}, got {value}"));
+            }
+        }
+        if let Some(values) = schema["enum"].as_array() {
+            if !values.contains(value) {
+                return Err(eyre!("{path}: {value} is not one of {values:?}"));
+            }
+        }
+        if let Some(variants) = variants(schema) {
+            if !variants
+                .iter()
+                .any(|variant| self.check(variant, value, path).is_ok())
+            {
+                return Err(eyre!("{path}: {value} matches none of the variants"));
+            }
+        }
+        for part in schema["allOf"].as_array().into_iter().flatten() {
+            self.check(part, value, path)?;
+        }
+        let kind = match schema["type"].as_str() {
+            Some(kind) => kind,
+            None => return Ok(()),
+        };
+        let mismatch = || eyre!("{path}: expected {kind}, got {value}");
+        match kind {
+            "object" => {
+                let object = value.as_object().ok_or_else(mismatch)?;
+                for name in schema["required"].as_array().into_iter().flatten() {
+                    let name = name.as_str().unwrap_or_default();
+                    if !object.contains_key(name) {
+                        return Err(eyre!("{path}: missing property {name}"));
+                    }
+                }
+                for (name, property) in schema["properties"].as_object().into_iter().flatten() {
+                    if let Some(value) = object.get(name) {
+                        self.check(property, value, &format!("{path}.{name}"))?;
+                    }
+                }
+            }
+            "array" => {
+                let items = value.as_array().ok_or_else(mismatch)?;
+                if let Some(schema) = schema.get("items") {
+                    for (index, item) in items.iter().enumerate() {
+                        self.check(schema, item, &format!("{path}[{index}]"))?;
+                    }
+                }
+            }
+            "string" => {
+                let string = value.as_str().ok_or_else(mismatch)?;
+                if let Some(pattern) = schema["pattern"].as_str() {
+                    if !Regex::new(pattern)?.is_match(string) {
+                        return Err(eyre!("{path}: {string} doesn't match {pattern}"));
+                    }
+                }
+            }
+            "integer" | "number" => {
+                let integer = value.is_i64() || value.is_u64();
+                let number = value
+                    .as_f64()
+                    .filter(|_| kind == "number" || integer)
+                    .ok_or_else(mismatch)?;
+                if let Some(minimum) = schema["minimum"].as_f64() {
+                    if number < minimum {
+                        return Err(eyre!("{path}: {value} is lower than {minimum}"));
+                    }
+                }
+            }
+            "boolean" => {
+                value.as_bool().ok_or_else(mismatch)?;
+            }
+            "null" => {
+                value.as_null().ok_or_else(mismatch)?;
+            }
+            other => return Err(eyre!("Unsupported schema type {other}")),
+        }
+        Ok(())
+    }
+}
+
+/// JSON-RPC response with a result or an error.
+fn envelope(field: &str, value: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": REQUEST_ID, field: value })
+}
+
+fn variants(schema: &Value) -> Option<&Vec<Value>> {
+    schema["oneOf"]
+        .as_array()
+        .or_else(|| schema["anyOf"].as_array())
+}
+
+/// Shortest string matching a regular expression, for the subset the spec uses: anchors,
+/// groups of alternatives, character classes, escapes and quantifiers.
+fn sample_pattern(pattern: &str) -> Result<String> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut pos = 0;
+    let sample = sample_alternatives(&chars, &mut pos)?;
+    match pos == chars.len() {
+        true => Ok(sample),
+        false => Err(eyre!("Unbalanced pattern {pattern}")),
+    }
+}
+
+/// Sample of the first alternative, the others are skipped.
+fn sample_alternatives(chars: &[char], pos: &mut usize) -> Result<String> {
+    let sample = sample_sequence(chars, pos)?;
+    while chars.get(*pos) == Some(&'|') {
+        *pos += 1;
+        sample_sequence(chars, pos)?;
+    }
+    Ok(sample)
+}
+
+fn sample_sequence(chars: &[char], pos: &mut usize) -> Result<String> {
+    let mut sample = String::new();
+    while let Some(c) = chars.get(*pos).copied() {
+        *pos += 1;
+        let atom = match c {
+            '|' | ')' => {
+                *pos -= 1;
+                break;
+            }
+            '^' | '$' => continue,
+            '(' => {
+                if chars.get(*pos..*pos + 2) == Some(&['?', ':'][..]) {
+                    *pos += 2;
+                }
+                let atom = sample_alternatives(chars, pos)?;
+                if chars.get(*pos) != Some(&')') {
+                    return Err(eyre!("Unclosed group"));
+                }
+                *pos += 1;
+                atom
+            }
+            '[' => {
+                let first = match chars.get(*pos) {
+                    Some('^') => return Err(eyre!("Negated classes are not supported")),
+                    Some('\\') => escaped(chars.get(*pos + 1).copied())?,
+                    Some(first) => first.to_string(),
+                    None => return Err(eyre!("Unclosed class")),
+                };
+                let end = chars[*pos + 1..]
+                    .iter()
+                    .position(|c| *c == ']')
+                    .ok_or_else(|| eyre!("Unclosed class"))?;
+                *pos += end + 2;
+                first
+            }
+            '\\' => {
+                *pos += 1;
+                escaped(chars.get(*pos - 1).copied())?
+            }
+            '.' => "a".to_string(),
+            c => c.to_string(),
+        };
+        let repeat = min_repeat(chars, pos)?;
+        sample.push_str(&atom.repeat(repeat));
+    }
+    Ok(sample)
+}
+
+/// Sample of an escaped character or class.
+fn escaped(c: Option<char>) -> Result<String> {
+    match c.ok_or_else(|| eyre!("Dangling escape"))? {
+        'd' => Ok("0".to_string()),
+        'w' => Ok("a".to_string()),
+        's' => Ok(" ".to_string()),
+        c => Ok(c.to_string()),
+    }
+}
+
+/// Minimum repeat of the quantifier following an atom, 1 without quantifier.
+fn min_repeat(chars: &[char], pos: &mut usize) -> Result<usize> {
+    match chars.get(*pos) {
+        Some('?') | Some('*') => {
+            *pos += 1;
+            Ok(0)
+        }
+        Some('+') => {
+            *pos += 1;
+            Ok(1)
+        }
+        Some('{') => {
+            let end = chars[*pos..]
+                .iter()
+                .position(|c| *c == '}')
+                .ok_or_else(|| eyre!("Unclosed quantifier"))?
+                + *pos;
+            let quantifier: String = chars[*pos + 1..end].iter().collect();
+            *pos = end + 1;
+            let min = quantifier.split(',').next().unwrap_or_default().trim();
+            min.parse()
+                .map_err(|_| eyre!("Invalid quantifier {{{quantifier}}}"))
+        }
+        _ => Ok(1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_results_match_their_schema() {
+        let spec = OpenRpcSpec::starknet();
+
+        for method in spec.methods() {
+            let result = spec.result(method).unwrap();
+            spec.validate(spec.result_schema(method).unwrap(), &result)
+                .unwrap_or_else(|err| panic!("{method}: {err}"));
+        }
+        assert_eq!(spec.result("starknet_getNonce").unwrap(), json!("0x0"));
+        assert_eq!(spec.result("starknet_chainId").unwrap(), json!("0xa"));
+        let block = spec.result("starknet_getBlockWithTxHashes").unwrap();
+        assert_eq!(block["status"], "PENDING");
+        assert_eq!(block["transactions"], json!(["0x0"]));
+        assert_eq!(
+            sample_pattern("^0x0[0-7]{1}[a-fA-F0-9]{0,62}$").unwrap(),
+            "0x00"
+        );
+    }
+
+    #[test]
+    fn test_fixtures_outside_the_spec_are_rejected() {
+        let spec = OpenRpcSpec::starknet();
+
+        assert_eq!(
+            spec.response_with("starknet_getNonce", json!("0x5"))
+                .unwrap(),
+            json!({"jsonrpc": "2.0", "id": 1, "result": "0x5"})
+        );
+        assert!(spec.response_with("starknet_getNonce", json!("5")).is_err());
+        assert!(spec
+            .response_with("starknet_blockNumber", json!(-1))
+            .is_err());
+        assert!(spec
+            .response_with("starknet_blockHashAndNumber", json!({"block_hash": "0x1"}))
+            .is_err());
+        assert_eq!(
+            spec.error_response("starknet_getBlockTransactionCount", "BLOCK_NOT_FOUND")
+                .unwrap()["error"],
+            json!({"code": 24, "message": "Block not found"})
+        );
+        assert!(spec
+            .error_response("starknet_chainId", "BLOCK_NOT_FOUND")
+            .is_err());
+        assert!(spec.response("starknet_unknown").is_err());
+    }
+}
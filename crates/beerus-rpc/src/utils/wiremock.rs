@@ -1,8 +1,10 @@
+use super::openrpc::OpenRpcSpec;
 use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 use starknet::providers::jsonrpc::models::{BlockId, BlockTag};
 use wiremock::{
-    matchers::{body_json, method},
+    matchers::{body_json, body_partial_json, method},
     Mock, MockServer, ResponseTemplate,
 };
 
@@ -39,22 +41,78 @@ impl<'a, StarknetParams> StarknetRpcBaseData<'a, StarknetParams> {
             params,
         }
     }
+
+    pub const fn starknet_get_nonce(params: StarknetParams) -> Self {
+        Self {
+            id: 1,
+            jsonrpc: "2.0",
+            method: "starknet_getNonce",
+            params,
+        }
+    }
+
+    pub const fn starknet_estimate_fee(params: StarknetParams) -> Self {
+        Self {
+            id: 1,
+            jsonrpc: "2.0",
+            method: "starknet_estimateFee",
+            params,
+        }
+    }
+}
+
+/// Invoke transaction of version 3, paying its fee in STRK.
+pub fn invoke_v3_transaction() -> serde_json::Value {
+    serde_json::json!({
+        "type": "INVOKE",
+        "version": "0x3",
+        "sender_address": "0x5b5e9f6f6fb7d2647d81a8b2c2b99cbc9cc9d03d705576d7061812324dca5c0",
+        "calldata": [],
+        "signature": [],
+        "nonce": "0x0",
+        "resource_bounds": {
+            "l1_gas": {"max_amount": "0x100", "max_price_per_unit": "0x174876e800"},
+            "l2_gas": {"max_amount": "0x0", "max_price_per_unit": "0x0"}
+        },
+        "tip": "0x0",
+        "paymaster_data": [],
+        "account_deployment_data": [],
+        "nonce_data_availability_mode": "L1",
+        "fee_data_availability_mode": "L1"
+    })
 }
 
 pub async fn setup_wiremock() -> String {
+    setup_wiremock_server().await.uri()
+}
+
+/// Mocked StarkNet provider, the mocks are dropped along with the server.
+pub async fn setup_wiremock_server() -> MockServer {
     let mock_server = MockServer::start().await;
     mock_block_number().mount(&mock_server).await;
     mock_get_block_transaction_count().mount(&mock_server).await;
-    mock_server.uri()
+    mock_get_block_transaction_count_block_not_found()
+        .mount(&mock_server)
+        .await;
+    mock_get_pending_nonce().mount(&mock_server).await;
+    mock_estimate_fee_v3().mount(&mock_server).await;
+    mock_server
+}
+
+/// Mock answering a method of the spec, whatever its params, with a generated result.
+pub fn mock_from_spec(name: &str) -> Mock {
+    let body = OpenRpcSpec::starknet()
+        .response(name)
+        .unwrap_or_else(|err| panic!("Can't generate a fixture for {name}: {err}"));
+    Mock::given(method("POST"))
+        .and(body_partial_json(json!({ "method": name })))
+        .respond_with(response_template_with_status(StatusCode::OK).set_body_json(body))
 }
 
 fn mock_block_number() -> Mock {
     Mock::given(method("POST"))
         .and(body_json(StarknetRpcBaseData::stark_block_number(())))
-        .respond_with(response_template_with_status(StatusCode::OK).set_body_raw(
-            include_str!("data/blocks/starknet_blockNumber.json"),
-            "application/json",
-        ))
+        .respond_with(spec_response("starknet_blockNumber", json!(19640)))
 }
 
 fn mock_get_block_transaction_count() -> Mock {
@@ -63,13 +121,66 @@ fn mock_get_block_transaction_count() -> Mock {
         .and(body_json(
             StarknetRpcBaseData::starknet_get_block_transaction_count([&latest_block]),
         ))
+        .respond_with(spec_response(
+            "starknet_getBlockTransactionCount",
+            json!(90),
+        ))
+}
+
+fn mock_get_block_transaction_count_block_not_found() -> Mock {
+    let unknown_block = BlockId::Number(u64::MAX);
+    Mock::given(method("POST"))
+        .and(body_json(
+            StarknetRpcBaseData::starknet_get_block_transaction_count([&unknown_block]),
+        ))
+        .respond_with(spec_error(
+            "starknet_getBlockTransactionCount",
+            "BLOCK_NOT_FOUND",
+        ))
+}
+
+fn mock_get_pending_nonce() -> Mock {
+    let pending_block = BlockId::Tag(BlockTag::Pending);
+    Mock::given(method("POST"))
+        .and(body_json(StarknetRpcBaseData::starknet_get_nonce((
+            &pending_block,
+            "0x1",
+        ))))
+        .respond_with(spec_response("starknet_getNonce", json!("0x5")))
+}
+
+/// Fee estimates of v3 transactions postdate the vendored spec, the response is kept as is.
+fn mock_estimate_fee_v3() -> Mock {
+    Mock::given(method("POST"))
+        .and(body_json(StarknetRpcBaseData::starknet_estimate_fee(
+            serde_json::json!({
+                "request": [invoke_v3_transaction()],
+                "block_id": "latest",
+            }),
+        )))
         .respond_with(response_template_with_status(StatusCode::OK).set_body_raw(
-            include_str!("data/blocks/starknet_getBlockTransactionCount.json"),
+            include_str!("data/transactions/starknet_estimateFee_v3.json"),
             "application/json",
         ))
 }
 
-fn response_template_with_status(status_code: StatusCode) -> ResponseTemplate {
+/// Response of a method with the given result, which must match the spec.
+fn spec_response(method: &str, result: Value) -> ResponseTemplate {
+    let body = OpenRpcSpec::starknet()
+        .response_with(method, result)
+        .unwrap_or_else(|err| panic!("Invalid fixture: {err}"));
+    response_template_with_status(StatusCode::OK).set_body_json(body)
+}
+
+/// Response of a method with one of the errors it declares in the spec.
+fn spec_error(method: &str, error: &str) -> ResponseTemplate {
+    let body = OpenRpcSpec::starknet()
+        .error_response(method, error)
+        .unwrap_or_else(|err| panic!("Invalid fixture: {err}"));
+    response_template_with_status(StatusCode::OK).set_body_json(body)
+}
+
+pub(crate) fn response_template_with_status(status_code: StatusCode) -> ResponseTemplate {
     ResponseTemplate::new(status_code)
         .append_header("vary", "Accept-Encoding")
         .append_header("vary", "Origin")
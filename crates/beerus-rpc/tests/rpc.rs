@@ -2,8 +2,28 @@ mod utils;
 
 #[cfg(test)]
 mod tests {
-    use crate::utils::setup_beerus_rpc;
-    use beerus_rpc::server::BeerusApiServer;
+    use crate::utils::{
+        setup_admin_rpc, setup_beerus, setup_beerus_rpc, setup_beerus_rpc_with_ethereum,
+    };
+    use beerus_core::lightclient::{
+        beerus::NodeData,
+        service::BeerusHandle,
+        starknet::fee::{PriceUnit, SimulationFlag},
+    };
+    use beerus_rpc::{
+        admin::AdminApiServer,
+        clients::ClientRegistry,
+        compat::SpecVersion,
+        server::{BeerusApiServer, BeerusRpc, LogFilter},
+        utils::{ethereum_wiremock::EthereumFixtures, wiremock::invoke_v3_transaction},
+    };
+    use ethers::types::{Address, U256};
+    use jsonrpsee::types::error::CallError;
+    use starknet::core::types::{
+        FieldElement, InvokeTransactionReceipt, MaybePendingTransactionReceipt, TransactionReceipt,
+        TransactionStatus,
+    };
+    use std::sync::Arc;
 
     #[tokio::test]
     async fn test_block_number_is_ok() {
@@ -23,4 +43,318 @@ mod tests {
             .unwrap();
         assert_eq!(transaction_count, 90);
     }
+
+    #[tokio::test]
+    async fn test_get_nonce_at_pending_block_is_ok() {
+        let beerus_rpc = setup_beerus_rpc().await;
+        let nonce = beerus_rpc
+            .starknet_get_nonce("tag".to_string(), "pending".to_string(), "0x1".to_string())
+            .await
+            .unwrap();
+        assert_eq!(nonce, starknet::core::types::FieldElement::from(5u64));
+    }
+
+    #[tokio::test]
+    async fn test_estimate_fee_of_v3_transaction_is_in_fri() {
+        let beerus_rpc = setup_beerus_rpc().await;
+        let transaction = serde_json::from_value(invoke_v3_transaction()).unwrap();
+        let estimates = beerus_rpc
+            .starknet_estimate_fee(
+                "tag".to_string(),
+                "latest".to_string(),
+                vec![transaction],
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(estimates.len(), 1);
+        assert_eq!(estimates[0].unit, PriceUnit::Fri);
+        assert_eq!(
+            estimates[0].overall_fee,
+            starknet::core::types::FieldElement::from(0x21782aed880_u64)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_estimate_fee_with_skip_fee_charge_is_rejected() {
+        let beerus_rpc = setup_beerus_rpc().await;
+        let transaction = serde_json::from_value(invoke_v3_transaction()).unwrap();
+        let result = beerus_rpc
+            .starknet_estimate_fee(
+                "tag".to_string(),
+                "latest".to_string(),
+                vec![transaction],
+                Some(vec![SimulationFlag::SkipFeeCharge]),
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(jsonrpsee::core::Error::Call(CallError::InvalidParams(_)))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_beerus_status_is_ok() {
+        let beerus_rpc = setup_beerus_rpc().await;
+        let status = beerus_rpc.beerus_status().await.unwrap();
+        assert_eq!(status.block_number, 0);
+        assert_eq!(status.last_block_hash_check, None);
+    }
+
+    #[tokio::test]
+    async fn test_beerus_status_reports_provider_health() {
+        let beerus_rpc = setup_beerus_rpc().await;
+        beerus_rpc.starknet_block_number().await.unwrap();
+        let status = beerus_rpc.beerus_status().await.unwrap();
+        let provider = &status.providers[0];
+        assert!(provider.provider.starts_with("127.0.0.1:"));
+        assert!(provider.requests >= 1);
+        assert_eq!(provider.failures, 0);
+        assert!(!provider.down_ranked);
+    }
+
+    #[tokio::test]
+    async fn test_beerus_handshake_echoes_challenge() {
+        let beerus_rpc = setup_beerus_rpc().await;
+        let handshake = beerus_rpc
+            .beerus_handshake(Some("0xc0ffee".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(handshake.challenge, Some("0xc0ffee".to_string()));
+        assert_eq!(handshake.signer, None);
+    }
+
+    #[tokio::test]
+    async fn test_beerus_get_block_range_is_ok() {
+        let beerus_rpc = setup_beerus_rpc().await;
+        let blocks = beerus_rpc.beerus_get_block_range(0, 99).await.unwrap();
+        assert!(blocks.is_empty());
+        assert!(beerus_rpc.beerus_get_block_range(0, 100).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_beerus_get_class_declaration_of_unknown_class_is_none() {
+        let beerus_rpc = setup_beerus_rpc().await;
+        let declaration = beerus_rpc
+            .beerus_get_class_declaration("0x1234".to_string())
+            .await
+            .unwrap();
+        assert_eq!(declaration, None);
+        assert!(beerus_rpc
+            .beerus_get_class_declaration("not a felt".to_string())
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_beerus_get_contract_deployment_of_unknown_contract_is_none() {
+        let beerus_rpc = setup_beerus_rpc().await;
+        let deployment = beerus_rpc
+            .beerus_get_contract_deployment("0x1234".to_string())
+            .await
+            .unwrap();
+        assert_eq!(deployment, None);
+        let deployments = beerus_rpc
+            .beerus_get_contracts_by_class("0x1234".to_string())
+            .await
+            .unwrap();
+        assert!(deployments.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_beerus_get_transaction_proof_of_unknown_transaction_is_err() {
+        let beerus_rpc = setup_beerus_rpc().await;
+        let result = beerus_rpc
+            .beerus_get_transaction_proof("0x1234".to_string())
+            .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_admin_prune_to_is_ok() {
+        let admin_rpc = setup_admin_rpc().await;
+        let pruned = admin_rpc.admin_prune_to(10).await.unwrap();
+        assert_eq!(pruned, 0);
+        let stats = admin_rpc.admin_retention_stats().await.unwrap();
+        assert_eq!(stats.first_block_number, None);
+        assert_eq!(stats.payload_stats.blocks, 0);
+    }
+
+    #[tokio::test]
+    async fn test_admin_client_stats_are_reported_per_client() {
+        let admin_rpc = setup_admin_rpc().await;
+        assert!(admin_rpc.admin_client_stats().await.unwrap().is_empty());
+
+        let clients = ClientRegistry::parse("wallet:k3y:10").unwrap();
+        clients.admit("k3y", 42);
+        let admin_rpc = setup_admin_rpc()
+            .await
+            .with_clients(Some(Arc::new(clients)));
+        let stats = admin_rpc.admin_client_stats().await.unwrap();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].client, "wallet");
+        assert_eq!(stats[0].bytes_in, 42);
+    }
+
+    #[tokio::test]
+    async fn test_admin_rotate_provider_with_invalid_url_is_err() {
+        let admin_rpc = setup_admin_rpc().await;
+        let result = admin_rpc
+            .admin_rotate_provider("not a url".to_string())
+            .await;
+        assert!(matches!(
+            result,
+            Err(jsonrpsee::core::Error::Call(CallError::InvalidParams(_)))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_spec_version_is_ok() {
+        let beerus_rpc = setup_beerus_rpc()
+            .await
+            .with_spec_version(SpecVersion::V0_4);
+        let spec_version = beerus_rpc.starknet_spec_version().await.unwrap();
+        assert_eq!(spec_version, "0.4.0");
+    }
+
+    #[tokio::test]
+    async fn test_get_storage_batch_with_invalid_key_is_err() {
+        let beerus_rpc = setup_beerus_rpc().await;
+        let result = beerus_rpc
+            .beerus_get_storage_batch(
+                "0x1".to_string(),
+                vec!["0x1".to_string(), "key".to_string()],
+                None,
+            )
+            .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_get_transaction_receipt_with_invalid_hash_is_invalid_params() {
+        let beerus_rpc = setup_beerus_rpc().await;
+        let result = beerus_rpc
+            .starknet_get_transaction_receipt("0xnot_a_hash".to_string())
+            .await;
+        assert!(matches!(
+            result,
+            Err(jsonrpsee::core::Error::Call(CallError::InvalidParams(_)))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_get_transaction_receipt_is_answered_from_the_cache() {
+        let beerus: BeerusHandle = setup_beerus().await.into();
+        // The provider mock doesn't know this transaction, only the cache does.
+        let tx_hash = FieldElement::from_hex_be("0xdead").unwrap();
+        let mut node = NodeData::new();
+        Arc::make_mut(&mut node.receipts).insert(
+            10,
+            Arc::new(vec![MaybePendingTransactionReceipt::Receipt(
+                TransactionReceipt::Invoke(InvokeTransactionReceipt {
+                    transaction_hash: tx_hash,
+                    actual_fee: FieldElement::ONE,
+                    status: TransactionStatus::AcceptedOnL2,
+                    block_hash: FieldElement::ONE,
+                    block_number: 10,
+                    messages_sent: vec![],
+                    events: vec![],
+                }),
+            )]),
+        );
+        beerus.node.store(Arc::new(node));
+        let beerus_rpc = BeerusRpc::new(beerus);
+
+        let receipt = beerus_rpc
+            .starknet_get_transaction_receipt("0xdead".to_string())
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            receipt,
+            MaybePendingTransactionReceipt::Receipt(TransactionReceipt::Invoke(receipt))
+                if receipt.transaction_hash == tx_hash
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_get_block_transaction_count_of_unknown_block_is_block_not_found() {
+        let beerus_rpc = setup_beerus_rpc().await;
+        let result = beerus_rpc
+            .starknet_get_block_transaction_count("number".to_string(), u64::MAX.to_string())
+            .await;
+        match result {
+            Err(jsonrpsee::core::Error::Call(CallError::Custom(error))) => {
+                assert_eq!(error.code(), 24);
+            }
+            other => panic!("Expected a BLOCK_NOT_FOUND error, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_eth_get_balance_with_invalid_block_is_invalid_params() {
+        let beerus_rpc = setup_beerus_rpc().await;
+        let result = beerus_rpc
+            .eth_get_balance(
+                "0xc662c410C0ECf747543f5bA90660f6ABeBD9C8c4".to_string(),
+                Some("pending".to_string()),
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(jsonrpsee::core::Error::Call(CallError::InvalidParams(_)))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_eth_get_logs_with_block_hash_and_range_is_invalid_params() {
+        let beerus_rpc = setup_beerus_rpc().await;
+        let filter = LogFilter {
+            from_block: Some("0x1".to_string()),
+            block_hash: Some(
+                "0xc9bb964b3fe087354bc1c1904518acc2b9df7ebedcb89215e9f3b41f47b6c31d".to_string(),
+            ),
+            ..Default::default()
+        };
+        let result = beerus_rpc.eth_get_logs(filter).await;
+        assert!(matches!(
+            result,
+            Err(jsonrpsee::core::Error::Call(CallError::InvalidParams(_)))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_beerus_l1_proven_state_is_read_from_the_core_contract() {
+        let fixtures = EthereumFixtures::default();
+        let (beerus_rpc, _ethereum_wiremock) = setup_beerus_rpc_with_ethereum(&fixtures).await;
+
+        let block_number = beerus_rpc.beerus_l1_proven_block_number().await.unwrap();
+        let state_root = beerus_rpc.beerus_l1_state_root().await.unwrap();
+
+        assert_eq!(block_number, fixtures.proven_block_number);
+        assert_eq!(state_root, FieldElement::from(0x5ca1ab1e_u64));
+    }
+
+    #[tokio::test]
+    async fn test_eth_get_balance_is_read_from_the_execution_rpc() {
+        let address: Address = "0x00000000219ab540356cBB839Cbe05303d7705Fa"
+            .parse()
+            .unwrap();
+        let fixtures = EthereumFixtures {
+            balances: vec![(address, U256::from(10_u64).pow(18.into()))],
+            ..EthereumFixtures::default()
+        };
+        let (beerus_rpc, _ethereum_wiremock) = setup_beerus_rpc_with_ethereum(&fixtures).await;
+
+        let balance = beerus_rpc
+            .eth_get_balance(format!("{address:#x}"), None)
+            .await
+            .unwrap();
+
+        assert_eq!(balance, U256::from(10_u64).pow(18.into()));
+        assert!(beerus_rpc
+            .eth_get_balance(format!("{:#x}", Address::zero()), None)
+            .await
+            .is_err());
+    }
 }
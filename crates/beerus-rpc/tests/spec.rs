@@ -0,0 +1,179 @@
+//! StarkNet JSON-RPC spec compliance.
+//! Runs the vectors of `spec/vectors.json` against a Beerus RPC server backed by wiremock.
+//! Each vector sends a spec shaped request and checks either the result against a subset of
+//! the spec schema, or the error code. Vectors with a `known_deviation` are reported but don't
+//! fail the suite.
+
+#[cfg(test)]
+mod tests {
+    use beerus_core::{
+        config::Config,
+        lightclient::{
+            beerus::{BeerusLightClient, SyncStatus},
+            ethereum::MockEthereumLightClient,
+            starknet::StarkNetLightClientImpl,
+        },
+    };
+    use beerus_rpc::run_server;
+    use serde_json::{json, Value};
+    use wiremock::{
+        matchers::{body_partial_json, method},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[tokio::test]
+    async fn test_spec_vectors() {
+        let vectors: Vec<Value> = serde_json::from_str(include_str!("spec/vectors.json")).unwrap();
+
+        // Given
+        let upstream = MockServer::start().await;
+        for vector in vectors.iter() {
+            if let Some(mock) = vector.get("upstream") {
+                mount_upstream(&upstream, mock).await;
+            }
+        }
+        std::env::set_var("ETHEREUM_CONSENSUS_RPC_URL", "");
+        std::env::set_var("ETHEREUM_EXECUTION_RPC_URL", "");
+        std::env::set_var("STARKNET_RPC_URL", upstream.uri());
+        std::env::set_var("RPC_SPEC_VERSION", "0.4");
+        std::env::set_var("PORT", "0");
+        let config = Config::default();
+        let beerus = BeerusLightClient::new(
+            config.clone(),
+            Box::new(MockEthereumLightClient::new()),
+            Box::new(StarkNetLightClientImpl::new(&config).unwrap()),
+        );
+        // The light clients are mocked, serve the vectors as if they were synced.
+        beerus.sync_status.send_replace(SyncStatus::Synced);
+        let (addr, handle) = run_server(beerus.into(), None).await.unwrap();
+        let url = format!("http://127.0.0.1:{}", addr.port());
+        let client = reqwest::Client::new();
+
+        // When
+        let mut deviations = Vec::new();
+        for vector in vectors.iter() {
+            let name = vector["name"].as_str().unwrap();
+            let mut request = vector["request"].clone();
+            request["jsonrpc"] = json!("2.0");
+            request["id"] = json!(1);
+            let response = client
+                .post(&url)
+                .header("Content-Type", "application/json")
+                .body(request.to_string())
+                .send()
+                .await
+                .unwrap()
+                .bytes()
+                .await
+                .unwrap();
+            let response: Value = serde_json::from_slice(&response).unwrap();
+
+            match (
+                check_response(vector, &response),
+                vector.get("known_deviation"),
+            ) {
+                (Err(deviation), None) => deviations.push(format!("{name}: {deviation}")),
+                (Ok(()), Some(_)) => {
+                    println!("{name} now complies with the spec, remove its known deviation")
+                }
+                _ => {}
+            }
+        }
+        handle.stop().unwrap();
+
+        // Then
+        assert!(
+            deviations.is_empty(),
+            "Spec deviations:\n{}",
+            deviations.join("\n")
+        );
+    }
+
+    async fn mount_upstream(server: &MockServer, mock: &Value) {
+        let mut body = json!({ "method": mock["method"] });
+        if let Some(params) = mock.get("params") {
+            body["params"] = params.clone();
+        }
+        let mut response = mock["response"].clone();
+        response["jsonrpc"] = json!("2.0");
+        response["id"] = json!(1);
+        Mock::given(method("POST"))
+            .and(body_partial_json(body))
+            .respond_with(ResponseTemplate::new(200).set_body_json(response))
+            .mount(server)
+            .await;
+    }
+
+    /// Check a response against the expected result schema or error code of a vector.
+    fn check_response(vector: &Value, response: &Value) -> Result<(), String> {
+        if response["jsonrpc"] != json!("2.0") || response["id"] != json!(1) {
+            return Err(format!("invalid JSON-RPC envelope {response}"));
+        }
+        match (vector.get("result"), vector.get("error")) {
+            (Some(schema), _) => match response.get("result") {
+                Some(result) => check_schema(result, schema, "result"),
+                None => Err(format!("expected a result, got {response}")),
+            },
+            (_, Some(error)) => match response.get("error") {
+                Some(actual) if actual["code"] == error["code"] => match actual["message"] {
+                    Value::String(_) => Ok(()),
+                    _ => Err(format!("error without message {actual}")),
+                },
+                _ => Err(format!("expected error {}, got {response}", error["code"])),
+            },
+            _ => Err("vector without result nor error".to_string()),
+        }
+    }
+
+    /// Check a value against the subset of JSON schema used by the vectors.
+    /// `felt` stands for the spec FELT type: a 0x prefixed hex string of at most 63 digits.
+    fn check_schema(value: &Value, schema: &Value, path: &str) -> Result<(), String> {
+        let valid = match schema["type"].as_str() {
+            Some("felt") => value.as_str().map_or(false, is_felt),
+            Some("string") => value.is_string(),
+            Some("integer") => match schema["minimum"].as_i64() {
+                Some(minimum) => value.as_i64().map_or(false, |value| value >= minimum),
+                None => value.is_i64() || value.is_u64(),
+            },
+            Some("boolean") => value.is_boolean(),
+            Some("array") => value.is_array(),
+            Some("object") => value.is_object(),
+            _ => return Err(format!("{path}: unsupported schema {schema}")),
+        };
+        if !valid {
+            return Err(format!("{path}: {value} does not match {schema}"));
+        }
+
+        if let Some(required) = schema["required"].as_array() {
+            for field in required.iter().filter_map(Value::as_str) {
+                if value.get(field).is_none() {
+                    return Err(format!("{path}: missing required field {field}"));
+                }
+            }
+        }
+        if let Some(properties) = schema["properties"].as_object() {
+            for (field, schema) in properties {
+                if let Some(value) = value.get(field) {
+                    check_schema(value, schema, &format!("{path}.{field}"))?;
+                }
+            }
+        }
+        if let (Some(items), Some(values)) = (schema.get("items"), value.as_array()) {
+            for (index, value) in values.iter().enumerate() {
+                check_schema(value, items, &format!("{path}[{index}]"))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn is_felt(value: &str) -> bool {
+        match value.strip_prefix("0x") {
+            Some(digits) => {
+                !digits.is_empty()
+                    && digits.len() <= 63
+                    && digits.chars().all(|c| c.is_ascii_hexdigit())
+            }
+            None => false,
+        }
+    }
+}
@@ -5,26 +5,68 @@ use beerus_core::{
         starknet::StarkNetLightClientImpl,
     },
 };
-use beerus_rpc::{server::BeerusRpc, utils::wiremock::setup_wiremock};
+use beerus_rpc::{
+    admin::AdminRpc,
+    server::BeerusRpc,
+    utils::{
+        ethereum_wiremock::{
+            setup_ethereum_wiremock, EthereumFixtures, EthereumWiremock,
+            WiremockEthereumLightClient,
+        },
+        wiremock::setup_wiremock,
+    },
+};
 
 pub async fn setup_beerus_rpc() -> BeerusRpc {
+    BeerusRpc::new(setup_beerus().await.into())
+}
+
+pub async fn setup_admin_rpc() -> AdminRpc {
+    AdminRpc::new(setup_beerus().await.into())
+}
+
+/// RPC with an Ethereum light client reading from mocks of L1 in the state of the fixtures,
+/// the mocks are dropped along with the returned servers.
+pub async fn setup_beerus_rpc_with_ethereum(
+    fixtures: &EthereumFixtures,
+) -> (BeerusRpc, EthereumWiremock) {
     let mock_starknet_rpc = setup_wiremock().await;
+    let ethereum_wiremock = setup_ethereum_wiremock(fixtures).await;
     set_mandatory_envs(mock_starknet_rpc);
     let config = Config::default();
 
-    let ethereum_lightclient = MockEthereumLightClient::new();
+    let ethereum_lightclient =
+        WiremockEthereumLightClient::new(&ethereum_wiremock, config.starknet_core_contract_address)
+            .unwrap();
     let starknet_lightclient = StarkNetLightClientImpl::new(&config).unwrap();
 
-    let beerus_client = BeerusLightClient::new(
+    let beerus = BeerusLightClient::new(
         config,
         Box::new(ethereum_lightclient),
         Box::new(starknet_lightclient),
     );
-    BeerusRpc::new(beerus_client)
+    (BeerusRpc::new(beerus.into()), ethereum_wiremock)
+}
+
+pub async fn setup_beerus() -> BeerusLightClient {
+    let mock_starknet_rpc = setup_wiremock().await;
+    set_mandatory_envs(mock_starknet_rpc);
+    let config = Config::default();
+
+    let ethereum_lightclient = MockEthereumLightClient::new();
+    let starknet_lightclient = StarkNetLightClientImpl::new(&config).unwrap();
+
+    BeerusLightClient::new(
+        config,
+        Box::new(ethereum_lightclient),
+        Box::new(starknet_lightclient),
+    )
 }
 
 fn set_mandatory_envs(starknet_rpc: String) {
     std::env::set_var("ETHEREUM_CONSENSUS_RPC_URL", "");
     std::env::set_var("ETHEREUM_EXECUTION_RPC_URL", "");
     std::env::set_var("STARKNET_RPC_URL", starknet_rpc);
+    // The latest block is read from the mocked provider rather than from L1.
+    std::env::set_var("BLOCK_NUMBER_SOURCE", "upstream");
 }
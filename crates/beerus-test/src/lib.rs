@@ -0,0 +1,333 @@
+//! End-to-end harness of the RPC server: Beerus is served on the loopback interface against
+//! the mocked StarkNet provider and L1 of `beerus_rpc::utils`, then driven over HTTP the way
+//! wallets do, with the requests of starknet.js and starknet-rs, and the whole responses are
+//! compared. It catches what the tests calling the RPC methods directly can't see: routing,
+//! middlewares, spec translation and serialization.
+use beerus_core::{
+    config::{Config, Profile},
+    lightclient::{
+        beerus::BeerusLightClient, service::BeerusHandle, starknet::StarkNetLightClientImpl,
+    },
+};
+use beerus_rpc::{
+    compat::SPEC_VERSION_ENV,
+    run_server_on,
+    utils::{
+        ethereum_wiremock::{
+            setup_ethereum_wiremock, EthereumFixtures, EthereumWiremock,
+            WiremockEthereumLightClient,
+        },
+        wiremock::setup_wiremock_server,
+    },
+};
+use ethers::types::{Address, U256};
+use eyre::{eyre, Result};
+use jsonrpsee::server::ServerHandle;
+use serde_json::{json, Value};
+use starknet::{
+    core::types::FieldElement,
+    providers::jsonrpc::{
+        models::{BlockId, BlockTag},
+        ErrorCode, HttpTransport, JsonRpcClient, JsonRpcClientError, RpcError,
+    },
+};
+use std::{net::SocketAddr, time::Duration};
+use url::Url;
+
+/// Spec version the harness speaks, the one of the requests of the wallets.
+const SPEC_VERSION: &str = "0.4";
+/// Time given to the light client to start against the mocks.
+const START_TIMEOUT: Duration = Duration::from_secs(30);
+/// Account whose balance is mocked on L1.
+const ACCOUNT: &str = "0x00000000219ab540356cBB839Cbe05303d7705Fa";
+
+/// Expected answer to a request.
+#[derive(Debug, Clone)]
+pub enum Expected {
+    /// The whole response, a batch being compared regardless of the order of its responses.
+    Response(Value),
+    /// An error response with this code, the message is the provider's.
+    ErrorCode(i64),
+}
+
+/// Request sent to the server and its expected answer.
+#[derive(Debug, Clone)]
+pub struct Case {
+    pub name: &'static str,
+    pub request: Value,
+    pub expected: Expected,
+}
+
+/// Outcome of the cases, in order.
+#[derive(Debug, Default)]
+pub struct Report {
+    pub results: Vec<(String, Result<()>)>,
+}
+
+impl Report {
+    pub fn failures(&self) -> impl Iterator<Item = &(String, Result<()>)> {
+        self.results.iter().filter(|(_, result)| result.is_err())
+    }
+}
+
+/// L1 state of the harness, with the balance of `ACCOUNT`.
+pub fn fixtures() -> EthereumFixtures {
+    EthereumFixtures {
+        balances: vec![(account(), U256::exp10(18))],
+        ..EthereumFixtures::default()
+    }
+}
+
+/// Address of the account whose balance is mocked.
+pub fn account() -> Address {
+    ACCOUNT.parse().expect("Valid address")
+}
+
+/// Requests shaped like the ones of starknet.js, with named params, and starknet-rs, with
+/// positional ones, answered from the mocks of `beerus_rpc::utils`.
+pub fn cases(fixtures: &EthereumFixtures) -> Vec<Case> {
+    let response =
+        |result: Value| Expected::Response(json!({"jsonrpc": "2.0", "id": 1, "result": result}));
+    let (account, balance) = fixtures.balances[0];
+    vec![
+        Case {
+            name: "starknet_blockNumber",
+            request: call("starknet_blockNumber", json!([])),
+            expected: response(json!(19640)),
+        },
+        Case {
+            name: "starknet_getBlockTransactionCount with named params",
+            request: call(
+                "starknet_getBlockTransactionCount",
+                json!({ "block_id": "latest" }),
+            ),
+            expected: response(json!(90)),
+        },
+        Case {
+            name: "starknet_getBlockTransactionCount with positional params",
+            request: call("starknet_getBlockTransactionCount", json!(["latest"])),
+            expected: response(json!(90)),
+        },
+        Case {
+            name: "starknet_getBlockTransactionCount of an unknown block",
+            request: call(
+                "starknet_getBlockTransactionCount",
+                json!([{ "block_number": u64::MAX }]),
+            ),
+            expected: Expected::ErrorCode(24),
+        },
+        Case {
+            name: "starknet_getNonce at the pending block",
+            request: call(
+                "starknet_getNonce",
+                json!({ "block_id": "pending", "contract_address": "0x1" }),
+            ),
+            expected: response(json!("0x5")),
+        },
+        Case {
+            name: "batch",
+            request: json!([
+                {"jsonrpc": "2.0", "id": 1, "method": "starknet_blockNumber", "params": []},
+                {"jsonrpc": "2.0", "id": 2, "method": "starknet_getBlockTransactionCount", "params": ["latest"]},
+            ]),
+            expected: Expected::Response(json!([
+                {"jsonrpc": "2.0", "id": 1, "result": 19640},
+                {"jsonrpc": "2.0", "id": 2, "result": 90},
+            ])),
+        },
+        Case {
+            name: "beerus_l1ProvenBlockNumber",
+            request: call("beerus_l1ProvenBlockNumber", json!([])),
+            expected: response(json!(fixtures.proven_block_number)),
+        },
+        Case {
+            name: "beerus_l1StateRoot",
+            request: call("beerus_l1StateRoot", json!([])),
+            expected: response(json!(format!("{:#x}", fixtures.state_root))),
+        },
+        Case {
+            name: "eth_getBalance",
+            request: call("eth_getBalance", json!([format!("{account:#x}"), "latest"])),
+            expected: response(json!(balance)),
+        },
+        Case {
+            name: "unknown method",
+            request: call("starknet_unknown", json!([])),
+            expected: Expected::Response(json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "error": {"code": -32601, "message": "Method not found"}
+            })),
+        },
+    ]
+}
+
+fn call(method: &str, params: Value) -> Value {
+    json!({"jsonrpc": "2.0", "id": 1, "method": method, "params": params})
+}
+
+/// Server under test, along with the mocks it reads from.
+pub struct Harness {
+    pub addr: SocketAddr,
+    pub fixtures: EthereumFixtures,
+    http_client: reqwest::Client,
+    handle: ServerHandle,
+    _starknet: wiremock::MockServer,
+    _ethereum: EthereumWiremock,
+}
+
+impl Harness {
+    /// Mock the upstreams, then start the light client and serve it on an ephemeral port.
+    /// # Errors
+    /// * If the light client doesn't start within `START_TIMEOUT`.
+    pub async fn start(fixtures: EthereumFixtures) -> Result<Self> {
+        let starknet = setup_wiremock_server().await;
+        let ethereum = setup_ethereum_wiremock(&fixtures).await;
+        let data_dir = std::env::temp_dir().join(format!("beerus-test-{}", std::process::id()));
+        let profile = Profile::parse(
+            &format!(
+                "ethereum_consensus_rpc_url = \"{}\"\n\
+                 ethereum_execution_rpc_url = \"{}\"\n\
+                 starknet_rpc_url = \"{}\"\n\
+                 data_dir = \"{}\"\n\
+                 block_number_source = \"upstream\"\n",
+                ethereum.consensus_rpc(),
+                ethereum.execution_rpc(),
+                starknet.uri(),
+                data_dir.display(),
+            ),
+            "default",
+        )?;
+        let config = Config::new_from_profile(&profile)?;
+        let ethereum_lightclient =
+            WiremockEthereumLightClient::new(&ethereum, fixtures.starknet_core_contract_address)?;
+        let starknet_lightclient = StarkNetLightClientImpl::new(&config)?;
+        let beerus = BeerusHandle::from(BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient),
+            Box::new(starknet_lightclient),
+        ));
+
+        beerus.start_in_background();
+        tokio::time::timeout(START_TIMEOUT, beerus.await_synced())
+            .await
+            .map_err(|_| eyre!("The light client didn't start within {START_TIMEOUT:?}"))?;
+        // The spec version is read from the environment by the server.
+        std::env::set_var(SPEC_VERSION_ENV, SPEC_VERSION);
+        let (addr, handle) =
+            run_server_on(beerus, None, SocketAddr::from(([127, 0, 0, 1], 0))).await?;
+
+        Ok(Self {
+            addr,
+            fixtures,
+            http_client: reqwest::Client::new(),
+            handle,
+            _starknet: starknet,
+            _ethereum: ethereum,
+        })
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Send the request of a case and compare the response with the expected one.
+    pub async fn run_case(&self, case: &Case) -> Result<()> {
+        let response: Value = self
+            .http_client
+            .post(self.url())
+            .json(&case.request)
+            .send()
+            .await?
+            .json()
+            .await?;
+        match &case.expected {
+            Expected::Response(expected) if same_response(expected, &response) => Ok(()),
+            Expected::ErrorCode(code) if response["error"]["code"] == *code => Ok(()),
+            expected => Err(eyre!("expected {expected:?}, got {response}")),
+        }
+    }
+
+    /// Call the server with the starknet-rs client, which deserializes the responses into its
+    /// own types.
+    pub async fn run_client_checks(&self) -> Vec<(String, Result<()>)> {
+        let client = JsonRpcClient::new(HttpTransport::new(
+            Url::parse(&self.url()).expect("Valid url"),
+        ));
+        let latest = BlockId::Tag(BlockTag::Latest);
+        let pending = BlockId::Tag(BlockTag::Pending);
+        vec![
+            (
+                "starknet-rs block_number".to_string(),
+                check_eq(client.block_number().await, 19640),
+            ),
+            (
+                "starknet-rs get_block_transaction_count".to_string(),
+                check_eq(client.get_block_transaction_count(&latest).await, 90),
+            ),
+            (
+                "starknet-rs get_nonce".to_string(),
+                check_eq(
+                    client.get_nonce(&pending, FieldElement::ONE).await,
+                    FieldElement::from(5_u64),
+                ),
+            ),
+            (
+                "starknet-rs get_block_transaction_count of an unknown block".to_string(),
+                match client
+                    .get_block_transaction_count(&BlockId::Number(u64::MAX))
+                    .await
+                {
+                    Err(JsonRpcClientError::RpcError(RpcError::Code(ErrorCode::BlockNotFound))) => {
+                        Ok(())
+                    }
+                    other => Err(eyre!("expected BLOCK_NOT_FOUND, got {other:?}")),
+                },
+            ),
+        ]
+    }
+
+    /// Run the cases then the client checks.
+    pub async fn run(&self) -> Report {
+        let mut report = Report::default();
+        for case in cases(&self.fixtures) {
+            let result = self.run_case(&case).await;
+            report.results.push((case.name.to_string(), result));
+        }
+        report.results.extend(self.run_client_checks().await);
+        report
+    }
+
+    /// Stop the server, the mocks are dropped along with the harness.
+    pub fn stop(self) -> Result<()> {
+        self.handle
+            .stop()
+            .map_err(|err| eyre!("Failed to stop the server: {err}"))
+    }
+}
+
+fn check_eq<T, E>(result: std::result::Result<T, E>, expected: T) -> Result<()>
+where
+    T: PartialEq + std::fmt::Debug,
+    E: std::fmt::Debug,
+{
+    match result {
+        Ok(value) if value == expected => Ok(()),
+        other => Err(eyre!("expected {expected:?}, got {other:?}")),
+    }
+}
+
+/// Whether two responses are the same, the responses of a batch in any order.
+fn same_response(expected: &Value, actual: &Value) -> bool {
+    match (expected, actual) {
+        (Value::Array(expected), Value::Array(actual)) => {
+            let by_id = |responses: &[Value]| {
+                let mut responses = responses.to_vec();
+                responses.sort_by_key(|response| response["id"].to_string());
+                responses
+            };
+            by_id(expected) == by_id(actual)
+        }
+        (expected, actual) => expected == actual,
+    }
+}
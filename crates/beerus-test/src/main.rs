@@ -0,0 +1,36 @@
+use beerus_test::{fixtures, Harness};
+use env_logger::Env;
+use log::{error, info};
+use std::process::exit;
+
+#[tokio::main]
+async fn main() {
+    env_logger::Builder::from_env(Env::default().default_filter_or("warn")).init();
+
+    let harness = match Harness::start(fixtures()).await {
+        Ok(harness) => harness,
+        Err(err) => {
+            error!("{err}");
+            exit(1);
+        }
+    };
+    info!("serving Beerus on {}", harness.url());
+    let report = harness.run().await;
+    for (name, result) in report.results.iter() {
+        match result {
+            Ok(()) => println!("ok     {name}"),
+            Err(err) => println!("FAILED {name}: {err}"),
+        }
+    }
+    let failures = report.failures().count();
+    println!(
+        "{} passed, {failures} failed",
+        report.results.len() - failures
+    );
+    if let Err(err) = harness.stop() {
+        error!("{err}");
+    }
+    if failures > 0 {
+        exit(1);
+    }
+}
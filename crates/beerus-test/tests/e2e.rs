@@ -0,0 +1,15 @@
+use beerus_test::{fixtures, Harness};
+
+#[tokio::test]
+async fn test_harness_cases_pass() {
+    let harness = Harness::start(fixtures()).await.unwrap();
+
+    let report = harness.run().await;
+
+    let failures: Vec<String> = report
+        .failures()
+        .map(|(name, result)| format!("{name}: {:?}", result.as_ref().unwrap_err()))
+        .collect();
+    assert!(failures.is_empty(), "{failures:#?}");
+    harness.stop().unwrap();
+}
@@ -0,0 +1,149 @@
+//! JavaScript bindings for running the Beerus light client in the browser.
+//! Field elements are passed around as hex strings.
+use beerus_core::{
+    config::Config,
+    lightclient::{
+        beerus::{BeerusLightClient, BlockTarget},
+        ethereum::helios_lightclient::HeliosLightClient,
+        starknet::StarkNetLightClientImpl,
+    },
+};
+use ethers::types::Address;
+use starknet::core::types::FieldElement;
+use std::str::FromStr;
+use wasm_bindgen::prelude::*;
+
+/// Beerus light client exposed to JavaScript.
+#[wasm_bindgen]
+pub struct BeerusClient {
+    beerus: BeerusLightClient,
+}
+
+#[wasm_bindgen]
+impl BeerusClient {
+    /// Create a new client. Call `start` before querying it.
+    /// # Arguments
+    /// * `ethereum_network` - `mainnet` or `goerli`.
+    /// * `ethereum_consensus_rpc` - Ethereum consensus RPC endpoint.
+    /// * `ethereum_execution_rpc` - Ethereum execution RPC endpoint.
+    /// * `starknet_rpc` - StarkNet RPC endpoint.
+    /// * `starknet_core_contract_address` - Address of the StarkNet core contract on L1.
+    pub async fn create(
+        ethereum_network: String,
+        ethereum_consensus_rpc: String,
+        ethereum_execution_rpc: String,
+        starknet_rpc: String,
+        starknet_core_contract_address: String,
+    ) -> Result<BeerusClient, JsError> {
+        console_error_panic_hook::set_once();
+
+        let config = Config::from_parts(
+            ethereum_network,
+            ethereum_consensus_rpc,
+            ethereum_execution_rpc,
+            starknet_rpc,
+            Address::from_str(&starknet_core_contract_address).map_err(to_js_error)?,
+            None,
+        );
+        let ethereum_lightclient = HeliosLightClient::new(config.clone())
+            .await
+            .map_err(to_js_error)?;
+        let starknet_lightclient = StarkNetLightClientImpl::new(&config).map_err(to_js_error)?;
+        let beerus = BeerusLightClient::new(
+            config,
+            Box::new(ethereum_lightclient),
+            Box::new(starknet_lightclient),
+        );
+        Ok(BeerusClient { beerus })
+    }
+
+    /// Sync the Ethereum light client and start following the StarkNet chain.
+    pub async fn start(&mut self) -> Result<(), JsError> {
+        self.beerus.start().await.map_err(to_js_error)
+    }
+
+    /// Return the last StarkNet block number proven on L1.
+    #[wasm_bindgen(js_name = l1ProvenBlockNumber)]
+    pub async fn l1_proven_block_number(&self) -> Result<u64, JsError> {
+        self.beerus
+            .starknet_l1_proven_block_number()
+            .await
+            .map_err(to_js_error)
+    }
+
+    /// Return the StarkNet state root proven on L1.
+    #[wasm_bindgen(js_name = l1StateRoot)]
+    pub async fn l1_state_root(&self) -> Result<String, JsError> {
+        let state_root = self
+            .beerus
+            .starknet_l1_state_root()
+            .await
+            .map_err(to_js_error)?;
+        Ok(format!("{state_root:#x}"))
+    }
+
+    /// Return the storage value at the given key, along with its verification metadata.
+    #[wasm_bindgen(js_name = getStorageAt)]
+    pub async fn get_storage_at(
+        &self,
+        contract_address: String,
+        storage_key: String,
+    ) -> Result<JsValue, JsError> {
+        let result = self
+            .beerus
+            .starknet_get_storage_at_with_metadata(
+                parse_felt(&contract_address)?,
+                parse_felt(&storage_key)?,
+                BlockTarget::L1Accepted,
+            )
+            .await
+            .map_err(to_js_error)?;
+        Ok(serde_wasm_bindgen::to_value(&result)?)
+    }
+
+    /// Return the nonce of a contract, along with its verification metadata.
+    #[wasm_bindgen(js_name = getNonce)]
+    pub async fn get_nonce(&self, contract_address: String) -> Result<JsValue, JsError> {
+        let result = self
+            .beerus
+            .starknet_get_nonce_with_metadata(
+                parse_felt(&contract_address)?,
+                BlockTarget::L1Accepted,
+            )
+            .await
+            .map_err(to_js_error)?;
+        Ok(serde_wasm_bindgen::to_value(&result)?)
+    }
+
+    /// Call a contract function, along with its verification metadata.
+    pub async fn call(
+        &self,
+        contract_address: String,
+        entry_point_selector: String,
+        calldata: Vec<String>,
+    ) -> Result<JsValue, JsError> {
+        let calldata = calldata
+            .iter()
+            .map(|value| parse_felt(value))
+            .collect::<Result<Vec<_>, _>>()?;
+        let result = self
+            .beerus
+            .starknet_call_contract_with_metadata(
+                parse_felt(&contract_address)?,
+                parse_felt(&entry_point_selector)?,
+                calldata,
+                BlockTarget::L1Accepted,
+            )
+            .await
+            .map_err(to_js_error)?;
+        Ok(serde_wasm_bindgen::to_value(&result)?)
+    }
+}
+
+fn parse_felt(value: &str) -> Result<FieldElement, JsError> {
+    FieldElement::from_hex_be(value).map_err(|e| JsError::new(&format!("{value}: {e}")))
+}
+
+fn to_js_error(e: impl std::fmt::Display) -> JsError {
+    JsError::new(&e.to_string())
+}
@@ -0,0 +1,20 @@
+#![no_main]
+use beerus_core::starknet_helper::block_id_string_to_block_id_type;
+use libfuzzer_sys::fuzz_target;
+use starknet::providers::jsonrpc::models::BlockId;
+
+fuzz_target!(|input: (&str, &str)| {
+    let (block_id_type, block_id) = input;
+    // A parsed number or hash prints back to an equivalent block id.
+    match block_id_string_to_block_id_type(block_id_type, block_id) {
+        Ok(BlockId::Number(number)) => assert_eq!(
+            block_id_string_to_block_id_type("number", &number.to_string()).unwrap(),
+            BlockId::Number(number)
+        ),
+        Ok(BlockId::Hash(hash)) => assert_eq!(
+            block_id_string_to_block_id_type("hash", &format!("{hash:#x}")).unwrap(),
+            BlockId::Hash(hash)
+        ),
+        _ => {}
+    }
+});
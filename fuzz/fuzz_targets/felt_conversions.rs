@@ -0,0 +1,29 @@
+#![no_main]
+use beerus_core::ethers_helper::{
+    address_to_u256, bytes_to_u256, felt_to_h256, felt_to_u256, h256_to_felt, u256_to_address,
+    u256_to_felt,
+};
+use ethers::types::H256;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let value = match bytes_to_u256(data) {
+        Ok(value) => value,
+        Err(_) => {
+            assert!(data.len() > 32);
+            return;
+        }
+    };
+    // A value is either a felt, which converts back to itself, or rejected.
+    if let Ok(felt) = u256_to_felt(value) {
+        assert_eq!(felt_to_u256(felt), value);
+        assert_eq!(h256_to_felt(felt_to_h256(felt)).unwrap(), felt);
+    } else {
+        let mut bytes = [0_u8; 32];
+        value.to_big_endian(&mut bytes);
+        assert!(h256_to_felt(H256::from(bytes)).is_err());
+    }
+    if let Ok(address) = u256_to_address(value) {
+        assert_eq!(address_to_u256(address), value);
+    }
+});
@@ -0,0 +1,46 @@
+#![no_main]
+use beerus_core::{
+    ethers_helper::{address_to_u256, felt_to_u256},
+    lightclient::messaging::L2ToL1Message,
+};
+use ethers::{
+    abi::{encode, Token},
+    types::{Address, H256},
+    utils::keccak256,
+};
+use libfuzzer_sys::fuzz_target;
+use starknet::core::types::FieldElement;
+
+/// Felt of 32 bytes, reduced below 2^251.
+fn felt(bytes: &[u8]) -> FieldElement {
+    let mut word = [0_u8; 32];
+    word[32 - bytes.len()..].copy_from_slice(bytes);
+    word[0] &= 0x07;
+    FieldElement::from_bytes_be(&word).unwrap()
+}
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 52 {
+        return;
+    }
+    let (from_address, rest) = data.split_at(32);
+    let (to_address, payload) = rest.split_at(20);
+    let message = L2ToL1Message {
+        from_address: felt(from_address),
+        to_address: Address::from_slice(to_address),
+        payload: payload.chunks(32).map(felt).collect(),
+    };
+
+    let mut tokens = vec![
+        Token::Uint(felt_to_u256(message.from_address)),
+        Token::Uint(address_to_u256(message.to_address)),
+        Token::Uint(message.payload.len().into()),
+    ];
+    tokens.extend(
+        message
+            .payload
+            .iter()
+            .map(|value| Token::Uint(felt_to_u256(*value))),
+    );
+    assert_eq!(message.hash(), H256::from(keccak256(encode(&tokens))));
+});
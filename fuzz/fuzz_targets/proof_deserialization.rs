@@ -0,0 +1,23 @@
+#![no_main]
+use beerus_core::lightclient::starknet::storage_proof::GetProofOutput;
+use libfuzzer_sys::fuzz_target;
+use starknet::core::types::FieldElement;
+
+fuzz_target!(|data: &[u8]| {
+    let output = match serde_json::from_slice::<GetProofOutput>(data) {
+        Ok(output) => output,
+        Err(_) => return,
+    };
+    // A proof read from a provider serializes back to itself, and verifying it never panics.
+    let json = serde_json::to_vec(&output).unwrap();
+    assert_eq!(
+        serde_json::from_slice::<GetProofOutput>(&json).unwrap(),
+        output
+    );
+    let _ = output.verify(
+        FieldElement::ONE,
+        FieldElement::TWO,
+        &[FieldElement::ONE],
+        &[FieldElement::ZERO],
+    );
+});